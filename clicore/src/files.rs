@@ -13,6 +13,23 @@ impl Files for StaticFiles {
             "data/socials.txt" => include_bytes!("../../data/basic_socials.txt"),
             "data/area/arealist.txt" => b"basic.are",
             "data/area/basic.are" => include_bytes!("../../data/basic_area.txt"),
+            // Saved data (e.g. notes) starts out empty, and is never saved
+            path if path.starts_with("data/notes/")
+                || matches!(
+                    path,
+                    "data/mail.json"
+                        | "data/accounts.json"
+                        | "data/forbidden_names.txt"
+                        | "data/command_trust.txt"
+                        | "data/messages.json"
+                        | "data/clans.json"
+                        | "data/clock.txt"
+                        | "data/auction.json"
+                        | "data/stalls.json"
+                ) =>
+            {
+                return Err(std::io::ErrorKind::NotFound.into())
+            }
             _ => panic!("Unknown file {}", path),
         };
 
@@ -77,6 +94,23 @@ impl Files for StaticFiles {
             "data/area/wild_1.are" => include_bytes!("../../data/area/wild_1.are"),
             "data/area/wild_2.are" => include_bytes!("../../data/area/wild_2.are"),
 
+            // Saved data (e.g. notes) starts out empty, and is never saved
+            path if path.starts_with("data/notes/")
+                || matches!(
+                    path,
+                    "data/mail.json"
+                        | "data/accounts.json"
+                        | "data/forbidden_names.txt"
+                        | "data/command_trust.txt"
+                        | "data/messages.json"
+                        | "data/clans.json"
+                        | "data/clock.txt"
+                        | "data/auction.json"
+                        | "data/stalls.json"
+                ) =>
+            {
+                return Err(std::io::ErrorKind::NotFound.into())
+            }
             _ => panic!("Unknown file {}", path),
         };
         Ok(contents.to_vec())
@@ -9,10 +9,15 @@ struct Game {
 }
 
 impl Game {
-    fn new(files: &dyn Files) -> Self {
-        Self {
-            world: WorldState::from_files(files),
-        }
+    fn new(files: &dyn Files, seed: Option<u64>) -> Self {
+        let mut world = match seed {
+            Some(seed) => WorldState::with_seed(files, seed),
+            None => WorldState::from_files(files),
+        };
+        // There's only one player, and they can use every command
+        world.trust_guests();
+
+        Self { world }
     }
 
     fn echoes(&mut self) -> &mut String {
@@ -60,8 +65,93 @@ fn echo(text: &str) {
     }
 }
 
+/// The seed from `--seed <number>` on the command line, to play the same
+/// game again.
+fn seed_argument() -> Option<u64> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            return args.next().and_then(|seed| seed.parse().ok());
+        }
+    }
+    None
+}
+
+/// The area of `dot <area>` on the command line, to print its rooms and
+/// exits as a Graphviz graph instead of playing.
+fn dot_argument() -> Option<Option<String>> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("dot") => Some(args.next()),
+        _ => None,
+    }
+}
+
+/// The area of `export [area]` on the command line, or all of them, to
+/// print as JSON instead of playing.
+fn export_argument() -> Option<Option<String>> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("export") => Some(args.next()),
+        _ => None,
+    }
+}
+
+/// The area of `validate [area]` on the command line, or all of them, to
+/// check for broken exits, resets and mobprog triggers instead of playing.
+fn validate_argument() -> Option<Option<String>> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("validate") => Some(args.next()),
+        _ => None,
+    }
+}
+
 fn main() {
     let files = files::StaticFiles;
+    let seed = seed_argument();
+
+    if let Some(area) = dot_argument() {
+        let area = area.unwrap_or_else(|| {
+            eprintln!("Usage: clicore dot <area>");
+            std::process::exit(2);
+        });
+        match Game::new(&files, seed).world.area_dot(&area) {
+            Ok(dot) => print!("{}", dot),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(area) = export_argument() {
+        match Game::new(&files, seed).world.export_json(area.as_deref()) {
+            Ok(json) => print!("{}", json),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(area) = validate_argument() {
+        match Game::new(&files, seed).world.validate(area.as_deref()) {
+            Ok(report) => {
+                print!("{}", report);
+                if report.errors() > 0 {
+                    std::process::exit(1);
+                }
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
     // Print "Welcome to DemiMUD" banner
     // Made with: figlet -f small Welcome to DemiMUD | lolcat --seed 25 --force
@@ -79,7 +169,7 @@ fn main() {
     // Print info about DemiMUD and help pages
     echo(&colorize(&files.read_file("clicore/notice.txt").unwrap()));
 
-    let mut game = Game::new(&files);
+    let mut game = Game::new(&files, seed);
     game.world.add_player("You");
     game.send_echoes().unwrap();
 
@@ -93,7 +183,7 @@ fn main() {
                 echo("Just you. It's a CLI after all.\r\n");
             }
             ["restart"] => {
-                game = Game::new(&files);
+                game = Game::new(&files, seed);
                 game.world.add_player("You");
                 echo("World reloaded.\r\n");
             }
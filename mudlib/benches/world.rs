@@ -0,0 +1,128 @@
+//! Benchmarks for loading the world, and running it.
+//!
+//! They use the Dawn of Time areas in `data/area` when they were set up with
+//! `data/setup.sh`, and the basic area otherwise. Run them with
+//! `cargo bench -p mudlib`.
+
+use std::{hint::black_box, path::Path};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use mudlib::{
+    benchmarks::{import_areas, parse_areas},
+    Files, WorldState,
+};
+
+/// Commands a player runs over and over, in this order
+const COMMANDS: &[&str] = &[
+    "look",
+    "inventory",
+    "say Hello there!",
+    "exits",
+    "time",
+    "weather",
+    "map",
+    "smile",
+];
+
+/// The Dawn of Time areas and socials if they are there, or the basic ones.
+/// Nothing is saved, and other data files start out empty.
+struct BenchFiles {
+    dawn_areas: bool,
+}
+
+impl BenchFiles {
+    fn new() -> Self {
+        BenchFiles {
+            dawn_areas: Path::new("../data/area/arealist.txt").exists(),
+        }
+    }
+}
+
+impl Files for BenchFiles {
+    fn read_file_raw(&self, path: &str) -> Result<Vec<u8>, std::io::Error> {
+        if self.dawn_areas && (path.starts_with("data/area/") || path == "data/socials.txt") {
+            return std::fs::read(format!("../{}", path));
+        }
+
+        let contents: &[u8] = match path {
+            "data/socials.txt" => include_bytes!("../../data/basic_socials.txt"),
+            "data/area/arealist.txt" => b"basic.are",
+            "data/area/basic.are" => include_bytes!("../../data/basic_area.txt"),
+            _ => return Err(std::io::ErrorKind::NotFound.into()),
+        };
+
+        Ok(contents.to_vec())
+    }
+}
+
+/// Every run starts from the same seed, to do the same work each time
+const SEED: u64 = 483;
+
+fn world_with_player(files: &BenchFiles) -> WorldState {
+    let mut world = WorldState::with_seed(files, SEED);
+    world.add_player("Bench");
+    world.process_player_command("Bench", &["recall", "mudschool"]);
+    world
+}
+
+fn load(c: &mut Criterion) {
+    let files = BenchFiles::new();
+
+    c.bench_function("parse areas", |b| b.iter(|| black_box(parse_areas(&files))));
+
+    let areas = parse_areas(&files);
+    c.bench_function("import areas", |b| {
+        b.iter(|| black_box(import_areas(&areas)))
+    });
+}
+
+fn ticks(c: &mut Criterion) {
+    let files = BenchFiles::new();
+    let mut group = c.benchmark_group("run");
+    group.sample_size(10);
+
+    // Each update of the world is a pulse, a second of game time
+    group.bench_function("1000 pulses", |b| {
+        b.iter_batched(
+            || world_with_player(&files),
+            |mut world| {
+                for _ in 0..1000 {
+                    world.update_world();
+                    if let Some(echoes) = world.player_echoes("Bench") {
+                        echoes.clear();
+                    }
+                }
+                world
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn commands(c: &mut Criterion) {
+    let files = BenchFiles::new();
+    let mut world = world_with_player(&files);
+    let commands: Vec<Vec<&str>> = COMMANDS
+        .iter()
+        .map(|command| command.split_whitespace().collect())
+        .collect();
+
+    let mut group = c.benchmark_group("dispatch");
+    group.throughput(Throughput::Elements(commands.len() as u64));
+    group.bench_function("player commands", |b| {
+        b.iter(|| {
+            for words in &commands {
+                world.process_player_command("Bench", words);
+            }
+            if let Some(echoes) = world.player_echoes("Bench") {
+                echoes.clear();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, load, ticks, commands);
+criterion_main!(benches);
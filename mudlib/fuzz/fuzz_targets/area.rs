@@ -0,0 +1,15 @@
+//! Feeds mutated area files to the area loader, which must turn anything it
+//! can't make sense of into an error, rather than panic or run out of memory.
+//!
+//! Run it from `mudlib/` with `cargo +nightly fuzz run area`, after copying
+//! some areas to start from into `fuzz/corpus/area`, e.g. the basic area.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(contents) = std::str::from_utf8(data) {
+        let _ = mudlib::fuzzing::parse_area(contents);
+    }
+});
@@ -0,0 +1,556 @@
+//! Player accounts, with passwords, name policy, and character deletion.
+//!
+//! Accounts are created when someone logs in with a name that isn't taken,
+//! and are saved to `data/accounts.json`. Passwords are stored as salted
+//! PBKDF2-HMAC-SHA256 hashes, slow to compute on purpose so that a leaked
+//! file can't be brute-forced quickly; older plain SHA-256 hashes are
+//! replaced the next time the password is typed right. After a few wrong
+//! passwords in a row, an account can't be tried again for a while.
+//!
+//! New names must be a single word of letters, and can't be one of the
+//! reserved names below, contain a word from `data/forbidden_names.txt` (one
+//! per line, optional), or be the keyword of a mobile in the world.
+//!
+//! The first account ever created is made an implementor; see `trust.rs`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    agent::EntityAgent, echo, files::Files, preferences::Preferences, state::unix_time,
+    trust::Trust,
+};
+
+const ACCOUNTS_FILE: &str = "data/accounts.json";
+const FORBIDDEN_NAMES_FILE: &str = "data/forbidden_names.txt";
+
+/// Names that would be confusing when used as keywords or in messages
+static RESERVED_NAMES: &[&str] = &[
+    "all",
+    "self",
+    "me",
+    "myself",
+    "you",
+    "someone",
+    "somebody",
+    "nobody",
+    "everyone",
+    "here",
+    "immortal",
+    "immortals",
+    "admin",
+    "god",
+    "gods",
+    "new",
+    "name",
+    "who",
+    "exit",
+    "quit",
+    "restart",
+    "reboot",
+    "wizlock",
+    "newlock",
+    "shutdown",
+    "limbo",
+    "void",
+];
+
+lazy_static! {
+    static ref DEFAULT_PREFERENCES: Preferences = Preferences::default();
+}
+
+const MIN_NAME_LENGTH: usize = 3;
+const MAX_NAME_LENGTH: usize = 12;
+const MIN_PASSWORD_LENGTH: usize = 5;
+/// Rounds of PBKDF2 for new password hashes
+const PASSWORD_ROUNDS: u32 = 600_000;
+/// Wrong passwords in a row before an account can't be tried for a while
+#[cfg(feature = "net")]
+const MAX_FAILED_LOGINS: u32 = 5;
+/// Seconds to wait after the last of too many wrong passwords
+#[cfg(feature = "net")]
+const LOGIN_LOCKOUT: u64 = 60;
+
+pub(crate) struct Accounts {
+    /// All accounts, by lowercase name
+    accounts: BTreeMap<String, Account>,
+    forbidden_names: Vec<String>,
+    /// Preferences of players without an account, which aren't saved
+    guest_preferences: BTreeMap<String, Preferences>,
+    /// Trust of players without an account
+    guest_trust: Trust,
+    /// Players who typed their password and must now confirm the deletion
+    pending_deletion: BTreeSet<String>,
+    /// Deleted players that should be disconnected; see `take_deleted()`
+    deleted: Vec<String>,
+    /// Wrong passwords typed in a row for each account, and when the last
+    /// one was, in unix time
+    failed_logins: BTreeMap<String, (u32, u64)>,
+    changed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct Account {
+    pub name: String,
+    salt: String,
+    password_hash: String,
+    /// Rounds of PBKDF2 of the hash, or 0 for a single round of SHA-256
+    #[serde(default)]
+    password_rounds: u32,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    #[serde(default)]
+    pub trust: Trust,
+    /// Used before trust levels; immortals become implementors when loaded
+    #[serde(default, skip_serializing)]
+    immortal: bool,
+    #[serde(default)]
+    pub preferences: Preferences,
+    /// The first and last vnum this player may build on, if any
+    #[serde(default)]
+    pub vnums: Option<(usize, usize)>,
+    /// Frozen players can't use any command; see `discipline.rs`
+    #[serde(default)]
+    pub frozen: bool,
+    /// Muted players can't talk on channels
+    #[serde(default)]
+    pub muted: bool,
+    /// Addresses the player logged in from, to spot new ones
+    #[serde(default)]
+    addresses: BTreeSet<String>,
+    /// Silver kept in the bank; see `bank.rs`
+    #[serde(default)]
+    bank: usize,
+    /// Whether the player was given their starting silver; accounts from
+    /// before this was kept had it already
+    #[serde(default = "given")]
+    starting_silver: bool,
+}
+
+fn given() -> bool {
+    true
+}
+
+impl Accounts {
+    pub fn load(files: &dyn Files) -> Self {
+        let mut accounts: BTreeMap<String, Account> = match files.read_file_raw(ACCOUNTS_FILE) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .unwrap_or_else(|err| panic!("Could not parse {}: {}", ACCOUNTS_FILE, err)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => panic!("Could not read {}: {}", ACCOUNTS_FILE, err),
+        };
+
+        let mut changed = false;
+        for account in accounts.values_mut().filter(|account| account.immortal) {
+            account.immortal = false;
+            account.trust = account.trust.max(Trust::Implementor);
+            changed = true;
+        }
+
+        let forbidden_names = match files.read_file(FORBIDDEN_NAMES_FILE) {
+            Ok(contents) => contents
+                .lines()
+                .map(|line| line.trim().to_lowercase())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => panic!("Could not read {}: {}", FORBIDDEN_NAMES_FILE, err),
+        };
+
+        Accounts {
+            accounts,
+            forbidden_names,
+            guest_preferences: BTreeMap::new(),
+            guest_trust: Trust::Player,
+            pending_deletion: BTreeSet::new(),
+            deleted: Vec::new(),
+            failed_logins: BTreeMap::new(),
+            changed,
+        }
+    }
+
+    pub fn save_changes(&mut self, files: &dyn Files) -> Result<(), std::io::Error> {
+        if self.changed {
+            let contents =
+                serde_json::to_vec_pretty(&self.accounts).expect("Accounts should serialize");
+            files.write_file_raw(ACCOUNTS_FILE, &contents)?;
+            self.changed = false;
+        }
+
+        Ok(())
+    }
+
+    pub fn account(&self, name: &str) -> Option<&Account> {
+        self.accounts.get(&name.to_lowercase())
+    }
+
+    pub fn trust(&self, name: &str) -> Trust {
+        self.account(name)
+            .map(|account| account.trust)
+            .unwrap_or(self.guest_trust)
+    }
+
+    pub fn set_guest_trust(&mut self, trust: Trust) {
+        self.guest_trust = trust;
+    }
+
+    pub fn set_trust(&mut self, name: &str, trust: Trust) {
+        if let Some(account) = self.accounts.get_mut(&name.to_lowercase()) {
+            account.trust = trust;
+            self.changed = true;
+        }
+    }
+
+    pub fn is_immortal(&self, name: &str) -> bool {
+        self.trust(name) >= Trust::Immortal
+    }
+
+    pub fn is_frozen(&self, name: &str) -> bool {
+        self.account(name).is_some_and(|account| account.frozen)
+    }
+
+    pub fn set_frozen(&mut self, name: &str, frozen: bool) {
+        if let Some(account) = self.accounts.get_mut(&name.to_lowercase()) {
+            account.frozen = frozen;
+            self.changed = true;
+        }
+    }
+
+    pub fn is_muted(&self, name: &str) -> bool {
+        self.account(name).is_some_and(|account| account.muted)
+    }
+
+    pub fn set_muted(&mut self, name: &str, muted: bool) {
+        if let Some(account) = self.accounts.get_mut(&name.to_lowercase()) {
+            account.muted = muted;
+            self.changed = true;
+        }
+    }
+
+    /// Remember an address the player logged in from, and tell whether it's
+    /// a new one.
+    #[cfg(feature = "net")]
+    pub fn remember_address(&mut self, name: &str, address: &str) -> bool {
+        match self.accounts.get_mut(&name.to_lowercase()) {
+            Some(account) => {
+                let new = account.addresses.insert(address.to_string());
+                self.changed |= new;
+                new
+            }
+            None => false,
+        }
+    }
+
+    pub fn bank_balance(&self, name: &str) -> usize {
+        self.account(name).map(|account| account.bank).unwrap_or(0)
+    }
+
+    pub fn set_bank_balance(&mut self, name: &str, balance: usize) {
+        if let Some(account) = self.accounts.get_mut(&name.to_lowercase()) {
+            account.bank = balance;
+            self.changed = true;
+        }
+    }
+
+    /// Count a player's starting silver as given, and tell whether it wasn't
+    /// already, so that nobody gets it twice. Guests have no account to keep
+    /// this in, and get `None`.
+    pub fn give_starting_silver(&mut self, name: &str) -> Option<bool> {
+        let account = self.accounts.get_mut(&name.to_lowercase())?;
+        let first_time = !account.starting_silver;
+        account.starting_silver = true;
+        self.changed |= first_time;
+        Some(first_time)
+    }
+
+    pub fn builder_vnums(&self, name: &str) -> Option<(usize, usize)> {
+        self.account(name).and_then(|account| account.vnums)
+    }
+
+    pub fn set_builder_vnums(&mut self, name: &str, vnums: Option<(usize, usize)>) {
+        if let Some(account) = self.accounts.get_mut(&name.to_lowercase()) {
+            account.vnums = vnums;
+            self.changed = true;
+        }
+    }
+
+    pub fn preferences(&self, name: &str) -> &Preferences {
+        match self.account(name) {
+            Some(account) => &account.preferences,
+            None => self
+                .guest_preferences
+                .get(name)
+                .unwrap_or(&DEFAULT_PREFERENCES),
+        }
+    }
+
+    /// Get a player's preferences for changing them; they will be saved.
+    pub fn preferences_mut(&mut self, name: &str) -> &mut Preferences {
+        match self.accounts.get_mut(&name.to_lowercase()) {
+            Some(account) => {
+                self.changed = true;
+                &mut account.preferences
+            }
+            None => self.guest_preferences.entry(name.to_string()).or_default(),
+        }
+    }
+
+    /// Seconds until passwords can be tried again for an account, after too
+    /// many wrong ones in a row; 0 if they can be tried now.
+    #[cfg(feature = "net")]
+    pub fn login_wait(&self, name: &str) -> u64 {
+        match self.failed_logins.get(&name.to_lowercase()) {
+            Some((failures, last)) if *failures >= MAX_FAILED_LOGINS => {
+                (last + LOGIN_LOCKOUT).saturating_sub(unix_time())
+            }
+            _ => 0,
+        }
+    }
+
+    /// Check a password, and count it if it is wrong; see `login_wait()`.
+    /// Old hashes are replaced with ones as slow as new ones.
+    pub fn check_password(&mut self, name: &str, password: &str) -> bool {
+        let lowercase = name.to_lowercase();
+        let account = match self.accounts.get_mut(&lowercase) {
+            Some(account) => account,
+            None => return false,
+        };
+
+        let hash = hash_password(&account.salt, password, account.password_rounds);
+        if hash != account.password_hash {
+            let failures = self.failed_logins.entry(lowercase).or_default();
+            *failures = (failures.0 + 1, unix_time());
+            return false;
+        }
+
+        if account.password_rounds < PASSWORD_ROUNDS {
+            account.password_hash = hash_password(&account.salt, password, PASSWORD_ROUNDS);
+            account.password_rounds = PASSWORD_ROUNDS;
+            self.changed = true;
+        }
+        self.failed_logins.remove(&lowercase);
+        true
+    }
+
+    /// Check a name for a new character, and return it properly capitalized.
+    ///
+    /// Mobile keywords are passed in by the caller, since they come from the
+    /// world's templates.
+    pub fn check_new_name<'a>(
+        &self,
+        name: &str,
+        mut mobile_keywords: impl Iterator<Item = &'a str>,
+    ) -> Result<String, &'static str> {
+        let lowercase = name.to_lowercase();
+
+        if !name.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err("Names can only contain letters.");
+        } else if name.len() < MIN_NAME_LENGTH || name.len() > MAX_NAME_LENGTH {
+            return Err("Names must be between 3 and 12 letters long.");
+        } else if self.accounts.contains_key(&lowercase) {
+            return Err("That name is already taken.");
+        } else if RESERVED_NAMES.contains(&lowercase.as_str()) {
+            return Err("That name is reserved; please choose another one.");
+        } else if self
+            .forbidden_names
+            .iter()
+            .any(|forbidden| lowercase.contains(forbidden.as_str()))
+        {
+            return Err("That name is not allowed; please choose another one.");
+        } else if mobile_keywords.any(|keyword| keyword.eq_ignore_ascii_case(name)) {
+            return Err(
+                "Someone in the realm already goes by that name; please choose another one.",
+            );
+        }
+
+        let mut chars = lowercase.chars();
+        let first = chars.next().expect("Checked length above");
+        Ok(first.to_ascii_uppercase().to_string() + chars.as_str())
+    }
+
+    pub fn check_new_password(password: &str) -> Result<(), &'static str> {
+        if password.len() < MIN_PASSWORD_LENGTH {
+            Err("Passwords must be at least 5 characters long.")
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn create(&mut self, name: &str, password: &str) {
+        let salt: [u8; 16] = rand::rng().random();
+        let salt = to_hex(&salt);
+        let password_hash = hash_password(&salt, password, PASSWORD_ROUNDS);
+
+        let account = Account {
+            name: name.to_string(),
+            salt,
+            password_hash,
+            password_rounds: PASSWORD_ROUNDS,
+            created: unix_time(),
+            trust: if self.accounts.is_empty() {
+                Trust::Implementor
+            } else {
+                Trust::Player
+            },
+            immortal: false,
+            preferences: Preferences::default(),
+            vnums: None,
+            frozen: false,
+            muted: false,
+            addresses: BTreeSet::new(),
+            bank: 0,
+            starting_silver: false,
+        };
+
+        self.changed = true;
+        self.accounts.insert(name.to_lowercase(), account);
+    }
+
+    fn delete(&mut self, name: &str) -> Option<Account> {
+        let account = self.accounts.remove(&name.to_lowercase())?;
+
+        self.pending_deletion.remove(&account.name);
+        self.deleted.push(account.name.clone());
+        self.changed = true;
+
+        Some(account)
+    }
+
+    /// Names of characters deleted since the last call, whose connections
+    /// should be closed.
+    pub fn take_deleted(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.deleted)
+    }
+}
+
+/// Hash a password with PBKDF2, or with a single round of SHA-256 like
+/// accounts used to be if there are no rounds.
+fn hash_password(salt: &str, password: &str, rounds: u32) -> String {
+    if rounds == 0 {
+        let mut hasher = Sha256::new();
+        hasher.update(salt.as_bytes());
+        hasher.update(password.as_bytes());
+        return to_hex(&hasher.finalize());
+    }
+
+    let mut hash = [0; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt.as_bytes(), rounds, &mut hash);
+    to_hex(&hash)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Deletion commands
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_delete(&mut self, words: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let player = match myself.player_name() {
+            Some(player) if self.accounts.account(player).is_some() => player.to_string(),
+            _ => {
+                echo!(self.info(), "You don't have a character to delete.\r\n");
+                return;
+            }
+        };
+
+        match words {
+            [] => {
+                echo!(
+                    self.info(),
+                    "This will `Rpermanently`^ delete your character. If you are sure, type\r\n\
+                    '`Wdelete <password>`^', then '`Wdelete confirm`^'.\r\n"
+                );
+            }
+            ["confirm"] if self.accounts.pending_deletion.contains(&player) => {
+                echo!(
+                    self.info(),
+                    "Your character has been deleted. Farewell!\r\n"
+                );
+                self.accounts.delete(&player);
+                self.forget_player(&player);
+            }
+            ["player", name] => self.do_delete_player(&player, name),
+            [password] => {
+                if self.accounts.check_password(&player, password) {
+                    self.accounts.pending_deletion.insert(player);
+                    echo!(
+                        self.info(),
+                        "Type '`Wdelete confirm`^' to delete your character. There is no going back!\r\n"
+                    );
+                } else {
+                    self.accounts.pending_deletion.remove(&player);
+                    echo!(self.info(), "Wrong password.\r\n");
+                }
+            }
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Wdelete <password>`^', then '`Wdelete confirm`^'.\r\n"
+                );
+            }
+        }
+    }
+
+    fn do_delete_player(&mut self, player: &str, name: &str) {
+        if !self.accounts.is_immortal(player) {
+            echo!(
+                self.info(),
+                "Only immortals can delete other characters.\r\n"
+            );
+            return;
+        }
+
+        let account = match self.accounts.account(name) {
+            Some(account) => account.clone(),
+            None => {
+                echo!(self.info(), "There is no character named like that.\r\n");
+                return;
+            }
+        };
+
+        if account.trust >= Trust::Immortal {
+            echo!(self.info(), "Immortals can't be deleted this way.\r\n");
+            return;
+        }
+
+        if let Some(victim_id) = self.entity_world.player_entity_id(&account.name) {
+            let victim = self.entity_world.entity_info(victim_id);
+            echo!(
+                self.players.info(&victim),
+                "Your character has been deleted by an immortal.\r\n"
+            );
+        }
+
+        self.accounts.delete(&account.name);
+        self.forget_player(&account.name);
+        println!("{} deleted the character {}.", player, account.name);
+
+        echo!(self.info(), "Deleted {}.\r\n", account.name);
+    }
+
+    /// Remove everything else that belongs to a deleted character.
+    fn forget_player(&mut self, name: &str) {
+        self.post_office.forget_player(name, self.entity_world);
+        self.message_queue.forget_player(name);
+        self.clans.forget_player(name);
+        self.stalls.forget_player(name);
+
+        if let Some(player_id) = self.entity_world.player_entity_id(name) {
+            let player = self.entity_world.entity_info(player_id);
+            let mut act = self.players.act_alone(&player);
+            echo!(act.others(), "$^$n fades out of existence.\r\n");
+
+            let limbo = self
+                .entity_world
+                .landmark("limbo")
+                .expect("Limbo should always exist");
+            self.entity_world.move_entity(player_id, limbo);
+        }
+    }
+}
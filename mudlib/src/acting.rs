@@ -50,12 +50,31 @@
 //!
 //! Example: "$^$n licks $mself with $s tongue." would be translated to "An
 //! apple licks itself with its tongue."
+//!
+//! The uppercase variables ($N, $M, $S, $E) refer to the target instead.
+//!
+//! Instead of a message for each of them, `.all()` sends one message to
+//! everyone, told from where each of them stands: the actor and the target
+//! read "you" instead of their own names and pronouns. Verbs that go with
+//! the actor are written as `$[you form|others form]`:
+//!
+//! ```ignore
+//! echo!(act.all(), "$^$n $[light|lights] $N, and $[hold|holds] it up.\r\n");
+//! ```
+//!
+//! The actor reads "You light a torch, and hold it up.", while the others
+//! read "Bob lights a torch, and holds it up.". A `self` right after a
+//! pronoun that is "you" makes "yourself".
+//!
+//! Actors that a player can't see (see `visibility.rs`) are called "someone"
+//! in what that player reads.
 
 use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter, Result, Write};
 
 use crate::{
     entity::{EntityId, EntityInfo},
+    visibility::Sight,
     world::Gender,
 };
 
@@ -66,6 +85,14 @@ pub(crate) struct Players {
 #[derive(Default)]
 pub(crate) struct PlayerEcho {
     pub echo_buffer: String,
+    /// The player's connection was closed, but their body is still around
+    pub link_dead: bool,
+    /// The immortal who sees everything this player sees; see `do_snoop()`
+    pub snooped_by: Option<String>,
+    pub sight: Sight,
+    /// The last prompt rendered for the player, with the change tick it was
+    /// rendered in; see `WorldState::prompt()`
+    pub prompt: Option<(u64, String)>,
     current_target_type: Option<TargetType>,
 }
 
@@ -98,6 +125,7 @@ impl Players {
 pub(crate) trait Actor {
     fn entity_id(&self) -> EntityId;
     fn is_player(&self, player_name: &str) -> bool;
+    fn player_name(&self) -> Option<&str>;
     fn colocated_with_player(&self, player_name: &str) -> bool;
 
     fn short_description(&self, f: &mut Formatter, capitalized: bool) -> Result;
@@ -118,13 +146,17 @@ pub(crate) trait Actor {
 
 impl<'e> Actor for EntityInfo<'e> {
     fn entity_id(&self) -> EntityId {
-        EntityInfo::entity_id(&self)
+        EntityInfo::entity_id(self)
     }
 
     fn is_player(&self, player_name: &str) -> bool {
         self.is_player_with_name(player_name)
     }
 
+    fn player_name(&self) -> Option<&str> {
+        EntityInfo::player_name(self)
+    }
+
     fn colocated_with_player(&self, player_name: &str) -> bool {
         self.colocated_with_player(player_name)
     }
@@ -176,6 +208,8 @@ pub(crate) struct ActingStage<'p, 'e, ActsType = ()> {
 
     current_actor: &'e dyn Actor,
     target_actor: Option<&'e dyn Actor>,
+    current_sight: Sight,
+    target_sight: Sight,
 }
 
 impl<'p, 'e> ActingStage<'p, 'e, ()> {
@@ -184,12 +218,19 @@ impl<'p, 'e> ActingStage<'p, 'e, ()> {
         current: &'e dyn Actor,
         target: Option<&'e dyn Actor>,
     ) -> ActingStage<'p, 'e> {
+        let current_sight = players.sight(current);
+        let target_sight = target
+            .map(|target| players.sight(target))
+            .unwrap_or_default();
+
         for (player_name, player_echo) in players.player_echoes.iter_mut() {
             player_echo.current_target_type = if current.is_player(player_name) {
                 Some(TargetType::Myself)
             } else if target.map(|target| target.is_player(player_name)) == Some(true) {
                 Some(TargetType::Target)
-            } else if current.colocated_with_player(player_name) {
+            } else if current.colocated_with_player(player_name)
+                && player_echo.sight.can_see(&current_sight)
+            {
                 Some(TargetType::Others)
             } else {
                 None
@@ -201,6 +242,8 @@ impl<'p, 'e> ActingStage<'p, 'e, ()> {
             acts: (),
             current_actor: current,
             target_actor: target,
+            current_sight,
+            target_sight,
         }
     }
 
@@ -216,6 +259,8 @@ impl<'p, 'e> ActingStage<'p, 'e, ()> {
             players: self.players,
             current_actor: self.current_actor,
             target_actor: self.target_actor,
+            current_sight: self.current_sight,
+            target_sight: self.target_sight,
         }
     }
 }
@@ -224,21 +269,29 @@ impl<'p, 'e, ActsType> ActingStage<'p, 'e, ActsType> {
     pub fn myself<'a>(&'a mut self) -> ActTarget<'a, 'p, 'e, ActsType> {
         ActTarget {
             stage: self,
-            target_type: TargetType::Myself,
+            target_type: Some(TargetType::Myself),
         }
     }
 
     pub fn target<'a>(&'a mut self) -> ActTarget<'a, 'p, 'e, ActsType> {
         ActTarget {
             stage: self,
-            target_type: TargetType::Target,
+            target_type: Some(TargetType::Target),
         }
     }
 
     pub fn others<'a>(&'a mut self) -> ActTarget<'a, 'p, 'e, ActsType> {
         ActTarget {
             stage: self,
-            target_type: TargetType::Others,
+            target_type: Some(TargetType::Others),
+        }
+    }
+
+    /// Everyone, each told the message from their own perspective.
+    pub fn all<'a>(&'a mut self) -> ActTarget<'a, 'p, 'e, ActsType> {
+        ActTarget {
+            stage: self,
+            target_type: None,
         }
     }
 }
@@ -249,7 +302,7 @@ impl<'p, 'e> ActingStage<'p, 'e, Acts> {
     }
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, Clone, Copy)]
 pub(crate) enum TargetType {
     Myself,
     Target,
@@ -273,49 +326,73 @@ impl Write for InfoTarget<'_> {
 
 pub(crate) struct ActTarget<'a, 'p: 'a, 'e: 'a, ActType = ()> {
     stage: &'a mut ActingStage<'p, 'e, ActType>,
-    target_type: TargetType,
+    /// Who the message is for, or everyone from their own perspective
+    target_type: Option<TargetType>,
 }
 
-impl Write for ActTarget<'_, '_, '_, ()> {
-    fn write_str(&mut self, message: &str) -> Result {
-        let message = ReplaceActVariables {
-            current: self.stage.current_actor,
-            target: self.stage.target_actor,
-            message,
-        };
+impl<ActsType> ActTarget<'_, '_, '_, ActsType> {
+    /// Write a message to each player it is for, naming the actors they can
+    /// see.
+    fn write_to_players(&mut self, message: &str) -> Result {
+        let stage = &mut *self.stage;
+
+        for player_echo in stage.players.player_echoes.values_mut() {
+            let target_type = match (player_echo.current_target_type, self.target_type) {
+                (Some(theirs), None) => theirs,
+                (Some(theirs), Some(wanted)) if theirs == wanted => theirs,
+                _ => continue,
+            };
 
-        for player_echo in self.stage.players.player_echoes.values_mut() {
-            if player_echo.current_target_type.as_ref() == Some(&self.target_type) {
-                write!(player_echo.echo_buffer, "{}", message)?;
-            }
+            let message = ReplaceActVariables {
+                current: stage.current_actor,
+                target: stage.target_actor,
+                current_seen: target_type == TargetType::Myself
+                    || player_echo.sight.can_see(&stage.current_sight),
+                target_seen: target_type == TargetType::Target
+                    || player_echo.sight.can_see(&stage.target_sight),
+                you: self.target_type.is_none().then_some(target_type),
+                message,
+            };
+            write!(player_echo.echo_buffer, "{}", message)?;
         }
 
         Ok(())
     }
 }
 
+impl Write for ActTarget<'_, '_, '_, ()> {
+    fn write_str(&mut self, message: &str) -> Result {
+        self.write_to_players(message)
+    }
+}
+
 impl Write for ActTarget<'_, '_, '_, Acts> {
     fn write_str(&mut self, message: &str) -> Result {
-        let message = ReplaceActVariables {
-            current: self.stage.current_actor,
-            target: self.stage.target_actor,
-            message,
+        let target_types = match self.target_type {
+            Some(target_type) => vec![target_type],
+            None => vec![TargetType::Myself, TargetType::Target, TargetType::Others],
         };
 
-        let stored_acts = match self.target_type {
-            TargetType::Myself => &mut self.stage.acts.myself,
-            TargetType::Target => &mut self.stage.acts.target,
-            TargetType::Others => &mut self.stage.acts.others,
-        };
-        write!(stored_acts, "{}", message)?;
+        for target_type in target_types {
+            // Stored acts name everyone, as if seen by all
+            let stored_message = ReplaceActVariables {
+                current: self.stage.current_actor,
+                target: self.stage.target_actor,
+                current_seen: true,
+                target_seen: true,
+                you: self.target_type.is_none().then_some(target_type),
+                message,
+            };
 
-        for player_echo in self.stage.players.player_echoes.values_mut() {
-            if player_echo.current_target_type.as_ref() == Some(&self.target_type) {
-                write!(player_echo.echo_buffer, "{}", message)?;
-            }
+            let stored_acts = match target_type {
+                TargetType::Myself => &mut self.stage.acts.myself,
+                TargetType::Target => &mut self.stage.acts.target,
+                TargetType::Others => &mut self.stage.acts.others,
+            };
+            write!(stored_acts, "{}", stored_message)?;
         }
 
-        Ok(())
+        self.write_to_players(message)
     }
 }
 
@@ -345,9 +422,33 @@ impl Display for EscapeVariables<'_> {
     }
 }
 
+fn someone(f: &mut Formatter, capitalized: bool) -> Result {
+    if capitalized {
+        "Someone".fmt(f)
+    } else {
+        "someone".fmt(f)
+    }
+}
+
+/// "You" instead of a name or pronoun, "your" before "self" and as a
+/// possessive.
+fn you(f: &mut Formatter, capitalized: bool, possessive: bool) -> Result {
+    match (capitalized, possessive) {
+        (false, false) => "you".fmt(f),
+        (false, true) => "your".fmt(f),
+        (true, false) => "You".fmt(f),
+        (true, true) => "Your".fmt(f),
+    }
+}
+
 struct ReplaceActVariables<'e, 'm> {
     current: &'e dyn Actor,
     target: Option<&'e dyn Actor>,
+    /// Actors that can't be seen are called "someone"
+    current_seen: bool,
+    target_seen: bool,
+    /// Who reads the message, for messages told from each one's perspective
+    you: Option<TargetType>,
     message: &'m str,
 }
 
@@ -371,10 +472,59 @@ impl Display for ReplaceActVariables<'_, '_> {
                 }
             }
 
+            let current_is_you = self.you == Some(TargetType::Myself);
+            let target_is_you = self.you == Some(TargetType::Target) && self.target.is_some();
+            let rest = message.get(2..).unwrap_or("");
+            let before_self = rest.starts_with("self");
+
             match message[1..].chars().next() {
                 Some('$') => {
                     '$'.fmt(f)?;
                 }
+                Some('n' | 'e') if current_is_you => {
+                    you(f, capitalized, false)?;
+                }
+                Some('m') if current_is_you => {
+                    you(f, capitalized, before_self)?;
+                }
+                Some('s') if current_is_you => {
+                    you(f, capitalized, true)?;
+                }
+                Some('N' | 'E') if target_is_you => {
+                    you(f, capitalized, false)?;
+                }
+                Some('M') if target_is_you => {
+                    you(f, capitalized, before_self)?;
+                }
+                Some('S') if target_is_you => {
+                    you(f, capitalized, true)?;
+                }
+                Some('[') if rest.contains(']') => {
+                    let end = message.find(']').expect("Checked above");
+                    let verbs = &message[2..end];
+                    let (you_form, others_form) = verbs.split_once('|').unwrap_or((verbs, verbs));
+                    let verb = if current_is_you {
+                        you_form
+                    } else {
+                        others_form
+                    };
+
+                    let mut letters = verb.chars();
+                    match letters.next() {
+                        Some(first) if capitalized => {
+                            first.to_uppercase().fmt(f)?;
+                            letters.as_str().fmt(f)?;
+                        }
+                        _ => verb.fmt(f)?,
+                    }
+
+                    capitalized = false;
+                    message = &message[end + 1..];
+                    continue;
+                }
+                Some('n') if !self.current_seen => {
+                    someone(f, capitalized)?;
+                }
                 Some('n') => {
                     self.current.short_description(f, capitalized)?;
                 }
@@ -387,6 +537,9 @@ impl Display for ReplaceActVariables<'_, '_> {
                 Some('s') => {
                     self.current.possessive_pronoun(f, capitalized)?;
                 }
+                Some('N') if self.target.is_some() && !self.target_seen => {
+                    someone(f, capitalized)?;
+                }
                 Some('N') if self.target.is_some() => {
                     let target = self.target.expect("Checked above");
                     target.short_description(f, capitalized)?;
@@ -0,0 +1,543 @@
+//! Commands for immortals to get around the world, move others, and create
+//! and remove mobiles and objects.
+//!
+//! Locations can be given as a room vnum, a landmark (like the names used by
+//! `recall`), the name of a player in the realm, or the keyword of a mobile or
+//! object anywhere in the world.
+//!
+//! When immortals `goto` somewhere, others see their poofout and poofin
+//! messages, which are kept in their preferences.
+//!
+//! Entities are never deleted while the world is running, so purged mobiles
+//! and objects are moved to limbo, like dead ones. What they held goes to the
+//! `junk` landmark of the configuration, which is limbo unless it says
+//! otherwise.
+//!
+//! Players can only be forced and snooped by those with more trust than
+//! them, and both are logged in the immortal log.
+
+use crate::{
+    acting::EscapeVariables,
+    agent::EntityAgent,
+    commands::process_agent_command,
+    echo,
+    entity::{ContentsPolicy, EntityId, EntityInfo, Found},
+    mobprogs::Action,
+    wizlog::LogKind,
+};
+
+const DEFAULT_POOFIN: &str = "appears in a swirling mist.";
+const DEFAULT_POOFOUT: &str = "disappears in a swirling mist.";
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Find a player in the realm, ignoring case.
+    pub fn find_player_anywhere(&self, name: &str) -> Option<EntityId> {
+        self.entity_world
+            .all_players()
+            .find(|player| {
+                player
+                    .player_name()
+                    .map(|player_name| player_name.eq_ignore_ascii_case(name))
+                    .unwrap_or(false)
+            })
+            .map(|player| player.entity_id())
+    }
+
+    /// Find the room of a location; see the module documentation.
+    pub fn find_location(&mut self, location: &str) -> Option<EntityId> {
+        if let Ok(vnum) = location.parse::<usize>() {
+            return self.room_with_vnum(vnum);
+        }
+
+        if let Some(room_id) = self.entity_world.landmark(&location.to_lowercase()) {
+            return Some(room_id);
+        }
+
+        if let Some(player_id) = self.find_player_anywhere(location) {
+            return Some(self.entity_world.room_of(player_id));
+        }
+
+        self.entity_world
+            .all_entities()
+            .filter(|entity| entity.is_mobile() || entity.is_object())
+            .find(|entity| {
+                entity
+                    .component_info()
+                    .keyword()
+                    .split_whitespace()
+                    .any(|word| word.eq_ignore_ascii_case(location))
+            })
+            .map(|entity| entity.room().entity_id())
+    }
+
+    fn poof_message(&self, arriving: bool) -> String {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let preferences = myself
+            .player_name()
+            .map(|player| self.accounts.preferences(player));
+
+        let poof = if arriving {
+            preferences.and_then(|preferences| preferences.poofin.as_deref())
+        } else {
+            preferences.and_then(|preferences| preferences.poofout.as_deref())
+        };
+
+        match poof {
+            Some(poof) => EscapeVariables(poof).to_string(),
+            None if arriving => DEFAULT_POOFIN.to_string(),
+            None => DEFAULT_POOFOUT.to_string(),
+        }
+    }
+
+    pub fn do_goto(&mut self, location: &str) {
+        let room_id = match self.find_location(location) {
+            Some(room_id) => room_id,
+            None => {
+                echo!(self.info(), "No such location.\r\n");
+                return;
+            }
+        };
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if myself.room().entity_id() == room_id {
+            echo!(self.info(), "You are already there.\r\n");
+            return;
+        }
+
+        let poofout = self.poof_message(false);
+        let mut act = self.players.act_alone(&myself);
+        echo!(act.others(), "$^$n {}\r\n", poofout);
+
+        self.entity_world.move_entity(self.entity_id, room_id);
+
+        let poofin = self.poof_message(true);
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let mut act = self.players.act_alone(&myself);
+        echo!(act.others(), "$^$n {}\r\n", poofin);
+
+        self.do_look_around();
+    }
+
+    pub fn do_transfer(&mut self, player_name: &str, location: Option<&str>) {
+        let target_id = match self.find_player_anywhere(player_name) {
+            Some(target_id) => target_id,
+            None => {
+                echo!(
+                    self.info(),
+                    "There is no player named like that in the realm.\r\n"
+                );
+                return;
+            }
+        };
+
+        let room_id = match location {
+            Some(location) => match self.find_location(location) {
+                Some(room_id) => room_id,
+                None => {
+                    echo!(self.info(), "No such location.\r\n");
+                    return;
+                }
+            },
+            None => self.entity_world.room_of(self.entity_id),
+        };
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let target = self.entity_world.entity_info(target_id);
+
+        if target.room().entity_id() == room_id {
+            let mut act = self.players.act_with(&myself, &target);
+            echo!(act.myself(), "$^$E is already there.\r\n");
+            return;
+        }
+
+        let mut act = self.players.act_alone(&target);
+        echo!(act.others(), "$^$n disappears in a mushroom cloud.\r\n");
+
+        self.entity_world.move_entity(target_id, room_id);
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let target = self.entity_world.entity_info(target_id);
+        let mut act = self.players.act_alone(&target);
+        echo!(act.others(), "$^$n arrives from a puff of smoke.\r\n");
+
+        let mut act = self.players.act_with(&myself, &target);
+        echo!(act.myself(), "You transfer $N.\r\n");
+        echo!(act.target(), "$^$n has transferred you.\r\n");
+
+        let mut agent = self.switch_agent(target_id);
+        agent.do_look_around();
+        agent.check_triggers_others(Action::Greet);
+    }
+
+    pub fn do_at(&mut self, location: &str, words: &[&str]) {
+        let room_id = match self.find_location(location) {
+            Some(room_id) => room_id,
+            None => {
+                echo!(self.info(), "No such location.\r\n");
+                return;
+            }
+        };
+
+        // Commands run through 'at' aren't checked for trust otherwise
+        if let Some(command) = words.first() {
+            if !self.trusted_with(command) {
+                self.do_unknown(command);
+                return;
+            }
+        }
+
+        let original_room_id = self.entity_world.room_of(self.entity_id);
+
+        self.entity_world.move_entity(self.entity_id, room_id);
+        process_agent_command(self, words);
+        self.entity_world
+            .move_entity(self.entity_id, original_room_id);
+    }
+
+    pub fn do_poof(&mut self, arriving: bool, message: &str) {
+        let command = if arriving { "poofin" } else { "poofout" };
+
+        let preferences = match self.my_preferences() {
+            Some(preferences) => preferences,
+            None => {
+                echo!(self.info(), "Only players have poof messages.\r\n");
+                return;
+            }
+        };
+
+        let poof = if arriving {
+            &mut preferences.poofin
+        } else {
+            &mut preferences.poofout
+        };
+
+        match message {
+            "" => {
+                let current = poof.clone();
+                let mut info = self.info();
+                match current {
+                    Some(current) => echo!(info, "Your {} message is: {}\r\n", command, current),
+                    None => echo!(info, "You are using the default {} message.\r\n", command),
+                }
+                echo!(
+                    info,
+                    "Use '`W{} <message>`^' to change it; others see your name followed by it.\r\n\
+                    Use '`W{} default`^' to go back to the default one.\r\n",
+                    command,
+                    command
+                );
+            }
+            "default" => {
+                *poof = None;
+                echo!(
+                    self.info(),
+                    "Your {} message is back to the default one.\r\n",
+                    command
+                );
+            }
+            message => {
+                *poof = Some(message.to_string());
+                echo!(
+                    self.info(),
+                    "Your {} message is now: {}\r\n",
+                    command,
+                    message
+                );
+            }
+        }
+    }
+
+    pub fn do_load(&mut self, kind: &str, vnum: &str) {
+        let vnum: usize = match vnum.parse() {
+            Ok(vnum) => vnum,
+            Err(_) => {
+                echo!(self.info(), "Vnum '{}' is not a valid number.\r\n", vnum);
+                return;
+            }
+        };
+
+        let room_id = self.entity_world.room_of(self.entity_id);
+
+        let spawned_id = match kind {
+            "mob" | "mobile" => match self.entity_world.spawn_mobile(vnum, room_id) {
+                Some(mobile_id) => mobile_id,
+                None => {
+                    echo!(self.info(), "There is no mobile with that vnum.\r\n");
+                    return;
+                }
+            },
+            "obj" | "object" => match self.entity_world.spawn_object(vnum, room_id) {
+                Some(object_id) => object_id,
+                None => {
+                    echo!(self.info(), "There is no object with that vnum.\r\n");
+                    return;
+                }
+            },
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Wload mob <vnum>`^' or '`Wload obj <vnum>`^'.\r\n"
+                );
+                return;
+            }
+        };
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let spawned = self.entity_world.entity_info(spawned_id);
+        let mut act = self.players.act_with(&myself, &spawned);
+        echo!(act.all(), "$^$n $[create|creates] $N from thin air.\r\n");
+    }
+
+    pub fn do_purge(&mut self, target_name: Option<&str>) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let target_name = match target_name {
+            Some(target_name) => target_name,
+            None => {
+                // Note boards are part of the room, so they are left alone
+                let purged: Vec<EntityId> = myself
+                    .room()
+                    .contained_entities()
+                    .filter(|entity| {
+                        entity.is_mobile()
+                            || entity
+                                .components()
+                                .object
+                                .as_ref()
+                                .map(|object| object.board.is_none())
+                                .unwrap_or(false)
+                    })
+                    .map(|entity| entity.entity_id())
+                    .collect();
+
+                let mut act = self.players.act_alone(&myself);
+                echo!(act.all(), "$^$n $[purge|purges] the room!\r\n");
+
+                for entity_id in purged {
+                    self.entity_world
+                        .extract_entity(entity_id, ContentsPolicy::Junk);
+                }
+                return;
+            }
+        };
+
+        let target = myself.find_entity(target_name, |entity| {
+            entity.is_mobile() || entity.is_object() || entity.is_player()
+        });
+
+        let target = match target {
+            Found::Other(target) if target.is_player() => {
+                echo!(self.info(), "Players can't be purged.\r\n");
+                return;
+            }
+            Found::Other(target) => target,
+            Found::Myself | Found::WrongSelf => {
+                echo!(self.info(), "You can't purge yourself.\r\n");
+                return;
+            }
+            Found::WrongOther(_) => {
+                echo!(self.info(), "Only mobiles and objects can be purged.\r\n");
+                return;
+            }
+            Found::Nothing => {
+                echo!(
+                    self.info(),
+                    "You don't see anything named {} here.\r\n",
+                    target_name
+                );
+                return;
+            }
+        };
+
+        let mut act = self.players.act_with(&myself, &target);
+        echo!(act.all(), "$^$n $[purge|purges] $N.\r\n");
+
+        let target_id = target.entity_id();
+        self.entity_world
+            .extract_entity(target_id, ContentsPolicy::Junk);
+    }
+
+    /// Make others run a command on their next tick.
+    pub fn do_force(&mut self, target_name: &str, words: &[&str]) {
+        // Others aren't checked for trust when forced, so check it here
+        if let Some(command) = words.first() {
+            if !self.trusted_with(command) {
+                echo!(self.info(), "You can't make anyone do that.\r\n");
+                return;
+            }
+        }
+
+        let command = words.join(" ");
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let my_trust = myself
+            .player_name()
+            .map(|player| self.accounts.trust(player));
+        // Mobiles have no trust, so that nobody gets around it by forcing
+        // a mobile, or through a mobprog, to force a player
+        let outranks = |victim: &EntityInfo| match (my_trust, victim.player_name()) {
+            (Some(my_trust), Some(victim)) => self.accounts.trust(victim) < my_trust,
+            (None, Some(_victim)) => false,
+            (_, None) => true,
+        };
+
+        let victims: Vec<EntityId> = if target_name == "all" {
+            self.entity_world
+                .all_players()
+                .filter(|player| *player != myself && outranks(player))
+                .map(|player| player.entity_id())
+                .collect()
+        } else {
+            let target = myself.find_entity(target_name, |entity| {
+                entity.is_mobile() || entity.is_player()
+            });
+
+            let victim = match target {
+                Found::Other(victim) => victim,
+                Found::Myself | Found::WrongSelf => {
+                    echo!(self.info(), "Just do it yourself.\r\n");
+                    return;
+                }
+                Found::WrongOther(_) => {
+                    echo!(self.info(), "Only mobiles and players can be forced.\r\n");
+                    return;
+                }
+                Found::Nothing => match self.find_player_anywhere(target_name) {
+                    Some(victim_id) => self.entity_world.entity_info(victim_id),
+                    None => {
+                        echo!(
+                            self.info(),
+                            "There is no one named like that in the realm.\r\n"
+                        );
+                        return;
+                    }
+                },
+            };
+
+            if !outranks(&victim) {
+                let mut act = self.players.act_with(&myself, &victim);
+                echo!(act.myself(), "$^$N is too powerful for you to force.\r\n");
+                return;
+            }
+
+            vec![victim.entity_id()]
+        };
+
+        for &victim_id in &victims {
+            let myself = self.entity_world.entity_info(self.entity_id);
+            let victim = self.entity_world.entity_info(victim_id);
+            let mut act = self.players.act_with(&myself, &victim);
+            echo!(
+                act.target(),
+                "$^$n forces you to '{}'.\r\n",
+                EscapeVariables(&command)
+            );
+
+            let mut victim = self.entity_world.entity_info_mut(victim_id);
+            victim
+                .components()
+                .general
+                .command_queue
+                .push((0, command.clone()));
+        }
+
+        let forced = if target_name == "all" {
+            "everyone".to_string()
+        } else {
+            self.entity_world.entity_info(victims[0]).to_string()
+        };
+
+        echo!(self.info(), "You force {} to '{}'.\r\n", forced, command);
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if let Some(player) = myself.player_name() {
+            let message = format!("{} forces {} to '{}'.", player, forced, command);
+            self.log_to_immortals(LogKind::Admin, &message);
+        }
+    }
+
+    /// Start or stop seeing everything a player sees and types.
+    pub fn do_snoop(&mut self, target_name: Option<&str>) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can snoop.\r\n");
+                return;
+            }
+        };
+
+        let target_id = match target_name {
+            Some(target_name) if !["me", "self", "myself"].contains(&target_name) => {
+                match self.find_player_anywhere(target_name) {
+                    Some(target_id) => Some(target_id),
+                    None => {
+                        echo!(
+                            self.info(),
+                            "There is no player named like that in the realm.\r\n"
+                        );
+                        return;
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let target = match target_id {
+            Some(target_id) if target_id != self.entity_id => {
+                self.entity_world.entity_info(target_id)
+            }
+            _ => {
+                let mut stopped = Vec::new();
+                for (snooped, player_echo) in self.players.player_echoes.iter_mut() {
+                    if player_echo.snooped_by.as_deref() == Some(player.as_str()) {
+                        player_echo.snooped_by = None;
+                        stopped.push(snooped.clone());
+                    }
+                }
+
+                if stopped.is_empty() {
+                    echo!(self.info(), "You aren't snooping anyone.\r\n");
+                } else {
+                    echo!(self.info(), "You stop snooping.\r\n");
+                    let message = format!("{} stops snooping {}.", player, stopped.join(", "));
+                    self.log_to_immortals(LogKind::Admin, &message);
+                }
+                return;
+            }
+        };
+
+        let target_name = target.player_name().expect("Is a player").to_string();
+
+        if self.accounts.trust(&target_name) >= self.accounts.trust(&player) {
+            let mut act = self.players.act_with(&myself, &target);
+            echo!(act.myself(), "$^$N is too powerful for you to snoop.\r\n");
+            return;
+        }
+
+        let player_echo = match self.players.player_echoes.get_mut(&target_name) {
+            Some(player_echo) => player_echo,
+            None => {
+                echo!(self.info(), "There is no one to snoop there.\r\n");
+                return;
+            }
+        };
+
+        match &player_echo.snooped_by {
+            Some(snooper) if *snooper == player => {
+                echo!(self.info(), "You are already snooping them.\r\n");
+            }
+            Some(_) => {
+                echo!(self.info(), "Someone else is already snooping them.\r\n");
+            }
+            None => {
+                player_echo.snooped_by = Some(player.clone());
+
+                let mut act = self.players.act_with(&myself, &target);
+                echo!(act.myself(), "You start snooping $N.\r\n");
+
+                let message = format!("{} starts snooping {}.", player, target_name);
+                self.log_to_immortals(LogKind::Admin, &message);
+            }
+        }
+    }
+}
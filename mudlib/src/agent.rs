@@ -1,22 +1,55 @@
 use crate::{
+    accounts::Accounts,
     acting::{InfoTarget, Players},
-    components::{Components, EntityType, GeneralData, InternComponent, Silver},
+    auction::Auction,
+    clans::Clans,
+    clock::Clock,
+    components::{Wealth, COPPER_PER_SILVER},
+    config::Config,
     echo,
     entity::{EntityId, EntityWorld},
+    event_log::EventLog,
     find_entities::MatchError,
-    import::VnumTemplates,
+    mail::PostOffice,
     mobprogs::Action,
+    notes::NoteBoards,
+    olc::Olc,
+    perf::Perf,
+    reload::Reloads,
+    rng::GameRng,
+    snapshots::Snapshots,
     socials::Socials,
-    state::Area,
-    world::{opposite_direction, Gender, Vnum},
+    stalls::Stalls,
+    tells::MessageQueue,
+    tick::Scheduler,
+    trust::CommandTrust,
+    weather::Weather,
+    world::{opposite_direction, World},
     WorldState,
 };
 
 pub(crate) struct EntityAgent<'e, 'p> {
     pub entity_world: &'e mut EntityWorld,
     pub socials: &'e Socials,
-    pub vnum_templates: &'e VnumTemplates,
-    pub areas: &'e Vec<Area>,
+    pub world: &'e mut World,
+    pub olc: &'e mut Olc,
+    pub accounts: &'e mut Accounts,
+    pub note_boards: &'e mut NoteBoards,
+    pub post_office: &'e mut PostOffice,
+    pub message_queue: &'e mut MessageQueue,
+    pub clans: &'e mut Clans,
+    pub auction: &'e mut Auction,
+    pub stalls: &'e mut Stalls,
+    pub snapshots: &'e mut Snapshots,
+    pub reloads: &'e mut Reloads,
+    pub event_log: &'e mut EventLog,
+    pub command_trust: &'e CommandTrust,
+    pub config: &'e Config,
+    pub scheduler: &'e Scheduler,
+    pub perf: &'e Perf,
+    pub clock: &'e Clock,
+    pub weather: &'e Weather,
+    pub rng: &'e mut GameRng,
     pub players: &'p mut Players,
 
     pub entity_id: EntityId,
@@ -27,8 +60,25 @@ impl EntityAgent<'_, '_> {
         EntityAgent {
             entity_world: &mut world_state.entity_world,
             socials: &world_state.socials,
-            vnum_templates: &world_state.vnum_templates,
-            areas: &world_state.areas,
+            world: &mut world_state.world,
+            olc: &mut world_state.olc,
+            accounts: &mut world_state.accounts,
+            note_boards: &mut world_state.note_boards,
+            post_office: &mut world_state.post_office,
+            message_queue: &mut world_state.message_queue,
+            clans: &mut world_state.clans,
+            auction: &mut world_state.auction,
+            stalls: &mut world_state.stalls,
+            snapshots: &mut world_state.snapshots,
+            reloads: &mut world_state.reloads,
+            event_log: &mut world_state.event_log,
+            command_trust: &world_state.command_trust,
+            config: &world_state.config,
+            scheduler: &world_state.scheduler,
+            perf: &world_state.perf,
+            clock: &world_state.clock,
+            weather: &world_state.weather,
+            rng: &mut world_state.rng,
             players: &mut world_state.players,
 
             entity_id,
@@ -39,8 +89,25 @@ impl EntityAgent<'_, '_> {
         EntityAgent {
             entity_world: self.entity_world,
             socials: self.socials,
-            vnum_templates: self.vnum_templates,
-            areas: self.areas,
+            world: self.world,
+            olc: self.olc,
+            accounts: self.accounts,
+            note_boards: self.note_boards,
+            post_office: self.post_office,
+            message_queue: self.message_queue,
+            clans: self.clans,
+            auction: self.auction,
+            stalls: self.stalls,
+            snapshots: self.snapshots,
+            reloads: self.reloads,
+            event_log: self.event_log,
+            command_trust: self.command_trust,
+            config: self.config,
+            scheduler: self.scheduler,
+            perf: self.perf,
+            clock: self.clock,
+            weather: self.weather,
+            rng: self.rng,
             players: self.players,
 
             entity_id,
@@ -78,16 +145,17 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         let myself = self.entity_world.entity_info(self.entity_id);
         let room = self.entity_world.entity_info(from_room_id);
 
+        let leader = Some(myself.weak_ref());
         for follower in room.contained_entities() {
-            let main_keyword = myself.main_keyword();
-            if let Some(following) = &follower.components().general.following {
-                if following == main_keyword {
-                    followers.push(follower.entity_id());
-
-                    let mut act = self.players.act_with(&follower, &myself);
-                    echo!(act.myself(), "You follow $N to the {}.\r\n", direction);
-                    echo!(act.others(), "$^$n follows $N to the {}.\r\n", direction);
-                }
+            if follower.components().general.following == leader {
+                followers.push(follower.entity_id());
+
+                let mut act = self.players.act_with(&follower, &myself);
+                echo!(
+                    act.all(),
+                    "$^$n $[follow|follows] $N to the {}.\r\n",
+                    direction
+                );
             }
         }
 
@@ -108,132 +176,68 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
 
             self.entity_world.move_entity(follower_id, to_room_id);
             let mut agent = self.switch_agent(follower_id);
-            agent.do_look();
+            agent.do_look_around();
             agent.check_triggers_self(Action::Entry);
         }
     }
 }
 
-// Silver handling
+// Coin handling
 impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Give an entity coins worth some silver.
     pub fn add_silver(&mut self, amount: usize, to_entity: EntityId) {
-        let entity = self.entity_world.entity_info(to_entity);
-
-        // See if the silver coins can be joined with an existing pile in the inventory
-        let silver_pile = entity
-            .objects()
-            .find(|object| object.components().silver.is_some())
-            .map(|object| object.entity_id());
-
-        match silver_pile {
-            Some(silver_pile_id) => {
-                // Add to an existing pile
-                let mut silver_pile = self.entity_world.entity_info_mut(silver_pile_id);
-
-                let silver = silver_pile
-                    .components()
-                    .silver
-                    .as_mut()
-                    .expect("Filtered above to one that has the component");
-
-                silver.amount += amount;
-                let new_amount = silver.amount;
-
-                let (mut silver_pile, interner) = self
-                    .entity_world
-                    .entity_info_mut_with_interner(silver_pile_id);
-
-                let short_description = format!("{} silver coins", new_amount);
-                silver_pile.set_short_description(interner, &short_description);
-            }
-            None => {
-                // Otherwise create a new pile
-                let keyword = "silver coins";
-                let short_description = format!("{} silver coins", amount);
-
-                let title = "Swimming in silver coins.";
-                let internal = "You are inside a pile of silver coins.";
-                let external = "A pile of silver coins.";
-                let lateral = "A pile of silver coins is on the ground here.";
-
-                let act_info = self.entity_world.interner.act_info(
-                    keyword,
-                    &short_description,
-                    Gender::Neutral,
-                );
-                let descriptions = self
-                    .entity_world
-                    .interner
-                    .descriptions(title, internal, external, lateral);
-
-                self.entity_world.insert_entity(
-                    to_entity,
-                    Components {
-                        act_info,
-                        descriptions,
-                        general: GeneralData {
-                            vnum: Vnum(0),
-                            area: "silver".to_string(),
-                            sector: None,
-                            entity_type: EntityType::Object,
-                            equipped: None,
-                            command_queue: Vec::new(),
-                            following: None,
-                        },
-                        mobile: None,
-                        object: None,
-                        door: None,
-                        mobprog: None,
-                        silver: Some(Silver { amount }),
-                    },
-                );
-            }
-        };
+        self.entity_world
+            .add_coins(to_entity, &Wealth::from_silver(amount));
     }
 
+    /// Take coins worth some silver from an entity, with change in smaller
+    /// coins. Nothing is taken if it doesn't carry enough.
     pub fn remove_silver(&mut self, amount: usize, to_entity: EntityId) -> bool {
         let entity = self.entity_world.entity_info(to_entity);
 
-        // See if the silver coins can be joined with an existing pile in the inventory
-        let silver_pile = entity
+        let pile = entity
             .objects()
-            .find(|object| object.components().silver.is_some())
-            .map(|object| object.entity_id());
-
-        match silver_pile {
-            Some(silver_pile_id) => {
-                let new_amount = {
-                    let mut silver_pile = self.entity_world.entity_info_mut(silver_pile_id);
-                    let silver = silver_pile
-                        .components()
-                        .silver
-                        .as_mut()
-                        .expect("Filtered above to one that has the component");
-
-                    if silver.amount >= amount {
-                        silver.amount -= amount;
-                        Some(silver.amount)
-                    } else {
-                        None
-                    }
-                };
-
-                if let Some(new_amount) = new_amount {
-                    if new_amount != 0 {
-                        let (mut silver_pile, interner) = self
-                            .entity_world
-                            .entity_info_mut_with_interner(silver_pile_id);
-
-                        let short_description = format!("{} silver coins", new_amount);
-                        silver_pile.set_short_description(interner, &short_description);
-                    } else {
-                        let mut agent = self.switch_agent(silver_pile_id);
-                        agent.do_die();
-                    }
+            .find(|object| object.components().wealth.is_some())
+            .map(|object| {
+                let wealth = object.components().wealth.clone();
+                (object.entity_id(), wealth.expect("Filtered above"))
+            });
+
+        match pile {
+            Some((pile_id, mut wealth)) => {
+                if !wealth.pay(amount * COPPER_PER_SILVER) {
+                    return false;
                 }
-                new_amount.is_some()
+
+                if wealth.is_empty() {
+                    let mut agent = self.switch_agent(pile_id);
+                    agent.do_die();
+                } else {
+                    self.entity_world.set_coins(pile_id, wealth);
+                }
+                true
             }
             None => false,
         }
     }
+    /// Join a pile of coins that was just moved with another pile where it
+    /// landed, so that coins never lie around in several piles.
+    pub fn merge_coins(&mut self, pile_id: EntityId) {
+        let pile = self.entity_world.entity_info(pile_id);
+        let wealth = match &pile.components().wealth {
+            Some(wealth) => wealth.clone(),
+            None => return,
+        };
+
+        let container_id = pile.room().entity_id();
+        let other_pile = pile
+            .room()
+            .objects()
+            .any(|object| object != pile && object.components().wealth.is_some());
+
+        if other_pile {
+            self.switch_agent(pile_id).do_die();
+            self.entity_world.add_coins(container_id, &wealth);
+        }
+    }
 }
@@ -0,0 +1,129 @@
+//! Kinds of entities that game features make on their own, like corpses and
+//! piles of coins, on top of one of the `EntityType`s.
+//!
+//! Each archetype has the descriptions of what it makes, with `%n` where a
+//! name goes, e.g. "The corpse of %n is lying here.". They are registered
+//! when the entity world is made, and `EntityWorld::spawn_archetype()` makes
+//! entities of them, which remember their archetype in `GeneralData`. A new
+//! kind of entity is one more archetype here, instead of a new `EntityType`
+//! that every match on it has to handle.
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    components::{Components, EntityType, GeneralData, InternComponent, MyStringInterner},
+    world::{Gender, Vnum},
+};
+
+/// Corpses left by the dead; see `crate::decay`
+pub(crate) const CORPSE: &str = "corpse";
+/// Piles of coins; see `crate::components::Wealth`
+pub(crate) const COINS: &str = "coins";
+
+#[derive(Clone)]
+pub(crate) struct Archetype {
+    pub name: &'static str,
+    /// What the entities are to the rest of the game
+    pub entity_type: EntityType,
+    pub keyword: &'static str,
+    pub short_description: &'static str,
+    pub title: &'static str,
+    pub internal: &'static str,
+    pub external: &'static str,
+    pub lateral: &'static str,
+}
+
+impl Archetype {
+    /// Components of an entity of this archetype in an area, with its
+    /// descriptions filled in with a name, and nothing else but its general
+    /// data.
+    pub fn components(
+        &self,
+        interner: &mut MyStringInterner,
+        name: &str,
+        area: Arc<str>,
+    ) -> Components {
+        let fill = |template: &str| template.replace("%n", name);
+
+        let act_info = interner.act_info(
+            &fill(self.keyword),
+            &fill(self.short_description),
+            Gender::Neutral,
+        );
+        let descriptions = interner.descriptions(
+            &fill(self.title),
+            &fill(self.internal),
+            &fill(self.external),
+            &fill(self.lateral),
+        );
+
+        Components {
+            act_info,
+            descriptions,
+            general: GeneralData {
+                vnum: Vnum(0),
+                area,
+                sector: None,
+                entity_type: self.entity_type,
+                archetype: Some(self.name.into()),
+                equipped: None,
+                command_queue: Vec::new(),
+                following: None,
+                only_at: None,
+                extras: None,
+            },
+            mobile: None,
+            object: None,
+            door: None,
+            mobprog: None,
+            wealth: None,
+            vitals: None,
+            decay: None,
+            attributes: None,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct ArchetypeRegistry {
+    archetypes: HashMap<&'static str, Archetype>,
+}
+
+impl ArchetypeRegistry {
+    /// A registry with the archetypes of all of the game's features.
+    pub fn new() -> Self {
+        let mut archetypes = ArchetypeRegistry::default();
+
+        archetypes.register(Archetype {
+            name: CORPSE,
+            entity_type: EntityType::Object,
+            keyword: "corpse",
+            short_description: "the corpse of %n",
+            title: "Inside the corpse of %n.",
+            internal: "You are inside a corpse. It is not a nice place to be.",
+            external: "This is the dead body of %n.",
+            lateral: "The corpse of %n is lying here.",
+        });
+        archetypes.register(Archetype {
+            name: COINS,
+            entity_type: EntityType::Object,
+            keyword: "coins",
+            short_description: "%n",
+            title: "Swimming in coins.",
+            internal: "You are inside a pile of coins.",
+            external: "A pile of coins.",
+            lateral: "A pile of coins is on the ground here.",
+        });
+
+        archetypes
+    }
+
+    /// Add an archetype, or replace the one with the same name.
+    pub fn register(&mut self, archetype: Archetype) {
+        self.archetypes.insert(archetype.name, archetype);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Archetype> {
+        self.archetypes.get(name)
+    }
+}
@@ -0,0 +1,135 @@
+//! Reading areas from a `.tar.zst`, `.tar.gz` or `.zip` archive, as if it were
+//! a directory, so that a world can be handed out as a single file.
+//!
+//! `areas` under `[world]`, or the directory of another source, may be an
+//! archive like `data/world.tar.zst`, holding arealist.txt and the area files.
+//! Their paths are then like `data/world.tar.zst/midgaard.are`, and
+//! `ArchiveFiles` reads them from the archive, which it unpacks in memory the
+//! first time one of its files is needed.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    io::{self, Cursor, Read},
+};
+
+use crate::files::Files;
+
+/// Endings of the archives that can be read
+const ARCHIVE_EXTENSIONS: &[&str] = &[".tar.zst", ".tzst", ".tar.gz", ".tgz", ".zip"];
+
+/// Files of each archive, by their path inside it
+type Unpacked = BTreeMap<String, Vec<u8>>;
+
+/// Files that reads paths inside archives from the archives, and others
+/// from the files it wraps. Only reading is supported.
+pub(crate) struct ArchiveFiles<'f> {
+    files: &'f dyn Files,
+    /// Archives unpacked so far, by their path
+    archives: RefCell<BTreeMap<String, Unpacked>>,
+}
+
+impl<'f> ArchiveFiles<'f> {
+    pub fn new(files: &'f dyn Files) -> Self {
+        ArchiveFiles {
+            files,
+            archives: RefCell::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Files for ArchiveFiles<'_> {
+    fn read_file_raw(&self, path: &str) -> Result<Vec<u8>, io::Error> {
+        let (archive_path, name) = match split_archive_path(path) {
+            Some(split) => split,
+            None => return self.files.read_file_raw(path),
+        };
+
+        let mut archives = self.archives.borrow_mut();
+        if !archives.contains_key(archive_path) {
+            let bytes = self.files.read_file_raw(archive_path)?;
+            let unpacked = unpack(archive_path, &bytes).map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("Cannot unpack {}: {}", archive_path, err),
+                )
+            })?;
+            archives.insert(archive_path.to_string(), unpacked);
+        }
+
+        archives[archive_path].get(name).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("There is no {} in {}", name, archive_path),
+            )
+        })
+    }
+}
+
+/// Whether a path is that of an archive that can be read like a directory.
+fn is_archive(path: &str) -> bool {
+    ARCHIVE_EXTENSIONS
+        .iter()
+        .any(|extension| path.to_ascii_lowercase().ends_with(extension))
+}
+
+/// Split a path inside an archive into the path of the archive and the path
+/// of the file inside it.
+fn split_archive_path(path: &str) -> Option<(&str, &str)> {
+    path.match_indices('/')
+        .map(|(index, _)| (&path[..index], &path[index + 1..]))
+        .find(|(archive_path, _name)| is_archive(archive_path))
+}
+
+/// Read all the files of an archive, telling its kind by its extension.
+fn unpack(path: &str, bytes: &[u8]) -> io::Result<Unpacked> {
+    let path = path.to_ascii_lowercase();
+    if path.ends_with(".zip") {
+        unpack_zip(bytes)
+    } else if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+        unpack_tar(flate2::read::GzDecoder::new(bytes))
+    } else {
+        let decoder = ruzstd::decoding::StreamingDecoder::new(bytes).map_err(io::Error::other)?;
+        unpack_tar(decoder)
+    }
+}
+
+fn unpack_tar(reader: impl Read) -> io::Result<Unpacked> {
+    let mut unpacked = Unpacked::new();
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let name = entry_name(&entry.path()?.to_string_lossy());
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        unpacked.insert(name, contents);
+    }
+    Ok(unpacked)
+}
+
+fn unpack_zip(bytes: &[u8]) -> io::Result<Unpacked> {
+    let mut unpacked = Unpacked::new();
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(io::Error::other)?;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).map_err(io::Error::other)?;
+        if !file.is_file() {
+            continue;
+        }
+
+        let name = entry_name(&file.name().map_err(io::Error::other)?);
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        unpacked.insert(name, contents);
+    }
+    Ok(unpacked)
+}
+
+/// Path of a file inside an archive, as areas refer to it: `tar -C area .`
+/// puts `./` before every file.
+fn entry_name(name: &str) -> String {
+    name.replace('\\', "/").trim_start_matches("./").to_string()
+}
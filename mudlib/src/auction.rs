@@ -0,0 +1,456 @@
+//! A realm-wide auction, one lot at a time.
+//!
+//! `auction <item> <min bid>` puts an object up for auction, and everyone on
+//! the auction channel hears about it and about every `bid`. The auction
+//! ends two minutes later; a bid in the last half minute makes it last half a
+//! minute longer, so nobody can win by bidding at the last second.
+//!
+//! Both the object and the highest bid are held in escrow: the object is
+//! taken from the seller when the auction starts, and the silver from each
+//! bidder when they bid, and given back to them when someone outbids them. At
+//! the end, the winner gets the object, and the winning bid goes to the
+//! seller's bank account. Objects are held whole, like in the mail, and sent
+//! by mail to players who aren't around to receive them.
+//!
+//! The lot is saved to `data/auction.json` whenever it changes, so auctions
+//! carry on after a restart.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accounts::Accounts,
+    acting::Players,
+    agent::EntityAgent,
+    echo,
+    escrow::Escrowed,
+    files::Files,
+    find_entities::EntityIterator,
+    mail::{Attachment, Letter},
+    snapshots::tell,
+    state::unix_time,
+    WorldState,
+};
+
+const AUCTION_FILE: &str = "data/auction.json";
+
+/// Seconds an auction lasts
+const AUCTION_TIME: u64 = 120;
+/// Seconds an auction lasts at least after a bid
+const SNIPE_TIME: u64 = 30;
+/// Seconds left at which bidders are warned that the auction is ending
+const WARNINGS: &[(u64, &str)] = &[(20, "going once"), (10, "going twice")];
+
+pub(crate) struct Auction {
+    lot: Option<Lot>,
+    changed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Lot {
+    seller: String,
+    /// The object being auctioned, held in escrow; auctions saved before
+    /// objects were kept whole have its vnum
+    #[serde(alias = "vnum")]
+    object: Attachment,
+    /// What the object is called, e.g. "a short sword"
+    item: String,
+    min_bid: usize,
+    /// The highest bid so far, held in escrow
+    bid: usize,
+    bidder: Option<String>,
+    /// When the auction ends, in seconds since the Unix epoch
+    ends: u64,
+    /// How many warnings were given
+    warnings: usize,
+}
+
+impl Lot {
+    /// The lowest bid that would be accepted now.
+    fn next_bid(&self) -> usize {
+        match self.bidder {
+            Some(_) => self.bid + (self.bid / 10).max(1),
+            None => self.min_bid,
+        }
+    }
+}
+
+impl Auction {
+    pub fn load(files: &dyn Files) -> Self {
+        let lot = match files.read_file_raw(AUCTION_FILE) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .unwrap_or_else(|err| panic!("Could not parse {}: {}", AUCTION_FILE, err)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => panic!("Could not read {}: {}", AUCTION_FILE, err),
+        };
+
+        Auction {
+            lot,
+            changed: false,
+        }
+    }
+
+    pub fn save_changes(&mut self, files: &dyn Files) -> Result<(), std::io::Error> {
+        if self.changed {
+            let contents = serde_json::to_vec_pretty(&self.lot).expect("Auction should serialize");
+            files.write_file_raw(AUCTION_FILE, &contents)?;
+            self.changed = false;
+        }
+
+        Ok(())
+    }
+}
+
+/// Tell everyone listening to the auction channel about the auction.
+fn announce(players: &mut Players, accounts: &Accounts, message: &str) {
+    for (player, player_echo) in players.player_echoes.iter_mut() {
+        if player_echo.link_dead
+            || accounts
+                .preferences(player)
+                .channels_off
+                .contains("auction")
+        {
+            continue;
+        }
+
+        player_echo
+            .echo_buffer
+            .push_str(&format!("`GAuction: {}`^\r\n", message));
+    }
+}
+
+/// Give an object to a player, or send it by mail if they aren't around.
+fn deliver_object(world_state: &mut WorldState, player: &str, object: Attachment, subject: &str) {
+    match world_state.entity_world.player_entity_id(player) {
+        Some(player_id) => object.release(&mut world_state.entity_world, player_id),
+        None => {
+            world_state.post_office.deliver(Letter {
+                sender: "the auctioneer".to_string(),
+                to: player.to_string(),
+                subject: subject.to_string(),
+                text: "Here is what you are owed from the auction.\r\n".to_string(),
+                date: unix_time(),
+                objects: vec![object],
+                ..Default::default()
+            });
+            tell(world_state, player, "`WIt was sent to you by mail.`^\r\n");
+        }
+    }
+}
+
+/// Warn bidders of an auction that is about to end, and end it when its
+/// time is up.
+pub(crate) fn update_auction(world_state: &mut WorldState) {
+    let now = unix_time();
+    let lot = match &mut world_state.auction.lot {
+        Some(lot) => lot,
+        None => return,
+    };
+
+    let left = lot.ends.saturating_sub(now);
+
+    if left > 0 {
+        if let Some((seconds, warning)) = WARNINGS.get(lot.warnings) {
+            if left <= *seconds {
+                lot.warnings += 1;
+                let message = match lot.bidder {
+                    Some(_) => format!("{}: {}, for {} silver!", lot.item, warning, lot.bid),
+                    None => format!("{}: {}, with no bids yet!", lot.item, warning),
+                };
+                announce(&mut world_state.players, &world_state.accounts, &message);
+            }
+        }
+        return;
+    }
+
+    let lot = world_state.auction.lot.take().expect("Checked above");
+    world_state.auction.changed = true;
+
+    match &lot.bidder {
+        Some(bidder) => {
+            let message = format!("{} is sold to {} for {} silver!", lot.item, bidder, lot.bid);
+            announce(&mut world_state.players, &world_state.accounts, &message);
+
+            let balance = world_state.accounts.bank_balance(&lot.seller);
+            world_state
+                .accounts
+                .set_bank_balance(&lot.seller, balance + lot.bid);
+            tell(
+                world_state,
+                &lot.seller,
+                &format!(
+                    "`WThe {} silver for {} was paid into your bank account.`^\r\n",
+                    lot.bid, lot.item
+                ),
+            );
+
+            tell(
+                world_state,
+                bidder,
+                &format!("`WYou receive {}.`^\r\n", lot.item),
+            );
+            let bidder = bidder.clone();
+            deliver_object(world_state, &bidder, lot.object, "Your auction win");
+        }
+        None => {
+            let message = format!("{} got no bids, and goes back to {}.", lot.item, lot.seller);
+            announce(&mut world_state.players, &world_state.accounts, &message);
+
+            tell(
+                world_state,
+                &lot.seller,
+                &format!("`WYou get {} back.`^\r\n", lot.item),
+            );
+            deliver_object(world_state, &lot.seller, lot.object, "Your unsold auction");
+        }
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_auction(&mut self, words: &[&str]) {
+        match words {
+            [] => self.do_auction_show(),
+            ["quiet"] => self.do_channel("auction", ""),
+            ["cancel"] => self.do_auction_cancel(),
+            [item, min_bid] => self.do_auction_start(item, min_bid),
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Wauction`^', '`Wauction <item> <min bid>`^', '`Wauction cancel`^', \
+                    '`Wauction quiet`^', '`Wbid <amount>`^'.\r\n"
+                );
+            }
+        }
+    }
+
+    /// The name of the player taking part in an auction, or tell them they
+    /// can't.
+    fn auction_player(&mut self) -> Option<String> {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        match myself.player_name() {
+            Some(player) if self.accounts.account(player).is_some() => Some(player.to_string()),
+            _ => {
+                echo!(
+                    self.info(),
+                    "Only players with an account can take part in auctions.\r\n"
+                );
+                None
+            }
+        }
+    }
+
+    fn do_auction_show(&mut self) {
+        let lot = match &self.auction.lot {
+            Some(lot) => lot.clone(),
+            None => {
+                echo!(self.info(), "Nothing is being auctioned right now.\r\n");
+                return;
+            }
+        };
+
+        let left = lot.ends.saturating_sub(unix_time());
+        let mut info = self.info();
+        echo!(
+            info,
+            "{} is auctioning {}, with {} seconds to go.\r\n",
+            lot.seller,
+            lot.item,
+            left
+        );
+        match &lot.bidder {
+            Some(bidder) => echo!(
+                info,
+                "The highest bid is {} silver, by {}.\r\n",
+                lot.bid,
+                bidder
+            ),
+            None => echo!(info, "There are no bids yet.\r\n"),
+        }
+        echo!(
+            info,
+            "The next bid must be at least `W{}`^ silver.\r\n",
+            lot.next_bid()
+        );
+    }
+
+    fn do_auction_start(&mut self, object_name: &str, min_bid: &str) {
+        let min_bid = match min_bid.parse::<usize>() {
+            Ok(min_bid) if min_bid > 0 => min_bid,
+            _ => {
+                echo!(self.info(), "That's not a valid amount of silver.\r\n");
+                return;
+            }
+        };
+
+        let player = match self.auction_player() {
+            Some(player) => player,
+            None => return,
+        };
+
+        if self.auction.lot.is_some() {
+            echo!(
+                self.info(),
+                "Something is already being auctioned; wait for it to end.\r\n"
+            );
+            return;
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let found = myself
+            .objects()
+            .filter_by_keyword(object_name)
+            .filter_or(
+                |e| e.components().wealth.is_none(),
+                "Silver can't be auctioned.",
+            )
+            .filter_or(
+                |e| e.components().general.equipped.is_none(),
+                "You need to remove $N first.",
+            )
+            .filter_or(
+                |e| e.objects().next().is_none(),
+                "You need to empty $N first.",
+            )
+            .find_one_or("You aren't holding anything named like that.");
+
+        let object = match found {
+            Ok(object) => object,
+            Err(err) => return self.echo_error(err),
+        };
+
+        let item = object.component_info().short_description().to_string();
+        let mut act = self.players.act_with(&myself, &object);
+        echo!(act.myself(), "You hand $N over to the auctioneers.\r\n");
+        echo!(act.others(), "$^$n wraps up $N.\r\n");
+
+        let object_id = object.entity_id();
+        let lot = Lot {
+            seller: player.clone(),
+            object: Attachment::Escrowed(Escrowed::take(self.entity_world, object_id)),
+            item,
+            min_bid,
+            bid: 0,
+            bidder: None,
+            ends: unix_time() + AUCTION_TIME,
+            warnings: 0,
+        };
+
+        let message = format!(
+            "{} puts {} up for auction, starting at {} silver.",
+            player, lot.item, min_bid
+        );
+        self.auction.lot = Some(lot);
+        self.auction.changed = true;
+        announce(self.players, self.accounts, &message);
+    }
+
+    fn do_auction_cancel(&mut self) {
+        let player = match self.auction_player() {
+            Some(player) => player,
+            None => return,
+        };
+
+        let lot = match &self.auction.lot {
+            Some(lot) if lot.seller == player => lot,
+            _ => {
+                echo!(self.info(), "You aren't auctioning anything.\r\n");
+                return;
+            }
+        };
+
+        if lot.bidder.is_some() {
+            echo!(
+                self.info(),
+                "Someone already bid on it; the auction has to go on.\r\n"
+            );
+            return;
+        }
+
+        let lot = self.auction.lot.take().expect("Checked above");
+        self.auction.changed = true;
+
+        let message = format!("{} calls off the auction of {}.", player, lot.item);
+        announce(self.players, self.accounts, &message);
+
+        lot.object.release(self.entity_world, self.entity_id);
+        echo!(self.info(), "You get {} back.\r\n", lot.item);
+    }
+
+    pub fn do_bid(&mut self, amount: &str) {
+        let amount = match amount.parse::<usize>() {
+            Ok(amount) if amount > 0 => amount,
+            _ => {
+                echo!(self.info(), "That's not a valid amount of silver.\r\n");
+                return;
+            }
+        };
+
+        let player = match self.auction_player() {
+            Some(player) => player,
+            None => return,
+        };
+
+        let lot = match &self.auction.lot {
+            Some(lot) => lot.clone(),
+            None => {
+                echo!(self.info(), "Nothing is being auctioned right now.\r\n");
+                return;
+            }
+        };
+
+        if lot.seller == player {
+            echo!(self.info(), "You can't bid on your own auction.\r\n");
+            return;
+        }
+
+        if lot.bidder.as_ref() == Some(&player) {
+            echo!(self.info(), "You already have the highest bid.\r\n");
+            return;
+        }
+
+        if amount < lot.next_bid() {
+            echo!(
+                self.info(),
+                "You need to bid at least {} silver.\r\n",
+                lot.next_bid()
+            );
+            return;
+        }
+
+        if !self.remove_silver(amount, self.entity_id) {
+            echo!(self.info(), "You don't have that much silver.\r\n");
+            return;
+        }
+
+        // Give the silver of the last bid back
+        if let Some(outbid) = &lot.bidder {
+            match self.entity_world.player_entity_id(outbid) {
+                Some(outbid_id) => {
+                    self.add_silver(lot.bid, outbid_id);
+                    let outbid = self.entity_world.entity_info(outbid_id);
+                    echo!(
+                        self.players.info(&outbid),
+                        "`WYou were outbid, and get your {} silver back.`^\r\n",
+                        lot.bid
+                    );
+                }
+                None => {
+                    let balance = self.accounts.bank_balance(outbid);
+                    self.accounts.set_bank_balance(outbid, balance + lot.bid);
+                }
+            }
+        }
+
+        let now = unix_time();
+        let lot = self.auction.lot.as_mut().expect("Checked above");
+        lot.bid = amount;
+        lot.bidder = Some(player.clone());
+        lot.ends = lot.ends.max(now + SNIPE_TIME);
+        lot.warnings = 0;
+        self.auction.changed = true;
+
+        let message = format!("{} bids {} silver on {}.", player, amount, lot.item);
+        announce(self.players, self.accounts, &message);
+    }
+}
@@ -0,0 +1,240 @@
+//! Banks, so that players don't need to carry all their silver around.
+//!
+//! A bank is any room with a banker (a mobile with the `banker` act flag).
+//! There, players can `deposit` and `withdraw` silver, and send some of it
+//! to another player's account with `bank transfer`; `balance` works
+//! anywhere. Balances are kept with player accounts, and saved with them.
+
+use crate::{agent::EntityAgent, echo, entity::EntityId};
+
+/// An amount of silver, or all of it.
+enum Amount {
+    All,
+    Some(usize),
+}
+
+fn parse_amount(amount: &str) -> Option<Amount> {
+    match amount {
+        "all" => Some(Amount::All),
+        amount => match amount.parse::<usize>() {
+            Ok(amount) if amount > 0 => Some(Amount::Some(amount)),
+            _ => None,
+        },
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Find a banker in the room, or tell the player to go find one.
+    fn banker(&mut self) -> Option<EntityId> {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let banker = myself.room().mobiles().find(|mobile| {
+            mobile
+                .components()
+                .mobile
+                .as_ref()
+                .map(|mobile| mobile.banker)
+                .unwrap_or(false)
+        });
+
+        match banker {
+            Some(banker) => Some(banker.entity_id()),
+            None => {
+                echo!(
+                    self.info(),
+                    "You need to be at a bank, near a banker, to do that.\r\n"
+                );
+                None
+            }
+        }
+    }
+
+    /// The name of the player with a bank account, or tell them they can't
+    /// have one.
+    fn account_holder(&mut self) -> Option<String> {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        match myself.player_name() {
+            Some(player) if self.accounts.account(player).is_some() => Some(player.to_string()),
+            _ => {
+                echo!(
+                    self.info(),
+                    "Only players with an account can use a bank.\r\n"
+                );
+                None
+            }
+        }
+    }
+
+    fn carried_silver(&self) -> usize {
+        self.entity_world
+            .entity_info(self.entity_id)
+            .objects()
+            .filter_map(|object| object.components().wealth.as_ref())
+            .map(|wealth| wealth.silver_value())
+            .sum()
+    }
+
+    pub fn do_balance(&mut self) {
+        let player = match self.account_holder() {
+            Some(player) => player,
+            None => return,
+        };
+
+        let balance = self.accounts.bank_balance(&player);
+        let carried = self.carried_silver();
+        echo!(
+            self.info(),
+            "You have `W{}`^ silver coins in the bank, and {} with you.\r\n",
+            balance,
+            carried
+        );
+    }
+
+    pub fn do_deposit(&mut self, amount: &str) {
+        let amount = match parse_amount(amount) {
+            Some(Amount::All) => self.carried_silver(),
+            Some(Amount::Some(amount)) => amount,
+            None => {
+                echo!(self.info(), "That's not a valid amount of silver.\r\n");
+                return;
+            }
+        };
+
+        let player = match self.account_holder() {
+            Some(player) => player,
+            None => return,
+        };
+        let banker_id = match self.banker() {
+            Some(banker_id) => banker_id,
+            None => return,
+        };
+
+        if amount == 0 || !self.remove_silver(amount, self.entity_id) {
+            echo!(self.info(), "You don't have that much silver.\r\n");
+            return;
+        }
+
+        let balance = self.accounts.bank_balance(&player) + amount;
+        self.accounts.set_bank_balance(&player, balance);
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let banker = self.entity_world.entity_info(banker_id);
+        let mut act = self.players.act_with(&myself, &banker);
+        echo!(
+            act.myself(),
+            "You hand $N {} silver coins; you now have {} in the bank.\r\n",
+            amount,
+            balance
+        );
+        echo!(act.others(), "$^$n hands $N a pouch of silver.\r\n");
+    }
+
+    pub fn do_withdraw(&mut self, amount: &str) {
+        let player = match self.account_holder() {
+            Some(player) => player,
+            None => return,
+        };
+        let balance = self.accounts.bank_balance(&player);
+
+        let amount = match parse_amount(amount) {
+            Some(Amount::All) => balance,
+            Some(Amount::Some(amount)) => amount,
+            None => {
+                echo!(self.info(), "That's not a valid amount of silver.\r\n");
+                return;
+            }
+        };
+
+        let banker_id = match self.banker() {
+            Some(banker_id) => banker_id,
+            None => return,
+        };
+
+        if amount == 0 || amount > balance {
+            echo!(
+                self.info(),
+                "You only have {} silver coins in the bank.\r\n",
+                balance
+            );
+            return;
+        }
+
+        self.accounts.set_bank_balance(&player, balance - amount);
+        self.add_silver(amount, self.entity_id);
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let banker = self.entity_world.entity_info(banker_id);
+        let mut act = self.players.act_with(&myself, &banker);
+        echo!(
+            act.myself(),
+            "$^$N counts out {} silver coins for you; you have {} left in the bank.\r\n",
+            amount,
+            balance - amount
+        );
+        echo!(act.others(), "$^$N hands $n a pouch of silver.\r\n");
+    }
+
+    pub fn do_bank_transfer(&mut self, target: &str, amount: &str) {
+        let amount = match parse_amount(amount) {
+            Some(Amount::Some(amount)) => amount,
+            _ => {
+                echo!(self.info(), "That's not a valid amount of silver.\r\n");
+                return;
+            }
+        };
+
+        let player = match self.account_holder() {
+            Some(player) => player,
+            None => return,
+        };
+
+        // Use the account's spelling of the name
+        let target = match self.accounts.account(target) {
+            Some(account) if account.name.eq_ignore_ascii_case(&player) => {
+                echo!(self.info(), "That silver is already yours.\r\n");
+                return;
+            }
+            Some(account) => account.name.clone(),
+            None => {
+                echo!(self.info(), "There is no one named like that.\r\n");
+                return;
+            }
+        };
+
+        if self.banker().is_none() {
+            return;
+        }
+
+        let balance = self.accounts.bank_balance(&player);
+        if amount > balance {
+            echo!(
+                self.info(),
+                "You only have {} silver coins in the bank.\r\n",
+                balance
+            );
+            return;
+        }
+
+        self.accounts.set_bank_balance(&player, balance - amount);
+        let target_balance = self.accounts.bank_balance(&target) + amount;
+        self.accounts.set_bank_balance(&target, target_balance);
+
+        echo!(
+            self.info(),
+            "You send {} silver coins to {}'s bank account.\r\n",
+            amount,
+            target
+        );
+
+        if let Some(target_id) = self.entity_world.player_entity_id(&target) {
+            let target = self.entity_world.entity_info(target_id);
+            echo!(
+                self.players.info(&target),
+                "`W{} sends {} silver coins to your bank account.`^\r\n",
+                player,
+                amount
+            );
+        }
+    }
+}
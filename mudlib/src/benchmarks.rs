@@ -0,0 +1,36 @@
+//! Entry points into the parts of the mudlib that the benchmarks in
+//! `benches/` measure on their own. Not meant for anything else.
+
+use crate::{
+    config::Config,
+    entity::EntityWorld,
+    import::import_from_world,
+    rng::GameRng,
+    world::{load_world, TimeOfDay, World},
+    Files,
+};
+
+/// Areas read from their files, but not yet turned into entities.
+pub struct ParsedAreas(World);
+
+/// Read and parse every area in `data/area`, always the same way.
+pub fn parse_areas(files: &dyn Files) -> ParsedAreas {
+    ParsedAreas(load_world(
+        files,
+        &Config::default().world,
+        &mut GameRng::new(Some(0)),
+    ))
+}
+
+/// Turn parsed areas into entities, as when the game starts, and run their
+/// resets. Returns how many entities there are then.
+pub fn import_areas(areas: &ParsedAreas) -> usize {
+    let mut entity_world = EntityWorld::new();
+    import_from_world(
+        &mut entity_world,
+        &areas.0,
+        TimeOfDay::Day,
+        &Config::default(),
+    );
+    entity_world.entity_count()
+}
@@ -0,0 +1,72 @@
+//! Commands for immortals to send messages that don't say who they are from:
+//! `echo` to the room, `zecho` to the area, and `gecho` to the whole realm.
+//!
+//! Like everything else sent to players, messages can use color codes. Who
+//! sent them is put in the immortal log.
+
+use crate::{agent::EntityAgent, echo, entity::EntityId, wizlog::LogKind};
+
+#[derive(Clone, Copy)]
+pub(crate) enum Reach {
+    Room,
+    Area,
+    Realm,
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_broadcast(&mut self, reach: Reach, message: &str) {
+        let (command, verb) = match reach {
+            Reach::Room => ("echo", "echoes"),
+            Reach::Area => ("zecho", "zechoes"),
+            Reach::Realm => ("gecho", "gechoes"),
+        };
+
+        let message = message.trim();
+        if message.is_empty() {
+            echo!(self.info(), "Syntax: '`W{} <message>`^'.\r\n", command);
+            return;
+        }
+
+        let room = self.entity_world.entity_info(self.entity_id).room();
+        let room_id = room.entity_id();
+        let room_vnum = room.components().general.vnum.0;
+        let area = room.components().general.area.clone();
+
+        if matches!(reach, Reach::Area) && area.is_empty() {
+            echo!(self.info(), "This room isn't in any area.\r\n");
+            return;
+        }
+
+        let entity_world = &*self.entity_world;
+        let listeners: Vec<EntityId> = match reach {
+            Reach::Room => entity_world
+                .all_players()
+                .filter(|player| player.room().entity_id() == room_id)
+                .map(|player| player.entity_id())
+                .collect(),
+            Reach::Area => entity_world
+                .entities_in_area(&area)
+                .filter(|entity| entity.is_player())
+                .map(|player| player.entity_id())
+                .collect(),
+            Reach::Realm => entity_world
+                .all_players()
+                .map(|player| player.entity_id())
+                .collect(),
+        };
+
+        for listener_id in listeners {
+            let listener = self.entity_world.entity_info(listener_id);
+            echo!(self.players.info(&listener), "{}`^\r\n", message);
+        }
+
+        let place = match reach {
+            Reach::Room => format!("room {}", room_vnum),
+            Reach::Area => format!("area {}", area),
+            Reach::Realm => "the realm".to_string(),
+        };
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let message = format!("{} {} to {}: {}`^", myself, verb, place, message);
+        self.log_to_immortals(LogKind::Admin, &message);
+    }
+}
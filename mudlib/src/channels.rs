@@ -0,0 +1,205 @@
+//! Chat channels that reach players anywhere in the realm.
+//!
+//! Players can turn each channel off or on by using its command without a
+//! message; which channels are off is kept in their preferences.
+//!
+//! The clan channel is only heard by members of the speaker's clan. Channels
+//! are only heard by those trusted with their command, which keeps the
+//! immortal channel among immortals. Nobody talks on the auction channel;
+//! it only carries news of the auction.
+
+use crate::{agent::EntityAgent, echo, entity::EntityInfo};
+
+struct Channel {
+    name: &'static str,
+    /// Command used to talk on the channel
+    command: &'static str,
+    description: &'static str,
+    color: &'static str,
+    /// What the speaker sees, e.g. "You gossip"
+    you_say: &'static str,
+    /// What others see after the speaker's name, e.g. "gossips"
+    they_say: &'static str,
+}
+
+static CHANNELS: &[Channel] = &[
+    Channel {
+        name: "gossip",
+        command: "gossip",
+        description: "Chat with everyone in the realm",
+        color: "C",
+        you_say: "You gossip",
+        they_say: "gossips",
+    },
+    Channel {
+        name: "clan",
+        command: "clantalk",
+        description: "Talk with your clan, using '`Wclantalk`^'",
+        color: "M",
+        you_say: "You tell your clan",
+        they_say: "tells the clan",
+    },
+    Channel {
+        name: "immortal",
+        command: "immtalk",
+        description: "Talk with other immortals, using '`Wimmtalk`^'",
+        color: "Y",
+        you_say: "You tell the immortals",
+        they_say: "tells the immortals",
+    },
+    Channel {
+        name: "auction",
+        command: "auction",
+        description: "Hear about auctions, turned on or off with '`Wauction quiet`^'",
+        color: "G",
+        you_say: "You auction",
+        they_say: "auctions",
+    },
+];
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Check if a player is around to hear a channel, and wants to.
+    fn hears_channel(&self, listener: &EntityInfo, channel: &Channel) -> bool {
+        let listener_name = match listener.player_name() {
+            Some(listener_name) => listener_name,
+            None => return false,
+        };
+
+        let link_dead = self
+            .players
+            .player_echoes
+            .get(listener_name)
+            .map(|player_echo| player_echo.link_dead)
+            .unwrap_or(true);
+
+        !link_dead
+            && self.accounts.trust(listener_name) >= self.command_trust.required(channel.command)
+            && !self
+                .accounts
+                .preferences(listener_name)
+                .channels_off
+                .contains(channel.name)
+    }
+
+    pub fn do_channels(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player,
+            None => {
+                echo!(self.info(), "Only players can use channels.\r\n");
+                return;
+            }
+        };
+
+        let channels_off = self.accounts.preferences(player).channels_off.clone();
+        let channels: Vec<&Channel> = CHANNELS
+            .iter()
+            .filter(|channel| self.trusted_with(channel.command))
+            .collect();
+
+        let mut info = self.players.info(&myself);
+        echo!(info, "Channels:\r\n");
+        for channel in channels {
+            echo!(
+                info,
+                "  `W{:<8}`^ {} - {}\r\n",
+                channel.name,
+                if channels_off.contains(channel.name) {
+                    "`Roff`^"
+                } else {
+                    "`Gon `^"
+                },
+                channel.description
+            );
+        }
+        echo!(
+            info,
+            "Use a channel's command without a message to turn it on or off.\r\n"
+        );
+    }
+
+    pub fn do_channel(&mut self, channel: &str, message: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can use channels.\r\n");
+                return;
+            }
+        };
+
+        let channel = CHANNELS
+            .iter()
+            .find(|known| known.name == channel)
+            .expect("Only known channels are used");
+
+        // Only clan members hear the clan channel
+        let clan = if channel.name == "clan" {
+            match self.clans.clan_key_of(&player) {
+                Some(clan) => Some(clan),
+                None => {
+                    echo!(self.info(), "You aren't in a clan.\r\n");
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        if !message.is_empty() && self.accounts.is_muted(&player) {
+            echo!(
+                self.info(),
+                "You have been muted, and can't talk on channels.\r\n"
+            );
+            return;
+        }
+
+        let channels_off = &mut self.accounts.preferences_mut(&player).channels_off;
+
+        if message.is_empty() {
+            let now_on = channels_off.remove(channel.name);
+            if !now_on {
+                channels_off.insert(channel.name.to_string());
+            }
+
+            echo!(
+                self.info(),
+                "The `W{}`^ channel is now {}.\r\n",
+                channel.name,
+                if now_on { "`Gon`^" } else { "`Roff`^" }
+            );
+            return;
+        }
+
+        // Talking on a channel turns it back on
+        channels_off.remove(channel.name);
+
+        echo!(
+            self.info(),
+            "`{}{}, '{}'`^\r\n",
+            channel.color,
+            channel.you_say,
+            message
+        );
+
+        for listener in self.entity_world.all_players() {
+            let listener_name = listener.player_name().expect("Is a player");
+
+            if listener_name == player
+                || !self.hears_channel(&listener, channel)
+                || (clan.is_some() && self.clans.clan_key_of(listener_name) != clan)
+            {
+                continue;
+            }
+
+            echo!(
+                self.players.info(&listener),
+                "`{}{} {}, '{}'`^\r\n",
+                channel.color,
+                player,
+                channel.they_say,
+                message
+            );
+        }
+    }
+}
@@ -0,0 +1,587 @@
+//! CircleMUD zone loader.
+//!
+//! A CircleMUD zone is kept in four files with the same number: rooms in
+//! `.wld`, mobiles in `.mob`, objects in `.obj` and resets in `.zon`. An
+//! entry of the area list that ends in `.zon`, or whose file starts with the
+//! number of a zone, is read as a zone, with the other files next to it, or in the `wld`, `mob` and `obj` directories
+//! beside a `zon` directory, as Circle's `lib/world` keeps them. Files that
+//! don't exist are taken as empty. Zones of tbaMUD and CircleMUD 3.5, with
+//! their four sets of flags, are read too.
+//!
+//! Like ROM areas (see `crate::rom`), zones are read into the types of
+//! `crate::world`: flags the game knows about are turned into words, and
+//! the rest is read and skipped, as are shops, scripts and the specials of
+//! enhanced mobiles. Door resets set whether doors start closed or locked.
+//! The short name of a zone is that of its file, like "zone30" for
+//! `30.zon`.
+//!
+//! A zone edited with the OLC is saved as a Dawn of Time area, which takes
+//! its place in the area list.
+
+use std::{convert::TryFrom, io};
+
+use crate::{
+    file_parser::{FileParser, ParseResult},
+    files::Files,
+    load::{check_vnum, read_number, remember_area, DEFAULT_RESET_INTERVAL},
+    rom::{
+        apply_door_resets, to_vnum, CONT_CLOSEABLE, CONT_CLOSED, CONT_LOCKED, DIRECTIONS,
+        WEAR_FLAGS, WEAR_LOCATIONS,
+    },
+    world::{
+        Area, AreaData, AreaFormat, Exit, ExtraDescription, Gender, Mobile, Object, ObjectFlags,
+        ResetCommand, Room, Vnum,
+    },
+};
+
+/// Sectors of rooms, by their number
+const SECTORS: [&str; 10] = [
+    "inside",
+    "city",
+    "field",
+    "forest",
+    "hills",
+    "mountain",
+    "swim",
+    "noswim",
+    "underwater",
+    "air",
+];
+
+/// Item types, by their number; 0 is unused
+const ITEM_TYPES: [&str; 24] = [
+    "trash",
+    "light",
+    "scroll",
+    "wand",
+    "staff",
+    "weapon",
+    "fireweapon",
+    "missile",
+    "treasure",
+    "armor",
+    "potion",
+    "clothing",
+    "other",
+    "trash",
+    "trap",
+    "container",
+    "note",
+    "drink_con",
+    "key",
+    "food",
+    "money",
+    "pen",
+    "boat",
+    "fountain",
+];
+
+/// Liquids of drink containers and fountains, by their number
+const LIQUIDS: [&str; 16] = [
+    "water",
+    "beer",
+    "wine",
+    "ale",
+    "dark ale",
+    "whisky",
+    "lemonade",
+    "firebreather",
+    "local specialty",
+    "slime mold juice",
+    "milk",
+    "tea",
+    "coffee",
+    "blood",
+    "salt water",
+    "water",
+];
+
+// Flags of mobiles and rooms that the game knows about
+const ACT_SENTINEL: u64 = bit(b'b');
+const ACT_SCAVENGER: u64 = bit(b'c');
+const ACT_AGGRESSIVE: u64 = bit(b'f');
+const ACT_STAY_ZONE: u64 = bit(b'g');
+const ROOM_NO_MOB: u64 = bit(b'c');
+
+/// The bit of a flag letter; unlike ROM, Circle counts from a to z and then
+/// A to Z.
+const fn bit(letter: u8) -> u64 {
+    match letter {
+        b'a'..=b'z' => 1 << (letter - b'a'),
+        b'A'..=b'Z' => 1 << (letter - b'A' + 26),
+        _ => 0,
+    }
+}
+
+/// Parse flags like "bcf", or a number with the same bits.
+fn parse_flags(parser: &FileParser, word: &str) -> ParseResult<u64> {
+    if word.bytes().all(|c| c.is_ascii_alphabetic()) {
+        Ok(word.bytes().map(bit).fold(0, |flags, bit| flags | bit))
+    } else {
+        let number: i64 = parser.parse(word, "flags")?;
+        Ok(number.max(0) as u64)
+    }
+}
+
+/// A file of a zone: its path and contents
+type ZoneFile = (String, String);
+
+/// The files of a zone, read before they are parsed
+pub(super) struct CircleZone {
+    zone: ZoneFile,
+    rooms: ZoneFile,
+    mobiles: ZoneFile,
+    objects: ZoneFile,
+}
+
+/// Read the files of a zone, from the path of its `.zon` file.
+pub(super) fn read_circle_zone(files: &dyn Files, path: &str) -> io::Result<CircleZone> {
+    let zone = files.read_file(path)?;
+    let (directory, file_name) = path.rsplit_once('/').unwrap_or(("", path));
+    let number = stem(file_name);
+
+    let read = |extension: &str| {
+        let next_to_zone = format!("{}/{}.{}", directory, number, extension);
+        let in_world_directory = directory
+            .strip_suffix("zon")
+            .map(|world| format!("{}{}/{}.{}", world, extension, number, extension));
+
+        std::iter::once(next_to_zone)
+            .chain(in_world_directory)
+            .find_map(|path| files.read_file(&path).ok().map(|contents| (path, contents)))
+            .unwrap_or_default()
+    };
+
+    Ok(CircleZone {
+        zone: (path.to_string(), zone),
+        rooms: read("wld"),
+        mobiles: read("mob"),
+        objects: read("obj"),
+    })
+}
+
+/// The short name of a zone, from its file name, e.g. "zone30" for
+/// "data/area/30.zon".
+fn short_name(file_name: &str) -> String {
+    let file_name = file_name.rsplit(['/', '\\']).next().unwrap_or("");
+    let stem = stem(file_name);
+    if stem.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("zone{}", stem)
+    } else {
+        stem.to_string()
+    }
+}
+
+/// A file name without its extension, like "30" for "30.zon".
+pub(super) fn stem(file_name: &str) -> &str {
+    file_name
+        .rsplit_once('.')
+        .map_or(file_name, |(stem, _extension)| stem)
+}
+
+/// Parse the files of a zone into an area.
+pub(super) fn load_circle_zone(zone: &CircleZone) -> ParseResult<Area> {
+    let (zone_path, zone_contents) = &zone.zone;
+    let mut parser = FileParser::new(zone_contents, zone_path);
+    // Rooms, exits and how their doors start, from the zone commands
+    let mut doors = Vec::new();
+    let (area_data, resets) = load_zone(&mut parser, &short_name(zone_path), &mut doors)?;
+
+    let mut area = Area {
+        area_data,
+        rooms: load_records(&zone.rooms, load_room)?,
+        objects: load_records(&zone.objects, load_object)?,
+        mobiles: load_records(&zone.mobiles, load_mobile)?,
+        resets,
+        shops: Vec::new(),
+        mobprogs: Vec::new(),
+        helps: Vec::new(),
+        socials: Vec::new(),
+    };
+
+    apply_door_resets(&mut area, doors);
+    remember_area(&mut area);
+
+    Ok(area)
+}
+
+/// Read the next line that isn't blank.
+fn read_line<'a>(parser: &mut FileParser<'a>) -> ParseResult<&'a str> {
+    parser.skip_all_space();
+    parser.read_until_newline()
+}
+
+/// Read the records of a file, each starting with its vnum like "#3001",
+/// until a '$' or the end of the file.
+fn load_records<T>(
+    (path, contents): &ZoneFile,
+    load: impl Fn(&mut FileParser, usize) -> ParseResult<T>,
+) -> ParseResult<Vec<T>> {
+    let mut parser = FileParser::new(contents, path);
+    let mut records = Vec::new();
+
+    loop {
+        match parser.peek_char() {
+            None | Some('$') => break,
+            // Scripts of tbaMUD
+            Some('T') => {
+                read_line(&mut parser)?;
+            }
+            Some(_) => {
+                let section = parser.read_section()?;
+                let vnum = parser.parse(section, "vnum")?;
+                let vnum = check_vnum(&parser, vnum)?;
+                records.push(load(&mut parser, vnum)?);
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+fn load_room(parser: &mut FileParser, vnum: usize) -> ParseResult<Room> {
+    let mut room = Room {
+        vnum: Vnum(vnum),
+        name: parser.read_string()?.to_string(),
+        description: parser.read_string()?.to_string(),
+        ..Default::default()
+    };
+
+    // The zone number, the flags (four of them in tbaMUD) and the sector
+    let line = read_line(parser)?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if words.len() < 3 {
+        return Err(parser.error(&format!("Invalid room data: '{}'", line)));
+    }
+    let room_flags = parse_flags(parser, words[1])?;
+    room.no_mob = room_flags & ROOM_NO_MOB != 0;
+    let sector: usize = parser.parse(words[words.len() - 1], "sector")?;
+    room.sector = SECTORS.get(sector).copied().unwrap_or("inside").to_string();
+
+    loop {
+        // A letter, and for exits their direction right after it, e.g. "D0"
+        let word = parser.read_word()?;
+        let letter = word.chars().next().unwrap_or_default();
+
+        match letter {
+            'S' => break,
+            'D' => {
+                let door: usize = parser.parse(&word[1..], "direction")?;
+                let name = DIRECTIONS
+                    .get(door)
+                    .ok_or_else(|| parser.error(&format!("Invalid direction: {}", door)))?;
+                let description = parser.read_string()?;
+                let keywords = parser.read_string()?;
+                let door_flags: i32 = read_number(parser, "door flags")?;
+                let key: i64 = read_number(parser, "key vnum")?;
+                let to_room: i64 = read_number(parser, "exit vnum")?;
+
+                // Exits that lead nowhere
+                if to_room < 0 {
+                    continue;
+                }
+
+                let not_empty =
+                    |text: &str| Some(text.to_string()).filter(|text| !text.trim().is_empty());
+                room.exits.push(Exit {
+                    name: name.to_string(),
+                    vnum: to_vnum(parser, to_room)?,
+                    description: not_empty(description),
+                    extra_keywords: not_empty(keywords),
+                    has_door: door_flags > 0,
+                    key: if key > 0 {
+                        Some(to_vnum(parser, key)?)
+                    } else {
+                        None
+                    },
+                    ..Default::default()
+                });
+            }
+            'E' => room.extra_descriptions.push(ExtraDescription {
+                keyword: parser.read_string()?.to_string(),
+                description: parser.read_string()?.to_string(),
+            }),
+            _ => return Err(parser.error(&format!("Unrecognized room data: '{}'", word))),
+        }
+    }
+
+    Ok(room)
+}
+
+fn load_mobile(parser: &mut FileParser, vnum: usize) -> ParseResult<Mobile> {
+    let mut mobile = Mobile {
+        vnum: Vnum(vnum),
+        name: parser.read_string()?.to_string(),
+        short_description: parser.read_string()?.to_string(),
+        long_description: parser.read_string()?.to_string(),
+        description: parser.read_string()?.to_string(),
+        ..Default::default()
+    };
+
+    // The act and affect flags (four of each in tbaMUD), the alignment, and
+    // whether the mobile is simple or enhanced
+    let line = read_line(parser)?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let act = match words.len() {
+        4 | 10 => parse_flags(parser, words[0])?,
+        _ => return Err(parser.error(&format!("Invalid mobile data: '{}'", line))),
+    };
+    let enhanced = words[words.len() - 1] == "E";
+
+    // The level, THAC0, armor class, hit points and damage
+    let level: i32 = read_number(parser, "level")?;
+    mobile.level = level.max(0) as u32;
+    read_line(parser)?;
+    // Gold and experience
+    let gold: i64 = read_number(parser, "gold")?;
+    mobile.wealth.gold = gold.max(0) as usize;
+    read_line(parser)?;
+    // Positions, and the sex
+    let _position = parser.read_word()?;
+    let _default_position = parser.read_word()?;
+    mobile.gender = match read_number(parser, "sex")? {
+        1 => Gender::Male,
+        2 => Gender::Female,
+        _ => Gender::Neutral,
+    };
+
+    // Specials like "BareHandAttack: 12", up to an "E" of their own
+    if enhanced {
+        while read_line(parser)?.trim() != "E" {}
+    }
+
+    mobile.sentinel = act & ACT_SENTINEL != 0;
+    mobile.scavenger = act & ACT_SCAVENGER != 0;
+    mobile.aggressive = act & ACT_AGGRESSIVE != 0;
+    mobile.stay_area = act & ACT_STAY_ZONE != 0;
+
+    Ok(mobile)
+}
+
+fn load_object(parser: &mut FileParser, vnum: usize) -> ParseResult<Object> {
+    let mut object = Object {
+        vnum: Vnum(vnum),
+        name: parser.read_string()?.to_string(),
+        short_description: parser.read_string()?.to_string(),
+        description: parser.read_string()?.to_string(),
+        ..Default::default()
+    };
+    let _action_description = parser.read_string()?;
+
+    // The item type, the extra and wear flags, and in tbaMUD the flags it
+    // gives, each of them four times
+    let line = read_line(parser)?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let wear_flags = match words.len() {
+        3 => parse_flags(parser, words[2])?,
+        13 => parse_flags(parser, words[5])?,
+        _ => return Err(parser.error(&format!("Invalid object data: '{}'", line))),
+    };
+    let item_type: usize = parser.parse(words[0], "item type")?;
+    object.item_type = ITEM_TYPES
+        .get(item_type)
+        .ok_or_else(|| parser.error(&format!("Unknown item type: {}", item_type)))?
+        .to_string();
+
+    object.take = wear_flags & bit(b'a') != 0;
+    object.wear = WEAR_FLAGS
+        .iter()
+        .enumerate()
+        .skip(1)
+        .take(14)
+        .find(|(index, _flag)| wear_flags & (1 << index) != 0)
+        .map(|(_index, flag)| flag.to_string());
+
+    let mut values = [0; 4];
+    for value in &mut values {
+        *value = read_number(parser, "object value")?;
+    }
+
+    match object.item_type.as_str() {
+        "container" => {
+            let flags = values[1].max(0) as u64;
+            object.flags = ObjectFlags::Container {
+                closable: flags & CONT_CLOSEABLE != 0,
+                closed: flags & CONT_CLOSED != 0,
+                locked: flags & CONT_LOCKED != 0,
+            };
+        }
+        "light" => {
+            object.flags = ObjectFlags::Light {
+                capacity: 0,
+                hours: values[2],
+            };
+        }
+        "drink_con" | "fountain" => {
+            object.flags = ObjectFlags::Liquid {
+                liquid: usize::try_from(values[2])
+                    .ok()
+                    .and_then(|liquid| LIQUIDS.get(liquid))
+                    .unwrap_or(&"water")
+                    .to_string(),
+            };
+        }
+        _ => (),
+    }
+
+    // The weight, cost and rent, and in tbaMUD the level and timer
+    let line = read_line(parser)?;
+    let cost = line.split_whitespace().nth(1).unwrap_or_default();
+    object.cost = parser.parse(cost, "cost")?;
+
+    loop {
+        match parser.peek_char() {
+            // Affects on whoever wears it
+            Some('A') => {
+                parser.read_word()?;
+                let _location: i32 = read_number(parser, "apply location")?;
+                let _modifier: i32 = read_number(parser, "apply modifier")?;
+            }
+            Some('E') => {
+                parser.read_word()?;
+                object.extra_descriptions.push(ExtraDescription {
+                    keyword: parser.read_string()?.to_string(),
+                    description: parser.read_string()?.to_string(),
+                });
+            }
+            _ => break,
+        }
+    }
+
+    Ok(object)
+}
+
+/// Read a zone file: its name, vnums and lifespan, and its commands, keeping
+/// door commands apart as the room, the direction and whether the door is
+/// closed (1) or locked too (2).
+fn load_zone(
+    parser: &mut FileParser,
+    short_name: &str,
+    doors: &mut Vec<(Vnum, &'static str, i64)>,
+) -> ParseResult<(AreaData, Vec<ResetCommand>)> {
+    let section = parser.read_section()?;
+    let number: usize = parser.parse(section, "zone number")?;
+
+    // tbaMUD has the builders before the name
+    let mut credits = "";
+    let mut name = parser.read_string()?;
+    if !parser.peek_char().is_some_and(|c| c.is_ascii_digit()) {
+        credits = name;
+        name = parser.read_string()?;
+    }
+
+    // The top vnum, the lifespan and the reset mode; tbaMUD has the bottom
+    // vnum first, and zone flags after them
+    let line = read_line(parser)?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (bottom, top, lifespan) = match words.len() {
+        3 => (number * 100, words[0], words[1]),
+        n if n > 3 => (parser.parse(words[0], "bottom vnum")?, words[1], words[2]),
+        _ => return Err(parser.error(&format!("Invalid zone data: '{}'", line))),
+    };
+    let top: usize = parser.parse(top, "top vnum")?;
+    let lifespan: u16 = parser.parse(lifespan, "lifespan")?;
+
+    let area_data = AreaData {
+        name: name.trim().to_string(),
+        short_name: short_name.to_string(),
+        vnums: (
+            Vnum(check_vnum(parser, bottom)?),
+            Vnum(check_vnum(parser, top)?),
+        ),
+        credits: credits.trim().to_string(),
+        continent: Default::default(),
+        builders: Default::default(),
+        security: 0,
+        reset_interval: if lifespan > 0 {
+            lifespan
+        } else {
+            DEFAULT_RESET_INTERVAL
+        },
+        encounters: Vec::new(),
+        file_name: Default::default(),
+        source: None,
+        on_disk: false,
+        changed: false,
+        format: AreaFormat::Circle,
+    };
+
+    let mut resets = Vec::new();
+
+    loop {
+        let line = read_line(parser)?;
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+
+        // Whatever is after the arguments is a comment
+        let mut args = [0; 4];
+        if matches!(command, "M" | "O" | "G" | "E" | "P" | "D") {
+            let count = match command {
+                "G" => 3,
+                _ => 4,
+            };
+            for arg in args.iter_mut().take(count) {
+                let word = words.next().unwrap_or_default();
+                *arg = parser.parse(word, "zone command argument")?;
+            }
+        }
+        let [_if_flag, arg1, arg2, arg3]: [i64; 4] = args;
+        let limit = |limit: i64| limit.clamp(0, i16::MAX.into()) as i16;
+
+        match command {
+            "S" | "$" => break,
+            "M" => resets.push(ResetCommand::Mob {
+                m_num: to_vnum(parser, arg1)?,
+                global_limit: limit(arg2) as u16,
+                r_num: to_vnum(parser, arg3)?,
+                room_limit: limit(arg2) as u16,
+                only_at: None,
+            }),
+            "O" => resets.push(ResetCommand::Object {
+                o_num: to_vnum(parser, arg1)?,
+                global_limit: limit(arg2),
+                r_num: to_vnum(parser, arg3)?,
+                only_at: None,
+            }),
+            "P" => resets.push(ResetCommand::Put {
+                o_num: to_vnum(parser, arg1)?,
+                global_limit: limit(arg2),
+                c_num: to_vnum(parser, arg3)?,
+                container_limit: limit(arg2),
+            }),
+            "G" => resets.push(ResetCommand::Give {
+                o_num: to_vnum(parser, arg1)?,
+                global_limit: limit(arg2),
+            }),
+            "E" => {
+                let o_num = to_vnum(parser, arg1)?;
+                // Equipment that isn't worn is only carried
+                match usize::try_from(arg3)
+                    .ok()
+                    .and_then(|at| WEAR_LOCATIONS.get(at))
+                {
+                    Some(location) => resets.push(ResetCommand::Equip {
+                        o_num,
+                        global_limit: limit(arg2),
+                        location: location.to_string(),
+                    }),
+                    None => resets.push(ResetCommand::Give {
+                        o_num,
+                        global_limit: limit(arg2),
+                    }),
+                }
+            }
+            "D" => {
+                let direction = usize::try_from(arg2)
+                    .ok()
+                    .and_then(|door| DIRECTIONS.get(door))
+                    .ok_or_else(|| parser.error(&format!("Invalid direction: {}", arg2)))?;
+                doors.push((to_vnum(parser, arg1)?, *direction, arg3));
+            }
+            // Objects removed from rooms, scripts, variables, and comments
+            _ => (),
+        }
+    }
+
+    Ok((area_data, resets))
+}
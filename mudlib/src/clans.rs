@@ -0,0 +1,856 @@
+//! Clans: groups of players with ranks, a hall, and a shared treasury.
+//!
+//! Clans are created by immortals with `clanedit`, which can also set their
+//! leader, rank titles, hall and treasury. Leaders then run their own clan
+//! with `clan` subcommands: inviting, removing, promoting and demoting
+//! members, and withdrawing from the treasury.
+//!
+//! A clan's hall is a range of room vnums that only its members (and
+//! immortals) can walk into. If a clan has a hall, its treasury can only be
+//! used from inside it.
+//!
+//! Clans and their rosters are saved to `data/clans.json` whenever they
+//! change. Invitations are not saved.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{agent::EntityAgent, echo, entity::EntityId, files::Files};
+
+const CLANS_FILE: &str = "data/clans.json";
+
+/// Rank titles of new clans, from lowest to highest
+static DEFAULT_RANKS: &[&str] = &["Recruit", "Member", "Officer", "Leader"];
+
+pub(crate) struct Clans {
+    /// All clans, by lowercase name
+    clans: BTreeMap<String, Clan>,
+    /// Invitations waiting for an answer, from lowercase player name to
+    /// lowercase clan name
+    invitations: BTreeMap<String, String>,
+    changed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct Clan {
+    pub name: String,
+    /// Rank titles, from lowest to highest; the highest rank leads the clan
+    pub ranks: Vec<String>,
+    /// Members, by lowercase player name
+    pub members: BTreeMap<String, Member>,
+    /// First and last vnum of the rooms that make up the clan hall
+    #[serde(default)]
+    pub hall: Option<(usize, usize)>,
+    /// Silver coins kept by the clan
+    #[serde(default)]
+    pub treasury: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct Member {
+    pub name: String,
+    /// Index into the clan's rank titles
+    pub rank: usize,
+}
+
+impl Clan {
+    fn leader_rank(&self) -> usize {
+        self.ranks.len() - 1
+    }
+
+    fn member(&self, player: &str) -> Option<&Member> {
+        self.members.get(&player.to_lowercase())
+    }
+
+    fn rank_title(&self, rank: usize) -> &str {
+        &self.ranks[rank.min(self.leader_rank())]
+    }
+
+    fn is_leader(&self, player: &str) -> bool {
+        self.member(player)
+            .map(|member| member.rank >= self.leader_rank())
+            .unwrap_or(false)
+    }
+
+    fn hall_contains(&self, vnum: usize) -> bool {
+        match self.hall {
+            Some((first, last)) => vnum >= first && vnum <= last,
+            None => false,
+        }
+    }
+}
+
+impl Clans {
+    pub fn load(files: &dyn Files) -> Self {
+        let clans = match files.read_file_raw(CLANS_FILE) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .unwrap_or_else(|err| panic!("Could not parse {}: {}", CLANS_FILE, err)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => panic!("Could not read {}: {}", CLANS_FILE, err),
+        };
+
+        Clans {
+            clans,
+            invitations: BTreeMap::new(),
+            changed: false,
+        }
+    }
+
+    pub fn save_changes(&mut self, files: &dyn Files) -> Result<(), std::io::Error> {
+        if self.changed {
+            let contents = serde_json::to_vec_pretty(&self.clans).expect("Clans should serialize");
+            files.write_file_raw(CLANS_FILE, &contents)?;
+            self.changed = false;
+        }
+
+        Ok(())
+    }
+
+    /// Get the lowercase name of a player's clan.
+    pub fn clan_key_of(&self, player: &str) -> Option<String> {
+        let player = player.to_lowercase();
+        self.clans
+            .iter()
+            .find(|(_key, clan)| clan.members.contains_key(&player))
+            .map(|(key, _clan)| key.clone())
+    }
+
+    /// Get the clan whose hall includes the room with this vnum.
+    pub fn hall_owner(&self, vnum: usize) -> Option<&Clan> {
+        self.clans.values().find(|clan| clan.hall_contains(vnum))
+    }
+
+    /// Take a deleted character off their clan's roster.
+    pub fn forget_player(&mut self, player: &str) {
+        let player = player.to_lowercase();
+        self.invitations.remove(&player);
+
+        for clan in self.clans.values_mut() {
+            if clan.members.remove(&player).is_some() {
+                self.changed = true;
+            }
+        }
+    }
+
+    fn add_member(&mut self, clan_key: &str, player: &str, rank: usize) {
+        self.forget_player(player);
+
+        let clan = self.clans.get_mut(clan_key).expect("Clan should exist");
+        let rank = rank.min(clan.leader_rank());
+        clan.members.insert(
+            player.to_lowercase(),
+            Member {
+                name: player.to_string(),
+                rank,
+            },
+        );
+        self.changed = true;
+    }
+}
+
+fn parse_amount(amount: &str) -> Option<usize> {
+    amount.parse::<usize>().ok().filter(|amount| *amount > 0)
+}
+
+// Clan commands
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_clan(&mut self, words: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can join clans.\r\n");
+                return;
+            }
+        };
+
+        match words {
+            ["accept"] | ["join"] => return self.do_clan_accept(&player),
+            ["decline"] => return self.do_clan_decline(&player),
+            _ => (),
+        }
+
+        let clan_key = match self.clans.clan_key_of(&player) {
+            Some(clan_key) => clan_key,
+            None => {
+                let invitation = self.clans.invitations.get(&player.to_lowercase());
+                match invitation.and_then(|clan_key| self.clans.clans.get(clan_key)) {
+                    Some(clan) => {
+                        let clan_name = clan.name.clone();
+                        echo!(
+                            self.info(),
+                            "You have been invited to join the `W{}`^ clan; type '`Wclan accept`^' \
+                            or '`Wclan decline`^'.\r\n",
+                            clan_name
+                        );
+                    }
+                    None => echo!(self.info(), "You aren't in a clan.\r\n"),
+                }
+                return;
+            }
+        };
+
+        match words {
+            [] | ["info"] => self.do_clan_info(&player, &clan_key),
+            ["roster"] | ["members"] => self.do_clan_roster(&clan_key),
+            ["invite", target] => self.do_clan_invite(&player, &clan_key, target),
+            ["remove", target] | ["kick", target] => {
+                self.do_clan_remove(&player, &clan_key, target)
+            }
+            ["promote", target] => self.do_clan_rank(&player, &clan_key, target, true),
+            ["demote", target] => self.do_clan_rank(&player, &clan_key, target, false),
+            ["leave"] => self.do_clan_leave(&player, &clan_key),
+            ["deposit", amount] => self.do_clan_deposit(&player, &clan_key, amount),
+            ["withdraw", amount] => self.do_clan_withdraw(&player, &clan_key, amount),
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Wclan`^', '`Wclan roster`^', '`Wclan invite <player>`^', \
+                    '`Wclan remove <player>`^', '`Wclan promote <player>`^', \
+                    '`Wclan demote <player>`^', '`Wclan deposit <amount>`^', \
+                    '`Wclan withdraw <amount>`^', '`Wclan leave`^'.\r\n"
+                );
+            }
+        }
+    }
+
+    fn do_clan_info(&mut self, player: &str, clan_key: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let clan = &self.clans.clans[clan_key];
+        let member = clan.member(player).expect("Player is in the clan");
+
+        let mut info = self.players.info(&myself);
+        echo!(
+            info,
+            "You are a `W{}`^ of the `W{}`^ clan.\r\n",
+            clan.rank_title(member.rank),
+            clan.name
+        );
+        echo!(
+            info,
+            "Members: {}. Treasury: {} silver coins.\r\n",
+            clan.members.len(),
+            clan.treasury
+        );
+        match clan.hall {
+            Some((first, last)) => echo!(info, "Clan hall: rooms {} to {}.\r\n", first, last),
+            None => echo!(info, "Your clan has no hall.\r\n"),
+        }
+        echo!(
+            info,
+            "Type '`Wclan roster`^' to see the members, or '`Wclantalk <message>`^' to talk \
+            to them.\r\n"
+        );
+    }
+
+    fn do_clan_roster(&mut self, clan_key: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let clan = &self.clans.clans[clan_key];
+
+        let mut members: Vec<&Member> = clan.members.values().collect();
+        members.sort_by(|a, b| b.rank.cmp(&a.rank).then(a.name.cmp(&b.name)));
+
+        let mut info = self.players.info(&myself);
+        echo!(info, "Members of the `W{}`^ clan:\r\n", clan.name);
+        for member in members {
+            let online = self.entity_world.player_entity_id(&member.name).is_some();
+            echo!(
+                info,
+                "  `W{:<12}`^ {:<12}{}\r\n",
+                member.name,
+                clan.rank_title(member.rank),
+                if online { " `G(in the realm)`^" } else { "" }
+            );
+        }
+    }
+
+    fn do_clan_invite(&mut self, player: &str, clan_key: &str, target: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let clan = &self.clans.clans[clan_key];
+
+        if !clan.is_leader(player) {
+            echo!(self.info(), "Only clan leaders can invite new members.\r\n");
+            return;
+        }
+
+        let target = match self.entity_world.all_players().find(|other| {
+            other
+                .player_name()
+                .unwrap_or("")
+                .eq_ignore_ascii_case(target)
+        }) {
+            Some(target) => target,
+            None => {
+                echo!(
+                    self.players.info(&myself),
+                    "There is no one in the realm named like that.\r\n"
+                );
+                return;
+            }
+        };
+        let target_name = target.player_name().expect("Is a player");
+
+        if self.clans.clan_key_of(target_name).is_some() {
+            echo!(
+                self.players.info(&myself),
+                "{} is already in a clan.\r\n",
+                target_name
+            );
+            return;
+        }
+
+        let clan_name = clan.name.clone();
+        self.clans
+            .invitations
+            .insert(target_name.to_lowercase(), clan_key.to_string());
+
+        echo!(
+            self.players.info(&target),
+            "{} invites you to join the `W{}`^ clan. Type '`Wclan accept`^' to join, or \
+            '`Wclan decline`^'.\r\n",
+            player,
+            clan_name
+        );
+        echo!(
+            self.players.info(&myself),
+            "You invite {} to join your clan.\r\n",
+            target_name
+        );
+    }
+
+    fn do_clan_accept(&mut self, player: &str) {
+        let clan_key = match self.clans.invitations.remove(&player.to_lowercase()) {
+            Some(clan_key) if self.clans.clans.contains_key(&clan_key) => clan_key,
+            _ => {
+                echo!(self.info(), "Nobody invited you to a clan.\r\n");
+                return;
+            }
+        };
+
+        if self.clans.clan_key_of(player).is_some() {
+            echo!(self.info(), "You are already in a clan.\r\n");
+            return;
+        }
+
+        self.clans.add_member(&clan_key, player, 0);
+
+        let clan_name = self.clans.clans[&clan_key].name.clone();
+        self.echo_to_clan(&clan_key, &format!("{} has joined the clan!", player));
+        echo!(self.info(), "Welcome to the `W{}`^ clan!\r\n", clan_name);
+    }
+
+    fn do_clan_decline(&mut self, player: &str) {
+        if self
+            .clans
+            .invitations
+            .remove(&player.to_lowercase())
+            .is_some()
+        {
+            echo!(self.info(), "You decline the invitation.\r\n");
+        } else {
+            echo!(self.info(), "Nobody invited you to a clan.\r\n");
+        }
+    }
+
+    fn do_clan_remove(&mut self, player: &str, clan_key: &str, target: &str) {
+        let clan = &self.clans.clans[clan_key];
+
+        if !clan.is_leader(player) {
+            echo!(self.info(), "Only clan leaders can remove members.\r\n");
+            return;
+        }
+
+        let member = match clan.member(target) {
+            Some(member) => member.clone(),
+            None => {
+                echo!(self.info(), "There is no member named like that.\r\n");
+                return;
+            }
+        };
+
+        if member.rank >= clan.leader_rank() {
+            echo!(self.info(), "Leaders can't be removed from the clan.\r\n");
+            return;
+        }
+
+        self.clans.forget_player(&member.name);
+        self.echo_to_clan(
+            clan_key,
+            &format!("{} has removed {} from the clan.", player, member.name),
+        );
+
+        if let Some(target_id) = self.entity_world.player_entity_id(&member.name) {
+            let target = self.entity_world.entity_info(target_id);
+            echo!(
+                self.players.info(&target),
+                "You have been removed from your clan.\r\n"
+            );
+        }
+    }
+
+    fn do_clan_rank(&mut self, player: &str, clan_key: &str, target: &str, promote: bool) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let mut info = self.players.info(&myself);
+        let clan = self.clans.clans.get_mut(clan_key).expect("Clan exists");
+
+        if !clan.is_leader(player) {
+            echo!(
+                info,
+                "Only clan leaders can promote and demote members.\r\n"
+            );
+            return;
+        }
+
+        let leader_rank = clan.leader_rank();
+        let member = match clan.members.get_mut(&target.to_lowercase()) {
+            Some(member) => member,
+            None => {
+                echo!(info, "There is no member named like that.\r\n");
+                return;
+            }
+        };
+
+        if member.rank >= leader_rank {
+            echo!(
+                info,
+                "{} already leads the clan; you can't change their rank.\r\n",
+                member.name
+            );
+            return;
+        }
+
+        if promote {
+            member.rank += 1;
+        } else if member.rank == 0 {
+            echo!(info, "{} already has the lowest rank.\r\n", member.name);
+            return;
+        } else {
+            member.rank -= 1;
+        }
+
+        let message = format!(
+            "{} has been {} to {}.",
+            member.name,
+            if promote { "promoted" } else { "demoted" },
+            clan.ranks[member.rank]
+        );
+        self.clans.changed = true;
+        self.echo_to_clan(clan_key, &message);
+    }
+
+    fn do_clan_leave(&mut self, player: &str, clan_key: &str) {
+        let clan = &self.clans.clans[clan_key];
+
+        let leaders = clan
+            .members
+            .values()
+            .filter(|member| member.rank >= clan.leader_rank())
+            .count();
+
+        if clan.is_leader(player) && leaders == 1 && clan.members.len() > 1 {
+            echo!(
+                self.info(),
+                "You are the clan's only leader; promote someone to lead it first.\r\n"
+            );
+            return;
+        }
+
+        let clan_name = clan.name.clone();
+        self.clans.forget_player(player);
+        self.echo_to_clan(clan_key, &format!("{} has left the clan.", player));
+        echo!(self.info(), "You leave the `W{}`^ clan.\r\n", clan_name);
+    }
+
+    /// Check that the treasury can be used from here, and echo why if not.
+    fn in_clan_hall(&mut self, clan_key: &str) -> bool {
+        let room = self.entity_world.room_of(self.entity_id);
+        let vnum = self
+            .entity_world
+            .entity_info(room)
+            .components()
+            .general
+            .vnum
+            .0;
+        let clan = &self.clans.clans[clan_key];
+
+        if clan.hall.is_some() && !clan.hall_contains(vnum) {
+            echo!(
+                self.info(),
+                "The clan's treasury is kept in the clan hall.\r\n"
+            );
+            false
+        } else {
+            true
+        }
+    }
+
+    fn do_clan_deposit(&mut self, player: &str, clan_key: &str, amount: &str) {
+        let amount = match parse_amount(amount) {
+            Some(amount) => amount,
+            None => {
+                echo!(self.info(), "That's not a valid amount of silver.\r\n");
+                return;
+            }
+        };
+
+        if !self.in_clan_hall(clan_key) {
+            return;
+        }
+
+        if !self.remove_silver(amount, self.entity_id) {
+            echo!(self.info(), "You don't have that much silver.\r\n");
+            return;
+        }
+
+        let clan = self.clans.clans.get_mut(clan_key).expect("Clan exists");
+        clan.treasury += amount;
+        self.clans.changed = true;
+
+        self.echo_to_clan(
+            clan_key,
+            &format!(
+                "{} deposits {} silver coins into the clan's treasury.",
+                player, amount
+            ),
+        );
+    }
+
+    fn do_clan_withdraw(&mut self, player: &str, clan_key: &str, amount: &str) {
+        let amount = match parse_amount(amount) {
+            Some(amount) => amount,
+            None => {
+                echo!(self.info(), "That's not a valid amount of silver.\r\n");
+                return;
+            }
+        };
+
+        if !self.clans.clans[clan_key].is_leader(player) {
+            echo!(
+                self.info(),
+                "Only clan leaders can withdraw from the treasury.\r\n"
+            );
+            return;
+        }
+
+        if !self.in_clan_hall(clan_key) {
+            return;
+        }
+
+        let clan = self.clans.clans.get_mut(clan_key).expect("Clan exists");
+        if clan.treasury < amount {
+            echo!(
+                self.info(),
+                "The treasury doesn't have that much silver.\r\n"
+            );
+            return;
+        }
+
+        clan.treasury -= amount;
+        self.clans.changed = true;
+        self.add_silver(amount, self.entity_id);
+
+        self.echo_to_clan(
+            clan_key,
+            &format!(
+                "{} withdraws {} silver coins from the clan's treasury.",
+                player, amount
+            ),
+        );
+    }
+
+    /// Echo a notice to all members of a clan who are in the realm.
+    fn echo_to_clan(&mut self, clan_key: &str, message: &str) {
+        let clan = match self.clans.clans.get(clan_key) {
+            Some(clan) => clan,
+            None => return,
+        };
+
+        for member in self.entity_world.all_players() {
+            if clan
+                .member(member.player_name().expect("Is a player"))
+                .is_some()
+            {
+                echo!(
+                    self.players.info(&member),
+                    "`M[{}] {}`^\r\n",
+                    clan.name,
+                    message
+                );
+            }
+        }
+    }
+
+    /// Explain why the current entity may not walk into a room, if it's a
+    /// clan hall they don't belong to.
+    pub fn clan_hall_refusal(&self, room_id: EntityId) -> Option<String> {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = myself.player_name()?;
+
+        let vnum = self
+            .entity_world
+            .entity_info(room_id)
+            .components()
+            .general
+            .vnum
+            .0;
+        let clan = self.clans.hall_owner(vnum)?;
+
+        if clan.member(player).is_some() || self.accounts.is_immortal(player) {
+            None
+        } else {
+            Some(format!(
+                "That is the hall of the {} clan; only its members may enter.",
+                clan.name
+            ))
+        }
+    }
+}
+
+// Clan editor for immortals; who can use it is checked by the dispatcher
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_clanedit(&mut self, words: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        if myself.player_name().is_none() {
+            echo!(self.info(), "Only players can edit clans.\r\n");
+            return;
+        }
+
+        match words {
+            [] | ["list"] => self.do_clanedit_list(),
+            ["create", name] => self.do_clanedit_create(name),
+            ["delete", name] => self.do_clanedit_delete(name),
+            [name, ref command @ ..] => {
+                let clan_key = name.to_lowercase();
+                if !self.clans.clans.contains_key(&clan_key) {
+                    echo!(self.info(), "There is no clan named like that.\r\n");
+                    return;
+                }
+
+                match command {
+                    [] | ["show"] => {
+                        self.do_clanedit_list_one(&clan_key);
+                    }
+                    ["leader", player] => self.do_clanedit_leader(&clan_key, player),
+                    ["remove", player] => self.do_clanedit_remove(&clan_key, player),
+                    ["ranks", ref ranks @ ..] => self.do_clanedit_ranks(&clan_key, ranks),
+                    ["hall", "none"] => self.do_clanedit_hall(&clan_key, None),
+                    ["hall", first, last] => {
+                        match (first.parse::<usize>(), last.parse::<usize>()) {
+                            (Ok(first), Ok(last)) if first <= last => {
+                                self.do_clanedit_hall(&clan_key, Some((first, last)))
+                            }
+                            _ => echo!(self.info(), "That's not a valid range of vnums.\r\n"),
+                        }
+                    }
+                    ["treasury", amount] => match amount.parse::<usize>() {
+                        Ok(amount) => {
+                            let clan = self.clans.clans.get_mut(&clan_key).expect("Checked above");
+                            clan.treasury = amount;
+                            self.clans.changed = true;
+                            echo!(self.info(), "Treasury set to {} silver coins.\r\n", amount);
+                        }
+                        Err(_) => echo!(self.info(), "That's not a valid amount of silver.\r\n"),
+                    },
+                    _ => self.echo_clanedit_syntax(),
+                }
+            }
+        }
+    }
+
+    fn echo_clanedit_syntax(&mut self) {
+        echo!(
+            self.info(),
+            "Syntax: '`Wclanedit`^', '`Wclanedit create <clan>`^', '`Wclanedit delete <clan>`^',\r\n\
+            '`Wclanedit <clan>`^', '`Wclanedit <clan> leader <player>`^', \
+            '`Wclanedit <clan> remove <player>`^',\r\n\
+            '`Wclanedit <clan> ranks <lowest> ... <highest>`^', \
+            '`Wclanedit <clan> hall <first vnum> <last vnum>`^',\r\n\
+            '`Wclanedit <clan> hall none`^', '`Wclanedit <clan> treasury <amount>`^'.\r\n"
+        );
+    }
+
+    fn do_clanedit_list(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let mut info = self.players.info(&myself);
+
+        if self.clans.clans.is_empty() {
+            echo!(
+                info,
+                "There are no clans. Use '`Wclanedit create <clan>`^' to make one.\r\n"
+            );
+            return;
+        }
+
+        echo!(info, "Clans:\r\n");
+        for clan in self.clans.clans.values() {
+            echo!(
+                info,
+                "  `W{:<14}`^ {} members, {} silver{}\r\n",
+                clan.name,
+                clan.members.len(),
+                clan.treasury,
+                match clan.hall {
+                    Some((first, last)) => format!(", hall {}-{}", first, last),
+                    None => String::new(),
+                }
+            );
+        }
+    }
+
+    fn do_clanedit_list_one(&mut self, clan_key: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let clan = &self.clans.clans[clan_key];
+        let mut info = self.players.info(&myself);
+
+        echo!(info, "Clan `W{}`^\r\n", clan.name);
+        echo!(info, "Ranks: {}\r\n", clan.ranks.join(", "));
+        match clan.hall {
+            Some((first, last)) => echo!(info, "Hall: rooms {} to {}\r\n", first, last),
+            None => echo!(info, "Hall: none\r\n"),
+        }
+        echo!(info, "Treasury: {} silver coins\r\n", clan.treasury);
+        echo!(info, "Members:\r\n");
+        for member in clan.members.values() {
+            echo!(
+                info,
+                "  {} ({})\r\n",
+                member.name,
+                clan.rank_title(member.rank)
+            );
+        }
+    }
+
+    fn do_clanedit_create(&mut self, name: &str) {
+        let clan_key = name.to_lowercase();
+
+        if !name.chars().all(|c| c.is_ascii_alphabetic()) || name.len() > 20 {
+            echo!(
+                self.info(),
+                "Clan names must be a single word of at most 20 letters.\r\n"
+            );
+            return;
+        } else if self.clans.clans.contains_key(&clan_key) {
+            echo!(self.info(), "That clan already exists.\r\n");
+            return;
+        }
+
+        self.clans.clans.insert(
+            clan_key,
+            Clan {
+                name: name.to_string(),
+                ranks: DEFAULT_RANKS.iter().map(|rank| rank.to_string()).collect(),
+                members: BTreeMap::new(),
+                hall: None,
+                treasury: 0,
+            },
+        );
+        self.clans.changed = true;
+
+        echo!(
+            self.info(),
+            "Created the `W{}`^ clan. Give it a leader with '`Wclanedit {} leader <player>`^'.\r\n",
+            name,
+            name
+        );
+    }
+
+    fn do_clanedit_delete(&mut self, name: &str) {
+        let clan_key = name.to_lowercase();
+
+        match self.clans.clans.remove(&clan_key) {
+            Some(clan) => {
+                self.clans
+                    .invitations
+                    .retain(|_player, invited_to| *invited_to != clan_key);
+                self.clans.changed = true;
+                echo!(self.info(), "Deleted the `W{}`^ clan.\r\n", clan.name);
+            }
+            None => echo!(self.info(), "There is no clan named like that.\r\n"),
+        }
+    }
+
+    fn do_clanedit_leader(&mut self, clan_key: &str, player: &str) {
+        let name = match self.accounts.account(player) {
+            Some(account) => account.name.clone(),
+            None => {
+                echo!(self.info(), "There is no character named like that.\r\n");
+                return;
+            }
+        };
+
+        self.clans.add_member(clan_key, &name, usize::MAX);
+        self.echo_to_clan(clan_key, &format!("{} now leads the clan.", name));
+
+        let clan_name = self.clans.clans[clan_key].name.clone();
+        echo!(
+            self.info(),
+            "{} now leads the `W{}`^ clan.\r\n",
+            name,
+            clan_name
+        );
+    }
+
+    fn do_clanedit_remove(&mut self, clan_key: &str, player: &str) {
+        if self.clans.clans[clan_key].member(player).is_none() {
+            echo!(self.info(), "There is no member named like that.\r\n");
+            return;
+        }
+
+        self.clans.forget_player(player);
+        echo!(self.info(), "Removed.\r\n");
+    }
+
+    fn do_clanedit_ranks(&mut self, clan_key: &str, ranks: &[&str]) {
+        if ranks.len() < 2 {
+            echo!(
+                self.info(),
+                "A clan needs at least two ranks, from lowest to highest.\r\n"
+            );
+            return;
+        }
+
+        let clan = self
+            .clans
+            .clans
+            .get_mut(clan_key)
+            .expect("Checked by caller");
+        let old_leader_rank = clan.leader_rank();
+        clan.ranks = ranks.iter().map(|rank| rank.to_string()).collect();
+        let new_leader_rank = clan.leader_rank();
+
+        // Leaders stay leaders, and nobody else becomes one
+        for member in clan.members.values_mut() {
+            member.rank = if member.rank >= old_leader_rank {
+                new_leader_rank
+            } else {
+                member.rank.min(new_leader_rank - 1)
+            };
+        }
+        self.clans.changed = true;
+
+        echo!(self.info(), "Ranks set to: {}\r\n", ranks.join(", "));
+    }
+
+    fn do_clanedit_hall(&mut self, clan_key: &str, hall: Option<(usize, usize)>) {
+        let clan = self
+            .clans
+            .clans
+            .get_mut(clan_key)
+            .expect("Checked by caller");
+        clan.hall = hall;
+        self.clans.changed = true;
+
+        match hall {
+            Some((first, last)) => echo!(
+                self.info(),
+                "The clan hall is now rooms {} to {}.\r\n",
+                first,
+                last
+            ),
+            None => echo!(self.info(), "The clan no longer has a hall.\r\n"),
+        }
+    }
+}
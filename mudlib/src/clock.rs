@@ -0,0 +1,344 @@
+//! The game clock and calendar. A game hour lasts one real minute, so a day
+//! in the realm goes by in 24 minutes; like in Dawn of Time, there are 7 days
+//! in a week, 35 days in a month and 17 months in a year.
+//!
+//! The time is worked out from the real time, so it carries on across reboots
+//! without being saved. The calendar starts at an epoch, which can be set
+//! with a line like `epoch 1600000000` (in seconds since the Unix epoch) in
+//! the optional `data/clock.txt` file.
+//!
+//! Shops keep to their opening hours by the clock, and mobprogs with an hour
+//! trigger run when their hour comes; a shopkeeper can use one to lock up
+//! their shop at night. Players outdoors see the sun rise and set, and some
+//! rooms and mobiles look different at night; see `crate::day_night`.
+//!
+//! Months fall into seasons, which the weather follows, and the moon goes
+//! through its phases every 28 days. Mobprogs can check both with `if season
+//! winter` or `if moon full`, e.g. for a werewolf that only prowls under a
+//! full moon.
+
+use crate::{
+    agent::EntityAgent, echo, files::Files, state::unix_time, weather::is_outdoors,
+    world::TimeOfDay, WorldState,
+};
+
+const CLOCK_FILE: &str = "data/clock.txt";
+
+const SECONDS_PER_HOUR: u64 = 60;
+const HOURS_PER_DAY: u64 = 24;
+const DAYS_PER_WEEK: u64 = 7;
+const DAYS_PER_MONTH: u64 = 35;
+const MONTHS_PER_YEAR: u64 = 17;
+/// Days from one new moon to the next
+const DAYS_PER_MOON: u64 = 28;
+
+/// The hours the sun rises and sets at
+const SUNRISE: u8 = 6;
+const SUNSET: u8 = 19;
+
+static DAY_NAMES: &[&str] = &[
+    "the Moon",
+    "the Bull",
+    "Deception",
+    "Thunder",
+    "Freedom",
+    "the Great Gods",
+    "the Sun",
+];
+
+static MONTH_NAMES: &[&str] = &[
+    "Winter",
+    "the Winter Wolf",
+    "the Frost Giant",
+    "the Old Forces",
+    "the Grand Struggle",
+    "the Spring",
+    "Nature",
+    "Futility",
+    "the Dragon",
+    "the Sun",
+    "the Heat",
+    "the Battle",
+    "the Dark Shades",
+    "the Shadows",
+    "the Long Shadows",
+    "the Ancient Darkness",
+    "the Great Evil",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Season {
+    Winter,
+    Spring,
+    Summer,
+    Autumn,
+}
+
+const SEASONS: &[(Season, &str)] = &[
+    (Season::Winter, "winter"),
+    (Season::Spring, "spring"),
+    (Season::Summer, "summer"),
+    (Season::Autumn, "autumn"),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MoonPhase {
+    New,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    Full,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+
+/// Moon phases in order from the new moon, with their names in mobprogs
+const MOON_PHASES: &[(MoonPhase, &str)] = &[
+    (MoonPhase::New, "new"),
+    (MoonPhase::WaxingCrescent, "waxing_crescent"),
+    (MoonPhase::FirstQuarter, "first_quarter"),
+    (MoonPhase::WaxingGibbous, "waxing_gibbous"),
+    (MoonPhase::Full, "full"),
+    (MoonPhase::WaningGibbous, "waning_gibbous"),
+    (MoonPhase::LastQuarter, "last_quarter"),
+    (MoonPhase::WaningCrescent, "waning_crescent"),
+];
+
+impl Season {
+    /// The season of a month, counting from 0 like `GameTime` does.
+    pub fn of_month(month: u8) -> Self {
+        match month {
+            4..=7 => Season::Spring,
+            8..=11 => Season::Summer,
+            12..=14 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        SEASONS
+            .iter()
+            .find(|(season, _name)| *season == self)
+            .map(|(_season, name)| *name)
+            .unwrap()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        SEASONS
+            .iter()
+            .find(|(_season, season_name)| season_name.eq_ignore_ascii_case(name))
+            .map(|(season, _name)| *season)
+    }
+}
+
+impl MoonPhase {
+    pub fn from_name(name: &str) -> Option<Self> {
+        MOON_PHASES
+            .iter()
+            .find(|(_phase, phase_name)| phase_name.eq_ignore_ascii_case(name))
+            .map(|(phase, _name)| *phase)
+    }
+
+    /// How the moon looks in this phase, e.g. "a waxing crescent".
+    pub fn describe(self) -> &'static str {
+        match self {
+            MoonPhase::New => "new",
+            MoonPhase::WaxingCrescent => "a waxing crescent",
+            MoonPhase::FirstQuarter => "in its first quarter",
+            MoonPhase::WaxingGibbous => "waxing gibbous",
+            MoonPhase::Full => "full",
+            MoonPhase::WaningGibbous => "waning gibbous",
+            MoonPhase::LastQuarter => "in its last quarter",
+            MoonPhase::WaningCrescent => "a waning crescent",
+        }
+    }
+}
+
+pub(crate) struct Clock {
+    /// When the calendar starts, in seconds since the Unix epoch
+    epoch: u64,
+}
+
+/// A moment on the game calendar. Days and months count from 0.
+#[derive(Clone, Copy)]
+pub(crate) struct GameTime {
+    pub hour: u8,
+    pub day: u8,
+    pub month: u8,
+    pub year: u64,
+}
+
+impl Clock {
+    pub fn load(files: &dyn Files) -> Self {
+        let contents = match files.read_file(CLOCK_FILE) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => panic!("Could not read {}: {}", CLOCK_FILE, err),
+        };
+
+        let mut epoch = 0;
+
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["epoch", seconds] => {
+                    epoch = seconds
+                        .parse()
+                        .unwrap_or_else(|_| panic!("Invalid epoch {} in {}", seconds, CLOCK_FILE))
+                }
+                _ => panic!(
+                    "Lines in {} should look like 'epoch <seconds>', not: {}",
+                    CLOCK_FILE, line
+                ),
+            }
+        }
+
+        Clock { epoch }
+    }
+
+    /// The time on the game calendar right now.
+    pub fn now(&self) -> GameTime {
+        let hours = unix_time().saturating_sub(self.epoch) / SECONDS_PER_HOUR;
+        let days = hours / HOURS_PER_DAY;
+        let months = days / DAYS_PER_MONTH;
+
+        GameTime {
+            hour: (hours % HOURS_PER_DAY) as u8,
+            day: (days % DAYS_PER_MONTH) as u8,
+            month: (months % MONTHS_PER_YEAR) as u8,
+            year: months / MONTHS_PER_YEAR + 1,
+        }
+    }
+
+    /// The current hour of the game day, from 0 to 23.
+    pub fn hour(&self) -> u8 {
+        self.now().hour
+    }
+}
+
+impl GameTime {
+    /// Days since the calendar started.
+    fn days(&self) -> u64 {
+        (self.year - 1) * MONTHS_PER_YEAR * DAYS_PER_MONTH
+            + self.month as u64 * DAYS_PER_MONTH
+            + self.day as u64
+    }
+
+    fn day_name(&self) -> &'static str {
+        DAY_NAMES[(self.days() % DAYS_PER_WEEK) as usize]
+    }
+
+    pub fn season(&self) -> Season {
+        Season::of_month(self.month)
+    }
+
+    pub fn moon_phase(&self) -> MoonPhase {
+        let day_of_moon = self.days() % DAYS_PER_MOON;
+        let phase = day_of_moon * MOON_PHASES.len() as u64 / DAYS_PER_MOON;
+        MOON_PHASES[phase as usize].0
+    }
+}
+
+/// Whether an hour is between an opening and a closing hour; like in Dawn of
+/// Time, the closing hour itself is still open. Hours past midnight work too,
+/// as with a tavern open from 18 to 2.
+pub(crate) fn is_open(hour: u8, open_hour: u8, close_hour: u8) -> bool {
+    if open_hour <= close_hour {
+        hour >= open_hour && hour <= close_hour
+    } else {
+        hour >= open_hour || hour <= close_hour
+    }
+}
+
+/// Whether the sun is up at an hour.
+pub(crate) fn is_daylight(hour: u8) -> bool {
+    (SUNRISE..SUNSET).contains(&hour)
+}
+
+/// Whether it is day or night at an hour.
+pub(crate) fn time_of_day(hour: u8) -> TimeOfDay {
+    if is_daylight(hour) {
+        TimeOfDay::Day
+    } else {
+        TimeOfDay::Night
+    }
+}
+
+fn describe_hour(hour: u8) -> String {
+    match hour {
+        0 => "midnight".to_string(),
+        12 => "noon".to_string(),
+        1..=11 => format!("{} o'clock in the morning", hour),
+        13..=17 => format!("{} o'clock in the afternoon", hour - 12),
+        _ => format!("{} o'clock in the evening", hour - 12),
+    }
+}
+
+/// A number with its English ordinal suffix, e.g. "22nd".
+fn ordinal(number: u64) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", number, suffix)
+}
+
+/// Tell players outdoors that the sun rose or set, or that it's noon or
+/// midnight, when such an hour starts.
+pub(crate) fn announce_hour(world_state: &mut WorldState, hour: u8) {
+    let full_moon = world_state.clock.now().moon_phase() == MoonPhase::Full;
+    let message = match hour {
+        0 if full_moon => "The full moon is high in the sky; it is midnight.",
+        0 => "The moon is high in the sky; it is midnight.",
+        SUNRISE => "The sun rises in the east.",
+        12 => "The sun is high in the sky; it is noon.",
+        SUNSET if full_moon => "The sun slowly disappears in the west, and a full moon rises.",
+        SUNSET => "The sun slowly disappears in the west.",
+        _ => return,
+    };
+
+    for player in world_state.entity_world.all_players() {
+        if is_outdoors(&player.room()) {
+            echo!(world_state.players.info(&player), "{}\r\n", message);
+        }
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_time(&mut self) {
+        let time = self.clock.now();
+        let mut info = self.info();
+
+        echo!(
+            info,
+            "It is {}, on the Day of {}; {}.\r\n",
+            describe_hour(time.hour),
+            time.day_name(),
+            if is_daylight(time.hour) {
+                "the sun is up"
+            } else {
+                "the sun is down"
+            }
+        );
+        echo!(
+            info,
+            "It is the {} day of the Month of {}, in the year {}.\r\n",
+            ordinal(time.day as u64 + 1),
+            MONTH_NAMES[time.month as usize],
+            time.year
+        );
+        echo!(
+            info,
+            "It is {}, and the moon is {}.\r\n",
+            time.season().name(),
+            time.moon_phase().describe()
+        );
+    }
+}
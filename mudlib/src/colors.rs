@@ -73,6 +73,32 @@ pub fn colorize(text: &str) -> Cow<'_, str> {
     Cow::Owned(buffer)
 }
 
+/// Remove color codes, for players who don't want colors.
+///
+/// Codes that stand for text (like "`1" for a newline) are still replaced.
+pub fn decolorize(text: &str) -> Cow<'_, str> {
+    if !text.contains('`') && !text.contains('\x1b') {
+        return Cow::Borrowed(text);
+    }
+
+    let colorized = colorize(text);
+    let mut buffer = String::with_capacity(colorized.len());
+    let mut remaining: &str = &colorized;
+
+    while let Some(escape) = remaining.find('\x1b') {
+        buffer.push_str(&remaining[..escape]);
+        remaining = &remaining[escape..];
+        let end = remaining
+            .find('m')
+            .map(|end| end + 1)
+            .unwrap_or(remaining.len());
+        remaining = &remaining[end..];
+    }
+    buffer.push_str(remaining);
+
+    Cow::Owned(buffer)
+}
+
 pub fn recolor<'a>(color: &str, text: &'a str) -> Cow<'a, str> {
     if text.contains('`') {
         Cow::Owned(text.replace("`^", color).replace("`x", color))
@@ -0,0 +1,662 @@
+//! The table of every command, which what players, mobiles and mobprogs type
+//! is looked up in.
+//!
+//! Each command has a name, how many of its letters must be typed at least,
+//! the position one must be in to use it, the trust it needs (unless
+//! `data/command_trust.txt` says otherwise, see the `trust` module), and a
+//! handler that is given the words after the command.
+//!
+//! Like in ROM, commands can be abbreviated, and the table is searched in
+//! order, so its first commands win short abbreviations: `n` is `north`, `l`
+//! is `look` and `g` is `get`. A whole name always wins over an abbreviation
+//! of another command, and commands one isn't trusted with are skipped, so
+//! players never run into immortal commands by accident. Dangerous commands,
+//! like `delete`, have to be typed out.
+//!
+//! Whatever isn't in the table is tried as an exit to go through, and then
+//! as a social.
+
+use crate::{
+    agent::EntityAgent, broadcast::Reach, components::Position, discipline::Punishment, echo,
+    entity::EntityInfo, trust::Trust, vitals::position, vnum_search::TemplateKind,
+};
+
+/// What runs a command, with the words typed after it.
+pub(crate) type Handler = fn(&mut EntityAgent, &[&str]);
+
+pub(crate) struct Command {
+    pub name: &'static str,
+    /// How many letters of the name have to be typed at least
+    pub min_letters: usize,
+    /// The position one has to be in at least
+    pub position: Position,
+    /// The trust needed, unless changed in `data/command_trust.txt`
+    pub trust: Trust,
+    /// What the command is for, listed by `wizhelp` for trusted commands
+    pub description: &'static str,
+    /// Takes a password, which is left out wherever commands are logged or
+    /// shown to others
+    pub sensitive: bool,
+    pub handler: Handler,
+}
+
+/// A command anyone can use in any position, by any abbreviation.
+const fn command(name: &'static str, handler: Handler) -> Command {
+    Command {
+        name,
+        min_letters: 1,
+        position: Position::Sleeping,
+        trust: Trust::Player,
+        description: "",
+        sensitive: false,
+        handler,
+    }
+}
+
+impl Command {
+    /// Only abbreviate the command to at least some letters.
+    const fn letters(mut self, min_letters: usize) -> Self {
+        self.min_letters = min_letters;
+        self
+    }
+
+    /// Never abbreviate the command.
+    const fn whole(mut self) -> Self {
+        self.min_letters = self.name.len();
+        self
+    }
+
+    const fn resting(mut self) -> Self {
+        self.position = Position::Resting;
+        self
+    }
+
+    const fn standing(mut self) -> Self {
+        self.position = Position::Standing;
+        self
+    }
+
+    const fn trust(mut self, trust: Trust, description: &'static str) -> Self {
+        self.trust = trust;
+        self.description = description;
+        self
+    }
+
+    const fn sensitive(mut self) -> Self {
+        self.sensitive = true;
+        self
+    }
+}
+
+/// Ask for the right words after a command.
+fn syntax(agent: &mut EntityAgent, syntax: &str) {
+    echo!(agent.info(), "Syntax: {}.\r\n", syntax);
+}
+
+/// Commands handled by the server itself, before they get here.
+fn server_only(agent: &mut EntityAgent, _args: &[&str]) {
+    echo!(agent.info(), "Only the server can do that.\r\n");
+}
+
+pub(crate) static COMMANDS: &[Command] = &[
+    // Moving around, and what is done the most, comes first
+    command("north", |agent, _| {
+        agent.do_move("north");
+    })
+    .standing(),
+    command("east", |agent, _| {
+        agent.do_move("east");
+    })
+    .standing(),
+    command("south", |agent, _| {
+        agent.do_move("south");
+    })
+    .standing(),
+    command("west", |agent, _| {
+        agent.do_move("west");
+    })
+    .standing(),
+    command("up", |agent, _| {
+        agent.do_move("up");
+    })
+    .standing(),
+    command("down", |agent, _| {
+        agent.do_move("down");
+    })
+    .standing(),
+    command("look", look).resting(),
+    command("get", |agent, args| match *args {
+        [] => agent.do_get(None, false),
+        ["all"] => agent.do_get_all(false),
+        [item] => agent.do_get(Some(item), false),
+        [item, "forcefully"] => agent.do_get(Some(item), true),
+        [item, container] | [item, "from", container] => agent.do_get_from(item, container, false),
+        [item, container, "forcefully"] | [item, "from", container, "forcefully"] => {
+            agent.do_get_from(item, container, true)
+        }
+        _ => syntax(agent, "'`Wget <item> [from] [container]`^'"),
+    })
+    .resting(),
+    command("inventory", |agent, _| agent.do_inventory()),
+    command("exits", |agent, _| agent.do_exits()).resting(),
+    command("scan", |agent, _| agent.do_scan()).resting(),
+    command("say", say).resting(),
+    command("tell", |agent, args| match args {
+        [target, message @ ..] => agent.do_tell(target, &message.join(" ")),
+        [] => syntax(agent, "'`Wtell <player> <message>`^'"),
+    }),
+    command("drop", |agent, args| match *args {
+        [] => agent.do_drop(None, false),
+        ["all"] => agent.do_drop_all(false),
+        [item] => agent.do_drop(Some(item), false),
+        [item, "forcefully"] => agent.do_drop(Some(item), true),
+        _ => syntax(agent, "'`Wdrop <item>`^'"),
+    })
+    .resting(),
+    command("put", |agent, args| match *args {
+        [item, container] | [item, "into", container] => agent.do_put_into(item, container, false),
+        [item, container, "forcefully"] | [item, "into", container, "forcefully"] => {
+            agent.do_put_into(item, container, true)
+        }
+        _ => syntax(agent, "'`Wput <item> [into] <container>`^'"),
+    })
+    .resting(),
+    command("give", |agent, args| match *args {
+        [item, target] | [item, "to", target] => agent.do_give(item, target, false),
+        [item, target, "forcefully"] | [item, "to", target, "forcefully"] => {
+            agent.do_give(item, target, true)
+        }
+        _ => syntax(agent, "`Wgive <item> [to] <target>`^"),
+    })
+    .resting(),
+    command("open", |agent, args| match *args {
+        [target] => agent.do_open(target),
+        _ => syntax(agent, "'`Wopen <door or container>`^'"),
+    })
+    .resting(),
+    command("close", |agent, args| match *args {
+        [target] => agent.do_close(target),
+        _ => syntax(agent, "'`Wclose <door or container>`^'"),
+    })
+    .resting(),
+    command("lock", |agent, args| match *args {
+        [target] => agent.do_lock(target),
+        _ => syntax(agent, "'`Wlock <door or container>`^'"),
+    })
+    .resting(),
+    command("unlock", |agent, args| match *args {
+        [target] => agent.do_unlock(target),
+        _ => syntax(agent, "'`Wunlock <door or container>`^'"),
+    })
+    .resting(),
+    command("rest", |agent, _| agent.do_rest()),
+    command("sleep", |agent, _| agent.do_sleep()),
+    command("stand", |agent, _| agent.do_stand()),
+    command("wake", |agent, _| agent.do_stand()),
+    command("examine", look).resting(),
+    command("emote", |agent, args| agent.do_emote(&args.join(" "))).resting(),
+    command("pmote", |agent, args| match args {
+        [target, message @ ..] => agent.do_pmote(target, &message.join(" ")),
+        [] => syntax(agent, "'`Wpmote <target> <message>`^'"),
+    })
+    .resting(),
+    command("sayto", |agent, args| match args {
+        [target, message @ ..] => agent.do_say_to(target, &message.join(" ")),
+        [] => syntax(agent, "'`Wsayto <target> <message>`^'"),
+    })
+    .resting(),
+    command("rsay", say).resting(),
+    command("follow", |agent, args| match *args {
+        [target] => agent.do_follow(target),
+        _ => syntax(agent, "'`Wfollow <target>`^'"),
+    })
+    .resting(),
+    command("unfollow", |agent, _| agent.do_unfollow()).resting(),
+    command("recall", |agent, args| {
+        agent.do_recall(args.first().copied())
+    })
+    .standing(),
+    command("track", |agent, args| match *args {
+        [target] => agent.do_track(target),
+        _ => echo!(agent.info(), "Track whom?\r\n"),
+    })
+    .standing(),
+    command("map", |agent, _| agent.do_map()).resting(),
+    command("help", |agent, args| agent.do_help(&args.join(" "))),
+    command("time", |agent, _| agent.do_time()),
+    command("weather", |agent, _| agent.do_weather()).resting(),
+    command("areas", |agent, _| agent.do_areas()),
+    // Eating, drinking and lights
+    command("eat", |agent, args| match *args {
+        [item] => agent.do_eat(item, false),
+        [item, "forcefully"] => agent.do_eat(item, true),
+        _ => echo!(agent.info(), "Eat what?\r\n"),
+    })
+    .resting(),
+    command("drink", |agent, args| match *args {
+        [] => agent.do_drink(None),
+        [item, ..] => agent.do_drink(Some(item)),
+    })
+    .resting(),
+    command("fill", |agent, args| match *args {
+        [item] => agent.do_fill(item),
+        _ => echo!(agent.info(), "Fill what?\r\n"),
+    })
+    .resting(),
+    command("light", |agent, args| match *args {
+        [item] => agent.do_light(item),
+        _ => echo!(agent.info(), "Light what?\r\n"),
+    })
+    .resting(),
+    command("extinguish", |agent, args| match *args {
+        [item] => agent.do_extinguish(item),
+        _ => echo!(agent.info(), "Extinguish what?\r\n"),
+    })
+    .resting(),
+    // Shops, stalls, the bank and the auction
+    command("list", |agent, _| agent.do_list()).resting(),
+    command("wares", |agent, _| agent.do_list()).resting(),
+    command("buy", |agent, args| match *args {
+        [item] => agent.do_buy(item),
+        [pet, name] => agent.do_buy_pet(pet, Some(name)),
+        _ => echo!(agent.info(), "Buy what?\r\n"),
+    })
+    .resting(),
+    command("sell", |agent, args| match *args {
+        [item] => agent.do_sell(item),
+        _ => echo!(agent.info(), "Sell what?\r\n"),
+    })
+    .resting(),
+    command("value", |agent, args| match *args {
+        [item] => agent.do_value(item),
+        _ => echo!(agent.info(), "Value what?\r\n"),
+    })
+    .resting(),
+    command("haggle", |agent, _| agent.do_haggle()).resting(),
+    command("repair", |agent, args| match *args {
+        [item] => agent.do_repair(item),
+        _ => echo!(agent.info(), "Repair what?\r\n"),
+    })
+    .resting(),
+    command("browse", |agent, _| agent.do_browse()).resting(),
+    command("stall", |agent, args| agent.do_stall(args)).resting(),
+    command("balance", |agent, _| agent.do_balance()).resting(),
+    command("deposit", |agent, args| match *args {
+        [amount] => agent.do_deposit(amount),
+        _ => bank_syntax(agent),
+    })
+    .resting(),
+    command("withdraw", |agent, args| match *args {
+        [amount] => agent.do_withdraw(amount),
+        _ => bank_syntax(agent),
+    })
+    .resting(),
+    command("bank", |agent, args| match *args {
+        ["transfer", player, amount] => agent.do_bank_transfer(player, amount),
+        _ => bank_syntax(agent),
+    })
+    .resting(),
+    command("auction", |agent, args| agent.do_auction(args)),
+    command("bid", |agent, args| match *args {
+        [amount] => agent.do_bid(amount),
+        _ => syntax(agent, "'`Wbid <amount>`^'"),
+    }),
+    // Talking to others, and keeping in touch
+    command("messages", |agent, args| match *args {
+        ["clear"] => agent.do_messages(true),
+        _ => agent.do_messages(false),
+    }),
+    command("channels", |agent, _| agent.do_channels()),
+    command("gossip", |agent, args| {
+        agent.do_channel("gossip", &args.join(" "))
+    }),
+    command("clantalk", |agent, args| {
+        agent.do_channel("clan", &args.join(" "))
+    }),
+    command("ct", |agent, args| {
+        agent.do_channel("clan", &args.join(" "))
+    }),
+    command("clan", |agent, args| agent.do_clan(args)),
+    command("note", |agent, args| agent.do_note(args)),
+    command("boards", |agent, args| {
+        agent.do_board(args.first().copied())
+    })
+    .letters(5),
+    command("mail", |agent, args| agent.do_mail(args)),
+    command("socials", socials).letters(6),
+    command("emotes", socials),
+    // Settings
+    command("config", |agent, args| match *args {
+        [] => agent.do_config(None, None),
+        [toggle] => agent.do_config(Some(toggle), None),
+        [toggle, "on"] => agent.do_config(Some(toggle), Some(true)),
+        [toggle, "off"] => agent.do_config(Some(toggle), Some(false)),
+        _ => syntax(agent, "'`Wconfig <setting> [on|off]`^'"),
+    }),
+    command("prompt", |agent, args| agent.do_prompt(&args.join(" "))),
+    command("alias", |agent, args| match args {
+        [] => agent.do_alias(None, ""),
+        [alias, expansion @ ..] => agent.do_alias(Some(alias), &expansion.join(" ")),
+    }),
+    command("unalias", |agent, args| match *args {
+        [alias] => agent.do_unalias(alias),
+        _ => syntax(agent, "'`Wunalias <alias>`^'"),
+    }),
+    command("delete", |agent, args| agent.do_delete(args))
+        .whole()
+        .sensitive(),
+    command("die", |agent, _| agent.do_die()).whole(),
+    // Building
+    command("mob", |agent, args| agent.do_mob(args)).trust(Trust::Builder, "Run a mobprog command"),
+    command("mq", |agent, args| match args {
+        [ticks, command @ ..] => agent.do_queue(ticks, command.join(" ")),
+        [] => syntax(agent, "'`Wmq <ticks> <command>`^'"),
+    })
+    .whole()
+    .trust(Trust::Builder, "Queue a delayed command"),
+    command("aedit", |agent, args| agent.do_aedit(args))
+        .trust(Trust::Builder, "Create and change areas"),
+    command("asave", |agent, args| agent.do_asave(args))
+        .trust(Trust::Builder, "Save areas to their files"),
+    command("mpedit", |agent, args| agent.do_mpedit(args))
+        .trust(Trust::Builder, "Write and install mobprogs"),
+    command("ofind", |agent, args| {
+        agent.do_find_vnums(TemplateKind::Object, &args.join(" "))
+    })
+    .trust(Trust::Builder, "Find objects by name"),
+    command("mfind", |agent, args| {
+        agent.do_find_vnums(TemplateKind::Mobile, &args.join(" "))
+    })
+    .trust(Trust::Builder, "Find mobiles by name"),
+    command("rfind", |agent, args| {
+        agent.do_find_vnums(TemplateKind::Room, &args.join(" "))
+    })
+    .trust(Trust::Builder, "Find rooms by name"),
+    command("vnum", |agent, args| match *args {
+        ["gap", area] => agent.do_vnum_gap(area),
+        _ => syntax(agent, "'`Wvnum gap <area>`^'"),
+    })
+    .whole()
+    .trust(Trust::Builder, "Find free vnums in an area"),
+    command("validate", |agent, args| match *args {
+        [] => agent.do_validate(None),
+        [area] => agent.do_validate(Some(area)),
+        _ => syntax(agent, "'`Wvalidate [area]`^'"),
+    })
+    .whole()
+    .trust(Trust::Builder, "Check areas for broken resets and exits"),
+    // Immortals
+    command("force", |agent, args| match args {
+        [target, words @ ..] if !words.is_empty() => agent.do_force(target, words),
+        _ => syntax(
+            agent,
+            "'`Wforce <target> <command>`^' or '`Wforce all <command>`^'",
+        ),
+    })
+    .trust(Trust::Immortal, "Make others run a command"),
+    command("snoop", |agent, args| agent.do_snoop(args.first().copied()))
+        .trust(Trust::Immortal, "See what a player sees"),
+    command("immtalk", |agent, args| {
+        agent.do_channel("immortal", &args.join(" "))
+    })
+    .trust(Trust::Immortal, "Talk with other immortals"),
+    command("log", |agent, args| agent.do_log(args))
+        .trust(Trust::Immortal, "Choose what the log shows"),
+    command("history", |agent, args| agent.do_history(args))
+        .whole()
+        .trust(Trust::Immortal, "See what a player did lately"),
+    command("echo", |agent, args| {
+        agent.do_broadcast(Reach::Room, &args.join(" "))
+    })
+    .trust(Trust::Immortal, "Send a message to the room"),
+    command("zecho", |agent, args| {
+        agent.do_broadcast(Reach::Area, &args.join(" "))
+    })
+    .trust(Trust::Immortal, "Send a message to the area"),
+    command("gecho", |agent, args| {
+        agent.do_broadcast(Reach::Realm, &args.join(" "))
+    })
+    .trust(Trust::Immortal, "Send a message to everyone"),
+    command("wizinvis", |agent, args| {
+        agent.do_wizinvis(args.first().copied())
+    })
+    .trust(Trust::Immortal, "Hide from lesser players"),
+    command("holylight", |agent, _| agent.do_holylight())
+        .trust(Trust::Immortal, "See invisible immortals"),
+    command("goto", |agent, args| match *args {
+        [location] => agent.do_goto(location),
+        _ => goto_syntax(agent),
+    })
+    .trust(Trust::Immortal, "Go to a room or someone"),
+    command("transfer", |agent, args| match *args {
+        [player] => agent.do_transfer(player, None),
+        [player, location] => agent.do_transfer(player, Some(location)),
+        _ => goto_syntax(agent),
+    })
+    .trust(Trust::Immortal, "Bring or send a player"),
+    command("at", |agent, args| match args {
+        [location, command @ ..] if !command.is_empty() => agent.do_at(location, command),
+        _ => goto_syntax(agent),
+    })
+    .trust(Trust::Immortal, "Run a command elsewhere"),
+    command("poofin", |agent, args| agent.do_poof(true, &args.join(" ")))
+        .trust(Trust::Immortal, "Set your arrival message"),
+    command("poofout", |agent, args| {
+        agent.do_poof(false, &args.join(" "))
+    })
+    .trust(Trust::Immortal, "Set your leaving message"),
+    command("slay", |agent, args| match *args {
+        [target] => agent.do_slay(target),
+        _ => discipline_syntax(agent),
+    })
+    .whole()
+    .trust(Trust::Immortal, "Kill someone right away"),
+    command("restore", |agent, args| match *args {
+        [target] => agent.do_restore(target),
+        _ => discipline_syntax(agent),
+    })
+    .trust(Trust::Immortal, "Bring back the dead"),
+    command("freeze", |agent, args| match *args {
+        [player] => agent.do_punish(player, Punishment::Freeze),
+        _ => discipline_syntax(agent),
+    })
+    .whole()
+    .trust(Trust::Immortal, "Stop a player doing anything"),
+    command("mute", |agent, args| match *args {
+        [player] => agent.do_punish(player, Punishment::Mute),
+        _ => discipline_syntax(agent),
+    })
+    .trust(Trust::Immortal, "Keep a player off channels"),
+    command("silence", |agent, args| match *args {
+        [player] => agent.do_punish(player, Punishment::Mute),
+        _ => discipline_syntax(agent),
+    })
+    .trust(Trust::Immortal, "Same as mute"),
+    command("load", |agent, args| match *args {
+        [kind, vnum] => agent.do_load(kind, vnum),
+        _ => syntax(agent, "'`Wload mob <vnum>`^' or '`Wload obj <vnum>`^'"),
+    })
+    .trust(Trust::Immortal, "Create mobiles and objects"),
+    command("purge", |agent, args| agent.do_purge(args.first().copied()))
+        .whole()
+        .trust(Trust::Immortal, "Remove mobiles and objects"),
+    command("stat", |agent, args| stat(agent, "stat", args))
+        .trust(Trust::Immortal, "Inspect anything"),
+    command("mstat", |agent, args| stat(agent, "mstat", args))
+        .trust(Trust::Immortal, "Inspect a mobile or player"),
+    command("ostat", |agent, args| stat(agent, "ostat", args))
+        .trust(Trust::Immortal, "Inspect an object"),
+    command("rstat", |agent, args| stat(agent, "rstat", args))
+        .trust(Trust::Immortal, "Inspect a room"),
+    command("clanedit", |agent, args| agent.do_clanedit(args))
+        .trust(Trust::Immortal, "Create and change clans"),
+    command("vnums", |agent, args| agent.do_vnums(args))
+        .whole()
+        .trust(Trust::Immortal, "Assign vnums to builders"),
+    command("snapshot", |agent, args| agent.do_snapshot(args))
+        .whole()
+        .trust(Trust::Immortal, "Save or restore the world"),
+    command("snapshots", |agent, args| agent.do_snapshot(args))
+        .whole()
+        .trust(Trust::Immortal, "Same as snapshot"),
+    command("pulses", |agent, _| agent.do_pulses())
+        .trust(Trust::Immortal, "See how long systems take"),
+    command("perf", |agent, args| agent.do_perf(args))
+        .trust(Trust::Immortal, "See where the time of the game goes"),
+    command("memory", |agent, args| agent.do_memory(args))
+        .trust(Trust::Immortal, "Count entities and strings"),
+    command("worldstat", |agent, args| agent.do_worldstat(args))
+        .whole()
+        .trust(Trust::Immortal, "Find leaks in areas"),
+    command("wizhelp", |agent, _| agent.do_wizhelp()),
+    // Implementors
+    command("trust", |agent, args| match *args {
+        [player] => agent.do_trust(player, None),
+        [player, level] => agent.do_trust(player, Some(level)),
+        _ => syntax(agent, "'`Wtrust <player> [level]`^'"),
+    })
+    .whole()
+    .trust(Trust::Implementor, "Change a player's trust"),
+    command("set", |agent, args| agent.do_set(args))
+        .whole()
+        .trust(Trust::Implementor, "Change fields of anything"),
+    command("reload", |agent, args| {
+        agent.do_reload(args.first().copied(), args.get(1).copied())
+    })
+    .whole()
+    .trust(Trust::Implementor, "Read data files again"),
+    command("restart", server_only)
+        .whole()
+        .trust(Trust::Implementor, "Restart the server"),
+    command("shutdown", server_only)
+        .whole()
+        .trust(Trust::Implementor, "Shut the server down"),
+    command("reboot", server_only)
+        .whole()
+        .trust(Trust::Implementor, "Restart after a warning"),
+    command("wizlock", server_only)
+        .whole()
+        .trust(Trust::Implementor, "Only let immortals log in"),
+    command("newlock", server_only)
+        .whole()
+        .trust(Trust::Implementor, "Stop new characters"),
+    command("panic", |_, _| panic!("Oh no! I panicked!"))
+        .whole()
+        .trust(Trust::Implementor, "Crash the game on purpose"),
+];
+
+fn look(agent: &mut EntityAgent, args: &[&str]) {
+    match *args {
+        [] => agent.do_look(),
+        [target] | ["at", target] => agent.do_look_at(target),
+        _ => echo!(agent.info(), "Syntax: '`Wlook <word>`^'\r\n"),
+    }
+}
+
+fn say(agent: &mut EntityAgent, args: &[&str]) {
+    match args {
+        [target, message @ ..] if target.starts_with('>') => {
+            agent.do_say_to(&target[1..], &message.join(" "))
+        }
+        message => agent.do_say(&message.join(" ")),
+    }
+}
+
+fn socials(agent: &mut EntityAgent, args: &[&str]) {
+    agent.do_socials(args.first().copied());
+}
+
+fn stat(agent: &mut EntityAgent, kind: &str, args: &[&str]) {
+    agent.do_stat(kind, args.first().copied());
+}
+
+fn bank_syntax(agent: &mut EntityAgent) {
+    syntax(
+        agent,
+        "'`Wbalance`^', '`Wdeposit <amount>`^', '`Wwithdraw <amount>`^', \
+        '`Wbank transfer <player> <amount>`^'",
+    );
+}
+
+fn goto_syntax(agent: &mut EntityAgent) {
+    syntax(
+        agent,
+        "'`Wgoto <location>`^', '`Wtransfer <player> [location]`^', \
+        '`Wat <location> <command>`^'",
+    );
+}
+
+fn discipline_syntax(agent: &mut EntityAgent) {
+    syntax(
+        agent,
+        "'`Wslay <target>`^', '`Wrestore <target>`^', '`Wrestore all`^', \
+        '`Wfreeze <player>`^', '`Wmute <player>`^'",
+    );
+}
+
+/// The command a word is the name or an abbreviation of, out of those that
+/// are allowed.
+pub(crate) fn find_command(word: &str, allowed: impl Fn(&str) -> bool) -> Option<&'static Command> {
+    let word = word.to_lowercase();
+    let mut commands = COMMANDS.iter().filter(|command| allowed(command.name));
+
+    commands
+        .clone()
+        .find(|command| command.name == word)
+        .or_else(|| {
+            commands.find(|command| {
+                word.len() >= command.min_letters && command.name.starts_with(&word)
+            })
+        })
+}
+
+/// A command line as it can be logged or shown to others, without the
+/// arguments of sensitive commands, e.g. "delete ***".
+pub(crate) fn redacted(words: &[&str]) -> String {
+    match words {
+        [word, _arguments, ..]
+            if find_command(word, |_| true).is_some_and(|command| command.sensitive) =>
+        {
+            format!("{} ***", word)
+        }
+        words => words.join(" "),
+    }
+}
+
+/// The command with a name, if there is one.
+pub(crate) fn command_named(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// What to tell an entity that isn't in the position a command needs.
+pub(crate) fn position_refusal(command: &Command, entity: &EntityInfo) -> Option<&'static str> {
+    match (command.position, position(entity)?) {
+        (Position::Sleeping, _) => None,
+        (_, Position::Sleeping) => Some("In your dreams, or what?"),
+        (Position::Standing, Position::Resting) => Some("You need to stand up first."),
+        _ => None,
+    }
+}
+
+/// Words typed with a shortcut spelled out: `'hello` is `say hello`,
+/// `,grins` is `emote grins`, and `mq5 say hi` is `mq 5 say hi`.
+pub(crate) fn expand_shortcut<'w>(words: &[&'w str]) -> Option<Vec<&'w str>> {
+    let (first, rest) = words.split_first()?;
+
+    let (command, first) = if let Some(message) = first.strip_prefix('\'') {
+        ("say", message)
+    } else if let Some(message) = first.strip_prefix(',') {
+        ("emote", message)
+    } else if first.starts_with("mq") && first[2..].parse::<u32>().is_ok() {
+        ("mq", &first[2..])
+    } else {
+        return None;
+    };
+
+    let mut expanded = vec![command];
+    if !first.is_empty() {
+        expanded.push(first);
+    }
+    expanded.extend_from_slice(rest);
+    Some(expanded)
+}
@@ -1,212 +1,81 @@
 use crate::{
     acting::EscapeVariables,
     agent::EntityAgent,
+    clock::time_of_day,
     colors::recolor,
-    components::{Door, Mobile, Object},
+    command_table::{expand_shortcut, position_refusal, redacted},
+    components::Door,
+    drunk::stumbles,
+    durability::condition_description,
     echo,
-    entity::Found,
+    entity::{Found, Place},
+    event_log::EventKind,
     files::fix_newlines,
     find_entities::EntityIterator,
     mapper::make_map,
     mobprogs::Action,
     state::WorldState,
-    world::{common_direction, long_direction, opposite_direction, Shop},
+    vitals::not_standing,
+    wizlog::LogKind,
+    world::{common_direction, long_direction, opposite_direction},
 };
 
+/// Run a command, looking it up in the command table; see the
+/// `command_table` module. Returns whether it was understood.
 pub(crate) fn process_agent_command(agent: &mut EntityAgent, words: &[&str]) -> bool {
-    match *words {
-        ["panic"] => {
-            panic!("Oh no! I panicked!");
-        }
-        ["help"] => {
-            agent.do_help(None);
-        }
-        ["help", help_file] => {
-            agent.do_help(Some(help_file));
-        }
-        ["die"] => {
-            agent.do_die();
-        }
-        ["areas"] => {
-            agent.do_areas();
-        }
-        ["buy", item] => {
-            agent.do_buy(item);
-        }
-        ["sell", item] => {
-            agent.do_sell(item);
-        }
-        ["eat", item] => {
-            agent.do_eat(item, false);
-        }
-        ["eat", item, "forcefully"] => {
-            agent.do_eat(item, true);
-        }
-        ["mq", ticks, ref command @ ..] => {
-            agent.do_queue(ticks, command.join(" "));
-        }
-        [mq, ref command @ ..] if mq.starts_with("mq") && mq[2..].parse::<u32>().is_ok() => {
-            agent.do_queue(&mq[2..], command.join(" "));
-        }
-        ["mob", ref command @ ..] => {
-            agent.do_mob(command);
-        }
-        ["map"] => {
-            agent.do_map();
-        }
-        ["look"] | ["l"] | ["examine"] => {
-            agent.do_look();
-        }
-        [look, target] | [look, "at", target] if ["look", "l", "examine"].contains(&look) => {
-            agent.do_look_at(target);
-        }
-        ["look", ..] | ["l", ..] | ["examine", ..] => {
-            echo!(agent.info(), "Syntax: '`Wlook <word>`^'\r\n");
-        }
-        ["force", target, ref victim_words @ ..] => {
-            agent.do_force(target, victim_words);
-        }
-        ["say", target, ref message @ ..] if target.starts_with('>') => {
-            agent.do_say_to(&target[1..], &message.join(" "));
-        }
-        ["sayto", target, ref message @ ..] => {
-            agent.do_say_to(target, &message.join(" "));
-        }
-        ["say", ref message @ ..] => {
-            agent.do_say(&message.join(" "));
-        }
-        [target, ref message @ ..] if target.starts_with("'>") => {
-            agent.do_say_to(&target[2..], &message.join(" "));
-        }
-        [ref message @ ..] if !message.is_empty() && message[0].starts_with('\'') => {
-            agent.do_say(&message.join(" ")[1..]);
-        }
-        ["rsay", ref message @ ..] if false => {
-            // Disabled; not needed
-            agent.do_mob_rsay(&message.join(" "));
-        }
-        ["rsay", target, ref message @ ..] if target.starts_with('>') => {
-            agent.do_say_to(&target[1..], &message.join(" "));
-        }
-        ["rsay", ref message @ ..] => {
-            agent.do_say(&message.join(" "));
-        }
-        ["recall"] => {
-            agent.do_recall(None);
-        }
-        ["recall", location] => {
-            agent.do_recall(Some(location));
-        }
-        ["exits"] => {
-            agent.do_exits();
-        }
-        ["get", "all"] => {
-            agent.do_get_all(false);
-        }
-        ["drop", "all"] => {
-            agent.do_drop_all(false);
-        }
-        ["get"] => {
-            agent.do_get(None, false);
-        }
-        ["get", item] => {
-            agent.do_get(Some(item), false);
-        }
-        ["get", item, "forcefully"] => {
-            agent.do_get(Some(item), true);
-        }
-        ["get", item, container] => {
-            agent.do_get_from(item, container, false);
-        }
-        ["get", item, "from", container] => {
-            agent.do_get_from(item, container, false);
-        }
-        ["get", item, container, "forcefully"] => {
-            agent.do_get_from(item, container, true);
-        }
-        ["get", item, "from", container, "forcefully"] => {
-            agent.do_get_from(item, container, true);
-        }
-        ["put", item, container] => {
-            agent.do_put_into(item, container, false);
-        }
-        ["put", item, "into", container] => {
-            agent.do_put_into(item, container, false);
-        }
-        ["put", item, container, "forcefully"] => {
-            agent.do_put_into(item, container, true);
-        }
-        ["put", item, "into", container, "forcefully"] => {
-            agent.do_put_into(item, container, true);
-        }
-        ["drop"] => {
-            agent.do_drop(None, false);
-        }
-        ["drop", item] => {
-            agent.do_drop(Some(item), false);
-        }
-        ["drop", item, "forcefully"] => {
-            agent.do_drop(Some(item), true);
-        }
-        ["give", item, target] | ["give", item, "to", target] => {
-            agent.do_give(item, target, false);
-        }
-        ["give", item, target, "forcefully"] | ["give", item, "to", target, "forcefully"] => {
-            agent.do_give(item, target, true);
-        }
-        ["give", ..] => {
-            echo!(agent.info(), "Syntax: `Wgive <item> [to] <target>`^\r\n");
-        }
-        ["open", target] => {
-            agent.do_open(target);
-        }
-        ["close", target] => {
-            agent.do_close(target);
-        }
-        ["unlock", target] => {
-            agent.do_unlock(target);
-        }
-        ["lock", target] => {
-            agent.do_lock(target);
-        }
-        ["i"] | ["inv"] | ["inventory"] => {
-            agent.do_inventory();
-        }
-        ["list"] | ["wares"] => {
-            agent.do_list();
-        }
-        ["follow", target] => {
-            agent.do_follow(target);
-        }
-        ["unfollow"] => {
-            agent.do_unfollow();
-        }
-        ["emote", ref message @ ..] => {
-            agent.do_emote(&message.join(" "));
-        }
-        [ref message @ ..] if !message.is_empty() && message[0].starts_with(',') => {
-            agent.do_emote(&message.join(" ")[1..]);
-        }
-        ["pmote", target, ref message @ ..] => {
-            agent.do_pmote(target, &message.join(" "));
-        }
-        ["social"] | ["socials"] | ["emotes"] => {
-            agent.do_socials(None);
-        }
-        ["social", social] | ["socials", social] | ["emotes", social] => {
-            agent.do_socials(Some(social));
+    let expanded = expand_shortcut(words);
+    let words = expanded.as_deref().unwrap_or(words);
+
+    let (word, args) = match words.split_first() {
+        Some((word, args)) => (*word, args),
+        None => return true,
+    };
+
+    let command = agent.command_for(word).filter(|command| {
+        command.name.eq_ignore_ascii_case(word) || !names_social_or_exit(agent, word)
+    });
+
+    if let Some(command) = command {
+        let _command = tracing::info_span!("command", name = command.name).entered();
+
+        let myself = agent.entity_world.entity_info(agent.entity_id);
+        if let Some(refusal) = position_refusal(command, &myself) {
+            echo!(agent.info(), "{}\r\n", refusal);
+            return true;
         }
-        [direction] if agent.do_move(direction) => (),
-        [social] if agent.do_social(social, None) => (),
-        [social, target] if agent.do_social(social, Some(target)) => (),
-        [cmd_word, ..] => {
-            agent.do_unknown(cmd_word);
-            return false;
+
+        (command.handler)(agent, args);
+        return true;
+    }
+
+    let _command = tracing::info_span!("command", name = "(other)").entered();
+    match *args {
+        [] if agent.do_move(word) => true,
+        [] if agent.do_social(word, None) => true,
+        [target] if agent.do_social(word, Some(target)) => true,
+        _ => {
+            agent.do_unknown(word);
+            false
         }
-        [] => (),
-    };
+    }
+}
+
+/// Whether a word is the whole name of a social, or a keyword of an exit out
+/// of the room, which then go before commands it is an abbreviation of.
+fn names_social_or_exit(agent: &EntityAgent, word: &str) -> bool {
+    if agent.socials.get(word).is_some() {
+        return true;
+    }
 
-    true
+    let myself = agent.entity_world.entity_info(agent.entity_id);
+    let room = myself.room();
+    let is_exit = room.exits().any(|exit| {
+        exit.component_info()
+            .keyword()
+            .split_whitespace()
+            .any(|keyword| keyword.eq_ignore_ascii_case(word))
+    });
+    is_exit
 }
 
 pub(crate) fn process_player_command(world_state: &mut WorldState, player: &str, words: &[&str]) {
@@ -225,15 +94,47 @@ pub(crate) fn process_player_command(world_state: &mut WorldState, player: &str,
         }
     };
 
-    let mut agent = EntityAgent {
-        entity_world: &mut world_state.entity_world,
-        socials: &world_state.socials,
-        vnum_templates: &world_state.vnum_templates,
-        areas: &world_state.areas,
-        players: &mut world_state.players,
-        entity_id: player_id,
+    world_state.echo_to_snooper(player, &format!("> {}", redacted(words)));
+
+    if !words.is_empty() {
+        let room = world_state.entity_world.entity_info(player_id).room();
+        let room = room.components().general.vnum.0;
+        world_state
+            .event_log
+            .record(EventKind::Command, player, Some(room), &redacted(words));
+    }
+
+    let expanded = world_state.accounts.preferences(player).expand_alias(words);
+    let expanded_words: Vec<&str>;
+    let words = match &expanded {
+        Some(expanded) => {
+            expanded_words = expanded.iter().map(String::as_str).collect();
+            &expanded_words
+        }
+        None => words,
     };
 
+    let mut agent = EntityAgent::new(world_state, player_id);
+
+    // Frozen players can't do anything until an immortal thaws them
+    if !words.is_empty() && agent.accounts.is_frozen(player) {
+        echo!(
+            agent.info(),
+            "You are frozen solid, and can't do anything!\r\n"
+        );
+        return;
+    }
+
+    // Commands a player isn't trusted with don't exist as far as they know
+    if let Some(command) = words.first() {
+        if !agent.trusted_with(command) {
+            agent.do_unknown(command);
+            let message = format!("{} tried to use '{}'.", player, redacted(words));
+            agent.log_to_immortals(LogKind::Command, &message);
+            return;
+        }
+    }
+
     process_agent_command(&mut agent, words);
 }
 
@@ -246,15 +147,38 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         );
     }
 
-    pub fn do_help(&mut self, help_file: Option<&str>) {
-        let help_text = match help_file {
-            Some("commands") => include_str!("../help_commands.txt"),
-            Some("emote") => include_str!("../help_emote.txt"),
-            Some("cli") => include_str!("../help_cli.txt"),
-            Some("demimud") => include_str!("../help_demimud.txt"),
-            Some("credits") => include_str!("../help_credits.txt"),
-            None => include_str!("../help.txt"),
-            _ => "Unknown help file. See '`Whelp`^' without an argument.\r\n",
+    /// Show one of the built-in help files, or else the first help entry of
+    /// the areas that the topic finds.
+    pub fn do_help(&mut self, topic: &str) {
+        let help_text = match topic {
+            "commands" => include_str!("../help_commands.txt"),
+            "emote" => include_str!("../help_emote.txt"),
+            "notes" | "note" => include_str!("../help_notes.txt"),
+            "mail" => include_str!("../help_mail.txt"),
+            "config" => include_str!("../help_config.txt"),
+            "clan" | "clans" => include_str!("../help_clans.txt"),
+            "cli" => include_str!("../help_cli.txt"),
+            "demimud" => include_str!("../help_demimud.txt"),
+            "credits" => include_str!("../help_credits.txt"),
+            "" => include_str!("../help.txt"),
+            _ => {
+                // Helps above ROM's hero level are for immortals
+                let myself = self.entity_world.entity_info(self.entity_id);
+                let level = match myself.player_name() {
+                    Some(player) if !self.accounts.is_immortal(player) => 51,
+                    _ => i32::MAX,
+                };
+
+                let help = self.world.find_help(topic, level);
+                match help.map(|help| help.text.trim_end().to_string()) {
+                    Some(text) => echo!(self.info(), "{}\r\n", text),
+                    None => echo!(
+                        self.info(),
+                        "No help on that word. See '`Whelp`^' without an argument.\r\n"
+                    ),
+                }
+                return;
+            }
         };
         echo!(self.info(), "{}", fix_newlines(help_text));
     }
@@ -264,8 +188,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
 
         if myself.is_mobile() || myself.is_player() {
             let mut act = self.players.act_alone(&myself);
-            echo!(act.myself(), "You are `RDEAD`^.\r\n");
-            echo!(act.others(), "$n is `RDEAD`^.\r\n");
+            echo!(act.all(), "$^$n $[are|is] `RDEAD`^.\r\n");
 
             let holder = myself.room();
             let mut act = self.players.act_with(&holder, &myself);
@@ -273,17 +196,102 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             echo!(act.others(), "$^$N is `RDEAD`^.\r\n");
         }
 
+        let message = myself.player_name().map(|player| {
+            let room = myself.room();
+            format!(
+                "{} died in {} (vnum {}).",
+                player,
+                room.component_info().internal_title(),
+                room.components().general.vnum.0
+            )
+        });
+
+        // Scavengers drop everything, so that what they picked up isn't lost
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let scavenger = myself
+            .components()
+            .mobile
+            .as_ref()
+            .map(|mobile| mobile.scavenger)
+            .unwrap_or(false);
+        if scavenger {
+            let room_id = myself.room().entity_id();
+            let carried: Vec<_> = myself
+                .objects()
+                .filter(|object| object.components().wealth.is_none())
+                .map(|object| object.entity_id())
+                .collect();
+
+            for object_id in carried {
+                self.entity_world
+                    .entity_info_mut(object_id)
+                    .components()
+                    .general
+                    .equipped = None;
+                self.entity_world.move_entity(object_id, room_id);
+            }
+        }
+
+        // The dead drop their coins where they fell
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if myself.is_mobile() || myself.is_player() {
+            let room_id = myself.room().entity_id();
+            let coin_pile = myself
+                .objects()
+                .find(|object| object.components().wealth.is_some())
+                .map(|object| object.entity_id());
+
+            if let Some(coin_pile_id) = coin_pile {
+                self.entity_world.move_entity(coin_pile_id, room_id);
+                self.merge_coins(coin_pile_id);
+            }
+        }
+
+        // Nobody hunts the dead, and the dead hunt nobody
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if let Some(player) = myself.player_name() {
+            let player = player.to_string();
+            self.forget_prey(&player);
+        } else if myself.is_mobile() {
+            self.stop_hunting(true);
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if myself.is_mobile() || myself.is_player() {
+            self.make_corpse();
+        }
+
+        // Logged before leaving for limbo, to note the room of the death
+        if let Some(message) = message {
+            self.log_to_immortals(LogKind::Death, &message);
+        }
+
+        // Dead players wait to be restored in the death room, and dead
+        // mobiles in limbo; anything else is gone for good
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if !myself.is_mobile() && !myself.is_player() {
+            self.entity_world.destroy_entity(self.entity_id);
+            return;
+        }
         let limbo = self
             .entity_world
             .landmark("limbo")
             .expect("Limbo should always exist");
-        self.entity_world.move_entity(self.entity_id, limbo);
+        let is_player = myself.is_player();
+        let to_room_id = match self.entity_world.place(Place::Death) {
+            Some(death_room_id) if is_player => death_room_id,
+            _ => limbo,
+        };
+        self.entity_world.move_entity(self.entity_id, to_room_id);
     }
 
     pub fn do_areas(&mut self) {
-        echo!(self.info(), "Areas:\r\n");
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let mut info = self.players.info(&myself);
+
+        echo!(info, "Areas:\r\n");
 
-        for area in self.areas {
+        for (area, _resets) in &self.world.areas {
             // Expensive, but let's honor them properly.
             let credits = area
                 .credits
@@ -293,7 +301,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
                 .join(", ");
 
             echo!(
-                self.info(),
+                info,
                 "`C{:>32}`^ - `g{:>5}`^..`g{:>5}`^ - {}\r\n",
                 area.name,
                 area.vnums.0 .0,
@@ -303,130 +311,6 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         }
     }
 
-    pub fn do_buy(&mut self, item_name: &str) {
-        let myself = self.entity_world.entity_info(self.entity_id);
-
-        let found = myself
-            .room()
-            .contained_entities()
-            .with_component::<Shop>()
-            .filter_or(
-                |e| *e != myself,
-                "The only shopkeeper here is yourself.\r\n",
-            )
-            .find_one_with_component_or("You don't see any shopkeepers here.");
-
-        let (entity, shop_info) = match found {
-            Ok(found) => found,
-            Err(error) => return self.echo_error(error),
-        };
-
-        let shopkeeper_id = entity.entity_id();
-
-        let found = entity
-            .objects()
-            .filter_by_keyword(item_name)
-            .with_component::<Object>()
-            .find_one_with_component_or("You don't see anything named like that to buy.");
-
-        let (item, object) = match found {
-            Ok(item) => item,
-            Err(error) => return self.echo_error(error),
-        };
-
-        let item_id = item.entity_id();
-        let cost = (object.cost * shop_info.profit_buy as i32 / 100) as usize;
-
-        if self.remove_silver(cost, self.entity_id) {
-            // Clone it so that the shopkeeper can keep selling it
-            let item = self.entity_world.entity_info(item_id);
-            let components = item.components().clone();
-            self.entity_world.insert_entity(self.entity_id, components);
-
-            let myself = self.entity_world.entity_info(self.entity_id);
-            let shopkeeper = self.entity_world.entity_info(shopkeeper_id);
-            let item = self.entity_world.entity_info(item_id);
-            let mut act = self.players.act_with(&myself, &shopkeeper);
-            echo!(
-                act.myself(),
-                "You buy {} from $N for {} silver.\r\n",
-                item,
-                cost
-            );
-            echo!(
-                act.target(),
-                "$^$n buys {} from you for {} silver.\r\n",
-                item,
-                cost
-            );
-            echo!(act.others(), "$^$n buys {} from $N.\r\n", item);
-        } else {
-            echo!(
-                self.info(),
-                "You don't have the {} silver to pay for it!\r\n",
-                cost
-            );
-        }
-    }
-
-    pub fn do_sell(&mut self, item_name: &str) {
-        let myself = self.entity_world.entity_info(self.entity_id);
-
-        let found = myself
-            .room()
-            .contained_entities()
-            .with_component::<Shop>()
-            .filter_or(
-                |e| *e != myself,
-                "The only shopkeeper here is yourself.\r\n",
-            )
-            .find_one_with_component_or("You don't see any shopkeepers here.");
-
-        let (entity, shop_info) = match found {
-            Ok(found) => found,
-            Err(error) => return self.echo_error(error),
-        };
-
-        let shopkeeper_id = entity.entity_id();
-
-        let found = myself
-            .objects()
-            .filter_by_keyword(item_name)
-            .with_component_or::<Object>("$^$N is not an object you can sell.")
-            .find_one_with_component_or("You don't own anything named like that to sell.");
-
-        let (item, object) = match found {
-            Ok(found) => found,
-            Err(error) => return self.echo_error(error),
-        };
-
-        // FIXME: fix integer types
-        let cost = (object.cost * shop_info.profit_sell as i32 / 100) as usize;
-        let item_id = item.entity_id();
-
-        self.add_silver(cost, self.entity_id);
-        let mut item_agent = self.switch_agent(item_id);
-        item_agent.do_die();
-
-        let myself = self.entity_world.entity_info(self.entity_id);
-        let shopkeeper = self.entity_world.entity_info(shopkeeper_id);
-        let item = self.entity_world.entity_info(item_id);
-        let mut act = self.players.act_with(&myself, &shopkeeper);
-        echo!(
-            act.myself(),
-            "You sell {} to $N for {} silver.\r\n",
-            item,
-            cost
-        );
-        echo!(
-            act.target(),
-            "$^$n sells {} to you for {} silver.\r\n",
-            item,
-            cost
-        );
-        echo!(act.others(), "$^$n sells {} to $N.\r\n", item);
-    }
-
     pub fn do_eat(&mut self, item_name: &str, forcefully: bool) {
         let myself = self.entity_world.entity_info(self.entity_id);
 
@@ -475,6 +359,8 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
 
         let mut agent = self.switch_agent(food_id);
         agent.do_die();
+
+        self.eat_meal();
     }
 
     pub fn do_queue(&mut self, ticks: &str, command: String) {
@@ -496,29 +382,57 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
     }
 
     pub fn do_map(&mut self) {
-        let map = make_map(
-            &self.entity_world,
-            self.entity_world.room_of(self.entity_id),
-        );
+        let map = make_map(self.entity_world, self.entity_world.room_of(self.entity_id));
 
         echo!(self.info(), "{}", map);
     }
 
     pub fn do_look(&mut self) {
+        self.look_room(false);
+    }
+
+    /// Look at the room after arriving in it, leaving out the description if
+    /// the player prefers it brief.
+    pub fn do_look_around(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let brief = match myself.player_name() {
+            Some(player) => self.accounts.preferences(player).brief,
+            None => false,
+        };
+
+        self.look_room(brief);
+    }
+
+    fn look_room(&mut self, brief: bool) {
         let myself = self.entity_world.entity_info(self.entity_id);
         let room_id = self.entity_world.room_of(self.entity_id);
         let room = self.entity_world.entity_info(room_id);
 
+        let seen = self.players.seen_by(&myself);
+        let objects: Vec<_> = room.objects().filter(|object| seen(object)).collect();
+        let mobiles: Vec<_> = room.mobiles().filter(|mobile| seen(mobile)).collect();
+        let players: Vec<_> = room
+            .players()
+            .filter(|player| player.entity_id() != self.entity_id && seen(player))
+            .map(|player| {
+                let wizinvis = self.players.sight(&player).wizinvis.is_some();
+                (player, wizinvis)
+            })
+            .collect();
+
+        let time_of_day = time_of_day(self.clock.hour());
         let mut info = self.players.info(&myself);
 
         // Title
         echo!(info, "`y{}`^\r\n", room.component_info().internal_title());
 
         // Description
-        let description = room.component_info().internal_description();
-        echo!(info, "{}", description);
-        if !description.ends_with('\r') && !description.ends_with('\n') {
-            echo!(info, "\r\n");
+        if !brief {
+            let description = room.component_info().internal_description_at(time_of_day);
+            echo!(info, "{}", description);
+            if !description.ends_with('\r') && !description.ends_with('\n') {
+                echo!(info, "\r\n");
+            }
         }
 
         // Exits
@@ -551,7 +465,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         }
 
         // Objects
-        for object in room.objects() {
+        for object in objects {
             let container_state = match &object.components().door {
                 Some(door) if door.locked => " (locked)",
                 Some(door) if door.closed => " (closed)",
@@ -568,23 +482,21 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         }
 
         // Mobiles
-        for mobile in room.mobiles() {
+        for mobile in mobiles {
             echo!(
                 info,
                 "`m{}`^\r\n",
-                mobile.component_info().lateral_description()
+                mobile.component_info().lateral_description_at(time_of_day)
             );
         }
 
         // Players
-        for player in room.players() {
-            if player.entity_id() == self.entity_id {
-                continue;
-            }
-
+        for (player, wizinvis) in players {
+            let wizinvis = if wizinvis { "(Wizinvis) " } else { "" };
             echo!(
                 info,
-                "`M{}`^\r\n",
+                "`M{}{}`^\r\n",
+                wizinvis,
                 player.component_info().lateral_description()
             );
         }
@@ -599,7 +511,14 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             .find_one_or("You don't see anything named like that here.\r\n");
 
         let target = match found {
-            Ok(target) => target,
+            Ok(target) if self.players.can_see(&myself, &target) => target,
+            Ok(_) => {
+                echo!(
+                    self.players.info(&myself),
+                    "You don't see anything named like that here.\r\n"
+                );
+                return;
+            }
             Err(error) => return self.echo_error(error),
         };
 
@@ -630,6 +549,10 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         }
 
+        if let Some(condition) = condition_description(&target) {
+            echo!(act.myself(), "$^$E is {}.\r\n", condition);
+        }
+
         // Contents
         let mut first = true;
         let mut column = 0;
@@ -677,64 +600,8 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         }
     }
 
-    pub fn do_force(&mut self, target_name: &str, words: &[&str]) {
-        let myself = self.entity_world.entity_info(self.entity_id);
-        let target = myself.find_entity(target_name, |entity| {
-            // Prefer objects over their descriptions, and others over self
-            !entity.is_extra_description() && entity.entity_id() != myself.entity_id()
-        });
-
-        let target = match target {
-            Found::Myself | Found::WrongSelf => {
-                let mut act = self.players.act_alone(&myself);
-                echo!(act.myself(), "You snap your fingers at yourself.\r\n");
-                echo!(act.others(), "He snaps his fingers at himself.\r\n");
-                myself
-            }
-            Found::Other(other) | Found::WrongOther(other) => {
-                let mut act = self.players.act_with(&myself, &other);
-                echo!(act.myself(), "You snap your fingers at $N.\r\n");
-                echo!(act.target(), "$^$n snaps $s fingers at you.\r\n");
-                echo!(act.others(), "$^$n snaps $s fingers at $N.\r\n");
-                other
-            }
-            Found::Nothing => {
-                echo!(
-                    self.info(),
-                    "You don't see anything named {} in the room.\r\n",
-                    target_name
-                );
-                return;
-            }
-        };
-
-        let mut info = self.players.info(&target);
-        echo!(info, "You feel compelled to: {:?}\r\n", words);
-
-        let target_id = target.entity_id();
-
-        let mut agent = self.switch_agent(target_id);
-
-        if !process_agent_command(&mut agent, words) {
-            let myself = self.entity_world.entity_info(self.entity_id);
-            let target = self.entity_world.entity_info(target_id);
-            let mut act = self.players.act_with(&myself, &target);
-            echo!(
-                act.myself(),
-                "$^$E didn't quite understand your command.\r\n"
-            );
-            echo!(
-                act.target(),
-                "You tried to do it, but you didn't quite understand what $e meant.\r\n"
-            );
-            echo!(
-                act.others(),
-                "$^$n tries to do something, but stops with a confused look.\r\n"
-            );
-        }
-    }
-
     pub fn do_say(&mut self, message: &str) {
+        let message = &self.slurred(message);
         let myself = self.entity_world.entity_info(self.entity_id);
         let mut act = self.players.act_alone(&myself);
 
@@ -768,7 +635,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
                 Some(&message[start + 1..message.len() - 1]),
             )
         } else {
-            (&message[..], None)
+            (message, None)
         };
 
         let message = message.trim();
@@ -821,15 +688,16 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
                 if emote_suffix.is_some() { "." } else { "" },
             );
 
-            self.check_triggers_others(Action::Speech { message: &message })
+            self.check_triggers_others(Action::Speech { message })
         } else {
             echo!(self.info(), "You say nothing whatsoever.\r\n");
         }
     }
 
     pub fn do_say_to(&mut self, target: &str, message: &str) {
+        let message = &self.slurred(message);
         let myself = self.entity_world.entity_info(self.entity_id);
-        let target = myself.find_entity(target, |_| true);
+        let target = myself.find_seen_entity(target, |_| true, self.players.seen_by(&myself));
 
         let target = match target {
             Found::Myself | Found::WrongSelf => {
@@ -914,14 +782,19 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         }
     }
 
+    /// Go to the recall room of the configuration, or to a landmark.
     pub fn do_recall(&mut self, location: Option<&str>) {
         let myself = self.entity_world.entity_info(self.entity_id);
 
-        let location = match location {
-            Some(location) => location,
+        let to_room_id = match location {
+            Some(location) => self.entity_world.landmark(location),
+            None => self.entity_world.place(Place::Recall),
+        };
+        let to_room_id = match to_room_id {
+            Some(to_room_id) => to_room_id,
             None => {
                 let mut info = self.info();
-                echo!(info, "You can recall to:\r\n");
+                echo!(info, "Unknown location; you can recall to:\r\n");
                 let places = &[
                     "`Wrecall mekali`^ - A Large Plaza in Mekali City",
                     "`Wrecall gnomehill`^ - A Large Plaza on Gnome Hill",
@@ -935,33 +808,46 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        if let Some(room_id) = self.entity_world.landmark(location) {
-            let mut act = self.players.act_alone(&myself);
-            echo!(
-                act.myself(),
-                "You close your eyes in prayer, and feel your surroundings shift around you.\r\n",
-            );
-            echo!(
-                act.others(),
-                "$n close $s eyes in prayer, and fades out into thin air.\r\n",
-            );
+        let room_vnum = myself.room().components().general.vnum.0;
+        let no_recall = match self.world.rooms.get(room_vnum) {
+            Some(room) if room_vnum != 0 && room.vnum.0 == room_vnum => room.no_recall,
+            _ => false,
+        };
+        if no_recall {
+            echo!(self.info(), "You can't recall from here.\r\n");
+            return;
+        }
 
-            self.entity_world.move_entity(self.entity_id, room_id);
-            self.do_look();
+        let mut act = self.players.act_alone(&myself);
+        echo!(
+            act.myself(),
+            "You close your eyes in prayer, and feel your surroundings shift around you.\r\n",
+        );
+        echo!(
+            act.others(),
+            "$n close $s eyes in prayer, and fades out into thin air.\r\n",
+        );
 
-            // A temporary substitute for logging in to make it easier to test
-            self.check_triggers_others(Action::Login);
-        } else {
-            echo!(
-                self.info(),
-                "Unknown location; type `Wrecall`^ to see a list.\r\n"
-            );
-        }
+        self.entity_world.move_entity(self.entity_id, to_room_id);
+        self.do_look_around();
+
+        // A temporary substitute for logging in to make it easier to test
+        self.check_triggers_others(Action::Login);
     }
 
     pub fn do_move(&mut self, direction: &str) -> bool {
         let direction = long_direction(direction);
 
+        // Exits into areas that wait to be imported lead there once they are
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let waiting_exit = match myself.find_entity(direction, |entity| entity.is_exit()) {
+            Found::Other(exit) if exit.leads_to().is_none() => Some(exit.entity_id()),
+            _ => None,
+        };
+        if let Some(exit_id) = waiting_exit {
+            self.leads_to(exit_id);
+        }
+
         let myself = self.entity_world.entity_info(self.entity_id);
         let target = myself.find_entity(direction, |entity| entity.is_exit());
 
@@ -1007,9 +893,32 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         }
 
+        if let Some(refusal) = not_standing(&myself) {
+            echo!(self.players.info(&myself), "{}\r\n", refusal);
+            return true;
+        }
+
+        if stumbles(&myself, self.rng) {
+            let mut act = self.players.act_alone(&myself);
+            echo!(
+                act.myself(),
+                "You stumble over your own feet, and go nowhere.\r\n"
+            );
+            echo!(act.others(), "$^$n stumbles over $s own feet.\r\n");
+            return true;
+        }
+
+        if let Some(refusal) = exit
+            .leads_to()
+            .and_then(|room_id| self.clan_hall_refusal(room_id))
+        {
+            echo!(self.players.info(&myself), "{}\r\n", refusal);
+            return true;
+        }
+
         let mut act = self.players.act_alone(&myself);
 
-        let exit_keyword = exit.main_keyword();
+        let exit_keyword = exit.main_keyword().to_string();
 
         let from_room_id = myself.room().entity_id();
         let to_room_id = match exit.leads_to() {
@@ -1027,8 +936,14 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
 
         let exit_id = exit.entity_id();
 
-        echo!(act.myself(), "You walk {}.\r\n", exit_keyword);
-        echo!(act.others(), "$^$n leaves {}.\r\n", exit_keyword);
+        if let Err(refusal) = self.cross_terrain(from_room_id, to_room_id) {
+            echo!(self.info(), "{}\r\n", refusal);
+            return true;
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let mut act = self.players.act_alone(&myself);
+        echo!(act.all(), "$^$n $[walk|leaves] {}.\r\n", exit_keyword);
 
         self.check_triggers_others(Action::Exit { direction });
 
@@ -1045,7 +960,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         );
 
         // Admire new surroundings.
-        self.do_look();
+        self.do_look_around();
 
         // Allow followers to admire new surroundings.
         self.check_followers(from_room_id, direction, to_room_id);
@@ -1084,6 +999,53 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         }
     }
 
+    /// Who is in the room, and in the rooms next to it through open exits.
+    pub fn do_scan(&mut self) {
+        let room_id = self.entity_world.room_of(self.entity_id);
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let seen = self.players.seen_by(&myself);
+
+        let characters_in = |room_id| {
+            self.entity_world
+                .entities_in_room(room_id)
+                .filter(|entity| entity.is_mobile() || entity.is_player())
+                .filter(|entity| entity.entity_id() != self.entity_id && seen(entity))
+                .map(|entity| entity.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let mut seen_around = Vec::new();
+        for character in characters_in(room_id) {
+            seen_around.push(format!("{}, right here.", character));
+        }
+        for (exit, next_room) in self.entity_world.adjacent_rooms(room_id) {
+            if exit
+                .components()
+                .door
+                .as_ref()
+                .is_some_and(|door| door.closed)
+            {
+                continue;
+            }
+            for character in characters_in(next_room.entity_id()) {
+                seen_around.push(format!(
+                    "{}, nearby to the {}.",
+                    character,
+                    exit.main_keyword()
+                ));
+            }
+        }
+
+        let mut info = self.players.info(&myself);
+        echo!(info, "Looking around you see:\r\n");
+        if seen_around.is_empty() {
+            echo!(info, "  No one.\r\n");
+        }
+        for line in seen_around {
+            echo!(info, "  {}\r\n", line);
+        }
+    }
+
     pub fn do_emote(&mut self, message: &str) {
         let myself = self.entity_world.entity_info(self.entity_id);
         let mut act = self.players.act_alone(&myself).store_acts();
@@ -1118,9 +1080,11 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
     pub fn do_pmote(&mut self, target: &str, message: &str) {
         let myself = self.entity_world.entity_info(self.entity_id);
 
-        let target = myself.find_entity(target, |e| {
-            !e.is_extra_description() && e.entity_id() != myself.entity_id()
-        });
+        let target = myself.find_seen_entity(
+            target,
+            |e| !e.is_extra_description() && e.entity_id() != myself.entity_id(),
+            self.players.seen_by(&myself),
+        );
 
         let target = match target {
             Found::Myself | Found::WrongSelf => {
@@ -1234,7 +1198,11 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         let myself = self.entity_world.entity_info(self.entity_id);
 
         if let Some(target) = target {
-            let target = myself.find_entity(target, |e| !e.is_extra_description());
+            let target = myself.find_seen_entity(
+                target,
+                |e| !e.is_extra_description(),
+                self.players.seen_by(&myself),
+            );
 
             match target {
                 Found::Myself | Found::WrongSelf => {
@@ -1360,6 +1328,8 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
                 );
                 let acts2 = act.into_acts();
 
+                self.merge_coins(other_id);
+
                 // Check triggers only have everyone saw the message, so that
                 // the events are seen in order.
                 self.check_act_triggers(acts1);
@@ -1450,6 +1420,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         let object_id = object.entity_id();
 
         self.entity_world.move_entity(object_id, self.entity_id);
+        self.merge_coins(object_id);
         self.check_act_triggers(acts);
     }
 
@@ -1490,6 +1461,8 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         let object_id = object.entity_id();
         let room_id = self.entity_world.room_of(myself.entity_id());
         self.entity_world.move_entity(object_id, room_id);
+        self.merge_coins(object_id);
+        self.start_spoiling(object_id);
 
         self.check_act_triggers(acts);
     }
@@ -1534,7 +1507,14 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             .find_one_or("You don't see anyone here named like that.");
 
         let target = match found {
-            Ok(target) => target,
+            Ok(target) if self.players.can_see(&myself, &target) => target,
+            Ok(_) => {
+                echo!(
+                    self.players.info(&myself),
+                    "You don't see anyone here named like that.\r\n"
+                );
+                return;
+            }
             Err(error) => return self.echo_error(error),
         };
 
@@ -1743,50 +1723,15 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        door.closed = false;
+        let locked = door.locked;
+        self.entity_world.set_door_state(target_id, false, locked);
 
         let myself = self.entity_world.entity_info(self.entity_id);
         let target = self.entity_world.entity_info(target_id);
         let mut act = self.players.act_with(&myself, &target).store_acts();
-        echo!(act.myself(), "You open $N.\r\n");
-        echo!(act.target(), "$^$n opens you.\r\n");
-        echo!(act.others(), "$^$n opens $N.\r\n");
-        let acts1 = act.into_acts();
-        let mut acts2 = None;
-
-        if let Some(leads_to) = target.leads_to() {
-            let other_room = self.entity_world.entity_info(leads_to);
-
-            let mut other_exit_id = None;
-
-            for other_exit in other_room.exits() {
-                if other_exit.main_keyword() == opposite_direction(target.main_keyword()) {
-                    other_exit_id = Some(other_exit.entity_id());
-                    break;
-                }
-            }
-
-            if let Some(other_exit_id) = other_exit_id {
-                let mut other_exit = self.entity_world.entity_info_mut(other_exit_id);
-
-                if let Some(door) = &mut other_exit.components().door {
-                    if door.closed {
-                        door.closed = false;
-
-                        let other_exit = self.entity_world.entity_info(other_exit_id);
-                        let mut act = self.players.act_alone(&other_exit).store_acts();
-                        echo!(act.myself(), "You are opened from the other side.\r\n");
-                        echo!(act.others(), "$^$n is opened from the other side.\r\n");
-                        acts2 = Some(act.into_acts());
-                    }
-                }
-            }
-        }
-
-        self.check_act_triggers(acts1);
-        if let Some(acts2) = acts2 {
-            self.check_act_triggers(acts2);
-        }
+        echo!(act.all(), "$^$n $[open|opens] $N.\r\n");
+        let acts = act.into_acts();
+        self.check_act_triggers(acts);
     }
 
     pub fn do_close(&mut self, target: &str) {
@@ -1840,50 +1785,15 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        door.closed = true;
+        let locked = door.locked;
+        self.entity_world.set_door_state(target_id, true, locked);
 
         let myself = self.entity_world.entity_info(self.entity_id);
         let target = self.entity_world.entity_info(target_id);
         let mut act = self.players.act_with(&myself, &target).store_acts();
-        echo!(act.myself(), "You close $N.\r\n");
-        echo!(act.target(), "$^$n closes you.\r\n");
-        echo!(act.others(), "$^$n closes $N.\r\n");
-        let acts1 = act.into_acts();
-        let mut acts2 = None;
-
-        if let Some(leads_to) = target.leads_to() {
-            let other_room = self.entity_world.entity_info(leads_to);
-
-            let mut other_exit_id = None;
-
-            for other_exit in other_room.exits() {
-                if other_exit.main_keyword() == opposite_direction(target.main_keyword()) {
-                    other_exit_id = Some(other_exit.entity_id());
-                    break;
-                }
-            }
-
-            if let Some(other_exit_id) = other_exit_id {
-                let mut other_exit = self.entity_world.entity_info_mut(other_exit_id);
-
-                if let Some(door) = &mut other_exit.components().door {
-                    if !door.closed {
-                        door.closed = true;
-
-                        let other_exit = self.entity_world.entity_info(other_exit_id);
-                        let mut act = self.players.act_alone(&other_exit).store_acts();
-                        echo!(act.myself(), "You are closed from the other side.\r\n");
-                        echo!(act.others(), "$^$n is closed from the other side.\r\n");
-                        acts2 = Some(act.into_acts());
-                    }
-                }
-            }
-        }
-
-        self.check_act_triggers(acts1);
-        if let Some(acts2) = acts2 {
-            self.check_act_triggers(acts2);
-        }
+        echo!(act.all(), "$^$n $[close|closes] $N.\r\n");
+        let acts = act.into_acts();
+        self.check_act_triggers(acts);
     }
 
     pub fn do_unlock(&mut self, target: &str) {
@@ -1937,53 +1847,15 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        door.locked = false;
+        let closed = door.closed;
+        self.entity_world.set_door_state(target_id, closed, false);
 
         let myself = self.entity_world.entity_info(self.entity_id);
         let target = self.entity_world.entity_info(target_id);
         let mut act = self.players.act_with(&myself, &target).store_acts();
-        echo!(act.myself(), "You unlock $N.\r\n");
-        echo!(act.target(), "$^$n unlocks you.\r\n");
-        echo!(act.others(), "$^$n unlocks $N.\r\n");
-        let acts1 = act.into_acts();
-        let mut acts2 = None;
-
-        if let Some(leads_to) = target.leads_to() {
-            let other_room = self.entity_world.entity_info(leads_to);
-
-            let mut other_exit_id = None;
-
-            for other_exit in other_room.exits() {
-                if other_exit.main_keyword() == opposite_direction(target.main_keyword()) {
-                    other_exit_id = Some(other_exit.entity_id());
-                    break;
-                }
-            }
-
-            if let Some(other_exit_id) = other_exit_id {
-                let mut other_exit = self.entity_world.entity_info_mut(other_exit_id);
-
-                if let Some(door) = &mut other_exit.components().door {
-                    if !door.locked {
-                        door.locked = false;
-
-                        let other_exit = self.entity_world.entity_info(other_exit_id);
-                        let mut act = self.players.act_alone(&other_exit).store_acts();
-                        echo!(act.myself(), "You are unlocked from the other side.\r\n");
-                        echo!(
-                            act.others(),
-                            "You hear a clicking sound as $n is unlocked.\r\n"
-                        );
-                        acts2 = Some(act.into_acts());
-                    }
-                }
-            }
-        }
-
-        self.check_act_triggers(acts1);
-        if let Some(acts2) = acts2 {
-            self.check_act_triggers(acts2);
-        }
+        echo!(act.all(), "$^$n $[unlock|unlocks] $N.\r\n");
+        let acts = act.into_acts();
+        self.check_act_triggers(acts);
     }
 
     pub fn do_lock(&mut self, _target: &str) {
@@ -1998,6 +1870,11 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         let mut first = true;
         let mut column = 4;
         for item in myself.contained_entities() {
+            let lit = matches!(
+                &item.components().object,
+                Some(object) if matches!(&object.light, Some(light) if light.lit)
+            );
+
             if first {
                 first = false;
             } else {
@@ -2013,68 +1890,22 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             let short_description = item.component_info().short_description();
             echo!(info, "{}", short_description);
             column += short_description.len();
-        }
-        echo!(info, "\r\n");
-    }
-
-    pub fn do_list(&mut self) {
-        let myself = self.entity_world.entity_info(self.entity_id);
-
-        let found = myself
-            .room()
-            .contained_entities()
-            .filter_or(
-                |e| *e != myself,
-                "The only shopkeeper here is yourself.\r\n",
-            )
-            .with_component::<Mobile>()
-            .prefer_component(|_e, mobile| mobile.shopkeeper.is_some())
-            .find_one_with_component_or("You don't see anyone here.");
-
-        let (entity, mobile) = match found {
-            Ok(found) => found,
-            Err(error) => return self.echo_error(error),
-        };
-
-        if let Some(shop_info) = &mobile.shopkeeper {
-            let mut act = self.players.act_with(&myself, &entity);
-            echo!(
-                act.target(),
-                "$^$n asks you about your wares, and you show $m what you have.\r\n"
-            );
-            echo!(act.others(), "$^$n asks $N about $S wares.\r\n");
-
-            echo!(act.myself(), "$^$N shows you $S wares:\r\n");
-
-            let mut info = self.players.info(&myself);
-            for item in entity.objects() {
-                if let Some(object_info) = &item.components().object {
-                    let price = object_info.cost * shop_info.profit_buy as i32 / 100;
-                    echo!(
-                        info,
-                        "  {}: `W{}`^ silver coins\r\n",
-                        item.component_info().short_description(),
-                        price
-                    );
-                } else {
-                    echo!(
-                        info,
-                        "  {}: priceless\r\n",
-                        item.component_info().short_description()
-                    );
-                }
+            if lit {
+                echo!(info, " (lit)");
+                column += 6;
             }
-        } else {
-            echo!(self.info(), "You don't see any shopkeepers here.\r\n");
         }
+        echo!(info, "\r\n");
     }
 
     pub fn do_follow(&mut self, target: &str) {
         let myself = self.entity_world.entity_info(self.entity_id);
 
-        let target = myself.find_entity(target, |entity| {
-            !entity.is_extra_description() && entity.entity_id() != self.entity_id
-        });
+        let target = myself.find_seen_entity(
+            target,
+            |entity| !entity.is_extra_description() && entity.entity_id() != self.entity_id,
+            self.players.seen_by(&myself),
+        );
 
         let target = match target {
             Found::Myself | Found::WrongSelf => {
@@ -2097,9 +1928,9 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         echo!(act.others(), "$^$n starts following $N.\r\n");
         let acts = act.into_acts();
 
-        let main_keyword = target.main_keyword().to_string();
+        let leader = target.weak_ref();
         let mut myself = self.entity_world.entity_info_mut(self.entity_id);
-        myself.components().general.following = Some(main_keyword);
+        myself.components().general.following = Some(leader);
 
         self.check_act_triggers(acts);
     }
@@ -2107,8 +1938,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
     pub fn do_unfollow(&mut self) {
         let myself = self.entity_world.entity_info(self.entity_id);
         let mut act = self.players.act_alone(&myself);
-        echo!(act.myself(), "You stop following anyone.\r\n");
-        echo!(act.others(), "$^$n stops following anyone.\r\n");
+        echo!(act.all(), "$^$n $[stop|stops] following anyone.\r\n");
 
         let mut myself = self.entity_world.entity_info_mut(self.entity_id);
         myself.components().general.following = None;
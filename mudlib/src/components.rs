@@ -1,39 +1,124 @@
-use string_interner::StringInterner;
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use string_interner::{StringInterner, Symbol};
 
 use crate::{
-    entity::EntityInfo,
-    world::{Gender, MobProgTrigger, Shop, Vnum},
+    entity::{EntityInfo, FixedState, WeakEntityRef},
+    world::{Gender, MobProgTrigger, Shop, TimeOfDay, Vnum},
 };
 
-#[derive(Clone)]
+#[derive(Clone, Hash, PartialEq, Eq)]
 pub(crate) struct IntStr {
     symbol: string_interner::symbol::SymbolU32,
 }
 
-#[derive(Clone)]
+// Symbols are stored as their index in the interner. The interner's own serde
+// implementation for symbols doesn't read back what it writes (it is off by
+// one), so it isn't used.
+impl Serialize for IntStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.symbol.to_usize().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for IntStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let index = usize::deserialize(deserializer)?;
+        let symbol = Symbol::try_from_usize(index)
+            .ok_or_else(|| serde::de::Error::custom("invalid string index"))?;
+        Ok(IntStr { symbol })
+    }
+}
+
+/// Everything about an entity. Entities spawned from the same template share
+/// what never changes, like their descriptions, through `Arc`s, so that
+/// cloning the components of a template is cheap; changing a shared part
+/// copies it first. Shared parts aren't shared anymore after a snapshot is
+/// restored.
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Components {
-    pub act_info: ActInfo,
-    pub descriptions: Descriptions,
+    pub act_info: Arc<ActInfo>,
+    pub descriptions: Arc<Descriptions>,
     pub general: GeneralData,
     pub mobile: Option<Mobile>,
     pub object: Option<Object>,
     pub door: Option<Door>,
     pub mobprog: Option<MobProg>,
-    pub silver: Option<Silver>,
+    /// Coins, in a pile of them
+    pub wealth: Option<Wealth>,
+    /// Hit points, mana and moves of players and mobiles
+    #[serde(default)]
+    pub vitals: Option<Vitals>,
+    /// Corpses, food on the ground and conjured things, which don't last
+    #[serde(default)]
+    pub decay: Option<Decay>,
+    /// Flags and counters of mobprogs, quests and builders, by name
+    #[serde(default)]
+    pub attributes: Option<Attributes>,
+}
+
+impl Components {
+    /// Go through every interned string of the components.
+    fn for_each_string(&self, mut f: impl FnMut(&IntStr)) {
+        f(&self.act_info.keyword);
+        f(&self.act_info.short_description);
+
+        let descriptions = &self.descriptions;
+        f(&descriptions.internal_title);
+        f(&descriptions.internal);
+        f(&descriptions.external);
+        f(&descriptions.lateral);
+        descriptions.night_internal.iter().for_each(&mut f);
+        descriptions.night_lateral.iter().for_each(&mut f);
+
+        if let Some(attributes) = &self.attributes {
+            attributes.values.keys().for_each(f);
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct GeneralData {
     pub vnum: Vnum,
-    pub area: String,
-    pub sector: Option<String>,
+    pub area: Arc<str>,
+    pub sector: Option<Arc<str>>,
     pub entity_type: EntityType,
+    /// What made it, if a game feature did; see `crate::archetypes`
+    #[serde(default)]
+    pub archetype: Option<Arc<str>>,
     pub equipped: Option<String>,
     pub command_queue: Vec<(u16, String)>,
-    pub following: Option<String>,
+    /// Who it follows around. Snapshots from before this was a weak reference
+    /// have the keyword of the leader instead, which is dropped
+    #[serde(default, deserialize_with = "deserialize_following")]
+    pub following: Option<WeakEntityRef>,
+    /// Spawned by a reset for the day or the night only, and gone when it ends
+    #[serde(default)]
+    pub only_at: Option<TimeOfDay>,
+    /// Keys of the area file of its template that the game has no use for,
+    /// shared by everything spawned from it; see `crate::world::Mobile`
+    #[serde(default)]
+    pub extras: Option<Arc<[(String, String)]>>,
+}
+
+fn deserialize_following<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<WeakEntityRef>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Following {
+        Leader(WeakEntityRef),
+        Keyword(serde::de::IgnoredAny),
+    }
+
+    Ok(match Option::<Following>::deserialize(deserializer)? {
+        Some(Following::Leader(leader)) => Some(leader),
+        Some(Following::Keyword(_)) | None => None,
+    })
 }
 
-#[derive(Hash, Clone, Copy, PartialEq, Eq)]
+#[derive(Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum EntityType {
     Player,
     Mobile,
@@ -44,47 +129,375 @@ pub enum EntityType {
     MobProg,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Mobile {
     pub wander: bool,
-    pub shopkeeper: Option<Shop>,
+    /// Doesn't wander out of the area it is in
+    #[serde(default)]
+    pub stay_area: bool,
+    /// Can wander through the air, and over deep water
+    #[serde(default)]
+    pub flying: bool,
+    /// Picks up the most valuable thing lying in its room now and then
+    #[serde(default)]
+    pub scavenger: bool,
+    /// Aggressive scavengers also wear or wield what they find, if it is
+    /// worth more than what they have on
+    #[serde(default)]
+    pub aggressive: bool,
+    pub shopkeeper: Option<Arc<Shop>>,
     pub remember: Option<String>,
+    /// Mobile pulses left to hunt whoever it remembers, or 0
+    #[serde(default)]
+    pub hunting: u32,
+    /// Room it is walking to, one step every mobile pulse
+    #[serde(default)]
+    pub walk_to: Option<Vnum>,
+    /// Sends and delivers mail for players in the same room
+    pub postmaster: bool,
+    /// Keeps silver in the bank for players in the same room
+    #[serde(default)]
+    pub banker: bool,
+    /// Mends worn weapons and armor for players in the same room
+    #[serde(default)]
+    pub repairer: bool,
+    /// Coins it carries when it is spawned
+    #[serde(default)]
+    pub wealth: Wealth,
+    /// Player who bought it from a pet shop
+    #[serde(default)]
+    pub pet_of: Option<String>,
+    /// Spawned as a wandering encounter, and leaves when nobody is around
+    #[serde(default)]
+    pub encounter: bool,
+    /// Players who haggled with this shopkeeper since their last deal with
+    /// it, and how many percent better that deal is for them
+    #[serde(default)]
+    pub haggled: Vec<(String, i64)>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Object {
     pub cost: i32,
     pub key: Option<Vnum>,
     pub container: bool,
     pub food: bool,
+    /// Can be drunk from, like a fountain or a flask
+    #[serde(default)]
+    pub drink: bool,
+    /// Name of the note board that can be read through this object
+    pub board: Option<String>,
+    /// Bought by a shopkeeper from a player, to be sold again only once
+    #[serde(default)]
+    pub resold: bool,
+    /// Can be picked up by scavengers
+    #[serde(default)]
+    pub take: bool,
+    /// Where it is worn, as a wear flag like "body" or "wield"
+    #[serde(default)]
+    pub wear: Option<String>,
+    /// Torches and lanterns, which burn while lit
+    #[serde(default)]
+    pub light: Option<Light>,
+    /// What it holds, for fountains and drink containers, e.g. "water"
+    #[serde(default)]
+    pub liquid: Option<String>,
+    /// Carries whoever holds it over deep water
+    #[serde(default)]
+    pub boat: bool,
+    /// How worn a weapon or a piece of armor is, from 100 when new down to
+    /// 0, when it breaks; see `crate::durability`
+    #[serde(default)]
+    pub condition: Option<u8>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Light {
+    pub lit: bool,
+    /// Game hours it can still burn for, or None if it never burns out
+    pub hours: Option<u16>,
+    /// Hours of oil it holds when filled up, or 0 if it can't be refilled
+    pub capacity: u16,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Door {
     pub closed: bool,
     pub locked: bool,
     pub key: Option<Vnum>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct MobProg {
     pub trigger: MobProgTrigger,
-    pub code: String,
+    pub code: Arc<str>,
+}
+
+/// Kinds of coins, from the least to the most valuable
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Denomination {
+    Copper,
+    Silver,
+    Gold,
+}
+
+impl Denomination {
+    pub const ALL: [Denomination; 3] = [
+        Denomination::Copper,
+        Denomination::Silver,
+        Denomination::Gold,
+    ];
+
+    /// What a coin is worth in copper coins
+    pub fn worth(self) -> usize {
+        match self {
+            Denomination::Copper => 1,
+            Denomination::Silver => COPPER_PER_SILVER,
+            Denomination::Gold => COPPER_PER_SILVER * SILVER_PER_GOLD,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Denomination::Copper => "copper",
+            Denomination::Silver => "silver",
+            Denomination::Gold => "gold",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Denomination> {
+        Denomination::ALL
+            .iter()
+            .copied()
+            .find(|denomination| denomination.name() == name)
+    }
+}
+
+/// Copper coins a silver coin is worth
+pub(crate) const COPPER_PER_SILVER: usize = 10;
+/// Silver coins a gold coin is worth
+pub(crate) const SILVER_PER_GOLD: usize = 100;
+
+/// Coins of each denomination. Prices, bank accounts and the like are in
+/// silver; coins of any denomination pay for them, and change comes back in
+/// smaller coins.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Wealth {
+    pub copper: usize,
+    pub silver: usize,
+    pub gold: usize,
+}
+
+impl Wealth {
+    /// Coins worth some silver, in as many gold coins as they make up
+    pub fn from_silver(amount: usize) -> Wealth {
+        Wealth {
+            copper: 0,
+            silver: amount % SILVER_PER_GOLD,
+            gold: amount / SILVER_PER_GOLD,
+        }
+    }
+
+    pub fn coins(&self, denomination: Denomination) -> usize {
+        match denomination {
+            Denomination::Copper => self.copper,
+            Denomination::Silver => self.silver,
+            Denomination::Gold => self.gold,
+        }
+    }
+
+    pub fn coins_mut(&mut self, denomination: Denomination) -> &mut usize {
+        match denomination {
+            Denomination::Copper => &mut self.copper,
+            Denomination::Silver => &mut self.silver,
+            Denomination::Gold => &mut self.gold,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        Denomination::ALL
+            .iter()
+            .all(|denomination| self.coins(*denomination) == 0)
+    }
+
+    /// What all the coins are worth in copper
+    pub fn value(&self) -> usize {
+        Denomination::ALL
+            .iter()
+            .map(|denomination| self.coins(*denomination) * denomination.worth())
+            .sum()
+    }
+
+    /// What all the coins are worth in silver, leaving out spare copper
+    pub fn silver_value(&self) -> usize {
+        self.value() / COPPER_PER_SILVER
+    }
+
+    pub fn add(&mut self, other: &Wealth) {
+        for denomination in Denomination::ALL {
+            *self.coins_mut(denomination) += other.coins(denomination);
+        }
+    }
+
+    /// Pay a price in copper, with the smallest coins first. When a coin is
+    /// worth more than what is left to pay, the change comes back in the
+    /// largest smaller coins. Nothing is paid if the coins aren't enough.
+    pub fn pay(&mut self, price: usize) -> bool {
+        if self.value() < price {
+            return false;
+        }
+
+        let mut owed = price;
+        for denomination in Denomination::ALL {
+            if owed == 0 {
+                break;
+            }
+
+            let worth = denomination.worth();
+            let coins = self.coins_mut(denomination);
+            let paid = (*coins).min(owed.div_ceil(worth));
+            *coins -= paid;
+
+            if paid * worth < owed {
+                owed -= paid * worth;
+            } else {
+                let mut change = paid * worth - owed;
+                owed = 0;
+                for smaller in Denomination::ALL.iter().rev() {
+                    if smaller.worth() < worth {
+                        *self.coins_mut(*smaller) += change / smaller.worth();
+                        change %= smaller.worth();
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Keywords of a pile of these coins, like "coins gold silver"
+    pub fn keywords(&self) -> String {
+        let mut keywords = vec!["coins"];
+        for denomination in Denomination::ALL.iter().rev() {
+            if self.coins(*denomination) != 0 {
+                keywords.push(denomination.name());
+            }
+        }
+        keywords.join(" ")
+    }
+}
+
+/// Like "3 gold, 12 silver and 5 copper coins", or "some coins" if there
+/// are none
+impl fmt::Display for Wealth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = Denomination::ALL
+            .iter()
+            .rev()
+            .filter(|denomination| self.coins(**denomination) != 0)
+            .map(|denomination| format!("{} {}", self.coins(*denomination), denomination.name()))
+            .collect();
+
+        match parts.split_last() {
+            None => write!(f, "some coins"),
+            Some((last, [])) => write!(f, "{} coins", last),
+            Some((last, rest)) => write!(f, "{} and {} coins", rest.join(", "), last),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Position {
+    Standing,
+    Resting,
+    Sleeping,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Vitals {
+    pub hit: u32,
+    pub max_hit: u32,
+    pub mana: u32,
+    pub max_mana: u32,
+    pub moves: u32,
+    pub max_moves: u32,
+    pub position: Position,
+    /// Game hours until hungry, for players
+    pub hunger: u8,
+    /// Game hours until thirsty, for players
+    pub thirst: u8,
+    /// Game hours the poison lasts
+    pub poisoned: u16,
+    /// How drunk, sobering up by one every game hour
+    #[serde(default)]
+    pub drunk: u8,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum DecayKind {
+    Corpse,
+    Food,
+    Conjured,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Decay {
+    pub kind: DecayKind,
+    /// Game hours until it is gone
+    pub ticks: u16,
+}
+
+/// Values tagged on an entity by name, e.g. `quest_stage=3`, so that
+/// mobprogs and builders can keep track of things without a component of
+/// their own. Names are interned like descriptions; see
+/// `EntityInfoMut::set_attribute()` and `EntityComponentInfo::attribute()`.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Attributes {
+    values: HashMap<IntStr, AttrValue, FixedState>,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum AttrValue {
+    Number(i64),
+    Text(String),
+}
+
+impl AttrValue {
+    /// A number if it reads as one, or else text.
+    pub fn parse(value: &str) -> Self {
+        match value.parse() {
+            Ok(number) => AttrValue::Number(number),
+            Err(_) => AttrValue::Text(value.to_string()),
+        }
+    }
+
+    /// The number, or 0 for text, so that counters start from nothing.
+    pub fn number(&self) -> i64 {
+        match self {
+            AttrValue::Number(number) => *number,
+            AttrValue::Text(_) => 0,
+        }
+    }
 }
 
-#[derive(Clone)]
-pub(crate) struct Silver {
-    pub amount: usize,
+impl fmt::Display for AttrValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttrValue::Number(number) => write!(f, "{}", number),
+            AttrValue::Text(text) => write!(f, "{}", text),
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct ActInfo {
     keyword: IntStr,
     short_description: IntStr,
     gender: Gender,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Descriptions {
     /// Internal title, seen when looking in the room inside of it (aka room title).
     /// Example: "In a forest."
@@ -98,6 +511,19 @@ pub(crate) struct Descriptions {
     /// Lateral description, seen when looking in the room ()
     /// Example: "An object is in the room here."
     lateral: IntStr,
+    /// Internal description seen at night instead, if different
+    #[serde(default)]
+    night_internal: Option<IntStr>,
+    /// Lateral description seen at night instead, if different.
+    /// Example: "A guard is dozing here."
+    #[serde(default)]
+    night_lateral: Option<IntStr>,
+}
+
+impl ActInfo {
+    pub fn set_gender(&mut self, gender: Gender) {
+        self.gender = gender;
+    }
 }
 
 pub(crate) trait ComponentFromEntity {
@@ -122,17 +548,211 @@ impl ComponentFromEntity for Mobile {
     }
 }
 
+impl ComponentFromEntity for Light {
+    fn component_from_entity<'e>(entity: &EntityInfo<'e>) -> Option<&'e Self> {
+        entity
+            .components()
+            .object
+            .as_ref()
+            .and_then(|object| object.light.as_ref())
+    }
+}
+
 impl ComponentFromEntity for Shop {
     fn component_from_entity<'e>(entity: &EntityInfo<'e>) -> Option<&'e Self> {
         entity
             .components()
             .mobile
             .as_ref()
-            .and_then(|mobile| mobile.shopkeeper.as_ref())
+            .and_then(|mobile| mobile.shopkeeper.as_deref())
+    }
+}
+
+pub type MyStringInterner =
+    StringInterner<string_interner::backend::StringBackend<string_interner::DefaultSymbol>>;
+
+pub(crate) trait InternerStatistics {
+    /// How many different strings are interned.
+    fn symbol_count(&self) -> usize;
+    /// Bytes taken by the interned strings, and by the index of where each
+    /// of them ends.
+    fn bytes_retained(&self) -> usize;
+}
+
+impl InternerStatistics for MyStringInterner {
+    fn symbol_count(&self) -> usize {
+        self.len()
+    }
+
+    fn bytes_retained(&self) -> usize {
+        let text: usize = self.iter().map(|(_symbol, string)| string.len()).sum();
+        text + self.len() * std::mem::size_of::<usize>()
+    }
+}
+
+/// Strings of the interner of the entity world, and what interning them saves;
+/// see `EntityWorld::string_statistics()`.
+pub(crate) struct StringStatistics {
+    /// Different strings interned
+    pub strings: usize,
+    /// Strings interned that no entity or template uses anymore
+    pub unused: usize,
+    /// Bytes taken by the interned strings; see `bytes_retained()`
+    pub bytes_retained: usize,
+    /// Bytes more that the strings would take if every entity had its own
+    pub bytes_saved: usize,
+}
+
+/// How many times components use each string of an interner, counting
+/// shared descriptions once for each entity that has them.
+pub(crate) struct StringUses {
+    uses: Vec<usize>,
+}
+
+impl StringUses {
+    pub fn new(interner: &MyStringInterner) -> Self {
+        StringUses {
+            uses: vec![0; interner.len()],
+        }
+    }
+
+    /// Count the strings of some components.
+    pub fn count(&mut self, components: &Components) {
+        components.for_each_string(|string| self.uses[string.symbol.to_usize()] += 1);
+    }
+
+    fn is_used(&self, symbol: string_interner::DefaultSymbol) -> bool {
+        self.uses[symbol.to_usize()] != 0
+    }
+
+    pub fn statistics(&self, interner: &MyStringInterner) -> StringStatistics {
+        let mut statistics = StringStatistics {
+            strings: interner.symbol_count(),
+            unused: 0,
+            bytes_retained: interner.bytes_retained(),
+            bytes_saved: 0,
+        };
+        for (symbol, string) in interner {
+            match self.uses[symbol.to_usize()] {
+                0 => statistics.unused += 1,
+                uses => statistics.bytes_saved += (uses - 1) * string.len(),
+            }
+        }
+        statistics
     }
 }
 
-pub type MyStringInterner = StringInterner<string_interner::backend::StringBackend<string_interner::DefaultSymbol>>;
+/// Where the strings of an interner went after they were interned again in
+/// another one. Components can be made with strings in an interner of their
+/// own, e.g. while areas are imported on several threads, and then moved
+/// over to the interner of the entity world. The interner of the entity world
+/// is itself interned again, without the strings nothing uses, to drop them.
+pub(crate) struct MovedStrings {
+    symbols: Vec<Option<IntStr>>,
+}
+
+/// Parts of components whose strings were moved already, so that components
+/// that shared them still share the moved ones. The old parts are kept too,
+/// so that nothing else is allocated where they were while strings move.
+#[derive(Default)]
+pub(crate) struct SharedParts {
+    act_info: HashMap<*const ActInfo, (Arc<ActInfo>, Arc<ActInfo>)>,
+    descriptions: HashMap<*const Descriptions, (Arc<Descriptions>, Arc<Descriptions>)>,
+}
+
+impl MovedStrings {
+    /// Intern all the strings of `from` in `into`.
+    pub fn intern(into: &mut MyStringInterner, from: &MyStringInterner) -> Self {
+        let mut symbols = vec![None; from.len()];
+        for (symbol, string) in from {
+            symbols[symbol.to_usize()] = Some(IntStr {
+                symbol: into.get_or_intern(string),
+            });
+        }
+
+        MovedStrings { symbols }
+    }
+
+    /// Intern the strings of `from` that are used in `into`; components
+    /// with others can't be moved.
+    pub fn intern_used(
+        into: &mut MyStringInterner,
+        from: &MyStringInterner,
+        uses: &StringUses,
+    ) -> Self {
+        let mut symbols = vec![None; from.len()];
+        for (symbol, string) in from {
+            if uses.is_used(symbol) {
+                symbols[symbol.to_usize()] = Some(IntStr {
+                    symbol: into.get_or_intern(string),
+                });
+            }
+        }
+
+        MovedStrings { symbols }
+    }
+
+    fn moved(&self, string: &IntStr) -> IntStr {
+        self.symbols[string.symbol.to_usize()]
+            .clone()
+            .expect("Strings of components should have been moved")
+    }
+
+    /// Change the strings of components from the interner they were moved
+    /// from to the one they were moved into.
+    pub fn move_components(&self, components: &mut Components) {
+        self.move_shared_components(components, &mut SharedParts::default());
+    }
+
+    /// Like `move_components()`, but parts shared with components moved
+    /// before with the same `shared` stay shared.
+    pub fn move_shared_components(&self, components: &mut Components, shared: &mut SharedParts) {
+        let act_info = &components.act_info;
+        let (_old, moved) = shared
+            .act_info
+            .entry(Arc::as_ptr(act_info))
+            .or_insert_with(|| {
+                let moved = ActInfo {
+                    keyword: self.moved(&act_info.keyword),
+                    short_description: self.moved(&act_info.short_description),
+                    gender: act_info.gender,
+                };
+                (act_info.clone(), Arc::new(moved))
+            });
+        components.act_info = moved.clone();
+
+        let descriptions = &components.descriptions;
+        let (_old, moved) = shared
+            .descriptions
+            .entry(Arc::as_ptr(descriptions))
+            .or_insert_with(|| {
+                let moved = Descriptions {
+                    internal_title: self.moved(&descriptions.internal_title),
+                    internal: self.moved(&descriptions.internal),
+                    external: self.moved(&descriptions.external),
+                    lateral: self.moved(&descriptions.lateral),
+                    night_internal: descriptions
+                        .night_internal
+                        .as_ref()
+                        .map(|string| self.moved(string)),
+                    night_lateral: descriptions
+                        .night_lateral
+                        .as_ref()
+                        .map(|string| self.moved(string)),
+                };
+                (descriptions.clone(), Arc::new(moved))
+            });
+        components.descriptions = moved.clone();
+
+        if let Some(attributes) = &mut components.attributes {
+            attributes.values = attributes
+                .values
+                .drain()
+                .map(|(name, value)| (self.moved(&name), value))
+                .collect();
+        }
+    }
+}
 
 pub(crate) struct EntityComponentInfo<'i, 'c> {
     interner: &'i MyStringInterner,
@@ -140,51 +760,114 @@ pub(crate) struct EntityComponentInfo<'i, 'c> {
 }
 
 pub(crate) trait InternComponent {
-    fn act_info(&mut self, keyword: &str, short_description: &str, gender: Gender) -> ActInfo;
-    fn set_short_description(&mut self, act_info: &mut ActInfo, short_description: &str);
+    fn act_info(&mut self, keyword: &str, short_description: &str, gender: Gender) -> Arc<ActInfo>;
+    fn set_short_description(&mut self, act_info: &mut Arc<ActInfo>, short_description: &str);
+    fn set_keyword(&mut self, act_info: &mut Arc<ActInfo>, keyword: &str);
     fn descriptions(
         &mut self,
         title: &str,
         internal: &str,
         external: &str,
         lateral: &str,
-    ) -> Descriptions;
+    ) -> Arc<Descriptions>;
+    fn set_night_descriptions(
+        &mut self,
+        descriptions: &mut Arc<Descriptions>,
+        internal: Option<&str>,
+        lateral: Option<&str>,
+    );
+    fn set_attribute(
+        &mut self,
+        attributes: &mut Option<Attributes>,
+        name: &str,
+        value: Option<AttrValue>,
+    );
 }
 
 impl InternComponent for MyStringInterner {
-    fn act_info(&mut self, keyword: &str, short_description: &str, gender: Gender) -> ActInfo {
+    fn act_info(&mut self, keyword: &str, short_description: &str, gender: Gender) -> Arc<ActInfo> {
         let mut intern = |string| IntStr {
             symbol: self.get_or_intern(string),
         };
-        ActInfo {
+        Arc::new(ActInfo {
             keyword: intern(keyword),
             short_description: intern(short_description),
             gender,
-        }
+        })
     }
 
-    fn set_short_description(&mut self, act_info: &mut ActInfo, short_description: &str) {
-        // Note: old value is forever lost; this kinda leaks
-        act_info.short_description = IntStr {
+    fn set_short_description(&mut self, act_info: &mut Arc<ActInfo>, short_description: &str) {
+        // The old one stays interned until `EntityWorld::collect_strings()`
+        Arc::make_mut(act_info).short_description = IntStr {
             symbol: self.get_or_intern(short_description),
         };
     }
 
+    fn set_keyword(&mut self, act_info: &mut Arc<ActInfo>, keyword: &str) {
+        Arc::make_mut(act_info).keyword = IntStr {
+            symbol: self.get_or_intern(keyword),
+        };
+    }
+
     fn descriptions(
         &mut self,
         title: &str,
         internal: &str,
         external: &str,
         lateral: &str,
-    ) -> Descriptions {
+    ) -> Arc<Descriptions> {
         let mut intern = |string| IntStr {
             symbol: self.get_or_intern(string),
         };
-        Descriptions {
+        Arc::new(Descriptions {
             internal_title: intern(title),
             internal: intern(internal),
             external: intern(external),
             lateral: intern(lateral),
+            night_internal: None,
+            night_lateral: None,
+        })
+    }
+
+    fn set_night_descriptions(
+        &mut self,
+        descriptions: &mut Arc<Descriptions>,
+        internal: Option<&str>,
+        lateral: Option<&str>,
+    ) {
+        let mut intern = |string| IntStr {
+            symbol: self.get_or_intern(string),
+        };
+        let descriptions = Arc::make_mut(descriptions);
+        descriptions.night_internal = internal.map(&mut intern);
+        descriptions.night_lateral = lateral.map(&mut intern);
+    }
+
+    fn set_attribute(
+        &mut self,
+        attributes: &mut Option<Attributes>,
+        name: &str,
+        value: Option<AttrValue>,
+    ) {
+        match value {
+            Some(value) => {
+                let name = IntStr {
+                    symbol: self.get_or_intern(name),
+                };
+                attributes
+                    .get_or_insert_with(Attributes::default)
+                    .values
+                    .insert(name, value);
+            }
+            None => {
+                if let (Some(values), Some(symbol)) = (attributes.as_mut(), self.get(name)) {
+                    values.values.remove(&IntStr { symbol });
+                    // Entities without attributes go without the component
+                    if values.values.is_empty() {
+                        *attributes = None;
+                    }
+                }
+            }
         }
     }
 }
@@ -223,6 +906,22 @@ impl<'i, 'c> EntityComponentInfo<'i, 'c> {
         self.resolve(&self.components.descriptions.lateral)
     }
 
+    /// The internal description, or the one for the night if it has one.
+    pub fn internal_description_at(&self, time_of_day: TimeOfDay) -> &'i str {
+        match &self.components.descriptions.night_internal {
+            Some(night) if time_of_day == TimeOfDay::Night => self.resolve(night),
+            _ => self.internal_description(),
+        }
+    }
+
+    /// The lateral description, or the one for the night if it has one.
+    pub fn lateral_description_at(&self, time_of_day: TimeOfDay) -> &'i str {
+        match &self.components.descriptions.night_lateral {
+            Some(night) if time_of_day == TimeOfDay::Night => self.resolve(night),
+            _ => self.lateral_description(),
+        }
+    }
+
     pub fn gender(&self) -> Gender {
         self.components.act_info.gender
     }
@@ -230,4 +929,26 @@ impl<'i, 'c> EntityComponentInfo<'i, 'c> {
     pub fn keyword(&self) -> &'i str {
         self.resolve(&self.components.act_info.keyword)
     }
+    /// The value of an attribute, if it has one by that name.
+    pub fn attribute(&self, name: &str) -> Option<&'c AttrValue> {
+        let symbol = self.interner.get(name)?;
+        self.components
+            .attributes
+            .as_ref()?
+            .values
+            .get(&IntStr { symbol })
+    }
+
+    /// All of its attributes, by name.
+    pub fn attributes(&self) -> Vec<(&'i str, &'c AttrValue)> {
+        let mut attributes: Vec<_> = self
+            .components
+            .attributes
+            .iter()
+            .flat_map(|attributes| &attributes.values)
+            .map(|(name, value)| (self.resolve(name), value))
+            .collect();
+        attributes.sort_by_key(|(name, _value)| *name);
+        attributes
+    }
 }
@@ -0,0 +1,200 @@
+//! The parts of the server configuration that the mudlib uses; see
+//! `netcore::config` for where the file is and how settings are overridden.
+//!
+//! ```toml
+//! [world]
+//! areas = "data/area"          # directory or archive with arealist.txt
+//! socials = "data/socials.txt"
+//! start = "gnomehill"          # landmark characters start at
+//! death = "limbo"              # landmark dead players wait at
+//! jail = "void"                # landmark frozen players are kept at
+//! recall = "mudschool"         # landmark `recall` takes players to
+//! junk = "limbo"               # landmark what purged things held goes to
+//! lazy_areas = false           # import areas once someone goes in them
+//! dungeon_rooms = 0            # rooms of a generated dungeon, if any
+//!
+//! [[world.sources]]            # more directories or archives with an arealist.txt
+//! directory = "data/rom"
+//! vnum_offset = 100000         # added to every vnum of their areas
+//! prefix = "rom-"              # put before the short names of their areas
+//!
+//! [landmarks]                  # room vnums, for goto and the start
+//! gnomehill = 23611
+//!
+//! [limits]
+//! aliases = 30
+//! ```
+//!
+//! Areas from other sources, like those of another MUD whose vnums are the
+//! same as ours, are loaded after those of `areas`, with their vnums moved
+//! up by the offset; landmarks use the vnums after the move. See
+//! `crate::load::apply_source()`.
+//!
+//! Landmarks whose room doesn't exist, and a start, death, jail, recall or junk
+//! landmark that isn't in the table, are put in the room with the lowest
+//! vnum instead; see `crate::import::place_landmarks()`, which also tells
+//! the entity world which rooms they are.
+//!
+//! With `lazy_areas`, large worlds start faster, as most of their areas are
+//! only imported when they are first needed; see `crate::lazy`.
+//!
+//! `[world]` and `[landmarks]` are used when the world is loaded, so changes
+//! to them take effect on the next `restart`. `[limits]` are looked up when
+//! they are needed, so `reload config` changes them right away. `[server]` is
+//! netcore's, and is only read when the server starts.
+
+use std::collections::BTreeMap;
+
+use serde::{de::IgnoredAny, Deserialize};
+
+use crate::entity::Place;
+
+/// Landmarks the game needs, and their rooms unless the file says otherwise.
+const DEFAULT_LANDMARKS: &[(&str, usize)] = &[
+    ("gnomehill", 23611),
+    ("mekali", 3000),
+    ("dzagari", 27003),
+    ("mudschool", 7371),
+    ("void", 1),
+    ("limbo", 2),
+];
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+    pub world: WorldConfig,
+    /// Added to the default landmarks, or moving them
+    landmarks: BTreeMap<String, usize>,
+    pub limits: Limits,
+    /// netcore's
+    server: IgnoredAny,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct WorldConfig {
+    /// Directory with the area files and their arealist.txt, or a `.tar.zst`,
+    /// `.tar.gz` or `.zip` archive of them; see `crate::archive`
+    pub areas: String,
+    pub socials: String,
+    /// Landmark where characters start
+    pub start: String,
+    /// Landmark where dead players wait to be restored
+    pub death: String,
+    /// Landmark where frozen players are kept
+    pub jail: String,
+    /// Landmark where `recall` takes players without saying where to
+    pub recall: String,
+    /// Landmark where what was in purged things goes
+    pub junk: String,
+    /// Other directories of areas, loaded after those of `areas`
+    pub sources: Vec<AreaSource>,
+    /// Import only the areas of landmarks and those next to them when the
+    /// game starts, and the others once someone goes in them
+    pub lazy_areas: bool,
+    /// Rooms of a dungeon generated after the areas are loaded, if any; see
+    /// `crate::procgen`
+    pub dungeon_rooms: usize,
+}
+
+/// A directory of areas from elsewhere, whose vnums and short names are
+/// changed so that they don't collide with those of other areas
+#[derive(Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct AreaSource {
+    /// Directory with the area files and their arealist.txt, or an archive
+    /// of them
+    pub directory: String,
+    /// Added to every vnum in the areas
+    pub vnum_offset: usize,
+    /// Put before the short name of each area
+    pub prefix: String,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        WorldConfig {
+            areas: "data/area".to_string(),
+            socials: "data/socials.txt".to_string(),
+            start: "gnomehill".to_string(),
+            death: "limbo".to_string(),
+            jail: "void".to_string(),
+            recall: "mudschool".to_string(),
+            junk: "limbo".to_string(),
+            sources: Vec::new(),
+            lazy_areas: false,
+            dungeon_rooms: 0,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Limits {
+    /// Aliases each player may have
+    pub aliases: usize,
+    /// Tells waiting for an offline player from the same sender
+    pub queued_tells: usize,
+    /// Wilderness encounters around at once
+    pub encounters: usize,
+    /// Minutes between scheduled snapshots of the world
+    pub snapshot_minutes: u64,
+    /// Snapshots kept before the oldest ones are thrown away
+    pub snapshots: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            aliases: 30,
+            queued_tells: 5,
+            encounters: 20,
+            snapshot_minutes: 30,
+            snapshots: 24,
+        }
+    }
+}
+
+impl WorldConfig {
+    /// The landmark of each place the game sends characters to.
+    pub fn places(&self) -> [(Place, &str); 5] {
+        [
+            (Place::Start, &self.start),
+            (Place::Death, &self.death),
+            (Place::Jail, &self.jail),
+            (Place::Recall, &self.recall),
+            (Place::Junk, &self.junk),
+        ]
+    }
+}
+
+impl Config {
+    /// Read the configuration file, with the overrides of the environment and
+    /// of the command line.
+    #[cfg(feature = "net")]
+    pub fn load() -> Result<Config, String> {
+        netcore::config::load_config()?
+            .try_into()
+            .map_err(|err| err.to_string().trim_end().replace('\n', " "))
+    }
+
+    /// The CLI has no configuration file.
+    #[cfg(not(feature = "net"))]
+    pub fn load() -> Result<Config, String> {
+        Ok(Config::default())
+    }
+
+    /// Room vnums of each landmark.
+    pub fn landmarks(&self) -> BTreeMap<String, usize> {
+        let mut landmarks: BTreeMap<String, usize> = DEFAULT_LANDMARKS
+            .iter()
+            .map(|(landmark, vnum)| (landmark.to_string(), *vnum))
+            .collect();
+        landmarks.extend(
+            self.landmarks
+                .iter()
+                .map(|(landmark, vnum)| (landmark.to_lowercase(), *vnum)),
+        );
+        landmarks
+    }
+}
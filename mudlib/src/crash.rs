@@ -0,0 +1,196 @@
+//! Recovering from crashes of the server.
+//!
+//! While the server runs, a panic hook remembers the message and location of
+//! each panic, with a backtrace. When a command or an update of the world
+//! panics, the game that crashed is saved as well as it still can be:
+//! accounts and everything else that changed, and a snapshot of the world
+//! that immortals can roll back to with `snapshot restore`. A crash report
+//! with the panic, the backtrace, the seed and the last commands players
+//! typed is written to `data/crashes/<unix time>.txt`, and a new game is
+//! created while players stay connected.
+//!
+//! A panic anywhere else ends the mudlib, after the same save and report.
+//! netcore then stops the server, or starts it again from scratch if the
+//! `DEMIMUD_RESTART_ON_CRASH` environment variable is set.
+
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::Mutex,
+};
+
+use crate::{
+    files::{Files, RealFiles},
+    snapshots::take_snapshot,
+    state::unix_time,
+    WorldState,
+};
+
+const CRASHES_DIRECTORY: &str = "data/crashes";
+/// Commands kept for crash reports
+const RECENT_COMMANDS: usize = 50;
+
+struct PanicReport {
+    message: String,
+    location: String,
+    backtrace: String,
+}
+
+/// The last panic, as the hook saw it
+static LAST_PANIC: Mutex<Option<PanicReport>> = Mutex::new(None);
+
+/// Remembers panics for crash reports until it is dropped. The default hook
+/// is set again then, as a hook can't outlive the mudlib library it is in.
+pub(crate) struct PanicHook;
+
+impl PanicHook {
+    pub fn install() -> Self {
+        let previous = std::panic::take_hook();
+
+        std::panic::set_hook(Box::new(move |info| {
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "(no message)".to_string());
+
+            let report = PanicReport {
+                message,
+                location: info
+                    .location()
+                    .map(|location| location.to_string())
+                    .unwrap_or_default(),
+                backtrace: Backtrace::force_capture().to_string(),
+            };
+            *LAST_PANIC.lock().unwrap_or_else(|err| err.into_inner()) = Some(report);
+
+            previous(info);
+        }));
+
+        PanicHook
+    }
+}
+
+impl Drop for PanicHook {
+    fn drop(&mut self) {
+        // Hooks can't be changed while unwinding, which aborts the process
+        // at the end of `do_things()` anyway.
+        if !std::thread::panicking() {
+            let _ = std::panic::take_hook();
+        }
+    }
+}
+
+pub(crate) struct Crashes {
+    recent_commands: VecDeque<String>,
+}
+
+impl Crashes {
+    pub fn new() -> Self {
+        Crashes {
+            recent_commands: VecDeque::with_capacity(RECENT_COMMANDS),
+        }
+    }
+
+    pub fn record_command(&mut self, player: &str, command: &str) {
+        if self.recent_commands.len() == RECENT_COMMANDS {
+            self.recent_commands.pop_front();
+        }
+        self.recent_commands
+            .push_back(format!("{} {}: {}", unix_time(), player, command));
+    }
+
+    /// Run part of the game, and if it panics, save what can be saved of it
+    /// and write a crash report. Returns whether it crashed; the game
+    /// shouldn't be used anymore then.
+    pub fn run<F>(&self, world_state: &mut WorldState, doing: &str, run: F) -> bool
+    where
+        F: FnOnce(&mut WorldState),
+    {
+        match catch_unwind(AssertUnwindSafe(|| run(world_state))) {
+            Ok(()) => false,
+            Err(_) => {
+                self.recover(world_state, doing);
+                true
+            }
+        }
+    }
+
+    /// Save what can be saved of a game that crashed while doing something,
+    /// and write a crash report about it.
+    pub fn recover(&self, world_state: &mut WorldState, doing: &str) {
+        let files = RealFiles;
+        // Taken first, as saving may panic again
+        let panic = LAST_PANIC
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+
+        // The game may be broken in ways that make saving it panic too
+        let saved = catch_unwind(AssertUnwindSafe(|| {
+            let players: Vec<String> = world_state
+                .entity_world
+                .all_players()
+                .filter_map(|player| player.player_name().map(|name| name.to_string()))
+                .collect();
+            let saved = world_state
+                .save_changes(&files)
+                .and_then(|()| take_snapshot(world_state, &files));
+            (players, saved)
+        }));
+
+        let names = |players: Vec<String>| {
+            if players.is_empty() {
+                "(none)".to_string()
+            } else {
+                players.join(", ")
+            }
+        };
+        let (players, saved) = match saved {
+            Ok((players, Ok(snapshot))) => (names(players), format!("snapshot {}", snapshot)),
+            Ok((players, Err(err))) => (names(players), format!("failed: {}", err)),
+            Err(_) => (
+                "(unknown)".to_string(),
+                "failed: it panicked too".to_string(),
+            ),
+        };
+
+        let mut report = format!(
+            "DemiMUD crashed while {}, at {}.\n\
+            Seed: {}\n\
+            Players: {}\n\
+            Saved: {}\n\n",
+            doing,
+            unix_time(),
+            world_state.seed(),
+            players,
+            saved
+        );
+        match &panic {
+            Some(panic) => report.push_str(&format!(
+                "Panic: {}\nAt: {}\n\n",
+                panic.message, panic.location
+            )),
+            None => report.push_str("The panic was not seen by the panic hook.\n\n"),
+        }
+
+        report.push_str("Last commands, oldest first:\n");
+        for command in &self.recent_commands {
+            report.push_str(command);
+            report.push('\n');
+        }
+
+        if let Some(panic) = &panic {
+            report.push_str("\nBacktrace:\n");
+            report.push_str(&panic.backtrace);
+        }
+
+        let path = format!("{}/{}.txt", CRASHES_DIRECTORY, unix_time());
+        match files.write_file_raw(&path, report.as_bytes()) {
+            Ok(()) => println!("Crashed while {}; wrote {}, {}.", doing, path, saved),
+            Err(err) => println!("Crashed while {}; could not write {}: {}", doing, path, err),
+        }
+    }
+}
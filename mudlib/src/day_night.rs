@@ -0,0 +1,61 @@
+//! Rooms and mobiles that look different at night, and mobiles and objects
+//! that are only around during the day or the night.
+//!
+//! Rooms can have a `NightDesc` that is seen instead of their description
+//! from sunset to sunrise, and mobiles a `NightLongD` that is seen instead of
+//! their long description, e.g. for a guard dozing at night.
+//!
+//! The first number of `M` and `O` resets, which is always 0 in Dawn of Time,
+//! can be `day` or `night` instead, as in `M night 3011 1 3001 1`. Those
+//! resets only spawn their mobile or object when that time comes, or when the
+//! game starts during it, and what they spawned goes away again when it ends.
+//! Objects that were picked up by then are kept.
+
+use crate::{
+    components::EntityType, echo, entity::EntityId, import::run_resets, world::TimeOfDay,
+    WorldState,
+};
+
+/// Remove what was only around until now, and spawn what is only around from
+/// now on, when day turns to night or night to day.
+pub(crate) fn update_day_night(world_state: &mut WorldState, time_of_day: TimeOfDay) {
+    let limbo = world_state
+        .entity_world
+        .landmark("limbo")
+        .expect("Limbo should always exist");
+
+    let gone: Vec<EntityId> = world_state
+        .entity_world
+        .all_entities()
+        .filter(|entity| {
+            matches!(entity.components().general.only_at, Some(only_at) if only_at != time_of_day)
+        })
+        .filter(|entity| {
+            let room = entity.room();
+            room.entity_id() != limbo
+                && (entity.is_mobile() || room.components().general.entity_type == EntityType::Room)
+        })
+        .map(|entity| entity.entity_id())
+        .collect();
+
+    for entity_id in gone {
+        let entity = world_state.entity_world.entity_info(entity_id);
+        let message = if entity.is_mobile() {
+            "$^$n slips away."
+        } else {
+            "$^$n fades away."
+        };
+        echo!(
+            world_state.players.act_alone(&entity).others(),
+            "{}\r\n",
+            message
+        );
+        world_state.entity_world.move_entity(entity_id, limbo);
+    }
+
+    for (_area_data, area_resets) in &world_state.world.areas {
+        run_resets(&mut world_state.entity_world, area_resets, |only_at| {
+            only_at == Some(time_of_day)
+        });
+    }
+}
@@ -0,0 +1,159 @@
+//! Things that don't last: corpses rot, food left on the ground spoils, and
+//! conjured things fade away.
+//!
+//! Anything with a decay counts down its game hours every tick, and those
+//! around it see it go through a few stages before it is gone for good.
+//! Players and mobiles leave a corpse behind when they die. Food only spoils
+//! while it lies on the ground, from when it was dropped; what resets put
+//! there keeps. Immortals can make anything fade away with `set <object>
+//! decay <hours>`. Whatever was in something that is gone is left where it
+//! was.
+
+use crate::{
+    agent::EntityAgent,
+    archetypes::CORPSE,
+    components::{Decay, DecayKind, EntityType, Object},
+    echo,
+    entity::{ContentsPolicy, EntityId, EntityInfo},
+    WorldState,
+};
+
+/// Game hours a corpse lasts
+const CORPSE_TICKS: u16 = 6;
+/// Game hours food lasts on the ground
+const FOOD_TICKS: u16 = 10;
+
+impl Decay {
+    /// Decay for an object, as food if it can be eaten.
+    pub fn for_object(object: &EntityInfo, ticks: u16) -> Self {
+        let food = object
+            .components()
+            .object
+            .as_ref()
+            .map(|object| object.food)
+            .unwrap_or(false);
+
+        Decay {
+            kind: if food {
+                DecayKind::Food
+            } else {
+                DecayKind::Conjured
+            },
+            ticks,
+        }
+    }
+}
+
+/// What happens to something that is decaying, with that many hours left.
+fn decay_stage(kind: DecayKind, ticks: u16) -> Option<&'static str> {
+    match (kind, ticks) {
+        (DecayKind::Corpse, 3) => Some("is buzzing with flies."),
+        (DecayKind::Corpse, 1) => Some("is rotting away."),
+        (DecayKind::Corpse, 0) => Some("decays into dust."),
+        (DecayKind::Food, 4) => Some("is starting to smell."),
+        (DecayKind::Food, 1) => Some("is covered in mould."),
+        (DecayKind::Food, 0) => Some("rots away."),
+        (DecayKind::Conjured, 1) => Some("flickers."),
+        (DecayKind::Conjured, 0) => Some("fades away."),
+        _ => None,
+    }
+}
+
+fn lies_in_room(entity: &EntityInfo) -> bool {
+    entity.room().components().general.entity_type == EntityType::Room
+}
+
+/// Tell those around an object what happens to it, e.g. "is rotting away.":
+/// everyone in the room if it lies on the ground, or else whoever holds it.
+pub(crate) fn tell_around(world_state: &mut WorldState, object_id: EntityId, what: &str) {
+    let object = world_state.entity_world.entity_info(object_id);
+    if lies_in_room(&object) {
+        let mut act = world_state.players.act_alone(&object);
+        echo!(act.others(), "$^$n {}\r\n", what);
+    } else {
+        let holder = object.room();
+        let mut act = world_state.players.act_with(&holder, &object);
+        echo!(act.myself(), "$^$N {}\r\n", what);
+    }
+}
+
+pub(crate) fn update_decay(world_state: &mut WorldState) {
+    let limbo = world_state
+        .entity_world
+        .landmark("limbo")
+        .expect("Limbo should always exist");
+
+    let decaying: Vec<EntityId> = world_state
+        .entity_world
+        .query::<&Decay>()
+        .filter(|(entity, decay)| decay.kind != DecayKind::Food || lies_in_room(entity))
+        .filter(|(entity, _decay)| entity.room().entity_id() != limbo)
+        .map(|(entity, _decay)| entity.entity_id())
+        .collect();
+
+    for entity_id in decaying {
+        let mut entity = world_state.entity_world.entity_info_mut(entity_id);
+        let (kind, ticks) = match &mut entity.components().decay {
+            Some(decay) => {
+                decay.ticks = decay.ticks.saturating_sub(1);
+                (decay.kind, decay.ticks)
+            }
+            None => continue,
+        };
+
+        if let Some(stage) = decay_stage(kind, ticks) {
+            tell_around(world_state, entity_id, stage);
+        }
+
+        if ticks == 0 {
+            world_state
+                .entity_world
+                .extract_entity(entity_id, ContentsPolicy::Dump);
+        }
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Leave the corpse of the current entity where it is.
+    pub(crate) fn make_corpse(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let name = myself.component_info().short_description().to_string();
+        let room_id = myself.room().entity_id();
+
+        self.entity_world
+            .spawn_archetype(CORPSE, room_id, &name, |components| {
+                components.object = Some(Object {
+                    cost: 0,
+                    key: None,
+                    container: false,
+                    food: false,
+                    drink: false,
+                    board: None,
+                    resold: false,
+                    take: false,
+                    wear: None,
+                    light: None,
+                    liquid: None,
+                    boat: false,
+                    condition: None,
+                });
+                components.decay = Some(Decay {
+                    kind: DecayKind::Corpse,
+                    ticks: CORPSE_TICKS,
+                });
+            })
+            .expect("Corpses are registered with the other archetypes");
+    }
+
+    /// Start spoiling food that was just dropped on the ground, unless it
+    /// already is.
+    pub(crate) fn start_spoiling(&mut self, object_id: EntityId) {
+        let object = self.entity_world.entity_info(object_id);
+        let decay = Decay::for_object(&object, FOOD_TICKS);
+
+        if decay.kind == DecayKind::Food && object.components().decay.is_none() {
+            let mut object = self.entity_world.entity_info_mut(object_id);
+            object.components().decay = Some(decay);
+        }
+    }
+}
@@ -0,0 +1,493 @@
+//! Differences between two loaded worlds, or between an area of the game
+//! and its file, for builders to review before `reload area` replaces it.
+//!
+//! Rooms, mobiles and objects are matched by vnum, and exits by their room
+//! and direction: those only in the new world were added, those only in the
+//! old one were removed, and for the others the names of the fields that
+//! differ are listed, like "description" or "key". Resets have no vnum of
+//! their own; they are matched within their area by what they spawn, and a
+//! reset that was removed and one that was added for the same thing count
+//! as one that changed. Shops and mobprogs aren't compared.
+//!
+//! `reload diff <area>` shows what reloading an area would change, and
+//! `diff_worlds()` compares two worlds loaded from different files.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    world::{Area, Exit, Mobile, Object, ResetCommand, Room, TimeOfDay, World},
+    WorldState,
+};
+
+/// Whether something is new, gone or different
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// What changed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Template {
+    Room,
+    /// An exit, by the vnum of its room
+    Exit,
+    Mobile,
+    Object,
+    /// A reset, by the vnum of the mobile or object it spawns, or of the
+    /// room of its door
+    Reset,
+}
+
+/// Something that differs between two worlds
+#[derive(Clone, Debug)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub template: Template,
+    pub vnum: usize,
+    /// Short name of the area
+    pub area: String,
+    /// The name of what was added or removed, or which of its fields changed
+    pub detail: String,
+}
+
+/// Everything that differs between two worlds
+#[derive(Default, Debug)]
+pub struct WorldDiff {
+    pub changes: Vec<Change>,
+}
+
+impl WorldDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// One line with how many things were added, removed and changed.
+    pub fn summary(&self) -> String {
+        let count = |kind: ChangeKind| {
+            self.changes
+                .iter()
+                .filter(|change| change.kind == kind)
+                .count()
+        };
+        format!(
+            "{} added, {} removed and {} changed",
+            count(ChangeKind::Added),
+            count(ChangeKind::Removed),
+            count(ChangeKind::Changed)
+        )
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+        };
+        match self.template {
+            Template::Room => write!(f, "{} room {}", kind, self.vnum)?,
+            Template::Exit => write!(f, "{} exit of room {}", kind, self.vnum)?,
+            Template::Mobile => write!(f, "{} mobile {}", kind, self.vnum)?,
+            Template::Object => write!(f, "{} object {}", kind, self.vnum)?,
+            Template::Reset => write!(f, "{} reset", kind)?,
+        }
+        write!(f, " in {}: {}", self.area, self.detail)
+    }
+}
+
+impl fmt::Display for WorldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        writeln!(f, "{}.", self.summary())
+    }
+}
+
+/// The templates and resets of one side of a comparison
+#[derive(Default)]
+struct Side<'a> {
+    rooms: BTreeMap<usize, &'a Room>,
+    mobiles: BTreeMap<usize, &'a Mobile>,
+    objects: BTreeMap<usize, &'a Object>,
+    /// Resets, with the short names of their areas
+    resets: Vec<(&'a str, &'a ResetCommand)>,
+}
+
+impl<'a> Side<'a> {
+    /// Every area of a world; the templates of its empty slots don't count.
+    fn of_world(world: &'a World) -> Self {
+        Side {
+            rooms: by_vnum(&world.rooms, |room| room.vnum.0),
+            mobiles: by_vnum(&world.mobiles, |mobile| mobile.vnum.0),
+            objects: by_vnum(&world.objects, |object| object.vnum.0),
+            resets: world
+                .areas
+                .iter()
+                .flat_map(|(area_data, resets)| {
+                    resets
+                        .iter()
+                        .map(move |reset| (area_data.short_name.as_str(), reset))
+                })
+                .collect(),
+        }
+    }
+
+    /// One area of a world, by its index.
+    fn of_world_area(world: &'a World, index: usize) -> Self {
+        let (area_data, resets) = &world.areas[index];
+        let name = area_data.short_name.as_str();
+        let mut side = Side::of_world(world);
+        side.rooms.retain(|_vnum, room| room.area == name);
+        side.mobiles.retain(|_vnum, mobile| mobile.area == name);
+        side.objects.retain(|_vnum, object| object.area == name);
+        side.resets = resets.iter().map(|reset| (name, reset)).collect();
+        side
+    }
+
+    /// An area loaded from its file.
+    fn of_area(area: &'a Area) -> Self {
+        let name = area.area_data.short_name.as_str();
+        Side {
+            rooms: by_vnum(&area.rooms, |room| room.vnum.0),
+            mobiles: by_vnum(&area.mobiles, |mobile| mobile.vnum.0),
+            objects: by_vnum(&area.objects, |object| object.vnum.0),
+            resets: area.resets.iter().map(|reset| (name, reset)).collect(),
+        }
+    }
+}
+
+fn by_vnum<T>(templates: &[T], vnum: impl Fn(&T) -> usize) -> BTreeMap<usize, &T> {
+    templates
+        .iter()
+        .filter(|template| vnum(template) != 0)
+        .map(|template| (vnum(template), template))
+        .collect()
+}
+
+/// The names of the fields that differ between two templates, as they are
+/// serialized, leaving out some that are compared on their own.
+fn changed_fields<T: Serialize>(old: &T, new: &T, skipped: &[&str]) -> Vec<String> {
+    let to_map = |template: &T| match serde_json::to_value(template) {
+        Ok(Value::Object(map)) => map,
+        _ => Default::default(),
+    };
+    let old = to_map(old);
+    let new = to_map(new);
+
+    let fields: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    fields
+        .into_iter()
+        .filter(|field| !skipped.contains(&field.as_str()))
+        .filter(|field| old.get(*field) != new.get(*field))
+        .map(|field| field.to_string())
+        .collect()
+}
+
+/// Compare templates matched by vnum.
+fn diff_templates<T: Serialize>(
+    changes: &mut Vec<Change>,
+    template: Template,
+    old: &BTreeMap<usize, &T>,
+    new: &BTreeMap<usize, &T>,
+    area: impl Fn(&T) -> &str,
+    name: impl Fn(&T) -> &str,
+) {
+    let vnums: BTreeSet<usize> = old.keys().chain(new.keys()).copied().collect();
+
+    for vnum in vnums {
+        let (kind, detail, in_area) = match (old.get(&vnum), new.get(&vnum)) {
+            (None, Some(new)) => (ChangeKind::Added, name(new).to_string(), area(new)),
+            (Some(old), None) => (ChangeKind::Removed, name(old).to_string(), area(old)),
+            (Some(old), Some(new)) => {
+                let fields = changed_fields(*old, *new, &["vnum", "area", "exits"]);
+                if fields.is_empty() {
+                    continue;
+                }
+                (ChangeKind::Changed, fields.join(", "), area(new))
+            }
+            (None, None) => continue,
+        };
+
+        changes.push(Change {
+            kind,
+            template,
+            vnum,
+            area: in_area.to_string(),
+            detail,
+        });
+    }
+}
+
+/// Compare the exits of rooms in both worlds, matched by direction.
+fn diff_exits(changes: &mut Vec<Change>, old: &Room, new: &Room) {
+    let by_direction = |room: &'_ Room| -> BTreeMap<String, Exit> {
+        room.exits
+            .iter()
+            .map(|exit| (exit.name.clone(), exit.clone()))
+            .collect()
+    };
+    let old_exits = by_direction(old);
+    let new_exits = by_direction(new);
+    let directions: BTreeSet<&String> = old_exits.keys().chain(new_exits.keys()).collect();
+
+    for direction in directions {
+        let (kind, detail) = match (old_exits.get(direction), new_exits.get(direction)) {
+            (None, Some(exit)) => (
+                ChangeKind::Added,
+                format!("{} to room {}", direction, exit.vnum.0),
+            ),
+            (Some(exit), None) => (
+                ChangeKind::Removed,
+                format!("{} to room {}", direction, exit.vnum.0),
+            ),
+            (Some(old), Some(new)) => {
+                let fields = changed_fields(old, new, &["name"]);
+                if fields.is_empty() {
+                    continue;
+                }
+                (
+                    ChangeKind::Changed,
+                    format!("{}: {}", direction, fields.join(", ")),
+                )
+            }
+            (None, None) => continue,
+        };
+
+        changes.push(Change {
+            kind,
+            template: Template::Exit,
+            vnum: new.vnum.0,
+            area: new.area.clone(),
+            detail,
+        });
+    }
+}
+
+/// The vnum of what a reset spawns, or of the room of its door.
+fn reset_vnum(reset: &ResetCommand) -> usize {
+    match reset {
+        ResetCommand::Mob { m_num, .. } => m_num.0,
+        ResetCommand::Object { o_num, .. }
+        | ResetCommand::Give { o_num, .. }
+        | ResetCommand::Equip { o_num, .. }
+        | ResetCommand::Put { o_num, .. } => o_num.0,
+        ResetCommand::Door { r_num, .. } => r_num.0,
+    }
+}
+
+/// What a reset does, in words.
+fn describe_reset(reset: &ResetCommand) -> String {
+    let only_at = |only_at: &Option<TimeOfDay>| match only_at {
+        Some(TimeOfDay::Day) => " by day",
+        Some(TimeOfDay::Night) => " by night",
+        None => "",
+    };
+
+    match reset {
+        ResetCommand::Mob {
+            m_num,
+            global_limit,
+            r_num,
+            room_limit,
+            only_at: at,
+        } => format!(
+            "mobile {} in room {}{}, up to {} there and {} in all",
+            m_num.0,
+            r_num.0,
+            only_at(at),
+            room_limit,
+            global_limit
+        ),
+        ResetCommand::Object {
+            o_num,
+            global_limit,
+            r_num,
+            only_at: at,
+        } => format!(
+            "object {} in room {}{}, up to {}",
+            o_num.0,
+            r_num.0,
+            only_at(at),
+            global_limit
+        ),
+        ResetCommand::Door {
+            r_num,
+            direction,
+            door_flags,
+        } => format!(
+            "door {} of room {}, {}",
+            direction,
+            r_num.0,
+            if door_flags.is_empty() {
+                "open".to_string()
+            } else {
+                door_flags.join(" and ")
+            }
+        ),
+        ResetCommand::Give {
+            o_num,
+            global_limit,
+        } => format!("object {} given, up to {}", o_num.0, global_limit),
+        ResetCommand::Equip {
+            o_num,
+            global_limit,
+            location,
+        } => format!(
+            "object {} worn at {}, up to {}",
+            o_num.0, location, global_limit
+        ),
+        ResetCommand::Put {
+            o_num,
+            global_limit,
+            c_num,
+            container_limit,
+        } => format!(
+            "object {} put in object {}, up to {} there and {} in all",
+            o_num.0, c_num.0, container_limit, global_limit
+        ),
+    }
+}
+
+/// Compare resets, as the same reset may be in an area more than once.
+fn diff_resets(
+    changes: &mut Vec<Change>,
+    old: &[(&str, &ResetCommand)],
+    new: &[(&str, &ResetCommand)],
+) {
+    let key = |(area, reset): &(&str, &ResetCommand)| {
+        let json = serde_json::to_string(reset).unwrap_or_default();
+        (area.to_string(), reset_vnum(reset), json)
+    };
+    let mut removed: Vec<(String, usize, String, &ResetCommand)> = Vec::new();
+    let mut new_keys: Vec<(String, usize, String)> = new.iter().map(key).collect();
+    for reset in old {
+        let (area, vnum, json) = key(reset);
+        match new_keys
+            .iter()
+            .position(|new| *new == (area.clone(), vnum, json.clone()))
+        {
+            Some(index) => {
+                new_keys.remove(index);
+            }
+            None => removed.push((area, vnum, json, reset.1)),
+        }
+    }
+
+    // New resets that weren't in the old world, in their order
+    let mut added: Vec<(String, usize, &ResetCommand)> = Vec::new();
+    for reset in new {
+        let (area, vnum, json) = key(reset);
+        if let Some(index) = new_keys
+            .iter()
+            .position(|new| *new == (area.clone(), vnum, json.clone()))
+        {
+            new_keys.remove(index);
+            added.push((area, vnum, reset.1));
+        }
+    }
+
+    for (area, vnum, _json, old_reset) in removed {
+        let same_thing = added.iter().position(|(added_area, added_vnum, reset)| {
+            *added_area == area
+                && *added_vnum == vnum
+                && std::mem::discriminant(*reset) == std::mem::discriminant(old_reset)
+        });
+        let (kind, detail) = match same_thing {
+            Some(index) => {
+                let (_area, _vnum, new_reset) = added.remove(index);
+                (
+                    ChangeKind::Changed,
+                    format!(
+                        "{}, was {}",
+                        describe_reset(new_reset),
+                        describe_reset(old_reset)
+                    ),
+                )
+            }
+            None => (ChangeKind::Removed, describe_reset(old_reset)),
+        };
+        changes.push(Change {
+            kind,
+            template: Template::Reset,
+            vnum,
+            area,
+            detail,
+        });
+    }
+    for (area, vnum, reset) in added {
+        changes.push(Change {
+            kind: ChangeKind::Added,
+            template: Template::Reset,
+            vnum,
+            area,
+            detail: describe_reset(reset),
+        });
+    }
+}
+
+fn diff_sides(old: &Side, new: &Side) -> WorldDiff {
+    let mut changes = Vec::new();
+
+    diff_templates(
+        &mut changes,
+        Template::Room,
+        &old.rooms,
+        &new.rooms,
+        |room| &room.area,
+        |room| &room.name,
+    );
+    for (vnum, new_room) in &new.rooms {
+        if let Some(old_room) = old.rooms.get(vnum) {
+            diff_exits(&mut changes, old_room, new_room);
+        }
+    }
+    diff_templates(
+        &mut changes,
+        Template::Mobile,
+        &old.mobiles,
+        &new.mobiles,
+        |mobile| &mobile.area,
+        |mobile| &mobile.short_description,
+    );
+    diff_templates(
+        &mut changes,
+        Template::Object,
+        &old.objects,
+        &new.objects,
+        |object| &object.area,
+        |object| &object.short_description,
+    );
+    diff_resets(&mut changes, &old.resets, &new.resets);
+
+    // Exits right after the change of their room, if it has one
+    changes.sort_by_key(|change| match change.template {
+        Template::Room | Template::Exit => (0, change.vnum),
+        Template::Mobile => (1, change.vnum),
+        Template::Object => (2, change.vnum),
+        Template::Reset => (3, 0),
+    });
+
+    WorldDiff { changes }
+}
+
+/// What differs between an area of a world, by its index, and the same
+/// area loaded from its file again.
+pub(crate) fn diff_area(world: &World, index: usize, area: &Area) -> WorldDiff {
+    diff_sides(&Side::of_world_area(world, index), &Side::of_area(area))
+}
+
+/// What differs between the areas of two loaded worlds, e.g. one loaded
+/// before an area file was edited and one after.
+pub fn diff_worlds(old: &WorldState, new: &WorldState) -> WorldDiff {
+    diff_sides(&Side::of_world(&old.world), &Side::of_world(&new.world))
+}
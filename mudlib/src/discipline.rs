@@ -0,0 +1,247 @@
+//! Commands for immortals to deal with players and mobiles: `slay`, `restore`,
+//! `freeze` and `mute`.
+//!
+//! Frozen players can't use any command, and muted players can't talk on
+//! channels. Both are kept in their account, so logging out doesn't get rid
+//! of them; using the command again lifts them. Frozen players are put in
+//! the jail room of the configuration, and log in there.
+//!
+//! Like with `force`, players can only be punished by those with more trust
+//! than them, and everything is put in the immortal log.
+
+use crate::{
+    agent::EntityAgent,
+    echo,
+    entity::{EntityId, Found, Place},
+    wizlog::LogKind,
+};
+
+#[derive(Clone, Copy)]
+pub(crate) enum Punishment {
+    Freeze,
+    Mute,
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Check if the current player has more trust than another one.
+    /// Mobiles outrank nobody, so that mobprogs can't be used against players.
+    pub(crate) fn outranks(&self, player: &str) -> bool {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        match myself.player_name() {
+            Some(me) => self.accounts.trust(player) < self.accounts.trust(me),
+            None => false,
+        }
+    }
+
+    /// Kill a mobile or player right away.
+    pub fn do_slay(&mut self, target_name: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let target = myself.find_entity(target_name, |entity| {
+            entity.is_mobile() || entity.is_player()
+        });
+
+        let victim = match target {
+            Found::Other(victim) => victim,
+            Found::Myself | Found::WrongSelf => {
+                echo!(self.info(), "Try '`Wdie`^' instead.\r\n");
+                return;
+            }
+            Found::WrongOther(_) => {
+                echo!(self.info(), "Only mobiles and players can be slain.\r\n");
+                return;
+            }
+            Found::Nothing => {
+                echo!(
+                    self.info(),
+                    "You don't see anyone named {} here.\r\n",
+                    target_name
+                );
+                return;
+            }
+        };
+
+        if let Some(victim_name) = victim.player_name() {
+            if !self.outranks(victim_name) {
+                let mut act = self.players.act_with(&myself, &victim);
+                echo!(act.myself(), "$^$N is too powerful for you to slay.\r\n");
+                return;
+            }
+        }
+
+        let mut act = self.players.act_with(&myself, &victim);
+        echo!(act.all(), "$^$n $[slay|slays] $N in cold blood!\r\n");
+
+        let message = match (myself.player_name(), victim.player_name()) {
+            (Some(me), Some(victim_name)) => Some(format!("{} slays {}.", me, victim_name)),
+            _ => None,
+        };
+        let victim_id = victim.entity_id();
+
+        self.switch_agent(victim_id).do_die();
+
+        if let Some(message) = message {
+            self.log_to_immortals(LogKind::Admin, &message);
+        }
+    }
+
+    /// Bring a mobile or player back from the dead, and stop anything they
+    /// were about to do. `restore all` restores every player in the realm.
+    pub fn do_restore(&mut self, target_name: &str) {
+        let limbo = self
+            .entity_world
+            .landmark("limbo")
+            .expect("Limbo should always exist");
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let targets: Vec<EntityId> = if target_name == "all" {
+            self.entity_world
+                .all_players()
+                .filter(|player| *player != myself)
+                .map(|player| player.entity_id())
+                .collect()
+        } else {
+            let target = myself.find_entity(target_name, |entity| {
+                entity.is_mobile() || entity.is_player()
+            });
+
+            match target {
+                Found::Other(target) => vec![target.entity_id()],
+                Found::Myself | Found::WrongSelf => vec![self.entity_id],
+                Found::WrongOther(_) => {
+                    echo!(self.info(), "Only mobiles and players can be restored.\r\n");
+                    return;
+                }
+                Found::Nothing => {
+                    // Dead mobiles can still be found in limbo
+                    let dead_mobile = self
+                        .entity_world
+                        .entity_info(limbo)
+                        .mobiles()
+                        .find(|mobile| {
+                            mobile
+                                .component_info()
+                                .keyword()
+                                .split_whitespace()
+                                .any(|word| word.eq_ignore_ascii_case(target_name))
+                        })
+                        .map(|mobile| mobile.entity_id());
+
+                    match self.find_player_anywhere(target_name).or(dead_mobile) {
+                        Some(target_id) => vec![target_id],
+                        None => {
+                            echo!(
+                                self.info(),
+                                "There is no one named like that in the realm.\r\n"
+                            );
+                            return;
+                        }
+                    }
+                }
+            }
+        };
+
+        let room_id = self.entity_world.room_of(self.entity_id);
+        let death_room = self.entity_world.place(Place::Death);
+
+        for &target_id in &targets {
+            // The dead are brought to the one restoring them
+            let target_room = self.entity_world.room_of(target_id);
+            let is_player = self.entity_world.entity_info(target_id).is_player();
+            if target_room == limbo || (is_player && Some(target_room) == death_room) {
+                self.entity_world.move_entity(target_id, room_id);
+            }
+
+            let mut target = self.entity_world.entity_info_mut(target_id);
+            target.components().general.command_queue.clear();
+
+            if target_id != self.entity_id {
+                let myself = self.entity_world.entity_info(self.entity_id);
+                let target = self.entity_world.entity_info(target_id);
+                let mut act = self.players.act_with(&myself, &target);
+                echo!(act.target(), "$^$n has restored you.\r\n");
+            }
+        }
+
+        let restored = if target_name == "all" {
+            "everyone".to_string()
+        } else {
+            self.entity_world.entity_info(targets[0]).to_string()
+        };
+        echo!(self.info(), "You restore {}.\r\n", restored);
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if let Some(player) = myself.player_name() {
+            let message = format!("{} restores {}.", player, restored);
+            self.log_to_immortals(LogKind::Admin, &message);
+        }
+    }
+
+    /// Freeze or mute a player, or lift it if they already are.
+    pub fn do_punish(&mut self, player_name: &str, punishment: Punishment) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let name = match self.accounts.account(player_name) {
+            Some(account) => account.name.clone(),
+            None => {
+                echo!(self.info(), "There is no character named like that.\r\n");
+                return;
+            }
+        };
+
+        if myself.player_name() == Some(name.as_str()) {
+            echo!(self.info(), "You can't do that to yourself.\r\n");
+            return;
+        } else if !self.outranks(&name) {
+            echo!(self.info(), "{} is too powerful for you.\r\n", name);
+            return;
+        }
+
+        let (punished, done, undone, to_victim) = match punishment {
+            Punishment::Freeze => {
+                let frozen = !self.accounts.is_frozen(&name);
+                self.accounts.set_frozen(&name, frozen);
+                let to_victim = if frozen {
+                    "You are frozen solid, and can't do anything!\r\n"
+                } else {
+                    "You can move again.\r\n"
+                };
+                (frozen, ("freeze", "freezes"), ("thaw", "thaws"), to_victim)
+            }
+            Punishment::Mute => {
+                let muted = !self.accounts.is_muted(&name);
+                self.accounts.set_muted(&name, muted);
+                let to_victim = if muted {
+                    "You can no longer talk on channels.\r\n"
+                } else {
+                    "You can talk on channels again.\r\n"
+                };
+                (muted, ("mute", "mutes"), ("unmute", "unmutes"), to_victim)
+            }
+        };
+
+        if let Some(victim_id) = self.entity_world.player_entity_id(&name) {
+            let victim = self.entity_world.entity_info(victim_id);
+            echo!(self.players.info(&victim), "{}", to_victim);
+        }
+
+        let (you_verb, they_verb) = if punished { done } else { undone };
+        echo!(self.players.info(&myself), "You {} {}.\r\n", you_verb, name);
+
+        let message = format!(
+            "{} {} {}.",
+            myself.player_name().unwrap_or("Someone"),
+            they_verb,
+            name
+        );
+        self.log_to_immortals(LogKind::Admin, &message);
+
+        // Frozen players are kept in jail until they are let out
+        let victim_id = self.entity_world.player_entity_id(&name);
+        let jail = self.entity_world.place(Place::Jail);
+        if let (Punishment::Freeze, true, Some(victim_id), Some(jail)) =
+            (punishment, punished, victim_id, jail)
+        {
+            self.entity_world.move_entity(victim_id, jail);
+        }
+    }
+}
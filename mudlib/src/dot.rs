@@ -0,0 +1,223 @@
+//! The rooms of an area and their exits as a Graphviz DOT graph, for builders
+//! to look at how an area is laid out outside of the game, e.g. with
+//! `dot -Tsvg mudschool.dot -o mudschool.svg`.
+//!
+//! Rooms are colored by sector. Exits that go both ways between two rooms are
+//! drawn once, with arrows at both ends, so that one-way exits stand out with
+//! a single arrow. Doors are written next to their exit, along with whether
+//! they start closed or locked and the vnum of their key. Rooms of other
+//! areas that exits lead to are dashed, and exits to rooms that don't exist
+//! lead to a red room.
+//!
+//! The graph is made from the areas as they would be saved, so it includes
+//! changes made with the OLC. The CLI prints it with `clicore dot <area>`.
+
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::{
+    world::{opposite_direction, Exit, Room, World},
+    WorldState,
+};
+
+/// Fill color of rooms of a sector
+fn sector_color(sector: &str) -> &'static str {
+    match sector {
+        "inside" => "lightyellow",
+        "city" => "lightgray",
+        "field" => "palegreen",
+        "forest" => "darkseagreen",
+        "hills" => "khaki",
+        "mountain" => "burlywood",
+        "swim" | "water_swim" => "lightblue",
+        "noswim" | "water_noswim" => "steelblue",
+        "underwater" => "royalblue",
+        "air" => "azure",
+        "desert" => "wheat",
+        "cave" => "darkgray",
+        "swamp" => "darkolivegreen3",
+        _ => "white",
+    }
+}
+
+/// Rooms without a sector are inside, as for the mapper
+fn sector(room: &Room) -> &str {
+    match room.sector.as_str() {
+        "" => "inside",
+        sector => sector,
+    }
+}
+
+/// A string in double quotes, with what DOT would misread escaped.
+fn quoted(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.trim().chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => (),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn door(exit: &Exit) -> Option<String> {
+    if !exit.has_door {
+        return None;
+    }
+
+    let mut door = if exit.is_locked {
+        "locked door".to_string()
+    } else if exit.is_closed {
+        "closed door".to_string()
+    } else {
+        "door".to_string()
+    };
+    if let Some(key) = exit.key.filter(|key| key.0 != 0) {
+        let _ = write!(door, ", key {}", key.0);
+    }
+
+    Some(door)
+}
+
+fn room(world: &World, vnum: usize) -> Option<&Room> {
+    world.rooms.get(vnum).filter(|room| room.vnum.0 != 0)
+}
+
+/// The exit of a room that leads back the way an exit came, if there is one.
+fn way_back<'w>(world: &'w World, from: &Room, exit: &Exit) -> Option<&'w Exit> {
+    room(world, exit.vnum.0)?
+        .exits
+        .iter()
+        .find(|back| back.vnum.0 == from.vnum.0 && back.name == opposite_direction(&exit.name))
+}
+
+/// The rooms of an area and their exits as a DOT graph.
+fn area_graph(world: &World, index: usize) -> String {
+    let (area, _resets) = &world.areas[index];
+    let rooms: Vec<&Room> = world
+        .rooms
+        .iter()
+        .filter(|room| room.vnum.0 != 0 && room.area == area.short_name)
+        .collect();
+
+    let mut dot = String::new();
+    let _ = writeln!(dot, "digraph {} {{", quoted(&area.short_name));
+    let _ = writeln!(
+        dot,
+        "    graph [label={}, labelloc=t, fontname=\"Helvetica\"];",
+        quoted(&format!(
+            "{} ({}), vnums {} to {}",
+            area.name, area.short_name, area.vnums.0 .0, area.vnums.1 .0
+        ))
+    );
+    dot.push_str("    node [shape=box, style=\"rounded,filled\", fontname=\"Helvetica\"];\n");
+    dot.push_str("    edge [fontname=\"Helvetica\", fontsize=10];\n\n");
+
+    let mut sectors = BTreeSet::new();
+    for room in &rooms {
+        sectors.insert(sector(room));
+        let _ = writeln!(
+            dot,
+            "    r{} [label={}, fillcolor={}];",
+            room.vnum.0,
+            quoted(&format!("{}\n{}", room.vnum.0, room.name)),
+            quoted(sector_color(sector(room)))
+        );
+    }
+
+    // Rooms outside of the area, or missing altogether
+    let mut elsewhere = BTreeSet::new();
+    dot.push('\n');
+
+    for from in &rooms {
+        for exit in &from.exits {
+            let to = exit.vnum.0;
+            let in_area = room(world, to).is_some_and(|room| room.area == area.short_name);
+            let back = way_back(world, from, exit);
+
+            // Exits both ways within the area are drawn from the lower vnum,
+            // or from the first name for a room leading to itself
+            let drawn_from_other_side = match back {
+                Some(back) if in_area => {
+                    to < from.vnum.0 || (to == from.vnum.0 && exit.name > back.name)
+                }
+                _ => false,
+            };
+            if drawn_from_other_side {
+                continue;
+            }
+
+            let mut label = match back {
+                Some(back) if back.name != exit.name => format!("{} / {}", exit.name, back.name),
+                _ => exit.name.clone(),
+            };
+            if let Some(door) = door(exit).or_else(|| back.and_then(door)) {
+                let _ = write!(label, "\n({})", door);
+            }
+
+            let mut attributes = format!("label={}", quoted(&label));
+            if back.is_some() {
+                attributes.push_str(", dir=both");
+            }
+            let _ = writeln!(dot, "    r{} -> r{} [{}];", from.vnum.0, to, attributes);
+
+            if !in_area {
+                elsewhere.insert(to);
+            }
+        }
+    }
+
+    if !elsewhere.is_empty() {
+        dot.push('\n');
+    }
+    for vnum in elsewhere {
+        let _ = match room(world, vnum) {
+            Some(room) => writeln!(
+                dot,
+                "    r{} [label={}, style=\"rounded,dashed\"];",
+                vnum,
+                quoted(&format!("{}\n{}\n({})", vnum, room.name, room.area))
+            ),
+            None => writeln!(
+                dot,
+                "    r{} [label={}, style=\"rounded,dashed\", color=red, fontcolor=red];",
+                vnum,
+                quoted(&format!("{}\n(no such room)", vnum))
+            ),
+        };
+    }
+
+    if !sectors.is_empty() {
+        dot.push_str("\n    subgraph cluster_sectors {\n        label=\"Sectors\";\n");
+        for sector in sectors {
+            let _ = writeln!(
+                dot,
+                "        {} [label={}, fillcolor={}];",
+                quoted(&format!("sector {}", sector)),
+                quoted(sector),
+                quoted(sector_color(sector))
+            );
+        }
+        dot.push_str("    }\n");
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+impl WorldState {
+    /// The rooms of an area, found by its short name or file name, and their
+    /// exits as a Graphviz DOT graph.
+    pub fn area_dot(&self, area: &str) -> Result<String, String> {
+        match self.world.find_area(area) {
+            Some(index) => Ok(area_graph(&self.world, index)),
+            None => Err(format!("There is no area called '{}'.", area)),
+        }
+    }
+}
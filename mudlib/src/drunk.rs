@@ -0,0 +1,136 @@
+//! Getting drunk from beer, wine and stronger drinks.
+//!
+//! Drinking something alcoholic adds to how drunk a player or mobile is, by
+//! how strong it is. Past a point, what they say and tell comes out slurred,
+//! and they sometimes stumble instead of going where they meant to. They
+//! sober up by one every tick, and get thirsty faster while they do; whoever
+//! sobers up completely is left with a hangover and a dry mouth.
+
+use crate::{agent::EntityAgent, components::Vitals, echo, entity::EntityInfo, rng::GameRng};
+
+/// How drunk one can get at most
+const MAX_DRUNK: u8 = 48;
+/// How drunk one is before it shows
+const TIPSY: u8 = 10;
+/// What thirst is left after a hangover, at most
+const HANGOVER_THIRST: u8 = 3;
+
+/// How strong a liquid is, for the liquids of Dawn of Time; 0 for anything
+/// that isn't alcoholic.
+fn strength(liquid: &str) -> u8 {
+    match liquid {
+        "beer" | "ale" => 2,
+        "dark ale" | "mead" | "cordial" => 3,
+        "red wine" | "white wine" | "rose wine" | "elvish wine" | "benedictine wine"
+        | "champagne" | "icewine" | "sherry" | "amontillado" | "framboise" | "wine" => 4,
+        "local specialty" | "brandy" | "schnapps" | "rum" | "aquavit" => 6,
+        "whisky" | "vodka" | "firebreather" | "absinthe" => 8,
+        _ => 0,
+    }
+}
+
+/// How drunk an entity is, if at all.
+pub(crate) fn drunkenness(entity: &EntityInfo) -> u8 {
+    entity
+        .components()
+        .vitals
+        .as_ref()
+        .map(|vitals| vitals.drunk)
+        .unwrap_or(0)
+}
+
+/// Whether an entity is drunk enough to slur and stumble.
+fn is_drunk(entity: &EntityInfo) -> bool {
+    drunkenness(entity) > TIPSY
+}
+
+/// Whether a drunk entity stumbles instead of going somewhere, more often the
+/// drunker it is.
+pub(crate) fn stumbles(entity: &EntityInfo, rng: &mut GameRng) -> bool {
+    is_drunk(entity) && rng.range(0..100) < drunkenness(entity)
+}
+
+/// Say a message the way someone that drunk would: s turns into sh, vowels
+/// drag on, and the odd hiccup gets in. Colour codes are left alone.
+fn slur(message: &str, drunk: u8, rng: &mut GameRng) -> String {
+    let chance = u32::from(drunk.min(40));
+    let mut slurred = String::with_capacity(message.len() * 2);
+    let mut escaped = false;
+
+    for c in message.chars() {
+        slurred.push(c);
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match c {
+            '`' | '$' => escaped = true,
+            ' ' if rng.range(0..20) == 0 => slurred.push_str("*hic* "),
+            _ if rng.range(0..100) >= chance => (),
+            's' | 'S' => slurred.push('h'),
+            'a' | 'e' | 'i' | 'o' | 'u' | 'A' | 'E' | 'I' | 'O' | 'U' => {
+                slurred.push(c.to_ascii_lowercase())
+            }
+            _ => (),
+        }
+    }
+
+    slurred
+}
+
+/// Sober up by a tick, and tell how it goes, if there is anything to tell.
+pub(crate) fn sober_up(vitals: &mut Vitals) -> Option<&'static str> {
+    if vitals.drunk == 0 {
+        return None;
+    }
+
+    vitals.drunk -= 1;
+    vitals.thirst = vitals.thirst.saturating_sub(1);
+
+    match vitals.drunk {
+        0 => {
+            vitals.thirst = vitals.thirst.min(HANGOVER_THIRST);
+            Some("Your head pounds, and your mouth is as dry as sand.")
+        }
+        TIPSY => Some("You feel sober again."),
+        _ => None,
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// What the current entity says, slurred if it is drunk.
+    pub(crate) fn slurred(&mut self, message: &str) -> String {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if is_drunk(&myself) {
+            slur(message, drunkenness(&myself), self.rng)
+        } else {
+            message.to_string()
+        }
+    }
+
+    /// Get the current entity drunker after a drink of some liquid, if it is
+    /// alcoholic.
+    pub(crate) fn drink_alcohol(&mut self, liquid: &str) {
+        let strength = strength(liquid);
+        if strength == 0 {
+            return;
+        }
+
+        let mut myself = self.entity_world.entity_info_mut(self.entity_id);
+        let (before, after) = match &mut myself.components().vitals {
+            Some(vitals) => {
+                let before = vitals.drunk;
+                vitals.drunk = vitals.drunk.saturating_add(strength).min(MAX_DRUNK);
+                (before, vitals.drunk)
+            }
+            None => return,
+        };
+
+        if before <= TIPSY && after > TIPSY {
+            echo!(self.info(), "You feel drunk.\r\n");
+        } else if after == MAX_DRUNK {
+            echo!(self.info(), "The room spins around you.\r\n");
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Weapons and armor that wear down in fights, and repairers who mend them.
+//!
+//! Weapons and armor spawn in perfect condition, at 100. Each hit wears down
+//! the weapon wielded by whoever hits, and a piece of armor worn by whoever
+//! is hit; at 0 the object breaks, and is gone. Hits are dealt by mobprogs
+//! with `mob damage`. Looking at (or examining) an object tells its
+//! condition.
+//!
+//! Repairers (mobiles with the `repairer` act flag, or from a SMAUG area's
+//! `#REPAIRS`) mend objects with `repair <item>`, for silver: more for the
+//! more worn, and for those of a higher level.
+
+use crate::{
+    agent::EntityAgent,
+    echo,
+    entity::{EntityId, EntityInfo},
+    find_entities::EntityIterator,
+};
+
+/// The condition of weapons and armor when they are new, or mended.
+pub(crate) const NEW_CONDITION: u8 = 100;
+
+/// Whether objects of an item type wear down.
+pub(crate) fn wears_down(item_type: &str) -> bool {
+    item_type == "weapon" || item_type == "armor"
+}
+
+/// How worn an object looks, if it can wear down.
+pub(crate) fn condition_description(object: &EntityInfo) -> Option<&'static str> {
+    let description = match condition(object)? {
+        100..=u8::MAX => "in perfect condition",
+        80..=99 => "in good condition",
+        60..=79 => "slightly worn",
+        40..=59 => "worn",
+        20..=39 => "badly worn",
+        _ => "about to fall apart",
+    };
+    Some(description)
+}
+
+/// The condition of an object, if it can wear down.
+fn condition(object: &EntityInfo) -> Option<u8> {
+    object.components().object.as_ref()?.condition
+}
+
+/// The level of an object, from the `Level` of its template in the area file.
+fn level(object: &EntityInfo) -> usize {
+    object
+        .components()
+        .general
+        .extras
+        .iter()
+        .flat_map(|extras| extras.iter())
+        .find(|(key, _value)| key == "Level")
+        .and_then(|(_key, value)| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// What a repairer charges to mend an object.
+fn repair_price(condition: u8, level: usize) -> usize {
+    let damage = NEW_CONDITION.saturating_sub(condition) as usize;
+    (damage * (level + 5) / 10).max(1)
+}
+
+/// Whether an entity is a repairer.
+fn is_repairer(entity: &EntityInfo) -> bool {
+    entity
+        .components()
+        .mobile
+        .as_ref()
+        .map(|mobile| mobile.repairer)
+        .unwrap_or(false)
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Wear down the weapon of the current entity, and a piece of armor of
+    /// whoever it hits.
+    pub(crate) fn wear_from_hit(&mut self, target_id: EntityId) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let weapon = myself
+            .objects()
+            .find(|object| {
+                object.equipped().is_some()
+                    && object
+                        .components()
+                        .object
+                        .as_ref()
+                        .and_then(|o| o.wear.as_deref())
+                        == Some("wield")
+                    && condition(object).is_some()
+            })
+            .map(|weapon| weapon.entity_id());
+
+        let target = self.entity_world.entity_info(target_id);
+        let armor: Vec<EntityId> = target
+            .objects()
+            .filter(|object| object.equipped().is_some() && condition(object).is_some())
+            .filter(|object| Some(object.entity_id()) != weapon)
+            .map(|object| object.entity_id())
+            .collect();
+
+        if let Some(weapon_id) = weapon {
+            self.wear_down(self.entity_id, weapon_id);
+        }
+        if !armor.is_empty() {
+            let piece = armor[self.rng.range(0..armor.len())];
+            self.wear_down(target_id, piece);
+        }
+    }
+
+    /// Take a point off the condition of an object someone has on, which
+    /// breaks at 0.
+    fn wear_down(&mut self, holder_id: EntityId, object_id: EntityId) {
+        let mut object = self.entity_world.entity_info_mut(object_id);
+        let condition = match &mut object.components().object {
+            Some(object) => match &mut object.condition {
+                Some(condition) => {
+                    *condition = condition.saturating_sub(1);
+                    *condition
+                }
+                None => return,
+            },
+            None => return,
+        };
+        if condition > 0 {
+            return;
+        }
+
+        let holder = self.entity_world.entity_info(holder_id);
+        let object = self.entity_world.entity_info(object_id);
+        let mut act = self.players.act_with(&holder, &object);
+        echo!(act.myself(), "`R$^$N breaks into pieces!`^\r\n");
+        echo!(act.others(), "$^$N that $n has on breaks into pieces!\r\n");
+
+        self.entity_world.destroy_entity(object_id);
+    }
+
+    pub fn do_repair(&mut self, item_name: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let repairer = match myself
+            .room()
+            .mobiles()
+            .find(|mobile| is_repairer(mobile) && mobile != &myself)
+        {
+            Some(repairer) => repairer,
+            None => {
+                echo!(
+                    self.players.info(&myself),
+                    "You need to find someone who repairs things for that.\r\n"
+                );
+                return;
+            }
+        };
+
+        let found = myself
+            .objects()
+            .filter_by_keyword(item_name)
+            .filter_or(
+                |e| condition(e).is_some(),
+                "$^$N isn't something that can be repaired.",
+            )
+            .find_one_or("You don't own anything named like that.");
+        let item = match found {
+            Ok(item) => item,
+            Err(error) => return self.echo_error(error),
+        };
+
+        let condition = condition(&item).expect("Checked above");
+        let repairer_id = repairer.entity_id();
+        let item_id = item.entity_id();
+        if condition >= NEW_CONDITION {
+            let mut act = self.players.act_with(&myself, &repairer);
+            echo!(
+                act.myself(),
+                "$^$N says, 'There's nothing wrong with {}.'\r\n",
+                item
+            );
+            return;
+        }
+
+        let cost = repair_price(condition, level(&item));
+        if !self.remove_silver(cost, self.entity_id) {
+            echo!(
+                self.info(),
+                "You don't have the {} silver to have it repaired!\r\n",
+                cost
+            );
+            return;
+        }
+
+        let mut item = self.entity_world.entity_info_mut(item_id);
+        if let Some(object) = &mut item.components().object {
+            object.condition = Some(NEW_CONDITION);
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let repairer = self.entity_world.entity_info(repairer_id);
+        let item = self.entity_world.entity_info(item_id);
+        let mut act = self.players.act_with(&myself, &repairer);
+        echo!(
+            act.myself(),
+            "$^$N repairs {} for you, for {} silver.\r\n",
+            item,
+            cost
+        );
+        echo!(
+            act.target(),
+            "You repair {} for $n, for {} silver.\r\n",
+            item,
+            cost
+        );
+        echo!(act.others(), "$^$N repairs {} for $n.\r\n", item);
+    }
+}
@@ -0,0 +1,132 @@
+//! Wandering encounters, for players travelling through the wilderness.
+//!
+//! Areas list the mobiles that can be met in their rooms of each sector, with
+//! `Encounter <sector> <mobile vnum> <limit>` lines in their area data, or
+//! with `aedit <area> encounter`. Every mobile pulse, each player in a field,
+//! forest or hills room has a small chance to run into one of the encounters
+//! for that sector of the area, which turns up in the same room.
+//!
+//! No more of a mobile turn up than the limit of its encounter allows,
+//! counting those spawned by resets, and there are never more than a few
+//! encounters in the whole world at once. Encounters that nobody is around
+//! to see wander off again before long.
+
+use crate::{
+    components::EntityType,
+    echo,
+    entity::{EntityId, EntityInfo},
+    world::Vnum,
+    WorldState,
+};
+
+/// Sectors where players run into encounters
+const ENCOUNTER_SECTORS: &[&str] = &["field", "forest", "hills"];
+
+fn is_encounter(entity: &EntityInfo) -> bool {
+    matches!(&entity.components().mobile, Some(mobile) if mobile.encounter)
+}
+
+fn has_players(room: &EntityInfo) -> bool {
+    room.contained_entities().any(|entity| entity.is_player())
+}
+
+pub(crate) fn update_encounters(world_state: &mut WorldState) {
+    let limbo = world_state
+        .entity_world
+        .landmark("limbo")
+        .expect("Limbo should always exist");
+
+    // Encounters left alone wander off
+    let rng = &mut world_state.rng;
+    let leaving: Vec<EntityId> = world_state
+        .entity_world
+        .all_entities()
+        .filter(|entity| is_encounter(entity))
+        .filter(|entity| {
+            let room = entity.room();
+            room.entity_id() != limbo && !has_players(&room) && rng.one_in_bits(3)
+        })
+        .map(|entity| entity.entity_id())
+        .collect();
+
+    for entity_id in leaving {
+        let entity = world_state.entity_world.entity_info(entity_id);
+        echo!(
+            world_state.players.act_alone(&entity).others(),
+            "$^$n wanders off.\r\n"
+        );
+        world_state.entity_world.destroy_entity(entity_id);
+    }
+
+    let mut encounters = 0;
+    let mut rooms = Vec::new();
+    for entity in world_state.entity_world.all_entities() {
+        if !entity.is_player() && !is_encounter(&entity) {
+            continue;
+        }
+
+        let room = entity.room();
+        if room.entity_id() == limbo {
+            continue;
+        }
+        if is_encounter(&entity) {
+            encounters += 1;
+            continue;
+        }
+        if !world_state.rng.one_in_bits(6) {
+            continue;
+        }
+
+        let general = &room.components().general;
+        match general.sector.as_deref() {
+            Some(sector) if ENCOUNTER_SECTORS.contains(&sector) => {
+                rooms.push((room.entity_id(), general.area.clone(), sector.to_string()));
+            }
+            _ => (),
+        }
+    }
+
+    for (room_id, area, sector) in rooms {
+        if encounters >= world_state.config.limits.encounters {
+            break;
+        }
+
+        let candidates: Vec<(Vnum, u16)> = world_state
+            .world
+            .areas
+            .iter()
+            .filter(|(area_data, _resets)| area_data.short_name == *area)
+            .flat_map(|(area_data, _resets)| &area_data.encounters)
+            .filter(|encounter| encounter.sector == sector)
+            .map(|encounter| (encounter.vnum, encounter.limit))
+            .collect();
+        if candidates.is_empty() {
+            continue;
+        }
+
+        let (vnum, limit) = candidates[world_state.rng.range(0..candidates.len())];
+        let count = world_state
+            .entity_world
+            .count_in_game(EntityType::Mobile, vnum.0);
+        if vnum.0 == 0 || count >= usize::from(limit) {
+            continue;
+        }
+
+        let mobile_id = match world_state.entity_world.spawn_mobile(vnum.0, room_id) {
+            Some(mobile_id) => mobile_id,
+            None => continue,
+        };
+
+        let mut mobile = world_state.entity_world.entity_info_mut(mobile_id);
+        if let Some(mobile) = &mut mobile.components().mobile {
+            mobile.encounter = true;
+        }
+        encounters += 1;
+
+        let mobile = world_state.entity_world.entity_info(mobile_id);
+        echo!(
+            world_state.players.act_alone(&mobile).others(),
+            "$^$n arrives.\r\n"
+        );
+    }
+}
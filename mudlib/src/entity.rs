@@ -1,27 +1,143 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    hash::BuildHasherDefault,
     num::NonZeroUsize,
+    ops::Index,
+    sync::Arc,
 };
 
 use inflector::Inflector;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    components::{Components, EntityComponentInfo, EntityType, GeneralData, InternComponent, MyStringInterner},
+    archetypes::{ArchetypeRegistry, COINS},
+    components::{
+        AttrValue, Components, EntityComponentInfo, EntityType, GeneralData, InternComponent,
+        MovedStrings, MyStringInterner, SharedParts, StringStatistics, StringUses, Vitals, Wealth,
+    },
+    events::EntityEvent,
+    query::Query,
+    templates::TemplateRegistry,
     world::{Gender, Vnum},
 };
 
+/// Hashes the same way every time, unlike the default of `HashMap`, so that
+/// going through players gives them in the same order again when the same
+/// game is played from the same seed; see the `rng` module.
+pub(crate) type FixedState = BuildHasherDefault<DefaultHasher>;
+
 pub(crate) struct EntityWorld {
-    id_generator: IdGenerator,
     // FIXME
     pub interner: MyStringInterner,
-    entities: HashMap<RawEntityId, Entity>,
-    player_entities: HashMap<String, RawEntityId>,
+    /// What mobiles and objects are spawned from; see `crate::templates`
+    pub templates: TemplateRegistry,
+    /// Kinds of entities that game features make; see `crate::archetypes`
+    pub archetypes: ArchetypeRegistry,
+    entities: EntityArena,
+    player_entities: HashMap<String, RawEntityId, FixedState>,
     player_locations: BTreeMap<String, RawEntityId>,
-    landmarks: BTreeMap<&'static str, RawEntityId>,
+    landmarks: BTreeMap<String, RawEntityId>,
+    places: BTreeMap<Place, RawEntityId>,
+    room_index: RoomIndex,
+    /// Entities by the area they came from, in the order they came in, so
+    /// that what is done to an area only goes through its own
+    from_area: HashMap<Arc<str>, Vec<RawEntityId>, FixedState>,
+    /// What happened since the subscribers last heard; see `crate::events`
+    events: Vec<EntityEvent>,
+    /// Goes up every pulse; entities remember the one they last changed in,
+    /// see `changed_since()`
+    change_tick: u64,
     world_entity_id: RawEntityId,
     era: u16,
 }
 
+/// Where entities are, kept up to date as they are inserted and moved, so
+/// that what is in a room, and the rooms next to it, are found without going
+/// through the contents of everything there.
+#[derive(Default)]
+struct RoomIndex {
+    /// The room of each entity: the closest of its containers that is a room
+    room_of: HashMap<RawEntityId, RawEntityId, FixedState>,
+    /// Entities in each room, however deep, in the order they came in
+    in_room: HashMap<RawEntityId, Vec<RawEntityId>, FixedState>,
+    /// Exits of each room that lead somewhere, with where they lead
+    exits: HashMap<RawEntityId, Vec<(RawEntityId, RawEntityId)>, FixedState>,
+    /// How many mobiles and objects of each vnum are in a room other than
+    /// limbo, for the limits of resets
+    in_game: HashMap<(EntityType, usize), usize, FixedState>,
+}
+
+/// Rooms the game sends characters and things to, set by landmark in the
+/// configuration
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum Place {
+    /// Where characters start when they log in
+    Start,
+    /// Where dead players wait to be restored
+    Death,
+    /// Where frozen players are kept
+    Jail,
+    /// Where `recall` takes players without saying where to
+    Recall,
+    /// Where what was in purged things goes; see `ContentsPolicy::Junk`
+    Junk,
+}
+
+/// What happens to what an entity holds when it is taken out of the game with
+/// `EntityWorld::extract_entity()`. Its exits, extra descriptions and
+/// mobprogs are part of it, and are removed with it whatever the policy;
+/// players are never taken out of the game, and are put where it was instead.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum ContentsPolicy {
+    /// Is removed along with the entity
+    Destroy,
+    /// Is left where the entity was, like what spills out of a rotting corpse
+    Dump,
+    /// Goes to the junk room, where immortals can look for it
+    Junk,
+}
+
+/// Entities in the slots of their IDs, linked in the order they were
+/// inserted.
+///
+/// The slot of a removed entity is reused by the next entity inserted, the
+/// slot freed last first, and the generation of the slot goes up, so IDs and
+/// references taken of the old entity don't find the new one. Going through
+/// the links gives entities from the oldest to the newest, whatever their
+/// slots, e.g. the last object loaded by the resets.
+#[derive(Default)]
+struct EntityArena {
+    slots: Vec<Slot>,
+    /// Slots of removed entities, the one freed last at the end
+    free: Vec<usize>,
+    /// The slot of the oldest entity
+    first: Option<usize>,
+    /// The slot of the newest entity
+    last: Option<usize>,
+    len: usize,
+}
+
+#[derive(Default)]
+struct Slot {
+    /// Nothing while the slot is free
+    entity: Option<Entity>,
+    /// The generation of the next entity in the slot, while it is free
+    generation: u32,
+    /// The slot of the entity inserted before this one
+    previous: Option<usize>,
+    /// The slot of the entity inserted after this one
+    next: Option<usize>,
+}
+
+/// Entities of an arena, from the oldest to the newest, or back.
+struct Entities<'a> {
+    arena: &'a EntityArena,
+    front: Option<usize>,
+    back: Option<usize>,
+    remaining: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 struct Entity {
     components: Components,
     raw_entity_id: RawEntityId,
@@ -30,6 +146,12 @@ struct Entity {
     leads_to: Option<RawEntityId>,
     leads_from: Vec<RawEntityId>,
     created_in_era: u16,
+    /// How many entities had its slot before; see `EntityArena`
+    #[serde(default)]
+    generation: u32,
+    /// The change tick of the world when the entity last changed
+    #[serde(skip)]
+    changed_at: u64,
 
     player: Option<String>,
 }
@@ -38,15 +160,31 @@ struct Entity {
 pub(crate) struct EntityId {
     id: RawEntityId,
     era: u16,
+    generation: u32,
 }
 
 #[derive(Hash, Clone, Copy, PartialEq, Eq)]
 pub(crate) struct PermanentEntityId {
     id: RawEntityId,
     created_in_era: u16,
+    generation: u32,
 }
 
-#[derive(Hash, Clone, Copy, PartialEq, Eq)]
+/// A reference to an entity that can be kept in components, unlike
+/// `EntityId`, and stops resolving once the entity is taken out of the game,
+/// or waits in limbo; see `EntityWorld::resolve()`. The era the entity was
+/// created in and the generation of its slot are checked, like with
+/// `PermanentEntityId`, so it never finds another entity in the same slot.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct WeakEntityRef {
+    id: RawEntityId,
+    created_in_era: u16,
+    #[serde(default)]
+    generation: u32,
+}
+
+#[derive(Hash, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
 struct RawEntityId {
     id: NonZeroUsize,
 }
@@ -60,6 +198,7 @@ pub(crate) struct EntityInfo<'e> {
 pub(crate) struct EntityInfoMut<'e> {
     entity: &'e mut Entity,
     era: u16,
+    change_tick: u64,
 }
 
 pub(crate) enum Found<'a> {
@@ -70,31 +209,241 @@ pub(crate) enum Found<'a> {
     Nothing,
 }
 
-struct IdGenerator {
-    next_entity_id: NonZeroUsize,
+/// Everything needed to recreate an EntityWorld, written by
+/// `EntityWorld::snapshot_bytes()` and read back as an `EntitySnapshot`.
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    interner: &'a MyStringInterner,
+    entities: Vec<&'a Entity>,
+    free_slots: Vec<(RawEntityId, u32)>,
+    player_entities: &'a HashMap<String, RawEntityId, FixedState>,
+    player_locations: &'a BTreeMap<String, RawEntityId>,
+    world_entity_id: RawEntityId,
+}
+
+/// The whole entity world at some point, strings included, to go back to
+/// with `EntityWorld::restore()`.
+///
+/// Taking one with `EntityWorld::snapshot()` copies the entities, but not the
+/// descriptions they share through `Arc`s, so it is cheap enough for tests to
+/// go back to a known point again and again.
+#[derive(Clone, Deserialize)]
+pub(crate) struct EntitySnapshot {
+    interner: MyStringInterner,
+    /// In the order they were inserted
+    entities: Vec<Entity>,
+    /// Slots of removed entities, in the order they are reused, and the
+    /// generations of the next entities in them
+    #[serde(default)]
+    free_slots: Vec<(RawEntityId, u32)>,
+    player_entities: HashMap<String, RawEntityId, FixedState>,
+    player_locations: BTreeMap<String, RawEntityId>,
+    world_entity_id: RawEntityId,
+}
+
+/// An entity and everything in it, however deep, with their strings, to be
+/// kept apart from the entity world, e.g. in a file across restarts; see
+/// `EntityWorld::pack()`. Players aren't packed, nor where exits lead.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct EntityBundle {
+    interner: MyStringInterner,
+    /// The components of the entity first, then those of what it holds,
+    /// each with the index of its container in the bundle
+    entities: Vec<(Components, Option<usize>)>,
+}
+
+impl EntityBundle {
+    /// The components of the entity itself, with the strings of the bundle.
+    pub fn component_info(&self) -> EntityComponentInfo<'_, '_> {
+        let (components, _container) = &self.entities[0];
+        EntityComponentInfo::new(components, &self.interner)
+    }
+}
+
+impl EntitySnapshot {
+    /// Read a snapshot written by `EntityWorld::snapshot_bytes()`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
 }
 
-impl IdGenerator {
-    fn next(&mut self) -> RawEntityId {
-        let new_id = self.next_entity_id;
-        let next_id = self.next_entity_id.get().wrapping_add(1);
+impl EntityArena {
+    fn slot(raw_entity_id: RawEntityId) -> usize {
+        raw_entity_id.id.get() - 1
+    }
+
+    fn raw_entity_id(slot: usize) -> RawEntityId {
+        RawEntityId {
+            id: NonZeroUsize::new(slot + 1).expect("Slot + 1 != 0"),
+        }
+    }
+
+    /// An arena with entities in the order they were inserted, and free
+    /// slots in the order they are reused.
+    fn with_free_slots(entities: Vec<Entity>, free_slots: Vec<(RawEntityId, u32)>) -> Self {
+        let mut arena = EntityArena::default();
+        for entity in entities {
+            arena.insert(entity);
+        }
+        for (raw_entity_id, generation) in free_slots {
+            let slot = Self::slot(raw_entity_id);
+            if slot >= arena.slots.len() {
+                arena.slots.resize_with(slot + 1, Slot::default);
+            }
+            if arena.slots[slot].entity.is_none() {
+                arena.slots[slot].generation = generation;
+                arena.free.push(slot);
+            }
+        }
+        arena
+    }
+
+    fn get(&self, raw_entity_id: &RawEntityId) -> Option<&Entity> {
+        self.slots
+            .get(Self::slot(*raw_entity_id))
+            .and_then(|slot| slot.entity.as_ref())
+    }
+
+    fn get_mut(&mut self, raw_entity_id: &RawEntityId) -> Option<&mut Entity> {
+        self.slots
+            .get_mut(Self::slot(*raw_entity_id))
+            .and_then(|slot| slot.entity.as_mut())
+    }
+
+    /// The ID and generation of the next entity inserted.
+    fn next_id(&self) -> (RawEntityId, u32) {
+        match self.free.last() {
+            Some(&slot) => (Self::raw_entity_id(slot), self.slots[slot].generation),
+            None => (Self::raw_entity_id(self.slots.len()), 0),
+        }
+    }
 
-        self.next_entity_id = match NonZeroUsize::new(next_id) {
-            Some(id) => id,
-            // Reminder: max is 18446744073709551615, so it's unlikely
-            None => panic!("Ran out of IDs!"),
+    /// Put an entity in the slot of its ID, as the newest one.
+    fn insert(&mut self, entity: Entity) {
+        let slot = Self::slot(entity.raw_entity_id);
+        if slot >= self.slots.len() {
+            self.slots.resize_with(slot + 1, Slot::default);
+        }
+        if let Some(index) = self.free.iter().rposition(|free| *free == slot) {
+            self.free.remove(index);
+        }
+
+        let generation = entity.generation;
+        let old_entity = self.slots[slot].entity.replace(entity);
+        assert!(
+            old_entity.is_none(),
+            "Entities should only be inserted in free slots"
+        );
+        self.slots[slot].generation = generation;
+        self.slots[slot].previous = self.last;
+        self.slots[slot].next = None;
+        match self.last {
+            Some(last) => self.slots[last].next = Some(slot),
+            None => self.first = Some(slot),
+        }
+        self.last = Some(slot);
+        self.len += 1;
+    }
+
+    /// Take an entity out of its slot, which is freed for the next
+    /// generation.
+    fn remove(&mut self, raw_entity_id: &RawEntityId) -> Option<Entity> {
+        let slot = Self::slot(*raw_entity_id);
+        let entity = self.slots.get_mut(slot)?.entity.take()?;
+
+        let Slot { previous, next, .. } = self.slots[slot];
+        match previous {
+            Some(previous) => self.slots[previous].next = next,
+            None => self.first = next,
+        }
+        match next {
+            Some(next) => self.slots[next].previous = previous,
+            None => self.last = previous,
+        }
+        self.slots[slot] = Slot {
+            generation: entity.generation.wrapping_add(1),
+            ..Slot::default()
         };
 
-        RawEntityId { id: new_id }
+        self.free.push(slot);
+        self.len -= 1;
+        Some(entity)
+    }
+
+    /// The free slots, in the order they are reused, with the generations
+    /// of the next entities in them.
+    fn free_slots(&self) -> Vec<(RawEntityId, u32)> {
+        self.free
+            .iter()
+            .map(|&slot| (Self::raw_entity_id(slot), self.slots[slot].generation))
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The entities from the oldest to the newest.
+    fn values(&self) -> Entities<'_> {
+        Entities {
+            arena: self,
+            front: self.first,
+            back: self.last,
+            remaining: self.len,
+        }
+    }
+
+    /// The entities in the order of their slots.
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut Entity> {
+        self.slots
+            .iter_mut()
+            .filter_map(|slot| slot.entity.as_mut())
+    }
+}
+
+impl<'a> Iterator for Entities<'a> {
+    type Item = &'a Entity;
+
+    fn next(&mut self) -> Option<&'a Entity> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let slot = &self.arena.slots[self.front?];
+        self.front = slot.next;
+        self.remaining -= 1;
+        slot.entity.as_ref()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl DoubleEndedIterator for Entities<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let slot = &self.arena.slots[self.back?];
+        self.back = slot.previous;
+        self.remaining -= 1;
+        slot.entity.as_ref()
+    }
+}
+
+impl Index<&RawEntityId> for EntityArena {
+    type Output = Entity;
+
+    fn index(&self, raw_entity_id: &RawEntityId) -> &Entity {
+        self.get(raw_entity_id).expect("Entity should exist")
     }
 }
 
 impl EntityWorld {
     pub fn new() -> Self {
         let mut interner = MyStringInterner::new();
-        let mut id_generator = IdGenerator {
-            next_entity_id: NonZeroUsize::new(1).expect("1 != 0"),
-        };
+        let mut entities = EntityArena::default();
+        let (world_entity_id, generation) = entities.next_id();
 
         let keyword = "world";
         let short_description = "the entire world";
@@ -111,59 +460,646 @@ impl EntityWorld {
                 descriptions: interner.descriptions(title, internal, external, lateral),
                 general: GeneralData {
                     vnum: Vnum(0),
-                    area: "world".to_string(),
+                    area: "world".into(),
                     sector: None,
                     entity_type: EntityType::Room,
+                    archetype: None,
                     equipped: None,
                     command_queue: Vec::new(),
                     following: None,
+                    only_at: None,
+                    extras: None,
                 },
                 mobile: None,
                 object: None,
                 door: None,
                 mobprog: None,
-                silver: None,
+                wealth: None,
+                vitals: None,
+                decay: None,
+                attributes: None,
             },
-            raw_entity_id: id_generator.next(),
+            raw_entity_id: world_entity_id,
             contents: Vec::new(),
             contained_by: None,
             leads_to: None,
             leads_from: Vec::new(),
             created_in_era: 1,
+            generation,
+            changed_at: 1,
             player: None,
         };
 
-        let world_entity_id = world_entity.raw_entity_id;
+        let mut from_area = HashMap::default();
+        from_area.insert(
+            world_entity.components.general.area.clone(),
+            vec![world_entity_id],
+        );
 
-        let mut entities = HashMap::with_capacity(1);
-        entities.insert(world_entity_id, world_entity);
+        entities.insert(world_entity);
 
         EntityWorld {
-            id_generator,
             interner,
+            templates: TemplateRegistry::default(),
+            archetypes: ArchetypeRegistry::new(),
             entities,
-            player_entities: HashMap::new(),
+            player_entities: HashMap::default(),
             player_locations: BTreeMap::new(),
             landmarks: BTreeMap::new(),
+            places: BTreeMap::new(),
+            room_index: RoomIndex::default(),
+            from_area,
+            events: Vec::new(),
+            change_tick: 1,
             world_entity_id,
             era: 1,
         }
     }
 
+    /// Copy the whole world, including its strings, so that it can be
+    /// brought back later with `restore()`.
+    pub fn snapshot(&self) -> EntitySnapshot {
+        EntitySnapshot {
+            interner: self.interner.clone(),
+            entities: self.entities.values().cloned().collect(),
+            free_slots: self.entities.free_slots(),
+            player_entities: self.player_entities.clone(),
+            player_locations: self.player_locations.clone(),
+            world_entity_id: self.world_entity_id,
+        }
+    }
+
+    /// Go back to a snapshot, as a new era; see `from_snapshot()`.
+    pub fn restore(&mut self, snapshot: &EntitySnapshot) {
+        *self = EntityWorld::from_snapshot(snapshot.clone(), self);
+    }
+
+    /// Serialize the whole world, including its strings, so that it can be
+    /// read back with `EntitySnapshot::from_bytes()`.
+    pub fn snapshot_bytes(&self) -> Vec<u8> {
+        let snapshot = SnapshotRef {
+            interner: &self.interner,
+            entities: self.entities.values().collect(),
+            free_slots: self.entities.free_slots(),
+            player_entities: &self.player_entities,
+            player_locations: &self.player_locations,
+            world_entity_id: self.world_entity_id,
+        };
+
+        serde_json::to_vec(&snapshot).expect("Entity world should serialize")
+    }
+
+    /// Recreate a world from a snapshot, as a new era of the current world.
+    ///
+    /// Entities keep their IDs, so permanent IDs taken before the snapshot
+    /// (e.g. of rooms) stay valid, and those taken since of entities that
+    /// didn't exist then find nothing. Landmarks, places and templates are
+    /// taken from the current world, with its archetypes, and every entity
+    /// counts as changed.
+    pub fn from_snapshot(snapshot: EntitySnapshot, current: &EntityWorld) -> Self {
+        // The strings of templates may have been interned or collected since
+        let mut interner = snapshot.interner;
+        let mut templates = current.templates.clone();
+        let mut uses = StringUses::new(&current.interner);
+        for components in current.templates.all_components() {
+            uses.count(components);
+        }
+        let strings = MovedStrings::intern_used(&mut interner, &current.interner, &uses);
+        let mut shared = SharedParts::default();
+        for components in templates.all_components_mut() {
+            strings.move_shared_components(components, &mut shared);
+        }
+
+        let mut entity_world = EntityWorld {
+            interner,
+            templates,
+            archetypes: current.archetypes.clone(),
+            entities: EntityArena::with_free_slots(snapshot.entities, snapshot.free_slots),
+            player_entities: snapshot.player_entities,
+            player_locations: snapshot.player_locations,
+            landmarks: current.landmarks.clone(),
+            places: current.places.clone(),
+            room_index: RoomIndex::default(),
+            from_area: HashMap::default(),
+            events: Vec::new(),
+            change_tick: current.change_tick + 1,
+            world_entity_id: snapshot.world_entity_id,
+            era: current.era.wrapping_add(1),
+        };
+        entity_world.rebuild_room_index();
+        for entity in entity_world.entities.values() {
+            entity_world
+                .from_area
+                .entry(entity.components.general.area.clone())
+                .or_default()
+                .push(entity.raw_entity_id);
+        }
+
+        // Everything may differ from what systems saw before
+        let change_tick = entity_world.change_tick;
+        for entity in entity_world.entities.values_mut() {
+            entity.changed_at = change_tick;
+        }
+
+        entity_world
+    }
+
+    /// Index the room of every entity, and the exits of every room, again.
+    fn rebuild_room_index(&mut self) {
+        let mut room_index = RoomIndex::default();
+
+        for entity in self.entities.values() {
+            let mut container = entity.contained_by;
+            while let Some(container_id) = container {
+                let container_entity = self.entity_raw(container_id);
+                if container_entity.components.general.entity_type == EntityType::Room {
+                    break;
+                }
+                container = container_entity.contained_by;
+            }
+
+            if let Some(room_id) = container {
+                if let Some(counted) = self.counted_in_game(entity, Some(room_id)) {
+                    *room_index.in_game.entry(counted).or_default() += 1;
+                }
+                room_index.room_of.insert(entity.raw_entity_id, room_id);
+                room_index
+                    .in_room
+                    .entry(room_id)
+                    .or_default()
+                    .push(entity.raw_entity_id);
+            }
+        }
+
+        for entity in self.entities.values() {
+            if let (Some(to_room_id), Some(room_id)) = (
+                entity.leads_to,
+                room_index.room_of.get(&entity.raw_entity_id),
+            ) {
+                room_index
+                    .exits
+                    .entry(*room_id)
+                    .or_default()
+                    .push((entity.raw_entity_id, to_room_id));
+            }
+        }
+
+        self.room_index = room_index;
+    }
+
+    /// The room of what is put in a container: the container if it is a
+    /// room, or else its own room.
+    fn room_for_contents(&self, container: RawEntityId) -> RawEntityId {
+        if self.entity_raw(container).components.general.entity_type == EntityType::Room {
+            container
+        } else {
+            self.room_index
+                .room_of
+                .get(&container)
+                .copied()
+                .unwrap_or(container)
+        }
+    }
+
+    /// Index an entity as in the room of its container, and what it holds
+    /// too unless it is a room itself, after it was moved.
+    fn reindex_room(&mut self, raw_entity_id: RawEntityId) {
+        let entity = self.entity_raw(raw_entity_id);
+        let new_room = entity
+            .contained_by
+            .map(|container| self.room_for_contents(container));
+        let old_room = self.room_index.room_of.get(&raw_entity_id).copied();
+        if new_room == old_room {
+            return;
+        }
+
+        let is_room = entity.components.general.entity_type == EntityType::Room;
+        let leads_to = entity.leads_to;
+        let contents = entity.contents.clone();
+
+        let counted = (
+            self.counted_in_game(entity, old_room),
+            self.counted_in_game(entity, new_room),
+        );
+        match counted {
+            (Some(counted), None) => {
+                if let Some(count) = self.room_index.in_game.get_mut(&counted) {
+                    *count = count.saturating_sub(1);
+                }
+            }
+            (None, Some(counted)) => *self.room_index.in_game.entry(counted).or_default() += 1,
+            _ => (),
+        }
+
+        if let Some(old_room) = old_room {
+            if let Some(in_room) = self.room_index.in_room.get_mut(&old_room) {
+                in_room.retain(|entity_id| *entity_id != raw_entity_id);
+            }
+            if let Some(exits) = self.room_index.exits.get_mut(&old_room) {
+                exits.retain(|(exit_id, _)| *exit_id != raw_entity_id);
+            }
+        }
+        match new_room {
+            Some(new_room) => {
+                self.room_index.room_of.insert(raw_entity_id, new_room);
+                self.room_index
+                    .in_room
+                    .entry(new_room)
+                    .or_default()
+                    .push(raw_entity_id);
+                if let Some(to_room_id) = leads_to {
+                    self.room_index
+                        .exits
+                        .entry(new_room)
+                        .or_default()
+                        .push((raw_entity_id, to_room_id));
+                }
+            }
+            None => {
+                self.room_index.room_of.remove(&raw_entity_id);
+            }
+        }
+
+        if !is_room {
+            for contained_id in contents {
+                self.reindex_room(contained_id);
+            }
+        }
+    }
+
+    /// The template a mobile or object is counted by for the limits of
+    /// resets, while it is in a room other than limbo.
+    fn counted_in_game(
+        &self,
+        entity: &Entity,
+        room: Option<RawEntityId>,
+    ) -> Option<(EntityType, usize)> {
+        let general = &entity.components.general;
+        let counted = matches!(general.entity_type, EntityType::Mobile | EntityType::Object)
+            && general.vnum.0 != 0
+            && room.is_some()
+            && room.as_ref() != self.landmarks.get("limbo");
+        Some((general.entity_type, general.vnum.0)).filter(|_| counted)
+    }
+
+    /// How many mobiles or objects of a vnum are in the game, not counting
+    /// those in limbo.
+    pub fn count_in_game(&self, entity_type: EntityType, vnum: usize) -> usize {
+        self.room_index
+            .in_game
+            .get(&(entity_type, vnum))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Spawn a mobile from its template, with its mobprogs, in a room. Gives
+    /// nothing if there is no mobile with that vnum.
+    pub fn spawn_mobile(&mut self, vnum: usize, room_id: EntityId) -> Option<EntityId> {
+        let (components, mobprogs) = self.templates.mobile(vnum)?.clone();
+        let wealth = components
+            .mobile
+            .as_ref()
+            .map(|mobile| mobile.wealth.clone())
+            .unwrap_or_default();
+
+        let mobile_id = self.insert_entity(room_id, components);
+        for mobprog in mobprogs {
+            self.insert_entity(mobile_id, mobprog);
+        }
+        if !wealth.is_empty() {
+            self.add_coins(mobile_id, &wealth);
+        }
+
+        Some(mobile_id)
+    }
+
+    /// Spawn an object from its template, with its extra descriptions, in a
+    /// container. Gives nothing if there is no object with that vnum.
+    pub fn spawn_object(&mut self, vnum: usize, container_id: EntityId) -> Option<EntityId> {
+        let (components, extra_descriptions) = self.templates.object(vnum)?.clone();
+
+        let object_id = self.insert_entity(container_id, components);
+        for extra_description in extra_descriptions {
+            self.insert_entity(object_id, extra_description);
+        }
+
+        Some(object_id)
+    }
+
+    /// Give coins to an entity. They join the pile of coins it already has,
+    /// if any, so that coins never lie around in several piles.
+    pub fn add_coins(&mut self, to_entity: EntityId, wealth: &Wealth) {
+        let pile = self
+            .entity_info(to_entity)
+            .objects()
+            .find(|object| object.components().wealth.is_some())
+            .map(|object| object.entity_id());
+
+        match pile {
+            Some(pile_id) => {
+                let mut coins = self
+                    .entity_info(pile_id)
+                    .components()
+                    .wealth
+                    .clone()
+                    .expect("Filtered above to one that has the component");
+                coins.add(wealth);
+                self.set_coins(pile_id, coins);
+            }
+            None => {
+                let pile_id = self
+                    .spawn_archetype(COINS, to_entity, "some coins", |_components| ())
+                    .expect("Coins are registered with the other archetypes");
+                self.set_coins(pile_id, wealth.clone());
+            }
+        }
+    }
+
+    /// Make an entity of an archetype in a container, with its descriptions
+    /// filled in with a name, and the rest of its components from `fill`. It
+    /// belongs to the area of the container's room. Gives nothing if no
+    /// archetype has that name.
+    pub fn spawn_archetype(
+        &mut self,
+        archetype: &str,
+        container_id: EntityId,
+        name: &str,
+        fill: impl FnOnce(&mut Components),
+    ) -> Option<EntityId> {
+        let archetype = self.archetypes.get(archetype)?;
+        let room = self.room_for_contents(self.raw_entity_id(container_id));
+        let area = self.entity_raw(room).components.general.area.clone();
+        let mut components = archetype.components(&mut self.interner, name, area);
+        fill(&mut components);
+
+        Some(self.insert_entity(container_id, components))
+    }
+
+    /// Change the coins of a pile, and what it is called after them.
+    pub fn set_coins(&mut self, pile_id: EntityId, wealth: Wealth) {
+        let (mut pile, interner) = self.entity_info_mut_with_interner(pile_id);
+        pile.set_short_description(interner, &wealth.to_string());
+        pile.set_keyword(interner, &wealth.keywords());
+        pile.components().wealth = Some(wealth);
+    }
+
+    /// Copy an entity and everything in it, with their strings, into a
+    /// bundle; see `EntityBundle`.
+    pub fn pack(&self, entity_id: EntityId) -> EntityBundle {
+        let mut packed = vec![(self.raw_entity_id(entity_id), None)];
+        let mut index = 0;
+        while index < packed.len() {
+            let (raw_entity_id, _container) = packed[index];
+            for content in &self.entity_raw(raw_entity_id).contents {
+                if self.entity_raw(*content).player.is_none() {
+                    packed.push((*content, Some(index)));
+                }
+            }
+            index += 1;
+        }
+
+        let mut uses = StringUses::new(&self.interner);
+        for (raw_entity_id, _container) in &packed {
+            uses.count(&self.entity_raw(*raw_entity_id).components);
+        }
+        let mut interner = MyStringInterner::new();
+        let strings = MovedStrings::intern_used(&mut interner, &self.interner, &uses);
+
+        let mut shared = SharedParts::default();
+        let entities = packed
+            .into_iter()
+            .map(|(raw_entity_id, container)| {
+                let mut components = self.entity_raw(raw_entity_id).components.clone();
+                strings.move_shared_components(&mut components, &mut shared);
+                (components, container)
+            })
+            .collect();
+
+        EntityBundle { interner, entities }
+    }
+
+    /// Make the entities of a bundle again, in a container, and give the ID
+    /// of the one that holds the others.
+    pub fn unpack(&mut self, bundle: &EntityBundle, container_id: EntityId) -> EntityId {
+        let strings = MovedStrings::intern(&mut self.interner, &bundle.interner);
+
+        let mut shared = SharedParts::default();
+        let mut unpacked: Vec<EntityId> = Vec::with_capacity(bundle.entities.len());
+        for (components, container) in &bundle.entities {
+            let mut components = components.clone();
+            strings.move_shared_components(&mut components, &mut shared);
+            let container_id = container.map_or(container_id, |index| unpacked[index]);
+            unpacked.push(self.insert_entity(container_id, components));
+        }
+
+        unpacked[0]
+    }
+
+    /// Put a copy of an entity in a container, along with copies of
+    /// everything in it, however deep: what is in containers, what mobiles
+    /// carry, their mobprogs and extra descriptions. Copies of exits lead
+    /// where the exits do. Players aren't copied, nor what they carry.
+    pub fn clone_entity_recursive(
+        &mut self,
+        entity_id: EntityId,
+        new_parent: EntityId,
+    ) -> EntityId {
+        let entity = self.entity(entity_id);
+        let components = entity.components.clone();
+        let leads_to = entity.leads_to;
+        let contents: Vec<RawEntityId> = entity
+            .contents
+            .iter()
+            .copied()
+            .filter(|content| self.entity_raw(*content).player.is_none())
+            .collect();
+
+        let clone_id = self.insert_entity(new_parent, components);
+        if let Some(leads_to) = leads_to {
+            let to_room_id = self.entity_id_raw(leads_to);
+            self.set_leads_to(clone_id, to_room_id);
+        }
+
+        for content in contents {
+            let content_id = self.entity_id_raw(content);
+            self.clone_entity_recursive(content_id, clone_id);
+        }
+
+        clone_id
+    }
+
     pub fn move_entity(&mut self, entity_id: EntityId, to_room_id: EntityId) {
+        let from_id = self
+            .entity(entity_id)
+            .contained_by
+            .map(|container| self.entity_id_raw(container));
+        self.relocate(entity_id, to_room_id);
+        self.events.push(EntityEvent::Moved {
+            entity_id,
+            from_id,
+            to_id: to_room_id,
+        });
+    }
+
+    /// Take an entity out of the game for good, along with what it holds.
+    pub fn destroy_entity(&mut self, entity_id: EntityId) {
+        self.extract_entity(entity_id, ContentsPolicy::Destroy);
+    }
+
+    /// Take an entity out of the game, and do with what it holds as the
+    /// policy says; see `ContentsPolicy`. The entity is removed from the
+    /// world, with whatever is left in it, and its ID and those of what went
+    /// with it must not be used anymore.
+    pub fn extract_entity(&mut self, entity_id: EntityId, policy: ContentsPolicy) {
+        let limbo = self.landmark("limbo").expect("Limbo should always exist");
+        let entity = self.entity_info(entity_id);
+        // What was in a room has nowhere to be dumped but the junk room
+        let container_id = match entity.entity.contained_by {
+            Some(container)
+                if container != limbo.id
+                    && entity.components().general.entity_type != EntityType::Room =>
+            {
+                self.entity_id_raw(container)
+            }
+            _ => self.place(Place::Junk).unwrap_or(limbo),
+        };
+
+        let contents: Vec<(EntityId, EntityId)> = entity
+            .contained_entities()
+            .filter_map(|content| {
+                let entity_type = content.components().general.entity_type;
+                let to_id = match (entity_type, policy) {
+                    (EntityType::Exit | EntityType::ExtraDescription | EntityType::MobProg, _) => {
+                        return None
+                    }
+                    (EntityType::Player, _) | (_, ContentsPolicy::Dump) => container_id,
+                    (_, ContentsPolicy::Junk) => self.place(Place::Junk).unwrap_or(limbo),
+                    (_, ContentsPolicy::Destroy) => return None,
+                };
+                Some((content.entity_id(), to_id))
+            })
+            .collect();
+
+        for (content_id, to_id) in contents {
+            self.move_entity(content_id, to_id);
+        }
+
+        self.remove_entity(self.raw_entity_id(entity_id));
+        self.events.push(EntityEvent::Destroyed { entity_id });
+    }
+
+    /// Remove an entity from the world, and everything still in it, however
+    /// deep, and forget them wherever they are indexed. Exits that led to it
+    /// lead nowhere anymore.
+    fn remove_entity(&mut self, raw_entity_id: RawEntityId) {
+        let contents = self.entity_raw(raw_entity_id).contents.clone();
+        for content in contents {
+            self.remove_entity(content);
+        }
+
+        let exit_id = self.entity_id_raw(raw_entity_id);
+        self.clear_leads_to(exit_id);
+        let leads_from = self.entity_raw(raw_entity_id).leads_from.clone();
+        for from_exit_id in leads_from {
+            self.clear_leads_to(self.entity_id_raw(from_exit_id));
+        }
+
+        let change_tick = self.change_tick;
+        if let Some(container) = self.entity_mut_raw(raw_entity_id).contained_by.take() {
+            let container = self.entity_mut_raw(container);
+            container
+                .contents
+                .retain(|contained_entity_id| *contained_entity_id != raw_entity_id);
+            container.changed_at = change_tick;
+        }
+        self.reindex_room(raw_entity_id);
+        self.room_index.in_room.remove(&raw_entity_id);
+        self.room_index.exits.remove(&raw_entity_id);
+
+        let area = self
+            .entity_raw(raw_entity_id)
+            .components
+            .general
+            .area
+            .clone();
+        if let Some(entities) = self.from_area.get_mut(&area) {
+            entities.retain(|entity_id| *entity_id != raw_entity_id);
+        }
+        self.landmarks
+            .retain(|_, room_id| *room_id != raw_entity_id);
+        self.places.retain(|_, room_id| *room_id != raw_entity_id);
+
+        self.entities.remove(&raw_entity_id);
+    }
+
+    /// Open, close, unlock or lock a door, if it isn't already.
+    pub fn set_door_state(&mut self, exit_id: EntityId, closed: bool, locked: bool) {
+        let change_tick = self.change_tick;
+        let exit = self.entity_mut(exit_id);
+        if let Some(door) = &mut exit.components.door {
+            if door.closed != closed || door.locked != locked {
+                door.closed = closed;
+                door.locked = locked;
+                exit.changed_at = change_tick;
+                self.events.push(EntityEvent::DoorStateChanged {
+                    exit_id,
+                    closed,
+                    locked,
+                });
+            }
+        }
+    }
+
+    /// Start a new change tick, once every pulse.
+    pub fn next_change_tick(&mut self) {
+        self.change_tick += 1;
+    }
+
+    /// The current change tick. Systems that only care about what changed
+    /// keep it when they run, to ask for what changed since next time.
+    pub fn change_tick(&self) -> u64 {
+        self.change_tick
+    }
+
+    /// Entities that changed in the given change tick or since: their
+    /// components were borrowed mutably, they were created or moved, or
+    /// something was put in them or taken out.
+    pub fn changed_since(&self, change_tick: u64) -> impl Iterator<Item = EntityInfo<'_>> {
+        self.all_entities()
+            .filter(move |entity| entity.changed_since(change_tick))
+    }
+
+    /// Count an entity as changed, for what is kept about it outside of the
+    /// entity world, like the preferences of a player.
+    pub fn mark_changed(&mut self, entity_id: EntityId) {
+        let change_tick = self.change_tick;
+        self.entity_mut(entity_id).changed_at = change_tick;
+    }
+
+    /// The events since the last call, for the subscribers to hear about.
+    pub fn take_events(&mut self) -> Vec<EntityEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn relocate(&mut self, entity_id: EntityId, to_room_id: EntityId) {
         let raw_entity_id = self.raw_entity_id(entity_id);
 
         // Move out of old room
         let original_room = self.entity_raw(raw_entity_id).contained_by;
         if let Some(room) = original_room {
+            let change_tick = self.change_tick;
             let room = self.entity_mut_raw(room);
             room.contents
-                .retain(|contained_entity_id| contained_entity_id != &raw_entity_id)
+                .retain(|contained_entity_id| contained_entity_id != &raw_entity_id);
+            room.changed_at = change_tick;
         }
 
         // Move into new room
         self.entity_mut(to_room_id).contents.push(raw_entity_id);
         self.entity_mut(entity_id).contained_by = Some(self.raw_entity_id(to_room_id));
+        self.reindex_room(raw_entity_id);
+        self.mark_changed(to_room_id);
+        self.mark_changed(entity_id);
 
         // Update world references
         if let Some(player) = &self
@@ -202,33 +1138,33 @@ impl EntityWorld {
                 .descriptions(&title, &internal, &external, &lateral),
             general: GeneralData {
                 vnum: Vnum(0),
-                area: "players".to_string(),
+                area: "players".into(),
                 sector: None,
                 entity_type: EntityType::Player,
+                archetype: None,
                 equipped: None,
                 command_queue: Vec::new(),
                 following: None,
+                only_at: None,
+                extras: None,
             },
             mobile: None,
             object: None,
             door: None,
             mobprog: None,
-            silver: None,
+            wealth: None,
+            vitals: Some(Vitals::for_player()),
+            decay: None,
+            attributes: None,
         }
     }
 
     pub fn add_player(&mut self, name: &str, components: Components) -> EntityId {
         if let Some(player_entity_id) = self.player_entities.get(name) {
-            return EntityId {
-                id: *player_entity_id,
-                era: self.era,
-            };
+            return self.entity_id_raw(*player_entity_id);
         }
 
-        let room_entity_id = EntityId {
-            id: self.world_entity_id,
-            era: self.era,
-        };
+        let room_entity_id = self.entity_id_raw(self.world_entity_id);
 
         let player_entity_id = self.insert_entity(room_entity_id, components);
 
@@ -246,13 +1182,31 @@ impl EntityWorld {
         if entity_id.era != self.era {
             panic!("Entity IDs should not be stored long-term!");
         }
+        let generation = self
+            .entities
+            .get(&entity_id.id)
+            .map(|entity| entity.generation);
+        if generation != Some(entity_id.generation) {
+            panic!("Entity IDs should not be used after the entity is destroyed");
+        }
         entity_id.id
     }
 
+    /// The ID of an entity that is in the world.
+    fn entity_id_raw(&self, raw_entity_id: RawEntityId) -> EntityId {
+        EntityId {
+            id: raw_entity_id,
+            era: self.era,
+            generation: self.entity_raw(raw_entity_id).generation,
+        }
+    }
+
     /// Attempt to retrieve an entity from an old ID; this entity may no longer exist.
     pub fn old_entity(&self, permanent_entity_id: &PermanentEntityId) -> Option<EntityInfo<'_>> {
         if let Some(entity) = self.entities.get(&permanent_entity_id.id) {
-            if entity.created_in_era == permanent_entity_id.created_in_era {
+            if entity.created_in_era == permanent_entity_id.created_in_era
+                && entity.generation == permanent_entity_id.generation
+            {
                 Some(EntityInfo {
                     entity,
                     entity_world: self,
@@ -265,6 +1219,41 @@ impl EntityWorld {
         }
     }
 
+    /// The entity a weak reference is to, unless it has been destroyed, or
+    /// waits out of the game in limbo.
+    pub fn resolve(&self, weak_ref: &WeakEntityRef) -> Option<EntityInfo<'_>> {
+        let entity = self.weak_entity(weak_ref)?;
+        if entity.contained_by.as_ref() == self.landmarks.get("limbo") {
+            return None;
+        }
+
+        Some(EntityInfo {
+            entity,
+            entity_world: self,
+        })
+    }
+
+    /// The entity a weak reference is to, only if it waits in limbo, like
+    /// what is held in escrow.
+    pub fn resolve_in_limbo(&self, weak_ref: &WeakEntityRef) -> Option<EntityInfo<'_>> {
+        let entity = self.weak_entity(weak_ref)?;
+        if entity.contained_by.as_ref() != self.landmarks.get("limbo") {
+            return None;
+        }
+
+        Some(EntityInfo {
+            entity,
+            entity_world: self,
+        })
+    }
+
+    fn weak_entity(&self, weak_ref: &WeakEntityRef) -> Option<&Entity> {
+        self.entities.get(&weak_ref.id).filter(|entity| {
+            entity.created_in_era == weak_ref.created_in_era
+                && entity.generation == weak_ref.generation
+        })
+    }
+
     fn entity(&self, entity_id: EntityId) -> &Entity {
         self.entity_raw(self.raw_entity_id(entity_id))
     }
@@ -272,7 +1261,7 @@ impl EntityWorld {
     fn entity_raw(&self, raw_entity_id: RawEntityId) -> &Entity {
         self.entities
             .get(&raw_entity_id)
-            .expect("Entities should not be deleted within an era")
+            .expect("Entity IDs should not be used after the entity is destroyed")
     }
 
     fn entity_mut(&mut self, entity_id: EntityId) -> &mut Entity {
@@ -282,7 +1271,7 @@ impl EntityWorld {
     fn entity_mut_raw(&mut self, raw_entity_id: RawEntityId) -> &mut Entity {
         self.entities
             .get_mut(&raw_entity_id)
-            .expect("Entities should not be deleted within an era")
+            .expect("Entity IDs should not be used after the entity is destroyed")
     }
 
     fn entity_info_raw(&self, entity_id: RawEntityId) -> EntityInfo<'_> {
@@ -308,9 +1297,14 @@ impl EntityWorld {
 
     pub fn entity_info_mut(&mut self, entity_id: EntityId) -> EntityInfoMut<'_> {
         let era = self.era;
+        let change_tick = self.change_tick;
         let entity = self.entity_mut(entity_id);
 
-        EntityInfoMut { entity, era }
+        EntityInfoMut {
+            entity,
+            era,
+            change_tick,
+        }
     }
 
     // Not nice, but it'll go away once I switch to string-cache's atoms
@@ -319,39 +1313,183 @@ impl EntityWorld {
         entity_id: EntityId,
     ) -> (EntityInfoMut<'_>, &'_ mut MyStringInterner) {
         let era = self.era;
+        let change_tick = self.change_tick;
         let raw_entity_id = self.raw_entity_id(entity_id);
         let entity = self
             .entities
             .get_mut(&raw_entity_id)
-            .expect("Entities should not be deleted within an era");
+            .expect("Entity IDs should not be used after the entity is destroyed");
 
-        (EntityInfoMut { entity, era }, &mut self.interner)
+        let entity_info = EntityInfoMut {
+            entity,
+            era,
+            change_tick,
+        };
+        (entity_info, &mut self.interner)
     }
 
-    pub fn all_entities(&self) -> impl Iterator<Item = EntityInfo<'_>> {
+    /// All entities, from the first one inserted to the last.
+    pub fn all_entities(&self) -> impl DoubleEndedIterator<Item = EntityInfo<'_>> {
         self.entities.values().map(move |entity| EntityInfo {
             entity,
             entity_world: self,
         })
     }
 
+    /// Entities with the components a query asks for, along with them, in
+    /// the order they were inserted; see `crate::query`.
+    pub fn query<'e, Q: Query<'e>>(&'e self) -> impl Iterator<Item = (EntityInfo<'e>, Q)> {
+        self.all_entities().filter_map(|entity| {
+            let components = entity.components();
+            Q::fetch(components).map(|found| (entity, found))
+        })
+    }
+
+    /// How many entities there are, including those waiting in limbo.
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// How many entities there are of each type.
+    pub fn entity_counts_by_type(&self) -> HashMap<EntityType, usize> {
+        let mut counts = HashMap::new();
+        for entity in self.entities.values() {
+            *counts
+                .entry(entity.components.general.entity_type)
+                .or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// How many entities came from each area, by the short name of the area.
+    pub fn entity_counts_by_area(&self) -> BTreeMap<&str, usize> {
+        self.from_area
+            .iter()
+            .map(|(area, entities)| (&**area, entities.len()))
+            .collect()
+    }
+
+    /// Entities that came from an area, by its short name, wherever they
+    /// are now, limbo included, in the order they came in, until they are
+    /// destroyed. An entity's area
+    /// is that of its template, or of whoever made it, and doesn't change.
+    pub fn entities_from_area(&self, area: &str) -> impl Iterator<Item = EntityInfo<'_>> {
+        self.from_area
+            .get(area)
+            .into_iter()
+            .flatten()
+            .map(move |entity_id| self.entity_info_raw(*entity_id))
+    }
+
+    /// The rooms of an area that are in the world, leaving out those that
+    /// `reload area` replaced.
+    pub fn rooms_of_area(&self, area: &str) -> impl Iterator<Item = EntityInfo<'_>> {
+        self.entities_from_area(area).filter(move |entity| {
+            entity.components().general.entity_type == EntityType::Room
+                && entity.entity.contained_by == Some(self.world_entity_id)
+        })
+    }
+
+    /// Everything in the rooms of an area, however deep, wherever it came
+    /// from; see `entities_in_room()`.
+    pub fn entities_in_area(&self, area: &str) -> impl Iterator<Item = EntityInfo<'_>> {
+        self.rooms_of_area(area)
+            .flat_map(move |room| self.entities_in_room(room.entity_id()))
+    }
+
+    /// How many times the strings of the interner are used, by templates and
+    /// the entities in the world, those waiting in limbo to come back
+    /// included. Destroyed entities are gone from the world, and what only
+    /// they used is unused.
+    fn string_uses(&self) -> StringUses {
+        let mut uses = StringUses::new(&self.interner);
+        for entity in self.entities.values() {
+            uses.count(&entity.components);
+        }
+        for components in self.templates.all_components() {
+            uses.count(components);
+        }
+        uses
+    }
+
+    /// How many strings are interned, how many of them are unused, and what
+    /// interning them saves.
+    pub fn string_statistics(&self) -> StringStatistics {
+        self.string_uses().statistics(&self.interner)
+    }
+
+    /// Drop the strings nothing uses anymore from the interner, like the old
+    /// names of renamed pets, and give how many were dropped. Strings are
+    /// interned again in a new interner, and descriptions that entities
+    /// shared are still shared.
+    pub fn collect_strings(&mut self) -> usize {
+        let uses = self.string_uses();
+        let mut interner = MyStringInterner::new();
+        let strings = MovedStrings::intern_used(&mut interner, &self.interner, &uses);
+
+        let mut shared = SharedParts::default();
+        for entity in self.entities.values_mut() {
+            strings.move_shared_components(&mut entity.components, &mut shared);
+        }
+        for components in self.templates.all_components_mut() {
+            strings.move_shared_components(components, &mut shared);
+        }
+
+        let dropped = self.interner.len() - interner.len();
+        self.interner = interner;
+        dropped
+    }
+
+    /// How many different descriptions entities have between them; those
+    /// spawned from the same template share theirs.
+    pub fn distinct_descriptions(&self) -> usize {
+        self.entities
+            .values()
+            .map(|entity| Arc::as_ptr(&entity.components.descriptions))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Entities that lost their container; see `EntityInfo::is_orphaned()`.
+    pub fn orphaned_entities(&self) -> impl Iterator<Item = EntityInfo<'_>> {
+        self.all_entities().filter(|entity| entity.is_orphaned())
+    }
+
+    pub fn all_players(&self) -> impl Iterator<Item = EntityInfo<'_>> {
+        self.player_entities
+            .values()
+            .map(move |raw_entity_id| self.entity_info_raw(*raw_entity_id))
+    }
+
     pub fn all_entities_mut(&mut self) -> impl Iterator<Item = EntityInfoMut<'_>> {
         let era = self.era;
-        self.entities
-            .values_mut()
-            .map(move |entity| EntityInfoMut { entity, era })
+        let change_tick = self.change_tick;
+        self.entities.values_mut().map(move |entity| EntityInfoMut {
+            entity,
+            era,
+            change_tick,
+        })
     }
 
-    pub fn add_landmark(&mut self, landmark: &'static str, entity_id: EntityId) {
+    pub fn add_landmark(&mut self, landmark: &str, entity_id: EntityId) {
         self.landmarks
-            .insert(landmark, self.raw_entity_id(entity_id));
+            .insert(landmark.to_string(), self.raw_entity_id(entity_id));
     }
 
     pub fn landmark(&self, landmark: &str) -> Option<EntityId> {
-        self.landmarks.get(landmark).map(|room_id| EntityId {
-            id: *room_id,
-            era: self.era,
-        })
+        self.landmarks
+            .get(landmark)
+            .map(|room_id| self.entity_id_raw(*room_id))
+    }
+
+    pub fn set_place(&mut self, place: Place, entity_id: EntityId) {
+        self.places.insert(place, self.raw_entity_id(entity_id));
+    }
+
+    pub fn place(&self, place: Place) -> Option<EntityId> {
+        self.places
+            .get(&place)
+            .map(|room_id| self.entity_id_raw(*room_id))
     }
 
     pub fn room_of(&self, entity_id: EntityId) -> EntityId {
@@ -365,32 +1503,23 @@ impl EntityWorld {
             )
             .expect("EntityWorld should manage .contained_by correctness");
 
-        EntityId {
-            id: room.raw_entity_id,
-            era: self.era,
-        }
+        self.entity_id_raw(room.raw_entity_id)
     }
 
     pub fn player_entity_id(&self, name: &str) -> Option<EntityId> {
         self.player_entities
             .get(name)
-            .map(|raw_entity_id| EntityId {
-                id: *raw_entity_id,
-                era: self.era,
-            })
+            .map(|raw_entity_id| self.entity_id_raw(*raw_entity_id))
     }
 
     pub fn world_entity_id(&self) -> EntityId {
-        EntityId {
-            id: self.world_entity_id,
-            era: self.era,
-        }
+        self.entity_id_raw(self.world_entity_id)
     }
 
     pub fn insert_entity(&mut self, container: EntityId, components: Components) -> EntityId {
         let container = self.raw_entity_id(container);
 
-        let raw_entity_id = self.id_generator.next();
+        let (raw_entity_id, generation) = self.entities.next_id();
 
         let new_entity = Entity {
             components,
@@ -400,33 +1529,93 @@ impl EntityWorld {
             leads_to: None,
             leads_from: Vec::new(),
             created_in_era: self.era,
+            generation,
+            changed_at: self.change_tick,
             player: None,
         };
 
-        self.entities.insert(raw_entity_id, new_entity);
+        self.from_area
+            .entry(new_entity.components.general.area.clone())
+            .or_default()
+            .push(raw_entity_id);
+        self.entities.insert(new_entity);
 
+        let change_tick = self.change_tick;
         let container_entity = self.entity_mut_raw(container);
         container_entity.contents.push(raw_entity_id);
-
-        EntityId {
-            id: raw_entity_id,
-            era: self.era,
-        }
+        container_entity.changed_at = change_tick;
+        self.reindex_room(raw_entity_id);
+
+        let entity_id = self.entity_id_raw(raw_entity_id);
+        self.events.push(EntityEvent::Created {
+            entity_id,
+            container_id: self.entity_id_raw(container),
+        });
+        entity_id
     }
 
+    /// Make an exit lead to a room, instead of wherever it led before.
     pub(crate) fn set_leads_to(&mut self, exit_id: EntityId, to_room_id: EntityId) {
+        self.clear_leads_to(exit_id);
+
         let exit_id = self.raw_entity_id(exit_id);
         let to_room_id = self.raw_entity_id(to_room_id);
 
-        let exit = self.entity_raw(exit_id);
+        self.entity_mut_raw(exit_id).leads_to = Some(to_room_id);
+        self.entity_mut_raw(to_room_id).leads_from.push(exit_id);
+        if let Some(room_id) = self.room_index.room_of.get(&exit_id) {
+            self.room_index
+                .exits
+                .entry(*room_id)
+                .or_default()
+                .push((exit_id, to_room_id));
+        }
+    }
+
+    /// Make an exit lead nowhere.
+    pub(crate) fn clear_leads_to(&mut self, exit_id: EntityId) {
+        let exit_id = self.raw_entity_id(exit_id);
 
-        if exit.leads_to.is_some() {
-            // FIXME
-            unimplemented!("Changing existing exit points is not yet implemented.");
+        if let Some(to_room_id) = self.entity_mut_raw(exit_id).leads_to.take() {
+            self.entity_mut_raw(to_room_id)
+                .leads_from
+                .retain(|from_exit_id| *from_exit_id != exit_id);
         }
+        if let Some(room_id) = self.room_index.room_of.get(&exit_id) {
+            if let Some(exits) = self.room_index.exits.get_mut(room_id) {
+                exits.retain(|(from_exit_id, _)| *from_exit_id != exit_id);
+            }
+        }
+    }
 
-        self.entity_mut_raw(exit_id).leads_to = Some(to_room_id);
-        self.entity_mut_raw(to_room_id).leads_from.push(exit_id);
+    /// Everything in a room, however deep: who is there and what they carry,
+    /// what lies there and in containers, and the exits and extra
+    /// descriptions of the room, in the order they came in.
+    pub fn entities_in_room(&self, room_id: EntityId) -> impl Iterator<Item = EntityInfo<'_>> {
+        self.room_index
+            .in_room
+            .get(&self.raw_entity_id(room_id))
+            .into_iter()
+            .flatten()
+            .map(move |entity_id| self.entity_info_raw(*entity_id))
+    }
+
+    /// The exits of a room that lead somewhere, with the rooms they lead to.
+    pub fn adjacent_rooms(
+        &self,
+        room_id: EntityId,
+    ) -> impl Iterator<Item = (EntityInfo<'_>, EntityInfo<'_>)> {
+        self.room_index
+            .exits
+            .get(&self.raw_entity_id(room_id))
+            .into_iter()
+            .flatten()
+            .map(move |(exit_id, to_room_id)| {
+                (
+                    self.entity_info_raw(*exit_id),
+                    self.entity_info_raw(*to_room_id),
+                )
+            })
     }
 }
 
@@ -435,10 +1624,18 @@ impl<'e> EntityInfoMut<'e> {
         EntityId {
             id: self.entity.raw_entity_id,
             era: self.era,
+            generation: self.entity.generation,
         }
     }
 
+    /// The components, without counting the entity as changed.
+    pub fn peek(&self) -> &Components {
+        &self.entity.components
+    }
+
+    /// The components, to change them; the entity counts as changed.
     pub fn components(&'e mut self) -> &'e mut Components {
+        self.entity.changed_at = self.change_tick;
         &mut self.entity.components
     }
 
@@ -447,8 +1644,25 @@ impl<'e> EntityInfoMut<'e> {
         interner: &mut MyStringInterner,
         short_description: &str,
     ) {
+        self.entity.changed_at = self.change_tick;
         interner.set_short_description(&mut self.entity.components.act_info, short_description);
     }
+
+    pub fn set_keyword(&mut self, interner: &mut MyStringInterner, keyword: &str) {
+        self.entity.changed_at = self.change_tick;
+        interner.set_keyword(&mut self.entity.components.act_info, keyword);
+    }
+
+    /// Set an attribute by name, or remove it if there is no value.
+    pub fn set_attribute(
+        &mut self,
+        interner: &mut MyStringInterner,
+        name: &str,
+        value: Option<AttrValue>,
+    ) {
+        self.entity.changed_at = self.change_tick;
+        interner.set_attribute(&mut self.entity.components.attributes, name, value);
+    }
 }
 
 impl<'e> EntityInfo<'e> {
@@ -456,6 +1670,7 @@ impl<'e> EntityInfo<'e> {
         EntityId {
             id: self.entity.raw_entity_id,
             era: self.entity_world.era,
+            generation: self.entity.generation,
         }
     }
 
@@ -463,9 +1678,24 @@ impl<'e> EntityInfo<'e> {
         PermanentEntityId {
             id: self.entity.raw_entity_id,
             created_in_era: self.entity.created_in_era,
+            generation: self.entity.generation,
+        }
+    }
+
+    pub fn weak_ref(&self) -> WeakEntityRef {
+        WeakEntityRef {
+            id: self.entity.raw_entity_id,
+            created_in_era: self.entity.created_in_era,
+            generation: self.entity.generation,
         }
     }
 
+    /// Whether the entity changed in the given change tick or since; see
+    /// `EntityWorld::changed_since()`.
+    pub fn changed_since(&self, change_tick: u64) -> bool {
+        self.entity.changed_at >= change_tick
+    }
+
     pub fn main_keyword(&self) -> &str {
         self.component_info()
             .keyword()
@@ -487,13 +1717,18 @@ impl<'e> EntityInfo<'e> {
     }
 
     pub fn leads_to(&self) -> Option<EntityId> {
-        self.entity.leads_to.map(|leads_to| {
-            let to_room = self.entity_world.entity_raw(leads_to);
-            EntityId {
-                id: to_room.raw_entity_id,
-                era: self.entity_world.era,
-            }
-        })
+        self.entity
+            .leads_to
+            .map(|leads_to| self.entity_world.entity_id_raw(leads_to))
+    }
+
+    /// Exits that lead to this room.
+    pub fn leads_from(&self) -> impl Iterator<Item = EntityInfo<'e>> {
+        let entity_world = self.entity_world;
+        self.entity
+            .leads_from
+            .iter()
+            .map(move |exit_id| entity_world.entity_info_raw(*exit_id))
     }
 
     pub fn room(&self) -> EntityInfo<'e> {
@@ -505,6 +1740,21 @@ impl<'e> EntityInfo<'e> {
         }
     }
 
+    /// Whether the container of this entity is gone, or doesn't have it
+    /// among its contents, or it has none and isn't the world itself. It
+    /// can't be reached from the world then; something went wrong when it
+    /// or its container was moved or removed.
+    pub fn is_orphaned(&self) -> bool {
+        match self.entity.contained_by {
+            None => self.entity.raw_entity_id != self.entity_world.world_entity_id,
+            Some(container) => self
+                .entity_world
+                .entities
+                .get(&container)
+                .is_none_or(|container| !container.contents.contains(&self.entity.raw_entity_id)),
+        }
+    }
+
     pub fn is_exit(&self) -> bool {
         matches!(self.entity.components.general.entity_type, EntityType::Exit)
     }
@@ -537,6 +1787,11 @@ impl<'e> EntityInfo<'e> {
         )
     }
 
+    /// The name of the player controlling this entity, if any.
+    pub fn player_name(&self) -> Option<&'e str> {
+        self.entity.player.as_deref()
+    }
+
     pub fn is_player_with_name(&self, player_name: &str) -> bool {
         self.entity_world.player_entities.get(player_name) == Some(&self.entity.raw_entity_id)
     }
@@ -633,6 +1888,16 @@ impl<'e> EntityInfo<'e> {
     pub fn find_entity<F>(&self, keyword: &str, matcher: F) -> Found<'e>
     where
         F: Fn(&EntityInfo) -> bool,
+    {
+        self.find_seen_entity(keyword, matcher, |_| true)
+    }
+
+    /// Like `find_entity()`, but entities that aren't seen are left out, as if
+    /// they weren't there; see `Players::seen_by()`.
+    pub fn find_seen_entity<F, S>(&self, keyword: &str, matcher: F, seen: S) -> Found<'e>
+    where
+        F: Fn(&EntityInfo) -> bool,
+        S: Fn(&EntityInfo) -> bool,
     {
         let room_id = self.entity_world.room_of(self.entity_id());
         let room = self.entity_world.entity_info(room_id);
@@ -652,6 +1917,10 @@ impl<'e> EntityInfo<'e> {
             .chain(room.contained_entities_with_descriptions());
 
         for entity in inventory_and_room {
+            if entity.entity_id() != self.entity_id() && !seen(&entity) {
+                continue;
+            }
+
             if entity
                 .component_info()
                 .keyword()
@@ -696,3 +1965,10 @@ impl std::fmt::Display for EntityInfo<'_> {
         self.short_description(f, capitalized)
     }
 }
+
+/// Shows the number of the entity, e.g. to tell entities apart in `stat`.
+impl std::fmt::Display for EntityId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id.id)
+    }
+}
@@ -0,0 +1,68 @@
+//! Objects the game holds for players, like those attached to letters or put
+//! up for auction.
+//!
+//! An escrowed object is taken out of the game whole, with everything about
+//! it, and waits in limbo, where nothing finds it and it doesn't decay, until
+//! it is released to someone. As entities don't survive a restart, it is also
+//! packed into an `EntityBundle` with its strings, saved with whatever holds
+//! it, and made again from that as it was if it isn't waiting anymore.
+
+use serde::{Deserialize, Serialize};
+
+use crate::entity::{EntityBundle, EntityId, EntityWorld, WeakEntityRef};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Escrowed {
+    /// The object waiting in limbo, until the game restarts
+    #[serde(skip)]
+    object: Option<WeakEntityRef>,
+    /// The object as it was when it was escrowed
+    bundle: EntityBundle,
+}
+
+impl Escrowed {
+    /// Take an object out of the game, to hold it until it is released.
+    pub fn take(entity_world: &mut EntityWorld, object_id: EntityId) -> Self {
+        let limbo = entity_world
+            .landmark("limbo")
+            .expect("Limbo should always exist");
+
+        let escrowed = Escrowed {
+            object: Some(entity_world.entity_info(object_id).weak_ref()),
+            bundle: entity_world.pack(object_id),
+        };
+        entity_world.move_entity(object_id, limbo);
+
+        escrowed
+    }
+
+    /// What the object is called, e.g. "a sword".
+    pub fn short_description(&self) -> &str {
+        self.bundle.component_info().short_description()
+    }
+
+    /// Give the object to someone, or make it again if it was lost with a
+    /// restart.
+    pub fn release(&self, entity_world: &mut EntityWorld, to_entity: EntityId) -> EntityId {
+        match self.waiting(entity_world) {
+            Some(object_id) => {
+                entity_world.move_entity(object_id, to_entity);
+                object_id
+            }
+            None => entity_world.unpack(&self.bundle, to_entity),
+        }
+    }
+
+    /// Get rid of the object for good, e.g. when who it was held for is
+    /// deleted.
+    pub fn destroy(&self, entity_world: &mut EntityWorld) {
+        if let Some(object_id) = self.waiting(entity_world) {
+            entity_world.destroy_entity(object_id);
+        }
+    }
+
+    fn waiting(&self, entity_world: &EntityWorld) -> Option<EntityId> {
+        let object = entity_world.resolve_in_limbo(self.object.as_ref()?)?;
+        Some(object.entity_id())
+    }
+}
@@ -0,0 +1,313 @@
+//! The event log: a record of every command players type, their logins and
+//! logouts, their deaths, and what immortals do, for immortals looking into
+//! reports of abuse.
+//!
+//! Each event is a line of JSON in `data/log/events.jsonl`, e.g.
+//! `{"time":1700000000,"kind":"command","player":"Bob","room":3001,"text":"get all"}`.
+//! When the file grows past a megabyte, it is moved to `events.1.jsonl`, the
+//! one before to `events.2.jsonl`, and so on; the oldest one is dropped.
+//!
+//! Events are kept in memory until `WorldState::save_changes()` appends them
+//! to the file. Immortals look up the events of a player with
+//! `history <player>`, which like `reload` only queues a request; the files
+//! are read later by `WorldState::run_history_lookups()`.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agent::EntityAgent, colors::decolorize, echo, files::Files, notes::format_age, snapshots::tell,
+    state::unix_time, WorldState,
+};
+
+const LOG_DIRECTORY: &str = "data/log";
+/// Size of the current file when it is rotated
+const ROTATE_BYTES: usize = 1024 * 1024;
+/// Files kept, counting the current one
+const KEPT_FILES: usize = 5;
+/// Events kept while they can't be written, e.g. in the CLI
+const MAX_PENDING: usize = 1000;
+/// Events `history` shows unless asked for another number, and at most
+const DEFAULT_HISTORY: usize = 20;
+const MAX_HISTORY: usize = 500;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EventKind {
+    Command,
+    Login,
+    Logout,
+    Death,
+    Admin,
+}
+
+impl EventKind {
+    fn color(self) -> &'static str {
+        match self {
+            EventKind::Command => "`w",
+            EventKind::Login | EventKind::Logout => "`G",
+            EventKind::Death => "`R",
+            EventKind::Admin => "`Y",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            EventKind::Command => "command",
+            EventKind::Login => "login",
+            EventKind::Logout => "logout",
+            EventKind::Death => "death",
+            EventKind::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Event {
+    /// Unix time
+    time: u64,
+    kind: EventKind,
+    player: String,
+    /// Vnum of the room the player was in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    room: Option<usize>,
+    text: String,
+}
+
+struct HistoryLookup {
+    by: String,
+    player: String,
+    count: usize,
+}
+
+pub(crate) struct EventLog {
+    pending: VecDeque<Event>,
+    /// Size of the current file, once it was looked at
+    current_size: Option<usize>,
+    lookups: Vec<HistoryLookup>,
+}
+
+/// The current file for 0, and rotated ones after it.
+fn log_path(index: usize) -> String {
+    match index {
+        0 => format!("{}/events.jsonl", LOG_DIRECTORY),
+        index => format!("{}/events.{}.jsonl", LOG_DIRECTORY, index),
+    }
+}
+
+fn not_found(result: Result<(), std::io::Error>) -> Result<(), std::io::Error> {
+    match result {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        EventLog {
+            pending: VecDeque::new(),
+            current_size: None,
+            lookups: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, kind: EventKind, player: &str, room: Option<usize>, text: &str) {
+        if self.pending.len() >= MAX_PENDING {
+            self.pending.pop_front();
+        }
+
+        self.pending.push_back(Event {
+            time: unix_time(),
+            kind,
+            player: player.to_string(),
+            room,
+            text: decolorize(text).into_owned(),
+        });
+    }
+
+    /// Append the events since the last call to the current file, rotating
+    /// files first if it grew too large.
+    pub fn save_changes(&mut self, files: &dyn Files) -> Result<(), std::io::Error> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = String::new();
+        for event in &self.pending {
+            lines.push_str(&serde_json::to_string(event)?);
+            lines.push('\n');
+        }
+
+        let current_size = match self.current_size {
+            Some(size) => size,
+            None => files
+                .read_file_raw(&log_path(0))
+                .map(|contents| contents.len())
+                .unwrap_or(0),
+        };
+
+        let current_size = if current_size > 0 && current_size + lines.len() > ROTATE_BYTES {
+            rotate(files)?;
+            0
+        } else {
+            current_size
+        };
+
+        files.append_file_raw(&log_path(0), lines.as_bytes())?;
+        self.current_size = Some(current_size + lines.len());
+        self.pending.clear();
+
+        Ok(())
+    }
+}
+
+/// Move each file to the next index, dropping the oldest one.
+fn rotate(files: &dyn Files) -> Result<(), std::io::Error> {
+    not_found(files.remove_file(&log_path(KEPT_FILES - 1)))?;
+
+    for index in (0..KEPT_FILES - 1).rev() {
+        let contents = match files.read_file_raw(&log_path(index)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        files.write_file_raw(&log_path(index + 1), &contents)?;
+        not_found(files.remove_file(&log_path(index)))?;
+    }
+
+    Ok(())
+}
+
+/// The last events of a player, oldest first, from the oldest file to the
+/// current one.
+fn player_history(
+    files: &dyn Files,
+    player: &str,
+    count: usize,
+) -> Result<Vec<Event>, std::io::Error> {
+    let mut events = VecDeque::new();
+
+    for index in (0..KEPT_FILES).rev() {
+        let contents = match files.read_file_raw(&log_path(index)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+
+        let contents = String::from_utf8_lossy(&contents);
+        // A line cut short by a crash is skipped, rather than the whole file
+        let player_events = contents
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Event>(line).ok())
+            .filter(|event| event.player.eq_ignore_ascii_case(player));
+
+        for event in player_events {
+            if events.len() == count {
+                events.pop_front();
+            }
+            events.push_back(event);
+        }
+    }
+
+    Ok(events.into())
+}
+
+/// Answer `history` commands.
+pub(crate) fn run_history_lookups(world_state: &mut WorldState, files: &dyn Files) {
+    if world_state.event_log.lookups.is_empty() {
+        return;
+    }
+
+    // Include what happened up to now
+    if let Err(err) = world_state.event_log.save_changes(files) {
+        println!("Could not write the event log: {}", err);
+    }
+
+    let now = unix_time();
+    for lookup in std::mem::take(&mut world_state.event_log.lookups) {
+        let mut message = String::new();
+
+        match player_history(files, &lookup.player, lookup.count) {
+            Ok(events) if events.is_empty() => {
+                echo!(message, "No events were logged for {}.\r\n", lookup.player);
+            }
+            Ok(events) => {
+                echo!(
+                    message,
+                    "The last {} events of {}:\r\n",
+                    events.len(),
+                    events[0].player
+                );
+                for event in events {
+                    let room = event
+                        .room
+                        .map(|room| format!("in {}", room))
+                        .unwrap_or_default();
+                    echo!(
+                        message,
+                        "`D{:>16} {:<9}`^ {}{:<7}`^ {}\r\n",
+                        format_age(now.saturating_sub(event.time)),
+                        room,
+                        event.kind.color(),
+                        event.kind.name(),
+                        event.text
+                    );
+                }
+            }
+            Err(err) => {
+                echo!(message, "Could not read the event log: {}\r\n", err);
+            }
+        }
+
+        tell(world_state, &lookup.by, &message);
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Add an event of the player this agent is for; nothing is logged for
+    /// mobiles.
+    pub fn record_event(&mut self, kind: EventKind, text: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if let Some(player) = myself.player_name() {
+            let room = myself.room().components().general.vnum.0;
+            self.event_log.record(kind, player, Some(room), text);
+        }
+    }
+
+    pub fn do_history(&mut self, args: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let by = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can look up history.\r\n");
+                return;
+            }
+        };
+
+        let (player, count) = match *args {
+            [player] => (player, DEFAULT_HISTORY),
+            [player, count] => match count.parse::<usize>() {
+                Ok(count) if count > 0 => (player, count.min(MAX_HISTORY)),
+                _ => {
+                    echo!(self.info(), "How many events should be shown?\r\n");
+                    return;
+                }
+            },
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Whistory <player>`^' or '`Whistory <player> <count>`^'.\r\n"
+                );
+                return;
+            }
+        };
+
+        self.event_log.lookups.push(HistoryLookup {
+            by,
+            player: player.to_string(),
+            count,
+        });
+        echo!(self.info(), "Looking through the event log...\r\n");
+    }
+}
@@ -0,0 +1,188 @@
+//! Events of the entity world, and the systems that subscribe to them.
+//!
+//! `EntityWorld` notes an `EntityEvent` whenever an entity is created, moved
+//! or destroyed, or a door changes, wherever in the game that happens. After
+//! each command, and each system of a pulse, `dispatch_events()` hands them to
+//! every subscriber of the `EventBus`, in the order they subscribed, so that
+//! what follows from a change is done in one place instead of by every
+//! command that makes it; e.g. a door opened by a player, a mobprog or a
+//! reset opens on the other side too.
+//!
+//! Subscribers may cause events of their own, which are dispatched in turn.
+
+use std::fmt;
+
+use crate::{echo, entity::EntityId, world::opposite_direction, WorldState};
+
+/// Times events caused by subscribers are dispatched in turn, at most, in
+/// case two of them keep undoing each other
+const MAX_ROUNDS: usize = 8;
+
+#[derive(Clone, Copy)]
+pub(crate) enum EntityEvent {
+    /// An entity was made in a container
+    Created {
+        entity_id: EntityId,
+        container_id: EntityId,
+    },
+    /// An entity went from a container to another
+    Moved {
+        entity_id: EntityId,
+        from_id: Option<EntityId>,
+        to_id: EntityId,
+    },
+    /// An entity was taken out of the game; see `EntityWorld::extract_entity()`
+    Destroyed { entity_id: EntityId },
+    /// A door was opened, closed, unlocked or locked
+    DoorStateChanged {
+        exit_id: EntityId,
+        closed: bool,
+        locked: bool,
+    },
+}
+
+/// A system that hears about every event.
+#[derive(Clone, Copy)]
+struct Subscriber {
+    name: &'static str,
+    handler: fn(&mut WorldState, &EntityEvent),
+}
+
+pub(crate) struct EventBus {
+    subscribers: Vec<Subscriber>,
+}
+
+impl EventBus {
+    /// An event bus with all of the game's subscribers, in the order they
+    /// hear about events.
+    pub fn new() -> Self {
+        let mut event_bus = EventBus {
+            subscribers: Vec::new(),
+        };
+
+        event_bus.subscribe("log", log_event);
+        event_bus.subscribe("doors", sync_doors);
+
+        event_bus
+    }
+
+    /// Hand every event to a handler from now on.
+    pub fn subscribe(&mut self, name: &'static str, handler: fn(&mut WorldState, &EntityEvent)) {
+        self.subscribers.push(Subscriber { name, handler });
+    }
+}
+
+/// Hand the events of the entity world so far to every subscriber.
+pub(crate) fn dispatch_events(world_state: &mut WorldState) {
+    for _ in 0..MAX_ROUNDS {
+        let events = world_state.entity_world.take_events();
+        if events.is_empty() {
+            return;
+        }
+
+        for event in &events {
+            for index in 0..world_state.event_bus.subscribers.len() {
+                let Subscriber { name, handler } = world_state.event_bus.subscribers[index];
+                tracing::info_span!("subscriber", name).in_scope(|| handler(world_state, event));
+            }
+        }
+    }
+
+    // Left over from subscribers that keep causing events
+    world_state.entity_world.take_events();
+}
+
+fn log_event(_world_state: &mut WorldState, event: &EntityEvent) {
+    tracing::trace!("{}", event);
+}
+
+/// Open, close, unlock or lock the other side of a door along with it, and
+/// tell those on that side.
+fn sync_doors(world_state: &mut WorldState, event: &EntityEvent) {
+    let (exit_id, closed, locked) = match *event {
+        EntityEvent::DoorStateChanged {
+            exit_id,
+            closed,
+            locked,
+        } => (exit_id, closed, locked),
+        _ => return,
+    };
+
+    let entity_world = &world_state.entity_world;
+    let exit = entity_world.entity_info(exit_id);
+    let other_side = exit.leads_to().and_then(|leads_to| {
+        entity_world
+            .entity_info(leads_to)
+            .exits()
+            .find(|other_exit| other_exit.main_keyword() == opposite_direction(exit.main_keyword()))
+            .and_then(|other_exit| {
+                let door = other_exit.components().door.as_ref()?;
+                Some((other_exit.entity_id(), door.closed, door.locked))
+            })
+    });
+    let (other_exit_id, other_closed, other_locked) = match other_side {
+        Some(other_side) if other_side.1 != closed || other_side.2 != locked => other_side,
+        _ => return,
+    };
+
+    world_state
+        .entity_world
+        .set_door_state(other_exit_id, closed, locked);
+
+    let other_exit = world_state.entity_world.entity_info(other_exit_id);
+    let mut act = world_state.players.act_alone(&other_exit);
+    if other_closed != closed {
+        let verb = if closed { "closed" } else { "opened" };
+        echo!(act.myself(), "You are {} from the other side.\r\n", verb);
+        echo!(act.others(), "$^$n is {} from the other side.\r\n", verb);
+    } else if other_locked != locked {
+        let verb = if locked { "locked" } else { "unlocked" };
+        echo!(act.myself(), "You are {} from the other side.\r\n", verb);
+        echo!(
+            act.others(),
+            "You hear a clicking sound as $n is {}.\r\n",
+            verb
+        );
+    }
+}
+
+/// Shows the numbers of the entities, like `stat` does.
+impl fmt::Display for EntityEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntityEvent::Created {
+                entity_id,
+                container_id,
+            } => write!(f, "entity {} created in {}", entity_id, container_id),
+            EntityEvent::Moved {
+                entity_id,
+                from_id: Some(from_id),
+                to_id,
+            } => write!(
+                f,
+                "entity {} moved from {} to {}",
+                entity_id, from_id, to_id
+            ),
+            EntityEvent::Moved {
+                entity_id,
+                from_id: None,
+                to_id,
+            } => write!(f, "entity {} moved to {}", entity_id, to_id),
+            EntityEvent::Destroyed { entity_id } => write!(f, "entity {} destroyed", entity_id),
+            EntityEvent::DoorStateChanged {
+                exit_id,
+                closed,
+                locked,
+            } => write!(
+                f,
+                "door {} is now {}",
+                exit_id,
+                match (closed, locked) {
+                    (_, true) => "locked",
+                    (true, false) => "closed",
+                    (false, false) => "open",
+                }
+            ),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Areas of the world as JSON, and loading them back.
+//!
+//! The JSON is a document like `{"format": "demimud-areas", "version": 1,
+//! "areas": [...]}`, with the areas as their types from `crate::world` are
+//! serialized: the area data, and its rooms, objects, mobiles, resets, shops
+//! and mobprogs. Keys are sorted, so that exports of the same areas are the
+//! same, and text uses plain newlines, to be edited in any tool. The version
+//! only changes when a change to the types would keep older documents from
+//! loading.
+//!
+//! A JSON file can be listed in the area list like any area file, and all
+//! the areas in it are loaded. When one of them is saved with `asave`, the
+//! file is written again as JSON, with all of its areas.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    file_parser::{FileParser, ParseResult},
+    files::fix_newlines,
+    load::{check_vnum, remember_area},
+    socials::Social,
+    world::{Area, AreaData, Help, MobProg, Mobile, Object, ResetCommand, Room, Shop, World},
+    WorldState,
+};
+
+/// What JSON documents of areas say they are
+const FORMAT: &str = "demimud-areas";
+/// Version of the JSON documents written
+const VERSION: u32 = 1;
+
+/// An area as in `Area`, with the templates borrowed from the world
+#[derive(Serialize)]
+struct AreaRef<'a> {
+    area_data: &'a AreaData,
+
+    rooms: Vec<&'a Room>,
+    objects: Vec<&'a Object>,
+    mobiles: Vec<&'a Mobile>,
+    resets: &'a [ResetCommand],
+    shops: Vec<&'a Shop>,
+    mobprogs: Vec<&'a MobProg>,
+    helps: Vec<&'a Help>,
+    socials: Vec<&'a Social>,
+}
+
+#[derive(Serialize)]
+struct Export<'a> {
+    format: &'a str,
+    version: u32,
+    areas: Vec<AreaRef<'a>>,
+}
+
+#[derive(Deserialize)]
+struct Import {
+    format: String,
+    version: u32,
+    areas: Vec<Area>,
+}
+
+/// Change every string in a JSON value, wherever it is.
+fn map_strings(value: &mut Value, change: &impl Fn(&str) -> String) {
+    match value {
+        Value::String(string) => *string = change(string),
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| map_strings(value, change)),
+        Value::Object(values) => values
+            .values_mut()
+            .for_each(|value| map_strings(value, change)),
+        _ => (),
+    }
+}
+
+/// Some areas of the world, by their index, as a JSON document.
+pub(super) fn areas_json(world: &World, indices: &[usize]) -> String {
+    let areas = indices
+        .iter()
+        .map(|&index| {
+            let (area_data, resets) = &world.areas[index];
+            let in_area = |area: &str| area == area_data.short_name;
+
+            AreaRef {
+                area_data,
+                rooms: world.rooms.iter().filter(|t| in_area(&t.area)).collect(),
+                objects: world.objects.iter().filter(|t| in_area(&t.area)).collect(),
+                mobiles: world.mobiles.iter().filter(|t| in_area(&t.area)).collect(),
+                resets,
+                shops: world.shops.iter().filter(|t| in_area(&t.area)).collect(),
+                mobprogs: world.mobprogs.iter().filter(|t| in_area(&t.area)).collect(),
+                helps: world.helps.iter().filter(|t| in_area(&t.area)).collect(),
+                socials: world.socials.iter().filter(|t| in_area(&t.area)).collect(),
+            }
+        })
+        .collect();
+
+    let export = Export {
+        format: FORMAT,
+        version: VERSION,
+        areas,
+    };
+
+    // Loaded text uses telnet's newlines; files use plain ones
+    let mut value = serde_json::to_value(&export).expect("Areas are always valid JSON");
+    map_strings(&mut value, &|string| string.replace("\r\n", "\n"));
+
+    let mut json = serde_json::to_string_pretty(&value).expect("Areas are always valid JSON");
+    json.push('\n');
+    json
+}
+
+/// Parse a JSON document of areas, as written by `areas_json()`.
+pub(super) fn load_json_areas(contents: &str, file_name: &str) -> ParseResult<Vec<Area>> {
+    let parser = FileParser::new(contents, file_name);
+    let json_error = |err: serde_json::Error| parser.error(&format!("Invalid JSON: {}", err));
+
+    let mut value: Value = serde_json::from_str(contents).map_err(json_error)?;
+    map_strings(&mut value, &|string| fix_newlines(string).into_owned());
+
+    let import: Import = serde_json::from_value(value).map_err(json_error)?;
+    if import.format != FORMAT {
+        return Err(parser.error(&format!("Not a document of areas: '{}'", import.format)));
+    }
+    if import.version != VERSION {
+        return Err(parser.error(&format!(
+            "Unsupported version {}, expected {}",
+            import.version, VERSION
+        )));
+    }
+
+    let mut areas = import.areas;
+    for area in &mut areas {
+        let vnums = area
+            .rooms
+            .iter()
+            .map(|room| room.vnum)
+            .chain(area.objects.iter().map(|object| object.vnum))
+            .chain(area.mobiles.iter().map(|mobile| mobile.vnum))
+            .chain(area.shops.iter().map(|shop| shop.vnum))
+            .chain(area.mobprogs.iter().map(|mobprog| mobprog.vnum));
+        for vnum in vnums {
+            check_vnum(&parser, vnum.0)?;
+        }
+
+        remember_area(area);
+    }
+
+    Ok(areas)
+}
+
+impl WorldState {
+    /// Every area, or only the one found by its short name or file name, as
+    /// a JSON document that can be loaded back.
+    pub fn export_json(&self, area: Option<&str>) -> Result<String, String> {
+        let indices: Vec<usize> = match area {
+            Some(area) => match self.world.find_area(area) {
+                Some(index) => vec![index],
+                None => return Err(format!("There is no area called '{}'.", area)),
+            },
+            None => (0..self.world.areas.len()).collect(),
+        };
+
+        Ok(areas_json(&self.world, &indices))
+    }
+}
@@ -1,3 +1,30 @@
+use std::fmt::{self, Display, Formatter};
+
+/// What went wrong where in a file, e.g. an area file that was cut short or
+/// edited by hand.
+#[derive(Debug)]
+pub(crate) struct ParseError {
+    file_name: String,
+    line: usize,
+    column: usize,
+    message: String,
+    last_line: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "In file {}, line {}, column {}: {}\nLast line: {:?}",
+            self.file_name, self.line, self.column, self.message, self.last_line
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub(crate) type ParseResult<T> = Result<T, ParseError>;
+
 pub(crate) struct FileParser<'a> {
     remaining: &'a str,
     all_text: &'a str,
@@ -13,61 +40,75 @@ impl<'a> FileParser<'a> {
         }
     }
 
-    pub fn panic_on_line(&self, message: &str) -> ! {
-        let bytes_read = self.all_text.len() - self.remaining.len();
+    /// An error at the current position.
+    pub fn error(&self, message: &str) -> ParseError {
+        self.error_at(self.all_text.len() - self.remaining.len(), message)
+    }
+
+    /// An error at a byte of the text, like one found by another parser.
+    pub fn error_at(&self, position: usize, message: &str) -> ParseError {
+        let mut bytes_read = position.min(self.all_text.len());
+        while !self.all_text.is_char_boundary(bytes_read) {
+            bytes_read -= 1;
+        }
         let processed_slice = &self.all_text[0..bytes_read];
         let lines = processed_slice.chars().filter(|c| *c == '\n').count();
-        let columns = processed_slice
-            .chars()
-            .rev()
-            .take_while(|c| *c != '\n')
-            .count();
-        let last_line = &processed_slice[processed_slice.len() - columns..];
-
-        panic!(
-            "In file {}, line {}, column {}: {}\nLast line: {:?}\n",
-            self.file_name,
-            lines + 1,
-            columns,
-            message,
-            last_line
-        );
+        let last_line = processed_slice
+            .rsplit('\n')
+            .next()
+            .unwrap_or(processed_slice);
+
+        ParseError {
+            file_name: self.file_name.to_string(),
+            line: lines + 1,
+            column: last_line.chars().count(),
+            message: message.to_string(),
+            last_line: last_line.to_string(),
+        }
     }
 
-    pub fn read_section(&mut self) -> &'a str {
-        let start = self.remaining.find(|c: char| !c.is_whitespace()).unwrap();
+    /// Parse a word as a number, or fail with what it was for.
+    pub fn parse<T: std::str::FromStr>(&self, word: &str, what: &str) -> ParseResult<T> {
+        word.trim()
+            .parse()
+            .map_err(|_| self.error(&format!("Invalid {}: '{}'", what, word)))
+    }
+
+    pub fn read_section(&mut self) -> ParseResult<&'a str> {
+        let start = self
+            .remaining
+            .find(|c: char| !c.is_whitespace())
+            .ok_or_else(|| self.error("Expected a section, got the end of the file"))?;
         let end = self.remaining[start..]
             .find(|c: char| c.is_whitespace())
-            .unwrap();
+            .unwrap_or(self.remaining.len() - start);
 
-        if &self.remaining[start..start + 1] != "#" {
-            self.panic_on_line(&format!(
-                "Expected '#', got '{}'",
-                &self.remaining[start..start + 1]
-            ))
+        if !self.remaining[start..].starts_with('#') {
+            let got = self.remaining[start..].chars().next().unwrap_or_default();
+            return Err(self.error(&format!("Expected '#', got '{}'", got)));
         }
 
         let section = &self.remaining[start + 1..start + end];
         self.remaining = &self.remaining[start + end..];
-        self.skip_one_newline();
-        section
+        self.skip_one_newline()?;
+        Ok(section)
     }
 
-    pub fn read_word(&mut self) -> &'a str {
+    pub fn read_word(&mut self) -> ParseResult<&'a str> {
         let start = self
             .remaining
             .find(|c: char| !c.is_ascii_whitespace())
-            .unwrap();
+            .ok_or_else(|| self.error("Expected a word, got the end of the file"))?;
         let end = self.remaining[start..]
             .find(|c: char| c.is_ascii_whitespace())
-            .unwrap();
+            .unwrap_or(self.remaining.len() - start);
 
         let section = &self.remaining[start..start + end];
         self.remaining = &self.remaining[start + end..];
-        section
+        Ok(section)
     }
 
-    pub fn skip_one_newline(&mut self) {
+    pub fn skip_one_newline(&mut self) -> ParseResult<()> {
         if self.remaining.is_empty() {
             // Nothing to skip
         } else if self.remaining.starts_with('\r') {
@@ -78,15 +119,18 @@ impl<'a> FileParser<'a> {
         } else if self.remaining.starts_with('\n') {
             self.remaining = &self.remaining[1..];
         } else {
-            self.panic_on_line("No newline found to skip");
+            return Err(self.error("No newline found to skip"));
         }
+        Ok(())
     }
 
-    pub fn skip_one_space(&mut self) {
-        if self.remaining.chars().next() != Some(' ') {
-            self.panic_on_line(&format!("Expected ' ', got '{}'", &self.remaining[..1]))
+    pub fn skip_one_space(&mut self) -> ParseResult<()> {
+        if !self.remaining.starts_with(' ') {
+            let got = self.remaining.chars().next().unwrap_or_default();
+            return Err(self.error(&format!("Expected ' ', got '{}'", got)));
         }
         self.remaining = &self.remaining[1..];
+        Ok(())
     }
 
     pub fn skip_all_space(&mut self) {
@@ -97,26 +141,66 @@ impl<'a> FileParser<'a> {
         self.remaining = &self.remaining[start..];
     }
 
-    pub fn read_until_newline(&mut self) -> &'a str {
-        let end = self.remaining.find('\n').unwrap();
+    pub fn read_until_newline(&mut self) -> ParseResult<&'a str> {
+        let end = self
+            .remaining
+            .find('\n')
+            .ok_or_else(|| self.error("Expected a newline, got the end of the file"))?;
 
         let section = &self.remaining[..end];
         self.remaining = &self.remaining[end..];
-        self.skip_one_newline();
+        self.skip_one_newline()?;
 
-        if section.ends_with('\r') {
-            &section[..section.len() - 1]
-        } else {
-            section
+        Ok(section.strip_suffix('\r').unwrap_or(section))
+    }
+
+    /// The next character that isn't whitespace, without reading it.
+    pub fn peek_char(&self) -> Option<char> {
+        self.remaining.trim_start().chars().next()
+    }
+
+    /// Read a string up to a '~' after any whitespace, as ROM reads them:
+    /// the rest of its line may be blank, or hold more.
+    pub fn read_string(&mut self) -> ParseResult<&'a str> {
+        self.skip_all_space();
+        let end = self
+            .remaining
+            .find('~')
+            .ok_or_else(|| self.error("Expected a '~', got the end of the file"))?;
+
+        let section = &self.remaining[..end];
+        self.remaining = self.remaining[end + 1..].trim_start_matches([' ', '\t']);
+        if self.remaining.starts_with(['\r', '\n']) {
+            self.skip_one_newline()?;
         }
+        Ok(section)
     }
 
-    pub fn read_until_tilde(&mut self) -> &'a str {
-        let end = self.remaining.find('~').unwrap();
+    /// Read a word, or several words between quotes like 'red wine'.
+    pub fn read_quoted_word(&mut self) -> ParseResult<&'a str> {
+        self.skip_all_space();
+        let quote = match self.remaining.chars().next() {
+            Some(quote @ ('\'' | '"')) => quote,
+            _ => return self.read_word(),
+        };
+
+        let end = self.remaining[1..]
+            .find(quote)
+            .ok_or_else(|| self.error(&format!("Expected a closing {}", quote)))?;
+        let section = &self.remaining[1..end + 1];
+        self.remaining = &self.remaining[end + 2..];
+        Ok(section)
+    }
+
+    pub fn read_until_tilde(&mut self) -> ParseResult<&'a str> {
+        let end = self
+            .remaining
+            .find('~')
+            .ok_or_else(|| self.error("Expected a '~', got the end of the file"))?;
 
         let section = &self.remaining[..end];
         self.remaining = &self.remaining[end + 1..];
-        self.skip_one_newline();
-        section
+        self.skip_one_newline()?;
+        Ok(section)
     }
 }
@@ -2,6 +2,9 @@
 //!
 //! This is used to emulate reading files on WASM in a browser, while using the
 //! real filesystem otherwise.
+//!
+//! Writing (and appending, listing or removing files) is only supported on the real
+//! filesystem; other implementations simply refuse to persist anything.
 
 use std::{borrow::Cow, fs::File, path::Path};
 
@@ -15,8 +18,40 @@ pub trait Files {
         let string = String::from_utf8_lossy(&bytes);
         Ok(fix_newlines(&string).into_owned())
     }
+
+    /// Replace a file's contents, creating it (and its directory) if needed
+    fn write_file_raw(&self, path: &str, _contents: &[u8]) -> Result<(), std::io::Error> {
+        Err(std::io::Error::other(format!(
+            "Cannot write to {}: writing files is not supported here",
+            path
+        )))
+    }
+
+    /// Add to the end of a file, creating it (and its directory) if needed
+    fn append_file_raw(&self, path: &str, _contents: &[u8]) -> Result<(), std::io::Error> {
+        Err(std::io::Error::other(format!(
+            "Cannot append to {}: writing files is not supported here",
+            path
+        )))
+    }
+
+    /// Get the names of the files in a directory
+    fn list_directory(&self, path: &str) -> Result<Vec<String>, std::io::Error> {
+        Err(std::io::Error::other(format!(
+            "Cannot list {}: listing files is not supported here",
+            path
+        )))
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), std::io::Error> {
+        Err(std::io::Error::other(format!(
+            "Cannot remove {}: removing files is not supported here",
+            path
+        )))
+    }
 }
 
+#[cfg_attr(not(feature = "net"), allow(dead_code))]
 pub(crate) struct RealFiles;
 
 impl Files for RealFiles {
@@ -29,6 +64,50 @@ impl Files for RealFiles {
 
         Ok(bytes)
     }
+
+    fn write_file_raw(&self, path: &str, contents: &[u8]) -> Result<(), std::io::Error> {
+        let path = Path::new(path);
+
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        // Write to a temporary file first so that a crash never leaves a
+        // half-written file behind.
+        let temporary_path = path.with_extension("tmp");
+        std::fs::write(&temporary_path, contents)?;
+        std::fs::rename(&temporary_path, path)
+    }
+
+    fn append_file_raw(&self, path: &str, contents: &[u8]) -> Result<(), std::io::Error> {
+        use std::io::Write;
+
+        let path = Path::new(path);
+
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        file.write_all(contents)
+    }
+
+    fn list_directory(&self, path: &str) -> Result<Vec<String>, std::io::Error> {
+        let mut names = Vec::new();
+
+        for entry in std::fs::read_dir(Path::new(path))? {
+            names.push(entry?.file_name().to_string_lossy().into_owned());
+        }
+
+        Ok(names)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), std::io::Error> {
+        std::fs::remove_file(Path::new(path))
+    }
 }
 
 fn valid_newlines(text: &str) -> bool {
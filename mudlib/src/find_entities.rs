@@ -104,17 +104,24 @@ where
     type Item = MatchCandidate<'q, ()>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next_match_candidate().filter(|item| {
-            let entity = match item {
+        // Skip candidates without the keyword, rather than stopping at them
+        while let Some(item) = self.inner.next_match_candidate() {
+            let entity = match &item {
                 GoodMatch { entity, .. } => entity,
                 BadMatch { entity, .. } => entity,
             };
-            entity
+            let matches = entity
                 .component_info()
                 .keyword()
                 .split_whitespace()
-                .any(|word| word.eq_ignore_ascii_case(self.keyword))
-        })
+                .any(|word| word.eq_ignore_ascii_case(self.keyword));
+
+            if matches {
+                return Some(item);
+            }
+        }
+
+        None
     }
 }
 
@@ -160,6 +167,7 @@ where
     }
 }
 
+#[allow(dead_code)]
 pub(crate) struct Prefer<'q, I: 'q, P> {
     inner: I,
     predicate: P,
@@ -179,7 +187,7 @@ where
                 GoodMatch {
                     entity, preferred, ..
                 } => {
-                    let new_preferred = (self.predicate)(&entity);
+                    let new_preferred = (self.predicate)(entity);
                     *preferred = Some(new_preferred);
                 }
                 BadMatch { .. } => (),
@@ -211,7 +219,7 @@ where
                     component,
                     ..
                 } => {
-                    let new_preferred = (self.predicate)(&entity, component);
+                    let new_preferred = (self.predicate)(entity, component);
                     *preferred = Some(new_preferred);
                 }
                 BadMatch { .. } => (),
@@ -336,11 +344,12 @@ where
 pub(crate) trait EntityIterator<'e, C: 'e>: Sized {
     fn next_match_candidate(&mut self) -> Option<MatchCandidate<'e, C>>;
 
+    #[allow(dead_code)]
     fn prefer<'p, P: Fn(&EntityInfo<'p>) -> bool>(self, prefer: P) -> Prefer<'p, Self, P> {
         Prefer {
             inner: self,
             predicate: prefer,
-            shadow: PhantomData::default(),
+            shadow: PhantomData,
         }
     }
 
@@ -351,7 +360,7 @@ pub(crate) trait EntityIterator<'e, C: 'e>: Sized {
         PreferComponent {
             inner: self,
             predicate: prefer,
-            shadow: PhantomData::default(),
+            shadow: PhantomData,
         }
     }
 
@@ -362,16 +371,16 @@ pub(crate) trait EntityIterator<'e, C: 'e>: Sized {
         WithComponentOrError {
             inner: self,
             error,
-            shadow1: PhantomData::default(),
-            shadow2: PhantomData::default(),
+            shadow1: PhantomData,
+            shadow2: PhantomData,
         }
     }
 
     fn with_component<NewComponent>(self) -> WithComponent<'e, Self, C, NewComponent> {
         WithComponent {
             inner: self,
-            shadow1: PhantomData::default(),
-            shadow2: PhantomData::default(),
+            shadow1: PhantomData,
+            shadow2: PhantomData,
         }
     }
 
@@ -391,7 +400,7 @@ pub(crate) trait EntityIterator<'e, C: 'e>: Sized {
             inner: self,
             predicate: filter,
             error,
-            shadow: PhantomData::default(),
+            shadow: PhantomData,
         }
     }
 
@@ -0,0 +1,12 @@
+//! Entry points into the area loader for the fuzz targets in `fuzz/`. Not
+//! meant for anything else.
+
+use crate::{load::load_area, rng::GameRng};
+
+/// Parse the contents of an area file, as when the game starts. Returns the
+/// error for broken areas; it should never panic, whatever the contents.
+pub fn parse_area(contents: &str) -> Result<(), String> {
+    load_area(contents, "fuzzed.are", &mut GameRng::new(Some(0)))
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,149 @@
+//! Mobiles hunting down players they remember.
+//!
+//! A mobprog can start a hunt with `mob hunt <player>`, which also remembers
+//! the player for `$q`. Every mobile pulse after that, the hunter takes one
+//! step along the shortest way to its prey, found with
+//! `crate::pathfinding::find_path()`. Once it finds them, it stops hunting
+//! but still remembers them, so that its mobprogs can deal with them.
+//!
+//! Hunters give up after a few minutes, when their prey dies or leaves the
+//! realm, and with `mob forget`.
+
+use crate::{agent::EntityAgent, components::Mobile, echo, entity::EntityId, WorldState};
+
+/// Mobile pulses a hunt lasts, about five minutes
+const HUNT_PULSES: u32 = 75;
+
+pub(crate) fn update_hunting(world_state: &mut WorldState) {
+    let mut hunters = Vec::new();
+
+    for (entity, mobile) in world_state.entity_world.query::<&Mobile>() {
+        if mobile.hunting > 0 {
+            hunters.push(entity.entity_id());
+        }
+    }
+
+    for hunter_id in hunters {
+        let mut agent = EntityAgent::new(world_state, hunter_id);
+        agent.hunt();
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Stop hunting, and also forget who was hunted if `forget` is set.
+    pub(crate) fn stop_hunting(&mut self, forget: bool) {
+        let mut myself = self.entity_world.entity_info_mut(self.entity_id);
+        if let Some(mobile) = &mut myself.components().mobile {
+            mobile.hunting = 0;
+            if forget {
+                mobile.remember = None;
+            }
+        }
+    }
+
+    /// Take a step towards the prey of the current entity.
+    fn hunt(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let prey = match &myself.components().mobile {
+            Some(mobile) => mobile.remember.clone(),
+            None => return,
+        };
+        let prey_id = prey.and_then(|prey| {
+            self.entity_world
+                .all_players()
+                .find(|player| {
+                    player
+                        .player_name()
+                        .map(|name| name.eq_ignore_ascii_case(&prey))
+                        .unwrap_or(false)
+                })
+                .map(|player| player.entity_id())
+        });
+
+        let prey_id = match prey_id {
+            Some(prey_id) => prey_id,
+            None => return self.stop_hunting(true),
+        };
+
+        let mut myself = self.entity_world.entity_info_mut(self.entity_id);
+        if let Some(mobile) = &mut myself.components().mobile {
+            mobile.hunting -= 1;
+            if mobile.hunting == 0 {
+                mobile.remember = None;
+                return;
+            }
+        }
+
+        let prey_room_id = self.entity_world.room_of(prey_id);
+        if self.entity_world.room_of(self.entity_id) == prey_room_id {
+            let myself = self.entity_world.entity_info(self.entity_id);
+            let prey = self.entity_world.entity_info(prey_id);
+            let mut act = self.players.act_with(&myself, &prey);
+            echo!(act.target(), "$^$n has found you!\r\n");
+            echo!(act.others(), "$^$n glares at $N.\r\n");
+            return self.stop_hunting(false);
+        }
+
+        match self.first_step_to(prey_room_id) {
+            Some(exit) => {
+                self.do_move(&exit);
+            }
+            // The prey is somewhere the hunter can't go
+            None => self.stop_hunting(true),
+        }
+    }
+
+    pub fn do_mob_hunt(&mut self, target: &str) {
+        let mut myself = self.entity_world.entity_info_mut(self.entity_id);
+
+        let hunting = if let Some(mobile) = &mut myself.components().mobile {
+            mobile.remember = Some(target.to_string());
+            mobile.hunting = HUNT_PULSES;
+            true
+        } else {
+            false
+        };
+
+        if hunting {
+            echo!(self.info(), "Hunting {}.\r\n", target);
+        } else {
+            echo!(
+                self.info(),
+                "But you are not a mobile! You can't hunt anyone.\r\n"
+            );
+        }
+    }
+
+    pub fn do_mob_forget(&mut self) {
+        self.stop_hunting(true);
+        echo!(self.info(), "Forgotten.\r\n");
+    }
+
+    /// Make mobiles hunting a player who died give up.
+    pub(crate) fn forget_prey(&mut self, player: &str) {
+        let hunters: Vec<EntityId> = self
+            .entity_world
+            .all_entities()
+            .filter(|entity| {
+                entity
+                    .components()
+                    .mobile
+                    .as_ref()
+                    .map(|mobile| {
+                        mobile.hunting > 0
+                            && mobile
+                                .remember
+                                .as_deref()
+                                .map(|remember| remember.eq_ignore_ascii_case(player))
+                                .unwrap_or(false)
+                    })
+                    .unwrap_or(false)
+            })
+            .map(|entity| entity.entity_id())
+            .collect();
+
+        for hunter_id in hunters {
+            self.switch_agent(hunter_id).stop_hunting(true);
+        }
+    }
+}
@@ -4,327 +4,689 @@
 //! area files, this module converts them into Entity objects and inserts them
 //! into an EntityWorld defined in `crate::entity`.
 
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use rayon::prelude::*;
 
 use crate::{
-    components::{Components, Door, EntityType, GeneralData, InternComponent, MobProg, MyStringInterner},
+    components::{
+        Components, Door, EntityType, GeneralData, InternComponent, Light, MobProg, MovedStrings,
+        MyStringInterner, Vitals,
+    },
+    config::Config,
+    durability::{wears_down, NEW_CONDITION},
     entity::{EntityId, EntityWorld, PermanentEntityId},
-    state::Area,
-    world::{Gender, MobProgTrigger, Mobile, Object, ObjectFlags, ResetCommand, Vnum, World},
+    lazy::{link_waiting_exits, WaitingAreas},
+    parallel::on_threads,
+    templates::TemplateRegistry,
+    world::{
+        Gender, MobProgTrigger, Mobile, Object, ObjectFlags, ResetCommand, Room, TimeOfDay, Vnum,
+        World,
+    },
 };
 
-pub(crate) struct VnumTemplates {
-    pub vnum_to_room_entity: Vec<Option<PermanentEntityId>>,
-    pub vnum_to_mobprog: Vec<Option<String>>,
-    pub object_components: Vec<Option<(Components, Vec<Components>)>>,
-    pub mobile_components: Vec<Option<(Components, Vec<Components>)>>,
+/// The rooms of an area, with their exits and extra descriptions, and the
+/// templates of its objects and mobiles. They are made for each area on its
+/// own, with strings in an interner of their own, before they are all put in
+/// the entity world.
+struct AreaTemplates {
+    interner: MyStringInterner,
+    rooms: Vec<RoomTemplate>,
+    objects: Vec<(Vnum, (Components, Vec<Components>))>,
+    mobiles: Vec<(Vnum, (Components, Vec<Components>))>,
+}
+
+struct RoomTemplate {
+    vnum: Vnum,
+    components: Components,
+    /// Exits, with the vnum of the room each leads to
+    exits: Vec<(Components, usize)>,
+    extra_descriptions: Vec<Components>,
 }
 
+/// What an area has of a world.
+#[derive(Default)]
+struct AreaContents<'w> {
+    rooms: Vec<&'w Room>,
+    objects: Vec<&'w Object>,
+    mobiles: Vec<&'w Mobile>,
+}
+
+/// Create entities for everything in a world, and run the resets of its
+/// areas, except those for another time of day. The rooms and resets of
+/// areas that wait to be imported are left out.
+///
+/// The templates of each area are made on several threads first; putting
+/// them in the entity world, linking exits and running resets is then done
+/// on this one.
 pub(crate) fn import_from_world(
     entity_world: &mut EntityWorld,
     world: &World,
-) -> (VnumTemplates, Vec<Area>) {
+    time_of_day: TimeOfDay,
+    config: &Config,
+) {
+    let waiting_areas = WaitingAreas::new(world, config);
+
+    // Vnums that no area uses are left empty in the world, and left out here
+    let mut areas: BTreeMap<&str, AreaContents> = BTreeMap::new();
+    for room in world
+        .rooms
+        .iter()
+        .filter(|room| room.vnum.0 != 0 && !waiting_areas.contains(&room.area))
+    {
+        areas.entry(&room.area).or_default().rooms.push(room);
+    }
+    for object in world.objects.iter().filter(|object| object.vnum.0 != 0) {
+        areas.entry(&object.area).or_default().objects.push(object);
+    }
+    for mobile in world.mobiles.iter().filter(|mobile| mobile.vnum.0 != 0) {
+        areas.entry(&mobile.area).or_default().mobiles.push(mobile);
+    }
+
+    let area_templates: Vec<AreaTemplates> = on_threads(|| {
+        areas
+            .into_par_iter()
+            .map(|(_area, contents)| import_area_templates(&contents, world))
+            .collect()
+    });
+
+    entity_world.templates = TemplateRegistry {
+        vnum_to_room_entity: vec![None; world.rooms.len()],
+        vnum_to_mobprog: vec![None; world.mobprogs.len()],
+        object_components: vec![None; world.objects.len()],
+        mobile_components: vec![None; world.mobiles.len()],
+        waiting_areas,
+    };
+
     let mut room_vnum_to_id = HashMap::new();
-    let mut exit_leads_to = HashMap::new();
-
-    for room in &world.rooms {
-        let room_id = {
-            let keyword = &room.name;
-            let short_description = &room.name;
-
-            let title = &room.name;
-            let external = format!("It's a room called '{}'.", room.name);
-            let internal = &room.description;
-            let lateral = format!("A room called '{}' is here.", room.name);
-
-            let room_components = Components {
-                act_info: entity_world.interner.act_info(
-                    keyword,
-                    short_description,
-                    Gender::Neutral,
-                ),
-                descriptions: entity_world
-                    .interner
-                    .descriptions(title, internal, &external, &lateral),
-                general: GeneralData {
-                    vnum: room.vnum,
-                    area: room.area.to_string(),
-                    sector: Some(room.sector.to_string()),
-                    entity_type: EntityType::Room,
-                    equipped: None,
-                    command_queue: Vec::new(),
-                    following: None,
-                },
-                mobile: None,
-                object: None,
-                door: None,
-                mobprog: None,
-                silver: None,
-            };
-
-            entity_world.insert_entity(entity_world.world_entity_id(), room_components)
+    let mut exit_leads_to = Vec::new();
+
+    for templates in area_templates {
+        insert_area_templates(
+            entity_world,
+            templates,
+            &mut room_vnum_to_id,
+            &mut exit_leads_to,
+        );
+    }
+
+    for (exit_id, leads_to) in exit_leads_to {
+        match room_vnum_to_id.get(&leads_to) {
+            Some(to_room_id) => entity_world.set_leads_to(exit_id, *to_room_id),
+            None => {
+                let exit = entity_world.entity_info(exit_id).permanent_entity_id();
+                entity_world
+                    .templates
+                    .waiting_areas
+                    .remember_exit(world, exit, leads_to);
+            }
+        }
+    }
+
+    place_landmarks(entity_world, config);
+
+    for mobprog in &world.mobprogs {
+        entity_world.templates.vnum_to_mobprog[mobprog.vnum.0] = Some(mobprog.code.clone());
+    }
+
+    for (area_data, area_resets) in &world.areas {
+        if entity_world
+            .templates
+            .waiting_areas
+            .contains(&area_data.short_name)
+        {
+            continue;
+        }
+
+        run_resets(entity_world, area_resets, |only_at| {
+            only_at.is_none() || only_at == Some(time_of_day)
+        });
+    }
+}
+
+/// Put each landmark of the configuration, and those of the places the game
+/// sends characters to, like where they start, in its room. Landmarks whose
+/// room doesn't exist are put in the room with the lowest vnum instead, so
+/// that the game still runs with areas that don't have them.
+pub(crate) fn place_landmarks(entity_world: &mut EntityWorld, config: &Config) {
+    let room_id = |entity_world: &EntityWorld, room: &PermanentEntityId| {
+        entity_world
+            .old_entity(room)
+            .map(|room| (room.components().general.vnum.0, room.entity_id()))
+    };
+    let fallback = entity_world
+        .templates
+        .vnum_to_room_entity
+        .iter()
+        .flatten()
+        .find_map(|room| room_id(entity_world, room));
+
+    let mut landmarks: Vec<(String, Option<usize>)> = config
+        .landmarks()
+        .into_iter()
+        .map(|(landmark, vnum)| (landmark, Some(vnum)))
+        .collect();
+    for (_place, place_landmark) in config.world.places() {
+        if !landmarks
+            .iter()
+            .any(|(landmark, _vnum)| landmark == place_landmark)
+        {
+            landmarks.push((place_landmark.to_string(), None));
+        }
+    }
+
+    for (landmark, vnum) in landmarks {
+        let room = vnum.and_then(|vnum| {
+            entity_world
+                .templates
+                .room(vnum)
+                .and_then(|room| room_id(entity_world, room))
+        });
+
+        let missing = match vnum {
+            Some(vnum) => format!("Room {} of landmark {} not found", vnum, landmark),
+            None => format!("Landmark {} has no room in the configuration", landmark),
         };
+        match (room, fallback) {
+            (Some((_vnum, room_id)), _) => entity_world.add_landmark(&landmark, room_id),
+            (None, Some((fallback_vnum, fallback_id))) => {
+                println!("{}; using room {} instead.", missing, fallback_vnum);
+                entity_world.add_landmark(&landmark, fallback_id);
+            }
+            (None, None) => println!("{}, and there are no rooms.", missing),
+        }
+    }
 
-        for exit in &room.exits {
-            let mut keyword = exit.name.to_string();
-            let short_description = format!("the {} exit", exit.name);
-
-            let title = format!("Inside an {} exit.", exit.name);
-            let external = exit
-                .description
-                .as_deref()
-                .unwrap_or("You don't see anything special in that direction.");
-            let internal = format!(
-                "You are inside an {} exit. That normally shouldn't be possible.",
-                exit.name
-            );
-            let lateral = format!("An exit leading {} is here.", exit.name);
-
-            let door = if exit.has_door {
-                keyword = String::from("door ") + &keyword;
-                Some(Door {
-                    closed: exit.is_closed,
-                    locked: exit.is_locked,
-                    key: exit.key,
-                })
-            } else {
-                None
-            };
+    for (place, landmark) in config.world.places() {
+        if let Some(room_id) = entity_world.landmark(landmark) {
+            entity_world.set_place(place, room_id);
+        }
+    }
+}
+
+/// Create entities for the rooms of one area of a world, with their exits
+/// linked to rooms in it or in other areas, and replace the templates of its
+/// objects, mobiles and mobprogs. Exits of areas that were imported before
+/// and waited for this one are linked too. Its resets are left to the
+/// caller. Returns the new rooms, by vnum.
+pub(crate) fn import_area(
+    entity_world: &mut EntityWorld,
+    world: &World,
+    area: &str,
+) -> HashMap<usize, EntityId> {
+    let contents = AreaContents {
+        rooms: world
+            .rooms
+            .iter()
+            .filter(|room| room.vnum.0 != 0 && room.area == area)
+            .collect(),
+        objects: world
+            .objects
+            .iter()
+            .filter(|object| object.vnum.0 != 0 && object.area == area)
+            .collect(),
+        mobiles: world
+            .mobiles
+            .iter()
+            .filter(|mobile| mobile.vnum.0 != 0 && mobile.area == area)
+            .collect(),
+    };
+    let templates = import_area_templates(&contents, world);
+
+    // The area may have more vnums than before
+    let vnum_templates = &mut entity_world.templates;
+    vnum_templates.vnum_to_room_entity.resize(
+        world
+            .rooms
+            .len()
+            .max(vnum_templates.vnum_to_room_entity.len()),
+        None,
+    );
+    vnum_templates.vnum_to_mobprog.resize(
+        world
+            .mobprogs
+            .len()
+            .max(vnum_templates.vnum_to_mobprog.len()),
+        None,
+    );
+    vnum_templates.object_components.resize(
+        world
+            .objects
+            .len()
+            .max(vnum_templates.object_components.len()),
+        None,
+    );
+    vnum_templates.mobile_components.resize(
+        world
+            .mobiles
+            .len()
+            .max(vnum_templates.mobile_components.len()),
+        None,
+    );
+
+    vnum_templates.waiting_areas.remove(area);
+    let mut room_vnum_to_id = HashMap::new();
+    let mut exit_leads_to = Vec::new();
+    insert_area_templates(
+        entity_world,
+        templates,
+        &mut room_vnum_to_id,
+        &mut exit_leads_to,
+    );
 
-            if let Some(extra_keywords) = &exit.extra_keywords {
-                keyword = extra_keywords.clone() + " " + &keyword;
+    for (exit_id, leads_to) in exit_leads_to {
+        let to_room_id = entity_world
+            .templates
+            .room(leads_to)
+            .and_then(|room| entity_world.old_entity(room))
+            .map(|room| room.entity_id());
+        match to_room_id {
+            Some(to_room_id) => entity_world.set_leads_to(exit_id, to_room_id),
+            None => {
+                let exit = entity_world.entity_info(exit_id).permanent_entity_id();
+                entity_world
+                    .templates
+                    .waiting_areas
+                    .remember_exit(world, exit, leads_to);
             }
+        }
+    }
+    link_waiting_exits(entity_world);
+
+    for mobprog in world.mobprogs.iter().filter(|mobprog| mobprog.area == area) {
+        entity_world.templates.vnum_to_mobprog[mobprog.vnum.0] = Some(mobprog.code.clone());
+    }
+
+    room_vnum_to_id
+}
 
-            let exit_components = Components {
-                act_info: entity_world.interner.act_info(
-                    &keyword,
-                    &short_description,
-                    Gender::Neutral,
-                ),
-                descriptions: entity_world
-                    .interner
-                    .descriptions(&title, &internal, external, &lateral),
-                general: GeneralData {
-                    vnum: Vnum(0),
-                    area: room.area.to_string(),
-                    sector: None,
-                    entity_type: EntityType::Exit,
-                    equipped: None,
-                    command_queue: Vec::new(),
-                    following: None,
-                },
-                mobile: None,
-                object: None,
-                door,
-                mobprog: None,
-                silver: None,
-            };
-            let exit_id = entity_world.insert_entity(room_id, exit_components);
-
-            exit_leads_to.insert(exit_id, exit.vnum.0);
+/// Put the templates of an area in the entity world: its rooms, with exits
+/// to be linked once all rooms are in, and the templates of its objects and
+/// mobiles.
+fn insert_area_templates(
+    entity_world: &mut EntityWorld,
+    mut templates: AreaTemplates,
+    room_vnum_to_id: &mut HashMap<usize, EntityId>,
+    exit_leads_to: &mut Vec<(EntityId, usize)>,
+) {
+    let strings = MovedStrings::intern(&mut entity_world.interner, &templates.interner);
+
+    for mut room in templates.rooms {
+        strings.move_components(&mut room.components);
+        let room_id = entity_world.insert_entity(entity_world.world_entity_id(), room.components);
+
+        for (mut exit, leads_to) in room.exits {
+            strings.move_components(&mut exit);
+            let exit_id = entity_world.insert_entity(room_id, exit);
+            exit_leads_to.push((exit_id, leads_to));
         }
 
-        for extra_description in &room.extra_descriptions {
-            let keyword = &extra_description.keyword;
-            let short_description =
-                format!("extra description called '{}'", extra_description.keyword);
-
-            let title = "Inside an extra description.";
-            let external = &extra_description.description;
-            let internal =
-                "You are inside an extra description. That normally shouldn't be possible.";
-            let lateral = format!(
-                "An extra description called '{}' is here.",
-                extra_description.keyword
-            );
-            let extra_description_components = Components {
-                act_info: entity_world.interner.act_info(
-                    keyword,
-                    &short_description,
-                    Gender::Neutral,
-                ),
-                descriptions: entity_world
-                    .interner
-                    .descriptions(title, internal, external, &lateral),
-                general: GeneralData {
-                    vnum: Vnum(0),
-                    area: room.area.to_string(),
-                    sector: None,
-                    entity_type: EntityType::ExtraDescription,
-                    equipped: None,
-                    command_queue: Vec::new(),
-                    following: None,
-                },
-                mobile: None,
-                object: None,
-                door: None,
-                mobprog: None,
-                silver: None,
-            };
-
-            entity_world.insert_entity(room_id, extra_description_components);
+        for mut extra_description in room.extra_descriptions {
+            strings.move_components(&mut extra_description);
+            entity_world.insert_entity(room_id, extra_description);
         }
 
         room_vnum_to_id.insert(room.vnum.0, room_id);
+        let permanent_id = entity_world.entity_info(room_id).permanent_entity_id();
+        entity_world.templates.vnum_to_room_entity[room.vnum.0] = Some(permanent_id);
     }
 
-    for (exit_id, leads_to) in exit_leads_to {
-        if let Some(to_room_id) = room_vnum_to_id.get(&leads_to) {
-            entity_world.set_leads_to(exit_id, *to_room_id);
+    let vnum_templates = &mut entity_world.templates;
+    let templates_by_vnum = [
+        (
+            &mut templates.objects,
+            &mut vnum_templates.object_components,
+        ),
+        (
+            &mut templates.mobiles,
+            &mut vnum_templates.mobile_components,
+        ),
+    ];
+    for (area_templates, components_by_vnum) in templates_by_vnum {
+        for (vnum, (mut components, mut contents)) in area_templates.drain(..) {
+            strings.move_components(&mut components);
+            for components in &mut contents {
+                strings.move_components(components);
+            }
+            components_by_vnum[vnum.0] = Some((components, contents));
         }
     }
+}
 
-    let landmarks = &[
-        ("gnomehill", 23611),
-        ("mekali", 3000),
-        ("dzagari", 27003),
-        ("mudschool", 7371),
-        ("void", 1),
-        ("limbo", 2),
-    ];
+/// Make the templates of an area, with its strings in an interner of its
+/// own.
+fn import_area_templates(contents: &AreaContents, world: &World) -> AreaTemplates {
+    let mut interner = MyStringInterner::new();
+
+    let rooms = contents
+        .rooms
+        .iter()
+        .map(|room| import_room_template(room, &mut interner))
+        .collect();
+    let objects = contents
+        .objects
+        .iter()
+        .map(|object| {
+            let components = import_object_components(object, &mut interner);
+            (object.vnum, components)
+        })
+        .collect();
+    let mobiles = contents
+        .mobiles
+        .iter()
+        .map(|mobile| {
+            let components = import_mobile_components(mobile, world, &mut interner);
+            (mobile.vnum, components)
+        })
+        .collect();
+
+    AreaTemplates {
+        interner,
+        rooms,
+        objects,
+        mobiles,
+    }
+}
 
-    for (landmark, vnum) in landmarks {
-        entity_world.add_landmark(
-            landmark,
-            *room_vnum_to_id
-                .get(&vnum)
-                .expect("GnomeHill landmark room not found."),
-        );
+/// Keys of a template that the game has no use for, to be shared by the
+/// entities spawned from it.
+fn shared_extras(extras: &[(String, String)]) -> Option<Arc<[(String, String)]>> {
+    if extras.is_empty() {
+        None
+    } else {
+        Some(extras.into())
     }
+}
 
-    let mut vnum_templates = VnumTemplates {
-        vnum_to_room_entity: Vec::with_capacity(world.rooms.len()),
-        vnum_to_mobprog: Vec::with_capacity(world.mobprogs.len()),
-        object_components: Vec::with_capacity(world.objects.len()),
-        mobile_components: Vec::with_capacity(world.mobiles.len()),
+fn import_room_template(room: &Room, interner: &mut MyStringInterner) -> RoomTemplate {
+    let keyword = &room.name;
+    let short_description = &room.name;
+
+    let title = &room.name;
+    let external = format!("It's a room called '{}'.", room.name);
+    let internal = &room.description;
+    let lateral = format!("A room called '{}' is here.", room.name);
+
+    let mut descriptions = interner.descriptions(title, internal, &external, &lateral);
+    interner.set_night_descriptions(&mut descriptions, room.night_description.as_deref(), None);
+
+    let components = Components {
+        act_info: interner.act_info(keyword, short_description, Gender::Neutral),
+        descriptions,
+        general: GeneralData {
+            vnum: room.vnum,
+            area: room.area.as_str().into(),
+            sector: Some(room.sector.as_str().into()),
+            entity_type: EntityType::Room,
+            archetype: None,
+            equipped: None,
+            command_queue: Vec::new(),
+            following: None,
+            only_at: None,
+            extras: shared_extras(&room.extras),
+        },
+        mobile: None,
+        object: None,
+        door: None,
+        mobprog: None,
+        wealth: None,
+        vitals: None,
+        decay: None,
+        attributes: None,
     };
 
-    vnum_templates
-        .vnum_to_room_entity
-        .resize(world.rooms.len(), None);
-    vnum_templates
-        .vnum_to_mobprog
-        .resize(world.rooms.len(), None);
-    vnum_templates
-        .object_components
-        .resize(world.rooms.len(), None);
-    vnum_templates
-        .mobile_components
-        .resize(world.rooms.len(), None);
-
-    for room in &world.rooms {
-        if room.vnum.0 != 0 {
-            let room_entity = entity_world.entity_info(room_vnum_to_id[&room.vnum.0]);
-            vnum_templates.vnum_to_room_entity[room.vnum.0] =
-                Some(room_entity.permanent_entity_id());
-        }
-    }
+    let mut exits = Vec::with_capacity(room.exits.len());
+    let mut extra_descriptions = Vec::with_capacity(room.extra_descriptions.len());
+
+    for exit in &room.exits {
+        let mut keyword = exit.name.to_string();
+        let short_description = format!("the {} exit", exit.name);
+
+        let title = format!("Inside an {} exit.", exit.name);
+        let external = exit
+            .description
+            .as_deref()
+            .unwrap_or("You don't see anything special in that direction.");
+        let internal = format!(
+            "You are inside an {} exit. That normally shouldn't be possible.",
+            exit.name
+        );
+        let lateral = format!("An exit leading {} is here.", exit.name);
 
-    for object in &world.objects {
-        if object.vnum.0 != 0 {
-            let components = import_object_components(object, &mut entity_world.interner);
-            vnum_templates.object_components[object.vnum.0] = Some(components);
+        let door = if exit.has_door {
+            keyword = String::from("door ") + &keyword;
+            Some(Door {
+                closed: exit.is_closed,
+                locked: exit.is_locked,
+                key: exit.key,
+            })
+        } else {
+            None
+        };
+
+        if let Some(extra_keywords) = &exit.extra_keywords {
+            keyword = extra_keywords.clone() + " " + &keyword;
         }
+
+        let exit_components = Components {
+            act_info: interner.act_info(&keyword, &short_description, Gender::Neutral),
+            descriptions: interner.descriptions(&title, &internal, external, &lateral),
+            general: GeneralData {
+                vnum: Vnum(0),
+                area: room.area.as_str().into(),
+                sector: None,
+                entity_type: EntityType::Exit,
+                archetype: None,
+                equipped: None,
+                command_queue: Vec::new(),
+                following: None,
+                only_at: None,
+                extras: None,
+            },
+            mobile: None,
+            object: None,
+            door,
+            mobprog: None,
+            wealth: None,
+            vitals: None,
+            decay: None,
+            attributes: None,
+        };
+        exits.push((exit_components, exit.vnum.0));
     }
 
-    for mobile in &world.mobiles {
-        if mobile.vnum.0 != 0 {
-            let components = import_mobile_components(mobile, world, &mut entity_world.interner);
-            vnum_templates.mobile_components[mobile.vnum.0] = Some(components);
-        }
+    for extra_description in &room.extra_descriptions {
+        let keyword = &extra_description.keyword;
+        let short_description = format!("extra description called '{}'", extra_description.keyword);
+
+        let title = "Inside an extra description.";
+        let external = &extra_description.description;
+        let internal = "You are inside an extra description. That normally shouldn't be possible.";
+        let lateral = format!(
+            "An extra description called '{}' is here.",
+            extra_description.keyword
+        );
+        let extra_description_components = Components {
+            act_info: interner.act_info(keyword, &short_description, Gender::Neutral),
+            descriptions: interner.descriptions(title, internal, external, &lateral),
+            general: GeneralData {
+                vnum: Vnum(0),
+                area: room.area.as_str().into(),
+                sector: None,
+                entity_type: EntityType::ExtraDescription,
+                archetype: None,
+                equipped: None,
+                command_queue: Vec::new(),
+                following: None,
+                only_at: None,
+                extras: None,
+            },
+            mobile: None,
+            object: None,
+            door: None,
+            mobprog: None,
+            wealth: None,
+            vitals: None,
+            decay: None,
+            attributes: None,
+        };
+
+        extra_descriptions.push(extra_description_components);
     }
 
-    for mobprog in &world.mobprogs {
-        vnum_templates.vnum_to_mobprog[mobprog.vnum.0] = Some(mobprog.code.clone());
+    RoomTemplate {
+        vnum: room.vnum,
+        components,
+        exits,
+        extra_descriptions,
     }
+}
+
+/// Run the resets of an area for which `should_run` is true, given the time of
+/// day they are limited to, if any. Entities spawned by resets that are
+/// limited to a time of day remember it, to be removed when it ends.
+///
+/// Resets keep to their limits: a mobile isn't spawned if there are as many
+/// of it in the game as its global limit, or in its room as its room limit,
+/// and an object likewise, or in its container as its container limit.
+/// Limits of 0 or less are no limit, as some area files have them.
+pub(crate) fn run_resets<F>(
+    entity_world: &mut EntityWorld,
+    area_resets: &[ResetCommand],
+    should_run: F,
+) where
+    F: Fn(Option<TimeOfDay>) -> bool,
+{
+    let room_id = |entity_world: &EntityWorld, vnum: Vnum| {
+        entity_world
+            .templates
+            .room(vnum.0)
+            .and_then(|room| entity_world.old_entity(room))
+            .map(|room| room.entity_id())
+    };
+    // Objects given to mobiles that weren't spawned are left out, and those
+    // above their limit or without a template
+    let can_spawn_object = |entity_world: &EntityWorld, vnum: Vnum, limit: i16| {
+        entity_world.templates.has_object(vnum.0)
+            && (limit <= 0
+                || entity_world.count_in_game(EntityType::Object, vnum.0) < limit as usize)
+    };
+
+    let mut last_mobile_id = None;
+
+    for reset_command in area_resets {
+        match reset_command {
+            ResetCommand::Mob {
+                m_num,
+                global_limit,
+                r_num,
+                room_limit,
+                only_at,
+            } => {
+                last_mobile_id = None;
+                if !should_run(*only_at) {
+                    continue;
+                }
+
+                // Resets of what doesn't exist are left out; see crate::validate
+                let room_entity_id = match room_id(entity_world, *r_num) {
+                    Some(room_entity_id) => room_entity_id,
+                    None => continue,
+                };
+                let in_game = entity_world.count_in_game(EntityType::Mobile, m_num.0);
+                let in_room = entity_world
+                    .entities_in_room(room_entity_id)
+                    .filter(|entity| {
+                        entity.is_mobile() && entity.components().general.vnum == *m_num
+                    })
+                    .count();
+                if (*global_limit > 0 && in_game >= usize::from(*global_limit))
+                    || (*room_limit > 0 && in_room >= usize::from(*room_limit))
+                {
+                    continue;
+                }
 
-    for (_area_data, area_resets) in &world.areas {
-        let mut last_mobile_id = None;
-
-        for reset_command in area_resets {
-            match reset_command {
-                ResetCommand::Mob {
-                    m_num,
-                    global_limit: _,
-                    r_num,
-                    room_limit: _,
-                } => {
-                    let room_entity_id = room_vnum_to_id[&r_num.0];
-                    let mobile_components = vnum_templates.mobile_components[m_num.0]
-                        .as_ref()
-                        .expect("Mobile with vnum does not exist");
-
-                    let mobile_entity_id =
-                        entity_world.insert_entity(room_entity_id, mobile_components.0.clone());
-                    last_mobile_id = Some(mobile_entity_id);
-
-                    for mobprog_components in &mobile_components.1 {
-                        entity_world.insert_entity(mobile_entity_id, mobprog_components.clone());
-                    }
+                last_mobile_id = entity_world.spawn_mobile(m_num.0, room_entity_id);
+                if let Some(mobile_id) = last_mobile_id {
+                    let mut mobile = entity_world.entity_info_mut(mobile_id);
+                    mobile.components().general.only_at = *only_at;
                 }
-                ResetCommand::Object {
-                    o_num,
-                    global_limit: _,
-                    r_num,
-                } => {
-                    let room_entity_id = room_vnum_to_id[&r_num.0];
-                    load_object(o_num.0, room_entity_id, &vnum_templates, entity_world);
+            }
+            ResetCommand::Object {
+                o_num,
+                global_limit,
+                r_num,
+                only_at,
+            } => {
+                if !should_run(*only_at) || !can_spawn_object(entity_world, *o_num, *global_limit) {
+                    continue;
                 }
-                ResetCommand::Door { .. } => {}
-                ResetCommand::Give {
-                    o_num,
-                    global_limit: _,
-                } => {
-                    let last_mobile_id = last_mobile_id.unwrap();
-                    load_object(o_num.0, last_mobile_id, &vnum_templates, entity_world);
+
+                let room_entity_id = match room_id(entity_world, *r_num) {
+                    Some(room_entity_id) => room_entity_id,
+                    None => continue,
+                };
+                if let Some(object_id) = entity_world.spawn_object(o_num.0, room_entity_id) {
+                    let mut object = entity_world.entity_info_mut(object_id);
+                    object.components().general.only_at = *only_at;
+                }
+            }
+            ResetCommand::Door { .. } => {}
+            ResetCommand::Give {
+                o_num,
+                global_limit,
+            } => {
+                if let Some(last_mobile_id) =
+                    last_mobile_id.filter(|_| can_spawn_object(entity_world, *o_num, *global_limit))
+                {
+                    entity_world.spawn_object(o_num.0, last_mobile_id);
                 }
-                ResetCommand::Equip {
-                    o_num,
-                    global_limit: _,
-                    location,
-                } => {
-                    let last_mobile_id = last_mobile_id.unwrap();
-
-                    let object_id =
-                        load_object(o_num.0, last_mobile_id, &vnum_templates, entity_world);
+            }
+            ResetCommand::Equip {
+                o_num,
+                global_limit,
+                location,
+            } => {
+                let last_mobile_id = last_mobile_id
+                    .filter(|_| can_spawn_object(entity_world, *o_num, *global_limit));
+                if let Some(object_id) = last_mobile_id
+                    .and_then(|mobile_id| entity_world.spawn_object(o_num.0, mobile_id))
+                {
                     let location = location.to_string();
                     let mut object_entity = entity_world.entity_info_mut(object_id);
                     object_entity.components().general.equipped = Some(location);
                 }
-                ResetCommand::Put {
-                    o_num,
-                    global_limit: _,
-                    c_num,
-                    container_limit: _,
-                } => {
-                    // FIXME: The iteration needs to be ordered to get the last object, which is not
-                    // possible to do with hashmaps; change this once entities use a Vec
-                    let mut container_id = None;
-                    for container in entity_world.all_entities() {
-                        if container.components().general.vnum == *c_num && container.is_object() {
-                            container_id = Some(container.entity_id());
-                            break;
-                        }
-                    }
-                    if let Some(container_id) = container_id {
-                        load_object(o_num.0, container_id, &vnum_templates, entity_world);
-                    }
+            }
+            ResetCommand::Put {
+                o_num,
+                global_limit,
+                c_num,
+                container_limit,
+            } => {
+                // Into the container loaded last, like the one just loaded by
+                // the reset before
+                let container = entity_world.all_entities().rev().find(|container| {
+                    container.components().general.vnum == *c_num && container.is_object()
+                });
+                let in_container = container.as_ref().map_or(0, |container| {
+                    container
+                        .objects()
+                        .filter(|object| object.components().general.vnum == *o_num)
+                        .count()
+                });
+                let container_id = container
+                    .map(|container| container.entity_id())
+                    .filter(|_| *container_limit <= 0 || in_container < *container_limit as usize)
+                    .filter(|_| can_spawn_object(entity_world, *o_num, *global_limit));
+                if let Some(container_id) = container_id {
+                    entity_world.spawn_object(o_num.0, container_id);
                 }
             }
         }
     }
-
-    let mut areas = Vec::with_capacity(world.areas.len());
-
-    for (area, _reset_commands) in &world.areas {
-        areas.push(Area {
-            name: area.name.clone(),
-            vnums: area.vnums,
-            credits: area.credits.clone(),
-        });
-    }
-
-    (vnum_templates, areas)
 }
 
 fn import_mobile_components(
@@ -351,8 +713,13 @@ fn import_mobile_components(
     );
     let lateral = &mobile.long_description;
 
-    let act_info = interner.act_info(keyword, &short_description, mobile.gender);
-    let descriptions = interner.descriptions(&title, &internal, external, lateral);
+    let act_info = interner.act_info(keyword, short_description, mobile.gender);
+    let mut descriptions = interner.descriptions(&title, &internal, external, lateral);
+    interner.set_night_descriptions(
+        &mut descriptions,
+        None,
+        mobile.night_long_description.as_deref(),
+    );
 
     let shop = world
         .shops
@@ -364,83 +731,125 @@ fn import_mobile_components(
         descriptions,
         general: GeneralData {
             vnum: mobile.vnum,
-            area: mobile.area.to_string(),
+            area: mobile.area.as_str().into(),
             sector: None,
             entity_type: EntityType::Mobile,
+            archetype: None,
             equipped: None,
             command_queue: Vec::new(),
             following: None,
+            only_at: None,
+            extras: shared_extras(&mobile.extras),
         },
         mobile: Some(crate::components::Mobile {
             wander: !mobile.sentinel,
-            shopkeeper: shop.cloned(),
+            stay_area: mobile.stay_area,
+            flying: mobile.flying,
+            scavenger: mobile.scavenger,
+            aggressive: mobile.aggressive,
+            shopkeeper: shop.cloned().map(Arc::new),
             remember: None,
+            hunting: 0,
+            walk_to: None,
+            postmaster: mobile.postmaster,
+            banker: mobile.banker,
+            repairer: mobile.repairer,
+            wealth: mobile.wealth.clone(),
+            pet_of: None,
+            encounter: false,
+            haggled: Vec::new(),
         }),
         object: None,
         door: None,
         mobprog: None,
-        silver: None,
+        wealth: None,
+        vitals: Some(Vitals::for_level(mobile.level)),
+        decay: None,
+        attributes: None,
     };
 
     for (mobprog_trigger, vnum) in &mobile.mobprog_triggers {
-        let mobprog = world
-            .mobprogs
-            .get(vnum.0)
-            .expect("Triggered mobprog should exist");
-        let keyword = "mobprog";
-        let trigger = match mobprog_trigger {
-            MobProgTrigger::Random { .. } => "on-chance",
-            MobProgTrigger::Speech { .. } => "on-speech",
-            MobProgTrigger::Greet { .. } => "on-greet",
-            MobProgTrigger::Entry { .. } => "on-entry",
-            MobProgTrigger::Act { .. } => "on-act",
-            MobProgTrigger::Exit { .. } => "on-exit",
-            MobProgTrigger::Bribe { .. } => "on-bribe",
-            MobProgTrigger::Give { .. } => "on-give",
-            MobProgTrigger::Kill { .. } => "on-kill",
-            MobProgTrigger::Death { .. } => "on-death",
-            MobProgTrigger::Hour { .. } => "on-hour",
-            MobProgTrigger::LoginRoom { .. } => "on-login",
+        // Triggers of mobprogs that don't exist are left out; see crate::validate
+        let mobprog = match world.mobprogs.get(vnum.0) {
+            Some(mobprog) if mobprog.vnum == *vnum => mobprog,
+            _ => continue,
         };
-        let short_description = format!("an {} mobprog titled '`S{}`^'", trigger, mobprog.title);
-
-        let title = "Inside a mobprog.";
-        let internal = "You are inside a mobprog. Instructions are floating all around the area.";
-        let external = format!(
-            "It's a mobprog. When triggered, it runs the following code:\r\n{}\r\n",
-            mobprog.code
-        );
-        let lateral = "A mobprog is installed here, affecting its surroundings.";
-
-        let act_info = interner.act_info(keyword, &short_description, Gender::Neutral);
-        let descriptions = interner.descriptions(title, internal, &external, lateral);
 
-        mobprogs.push(Components {
-            act_info,
-            descriptions,
-            general: GeneralData {
-                vnum: mobprog.vnum,
-                area: mobile.area.to_string(),
-                sector: None,
-                entity_type: EntityType::MobProg,
-                equipped: None,
-                command_queue: Vec::new(),
-                following: None,
-            },
-            mobile: None,
-            object: None,
-            door: None,
-            mobprog: Some(MobProg {
-                trigger: mobprog_trigger.clone(),
-                code: mobprog.code.clone(),
-            }),
-            silver: None,
-        });
+        mobprogs.push(import_mobprog_components(
+            mobprog,
+            mobprog_trigger,
+            &mobile.area,
+            interner,
+        ));
     }
 
     (mobile_components, mobprogs)
 }
 
+/// Create the components of a mobprog installed in a mobile of an area.
+pub(crate) fn import_mobprog_components(
+    mobprog: &crate::world::MobProg,
+    mobprog_trigger: &MobProgTrigger,
+    area: &str,
+    interner: &mut MyStringInterner,
+) -> Components {
+    let keyword = "mobprog";
+    let trigger = match mobprog_trigger {
+        MobProgTrigger::Random { .. } => "on-chance",
+        MobProgTrigger::Speech { .. } => "on-speech",
+        MobProgTrigger::Greet { .. } => "on-greet",
+        MobProgTrigger::Entry { .. } => "on-entry",
+        MobProgTrigger::Act { .. } => "on-act",
+        MobProgTrigger::Exit { .. } => "on-exit",
+        MobProgTrigger::Bribe { .. } => "on-bribe",
+        MobProgTrigger::Give { .. } => "on-give",
+        MobProgTrigger::Kill { .. } => "on-kill",
+        MobProgTrigger::Death { .. } => "on-death",
+        MobProgTrigger::Hour { .. } => "on-hour",
+        MobProgTrigger::LoginRoom => "on-login",
+    };
+    let short_description = format!("an {} mobprog titled '`S{}`^'", trigger, mobprog.title);
+
+    let title = "Inside a mobprog.";
+    let internal = "You are inside a mobprog. Instructions are floating all around the area.";
+    let external = format!(
+        "It's a mobprog. When triggered, it runs the following code:\r\n{}\r\n",
+        mobprog.code
+    );
+    let lateral = "A mobprog is installed here, affecting its surroundings.";
+
+    let act_info = interner.act_info(keyword, &short_description, Gender::Neutral);
+    let descriptions = interner.descriptions(title, internal, &external, lateral);
+
+    Components {
+        act_info,
+        descriptions,
+        general: GeneralData {
+            vnum: mobprog.vnum,
+            area: area.into(),
+            sector: None,
+            entity_type: EntityType::MobProg,
+            archetype: None,
+            equipped: None,
+            command_queue: Vec::new(),
+            following: None,
+            only_at: None,
+            extras: None,
+        },
+        mobile: None,
+        object: None,
+        door: None,
+        mobprog: Some(MobProg {
+            trigger: mobprog_trigger.clone(),
+            code: mobprog.code.as_str().into(),
+        }),
+        wealth: None,
+        vitals: None,
+        decay: None,
+        attributes: None,
+    }
+}
+
 fn import_object_components(
     object: &Object,
     interner: &mut MyStringInterner,
@@ -489,18 +898,24 @@ fn import_object_components(
             descriptions,
             general: GeneralData {
                 vnum: Vnum(0),
-                area: object.area.to_string(),
+                area: object.area.as_str().into(),
                 sector: None,
                 entity_type: EntityType::ExtraDescription,
+                archetype: None,
                 equipped: None,
                 command_queue: Vec::new(),
                 following: None,
+                only_at: None,
+                extras: None,
             },
             mobile: None,
             object: None,
             door: None,
             mobprog: None,
-            silver: None,
+            wealth: None,
+            vitals: None,
+            decay: None,
+            attributes: None,
         });
     }
 
@@ -521,7 +936,7 @@ fn import_object_components(
     };
 
     let act_info = interner.act_info(keyword, short_description, Gender::Neutral);
-    let descriptions = interner.descriptions(&title, &internal, external, lateral);
+    let descriptions = interner.descriptions(&title, internal, external, lateral);
 
     // If you squint hard enough at a lid, it might start to look like a door.
     let door = if let ObjectFlags::Container {
@@ -543,17 +958,40 @@ fn import_object_components(
         None
     };
 
+    // Lights that burn for ever have -1 hours, or 999 in some areas
+    let light = match object.flags {
+        ObjectFlags::Light { capacity, hours } => Some(Light {
+            lit: false,
+            hours: if hours < 0 || hours == 999 {
+                None
+            } else {
+                Some(hours.min(u16::MAX as i32) as u16)
+            },
+            capacity: capacity.clamp(0, u16::MAX as i32) as u16,
+        }),
+        _ => None,
+    };
+    let liquid = match &object.flags {
+        ObjectFlags::Liquid { liquid } => Some(liquid.clone()),
+        _ => None,
+    };
+    let drinkable = (object.item_type == "fountain" || object.item_type == "drink_con")
+        && liquid.as_deref() != Some("oil");
+
     let components = Components {
         act_info,
         descriptions,
         general: GeneralData {
             vnum: object.vnum,
-            area: object.area.to_string(),
+            area: object.area.as_str().into(),
             sector: None,
             entity_type: EntityType::Object,
+            archetype: None,
             equipped: None,
             command_queue: Vec::new(),
             following: None,
+            only_at: None,
+            extras: shared_extras(&object.extras),
         },
         mobile: None,
         object: Some(crate::components::Object {
@@ -565,30 +1003,43 @@ fn import_object_components(
             },
             container: object.item_type == "container",
             food: object.item_type == "food",
+            drink: drinkable,
+            board: note_board_name(object),
+            resold: false,
+            take: object.take,
+            wear: object.wear.clone(),
+            light,
+            liquid,
+            boat: object.item_type == "boat",
+            condition: wears_down(&object.item_type).then_some(NEW_CONDITION),
         }),
         door,
         mobprog: None,
-        silver: None,
+        wealth: None,
+        vitals: None,
+        decay: None,
+        attributes: None,
     };
 
     (components, extra_description_components)
 }
 
-fn load_object(
-    vnum: usize,
-    container: EntityId,
-    vnum_templates: &VnumTemplates,
-    entity_world: &mut EntityWorld,
-) -> EntityId {
-    let components = vnum_templates.object_components[vnum]
-        .as_ref()
-        .expect("Object with vnum does not exist");
-
-    let object_id = entity_world.insert_entity(container, components.0.clone());
+/// Guess which note board a board-like object (e.g. "a large bulletin board")
+/// should show.
+fn note_board_name(object: &Object) -> Option<String> {
+    let has_keyword = |keyword| object.name.split_whitespace().any(|word| word == keyword);
 
-    for extra_description_components in &components.1 {
-        entity_world.insert_entity(object_id, extra_description_components.clone());
+    if object.item_type != "board" && !has_keyword("bulletin") {
+        return None;
     }
 
-    object_id
+    let board = if has_keyword("immortal") {
+        "immortal"
+    } else if has_keyword("idea") || has_keyword("ideas") {
+        "ideas"
+    } else {
+        "general"
+    };
+
+    Some(board.to_string())
 }
@@ -0,0 +1,191 @@
+//! Importing areas only once someone goes in them, for large worlds that
+//! take long to import all at once.
+//!
+//! With `lazy_areas` set under `[world]` in the configuration, the game
+//! starts with the rooms of the areas that have landmarks, where players
+//! and anything sent to a landmark show up, and of the areas next to them.
+//! The rooms of the other areas wait, and their resets with them; exits
+//! into them lead nowhere for now. The first one to go through such an
+//! exit, or to a vnum of such an area with `goto`, `at`, `transfer` or a
+//! mobprog, imports it: its rooms are made, exits into it are linked, and
+//! its resets run. Templates of mobiles and objects are always made for all
+//! areas, since resets may use those of other areas.
+//!
+//! `reload area` of an area that waits imports it too.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    agent::EntityAgent,
+    clock::time_of_day,
+    config::Config,
+    entity::{EntityId, EntityWorld, PermanentEntityId},
+    import::{import_area, run_resets},
+    world::{TimeOfDay, World},
+};
+
+/// Areas whose rooms aren't in the entity world yet
+#[derive(Clone, Default)]
+pub(crate) struct WaitingAreas {
+    /// Short names of the areas
+    areas: BTreeSet<String>,
+    /// Exits of imported rooms that lead into them, and the vnums of the
+    /// rooms they lead to
+    exits: Vec<(PermanentEntityId, usize)>,
+}
+
+impl WaitingAreas {
+    /// The areas of a world that wait to be imported: all of them but those
+    /// with landmarks and those next to them, unless areas aren't imported
+    /// lazily.
+    pub fn new(world: &World, config: &Config) -> Self {
+        let mut waiting = WaitingAreas::default();
+        if !config.world.lazy_areas {
+            return waiting;
+        }
+
+        let room = |vnum: usize| world.rooms.get(vnum).filter(|room| room.vnum.0 != 0);
+        // Landmarks without a room are put in the one with the lowest vnum
+        let lowest = world.rooms.iter().find(|room| room.vnum.0 != 0);
+        let landmarks: BTreeSet<&str> = config
+            .landmarks()
+            .values()
+            .filter_map(|vnum| room(*vnum).or(lowest))
+            .chain(lowest)
+            .map(|room| room.area.as_str())
+            .collect();
+        let next_to_landmarks: BTreeSet<&str> = world
+            .rooms
+            .iter()
+            .filter(|room| landmarks.contains(room.area.as_str()))
+            .flat_map(|room| &room.exits)
+            .filter_map(|exit| room(exit.vnum.0))
+            .map(|room| room.area.as_str())
+            .collect();
+
+        waiting.areas = world
+            .areas
+            .iter()
+            .map(|(area_data, _resets)| area_data.short_name.as_str())
+            .filter(|area| !landmarks.contains(area) && !next_to_landmarks.contains(area))
+            .map(|area| area.to_string())
+            .collect();
+        waiting
+    }
+
+    pub fn contains(&self, area: &str) -> bool {
+        self.areas.contains(area)
+    }
+
+    /// Stop waiting for an area, as it is being imported.
+    pub fn remove(&mut self, area: &str) {
+        self.areas.remove(area);
+    }
+
+    /// Remember an exit that couldn't be linked, if the room it leads to is
+    /// in an area that waits.
+    pub fn remember_exit(&mut self, world: &World, exit: PermanentEntityId, leads_to: usize) {
+        let waits = world
+            .rooms
+            .get(leads_to)
+            .is_some_and(|room| room.vnum.0 != 0 && self.contains(&room.area));
+        if waits {
+            self.exits.push((exit, leads_to));
+        }
+    }
+}
+
+/// Link the exits that wait into rooms that were imported since.
+pub(crate) fn link_waiting_exits(entity_world: &mut EntityWorld) {
+    let mut exits = std::mem::take(&mut entity_world.templates.waiting_areas.exits);
+    exits.retain(|(exit, leads_to)| {
+        let exit_id = match entity_world.old_entity(exit) {
+            Some(exit) => exit.entity_id(),
+            // Gone, like the rooms of a reloaded area
+            None => return false,
+        };
+        let to_room_id = entity_world
+            .templates
+            .room(*leads_to)
+            .and_then(|room| entity_world.old_entity(room))
+            .map(|room| room.entity_id());
+
+        match to_room_id {
+            Some(to_room_id) => {
+                entity_world.set_leads_to(exit_id, to_room_id);
+                false
+            }
+            None => true,
+        }
+    });
+    entity_world.templates.waiting_areas.exits = exits;
+}
+
+/// Import an area that waits, and run its resets for a time of day. Returns
+/// whether it was waiting.
+pub(crate) fn import_waiting_area(
+    entity_world: &mut EntityWorld,
+    world: &World,
+    area: &str,
+    time_of_day: TimeOfDay,
+) -> bool {
+    if !entity_world.templates.waiting_areas.contains(area) {
+        return false;
+    }
+
+    import_area(entity_world, world, area);
+    if let Some((_area_data, resets)) = world
+        .areas
+        .iter()
+        .find(|(area_data, _resets)| area_data.short_name == area)
+    {
+        run_resets(entity_world, resets, |only_at| {
+            only_at.is_none() || only_at == Some(time_of_day)
+        });
+    }
+    println!("Imported the area {}.", area);
+
+    true
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// The room with a vnum, importing its area if it waits.
+    pub fn room_with_vnum(&mut self, vnum: usize) -> Option<EntityId> {
+        if let Some(room) = self.world.rooms.get(vnum).filter(|room| room.vnum.0 != 0) {
+            import_waiting_area(
+                self.entity_world,
+                self.world,
+                &room.area,
+                time_of_day(self.clock.hour()),
+            );
+        }
+
+        self.entity_world
+            .templates
+            .room(vnum)
+            .and_then(|permanent_id| self.entity_world.old_entity(permanent_id))
+            .map(|room| room.entity_id())
+    }
+
+    /// Where an exit leads, importing the area it leads into first if that
+    /// waits.
+    pub fn leads_to(&mut self, exit_id: EntityId) -> Option<EntityId> {
+        let exit = self.entity_world.entity_info(exit_id);
+        if let Some(room_id) = exit.leads_to() {
+            return Some(room_id);
+        }
+
+        let permanent_id = exit.permanent_entity_id();
+        let leads_to = self
+            .entity_world
+            .templates
+            .waiting_areas
+            .exits
+            .iter()
+            .find(|(exit, _leads_to)| *exit == permanent_id)
+            .map(|(_exit, leads_to)| *leads_to)?;
+        self.room_with_vnum(leads_to)?;
+
+        self.entity_world.entity_info(exit_id).leads_to()
+    }
+}
@@ -1,23 +1,99 @@
+mod accounts; // Player accounts, passwords, name policy, and deletion
 mod acting; // Process and output things like "$n flexes $s muscles."
+mod admin; // Immortal commands like goto, transfer, load and purge
 mod agent; // Object providing access to all game resources needed for commands
+mod archetypes; // Kinds of entities made by game features, like corpses and coins, registered at startup
+mod archive; // Reading areas from a .tar.zst, .tar.gz or .zip archive, as if it were a directory
+mod auction; // A realm-wide auction with bids, saved to data/auction.json
+mod bank; // Bankers, and keeping silver in the bank with deposit and withdraw
+#[doc(hidden)]
+pub mod benchmarks; // Parts of the mudlib that benches/ measure on their own
+mod broadcast; // Immortal commands to echo messages to a room, an area, or everyone
+mod channels; // Chat channels like gossip, which players can turn off
+mod circle; // CircleMUD zone loader, reading .zon, .wld, .mob and .obj files into the world
+mod clans; // Clans with ranks, halls and treasuries, saved to data/clans.json
+mod clock; // The game clock and calendar, which shops and hour mobprogs keep to
 mod colors; // Turn codes like "`w" into "\e[37m".
+mod command_table; // Every command, its abbreviations, and the position and trust it needs
 mod commands; // do_say, do_look, do_get, etc, implemented upon EntityAgent
 mod components; // Types of game data (mob, obj, etc) attached to entities
+mod config; // Settings from the server's TOML configuration file
+#[cfg(feature = "net")]
+mod crash; // Crash reports, and saving what can be saved when the server panics
+mod day_night; // Night descriptions, and mobiles and objects only around by day or night
+mod decay; // Corpses and food that rot, and conjured things that fade away
+pub mod diff; // Differences between two worlds, or an area and its file, before reloading it
+mod discipline; // Immortal commands to slay, restore, freeze and mute
+mod dot; // Export the rooms and exits of an area as a Graphviz graph
+mod drunk; // Alcoholic drinks, slurred speech and stumbling, and hangovers
+mod durability; // Weapons and armor that wear down in fights and break, and repairers who mend them
+mod encounters; // Mobiles that players run into in the wilderness, listed by each area
 mod entity; // Every object in the world and relation between objects
+mod escrow; // Objects held for players, like mail attachments and auction lots, kept whole across restarts
+mod event_log; // Every command, login, death and immortal action, as JSON in rotating files
+mod events; // Events of the entity world, like doors opening, that other systems subscribe to
+mod export; // Export areas of the world as JSON, and load them back
 mod file_parser; // Dawn of Time area format parser primitives
 mod files; // Abstraction trait for reading files
 mod find_entities; // Primitives to help with matching and filtering entities
+#[doc(hidden)]
+pub mod fuzzing; // Entry points into the area loader for the fuzz targets in fuzz/
+mod hunting; // Mobiles hunting down the players they remember
 mod import; // Use templates from a DoT world to insert new EntityWorld entities
+mod lazy; // Importing areas only once someone goes in them, for large worlds
+mod lights; // Torches and lanterns that burn out, and filling lanterns with oil
 mod load; // Dawn of Time area loader
+mod mail; // Player-to-player mail with escrowed attachments, saved to data/mail.json
 mod mapper; // Map generator
+mod memory; // Immortal command counting entities and interned strings
 mod mobprogs; // MobProg script runner, and additional do_mob_... commands
+mod mpedit; // In-game mobprog editor, checked with the mobprog parser
 #[cfg(feature = "net")]
 mod net; // Handle network players from NetServer; not used in WASM or CLI.
+mod notes; // Note boards (general, ideas, immortal) saved to data/notes/
+mod olc; // Online creation: the area editor, and saving areas with asave
+mod parallel; // Running the loading of the world on several threads
+mod pathfinding; // Shortest paths between rooms, for hunting, tracking and walking mobiles
+mod perf; // Time spent in each part of the game, from tracing spans, for the perf command
+mod pets; // Pet shops, which sell followers from the room next to them
+mod preferences; // Player settings, aliases, and prompt, saved with their account
+pub mod procgen; // Dungeons generated from a seed, to play without stock areas or stress-test
+mod query; // Typed queries of the components of entities, for game systems
+#[cfg(feature = "net")]
+mod reboot; // Shutdowns and reboots scheduled for later, with warnings
+mod reload; // Reload socials and command trust levels from their files while running
+mod rng; // Seeded random numbers for the whole game, to replay sessions exactly
+mod rom; // ROM 2.4 area loader, into the same types as Dawn of Time areas
+mod save; // Dawn of Time area writer
+mod scavengers; // Mobiles that pick up valuable things lying around, and wear them
+mod set; // Immortal command to change fields of entities while the game runs
+mod shops; // Shopkeepers and the list, buy, sell, value and haggle commands
+pub mod simulation; // Running the game without a server, for end-to-end tests
+mod smaug; // SMAUG area loader, with its extended room flags kept as words
+mod snapshots; // Timestamped snapshots of the entity world, and rollback
 mod socials; // Load socials from socials.txt
+mod stalls; // Stalls that players rent to sell things, saved to data/stalls.json
+mod stat; // Immortal commands to inspect the components of any entity
 mod state; // Main game object, glues everything together
-mod tick; // Things that mobs do every second (e.g. wandering around rooms)
-mod world; // Read-only representation of a set of Dawn of Time areas
+#[cfg(feature = "net")]
+mod status; // Who is online and the state of the game as JSON, for the HTTP API
+mod tells; // Player tells, and messages queued for offline players
+mod templates; // What mobiles and objects are spawned from, by vnum, kept by the entity world
+mod terrain; // Moves it costs to cross each sector, and boats for deep water
+mod tick; // The pulse scheduler, which runs game systems every few pulses
+mod toml_area; // Areas written by hand in TOML, for builders who don't know the area file formats
+mod trust; // Trust levels for players, and the trust each command needs
+mod validate; // Check areas for broken exits, resets and mobprog triggers, as a report
+mod visibility; // Wizinvis and holylight, and what players can see of each other
+mod vitals; // Hit points, mana and moves, and regenerating them every tick
+mod vnum_search; // Builder commands to find templates by name, and free vnums
+mod wander; // Mobiles wandering through open exits, and the rooms they keep out of
+mod weather; // Weather in each area, which players outdoors see change
+mod wizlog; // Immortal log of noteworthy events, which each immortal can filter
+mod world; // Representation of a set of Dawn of Time areas, as in area files
+mod worldstat; // Immortal command with the entities, templates and limits of each area
 
-pub use colors::colorize;
+pub use colors::{colorize, decolorize};
 pub use files::Files;
 pub use state::WorldState;
+pub use validate::{Diagnostic, Report, Severity};
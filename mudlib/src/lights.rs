@@ -0,0 +1,199 @@
+//! Torches and lanterns, which burn while they are lit.
+//!
+//! Objects of the `light` item type burn for as many game hours as the third
+//! of their `Values`, or for ever with -1 (or 999). The first value is how
+//! many hours of oil they hold when filled up, or 0 for torches that can't
+//! be refilled.
+//!
+//! Players `light` and `extinguish` them. Lit lights burn an hour every tick,
+//! dim before they go out, and go out by themselves when they burn out.
+//! Lanterns can be filled again with `fill`, from anything that holds oil: a
+//! `drink_con` or `fountain` whose liquid is `oil`.
+
+use crate::{
+    agent::EntityAgent,
+    components::{ComponentFromEntity, Light, Object},
+    decay::tell_around,
+    echo,
+    entity::{EntityId, EntityInfo},
+    find_entities::EntityIterator,
+    WorldState,
+};
+
+/// What players see as a light burns down, with that many hours left.
+fn burn_stage(hours: u16) -> Option<&'static str> {
+    match hours {
+        3 => Some("is getting dim."),
+        1 => Some("flickers, and is about to go out."),
+        0 => Some("burns out, and goes out."),
+        _ => None,
+    }
+}
+
+fn holds_oil(entity: &EntityInfo) -> bool {
+    matches!(
+        &entity.components().object,
+        Some(object) if object.liquid.as_deref() == Some("oil")
+    )
+}
+
+pub(crate) fn update_lights(world_state: &mut WorldState) {
+    let burning: Vec<EntityId> = world_state
+        .entity_world
+        .query::<&Object>()
+        .filter(|(_entity, object)| {
+            matches!(
+                &object.light,
+                Some(Light {
+                    lit: true,
+                    hours: Some(_),
+                    ..
+                })
+            )
+        })
+        .map(|(entity, _object)| entity.entity_id())
+        .collect();
+
+    for light_id in burning {
+        let mut light = world_state.entity_world.entity_info_mut(light_id);
+        let hours = match light
+            .components()
+            .object
+            .as_mut()
+            .and_then(|object| object.light.as_mut())
+        {
+            Some(Light {
+                lit,
+                hours: Some(hours),
+                ..
+            }) => {
+                *hours = hours.saturating_sub(1);
+                *lit = *hours > 0;
+                *hours
+            }
+            _ => continue,
+        };
+
+        if let Some(stage) = burn_stage(hours) {
+            tell_around(world_state, light_id, stage);
+        }
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    fn change_light<F: FnOnce(&mut Light)>(&mut self, light_id: EntityId, change: F) {
+        let mut light = self.entity_world.entity_info_mut(light_id);
+        if let Some(light) = light
+            .components()
+            .object
+            .as_mut()
+            .and_then(|object| object.light.as_mut())
+        {
+            change(light);
+        }
+    }
+
+    pub fn do_light(&mut self, item_name: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let found = myself
+            .objects()
+            .filter_by_keyword(item_name)
+            .with_component_or::<Light>("$^$N is not something you can light.")
+            .find_one_with_component_or("You aren't holding anything named like that.");
+
+        let (light, component) = match found {
+            Ok(found) => found,
+            Err(error) => return self.echo_error(error),
+        };
+
+        let mut act = self.players.act_with(&myself, &light);
+        if component.lit {
+            echo!(act.myself(), "$^$N is already lit.\r\n");
+            return;
+        }
+        if component.hours == Some(0) {
+            if component.capacity > 0 {
+                echo!(act.myself(), "$^$N is out of oil.\r\n");
+            } else {
+                echo!(act.myself(), "$^$N has burnt out.\r\n");
+            }
+            return;
+        }
+
+        echo!(act.all(), "$^$n $[light|lights] $N.\r\n");
+
+        let light_id = light.entity_id();
+        self.change_light(light_id, |light| light.lit = true);
+    }
+
+    pub fn do_extinguish(&mut self, item_name: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let found = myself
+            .objects()
+            .filter_by_keyword(item_name)
+            .with_component_or::<Light>("$^$N is not something you can extinguish.")
+            .find_one_with_component_or("You aren't holding anything named like that.");
+
+        let (light, component) = match found {
+            Ok(found) => found,
+            Err(error) => return self.echo_error(error),
+        };
+
+        let mut act = self.players.act_with(&myself, &light);
+        if !component.lit {
+            echo!(act.myself(), "$^$N isn't lit.\r\n");
+            return;
+        }
+
+        echo!(act.all(), "$^$n $[extinguish|extinguishes] $N.\r\n");
+
+        let light_id = light.entity_id();
+        self.change_light(light_id, |light| light.lit = false);
+    }
+
+    pub fn do_fill(&mut self, item_name: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let found = myself
+            .objects()
+            .filter_by_keyword(item_name)
+            .with_component_or::<Light>("$^$N is not something you can fill.")
+            .filter_or(
+                |light| matches!(Light::component_from_entity(light), Some(light) if light.capacity > 0),
+                "$^$N can't be refilled.",
+            )
+            .find_one_with_component_or("You aren't holding anything named like that.");
+
+        let (light, component) = match found {
+            Ok(found) => found,
+            Err(error) => return self.echo_error(error),
+        };
+
+        let mut act = self.players.act_with(&myself, &light);
+        if component.hours == Some(component.capacity) {
+            echo!(act.myself(), "$^$N is already full.\r\n");
+            return;
+        }
+
+        let oil = myself
+            .objects()
+            .chain(myself.room().objects())
+            .find(|object| holds_oil(object) && *object != light);
+        let oil = match oil {
+            Some(oil) => oil,
+            None => {
+                echo!(act.myself(), "There is no oil here to fill $N with.\r\n");
+                return;
+            }
+        };
+
+        echo!(
+            act.myself(),
+            "You fill $N with oil from {}.\r\n",
+            oil.component_info().short_description()
+        );
+        echo!(act.others(), "$^$n fills $N with oil.\r\n");
+
+        let light_id = light.entity_id();
+        self.change_light(light_id, |light| light.hours = Some(light.capacity));
+    }
+}
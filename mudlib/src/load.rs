@@ -2,19 +2,141 @@
 //!
 //! This module uses the basic primitives in `crate::file_parser` to read area
 //! files, parse rooms/mobiles/objects from them, and convert them into the
-//! plain object types from `crate::world`.
-
-use rand::random;
+//! plain object types from `crate::world`. ROM 2.4 and SMAUG area files, told
+//! apart by `AreaFormat::detect()`, are handed to `crate::rom` and
+//! `crate::smaug` instead.
 
 use crate::{
-    file_parser::FileParser,
+    components::Wealth,
+    config::AreaSource,
+    file_parser::{FileParser, ParseResult},
+    rng::GameRng,
+    socials::load_area_socials,
     world::{
-        Area, AreaData, Exit, ExtraDescription, Gender, MobProg, MobProgTrigger, Mobile, Object,
-        ObjectFlags, ResetCommand, Room, Shop, Vnum, VnumOrKeyword,
+        Area, AreaData, AreaFormat, Encounter, Exit, ExtraDescription, Gender, MobProg,
+        MobProgTrigger, Mobile, Object, ObjectFlags, ResetCommand, Room, Shop, TimeOfDay, Vnum,
+        VnumOrKeyword,
     },
 };
 
-pub(super) fn load_area(area_file_contents: &str, file_name: &str) -> Area {
+/// Minutes between resets, for areas that don't specify it
+pub(super) const DEFAULT_RESET_INTERVAL: u16 = 15;
+
+/// The highest vnum areas can use. Rooms, objects and mobiles are kept in
+/// lists indexed by vnum, which a broken vnum would make huge.
+pub(super) const MAX_VNUM: usize = 1_000_000;
+
+/// Keys of mobiles whose values end with a tilde; the others end with the line
+pub(super) const MOBILE_TEXT_KEYS: &[&str] = &[
+    "Name",
+    "ShortD",
+    "LongD",
+    "NightLongD",
+    "Desc",
+    "Race",
+    "Act",
+    "Act2",
+    "AffBy",
+    "AffBy2",
+    "Off",
+    "Imm",
+    "Res",
+    "Vuln",
+    "Form",
+    "Part",
+    "StartP",
+    "DefPos",
+    "Size",
+    "Sex",
+    "MProg",
+];
+
+const MOBILE_LINE_KEYS: &[&str] = &[
+    "Align",
+    "XPMod",
+    "Level",
+    "Hitroll",
+    "HitDice",
+    "ManaDice",
+    "DamDice",
+    "DamType",
+    "AC",
+    "Wealth",
+    "Material",
+    "Helpgroup",
+    "InnBuy",
+    "InnSell",
+    "InnOpen",
+    "InnClose",
+    "InnRoom",
+];
+
+/// Keys of objects whose values end with a tilde; the others end with the line
+pub(super) const OBJECT_TEXT_KEYS: &[&str] = &[
+    "Name",
+    "Short",
+    "Desc",
+    "ItemType",
+    "Material",
+    "Extra",
+    "Extra2",
+    "Wear",
+    "ClassAllowances",
+    "AttuneFlags",
+];
+
+const OBJECT_LINE_KEYS: &[&str] = &[
+    "Level",
+    "Cost",
+    "Condition",
+    "Asize",
+    "Rsize",
+    "Values",
+    "Weight",
+    "Affect",
+];
+
+/// Keys of rooms whose values end with a tilde; the others end with the line
+pub(super) const ROOM_TEXT_KEYS: &[&str] = &[
+    "Name",
+    "Desc",
+    "NightDesc",
+    "RoomFlags",
+    "Sector",
+    "RoomEcho",
+    "EDesc",
+    "EFlags",
+    "EKeywords",
+];
+
+const ROOM_LINE_KEYS: &[&str] = &[
+    "Mana",
+    "Heal",
+    "LockerQuant",
+    "LockerInitRent",
+    "LockerOngoRent",
+    "LockerWeight",
+    "LockerCapacity",
+    "LockerPickProof",
+    "Exit",
+    "EKeyvnum",
+];
+
+/// Parse an area file. Broken files are an error rather than a panic, as
+/// areas found on the internet often are.
+pub(super) fn load_area(
+    area_file_contents: &str,
+    file_name: &str,
+    rng: &mut GameRng,
+) -> ParseResult<Area> {
+    match AreaFormat::detect(area_file_contents) {
+        AreaFormat::Smaug => {
+            return crate::smaug::load_smaug_area(area_file_contents, file_name, rng)
+        }
+        AreaFormat::Rom => return crate::rom::load_rom_area(area_file_contents, file_name, rng),
+        _ => (),
+    }
+
     let mut parser = FileParser::new(area_file_contents, file_name);
 
     let mut area_data = None;
@@ -23,129 +145,298 @@ pub(super) fn load_area(area_file_contents: &str, file_name: &str) -> Area {
     let mut rooms = None;
     let mut resets = None;
     let mut shops = None;
+    let mut helps = None;
+    let mut socials = None;
     let mut mobprogs = None;
 
     loop {
-        let section = parser.read_section();
+        let section = parser.read_section()?;
 
         match section {
             "$" => break,
-            "DAWNAREADATA" => area_data = Some(load_area_data(&mut parser)),
-            "MOBILES" => mobiles = Some(load_mobile_data(&mut parser)),
-            "OBJECTS" => objects = Some(load_object_data(&mut parser)),
-            "ROOMS" => rooms = Some(load_room_data(&mut parser)),
-            "SPECIALS" => skip_specials(&mut parser),
-            "RESETS2" => resets = Some(load_resets(&mut parser)),
-            "SHOPS" => shops = Some(load_shops(&mut parser)),
-            "MOBPROGS" => mobprogs = Some(load_mobprogs(&mut parser)),
-            section => panic!("Unrecognized section: '#{}'", section),
+            "DAWNAREADATA" => area_data = Some(load_area_data(&mut parser)?),
+            "MOBILES" => mobiles = Some(load_mobile_data(&mut parser, rng)?),
+            "OBJECTS" => objects = Some(load_object_data(&mut parser)?),
+            "ROOMS" => rooms = Some(load_room_data(&mut parser)?),
+            "SPECIALS" => skip_specials(&mut parser)?,
+            "RESETS2" => resets = Some(load_resets(&mut parser)?),
+            "SHOPS" => shops = Some(load_shops(&mut parser)?),
+            "MOBPROGS" => mobprogs = Some(load_mobprogs(&mut parser)?),
+            "HELPS" => helps = Some(crate::rom::load_helps(&mut parser)?),
+            "SOCIALS" => socials = Some(load_area_socials(&mut parser)?),
+            section => return Err(parser.error(&format!("Unrecognized section: '#{}'", section))),
         }
     }
 
-    let area_data = area_data.unwrap();
-    let mut rooms = rooms.unwrap();
+    let area_data = area_data.ok_or_else(|| parser.error("Missing the #DAWNAREADATA section"))?;
+    // Other sections can be left out when there is nothing in them
+    let mut area = Area {
+        area_data,
+        rooms: rooms.unwrap_or_default(),
+        objects: objects.unwrap_or_default(),
+        mobiles: mobiles.unwrap_or_default(),
+        resets: resets.unwrap_or_default(),
+        shops: shops.unwrap_or_default(),
+        mobprogs: mobprogs.unwrap_or_default(),
+        helps: helps.unwrap_or_default(),
+        socials: socials.unwrap_or_default(),
+    };
+    remember_area(&mut area);
+
+    Ok(area)
+}
+
+/// Remember which area everything came from, to save it there again.
+pub(super) fn remember_area(area: &mut Area) {
+    let short_name = &area.area_data.short_name;
+
+    for room in &mut area.rooms {
+        room.area = short_name.clone();
+    }
+    for object in &mut area.objects {
+        object.area = short_name.clone();
+    }
+    for mobile in &mut area.mobiles {
+        mobile.area = short_name.clone();
+    }
+    for shop in &mut area.shops {
+        shop.area = short_name.clone();
+    }
+    for mobprog in &mut area.mobprogs {
+        mobprog.area = short_name.clone();
+    }
+    for help in &mut area.helps {
+        help.area = short_name.clone();
+    }
+    for social in &mut area.socials {
+        social.area = short_name.clone();
+    }
+}
 
-    for room in &mut rooms {
-        room.area = area_data.short_name.clone();
+/// Move the vnums of an area from another source up by its offset, and put
+/// its prefix before its short name, so that it can be loaded next to areas
+/// that use the same ones. Vnums of 0, which are none, are left as they are;
+/// so are vnums in the code of mobprogs.
+pub(super) fn apply_source(area: &mut Area, source: &AreaSource) -> Result<(), String> {
+    let offset = source.vnum_offset;
+    let highest = area
+        .rooms
+        .iter()
+        .map(|room| room.vnum.0)
+        .chain(area.objects.iter().map(|object| object.vnum.0))
+        .chain(area.mobiles.iter().map(|mobile| mobile.vnum.0))
+        .chain(area.mobprogs.iter().map(|mobprog| mobprog.vnum.0))
+        .max()
+        .unwrap_or(0);
+    if highest + offset > MAX_VNUM {
+        return Err(format!(
+            "vnum {} is above {} once moved up by {}",
+            highest + offset,
+            MAX_VNUM,
+            offset
+        ));
     }
 
-    Area {
-        area_data,
-        rooms,
-        objects: objects.unwrap(),
-        mobiles: mobiles.unwrap(),
-        resets: resets.unwrap(),
-        shops: shops.unwrap(),
-        mobprogs: mobprogs.unwrap(),
+    let mv = |vnum: &mut Vnum| {
+        if vnum.0 != 0 {
+            vnum.0 += offset;
+        }
+    };
+
+    mv(&mut area.area_data.vnums.0);
+    mv(&mut area.area_data.vnums.1);
+    for encounter in &mut area.area_data.encounters {
+        mv(&mut encounter.vnum);
     }
+    for room in &mut area.rooms {
+        mv(&mut room.vnum);
+        for exit in &mut room.exits {
+            mv(&mut exit.vnum);
+            if let Some(key) = &mut exit.key {
+                mv(key);
+            }
+        }
+    }
+    for object in &mut area.objects {
+        mv(&mut object.vnum);
+    }
+    for mobile in &mut area.mobiles {
+        mv(&mut mobile.vnum);
+        for (trigger, vnum) in &mut mobile.mobprog_triggers {
+            mv(vnum);
+            if let MobProgTrigger::Give {
+                item_vnum: VnumOrKeyword::Vnum(item),
+            } = trigger
+            {
+                mv(item);
+            }
+        }
+    }
+    for reset in &mut area.resets {
+        match reset {
+            ResetCommand::Mob { m_num, r_num, .. } => {
+                mv(m_num);
+                mv(r_num);
+            }
+            ResetCommand::Object { o_num, r_num, .. } => {
+                mv(o_num);
+                mv(r_num);
+            }
+            ResetCommand::Door { r_num, .. } => mv(r_num),
+            ResetCommand::Give { o_num, .. } | ResetCommand::Equip { o_num, .. } => mv(o_num),
+            ResetCommand::Put { o_num, c_num, .. } => {
+                mv(o_num);
+                mv(c_num);
+            }
+        }
+    }
+    for shop in &mut area.shops {
+        mv(&mut shop.vnum);
+    }
+    for mobprog in &mut area.mobprogs {
+        mv(&mut mobprog.vnum);
+    }
+
+    area.area_data.short_name = format!("{}{}", source.prefix, area.area_data.short_name);
+    remember_area(area);
+
+    Ok(())
 }
 
-fn load_area_data(parser: &mut FileParser) -> AreaData {
+/// Read the vnum of the next room, object, mobile, shop or mobprog from its
+/// section, e.g. "#3001"; 0 ends the list.
+pub(super) fn read_vnum_section(parser: &mut FileParser) -> ParseResult<usize> {
+    let section = parser.read_section()?;
+    let vnum = parser.parse(section, "vnum")?;
+    check_vnum(parser, vnum)
+}
+
+pub(super) fn check_vnum(parser: &FileParser, vnum: usize) -> ParseResult<usize> {
+    if vnum > MAX_VNUM {
+        return Err(parser.error(&format!("Vnum {} is above {}", vnum, MAX_VNUM)));
+    }
+    Ok(vnum)
+}
+
+pub(super) fn load_area_data(parser: &mut FileParser) -> ParseResult<AreaData> {
     let mut area_data = AreaData {
         name: Default::default(),
         short_name: Default::default(),
         vnums: Default::default(),
         credits: Default::default(),
         continent: Default::default(),
+        builders: Default::default(),
+        security: 0,
+        reset_interval: DEFAULT_RESET_INTERVAL,
+        encounters: Vec::new(),
+        file_name: Default::default(),
+        source: None,
+        on_disk: false,
+        changed: false,
+        format: AreaFormat::DawnOfTime,
     };
 
     loop {
-        let key = parser.read_word();
+        let key = parser.read_word()?;
         parser.skip_all_space();
 
         let value = match key {
             "End" | "END" => break,
             "Version" | "*parent_codebase" | "VNUMs" | "LRange" | "LComment" | "Security"
-            | "colourcode" | "MapScale" | "MapLevel" | "Vnum_offset" => parser.read_until_newline(),
+            | "colourcode" | "MapScale" | "MapLevel" | "Vnum_offset" | "ResetInterval"
+            | "Encounter" => parser.read_until_newline()?,
             "FromMUD" | "Name" | "ShortName" | "Builders" | "Credits" | "build_restricts"
-            | "AFlags" | "Colour" | "Continent" | "*LastSaved" => parser.read_until_tilde(),
-            section => panic!("Unrecognized area data section: '{}'", section),
+            | "AFlags" | "Colour" | "Continent" | "*LastSaved" => parser.read_until_tilde()?,
+            section => {
+                return Err(parser.error(&format!("Unrecognized area data section: '{}'", section)))
+            }
         };
 
         match key {
             "Name" => area_data.name = value.to_string(),
             "ShortName" => area_data.short_name = value.to_string(),
             "VNUMs" => {
-                let mut vnums = value.split_whitespace().map(|word| word.parse().unwrap());
-
-                let vnum_1 = vnums.next().unwrap();
-                let vnum_2 = vnums.next().unwrap();
+                let mut vnums = value.split_whitespace();
+                let (vnum_1, vnum_2) = match (vnums.next(), vnums.next()) {
+                    (Some(vnum_1), Some(vnum_2)) => (vnum_1, vnum_2),
+                    _ => return Err(parser.error("Expected two vnums")),
+                };
+                let vnum_1 = check_vnum(parser, parser.parse(vnum_1, "vnum")?)?;
+                let vnum_2 = check_vnum(parser, parser.parse(vnum_2, "vnum")?)?;
 
                 area_data.vnums = (Vnum(vnum_1), Vnum(vnum_2));
             }
             "Credits" => area_data.credits = value.to_string(),
             "Continent" => area_data.continent = value.to_string(),
+            "Builders" => area_data.builders = value.to_string(),
+            "Security" => area_data.security = parser.parse(value, "security")?,
+            "ResetInterval" => area_data.reset_interval = parser.parse(value, "reset interval")?,
+            "Encounter" => {
+                let mut words = value.split_whitespace();
+                let encounter = match (words.next(), words.next(), words.next()) {
+                    (Some(sector), Some(vnum), Some(limit)) => vnum
+                        .parse()
+                        .ok()
+                        .zip(limit.parse().ok())
+                        .map(|(vnum, limit)| Encounter {
+                            sector: sector.to_string(),
+                            vnum: Vnum(vnum),
+                            limit,
+                        }),
+                    _ => None,
+                };
+                area_data
+                    .encounters
+                    .push(encounter.ok_or_else(|| parser.error("Invalid encounter"))?);
+            }
             _ => (),
         }
     }
 
-    area_data
+    Ok(area_data)
 }
 
-fn load_mobile_data(parser: &mut FileParser) -> Vec<Mobile> {
+fn load_mobile_data(parser: &mut FileParser, rng: &mut GameRng) -> ParseResult<Vec<Mobile>> {
     let mut mobiles = Vec::new();
 
     loop {
-        let vnum = parser.read_section().parse().unwrap();
+        let vnum = read_vnum_section(parser)?;
 
         if vnum == 0 {
             break;
         }
 
-        mobiles.push(load_mobile(parser, vnum))
+        mobiles.push(load_mobile(parser, vnum, rng)?)
     }
 
-    mobiles
+    Ok(mobiles)
 }
 
-fn load_mobile(parser: &mut FileParser, vnum: usize) -> Mobile {
+fn load_mobile(parser: &mut FileParser, vnum: usize, rng: &mut GameRng) -> ParseResult<Mobile> {
     let mut mobile = Mobile {
         vnum: Vnum(vnum),
         ..Default::default()
     };
 
     loop {
-        let key = parser.read_word();
+        let key = parser.read_word()?;
 
         if key != "End" && key != "END" {
-            parser.skip_one_space();
+            parser.skip_one_space()?;
         }
 
         let value = match key {
             "END" | "End" => break,
-            "Name" | "ShortD" | "LongD" | "Desc" | "Race" | "Act" | "Act2" | "AffBy" | "AffBy2"
-            | "Off" | "Imm" | "Res" | "Vuln" | "Form" | "Part" | "StartP" | "DefPos" | "Size"
-            | "Sex" | "MProg" => parser.read_until_tilde(),
-            "Align" | "XPMod" | "Level" | "Hitroll" | "HitDice" | "ManaDice" | "DamDice"
-            | "DamType" | "AC" | "Wealth" | "Material" | "Helpgroup" | "InnBuy" | "InnSell"
-            | "InnOpen" | "InnClose" | "InnRoom" => parser.read_until_newline(),
-            key => panic!("Unrecognized mobile data key: '{}'", key),
+            key if MOBILE_TEXT_KEYS.contains(&key) => parser.read_until_tilde()?,
+            key if MOBILE_LINE_KEYS.contains(&key) => parser.read_until_newline()?,
+            key => return Err(parser.error(&format!("Unrecognized mobile data key: '{}'", key))),
         };
 
         match key {
             "Name" => mobile.name = value.to_string(),
             "ShortD" => mobile.short_description = value.to_string(),
             "LongD" => mobile.long_description = value.to_string(),
+            "NightLongD" => mobile.night_long_description = Some(value.to_string()),
             "Desc" => mobile.description = value.to_string(),
             "Sex" => {
                 mobile.gender = match value.trim_start() {
@@ -153,263 +444,339 @@ fn load_mobile(parser: &mut FileParser, vnum: usize) -> Mobile {
                     "female" => Gender::Female,
                     "neutral" => Gender::Neutral,
                     "random" => {
-                        if random() {
+                        if rng.coin() {
                             Gender::Male
                         } else {
                             Gender::Female
                         }
                     }
-                    gender => parser.panic_on_line(&format!("Unknown sex/gender: {}", gender)),
+                    gender => return Err(parser.error(&format!("Unknown sex/gender: {}", gender))),
                 }
             }
             "Act" => {
+                let mut unused = Vec::new();
                 for word in value.split_whitespace() {
                     match word {
                         "dont_wander" => mobile.sentinel = true,
+                        "stay_area" => mobile.stay_area = true,
+                        "scavenger" => mobile.scavenger = true,
+                        "aggressive" => mobile.aggressive = true,
                         "unseen" => mobile.unseen = true,
-                        _ => (),
+                        "postmaster" => mobile.postmaster = true,
+                        "banker" => mobile.banker = true,
+                        "repairer" => mobile.repairer = true,
+                        word => unused.push(word),
                     }
                 }
+                keep_extra(&mut mobile.extras, key, &unused.join(" "));
             }
-            "MProg" => {
-                let mut words = value.split_whitespace();
-
-                let (vnum, trigger) = match words.next().unwrap() {
-                    "SPEECH" => (
-                        words.next(),
-                        MobProgTrigger::Speech {
-                            pattern: words.collect::<Vec<_>>().join(" "),
-                        },
-                    ),
-                    "RANDOM" => (
-                        words.next(),
-                        MobProgTrigger::Random {
-                            chance: words.next().unwrap().parse().unwrap(),
-                        },
-                    ),
-                    "DEATH" => (
-                        words.next(),
-                        MobProgTrigger::Death {
-                            chance: words
-                                .next()
-                                .map(|word| if word == "all" { "100" } else { word })
-                                .unwrap()
-                                .parse()
-                                .unwrap(),
-                        },
-                    ),
-                    "EXIT" | "EXALL" => (
-                        words.next(),
-                        MobProgTrigger::Exit {
-                            direction: words.next().unwrap().to_string(),
-                        },
-                    ),
-                    "HOUR" => (
-                        words.next(),
-                        MobProgTrigger::Hour {
-                            hour: words.next().unwrap().parse().unwrap(),
-                        },
-                    ),
-                    "GREET" | "GRALL" => (
-                        words.next(),
-                        MobProgTrigger::Greet {
-                            chance: words.next().unwrap().parse().unwrap(),
-                        },
-                    ),
-                    "GIVE" => {
-                        let mopprog_vnum = words.next();
-                        let item = words.next().unwrap();
-                        let item_vnum = if let Ok(vnum) = item.parse() {
-                            VnumOrKeyword::Vnum(Vnum(vnum))
-                        } else {
-                            VnumOrKeyword::Keyword(item.to_string())
-                        };
-
-                        (mopprog_vnum, MobProgTrigger::Give { item_vnum })
+            "Level" => {
+                mobile.level = value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|level| level.parse().ok())
+                    .unwrap_or(0)
+            }
+            // In silver, as it is spawned with gold coins for each hundred
+            "Wealth" => {
+                let silver = value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|wealth| wealth.parse().ok())
+                    .unwrap_or(0);
+                mobile.wealth = Wealth::from_silver(silver)
+            }
+            "AffBy" => {
+                let mut unused = Vec::new();
+                for word in value.split_whitespace() {
+                    match word {
+                        "flying" => mobile.flying = true,
+                        word => unused.push(word),
                     }
-                    "ACT" => (
-                        words.next(),
-                        MobProgTrigger::Act {
-                            pattern: words.collect::<Vec<_>>().join(" ").to_string(),
-                        },
-                    ),
-                    "BRIBE" => (
-                        words.next(),
-                        MobProgTrigger::Bribe {
-                            amount: words.next().unwrap().parse().unwrap(),
-                        },
-                    ),
-                    "KILL" => (
-                        words.next(),
-                        MobProgTrigger::Kill {
-                            chance: words.next().unwrap().parse().unwrap(),
-                        },
-                    ),
-                    "ENTRY" => (
-                        words.next(),
-                        MobProgTrigger::Entry {
-                            chance: words.next().unwrap().parse().unwrap(),
-                        },
-                    ),
-                    "LOGINROOM" => (words.next(), MobProgTrigger::LoginRoom {}),
-                    "REPOP" | "COMMAND" | "SAYTO" | "TICK" | "FIGHT" | "HPCNT" | "DELAY"
-                    | "PREKILL" | "LOGOUTROOM" | "LOGINAREA" | "ROOMDEATH" => continue,
-                    trigger => panic!("Unknown mobprog trigger: {}", trigger),
-                };
-
-                let vnum = Vnum(vnum.unwrap().parse().unwrap());
-
-                mobile.mobprog_triggers.push((trigger, vnum));
+                }
+                keep_extra(&mut mobile.extras, key, &unused.join(" "));
             }
-            _ => (),
+            "MProg" => match parse_mobprog_trigger(value) {
+                Ok(Some((trigger, vnum))) => mobile.mobprog_triggers.push((trigger, vnum)),
+                Ok(None) => (),
+                Err(err) => return Err(parser.error(&err)),
+            },
+            _ => keep_extra(&mut mobile.extras, key, value),
         }
     }
 
-    mobile
+    Ok(mobile)
 }
 
-fn load_object_data(parser: &mut FileParser) -> Vec<Object> {
+/// Parse a mobprog trigger like "GREET 3 100": the kind of trigger, the
+/// mobprog's vnum, and what the trigger needs. Returns `None` for triggers
+/// that are not supported yet.
+pub(super) fn parse_mobprog_trigger(value: &str) -> Result<Option<(MobProgTrigger, Vnum)>, String> {
+    let mut words = value.split_whitespace();
+
+    let kind = words.next().ok_or("Missing mobprog trigger")?;
+    let vnum = words.next().ok_or("Missing mobprog vnum")?;
+    let vnum = Vnum(
+        vnum.parse()
+            .map_err(|_| format!("Invalid mobprog vnum: {}", vnum))?,
+    );
+
+    let argument = words.collect::<Vec<_>>().join(" ");
+    let number = |argument: &str| -> Result<usize, String> {
+        argument
+            .parse()
+            .map_err(|_| format!("Invalid number for {}: '{}'", kind, argument))
+    };
+    let small_number = |argument: &str| -> Result<u8, String> {
+        argument
+            .parse()
+            .map_err(|_| format!("Invalid number for {}: '{}'", kind, argument))
+    };
+
+    let trigger = match kind {
+        "SPEECH" => MobProgTrigger::Speech { pattern: argument },
+        "RANDOM" => MobProgTrigger::Random {
+            chance: small_number(&argument)?,
+        },
+        "DEATH" => MobProgTrigger::Death {
+            chance: small_number(if argument == "all" { "100" } else { &argument })?,
+        },
+        "EXIT" | "EXALL" => MobProgTrigger::Exit {
+            direction: argument,
+        },
+        "HOUR" => MobProgTrigger::Hour {
+            hour: small_number(&argument)?,
+        },
+        "GREET" | "GRALL" => MobProgTrigger::Greet {
+            chance: small_number(&argument)?,
+        },
+        "GIVE" => {
+            let item_vnum = if let Ok(vnum) = argument.parse() {
+                VnumOrKeyword::Vnum(Vnum(vnum))
+            } else {
+                VnumOrKeyword::Keyword(argument)
+            };
+
+            MobProgTrigger::Give { item_vnum }
+        }
+        "ACT" => MobProgTrigger::Act { pattern: argument },
+        "BRIBE" => MobProgTrigger::Bribe {
+            amount: number(&argument)?,
+        },
+        "KILL" => MobProgTrigger::Kill {
+            chance: small_number(&argument)?,
+        },
+        "ENTRY" => MobProgTrigger::Entry {
+            chance: small_number(&argument)?,
+        },
+        "LOGINROOM" => MobProgTrigger::LoginRoom,
+        "REPOP" | "COMMAND" | "SAYTO" | "TICK" | "FIGHT" | "HPCNT" | "DELAY" | "PREKILL"
+        | "LOGOUTROOM" | "LOGINAREA" | "ROOMDEATH" => return Ok(None),
+        trigger => return Err(format!("Unknown mobprog trigger: {}", trigger)),
+    };
+
+    Ok(Some((trigger, vnum)))
+}
+
+fn load_object_data(parser: &mut FileParser) -> ParseResult<Vec<Object>> {
     let mut objects = Vec::new();
 
     loop {
-        let vnum = parser.read_section().parse().unwrap();
+        let vnum = read_vnum_section(parser)?;
 
         if vnum == 0 {
             break;
         }
 
-        objects.push(load_object(parser, vnum))
+        objects.push(load_object(parser, vnum)?)
     }
 
-    objects
+    Ok(objects)
 }
 
-fn load_object(parser: &mut FileParser, vnum: usize) -> Object {
+fn load_object(parser: &mut FileParser, vnum: usize) -> ParseResult<Object> {
     let mut object = Object {
         vnum: Vnum(vnum),
         ..Default::default()
     };
 
     loop {
-        let key = parser.read_word();
+        let key = parser.read_word()?;
 
         if key != "End" && key != "END" {
-            parser.skip_one_space();
+            parser.skip_one_space()?;
         }
 
         let mut value2 = None;
 
         let value = match key {
             "END" | "End" => break,
-            "Name" | "Short" | "Desc" | "ItemType" | "Material" | "Extra" | "Extra2" | "Wear"
-            | "ClassAllowances" | "AttuneFlags" => parser.read_until_tilde(),
-            "Level" | "Cost" | "Condition" | "Asize" | "Rsize" | "Values" | "Weight" | "Affect" => {
-                parser.read_until_newline()
-            }
             "ExtraDesc" => {
-                value2 = Some(parser.read_until_tilde());
-                parser.read_until_tilde()
+                value2 = Some(parser.read_until_tilde()?);
+                parser.read_until_tilde()?
             }
-            key => panic!("Unrecognized object data key: '{}'", key),
+            key if OBJECT_TEXT_KEYS.contains(&key) => parser.read_until_tilde()?,
+            key if OBJECT_LINE_KEYS.contains(&key) => parser.read_until_newline()?,
+            key => return Err(parser.error(&format!("Unrecognized object data key: '{}'", key))),
         };
 
         match key {
             "Name" => object.name = value.to_string(),
             "Short" => object.short_description = value.to_string(),
-            "Cost" => object.cost = value.parse().expect("Invalid cost"),
+            "Cost" => object.cost = parser.parse(value, "cost")?,
             "Desc" => object.description = value.to_string(),
             "ItemType" => object.item_type = value.to_string(),
-            "Values" => {
-                if object.item_type == "container" {
-                    let mut values = value.split_whitespace();
-                    let _ignored = values.next();
-                    let flags = values.next().expect("Second value missing");
-
-                    let mut closable = false;
-                    let mut closed = false;
-                    let mut locked = false;
-
-                    for char in flags.chars() {
-                        match char {
-                            'A' => closable = true,
-                            'C' => closed = true,
-                            'D' => locked = true,
-                            _ => (),
-                        };
+            "Wear" => {
+                let mut unused = Vec::new();
+                for flag in value.split_whitespace() {
+                    match flag {
+                        "take" => object.take = true,
+                        "nosac" | "no_sac" => unused.push(flag),
+                        flag if object.wear.is_none() => object.wear = Some(flag.to_string()),
+                        flag => unused.push(flag),
                     }
+                }
+                keep_extra(&mut object.extras, key, &unused.join(" "));
+            }
+            "Values" if object.item_type == "container" => {
+                let mut values = value.split_whitespace();
+                let _ignored = values.next();
+                let flags = values
+                    .next()
+                    .ok_or_else(|| parser.error("Second value missing"))?;
+
+                let mut closable = false;
+                let mut closed = false;
+                let mut locked = false;
+
+                for char in flags.chars() {
+                    match char {
+                        'A' => closable = true,
+                        'C' => closed = true,
+                        'D' => locked = true,
+                        _ => (),
+                    };
+                }
 
-                    object.flags = ObjectFlags::Container {
-                        closable,
-                        closed,
-                        locked,
-                    }
+                object.flags = ObjectFlags::Container {
+                    closable,
+                    closed,
+                    locked,
+                }
+            }
+            "Values" if object.item_type == "light" => {
+                let values: Vec<&str> = value.split_whitespace().collect();
+                let number = |index: usize| {
+                    values
+                        .get(index)
+                        .and_then(|value| value.parse().ok())
+                        .unwrap_or(0)
+                };
+
+                object.flags = ObjectFlags::Light {
+                    capacity: number(0),
+                    hours: number(2),
+                }
+            }
+            "Values" if object.item_type == "fountain" || object.item_type == "drink_con" => {
+                // The liquid is quoted when it has several words, like 'red wine'
+                let mut liquid = value;
+                for _ in 0..2 {
+                    liquid = liquid
+                        .trim_start()
+                        .trim_start_matches(|c: char| !c.is_whitespace());
+                }
+                let liquid = liquid.trim_start();
+                let liquid = match liquid.strip_prefix('\'') {
+                    Some(quoted) => quoted.split('\'').next(),
+                    None => liquid.split_whitespace().next(),
+                };
+                object.flags = ObjectFlags::Liquid {
+                    liquid: liquid.unwrap_or("water").to_string(),
                 }
             }
             "ExtraDesc" => object.extra_descriptions.push(ExtraDescription {
-                keyword: value2.unwrap().to_string(),
+                keyword: value2.unwrap_or_default().to_string(),
                 description: value.to_string(),
             }),
-            _ => (),
+            // Values of other types of objects are kept as they are
+            _ => keep_extra(&mut object.extras, key, value),
         }
     }
 
-    object
+    Ok(object)
 }
 
-fn load_room_data(parser: &mut FileParser) -> Vec<Room> {
+fn load_room_data(parser: &mut FileParser) -> ParseResult<Vec<Room>> {
     let mut rooms = Vec::new();
 
     loop {
-        let vnum = parser.read_section().parse().unwrap();
+        let vnum = read_vnum_section(parser)?;
 
         if vnum == 0 {
             break;
         }
 
-        rooms.push(load_room(parser, vnum))
+        rooms.push(load_room(parser, vnum)?)
     }
 
-    rooms
+    Ok(rooms)
 }
 
-fn load_room(parser: &mut FileParser, vnum: usize) -> Room {
+fn load_room(parser: &mut FileParser, vnum: usize) -> ParseResult<Room> {
     let mut room = Room {
         vnum: Vnum(vnum),
         ..Default::default()
     };
 
     loop {
-        let key = parser.read_word();
+        let key = parser.read_word()?;
 
         if key != "End" && key != "END" {
-            parser.skip_one_space();
+            parser.skip_one_space()?;
         }
 
         let mut value2 = None;
 
         let value = match key {
             "END" | "End" => break,
-            "Name" | "Desc" | "RoomFlags" | "Sector" | "RoomEcho" | "EDesc" | "EFlags"
-            | "EKeywords" => parser.read_until_tilde(),
-            "Mana" | "Heal" | "LockerQuant" | "LockerInitRent" | "LockerOngoRent"
-            | "LockerWeight" | "LockerCapacity" | "LockerPickProof" | "Exit" | "EKeyvnum" => {
-                parser.read_until_newline()
-            }
             "ExtraDesc" => {
-                value2 = Some(parser.read_until_tilde());
-                parser.read_until_tilde()
+                value2 = Some(parser.read_until_tilde()?);
+                parser.read_until_tilde()?
             }
-            key => panic!("Unrecognized room data key: '{}'", key),
+            key if ROOM_TEXT_KEYS.contains(&key) => parser.read_until_tilde()?,
+            key if ROOM_LINE_KEYS.contains(&key) => parser.read_until_newline()?,
+            key => return Err(parser.error(&format!("Unrecognized room data key: '{}'", key))),
         };
 
+        // Exit details go with the exit before them
+        let no_exit = || parser.error(&format!("{} without an exit before it", key));
+
         match key {
             "Name" => room.name = value.to_string(),
             "Desc" => room.description = value.to_string(),
+            "NightDesc" => room.night_description = Some(value.to_string()),
             "Sector" => room.sector = value.to_string(),
+            "Heal" => room.heal_rate = value.trim().parse().ok().filter(|rate| *rate != 100),
+            "Mana" => room.mana_rate = value.trim().parse().ok().filter(|rate| *rate != 100),
+            "RoomFlags" => {
+                for flag in value.split_whitespace() {
+                    match flag {
+                        "pet_shop" => room.pet_shop = true,
+                        "stall" => room.stall = true,
+                        "no_mob" => room.no_mob = true,
+                        "no_recall" => room.no_recall = true,
+                        flag => room.flags.push(flag.to_string()),
+                    }
+                }
+            }
             "Exit" => {
                 let mut args = value.split_whitespace();
-                let name = args.next().unwrap();
-                let vnum = args.next().unwrap().parse().unwrap();
+                let (name, vnum) = match (args.next(), args.next()) {
+                    (Some(name), Some(vnum)) => (name, vnum),
+                    _ => return Err(parser.error("Expected an exit and its vnum")),
+                };
+                let vnum = check_vnum(parser, parser.parse(vnum, "exit vnum")?)?;
                 room.exits.push(Exit {
                     name: name.to_string(),
                     vnum: Vnum(vnum),
@@ -418,11 +785,11 @@ fn load_room(parser: &mut FileParser, vnum: usize) -> Room {
                 })
             }
             "EDesc" => {
-                let exit = room.exits.last_mut().unwrap();
+                let exit = room.exits.last_mut().ok_or_else(no_exit)?;
                 exit.description = Some(value.to_string());
             }
             "EFlags" => {
-                let exit = room.exits.last_mut().unwrap();
+                let exit = room.exits.last_mut().ok_or_else(no_exit)?;
 
                 for flag in value.split_whitespace() {
                     match flag {
@@ -436,148 +803,186 @@ fn load_room(parser: &mut FileParser, vnum: usize) -> Room {
             "EKeyvnum" => {
                 use std::convert::TryInto;
 
-                let exit = room.exits.last_mut().unwrap();
-                let vnum: i32 = value.parse().unwrap();
+                let exit = room.exits.last_mut().ok_or_else(no_exit)?;
+                let vnum: i32 = parser.parse(value, "key vnum")?;
                 // Skip it if it's -1
                 if let Ok(vnum) = vnum.try_into() {
                     exit.key = Some(Vnum(vnum));
                 }
             }
             "EKeywords" => {
-                let exit = room.exits.last_mut().unwrap();
+                let exit = room.exits.last_mut().ok_or_else(no_exit)?;
                 exit.extra_keywords = Some(value.to_string());
             }
             "ExtraDesc" => room.extra_descriptions.push(ExtraDescription {
-                keyword: value2.unwrap().to_string(),
+                keyword: value2.unwrap_or_default().to_string(),
                 description: value.to_string(),
             }),
+            "RoomEcho" | "LockerQuant" | "LockerInitRent" | "LockerOngoRent" | "LockerWeight"
+            | "LockerCapacity" | "LockerPickProof" => keep_extra(&mut room.extras, key, value),
             _ => (),
         }
     }
 
-    room
+    Ok(room)
 }
 
-fn skip_specials(parser: &mut FileParser) {
+/// Keep a key the game has no use for, unless its value is empty, so that
+/// saving the area writes it back.
+pub(super) fn keep_extra(extras: &mut Vec<(String, String)>, key: &str, value: &str) {
+    if !value.trim().is_empty() {
+        extras.push((key.to_string(), value.to_string()));
+    }
+}
+
+pub(super) fn skip_specials(parser: &mut FileParser) -> ParseResult<()> {
     loop {
-        let line = parser.read_until_newline();
+        let line = parser.read_until_newline()?;
         if line == "S" {
-            break;
+            return Ok(());
         }
     }
 }
 
-fn load_resets(parser: &mut FileParser) -> Vec<ResetCommand> {
+/// Read the first number of a mobile or object reset, which is always 0 in
+/// Dawn of Time; "day" or "night" instead make it spawn only then.
+fn read_reset_time(parser: &mut FileParser) -> ParseResult<Option<TimeOfDay>> {
+    match parser.read_word()? {
+        "0" => Ok(None),
+        "day" => Ok(Some(TimeOfDay::Day)),
+        "night" => Ok(Some(TimeOfDay::Night)),
+        word => Err(parser.error(&format!("Unknown reset time: {}", word))),
+    }
+}
+
+/// Read the next word of a reset as a number.
+pub(super) fn read_number<T: std::str::FromStr>(
+    parser: &mut FileParser,
+    what: &str,
+) -> ParseResult<T> {
+    let word = parser.read_word()?;
+    parser.parse(word, what)
+}
+
+fn read_reset_vnum(parser: &mut FileParser) -> ParseResult<Vnum> {
+    let vnum = read_number(parser, "vnum")?;
+    Ok(Vnum(check_vnum(parser, vnum)?))
+}
+
+/// Read the "0" that some resets start with.
+fn read_zero(parser: &mut FileParser) -> ParseResult<()> {
+    match parser.read_word()? {
+        "0" => Ok(()),
+        word => Err(parser.error(&format!("Expected 0, got {}", word))),
+    }
+}
+
+fn load_resets(parser: &mut FileParser) -> ParseResult<Vec<ResetCommand>> {
     let mut resets = Vec::new();
 
     loop {
-        let reset_type = parser.read_word();
+        let reset_type = parser.read_word()?;
 
         match reset_type {
             "S" => {
-                parser.skip_one_newline();
+                parser.skip_one_newline()?;
                 break;
             }
             "O" => {
-                let zero = parser.read_word();
-                let o_num = parser.read_word().parse().unwrap();
-                let global_limit = parser.read_word().parse().unwrap();
-                let r_num = parser.read_word().parse().unwrap();
-
-                assert_eq!(zero, "0");
+                let only_at = read_reset_time(parser)?;
+                let o_num = read_reset_vnum(parser)?;
+                let global_limit = read_number(parser, "limit")?;
+                let r_num = read_reset_vnum(parser)?;
 
                 resets.push(ResetCommand::Object {
-                    o_num: Vnum(o_num),
+                    o_num,
                     global_limit,
-                    r_num: Vnum(r_num),
+                    r_num,
+                    only_at,
                 })
             }
             "M" => {
-                let zero = parser.read_word();
-                let m_num = parser.read_word().parse().unwrap();
-                let global_limit = parser.read_word().parse().unwrap();
-                let r_num = parser.read_word().parse().unwrap();
-                let room_limit = parser.read_word().parse().unwrap();
-
-                assert_eq!(zero, "0");
+                let only_at = read_reset_time(parser)?;
+                let m_num = read_reset_vnum(parser)?;
+                let global_limit = read_number(parser, "limit")?;
+                let r_num = read_reset_vnum(parser)?;
+                let room_limit = read_number(parser, "room limit")?;
 
                 resets.push(ResetCommand::Mob {
-                    m_num: Vnum(m_num),
+                    m_num,
                     global_limit,
-                    r_num: Vnum(r_num),
+                    r_num,
                     room_limit,
+                    only_at,
                 })
             }
             "G" => {
-                let zero = parser.read_word();
-                let o_num = parser.read_word().parse().unwrap();
-                let global_limit = parser.read_word().parse().unwrap();
-
-                assert_eq!(zero, "0");
+                read_zero(parser)?;
+                let o_num = read_reset_vnum(parser)?;
+                let global_limit = read_number(parser, "limit")?;
 
                 resets.push(ResetCommand::Give {
-                    o_num: Vnum(o_num),
+                    o_num,
                     global_limit,
                 })
             }
             "E" => {
-                let zero = parser.read_word();
-                let o_num = parser.read_word().parse().unwrap();
-                let global_limit = parser.read_word().parse().unwrap();
-                let mut location = parser.read_word().to_string();
-
-                assert_eq!(location.pop(), Some('~'));
+                read_zero(parser)?;
+                let o_num = read_reset_vnum(parser)?;
+                let global_limit = read_number(parser, "limit")?;
+                let location = parser.read_word()?;
 
-                assert_eq!(zero, "0");
+                let location = location
+                    .strip_suffix('~')
+                    .ok_or_else(|| parser.error("Expected the location to end with '~'"))?
+                    .to_string();
 
                 resets.push(ResetCommand::Equip {
-                    o_num: Vnum(o_num),
+                    o_num,
                     global_limit,
                     location,
                 })
             }
             "P" => {
-                let zero = parser.read_word();
-                let o_num = parser.read_word().parse().unwrap();
-                let global_limit = parser.read_word().parse().unwrap();
-                let c_num = parser.read_word().parse().unwrap();
-                let container_limit = parser.read_word().parse().unwrap();
-
-                assert_eq!(zero, "0");
+                read_zero(parser)?;
+                let o_num = read_reset_vnum(parser)?;
+                let global_limit = read_number(parser, "limit")?;
+                let c_num = read_reset_vnum(parser)?;
+                let container_limit = read_number(parser, "container limit")?;
 
                 resets.push(ResetCommand::Put {
-                    o_num: Vnum(o_num),
+                    o_num,
                     global_limit,
-                    c_num: Vnum(c_num),
+                    c_num,
                     container_limit,
                 })
             }
             _ => {
-                parser.read_until_newline();
+                parser.read_until_newline()?;
             }
         }
     }
 
-    resets
+    Ok(resets)
 }
 
-fn load_shops(parser: &mut FileParser) -> Vec<Shop> {
+fn load_shops(parser: &mut FileParser) -> ParseResult<Vec<Shop>> {
     let mut shops = Vec::new();
 
     loop {
-        let vnum = parser.read_section().parse().unwrap();
+        let vnum = read_vnum_section(parser)?;
 
         if vnum == 0 {
             break;
         }
 
-        shops.push(load_shop(parser, vnum))
+        shops.push(load_shop(parser, vnum)?)
     }
 
-    shops
+    Ok(shops)
 }
 
-fn load_shop(parser: &mut FileParser, vnum: usize) -> Shop {
+fn load_shop(parser: &mut FileParser, vnum: usize) -> ParseResult<Shop> {
     let mut shop = Shop {
         vnum: Vnum(vnum),
         buy_types: Vec::new(),
@@ -586,94 +991,84 @@ fn load_shop(parser: &mut FileParser, vnum: usize) -> Shop {
         profit_sell: 100,
         open_hour: 0,
         close_hour: 24,
+        area: String::new(),
     };
 
     loop {
-        let key = parser.read_word();
+        let key = parser.read_word()?;
 
         match key {
-            "buy_type" => shop.buy_types.push(parser.read_until_tilde().to_string()),
-            "sell_type" => shop.buy_types.push(parser.read_until_tilde().to_string()),
+            "buy_type" => shop.buy_types.push(parser.read_until_tilde()?.to_string()),
+            "sell_type" => shop.sell_types.push(parser.read_until_tilde()?.to_string()),
             "open_hour" => {
-                shop.open_hour = parser
-                    .read_until_newline()
-                    .trim_start()
-                    .parse()
-                    .expect("Open hour")
+                let value = parser.read_until_newline()?;
+                shop.open_hour = parser.parse(value, "open hour")?
             }
             "close_hour" => {
-                shop.close_hour = parser
-                    .read_until_newline()
-                    .trim_start()
-                    .parse()
-                    .expect("Close hour")
+                let value = parser.read_until_newline()?;
+                shop.close_hour = parser.parse(value, "close hour")?
             }
             "profit_buy" => {
-                shop.profit_buy = parser
-                    .read_until_newline()
-                    .trim_start()
-                    .parse()
-                    .unwrap_or_else(|string| {
-                        parser.panic_on_line(&format!("Profit error for {}: '{}'", vnum, string))
-                    })
+                let value = parser.read_until_newline()?;
+                shop.profit_buy = parser.parse(value, &format!("buy profit for {}", vnum))?
             }
             "profit_sell" => {
-                shop.profit_sell = parser
-                    .read_until_newline()
-                    .trim_start()
-                    .parse()
-                    .expect("Profit sell")
+                let value = parser.read_until_newline()?;
+                shop.profit_sell = parser.parse(value, &format!("sell profit for {}", vnum))?
             }
             "END" => break,
-            key => parser.panic_on_line(&format!("Unknown shop key {}", key)),
+            key => return Err(parser.error(&format!("Unknown shop key {}", key))),
         }
     }
 
-    shop
+    Ok(shop)
 }
 
-fn load_mobprogs(parser: &mut FileParser) -> Vec<MobProg> {
+fn load_mobprogs(parser: &mut FileParser) -> ParseResult<Vec<MobProg>> {
     let mut mobprogs = Vec::new();
 
     loop {
-        let vnum = parser.read_section().parse().unwrap();
+        let vnum = read_vnum_section(parser)?;
 
         if vnum == 0 {
             break;
         }
 
-        mobprogs.push(load_mobprog(parser, vnum))
+        mobprogs.push(load_mobprog(parser, vnum)?)
     }
 
-    mobprogs
+    Ok(mobprogs)
 }
 
-fn load_mobprog(parser: &mut FileParser, vnum: usize) -> MobProg {
+fn load_mobprog(parser: &mut FileParser, vnum: usize) -> ParseResult<MobProg> {
     let mut title = None;
     let mut code = None;
     let mut disabled = None;
 
     loop {
-        let key = parser.read_word();
+        let key = parser.read_word()?;
 
         if key == "END" {
             break;
         }
 
-        parser.skip_one_space();
+        parser.skip_one_space()?;
 
         match key {
-            "title" => title = Some(parser.read_until_tilde().to_string()),
-            "code" => code = Some(parser.read_until_tilde().to_string()),
-            "disabled" => disabled = Some(parser.read_until_newline()),
-            key => parser.panic_on_line(&format!("Unknown mobprog key {}", key)),
+            "title" => title = Some(parser.read_until_tilde()?.to_string()),
+            "code" => code = Some(parser.read_until_tilde()?.to_string()),
+            "disabled" => disabled = Some(parser.read_until_newline()?),
+            key => return Err(parser.error(&format!("Unknown mobprog key {}", key))),
         }
     }
 
-    MobProg {
+    let disabled = disabled.ok_or_else(|| parser.error("Mobprog needs 'disabled'"))?;
+
+    Ok(MobProg {
         vnum: Vnum(vnum),
+        area: String::new(),
         title: title.unwrap_or_else(|| "<untitled>".to_string()),
         code: code.unwrap_or_else(|| "".to_string()),
-        disabled: disabled.expect("Needs disabled") != "true",
-    }
+        disabled: disabled == "true",
+    })
 }
@@ -0,0 +1,625 @@
+//! Player-to-player mail, sent and collected at a post office.
+//!
+//! A post office is any room with a postmaster (a mobile with the
+//! `postmaster` act flag). Letters are written like notes, with `mail write`,
+//! `mail +` and `mail send`, and wait in the recipient's mailbox until they
+//! pick them up at a post office.
+//!
+//! Letters can carry silver and objects. These are taken from the sender as
+//! soon as they are attached, and held in escrow until the recipient reads
+//! the letter. Objects are delivered as they were sent, e.g. a lantern with
+//! the oil it had, even after a restart; see `crate::escrow`.
+//!
+//! Mailboxes are saved to `data/mail.json` whenever they change.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agent::EntityAgent,
+    components::EntityComponentInfo,
+    echo,
+    entity::{EntityId, EntityWorld},
+    escrow::Escrowed,
+    files::Files,
+    find_entities::EntityIterator,
+    notes::format_age,
+    state::unix_time,
+};
+
+const MAIL_FILE: &str = "data/mail.json";
+
+pub(crate) struct PostOffice {
+    /// Letters waiting for each player, by lowercase player name
+    mailboxes: BTreeMap<String, Vec<Letter>>,
+    drafts: BTreeMap<String, Letter>,
+    changed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub(crate) struct Letter {
+    pub sender: String,
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    /// Seconds since the Unix epoch
+    pub date: u64,
+    pub read: bool,
+    /// Escrowed silver, delivered when the letter is read
+    pub silver: usize,
+    /// Escrowed objects, delivered when the letter is read
+    pub objects: Vec<Attachment>,
+}
+
+/// An object attached to a letter.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub(crate) enum Attachment {
+    Escrowed(Escrowed),
+    /// The vnum of an object attached before objects were kept whole, made
+    /// again from its template
+    Vnum(usize),
+}
+
+impl Attachment {
+    /// Give the object to someone. Templates can disappear if the areas
+    /// change between reboots, and then there is nothing to give.
+    pub fn release(&self, entity_world: &mut EntityWorld, to_entity: EntityId) {
+        match self {
+            Attachment::Escrowed(escrowed) => {
+                escrowed.release(entity_world, to_entity);
+            }
+            Attachment::Vnum(vnum) => {
+                entity_world.spawn_object(*vnum, to_entity);
+            }
+        }
+    }
+}
+
+impl Letter {
+    fn has_attachments(&self) -> bool {
+        self.silver != 0 || !self.objects.is_empty()
+    }
+}
+
+impl PostOffice {
+    pub fn load(files: &dyn Files) -> Self {
+        let mailboxes = match files.read_file_raw(MAIL_FILE) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .unwrap_or_else(|err| panic!("Could not parse {}: {}", MAIL_FILE, err)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => panic!("Could not read {}: {}", MAIL_FILE, err),
+        };
+
+        PostOffice {
+            mailboxes,
+            drafts: BTreeMap::new(),
+            changed: false,
+        }
+    }
+
+    pub fn save_changes(&mut self, files: &dyn Files) -> Result<(), std::io::Error> {
+        if self.changed {
+            let contents =
+                serde_json::to_vec_pretty(&self.mailboxes).expect("Mail should serialize");
+            files.write_file_raw(MAIL_FILE, &contents)?;
+            self.changed = false;
+        }
+
+        Ok(())
+    }
+
+    fn mailbox(&self, player: &str) -> &[Letter] {
+        self.mailboxes
+            .get(&player.to_lowercase())
+            .map(|letters| letters.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Throw away a deleted character's mailbox and unsent letter, and what
+    /// was attached to them.
+    pub fn forget_player(&mut self, player: &str, entity_world: &mut EntityWorld) {
+        let draft = self.drafts.remove(player);
+        let mailbox = self.mailboxes.remove(&player.to_lowercase());
+        if mailbox.is_some() {
+            self.changed = true;
+        }
+
+        for letter in draft.into_iter().chain(mailbox.into_iter().flatten()) {
+            for attachment in &letter.objects {
+                if let Attachment::Escrowed(escrowed) = attachment {
+                    escrowed.destroy(entity_world);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn deliver(&mut self, letter: Letter) {
+        self.mailboxes
+            .entry(letter.to.to_lowercase())
+            .or_default()
+            .push(letter);
+        self.changed = true;
+    }
+}
+
+// Mail commands
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_mail(&mut self, words: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can send and receive mail.\r\n");
+                return;
+            }
+        };
+
+        match words {
+            [] | ["list"] => self.do_mail_list(&player),
+            ["read", number] | [number] if number.parse::<usize>().is_ok() => {
+                self.do_mail_read(&player, number)
+            }
+            ["delete", number] => self.do_mail_delete(&player, number),
+            ["write", to, ref subject @ ..] => self.do_mail_write(&player, to, &subject.join(" ")),
+            ["subject", ref subject @ ..] if !subject.is_empty() => {
+                self.do_mail_subject(&player, &subject.join(" "))
+            }
+            ["+", ref line @ ..] => self.do_mail_add_line(&player, &line.join(" ")),
+            ["-"] => self.do_mail_remove_line(&player),
+            ["attach", amount, "silver"] | ["attach", amount, "coins"] => {
+                self.do_mail_attach_silver(&player, amount)
+            }
+            ["attach", object] => self.do_mail_attach(&player, object),
+            ["show"] => self.do_mail_show(&player),
+            ["clear"] => self.do_mail_clear(&player),
+            ["send"] => self.do_mail_send(&player),
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Wmail`^' to list your letters, '`Wmail read <number>`^', \
+                    '`Wmail delete <number>`^',\r\n\
+                    '`Wmail write <player> <subject>`^', '`Wmail + <text>`^', '`Wmail -`^', \
+                    '`Wmail attach <object>`^',\r\n\
+                    '`Wmail attach <amount> silver`^', '`Wmail show`^', '`Wmail clear`^', \
+                    '`Wmail send`^'.\r\n"
+                );
+            }
+        }
+    }
+
+    /// Tell a player about unread letters, e.g. when logging in.
+    pub fn echo_unread_mail(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player,
+            None => return,
+        };
+
+        let unread = self
+            .post_office
+            .mailbox(player)
+            .iter()
+            .filter(|letter| !letter.read)
+            .count();
+
+        if unread > 0 {
+            echo!(
+                self.players.info(&myself),
+                "You have `W{}`^ unread letter{} waiting at the post office.\r\n",
+                unread,
+                if unread == 1 { "" } else { "s" }
+            );
+        }
+    }
+
+    /// Find a postmaster in the room, or tell the player to go find one.
+    fn postmaster(&mut self) -> Option<EntityId> {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let postmaster = myself.room().mobiles().find(|mobile| {
+            mobile
+                .components()
+                .mobile
+                .as_ref()
+                .map(|mobile| mobile.postmaster)
+                .unwrap_or(false)
+        });
+
+        match postmaster {
+            Some(postmaster) => Some(postmaster.entity_id()),
+            None => {
+                echo!(
+                    self.info(),
+                    "You need to be at a post office, near a postmaster, to do that.\r\n"
+                );
+                None
+            }
+        }
+    }
+
+    fn mail_draft(&mut self, player: &str) -> Option<&mut Letter> {
+        if !self.post_office.drafts.contains_key(player) {
+            echo!(
+                self.info(),
+                "You aren't writing a letter; start one with '`Wmail write <player> <subject>`^'.\r\n"
+            );
+            return None;
+        }
+
+        self.post_office.drafts.get_mut(player)
+    }
+
+    /// Describe a letter's attachments, e.g. "50 silver coins, a sword".
+    fn describe_attachments(&self, letter: &Letter) -> String {
+        let mut attachments = Vec::new();
+
+        if letter.silver != 0 {
+            attachments.push(format!("{} silver coins", letter.silver));
+        }
+
+        for attachment in &letter.objects {
+            let vnum = match attachment {
+                Attachment::Escrowed(escrowed) => {
+                    attachments.push(escrowed.short_description().to_string());
+                    continue;
+                }
+                Attachment::Vnum(vnum) => *vnum,
+            };
+            let template = self.entity_world.templates.object(vnum);
+
+            attachments.push(match template {
+                Some((components, _extra_descriptions)) => {
+                    EntityComponentInfo::new(components, &self.entity_world.interner)
+                        .short_description()
+                        .to_string()
+                }
+                None => "something that got lost in the mail".to_string(),
+            });
+        }
+
+        attachments.join(", ")
+    }
+
+    /// Give a letter's attachments to someone, emptying the letter.
+    fn take_attachments(&mut self, letter: &mut Letter, to_entity: EntityId) {
+        if letter.silver != 0 {
+            self.add_silver(letter.silver, to_entity);
+            letter.silver = 0;
+        }
+
+        for attachment in letter.objects.drain(..) {
+            attachment.release(self.entity_world, to_entity);
+        }
+    }
+
+    fn do_mail_list(&mut self, player: &str) {
+        if self.postmaster().is_none() {
+            return;
+        }
+
+        let now = unix_time();
+        let mailbox = self.post_office.mailbox(player).to_vec();
+        let mut info = self.info();
+
+        if mailbox.is_empty() {
+            echo!(info, "There are no letters for you.\r\n");
+            return;
+        }
+
+        echo!(info, "Letters waiting for you:\r\n");
+        for (index, letter) in mailbox.iter().enumerate() {
+            echo!(
+                info,
+                "`W[{:>3}]`^{} `M{}`^: {} `S({}){}`^\r\n",
+                index + 1,
+                if letter.read { " " } else { "`G*`^" },
+                letter.sender,
+                letter.subject,
+                format_age(now.saturating_sub(letter.date)),
+                if letter.has_attachments() {
+                    ", with attachments"
+                } else {
+                    ""
+                },
+            );
+        }
+    }
+
+    fn do_mail_read(&mut self, player: &str, number: &str) {
+        if self.postmaster().is_none() {
+            return;
+        }
+
+        let mailbox_key = player.to_lowercase();
+        let index = number.parse::<usize>().unwrap_or(0).wrapping_sub(1);
+
+        let mut letter = match self
+            .post_office
+            .mailboxes
+            .get(&mailbox_key)
+            .and_then(|mailbox| mailbox.get(index))
+        {
+            Some(letter) => letter.clone(),
+            None => {
+                echo!(self.info(), "There is no letter with that number.\r\n");
+                return;
+            }
+        };
+
+        let now = unix_time();
+        let mut info = self.info();
+        echo!(
+            info,
+            "`W[{}] `M{}`W: {}`^\r\n",
+            index + 1,
+            letter.sender,
+            letter.subject
+        );
+        echo!(
+            info,
+            "`SSent {}.`^\r\n",
+            format_age(now.saturating_sub(letter.date))
+        );
+        echo!(info, "{}", letter.text);
+
+        if letter.has_attachments() {
+            let attachments = self.describe_attachments(&letter);
+            let postmaster_id = self.postmaster().expect("Checked above");
+            let myself = self.entity_world.entity_info(self.entity_id);
+            let postmaster = self.entity_world.entity_info(postmaster_id);
+            let mut act = self.players.act_with(&myself, &postmaster);
+            echo!(
+                act.myself(),
+                "$^$N hands you what came with the letter: {}.\r\n",
+                attachments
+            );
+            echo!(act.others(), "$^$N hands $n a parcel.\r\n");
+
+            self.take_attachments(&mut letter, self.entity_id);
+        }
+
+        letter.read = true;
+        if let Some(mailbox) = self.post_office.mailboxes.get_mut(&mailbox_key) {
+            mailbox[index] = letter;
+        }
+        self.post_office.changed = true;
+    }
+
+    fn do_mail_delete(&mut self, player: &str, number: &str) {
+        let mailbox_key = player.to_lowercase();
+        let index = number.parse::<usize>().unwrap_or(0).wrapping_sub(1);
+
+        let mailbox = match self.post_office.mailboxes.get_mut(&mailbox_key) {
+            Some(mailbox) if index < mailbox.len() => mailbox,
+            _ => {
+                echo!(self.info(), "There is no letter with that number.\r\n");
+                return;
+            }
+        };
+
+        if mailbox[index].has_attachments() {
+            echo!(
+                self.info(),
+                "That letter still has things attached; read it at a post office first.\r\n"
+            );
+            return;
+        }
+
+        mailbox.remove(index);
+        if mailbox.is_empty() {
+            self.post_office.mailboxes.remove(&mailbox_key);
+        }
+        self.post_office.changed = true;
+
+        echo!(self.info(), "Letter deleted.\r\n");
+    }
+
+    fn do_mail_write(&mut self, player: &str, to: &str, subject: &str) {
+        if let Some(draft) = self.post_office.drafts.get_mut(player) {
+            draft.to = to.to_string();
+            if !subject.is_empty() {
+                draft.subject = subject.to_string();
+            }
+            echo!(self.info(), "The letter is now addressed to {}.\r\n", to);
+            return;
+        }
+
+        self.post_office.drafts.insert(
+            player.to_string(),
+            Letter {
+                sender: player.to_string(),
+                to: to.to_string(),
+                subject: subject.to_string(),
+                ..Default::default()
+            },
+        );
+
+        echo!(
+            self.info(),
+            "You start writing a letter to {}. Use '`Wmail +`^' to add lines, '`Wmail attach`^'\r\n\
+            to include objects or silver, and '`Wmail send`^' at a post office to send it.\r\n",
+            to
+        );
+    }
+
+    fn do_mail_subject(&mut self, player: &str, subject: &str) {
+        if let Some(draft) = self.mail_draft(player) {
+            draft.subject = subject.to_string();
+            echo!(self.info(), "Subject set.\r\n");
+        }
+    }
+
+    fn do_mail_add_line(&mut self, player: &str, line: &str) {
+        if let Some(draft) = self.mail_draft(player) {
+            draft.text.push_str(line);
+            draft.text.push_str("\r\n");
+            echo!(self.info(), "Line added.\r\n");
+        }
+    }
+
+    fn do_mail_remove_line(&mut self, player: &str) {
+        if let Some(draft) = self.mail_draft(player) {
+            let without_newline = draft.text.trim_end_matches("\r\n");
+            let line_start = without_newline.rfind("\r\n").map(|end| end + 2);
+            let removed = line_start.is_some() || !without_newline.is_empty();
+            draft.text.truncate(line_start.unwrap_or(0));
+
+            if removed {
+                echo!(self.info(), "Last line removed.\r\n");
+            } else {
+                echo!(self.info(), "The letter has no lines to remove.\r\n");
+            }
+        }
+    }
+
+    fn do_mail_attach_silver(&mut self, player: &str, amount: &str) {
+        let amount = match amount.parse::<usize>() {
+            Ok(amount) if amount > 0 => amount,
+            _ => {
+                echo!(self.info(), "That's not a valid amount of silver.\r\n");
+                return;
+            }
+        };
+
+        if self.mail_draft(player).is_none() {
+            return;
+        }
+
+        if !self.remove_silver(amount, self.entity_id) {
+            echo!(self.info(), "You don't have that much silver.\r\n");
+            return;
+        }
+
+        let draft = self.mail_draft(player).expect("Checked above");
+        draft.silver += amount;
+        echo!(
+            self.info(),
+            "You attach {} silver coins to the letter.\r\n",
+            amount
+        );
+    }
+
+    fn do_mail_attach(&mut self, player: &str, object_name: &str) {
+        if self.mail_draft(player).is_none() {
+            return;
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let found = myself
+            .objects()
+            .filter_by_keyword(object_name)
+            .filter_or(
+                |e| e.components().wealth.is_none(),
+                "Use '`Wmail attach <amount> silver`^' to send silver.",
+            )
+            .filter_or(
+                |e| e.components().general.equipped.is_none(),
+                "You need to remove $N first.",
+            )
+            .filter_or(
+                |e| e.objects().next().is_none(),
+                "You need to empty $N first.",
+            )
+            .find_one_or("You aren't holding anything named like that.");
+
+        let object = match found {
+            Ok(object) => object,
+            Err(err) => return self.echo_error(err),
+        };
+
+        let mut act = self.players.act_with(&myself, &object);
+        echo!(
+            act.myself(),
+            "You wrap up $N and attach it to the letter.\r\n"
+        );
+        echo!(act.others(), "$^$n wraps up $N.\r\n");
+
+        let object_id = object.entity_id();
+        let escrowed = Escrowed::take(self.entity_world, object_id);
+
+        let draft = self.mail_draft(player).expect("Checked above");
+        draft.objects.push(Attachment::Escrowed(escrowed));
+    }
+
+    fn do_mail_show(&mut self, player: &str) {
+        if let Some(draft) = self.mail_draft(player) {
+            let draft = draft.clone();
+            let attachments = self.describe_attachments(&draft);
+            let mut info = self.info();
+            echo!(info, "`WFrom: `M{}`^\r\n", draft.sender);
+            echo!(info, "`WTo: `M{}`^\r\n", draft.to);
+            echo!(info, "`WSubject: `^{}\r\n", draft.subject);
+            if draft.has_attachments() {
+                echo!(info, "`WAttached: `^{}\r\n", attachments);
+            }
+            echo!(info, "{}", draft.text);
+        }
+    }
+
+    fn do_mail_clear(&mut self, player: &str) {
+        match self.post_office.drafts.remove(player) {
+            Some(mut draft) => {
+                if draft.has_attachments() {
+                    echo!(self.info(), "You take back what you attached to it.\r\n");
+                    self.take_attachments(&mut draft, self.entity_id);
+                }
+                echo!(self.info(), "Letter discarded.\r\n");
+            }
+            None => {
+                echo!(self.info(), "You aren't writing a letter.\r\n");
+            }
+        }
+    }
+
+    fn do_mail_send(&mut self, player: &str) {
+        let error = match self.post_office.drafts.get(player) {
+            None => Some(
+                "You aren't writing a letter; start one with '`Wmail write <player> <subject>`^'.",
+            ),
+            Some(draft) if draft.subject.is_empty() => {
+                Some("Your letter needs a subject; use '`Wmail subject <text>`^'.")
+            }
+            Some(draft) if draft.text.is_empty() && !draft.has_attachments() => {
+                Some("Your letter is empty; add lines with '`Wmail + <text>`^'.")
+            }
+            Some(_) => None,
+        };
+
+        if let Some(error) = error {
+            echo!(self.info(), "{}\r\n", error);
+            return;
+        }
+
+        let postmaster_id = match self.postmaster() {
+            Some(postmaster_id) => postmaster_id,
+            None => return,
+        };
+
+        let mut letter = self
+            .post_office
+            .drafts
+            .remove(player)
+            .expect("Checked above");
+        letter.date = unix_time();
+        let to = letter.to.clone();
+        self.post_office.deliver(letter);
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let postmaster = self.entity_world.entity_info(postmaster_id);
+        let mut act = self.players.act_with(&myself, &postmaster);
+        echo!(act.myself(), "You hand your letter for {} to $N.\r\n", to);
+        echo!(act.others(), "$^$n hands a letter to $N.\r\n");
+
+        if let Some(recipient_id) = self.entity_world.player_entity_id(&to) {
+            let recipient = self.entity_world.entity_info(recipient_id);
+            echo!(
+                self.players.info(&recipient),
+                "`WYou have new mail waiting at the post office.`^\r\n"
+            );
+        }
+    }
+}
@@ -28,10 +28,24 @@
 //!                                   +
 //! \-+---+---+---+---+---+---+---+---+---+---+---+---+-/
 //! ```
+//!
+//! Rooms with exits up or down show `^`, `v` or `%` for both, unless the
+//! player is in them. Exits that don't lead back the same way are drawn as
+//! arrows pointing where they go, or as their usual line when diagonal.
+//!
+//! Clients that asked for GMCP are also sent `Room.Info` for their mappers
+//! whenever they end up in another room, see `room_info()`.
 
 use std::ops::{Index, IndexMut};
 
-use crate::entity::{EntityId, EntityWorld};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    decolorize,
+    entity::{EntityId, EntityInfo, EntityWorld},
+    world::{opposite_direction, short_direction},
+    WorldState,
+};
 
 #[derive(Clone, Copy)]
 enum MapElement {
@@ -40,18 +54,34 @@ enum MapElement {
     Exit(u8),
 }
 
-const EXITS: &[(u8, i8, i8, &str)] = &[
-    // Character, row offset, column offset, name
-    (b'|', -1, 0, "north"),
-    (b'/', -1, 1, "northeast"),
-    (b'-', 0, 1, "east"),
-    (b'\\', 1, 1, "southeast"),
-    (b'|', 1, 0, "south"),
-    (b'/', 1, -1, "southwest"),
-    (b'-', 0, -1, "west"),
-    (b'\\', -1, -1, "northwest"),
+const EXITS: &[(u8, u8, i8, i8, &str)] = &[
+    // Character, one-way character, row offset, column offset, name
+    (b'|', b'^', -1, 0, "north"),
+    (b'/', b'/', -1, 1, "northeast"),
+    (b'-', b'>', 0, 1, "east"),
+    (b'\\', b'\\', 1, 1, "southeast"),
+    (b'|', b'v', 1, 0, "south"),
+    (b'/', b'/', 1, -1, "southwest"),
+    (b'-', b'<', 0, -1, "west"),
+    (b'\\', b'\\', -1, -1, "northwest"),
 ];
 
+const LEGEND: &str = "`B*`^ you  `B^`^ up  `Bv`^ down  `B%`^ up and down  \
+    + closed door  ^ > v < one way\r\n";
+
+/// Whether an exit has a way back from where it leads, by the opposite exit.
+fn leads_back(entity_world: &EntityWorld, room_id: EntityId, exit: &EntityInfo) -> bool {
+    let other_room = match exit.leads_to() {
+        Some(other_room) => entity_world.entity_info(other_room),
+        None => return false,
+    };
+    let back = opposite_direction(exit.main_keyword());
+
+    other_room.exits().any(|other_exit| {
+        other_exit.main_keyword() == back && other_exit.leads_to() == Some(room_id)
+    })
+}
+
 struct RoomMap {
     rooms: Vec<Option<EntityId>>,
     rows: usize,
@@ -78,7 +108,7 @@ impl RoomMap {
 
         let room = entity_world.entity_info(room_id);
 
-        for (_, row_offset, column_offset, dir_name) in EXITS {
+        for (_, _, row_offset, column_offset, dir_name) in EXITS {
             if let Some(exit) = room.exits().find(|e| e.main_keyword() == *dir_name) {
                 let other_room = match exit.leads_to() {
                     Some(other_room) => entity_world.entity_info(other_room),
@@ -171,22 +201,24 @@ pub(crate) fn make_map(entity_world: &EntityWorld, location: EntityId) -> String
                 "mountain" => b'S',
                 "cave" => b'S',
                 "swamp" => b'y',
-
-                sector => {
-                    dbg!(sector);
-                    b'w'
-                }
+                _ => b'w',
             };
 
+            let has_exit = |name| room.exits().any(|exit| exit.main_keyword() == name);
             let room_glyph = if column == mid_column && row == mid_row {
                 b'*'
             } else {
-                b' '
+                match (has_exit("up"), has_exit("down")) {
+                    (true, true) => b'%',
+                    (true, false) => b'^',
+                    (false, true) => b'v',
+                    (false, false) => b' ',
+                }
             };
 
             room_map[map_position] = MapElement::Room(color, room_glyph);
 
-            for (dir, row_offset, column_offset, dir_name) in EXITS {
+            for (dir, one_way, row_offset, column_offset, dir_name) in EXITS {
                 if let Some(exit_entity) = room.exits().find(|e| e.main_keyword() == *dir_name) {
                     let exit_position = map_position as isize
                         + (*row_offset as isize * map_columns as isize)
@@ -199,7 +231,13 @@ pub(crate) fn make_map(entity_world: &EntityWorld, location: EntityId) -> String
                         .map(|door| door.closed)
                         == Some(true);
 
-                    let symbol = if closed_door { b'+' } else { *dir };
+                    let symbol = if closed_door {
+                        b'+'
+                    } else if leads_back(entity_world, room_id, &exit_entity) {
+                        *dir
+                    } else {
+                        *one_way
+                    };
                     room_map[exit_position as usize] = MapElement::Exit(symbol);
                 }
             }
@@ -264,6 +302,46 @@ pub(crate) fn make_map(entity_world: &EntityWorld, location: EntityId) -> String
             "-+-"
         });
     }
+    map_string.push_str(LEGEND);
 
     map_string
 }
+
+impl WorldState {
+    /// GMCP `Room.Info` about the room a player is in, as sent to clients
+    /// for their mappers, with exits keyed by their short names.
+    pub fn room_info(&self, player: &str) -> Option<String> {
+        let player_id = self.entity_world.player_entity_id(player)?;
+        let room = self
+            .entity_world
+            .entity_info(self.entity_world.room_of(player_id));
+        let general = &room.components().general;
+
+        let mut exits = Map::new();
+        for exit in room.exits() {
+            let vnum = match exit.leads_to() {
+                Some(other_room) => {
+                    self.entity_world
+                        .entity_info(other_room)
+                        .components()
+                        .general
+                        .vnum
+                        .0
+                }
+                None => continue,
+            };
+            let name = short_direction(exit.main_keyword()).to_string();
+            exits.insert(name, Value::from(vnum));
+        }
+
+        let info = json!({
+            "num": general.vnum.0,
+            "name": decolorize(room.component_info().internal_title()).trim(),
+            "area": &*general.area,
+            "environment": general.sector.as_deref().unwrap_or("inside"),
+            "exits": exits,
+        });
+
+        Some(format!("Room.Info {}", info))
+    }
+}
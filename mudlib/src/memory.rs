@@ -0,0 +1,99 @@
+//! How much the world holds, for immortals planning how far it can grow.
+//!
+//! The `memory` command counts the entities of each type and from each area,
+//! the strings interned for their keywords and descriptions, and how many
+//! different descriptions they have, as entities spawned from the same
+//! template share them. Dead mobiles, and others waiting in limbo to come
+//! back, count too; destroyed entities are gone, and so are their strings
+//! once they are collected.
+//!
+//! Strings that nothing uses anymore, like the old names of renamed pets, are
+//! dropped from the interner once a game day, or right away with `memory
+//! collect`.
+
+use crate::{agent::EntityAgent, components::EntityType, echo, WorldState};
+
+/// Entity types, in the order they are listed
+const ENTITY_TYPES: &[(EntityType, &str)] = &[
+    (EntityType::Player, "players"),
+    (EntityType::Mobile, "mobiles"),
+    (EntityType::Object, "objects"),
+    (EntityType::Room, "rooms"),
+    (EntityType::Exit, "exits"),
+    (EntityType::ExtraDescription, "extra descriptions"),
+    (EntityType::MobProg, "mobprogs"),
+];
+
+/// Drop the strings nothing uses anymore.
+pub(crate) fn update_strings(world_state: &mut WorldState) {
+    let dropped = world_state.entity_world.collect_strings();
+    if dropped != 0 {
+        tracing::debug!("Dropped {} unused strings", dropped);
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_memory(&mut self, words: &[&str]) {
+        match words {
+            [] => (),
+            ["collect"] => {
+                let dropped = self.entity_world.collect_strings();
+                echo!(
+                    self.info(),
+                    "Dropped {} strings that nothing used anymore.\r\n",
+                    dropped
+                );
+                return;
+            }
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Wmemory`^' or '`Wmemory collect`^'.\r\n"
+                );
+                return;
+            }
+        }
+
+        let entity_world = &*self.entity_world;
+        let myself = entity_world.entity_info(self.entity_id);
+        let mut info = self.players.info(&myself);
+
+        let limbo = entity_world
+            .landmark("limbo")
+            .map(|limbo| entity_world.entity_info(limbo).contained_entities().count())
+            .unwrap_or(0);
+        echo!(
+            info,
+            "{} entities, {} of them waiting in limbo.\r\n",
+            entity_world.entity_count(),
+            limbo
+        );
+
+        let by_type = entity_world.entity_counts_by_type();
+        for (entity_type, name) in ENTITY_TYPES {
+            echo!(
+                info,
+                "  {:<20} {:>8}\r\n",
+                name,
+                by_type.get(entity_type).copied().unwrap_or(0)
+            );
+        }
+
+        let strings = entity_world.string_statistics();
+        echo!(
+            info,
+            "{} strings interned, {} of them unused, taking {} kB and saving {} kB; \
+            {} different descriptions.\r\n",
+            strings.strings,
+            strings.unused,
+            strings.bytes_retained / 1024,
+            strings.bytes_saved / 1024,
+            entity_world.distinct_descriptions()
+        );
+
+        echo!(info, "`W{:<20} {:>8}`^\r\n", "Area", "Entities");
+        for (area, count) in entity_world.entity_counts_by_area() {
+            echo!(info, "{:<20} {:>8}\r\n", area, count);
+        }
+    }
+}
@@ -1,10 +1,13 @@
 use crate::{
     acting::Acts,
     agent::EntityAgent,
+    clock::{MoonPhase, Season},
     commands::process_agent_command,
-    components::EntityComponentInfo,
+    components::{AttrValue, EntityComponentInfo},
     echo,
-    entity::{EntityId, Found},
+    entity::{EntityId, EntityInfo, Found},
+    set::is_attribute_name,
+    wizlog::LogKind,
     world::{MobProgTrigger, Vnum, VnumOrKeyword},
 };
 
@@ -36,6 +39,15 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             ["remember", target] => {
                 self.do_mob_remember(target);
             }
+            ["hunt", target] => {
+                self.do_mob_hunt(target);
+            }
+            ["forget"] => {
+                self.do_mob_forget();
+            }
+            ["walk", room] => {
+                self.do_mob_walk(room);
+            }
             ["rsay", ref message @ ..] => {
                 self.do_mob_rsay(&message.join(" "));
             }
@@ -46,12 +58,23 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
                 self.do_mob_vforce(target, command);
             }
             ["force", target, ref command @ ..] => {
-                // No difference from normal command
-                self.do_force(target, command);
+                self.do_mob_force(target, command);
             }
             ["silent", ref command @ ..] => {
                 self.do_mob_silent(command);
             }
+            ["setattr", target, name, ref value @ ..] if !value.is_empty() => {
+                self.do_mob_set_attribute(target, name, Some(&value.join(" ")));
+            }
+            ["delattr", target, name] => {
+                self.do_mob_set_attribute(target, name, None);
+            }
+            ["addattr", target, name, amount] => {
+                self.do_mob_add_attribute(target, name, amount);
+            }
+            ["damage", target, amount] => {
+                self.do_mob_damage(target, amount);
+            }
             ["mpfollow", target] => {
                 // No difference from normal command
                 self.do_follow(target);
@@ -66,7 +89,6 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
     }
 
     pub fn do_mob_transfer(&mut self, target_name: &str, to_room: &str) {
-        let myself = self.entity_world.entity_info(self.entity_id);
         let to_room_vnum: usize = match to_room.parse() {
             Ok(vnum) => vnum,
             Err(_) => {
@@ -79,15 +101,8 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        let room = self
-            .vnum_templates
-            .vnum_to_room_entity
-            .get(to_room_vnum)
-            .and_then(|permanent_id| *permanent_id)
-            .and_then(|permanent_id| self.entity_world.old_entity(&permanent_id));
-
-        let room_id = match room {
-            Some(entity) => entity.entity_id(),
+        let room_id = match self.room_with_vnum(to_room_vnum) {
+            Some(room_id) => room_id,
             None => {
                 echo!(
                     self.info(),
@@ -98,6 +113,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
+        let myself = self.entity_world.entity_info(self.entity_id);
         let target = myself.find_entity(target_name, |_| true);
 
         let target_id = match target {
@@ -114,9 +130,10 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
 
         let target = self.entity_world.entity_info(target_id);
         let mut act = self.players.act_with(&myself, &target);
-        echo!(act.myself(), "You teleport $N to another room.\r\n");
-        echo!(act.target(), "$^$n teleports you to another room.\r\n");
-        echo!(act.others(), "$^$n teleports $N to another room.\r\n");
+        echo!(
+            act.all(),
+            "$^$n $[teleport|teleports] $N to another room.\r\n"
+        );
 
         let from_room_id = target.room().entity_id();
 
@@ -131,7 +148,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
 
         // Also teleport followers.
         let mut agent = self.switch_agent(target_id);
-        agent.do_look();
+        agent.do_look_around();
         agent.check_followers(from_room_id, "void", room_id);
         agent.check_triggers_others(Action::Greet);
     }
@@ -151,7 +168,6 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
     }
 
     pub fn do_mob_at(&mut self, at_room: &str, commands: &[&str]) {
-        let myself = self.entity_world.entity_info(self.entity_id);
         let at_room_vnum: usize = match at_room.parse() {
             Ok(vnum) => vnum,
             Err(_) => {
@@ -160,15 +176,8 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        let room = self
-            .vnum_templates
-            .vnum_to_room_entity
-            .get(at_room_vnum)
-            .and_then(|permanent_id| *permanent_id)
-            .and_then(|permanent_id| self.entity_world.old_entity(&permanent_id));
-
-        let room_id = match room {
-            Some(entity) => entity.entity_id(),
+        let room_id = match self.room_with_vnum(at_room_vnum) {
+            Some(room_id) => room_id,
             None => {
                 echo!(
                     self.info(),
@@ -179,7 +188,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        let original_room_id = myself.room().entity_id();
+        let original_room_id = self.entity_world.room_of(self.entity_id);
 
         self.entity_world.move_entity(self.entity_id, room_id);
         process_agent_command(self, commands);
@@ -196,15 +205,8 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        let room = self
-            .vnum_templates
-            .vnum_to_room_entity
-            .get(to_room_vnum)
-            .and_then(|permanent_id| *permanent_id)
-            .and_then(|permanent_id| self.entity_world.old_entity(&permanent_id));
-
-        let room = match room {
-            Some(room) => room,
+        let room_id = match self.room_with_vnum(to_room_vnum) {
+            Some(room_id) => room_id,
             None => {
                 echo!(
                     self.info(),
@@ -215,7 +217,6 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        let room_id = room.entity_id();
         self.entity_world.move_entity(self.entity_id, room_id);
 
         echo!(self.info(), "You moved to room v{}.\r\n", to_room);
@@ -231,14 +232,9 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        let mobile_components = self
-            .vnum_templates
-            .mobile_components
-            .get(m_vnum)
-            .and_then(|components| components.as_ref());
-
-        let (mobile_components, mobprogs) = match mobile_components {
-            Some(components) => components,
+        let room_id = myself.room().entity_id();
+        let mobile_id = match self.entity_world.spawn_mobile(m_vnum, room_id) {
+            Some(mobile_id) => mobile_id,
             None => {
                 echo!(
                     self.info(),
@@ -249,15 +245,6 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        let room_id = myself.room().entity_id();
-
-        let mobile_id = self
-            .entity_world
-            .insert_entity(room_id, mobile_components.clone());
-        for mobprog in mobprogs {
-            self.entity_world.insert_entity(mobile_id, mobprog.clone());
-        }
-
         let myself = self.entity_world.entity_info(self.entity_id);
         let mobile = self.entity_world.entity_info(mobile_id);
         let mut act = self.players.act_with(&mobile, &myself);
@@ -285,14 +272,8 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        let object_components = self
-            .vnum_templates
-            .object_components
-            .get(o_vnum)
-            .and_then(|components| components.as_ref());
-
-        let (object_components, extra_descriptions) = match object_components {
-            Some(components) => components,
+        let object_id = match self.entity_world.spawn_object(o_vnum, self.entity_id) {
+            Some(object_id) => object_id,
             None => {
                 echo!(
                     self.info(),
@@ -303,14 +284,6 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        let object_id = self
-            .entity_world
-            .insert_entity(self.entity_id, object_components.clone());
-        for extra_description_components in extra_descriptions {
-            self.entity_world
-                .insert_entity(object_id, extra_description_components.clone());
-        }
-
         let myself = self.entity_world.entity_info(self.entity_id);
         let object = self.entity_world.entity_info(object_id);
         let mut act = self.players.act_with(&myself, &object);
@@ -341,7 +314,8 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         };
 
         let mobprog = self
-            .vnum_templates
+            .entity_world
+            .templates
             .vnum_to_mobprog
             .get(p_vnum)
             .and_then(|mobprog| mobprog.as_ref());
@@ -358,7 +332,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        self.run_mobprog(code, target.to_string());
+        self.run_mobprog(&code, target.to_string());
     }
 
     pub fn do_mob_remember(&mut self, target: &str) {
@@ -382,6 +356,79 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         }
     }
 
+    /// Tag something in the room, or the mobile itself as $i, with an
+    /// attribute, or remove it without a value.
+    pub fn do_mob_set_attribute(&mut self, target: &str, name: &str, value: Option<&str>) {
+        if !is_attribute_name(name) {
+            echo!(self.info(), "'{}' is not a valid attribute name.\r\n", name);
+            return;
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let target_id = match attribute_target(&myself, target) {
+            Some(target) => target.entity_id(),
+            None => {
+                echo!(self.info(), "I don't see anything here by that name.\r\n");
+                return;
+            }
+        };
+
+        let (mut target, interner) = self.entity_world.entity_info_mut_with_interner(target_id);
+        target.set_attribute(interner, name, value.map(AttrValue::parse));
+    }
+
+    /// Add to a counter attribute, which starts from 0.
+    pub fn do_mob_add_attribute(&mut self, target: &str, name: &str, amount: &str) {
+        let amount: i64 = match amount.parse() {
+            Ok(amount) => amount,
+            Err(_) => {
+                echo!(self.info(), "'{}' is not a number to add.\r\n", amount);
+                return;
+            }
+        };
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let counter = attribute_target(&myself, target)
+            .and_then(|target| target.component_info().attribute(name).cloned())
+            .map(|value| value.number())
+            .unwrap_or(0);
+
+        let value = counter.saturating_add(amount).to_string();
+        self.do_mob_set_attribute(target, name, Some(&value));
+    }
+
+    /// Hit someone in the room for some hit points, which wears down the
+    /// mobile's weapon and their armor. A hit leaves at least 1 hit point;
+    /// how a fight ends is up to the mobprog.
+    pub fn do_mob_damage(&mut self, target: &str, amount: &str) {
+        let amount: u32 = match amount.parse() {
+            Ok(amount) => amount,
+            Err(_) => {
+                echo!(
+                    self.info(),
+                    "'{}' is not a number of hit points.\r\n",
+                    amount
+                );
+                return;
+            }
+        };
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let target_id = match myself.find_entity(target, |e| e.is_mobile() || e.is_player()) {
+            Found::Other(target) => target.entity_id(),
+            _ => {
+                echo!(self.info(), "I don't see anyone here by that name.\r\n");
+                return;
+            }
+        };
+
+        let mut target = self.entity_world.entity_info_mut(target_id);
+        if let Some(vitals) = &mut target.components().vitals {
+            vitals.hit = vitals.hit.saturating_sub(amount).max(1);
+        }
+        self.wear_from_hit(target_id);
+    }
+
     pub fn do_mob_rsay(&mut self, message: &str) {
         let myself = self.entity_world.entity_info(self.entity_id);
 
@@ -428,13 +475,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             }
         };
 
-        let mobile_components = self
-            .vnum_templates
-            .mobile_components
-            .get(m_vnum)
-            .and_then(|components| components.as_ref());
-
-        if let Some((mobile_components, _mobprogs)) = mobile_components {
+        if let Some((mobile_components, _mobprogs)) = self.entity_world.templates.mobile(m_vnum) {
             let component_info =
                 EntityComponentInfo::new(mobile_components, &self.entity_world.interner);
             let keyword = component_info.keyword();
@@ -453,17 +494,85 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         }
     }
 
+    /// Make someone run a command right away, unlike the normal `force`.
+    pub fn do_mob_force(&mut self, target_name: &str, words: &[&str]) {
+        // Others aren't checked for trust when forced, so check it here
+        if let Some(command) = words.first() {
+            if !self.trusted_with(command) {
+                echo!(self.info(), "You can't make anyone do that.\r\n");
+                return;
+            }
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let target = myself.find_entity(target_name, |entity| {
+            // Prefer objects over their descriptions, and others over self
+            !entity.is_extra_description() && entity.entity_id() != myself.entity_id()
+        });
+
+        let target = match target {
+            Found::Myself | Found::WrongSelf => {
+                let mut act = self.players.act_alone(&myself);
+                echo!(act.all(), "$^$n $[snap|snaps] $s fingers at $mself.\r\n");
+                myself
+            }
+            Found::Other(other) | Found::WrongOther(other) => {
+                // Players can only be forced by those with more trust
+                if let (Some(me), Some(them)) = (myself.player_name(), other.player_name()) {
+                    if self.accounts.trust(them) >= self.accounts.trust(me) {
+                        let mut act = self.players.act_with(&myself, &other);
+                        echo!(act.myself(), "$^$N is too powerful for you to force.\r\n");
+                        return;
+                    }
+                }
+
+                let mut act = self.players.act_with(&myself, &other);
+                echo!(act.all(), "$^$n $[snap|snaps] $s fingers at $N.\r\n");
+                other
+            }
+            Found::Nothing => {
+                echo!(
+                    self.info(),
+                    "You don't see anything named {} in the room.\r\n",
+                    target_name
+                );
+                return;
+            }
+        };
+
+        let mut info = self.players.info(&target);
+        echo!(info, "You feel compelled to: {:?}\r\n", words);
+
+        let target_id = target.entity_id();
+
+        let mut agent = self.switch_agent(target_id);
+
+        if !process_agent_command(&mut agent, words) {
+            let myself = self.entity_world.entity_info(self.entity_id);
+            let target = self.entity_world.entity_info(target_id);
+            let mut act = self.players.act_with(&myself, &target);
+            echo!(
+                act.myself(),
+                "$^$E didn't quite understand your command.\r\n"
+            );
+            echo!(
+                act.target(),
+                "You tried to do it, but you didn't quite understand what $e meant.\r\n"
+            );
+            echo!(
+                act.others(),
+                "$^$n tries to do something, but stops with a confused look.\r\n"
+            );
+        }
+    }
+
     pub fn do_mob_silent(&mut self, command: &[&str]) {
         // It has to suffice for now...
         let myself = self.entity_world.entity_info(self.entity_id);
         let mut act = self.players.act_alone(&myself);
         echo!(
-            act.myself(),
-            "You pretend to do the next thing silently.\r\n"
-        );
-        echo!(
-            act.others(),
-            "$^$n pretends to do $s next action silently.\r\n"
+            act.all(),
+            "$^$n $[pretend|pretends] to do $s next action silently.\r\n"
         );
 
         process_agent_command(self, command);
@@ -488,6 +597,9 @@ pub(crate) enum Action<'a> {
 
     /// You gave an object to someone
     Give { object_id: EntityId },
+
+    /// A new hour started on the game clock
+    Hour { hour: u8 },
 }
 
 impl<'e, 'p> EntityAgent<'e, 'p> {
@@ -496,12 +608,18 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         let myself = self.entity_world.entity_info(self.entity_id);
         for item in myself.contained_entities() {
             if let Some(mobprog) = &item.components().mobprog {
-                if let (Action::Entry, MobProgTrigger::Entry { chance }) =
-                    (&action, &mobprog.trigger)
-                {
-                    if random_percent(*chance) {
+                match (&action, &mobprog.trigger) {
+                    (Action::Entry, MobProgTrigger::Entry { chance })
+                        if self.rng.percent(*chance) =>
+                    {
+                        triggered.push(mobprog.code.clone());
+                    }
+                    (Action::Hour { hour }, MobProgTrigger::Hour { hour: at_hour })
+                        if hour == at_hour =>
+                    {
                         triggered.push(mobprog.code.clone());
                     }
+                    _ => (),
                 }
             }
         }
@@ -515,7 +633,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
         for code in triggered {
             // TODO: Maybe running with this target isn't how it's supposed
             // to work?
-            self.run_mobprog(code, self_keyword.clone());
+            self.run_mobprog(&code, self_keyword.clone());
         }
     }
 
@@ -553,7 +671,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
 
         for code in triggered {
             let mut agent = self.switch_agent(target_id);
-            agent.run_mobprog(code, self_keyword.clone());
+            agent.run_mobprog(&code, self_keyword.clone());
         }
     }
 
@@ -569,28 +687,26 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
             for item in entity.contained_entities() {
                 if let Some(mobprog) = &item.components().mobprog {
                     match (&action, &mobprog.trigger) {
-                        (Action::Speech { message }, MobProgTrigger::Speech { pattern }) => {
-                            if message.contains(pattern) {
-                                triggered.push((entity.entity_id(), mobprog.code.clone()));
-                            }
+                        (Action::Speech { message }, MobProgTrigger::Speech { pattern })
+                            if message.contains(pattern) =>
+                        {
+                            triggered.push((entity.entity_id(), mobprog.code.clone()));
                         }
-                        (Action::Greet, MobProgTrigger::Greet { chance }) => {
-                            if random_percent(*chance) {
-                                triggered.push((entity.entity_id(), mobprog.code.clone()));
-                            }
+                        (Action::Greet, MobProgTrigger::Greet { chance })
+                            if self.rng.percent(*chance) =>
+                        {
+                            triggered.push((entity.entity_id(), mobprog.code.clone()));
                         }
                         (
                             Action::Exit { direction: dir1 },
                             MobProgTrigger::Exit { direction: dir2 },
-                        ) => {
-                            if dir1 == dir2 {
-                                triggered.push((entity.entity_id(), mobprog.code.clone()));
-                            }
+                        ) if dir1 == dir2 => {
+                            triggered.push((entity.entity_id(), mobprog.code.clone()));
                         }
-                        (Action::Entry, MobProgTrigger::Entry { chance }) => {
-                            if random_percent(*chance) {
-                                triggered.push((entity.entity_id(), mobprog.code.clone()));
-                            }
+                        (Action::Entry, MobProgTrigger::Entry { chance })
+                            if self.rng.percent(*chance) =>
+                        {
+                            triggered.push((entity.entity_id(), mobprog.code.clone()));
                         }
                         (Action::Login, MobProgTrigger::LoginRoom) => {
                             triggered.push((entity.entity_id(), mobprog.code.clone()));
@@ -609,7 +725,7 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
 
         for (entity_id, code) in triggered {
             let mut agent = self.switch_agent(entity_id);
-            agent.run_mobprog(code, self_keyword.clone());
+            agent.run_mobprog(&code, self_keyword.clone());
         }
     }
 
@@ -654,11 +770,20 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
 
         for (entity_id, code) in triggered {
             let mut agent = self.switch_agent(entity_id);
-            agent.run_mobprog(code, self_keyword.clone());
+            agent.run_mobprog(&code, self_keyword.clone());
         }
     }
 
-    pub fn run_mobprog(&mut self, code: String, target: String) {
+    pub fn run_mobprog(&mut self, code: &str, target: String) {
+        let vnum = self
+            .entity_world
+            .entity_info(self.entity_id)
+            .components()
+            .general
+            .vnum
+            .0;
+        let _mobprog = tracing::info_span!("mobprog", name = vnum).entered();
+
         let mut accept_commands = true;
 
         for command in code.lines() {
@@ -674,119 +799,286 @@ impl<'e, 'p> EntityAgent<'e, 'p> {
 
             let command = command.replace("$q", remembered);
 
-            if command.trim_start().starts_with("**") {
-                continue;
+            match parse_mobprog_line(&command) {
+                Ok(MobProgLine::Comment) => (),
+                Ok(MobProgLine::If(condition)) => {
+                    accept_commands = self.check_condition(&condition);
+                }
+                // Conditions that can't be understood are never true
+                Err(err) => {
+                    accept_commands = false;
+                    self.log_mobprog_error(&err);
+                }
+                Ok(MobProgLine::Else) => accept_commands = !accept_commands,
+                Ok(MobProgLine::EndIf) => accept_commands = true,
+                Ok(MobProgLine::End) if accept_commands => break,
+                Ok(MobProgLine::Command(words)) if accept_commands => {
+                    let known = process_agent_command(self, &words);
+                    if !known {
+                        let problem = format!("Unknown command '{}'.", words.join(" "));
+                        self.log_mobprog_error(&problem);
+                    }
+                }
+                _ => (),
+            };
+        }
+    }
+
+    fn log_mobprog_error(&mut self, problem: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let message = format!(
+            "Mobprog of {} (vnum {}) failed: {}",
+            myself,
+            myself.components().general.vnum.0,
+            problem
+        );
+        self.log_to_immortals(LogKind::Script, &message);
+    }
+
+    fn check_condition(&self, condition: &Condition<'_>) -> bool {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let remembered = myself
+            .components()
+            .mobile
+            .as_ref()
+            .and_then(|mobile| mobile.remember.as_deref())
+            .unwrap_or("nobody");
+
+        match condition {
+            Condition::InRoom(vnum) => *vnum == myself.room().components().general.vnum,
+            Condition::ObjectHere(object) => myself.room().objects().any(|item| match object {
+                VnumOrKeyword::Vnum(vnum) => *vnum == item.components().general.vnum,
+                VnumOrKeyword::Keyword(keyword) => item
+                    .component_info()
+                    .keyword()
+                    .split_whitespace()
+                    .any(|word| word == keyword),
+            }),
+            Condition::Carries { target, object } => {
+                let target = myself.find_entity(target, |e| e.is_mobile() || e.is_player());
+                let target = match target {
+                    Found::Myself => Some(myself),
+                    Found::Other(other) => Some(other),
+                    Found::WrongSelf | Found::WrongOther(_) | Found::Nothing => None,
+                };
+
+                if let Some(target) = target {
+                    target.objects().any(|o| {
+                        o.component_info()
+                            .keyword()
+                            .split_whitespace()
+                            .any(|word| word == *object)
+                    })
+                } else {
+                    false
+                }
+            }
+            // FIXME: Wrong, but, I don't know how it can be one, ever
+            Condition::IsNpc => true,
+            Condition::Attribute {
+                target,
+                name,
+                comparison,
+                value,
+            } => {
+                let actual = attribute_target(&myself, target)
+                    .and_then(|target| target.component_info().attribute(name).cloned())
+                    .unwrap_or(AttrValue::Number(0));
+                match comparison {
+                    Comparison::Equal => actual == *value,
+                    Comparison::NotEqual => actual != *value,
+                    Comparison::Less => actual.number() < value.number(),
+                    Comparison::LessOrEqual => actual.number() <= value.number(),
+                    Comparison::Greater => actual.number() > value.number(),
+                    Comparison::GreaterOrEqual => actual.number() >= value.number(),
+                }
             }
+            Condition::IsTarget(target) => *target == remembered,
+            Condition::Season(season) => self.clock.now().season() == *season,
+            Condition::Moon(phase) => self.clock.now().moon_phase() == *phase,
+            Condition::Not(condition) => !self.check_condition(condition),
+        }
+    }
+}
 
-            let words: Vec<_> = command.split_whitespace().collect();
+/// A line of mobprog code, as understood by `run_mobprog()`.
+enum MobProgLine<'a> {
+    /// Lines starting with "**"
+    Comment,
+    If(Condition<'a>),
+    Else,
+    EndIf,
+    End,
+    Command(Vec<&'a str>),
+}
 
-            let remembered = myself
-                .components()
-                .mobile
-                .as_ref()
-                .and_then(|mobile| mobile.remember.as_deref())
-                .unwrap_or("nobody");
+enum Condition<'a> {
+    /// The mobile is in the room with this vnum
+    InRoom(Vnum),
+    /// An object with this vnum or keyword is in the mobile's room
+    ObjectHere(VnumOrKeyword),
+    /// Someone in the room carries an object with this keyword
+    Carries {
+        target: &'a str,
+        object: &'a str,
+    },
+    IsNpc,
+    /// An attribute of something in the room compares to a value; missing
+    /// attributes count as 0
+    Attribute {
+        target: &'a str,
+        name: &'a str,
+        comparison: Comparison,
+        value: AttrValue,
+    },
+    /// The mobile remembers someone with this name
+    IsTarget(&'a str),
+    /// It is this season on the game clock
+    Season(Season),
+    /// The moon is in this phase
+    Moon(MoonPhase),
+    /// Written with a '!' in front of a condition, e.g. "!carries $n key"
+    Not(Box<Condition<'a>>),
+}
 
-            match words[..] {
-                ["if", ref condition @ ..] => {
-                    accept_commands = match *condition {
-                        ["room", target, "==", vnum] => {
-                            assert!(
-                                ["$i", "$I"].contains(&target),
-                                "Don't know how to handle other targets"
-                            );
+/// How the attribute of an "if attr" condition compares to its value; all
+/// but equality compare numbers
+#[derive(Clone, Copy)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
 
-                            let vnum: usize = vnum.parse().expect("Invalid vnum in mobprog");
+/// Something in the room that attribute commands and conditions are about,
+/// or the mobile itself as $i.
+fn attribute_target<'e>(myself: &EntityInfo<'e>, target: &str) -> Option<EntityInfo<'e>> {
+    if ["$i", "$I"].contains(&target) {
+        return Some(myself.clone());
+    }
 
-                            let myself = self.entity_world.entity_info(self.entity_id);
-                            let room = myself.room();
+    match myself.find_entity(target, |_| true) {
+        Found::Myself | Found::WrongSelf => Some(myself.clone()),
+        Found::Other(other) | Found::WrongOther(other) => Some(other),
+        Found::Nothing => None,
+    }
+}
 
-                            vnum == room.components().general.vnum.0
-                        }
-                        ["objhere", vnum] => {
-                            let objname = match vnum.parse() {
-                                Ok(vnum) => VnumOrKeyword::Vnum(Vnum(vnum)),
-                                Err(_) => VnumOrKeyword::Keyword(vnum.to_string()),
-                            };
-
-                            let mut found = false;
-
-                            for item in myself.room().objects() {
-                                let matches_item = match objname {
-                                    VnumOrKeyword::Vnum(vnum) => {
-                                        vnum == item.components().general.vnum
-                                    }
-                                    VnumOrKeyword::Keyword(ref keyword) => item
-                                        .component_info()
-                                        .keyword()
-                                        .split_whitespace()
-                                        .any(|word| word == keyword),
-                                };
-                                found = found || matches_item;
-                            }
-
-                            found
-                        }
-                        ["carries", target, object] => {
-                            let target =
-                                myself.find_entity(target, |e| e.is_mobile() || e.is_player());
-                            let target = match target {
-                                Found::Myself => Some(myself),
-                                Found::Other(other) => Some(other),
-                                Found::WrongSelf | Found::WrongOther(_) | Found::Nothing => None,
-                            };
-
-                            if let Some(target) = target {
-                                target.objects().any(|o| {
-                                    o.component_info()
-                                        .keyword()
-                                        .split_whitespace()
-                                        .any(|word| word == object)
-                                })
-                            } else {
-                                false
-                            }
-                        }
-                        // Silly, but temporary until I get a check_condition function
-                        ["!carries", target, object] => {
-                            let target =
-                                myself.find_entity(target, |e| e.is_mobile() || e.is_player());
-                            let target = match target {
-                                Found::Myself => Some(myself),
-                                Found::Other(other) => Some(other),
-                                Found::WrongSelf | Found::WrongOther(_) | Found::Nothing => None,
-                            };
-
-                            !if let Some(target) = target {
-                                target.objects().any(|o| {
-                                    o.component_info()
-                                        .keyword()
-                                        .split_whitespace()
-                                        .any(|word| word == object)
-                                })
-                            } else {
-                                false
-                            }
-                        }
-                        // FIXME: Wrong, but, I don't know how it can be one, ever
-                        ["isnpc", _target] => true,
-                        ["istarget", target] => target == remembered,
-                        ["!istarget", target] => target != remembered,
-                        _ => false,
-                    };
-                }
-                ["else"] => accept_commands = !accept_commands,
-                ["endif"] => accept_commands = true,
-                ["end"] if accept_commands => break,
-                ref command if accept_commands => {
-                    process_agent_command(self, command);
-                }
-                _ => (),
+fn parse_mobprog_line(line: &str) -> Result<MobProgLine<'_>, String> {
+    if line.trim_start().starts_with("**") {
+        return Ok(MobProgLine::Comment);
+    }
+
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    Ok(match words[..] {
+        ["if", ref condition @ ..] => MobProgLine::If(parse_condition(condition)?),
+        ["else"] => MobProgLine::Else,
+        ["endif"] => MobProgLine::EndIf,
+        ["end"] => MobProgLine::End,
+        _ => MobProgLine::Command(words),
+    })
+}
+
+fn parse_condition<'a>(words: &[&'a str]) -> Result<Condition<'a>, String> {
+    if let [first, ref rest @ ..] = words {
+        if let Some(condition) = first.strip_prefix('!') {
+            let mut words = vec![condition];
+            words.extend_from_slice(rest);
+            return Ok(Condition::Not(Box::new(parse_condition(&words)?)));
+        }
+    }
+
+    match *words {
+        ["room", target, "==", vnum] => {
+            if !["$i", "$I"].contains(&target) {
+                return Err(format!(
+                    "Only the room of $i can be checked, not {}.",
+                    target
+                ));
+            }
+
+            match vnum.parse() {
+                Ok(vnum) => Ok(Condition::InRoom(Vnum(vnum))),
+                Err(_) => Err(format!("'{}' is not a valid vnum.", vnum)),
+            }
+        }
+        ["objhere", object] => Ok(Condition::ObjectHere(match object.parse() {
+            Ok(vnum) => VnumOrKeyword::Vnum(Vnum(vnum)),
+            Err(_) => VnumOrKeyword::Keyword(object.to_string()),
+        })),
+        ["carries", target, object] => Ok(Condition::Carries { target, object }),
+        ["isnpc", _target] => Ok(Condition::IsNpc),
+        ["attr", target, name, comparison, value] => {
+            let comparison = match comparison {
+                "==" => Comparison::Equal,
+                "!=" => Comparison::NotEqual,
+                "<" => Comparison::Less,
+                "<=" => Comparison::LessOrEqual,
+                ">" => Comparison::Greater,
+                ">=" => Comparison::GreaterOrEqual,
+                _ => return Err(format!("'{}' is not a comparison.", comparison)),
             };
+            let value = AttrValue::parse(value);
+            if !matches!(comparison, Comparison::Equal | Comparison::NotEqual)
+                && !matches!(value, AttrValue::Number(_))
+            {
+                return Err(format!("'{}' is not a number to compare.", value));
+            }
+
+            Ok(Condition::Attribute {
+                target,
+                name,
+                comparison,
+                value,
+            })
         }
+        ["istarget", target] => Ok(Condition::IsTarget(target)),
+        ["season", season] => match Season::from_name(season) {
+            Some(season) => Ok(Condition::Season(season)),
+            None => Err(format!("'{}' is not a season.", season)),
+        },
+        ["moon", phase] => match MoonPhase::from_name(phase) {
+            Some(phase) => Ok(Condition::Moon(phase)),
+            None => Err(format!("'{}' is not a phase of the moon.", phase)),
+        },
+        _ => Err(format!("Unknown condition '{}'.", words.join(" "))),
     }
 }
 
-fn random_percent(chance: u8) -> bool {
-    rand::random::<u32>() % 100 < chance.into()
+/// Check that `run_mobprog()` understands all of a mobprog's code, returning
+/// the first problem found.
+pub(crate) fn check_mobprog(code: &str) -> Result<(), String> {
+    let mut in_if = false;
+
+    for (index, line) in code.lines().enumerate() {
+        let problem = match parse_mobprog_line(line) {
+            Err(err) => err,
+            Ok(MobProgLine::If(_)) if in_if => "An 'if' can't be inside another one.".to_string(),
+            Ok(MobProgLine::If(_)) => {
+                in_if = true;
+                continue;
+            }
+            Ok(MobProgLine::Else) | Ok(MobProgLine::EndIf) if !in_if => {
+                format!("'{}' without an 'if' before it.", line.trim())
+            }
+            Ok(MobProgLine::EndIf) => {
+                in_if = false;
+                continue;
+            }
+            Ok(_) => continue,
+        };
+
+        return Err(format!("Line {}: {}", index + 1, problem));
+    }
+
+    if in_if {
+        return Err("An 'if' is missing its 'endif'.".to_string());
+    }
+
+    Ok(())
 }
@@ -0,0 +1,574 @@
+//! In-game mobprog editor.
+//!
+//! `mpedit <vnum>` copies a mobprog (or a new one, in an area's range of
+//! vnums) into a draft, which is changed one line at a time. `mpedit save`
+//! checks the draft with the mobprog parser, and only then replaces the
+//! mobprog everywhere it is used: in the area, in mobile templates, and in
+//! every mobile that has it installed. Like with `aedit`, the area still needs
+//! an `asave` to write it to its file.
+//!
+//! `mpedit assign` and `mpedit unassign` install or remove the mobprog on all
+//! mobiles with a vnum, with a trigger written like in area files.
+//!
+//! Builders can only edit mobprogs, and change mobiles, in their own areas and
+//! vnums; see `EntityAgent::may_build()`.
+
+use crate::{
+    agent::EntityAgent,
+    components::{self, GeneralData},
+    echo,
+    import::import_mobprog_components,
+    load::parse_mobprog_trigger,
+    mobprogs::check_mobprog,
+    olc::{area_named, area_of_vnum},
+    save::format_mobprog_trigger,
+    world::{MobProg, Vnum},
+};
+
+pub(crate) struct MobProgDraft {
+    vnum: Vnum,
+    area: String,
+    title: String,
+    lines: Vec<String>,
+    changed: bool,
+}
+
+impl MobProgDraft {
+    /// The code as saved in area files, which starts on the line after "code".
+    fn code(&self) -> String {
+        let mut code = String::from("\r\n");
+        for line in &self.lines {
+            code.push_str(line);
+            code.push_str("\r\n");
+        }
+        code
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_mpedit(&mut self, words: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can edit mobprogs.\r\n");
+                return;
+            }
+        };
+
+        if let [vnum] = words {
+            if let Ok(vnum) = vnum.parse() {
+                self.do_mpedit_open(&player, vnum);
+                return;
+            }
+        }
+
+        if !self.olc.mobprog_drafts.contains_key(&player) {
+            match words {
+                [] => echo!(
+                    self.info(),
+                    "You are not editing a mobprog. Use '`Wmpedit <vnum>`^' to start.\r\n"
+                ),
+                _ => self.echo_mpedit_syntax(),
+            }
+            return;
+        }
+
+        let text = words.get(1..).unwrap_or_default().join(" ");
+        if text.contains('~') {
+            echo!(self.info(), "Mobprogs can't contain '~'.\r\n");
+            return;
+        }
+
+        match words {
+            [] | ["show"] => self.do_mpedit_show(&player),
+            ["title", _, ..] => {
+                self.edit_draft(&player, |draft| {
+                    draft.title = text;
+                    Ok("Title set.".to_string())
+                });
+            }
+            ["add", ..] => {
+                self.edit_draft(&player, |draft| {
+                    draft.lines.push(text);
+                    Ok(format!("Added line {}.", draft.lines.len()))
+                });
+            }
+            ["insert", number, ..] | ["replace", number, ..] | ["delete", number] => {
+                let index = match number.parse::<usize>() {
+                    Ok(number) if number > 0 => number - 1,
+                    _ => {
+                        echo!(self.info(), "That's not a valid line number.\r\n");
+                        return;
+                    }
+                };
+                let line = words[2..].join(" ");
+
+                self.edit_draft(&player, |draft| match words[0] {
+                    "insert" if index <= draft.lines.len() => {
+                        draft.lines.insert(index, line);
+                        Ok(format!("Inserted line {}.", index + 1))
+                    }
+                    "replace" if index < draft.lines.len() => {
+                        draft.lines[index] = line;
+                        Ok(format!("Replaced line {}.", index + 1))
+                    }
+                    "delete" if index < draft.lines.len() => {
+                        draft.lines.remove(index);
+                        Ok(format!("Deleted line {}.", index + 1))
+                    }
+                    _ => Err("There is no line with that number.".to_string()),
+                });
+            }
+            ["clear"] => {
+                self.edit_draft(&player, |draft| {
+                    draft.lines.clear();
+                    Ok("Removed all lines.".to_string())
+                });
+            }
+            ["check"] => {
+                let draft = &self.olc.mobprog_drafts[&player];
+                let checked = check_mobprog(&draft.lines.join("\n"));
+                match checked {
+                    Ok(()) => echo!(self.info(), "The mobprog looks fine.\r\n"),
+                    Err(err) => echo!(self.info(), "{}\r\n", err),
+                }
+            }
+            ["save"] => self.do_mpedit_save(&player),
+            ["cancel"] | ["done"] => {
+                let draft = self
+                    .olc
+                    .mobprog_drafts
+                    .remove(&player)
+                    .expect("Checked above");
+                if draft.changed {
+                    echo!(
+                        self.info(),
+                        "You stop editing mobprog #{}, and throw away your changes.\r\n",
+                        draft.vnum.0
+                    );
+                } else {
+                    echo!(
+                        self.info(),
+                        "You stop editing mobprog #{}.\r\n",
+                        draft.vnum.0
+                    );
+                }
+            }
+            ["assign", mobile_vnum, trigger, ref argument @ ..] => {
+                self.do_mpedit_assign(&player, mobile_vnum, trigger, &argument.join(" "));
+            }
+            ["unassign", mobile_vnum] => self.do_mpedit_unassign(&player, mobile_vnum),
+            _ => self.echo_mpedit_syntax(),
+        }
+    }
+
+    fn echo_mpedit_syntax(&mut self) {
+        echo!(
+            self.info(),
+            "Syntax: '`Wmpedit <vnum>`^', '`Wmpedit show`^', '`Wmpedit title <title>`^',\r\n\
+            '`Wmpedit add <code>`^', '`Wmpedit insert <line> <code>`^', \
+            '`Wmpedit replace <line> <code>`^',\r\n\
+            '`Wmpedit delete <line>`^', '`Wmpedit clear`^', '`Wmpedit check`^', \
+            '`Wmpedit save`^', '`Wmpedit cancel`^',\r\n\
+            '`Wmpedit assign <mobile vnum> <trigger> [argument]`^', \
+            '`Wmpedit unassign <mobile vnum>`^'.\r\n"
+        );
+    }
+
+    /// Change the current player's draft, and tell them how it went.
+    fn edit_draft(
+        &mut self,
+        player: &str,
+        edit: impl FnOnce(&mut MobProgDraft) -> Result<String, String>,
+    ) {
+        let draft = self
+            .olc
+            .mobprog_drafts
+            .get_mut(player)
+            .expect("Checked by do_mpedit");
+
+        match edit(draft) {
+            Ok(message) => {
+                draft.changed = true;
+                echo!(self.info(), "{}\r\n", message);
+            }
+            Err(err) => echo!(self.info(), "{}\r\n", err),
+        }
+    }
+
+    fn do_mpedit_open(&mut self, player: &str, vnum: usize) {
+        let editing = self
+            .olc
+            .mobprog_drafts
+            .get(player)
+            .map(|draft| (draft.vnum.0, draft.changed));
+
+        match editing {
+            Some((editing, _changed)) if editing == vnum => {
+                self.do_mpedit_show(player);
+                return;
+            }
+            Some((editing, true)) => {
+                echo!(
+                    self.info(),
+                    "You have unsaved changes to mobprog #{}; use '`Wmpedit save`^' or \
+                    '`Wmpedit cancel`^' first.\r\n",
+                    editing
+                );
+                return;
+            }
+            _ => (),
+        }
+
+        let existing = self
+            .world
+            .mobprogs
+            .get(vnum)
+            .filter(|mobprog| vnum != 0 && !mobprog.area.is_empty());
+
+        let draft = match existing {
+            Some(mobprog) => MobProgDraft {
+                vnum: Vnum(vnum),
+                area: mobprog.area.clone(),
+                title: mobprog.title.clone(),
+                lines: mobprog
+                    .code
+                    .strip_prefix("\r\n")
+                    .unwrap_or(&mobprog.code)
+                    .lines()
+                    .map(str::to_string)
+                    .collect(),
+                changed: false,
+            },
+            None => match area_of_vnum(self.world, vnum) {
+                Some(index) => MobProgDraft {
+                    vnum: Vnum(vnum),
+                    area: self.world.areas[index].0.short_name.clone(),
+                    title: "<untitled>".to_string(),
+                    lines: Vec::new(),
+                    changed: true,
+                },
+                None => {
+                    echo!(
+                        self.info(),
+                        "There is no mobprog with that vnum, and no area to create it in.\r\n"
+                    );
+                    return;
+                }
+            },
+        };
+
+        let created = existing.is_none();
+        if !self.may_build(area_named(self.world, &draft.area), Some((vnum, vnum))) {
+            return;
+        }
+
+        if created {
+            echo!(
+                self.info(),
+                "Creating mobprog #{} in the area `W{}`^.\r\n",
+                vnum,
+                draft.area
+            );
+        }
+
+        self.olc.mobprog_drafts.insert(player.to_string(), draft);
+        self.do_mpedit_show(player);
+    }
+
+    fn do_mpedit_show(&mut self, player: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let draft = &self.olc.mobprog_drafts[player];
+        let mut info = self.players.info(&myself);
+
+        echo!(
+            info,
+            "Mobprog `W#{}`^ '{}' in the area `W{}`^{}\r\n",
+            draft.vnum.0,
+            draft.title,
+            draft.area,
+            if draft.changed { " (not saved)" } else { "" }
+        );
+
+        if draft.lines.is_empty() {
+            echo!(info, "It has no code yet.\r\n");
+        }
+        for (index, line) in draft.lines.iter().enumerate() {
+            echo!(info, "`g{:>3}`^ {}\r\n", index + 1, line);
+        }
+
+        let installed: Vec<String> = self
+            .world
+            .mobiles
+            .iter()
+            .flat_map(|mobile| {
+                mobile
+                    .mobprog_triggers
+                    .iter()
+                    .filter(|(_trigger, vnum)| *vnum == draft.vnum)
+                    .map(move |(trigger, vnum)| {
+                        format!(
+                            "mobile #{} ({})",
+                            mobile.vnum.0,
+                            format_mobprog_trigger(trigger, *vnum)
+                        )
+                    })
+            })
+            .collect();
+
+        if installed.is_empty() {
+            echo!(info, "It is not installed on any mobile.\r\n");
+        } else {
+            echo!(info, "Installed on: {}\r\n", installed.join(", "));
+        }
+    }
+
+    fn do_mpedit_save(&mut self, player: &str) {
+        let draft = &self.olc.mobprog_drafts[player];
+        let vnum = draft.vnum;
+        let area = draft.area.clone();
+
+        if !self.may_build(area_named(self.world, &area), Some((vnum.0, vnum.0))) {
+            return;
+        }
+
+        let draft = &self.olc.mobprog_drafts[player];
+        if let Err(err) = check_mobprog(&draft.lines.join("\n")) {
+            echo!(self.info(), "{}\r\nThe mobprog was not saved.\r\n", err);
+            return;
+        }
+
+        let mobprog = MobProg {
+            vnum,
+            title: draft.title.clone(),
+            code: draft.code(),
+            disabled: false,
+            area: area.clone(),
+        };
+
+        if self.world.mobprogs.len() <= vnum.0 {
+            self.world.mobprogs.resize(vnum.0 + 1, MobProg::default());
+        }
+        self.world.mobprogs[vnum.0] = mobprog.clone();
+        if let Some(index) = area_named(self.world, &area) {
+            self.world.areas[index].0.changed = true;
+        }
+
+        // Replace the mobprog in templates, and in mobiles that have it
+        let vnum_to_mobprog = &mut self.entity_world.templates.vnum_to_mobprog;
+        if vnum_to_mobprog.len() <= vnum.0 {
+            vnum_to_mobprog.resize(vnum.0 + 1, None);
+        }
+        vnum_to_mobprog[vnum.0] = Some(mobprog.code.clone());
+
+        let interner = &mut self.entity_world.interner;
+        for (_mobile, mobprogs) in self
+            .entity_world
+            .templates
+            .mobile_components
+            .iter_mut()
+            .flatten()
+        {
+            for components in mobprogs.iter_mut() {
+                if let Some(installed) = &components.mobprog {
+                    if components.general.vnum == vnum {
+                        let trigger = installed.trigger.clone();
+                        let area = components.general.area.clone();
+                        *components =
+                            import_mobprog_components(&mobprog, &trigger, &area, interner);
+                    }
+                }
+            }
+        }
+
+        let installed: Vec<_> = self
+            .entity_world
+            .query::<(&components::MobProg, &GeneralData)>()
+            .filter(|(_entity, (_installed, general))| general.vnum == vnum)
+            .map(|(entity, (installed, general))| {
+                (
+                    entity.entity_id(),
+                    installed.trigger.clone(),
+                    general.area.clone(),
+                )
+            })
+            .collect();
+
+        for (entity_id, trigger, area) in &installed {
+            let components =
+                import_mobprog_components(&mobprog, trigger, area, &mut self.entity_world.interner);
+            let mut entity = self.entity_world.entity_info_mut(*entity_id);
+            *entity.components() = components;
+        }
+
+        self.olc
+            .mobprog_drafts
+            .get_mut(player)
+            .expect("Checked above")
+            .changed = false;
+
+        echo!(
+            self.info(),
+            "Saved mobprog #{}, which {} mobiles are using. Use '`Wasave {}`^' to save its area.\r\n",
+            vnum.0,
+            installed.len(),
+            area
+        );
+    }
+
+    /// Find a mobile for `mpedit assign` and `unassign`, and the mobprog
+    /// being edited, which has to be saved first.
+    fn mpedit_targets(&mut self, player: &str, mobile_vnum: &str) -> Option<(Vnum, MobProg)> {
+        let draft = &self.olc.mobprog_drafts[player];
+
+        let mobprog = self
+            .world
+            .mobprogs
+            .get(draft.vnum.0)
+            .filter(|mobprog| !mobprog.area.is_empty())
+            .cloned();
+        let mobprog = match mobprog {
+            Some(mobprog) => mobprog,
+            None => {
+                echo!(
+                    self.info(),
+                    "Save the mobprog with '`Wmpedit save`^' first.\r\n"
+                );
+                return None;
+            }
+        };
+
+        let mobile_vnum = mobile_vnum
+            .parse::<usize>()
+            .ok()
+            .filter(|vnum| *vnum != 0 && self.entity_world.templates.mobile(*vnum).is_some());
+        match mobile_vnum {
+            Some(mobile_vnum) => {
+                let area = area_named(self.world, &self.world.mobiles[mobile_vnum].area);
+                if self.may_build(area, Some((mobile_vnum, mobile_vnum))) {
+                    Some((Vnum(mobile_vnum), mobprog))
+                } else {
+                    None
+                }
+            }
+            None => {
+                echo!(self.info(), "There is no mobile with that vnum.\r\n");
+                None
+            }
+        }
+    }
+
+    fn do_mpedit_assign(&mut self, player: &str, mobile_vnum: &str, kind: &str, argument: &str) {
+        let (mobile_vnum, mobprog) = match self.mpedit_targets(player, mobile_vnum) {
+            Some(targets) => targets,
+            None => return,
+        };
+
+        // Triggers are written like in area files, e.g. "greet 100"
+        let trigger = format!("{} {} {}", kind.to_uppercase(), mobprog.vnum.0, argument);
+        let trigger = match parse_mobprog_trigger(&trigger) {
+            Ok(Some((trigger, _vnum))) => trigger,
+            Ok(None) | Err(_) => {
+                echo!(
+                    self.info(),
+                    "Triggers are: random/greet/entry/kill/death <chance>, speech/act <text>,\r\n\
+                    exit <direction>, bribe <amount>, give <vnum or keyword>, hour <hour>, and \
+                    loginroom.\r\n"
+                );
+                return;
+            }
+        };
+
+        let mobile = &mut self.world.mobiles[mobile_vnum.0];
+        mobile
+            .mobprog_triggers
+            .push((trigger.clone(), mobprog.vnum));
+        let area = mobile.area.clone();
+        if let Some(index) = area_named(self.world, &area) {
+            self.world.areas[index].0.changed = true;
+        }
+
+        let components =
+            import_mobprog_components(&mobprog, &trigger, &area, &mut self.entity_world.interner);
+
+        if let Some((_mobile, mobprogs)) =
+            &mut self.entity_world.templates.mobile_components[mobile_vnum.0]
+        {
+            mobprogs.push(components.clone());
+        }
+
+        let mobiles: Vec<_> = self
+            .entity_world
+            .all_entities()
+            .filter(|entity| entity.is_mobile() && entity.components().general.vnum == mobile_vnum)
+            .map(|entity| entity.entity_id())
+            .collect();
+        for mobile_id in &mobiles {
+            self.entity_world
+                .insert_entity(*mobile_id, components.clone());
+        }
+
+        echo!(
+            self.info(),
+            "Installed mobprog #{} on mobile #{} ({} in the world), triggered by '{}'.\r\n",
+            mobprog.vnum.0,
+            mobile_vnum.0,
+            mobiles.len(),
+            format_mobprog_trigger(&trigger, mobprog.vnum)
+        );
+    }
+
+    fn do_mpedit_unassign(&mut self, player: &str, mobile_vnum: &str) {
+        let (mobile_vnum, mobprog) = match self.mpedit_targets(player, mobile_vnum) {
+            Some(targets) => targets,
+            None => return,
+        };
+
+        let mobile = &mut self.world.mobiles[mobile_vnum.0];
+        let triggers = mobile.mobprog_triggers.len();
+        mobile
+            .mobprog_triggers
+            .retain(|(_trigger, vnum)| *vnum != mobprog.vnum);
+        if mobile.mobprog_triggers.len() == triggers {
+            echo!(self.info(), "That mobile doesn't have this mobprog.\r\n");
+            return;
+        }
+
+        let area = mobile.area.clone();
+        if let Some(index) = area_named(self.world, &area) {
+            self.world.areas[index].0.changed = true;
+        }
+
+        if let Some((_mobile, mobprogs)) =
+            &mut self.entity_world.templates.mobile_components[mobile_vnum.0]
+        {
+            mobprogs.retain(|components| components.general.vnum != mobprog.vnum);
+        }
+
+        // And from the mobiles spawned with it already
+        let uninstalled: Vec<_> = self
+            .entity_world
+            .all_entities()
+            .filter(|entity| entity.is_mobile() && entity.components().general.vnum == mobile_vnum)
+            .flat_map(|mobile| mobile.contained_entities())
+            .filter(|entity| {
+                entity.components().mobprog.is_some()
+                    && entity.components().general.vnum == mobprog.vnum
+            })
+            .map(|entity| entity.entity_id())
+            .collect();
+
+        for mobprog_id in uninstalled {
+            self.entity_world.destroy_entity(mobprog_id);
+        }
+
+        echo!(
+            self.info(),
+            "Removed mobprog #{} from mobile #{}.\r\n",
+            mobprog.vnum.0,
+            mobile_vnum.0
+        );
+    }
+}
@@ -1,15 +1,55 @@
-use std::panic::catch_unwind;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::{collections::BTreeMap, net::SocketAddr};
 
-use libtelnet_rs::{events::TelnetEvents, Parser};
+use libtelnet_rs::{
+    bytes::Bytes,
+    compatibility::CompatibilityTable,
+    events::{TelnetEvents, TelnetSubnegotiation},
+    telnet::{op_command, op_option},
+    Parser,
+};
 use netcore::{self, EntryCode, ExitCode, NetServer, Source};
 use serde::{Deserialize, Serialize};
 
-use crate::{acting::Players, colorize, files::RealFiles, socials, state, world, WorldState};
+use crate::{
+    colorize,
+    command_table::redacted,
+    config::Config,
+    crash::{Crashes, PanicHook},
+    decolorize,
+    files::RealFiles,
+    reboot::{ExitDue, ScheduledExit},
+    rng::GameRng,
+    socials,
+    state::{self, unix_time},
+    status::Started,
+    world, WorldState,
+};
+
+/// Wrong passwords a connection may type before it is closed
+const MAX_WRONG_PASSWORDS: u8 = 3;
+
+/// Sent to new connections, for clients to answer whether they want GMCP
+const GMCP_OFFER: &[u8] = &[op_command::IAC, op_command::WILL, op_option::GMCP];
 
 #[derive(Serialize, Deserialize)]
 struct ConnectionState {
     connections: BTreeMap<usize, Connection>,
+    #[serde(default)]
+    locks: Locks,
+    /// When the server started, in unix time
+    #[serde(default = "unix_time")]
+    started: u64,
+}
+
+/// Who may log in; kept with the connections so that it lasts through
+/// restarts and crashes.
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+struct Locks {
+    /// Only immortals may log in
+    wizlock: bool,
+    /// No new characters may be created
+    newlock: bool,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -19,22 +59,49 @@ struct Connection {
     command_buffer: String,
     sent_command: bool,
     no_prompt: bool,
+    #[serde(default)]
+    login: Option<Login>,
+    /// The client agreed to GMCP, and gets `Room.Info` for its mapper
+    #[serde(default)]
+    gmcp: bool,
+    /// The last `Room.Info` sent, to send it again only when it changes
+    #[serde(default)]
+    gmcp_room: Option<String>,
+    /// Wrong passwords typed; see `MAX_WRONG_PASSWORDS`
+    #[serde(default)]
+    wrong_passwords: u8,
+}
+
+/// What a connection that used the '`name`' command is expected to type next.
+#[derive(Serialize, Deserialize)]
+enum Login {
+    Password { name: String },
+    NewPassword { name: String },
+    ConfirmPassword { name: String, password: String },
 }
 
 struct Game {
     world_state: Box<WorldState>,
+    /// When the game was created, in unix time
+    started: u64,
 }
 
 impl Game {
     fn new(connection_state: &mut ConnectionState, reason: &str) -> Game {
         let files = RealFiles;
 
-        println!("Loading area data...");
-        let world = world::load_world(&files, "data/area");
-        println!("Loading socials.txt data...");
-        let socials = socials::load_socials(&files, "data/socials.txt");
+        let config = Config::load().unwrap_or_else(|err| {
+            println!("Using the default configuration: {}", err);
+            Config::default()
+        });
+
+        let mut rng = GameRng::from_environment();
+        println!("Loading area data with seed {}...", rng.seed());
+        let world = world::load_world(&files, &config.world, &mut rng);
+        println!("Loading {}...", config.world.socials);
+        let socials = socials::load_socials(&files, &config.world.socials);
         println!("Importing areas into entity world...");
-        let mut world_state = state::create_state(world, socials);
+        let mut world_state = state::create_state(world, socials, &files, rng, config);
 
         for connection in connection_state.connections.values() {
             if let Some(player) = &connection.player {
@@ -44,6 +111,7 @@ impl Game {
 
         let mut game = Game {
             world_state: Box::new(world_state),
+            started: unix_time(),
         };
 
         println!("Entity world loaded, game ready.");
@@ -59,6 +127,7 @@ impl Game {
 }
 
 #[no_mangle]
+#[allow(improper_ctypes_definitions)]
 pub extern "C" fn do_things(net_server: &mut NetServer, entry_code: EntryCode) -> ExitCode {
     let mut connection_state = match entry_code {
         EntryCode::New => {
@@ -67,9 +136,13 @@ pub extern "C" fn do_things(net_server: &mut NetServer, entry_code: EntryCode) -
             connections.insert(0, Connection::default());
             connections.insert(1, Connection::default());
 
-            ConnectionState { connections }
+            ConnectionState {
+                connections,
+                locks: Locks::default(),
+                started: unix_time(),
+            }
         }
-        EntryCode::Restarted { initializer } => bincode::deserialize(&*initializer).expect(""),
+        EntryCode::Restarted { initializer } => bincode::deserialize(&initializer).expect(""),
     };
 
     for (&target, connection) in &connection_state.connections {
@@ -83,32 +156,54 @@ pub extern "C" fn do_things(net_server: &mut NetServer, entry_code: EntryCode) -
         net_server.send_bytes(&Source(target), b"> ");
     }
 
-    let mut telnet_parser = Parser::new();
+    let _panic_hook = PanicHook::install();
+    let mut crashes = Crashes::new();
+
+    // Each connection negotiates telnet options on its own
+    let mut telnet_parsers: BTreeMap<usize, Parser> = BTreeMap::new();
 
     let mut game = Game::new(&mut connection_state, "restarted");
 
-    send_echoes(
-        net_server,
-        &mut game.world_state.players,
-        &mut connection_state,
-    );
+    send_echoes(net_server, &mut game.world_state, &mut connection_state);
+    publish_status(net_server, &game, &connection_state);
 
-    let restart = loop {
+    let mut scheduled_exit: Option<ScheduledExit> = None;
+
+    // Anything that panics outside of commands and updates of the world ends
+    // the mudlib, rather than unwinding into netcore.
+    let main_loop = catch_unwind(AssertUnwindSafe(|| loop {
         let mut schedule_restart = false;
         let mut schedule_exit = false;
+        let mut system_messages = Vec::new();
 
         let (source, event) = net_server.receive_event();
 
+        // Time what the game does with the event, but not waiting for it
+        let _timed = game.world_state.perf.enter();
+
         match event {
             netcore::NetEvent::Accepted(new_source, address) => {
                 println!("Accepted {}", address);
 
                 net_server.send_bytes(&new_source, b"Welcome to DemiMUD!\r\n");
+                if connection_state.locks.wizlock {
+                    net_server.send_bytes(
+                        &new_source,
+                        b"The realm is closed to all but immortals for now.\r\n",
+                    );
+                } else if connection_state.locks.newlock {
+                    net_server.send_bytes(
+                        &new_source,
+                        b"No new characters can be created for now.\r\n",
+                    );
+                }
                 net_server.send_bytes(
                     &new_source,
                     colorize("Set your name with '`Wname YourName`^' to log in.\r\n").as_bytes(),
                 );
                 net_server.send_bytes(&new_source, b"> ");
+                // Offer GMCP; the parser sees whether the client wants it
+                net_server.send_bytes(&new_source, GMCP_OFFER);
 
                 let connection = Connection {
                     player: None,
@@ -116,6 +211,10 @@ pub extern "C" fn do_things(net_server: &mut NetServer, entry_code: EntryCode) -
                     command_buffer: String::new(),
                     sent_command: false,
                     no_prompt: false,
+                    login: None,
+                    gmcp: false,
+                    gmcp_room: None,
+                    wrong_passwords: 0,
                 };
 
                 connection_state
@@ -127,23 +226,40 @@ pub extern "C" fn do_things(net_server: &mut NetServer, entry_code: EntryCode) -
                     .connections
                     .remove(&source.0)
                     .expect("Unregistered source");
+                telnet_parsers.remove(&source.0);
                 if let Some(player) = &connection.player {
                     println!(
                         "Player {} disconnected from {}.",
                         player,
                         connection.address.unwrap()
                     );
+                    game.world_state.disconnect_player(player);
                 } else {
                     println!("Disconnected {}", connection.address.unwrap());
                 }
             }
             netcore::NetEvent::Received(bytes) => {
+                let _receive = tracing::info_span!("network", name = "receive").entered();
+                let telnet_parser = telnet_parsers.entry(source.0).or_insert_with(telnet_parser);
                 for event in telnet_parser.receive(bytes) {
                     let world_state = &mut game.world_state;
 
                     match event {
+                        // The parser agrees to GMCP again when the client
+                        // takes up the offer, which it doesn't need to hear
+                        TelnetEvents::DataSend(data) if data == GMCP_OFFER => (),
                         TelnetEvents::DataSend(data) => {
-                            net_server.send_bytes(&source, &*data);
+                            net_server.send_bytes(&source, &data);
+                        }
+                        TelnetEvents::Negotiation(negotiation)
+                            if negotiation.option == op_option::GMCP =>
+                        {
+                            let connection = connection_state
+                                .connections
+                                .get_mut(&source.0)
+                                .expect("Unregistered connection");
+                            connection.gmcp = negotiation.command == op_command::DO;
+                            connection.gmcp_room = None;
                         }
                         TelnetEvents::DataReceive(data) => {
                             let connection = connection_state
@@ -151,9 +267,9 @@ pub extern "C" fn do_things(net_server: &mut NetServer, entry_code: EntryCode) -
                                 .get_mut(&source.0)
                                 .expect("Unregistered connection");
 
-                            let data = String::from_utf8_lossy(&*data);
+                            let data = String::from_utf8_lossy(&data);
                             let original_buffer = connection.command_buffer.len();
-                            connection.command_buffer.push_str(&*data);
+                            connection.command_buffer.push_str(&data);
 
                             if let Some(index) = data.find('\n') {
                                 // Unlike other players, this one doesn't get
@@ -173,8 +289,19 @@ pub extern "C" fn do_things(net_server: &mut NetServer, entry_code: EntryCode) -
 
                                 match words.as_slice() {
                                     &["who"] => {
+                                        let viewer = connection.player.clone();
+
                                         echo("Players currently connected to the realm:\r\n");
                                         for (target, connection) in &connection_state.connections {
+                                            // Leave out invisible immortals
+                                            if let Some(player) = &connection.player {
+                                                if !world_state
+                                                    .can_see_player(viewer.as_deref(), player)
+                                                {
+                                                    continue;
+                                                }
+                                            }
+
                                             if let Some(address) = &connection.address {
                                                 echo(&colorize(&format!(
                                                     "{}: `M{}`^ ({})\r\n",
@@ -185,6 +312,9 @@ pub extern "C" fn do_things(net_server: &mut NetServer, entry_code: EntryCode) -
                                             }
                                         }
                                     }
+                                    &["MSSP-REQUEST"] => {
+                                        echo(&mssp_reply(&connection_state));
+                                    }
                                     &["exit"] => {
                                         echo("Bye!\r\n");
                                         net_server.schedule_disconnect(&source);
@@ -195,35 +325,117 @@ pub extern "C" fn do_things(net_server: &mut NetServer, entry_code: EntryCode) -
                                             echo,
                                             connection,
                                             world_state,
+                                            connection_state.locks,
                                             command_words,
                                         );
+                                        if connection.wrong_passwords >= MAX_WRONG_PASSWORDS {
+                                            net_server.schedule_disconnect(&source);
+                                            connection.no_prompt = true;
+                                        }
                                     }
-                                    &["restart"] => {
+                                    &["restart"]
+                                        if connection.player.as_deref().is_some_and(|player| {
+                                            world_state.may_use_command(player, "restart")
+                                        }) =>
+                                    {
                                         echo("Scheduled restart.\r\n");
                                         schedule_restart = true;
                                     }
-                                    &["shutdown"] => {
+                                    &["shutdown"]
+                                        if connection.player.as_deref().is_some_and(|player| {
+                                            world_state.may_use_command(player, "shutdown")
+                                        }) =>
+                                    {
                                         echo("Scheduled shutdown.\r\n");
                                         schedule_exit = true;
                                     }
+                                    &[lock @ ("wizlock" | "newlock")]
+                                        if connection.player.as_deref().is_some_and(|player| {
+                                            world_state.may_use_command(player, lock)
+                                        }) =>
+                                    {
+                                        let locks = &mut connection_state.locks;
+                                        let locked = if lock == "wizlock" {
+                                            locks.wizlock = !locks.wizlock;
+                                            locks.wizlock
+                                        } else {
+                                            locks.newlock = !locks.newlock;
+                                            locks.newlock
+                                        };
+
+                                        let message = match (lock, locked) {
+                                            ("wizlock", true) => {
+                                                "The realm is now closed to all but immortals."
+                                            }
+                                            ("wizlock", false) => "The realm is open to all again.",
+                                            (_, true) => "No new characters can be created now.",
+                                            (_, false) => "New characters can be created again.",
+                                        };
+                                        println!(
+                                            "{} used {}: {}",
+                                            connection.player.as_deref().unwrap_or("?"),
+                                            lock,
+                                            message
+                                        );
+                                        system_messages.push(message.to_string());
+                                    }
+                                    &[command @ ("shutdown" | "reboot"), "in", minutes]
+                                        if connection.player.as_deref().is_some_and(|player| {
+                                            world_state.may_use_command(player, command)
+                                        }) =>
+                                    {
+                                        match ScheduledExit::new(command == "reboot", minutes) {
+                                            Ok(exit) => {
+                                                println!(
+                                                    "{} scheduled a {} in {} minutes.",
+                                                    connection.player.as_deref().unwrap_or("?"),
+                                                    command,
+                                                    minutes
+                                                );
+                                                system_messages.push(exit.announcement());
+                                                scheduled_exit = Some(exit);
+                                            }
+                                            Err(err) => echo(&format!("{}\r\n", err)),
+                                        }
+                                    }
+                                    &[command @ ("shutdown" | "reboot"), "cancel"]
+                                        if connection.player.as_deref().is_some_and(|player| {
+                                            world_state.may_use_command(player, command)
+                                        }) =>
+                                    {
+                                        match scheduled_exit.take() {
+                                            Some(exit) => {
+                                                println!(
+                                                    "{} cancelled the scheduled {}.",
+                                                    connection.player.as_deref().unwrap_or("?"),
+                                                    if exit.reboot { "reboot" } else { "shutdown" }
+                                                );
+                                                system_messages.push(exit.cancellation());
+                                            }
+                                            None => echo(
+                                                "There is no shutdown or reboot scheduled.\r\n",
+                                            ),
+                                        }
+                                    }
                                     words => {
                                         let player = connection
                                             .player
                                             .as_ref()
                                             .expect("Checked in previous match arm");
 
-                                        let old_game = catch_unwind(move || {
-                                            game.world_state.process_player_command(player, words);
-                                            game
-                                        });
+                                        crashes.record_command(player, &redacted(words));
 
-                                        game = match old_game {
-                                            Ok(game) => game,
-                                            Err(_err) => {
-                                                // Old game's kaput, make a new one
-                                                Game::new(&mut connection_state, "crashed")
-                                            }
-                                        };
+                                        let crashed = crashes.run(
+                                            &mut game.world_state,
+                                            "running a command",
+                                            |world_state| {
+                                                world_state.process_player_command(player, words)
+                                            },
+                                        );
+                                        if crashed {
+                                            // Old game's kaput, make a new one
+                                            game = Game::new(&mut connection_state, "crashed");
+                                        }
                                     }
                                 }
                             }
@@ -233,26 +445,74 @@ pub extern "C" fn do_things(net_server: &mut NetServer, entry_code: EntryCode) -
                 }
             }
             netcore::NetEvent::Tick => {
-                let old_game = catch_unwind(move || {
-                    game.world_state.update_world();
-                    game
-                });
-                game = match old_game {
-                    Ok(game) => game,
-                    Err(_err) => {
-                        // Old game's kaput, make a new one
-                        Game::new(&mut connection_state, "crashed")
-                    }
-                };
+                let crashed =
+                    crashes.run(&mut game.world_state, "updating the world", |world_state| {
+                        world_state.update_world()
+                    });
+                if crashed {
+                    // Old game's kaput, make a new one
+                    game = Game::new(&mut connection_state, "crashed");
+                }
+                publish_status(net_server, &game, &connection_state);
             }
         };
 
+        // Warn everyone about a scheduled shutdown or reboot, or carry it out.
+        if let Some(exit) = &mut scheduled_exit {
+            match exit.poll() {
+                Some(ExitDue::Warning(warning)) => system_messages.push(warning),
+                Some(ExitDue::Now) => {
+                    if exit.reboot {
+                        schedule_restart = true;
+                    } else {
+                        schedule_exit = true;
+                    }
+                    scheduled_exit = None;
+
+                    if let Err(err) = game.world_state.save_all(&RealFiles) {
+                        println!("Could not save everything before going down: {}", err);
+                    }
+                }
+                None => (),
+            }
+        }
+
+        for message in system_messages {
+            let message = format!("`D[`Rsystem`D]: `W{}`^\r\n", message);
+            broadcast(&mut game, &mut connection_state, &message);
+        }
+
+        // Take scheduled snapshots, or roll the world back.
+        game.world_state.run_snapshot_jobs(&RealFiles);
+
+        // Write areas that were asked to be saved.
+        game.world_state.run_area_saves(&RealFiles);
+
+        // Read data files again that were asked to be reloaded.
+        game.world_state.run_reloads(&RealFiles);
+
+        // Look up the history of players for immortals.
+        game.world_state.run_history_lookups(&RealFiles);
+
+        // Persist anything that changed (e.g. new notes).
+        if let Err(err) = game.world_state.save_changes(&RealFiles) {
+            println!("Could not save game data: {}", err);
+        }
+
         // Send all buffered output to players.
-        send_echoes(
-            net_server,
-            &mut game.world_state.players,
-            &mut connection_state,
-        );
+        send_echoes(net_server, &mut game.world_state, &mut connection_state);
+
+        // Disconnect characters that were just deleted.
+        for deleted in game.world_state.take_deleted_players() {
+            for (&target, connection) in &mut connection_state.connections {
+                if connection.player.as_deref() == Some(deleted.as_str()) {
+                    println!("Disconnecting deleted character {}.", deleted);
+                    connection.player = None;
+                    connection.no_prompt = true;
+                    net_server.schedule_disconnect(&Source(target));
+                }
+            }
+        }
 
         if schedule_restart {
             for &target in connection_state.connections.keys() {
@@ -275,6 +535,22 @@ pub extern "C" fn do_things(net_server: &mut NetServer, entry_code: EntryCode) -
         } else {
             continue;
         }
+    }));
+
+    let restart = match main_loop {
+        Ok(restart) => restart,
+        Err(_err) => {
+            crashes.recover(&mut game.world_state, "handling the network");
+            for &target in connection_state.connections.keys() {
+                if target == 0 || target == 1 {
+                    continue;
+                }
+                net_server.send_bytes(&Source(target), b"\r\nServer crashed, sorry!\r\n");
+                net_server.try_flush(&Source(target));
+            }
+            println!("Exiting mudlib's main loop after a crash.");
+            return ExitCode::Crashed;
+        }
     };
 
     println!("Exiting mudlib's main loop.");
@@ -294,33 +570,117 @@ fn process_login_command<F: FnMut(&str)>(
     mut echo: F,
     connection: &mut Connection,
     world_state: &mut WorldState,
+    locks: Locks,
     command_words: &[&str],
 ) {
-    match command_words {
-        &["name", name] => {
-            println!(
-                "Player {} logged in from {}.",
-                name,
-                connection.address.as_ref().unwrap()
-            );
-            connection.player = Some(name.to_string());
-            echo("Name set. Welcome!\r\n");
-            world_state.add_player(name);
-            // FIXME
-            // write!(
-            //     world_state.players.others(),
-            //     "{} materializes from thin air.\r\n",
-            //     name.to_title_case()
-            // )
-            // .unwrap();
+    match (connection.login.take(), command_words) {
+        (None, &["name", name]) => {
+            if let Some(account) = world_state.accounts.account(name) {
+                echo(&format!("Welcome back, {}. Password:\r\n", account.name));
+                connection.login = Some(Login::Password {
+                    name: account.name.clone(),
+                });
+                return;
+            }
+
+            if locks.newlock || locks.wizlock {
+                echo("No new characters can be created for now; please try again later.\r\n");
+                return;
+            }
+
+            match world_state.check_new_name(name) {
+                Ok(name) => {
+                    echo(&colorize(&format!(
+                        "A new character named `W{}`^! Choose a password:\r\n",
+                        name
+                    )));
+                    connection.login = Some(Login::NewPassword { name });
+                }
+                Err(error) => {
+                    echo(&format!("{}\r\n", error));
+                }
+            }
         }
-        &["name", ..] => {
+        (None, &["name", ..]) => {
             echo(&colorize(
                 "The '`Wname`^' command can only be used with one argument after it.\r\n",
             ));
         }
-        &[] => {}
-        _any_command => {
+        (Some(Login::Password { name }), &[password]) => {
+            let wait = world_state.accounts.login_wait(&name);
+            if wait > 0 {
+                echo(&format!(
+                    "Too many wrong passwords; please try again in {} seconds.\r\n",
+                    wait
+                ));
+                return;
+            }
+
+            if world_state.accounts.check_password(&name, password) {
+                if locks.wizlock && !world_state.accounts.is_immortal(&name) {
+                    echo("The realm is closed to all but immortals for now; please try again later.\r\n");
+                    return;
+                }
+                log_in(echo, connection, world_state, name, false);
+            } else {
+                println!(
+                    "Wrong password for {} from {}.",
+                    name,
+                    connection.address.as_ref().unwrap()
+                );
+                connection.wrong_passwords += 1;
+                if connection.wrong_passwords >= MAX_WRONG_PASSWORDS {
+                    echo("Wrong password. Goodbye!\r\n");
+                } else {
+                    echo(&colorize(
+                        "Wrong password. Type '`Wname YourName`^' to try again.\r\n",
+                    ));
+                }
+            }
+        }
+        (Some(Login::NewPassword { name }), &[password]) => {
+            match crate::accounts::Accounts::check_new_password(password) {
+                Ok(()) => {
+                    echo("Please type the password again to confirm it:\r\n");
+                    connection.login = Some(Login::ConfirmPassword {
+                        name,
+                        password: password.to_string(),
+                    });
+                }
+                Err(error) => {
+                    echo(&format!("{}\r\nChoose a password:\r\n", error));
+                    connection.login = Some(Login::NewPassword { name });
+                }
+            }
+        }
+        (Some(Login::ConfirmPassword { name, password }), &[confirmation]) => {
+            if password != confirmation {
+                echo("The passwords don't match. Choose a password:\r\n");
+                connection.login = Some(Login::NewPassword { name });
+                return;
+            }
+
+            // Someone else may have taken the name in the meantime
+            if let Err(error) = world_state.create_account(&name, &password) {
+                echo(&colorize(&format!(
+                    "{} Type '`Wname YourName`^' to try again.\r\n",
+                    error
+                )));
+                return;
+            }
+
+            println!("New character {} created.", name);
+            log_in(echo, connection, world_state, name, true);
+        }
+        (Some(login), &[]) => {
+            connection.login = Some(login);
+        }
+        (Some(login), _) => {
+            echo("Passwords must be a single word.\r\n");
+            connection.login = Some(login);
+        }
+        (None, &[]) => {}
+        (None, _any_command) => {
             echo(&colorize(
                 "But first, who are you? Type '`Wname SomeName`^' \
                 to set your name, or '`Wwho`^' to\r\n\
@@ -330,14 +690,56 @@ fn process_login_command<F: FnMut(&str)>(
     }
 }
 
+fn log_in<F: FnMut(&str)>(
+    mut echo: F,
+    connection: &mut Connection,
+    world_state: &mut WorldState,
+    name: String,
+    new_character: bool,
+) {
+    echo("Welcome!\r\n");
+    world_state.add_player(&name);
+
+    let address = connection.address.as_ref().unwrap().ip().to_string();
+    world_state.log_login(&name, &address, new_character);
+    connection.player = Some(name);
+}
+
 fn send_echoes(
     net_server: &mut NetServer,
-    players: &mut Players,
+    world_state: &mut WorldState,
     connection_state: &mut ConnectionState,
 ) {
-    for (target, connection) in &connection_state.connections {
+    let _send = tracing::info_span!("network", name = "send").entered();
+    world_state.mirror_snooped_echoes();
+
+    for (target, connection) in &mut connection_state.connections {
         if let Some(player) = &connection.player {
-            if let Some(player_echo) = players.player_echoes.get_mut(player.as_str()) {
+            // Mappers only need to hear about rooms they aren't in already
+            let room_info = match connection.gmcp {
+                true => world_state.room_info(player),
+                false => None,
+            };
+            if let Some(room_info) =
+                room_info.filter(|room_info| connection.gmcp_room.as_ref() != Some(room_info))
+            {
+                let data = Bytes::copy_from_slice(room_info.as_bytes());
+                let bytes: Bytes = TelnetSubnegotiation::new(op_option::GMCP, data).into();
+                net_server.send_bytes(&Source(*target), &bytes);
+                connection.gmcp_room = Some(room_info);
+            }
+
+            let prompt = world_state.prompt(player);
+            let wants_color = world_state.wants_color(player);
+            let color = |text| {
+                if wants_color {
+                    colorize(text)
+                } else {
+                    decolorize(text)
+                }
+            };
+
+            if let Some(player_echo) = world_state.players.player_echoes.get_mut(player.as_str()) {
                 let echoes = &mut player_echo.echo_buffer;
                 if echoes.is_empty() && !connection.sent_command {
                     continue;
@@ -350,14 +752,12 @@ fn send_echoes(
                     net_server.send_bytes(&target, b"\r\n");
                 }
 
-                net_server.send_bytes(&target, colorize(echoes).as_bytes());
+                net_server.send_bytes(&target, color(echoes).as_bytes());
 
                 // Also send them a prompt
                 if !connection.no_prompt {
-                    if let Some(player) = &connection.player {
-                        net_server.send_bytes(&target, player.as_bytes());
-                    }
-                    net_server.send_bytes(&target, b"> \xFF\xF9");
+                    net_server.send_bytes(&target, color(&prompt).as_bytes());
+                    net_server.send_bytes(&target, b"\xFF\xF9");
                 }
             }
         } else if connection.sent_command && !connection.no_prompt {
@@ -370,11 +770,60 @@ fn send_echoes(
         connection.sent_command = false;
     }
 
-    for player_echo in players.player_echoes.values_mut() {
+    for player_echo in world_state.players.player_echoes.values_mut() {
         player_echo.echo_buffer.clear();
     }
 }
 
+/// A telnet parser for a new connection, which may turn on GMCP.
+fn telnet_parser() -> Parser {
+    let mut support = CompatibilityTable::new();
+    support.support_local(op_option::GMCP);
+    Parser::with_support(support)
+}
+
+/// Publish who is online and the state of the game to the HTTP API.
+fn publish_status(net_server: &mut NetServer, game: &Game, connection_state: &ConnectionState) {
+    let players: Vec<&str> = connection_state
+        .connections
+        .values()
+        .filter_map(|connection| connection.player.as_deref())
+        .collect();
+    let started = Started {
+        server: connection_state.started,
+        game: game.started,
+    };
+
+    game.world_state
+        .publish_status(net_server, &players, &started);
+}
+
+/// Answer the plain text MSSP request that MUD listing sites send, with
+/// tab-separated variables and values.
+fn mssp_reply(connection_state: &ConnectionState) -> String {
+    let players = connection_state
+        .connections
+        .values()
+        .filter(|connection| connection.player.is_some())
+        .count();
+    let flag = |set: bool| if set { "1" } else { "0" };
+
+    let variables = [
+        ("NAME", "DemiMUD".to_string()),
+        ("CODEBASE", "DemiMUD".to_string()),
+        ("PLAYERS", players.to_string()),
+        ("WIZLOCK", flag(connection_state.locks.wizlock).to_string()),
+        ("NEWLOCK", flag(connection_state.locks.newlock).to_string()),
+    ];
+
+    let mut reply = "\r\nMSSP-REPLY-START\r\n".to_string();
+    for (variable, value) in &variables {
+        reply.push_str(&format!("{}\t{}\r\n", variable, value));
+    }
+    reply.push_str("MSSP-REPLY-END\r\n");
+    reply
+}
+
 fn broadcast(game: &mut Game, connection_state: &mut ConnectionState, message: &str) {
     for connection in connection_state.connections.values() {
         if let Some(player) = &connection.player {
@@ -0,0 +1,634 @@
+//! Note boards, with persistent notes and per-player read tracking.
+//!
+//! There is a fixed set of boards (general, ideas, immortal), some of which
+//! need a level of trust to be seen at all. Each board is saved into its own
+//! file in `data/notes/` whenever it changes, and loaded back when a new
+//! world is created, so notes survive restarts and crashes.
+//!
+//! A player writes a note by starting a draft with `note write`, filling it in
+//! with `note to`, `note subject` and `note +`, and then posting it with
+//! `note post` to their current board.
+//!
+//! The current board is the one selected with `board <name>`, unless there is
+//! an object with a board attached nearby (e.g. "a large bulletin board"), in
+//! which case that board is used instead.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{agent::EntityAgent, echo, files::Files, state::unix_time, trust::Trust};
+
+/// Name, description, days until notes expire (0 means never), and the trust
+/// needed to list, read and post on the board
+static BOARDS: &[(&str, &str, u64, Trust)] = &[
+    ("general", "General discussion", 21, Trust::Player),
+    ("ideas", "Suggestions for the realm", 60, Trust::Player),
+    (
+        "immortal",
+        "Announcements and discussion for immortals",
+        0,
+        Trust::Immortal,
+    ),
+];
+
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+pub(crate) struct NoteBoards {
+    boards: Vec<Board>,
+    drafts: BTreeMap<String, Note>,
+    selected_board: BTreeMap<String, usize>,
+}
+
+pub(crate) struct Board {
+    pub name: &'static str,
+    pub description: &'static str,
+    expire_days: u64,
+    trust: Trust,
+    contents: BoardContents,
+    changed: bool,
+}
+
+/// The part of a board that is saved to disk.
+#[derive(Serialize, Deserialize, Default)]
+struct BoardContents {
+    notes: Vec<Note>,
+    /// The date of the last note read by each player
+    last_read: BTreeMap<String, u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub(crate) struct Note {
+    pub sender: String,
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    /// Seconds since the Unix epoch; unique within a board
+    pub date: u64,
+}
+
+impl Note {
+    fn is_visible_to(&self, player: &str) -> bool {
+        self.sender == player
+            || self
+                .to
+                .split_whitespace()
+                .any(|to| to.eq_ignore_ascii_case("all") || to.eq_ignore_ascii_case(player))
+    }
+}
+
+impl Board {
+    fn file_path(&self) -> String {
+        format!("data/notes/{}.json", self.name)
+    }
+
+    /// Notes this player may see, numbered starting from 1.
+    fn visible_notes<'b>(&'b self, player: &'b str) -> impl Iterator<Item = (usize, &'b Note)> {
+        self.contents
+            .notes
+            .iter()
+            .filter(move |note| note.is_visible_to(player))
+            .enumerate()
+            .map(|(index, note)| (index + 1, note))
+    }
+
+    fn last_read(&self, player: &str) -> u64 {
+        self.contents.last_read.get(player).copied().unwrap_or(0)
+    }
+
+    pub fn unread_count(&self, player: &str) -> usize {
+        let last_read = self.last_read(player);
+
+        self.visible_notes(player)
+            .filter(|(_number, note)| note.date > last_read && note.sender != player)
+            .count()
+    }
+
+    fn mark_read(&mut self, player: &str, date: u64) {
+        if date > self.last_read(player) {
+            self.contents.last_read.insert(player.to_string(), date);
+            self.changed = true;
+        }
+    }
+
+    fn post(&mut self, mut note: Note, now: u64) {
+        // Dates are used to track which notes were read, so keep them unique.
+        let last_date = self.contents.notes.last().map(|note| note.date);
+        note.date = match last_date {
+            Some(date) if date >= now => date + 1,
+            _ => now,
+        };
+
+        self.contents.notes.push(note);
+        self.changed = true;
+    }
+
+    fn expire_notes(&mut self, now: u64) {
+        if self.expire_days == 0 {
+            return;
+        }
+
+        let oldest_allowed = now.saturating_sub(self.expire_days * SECONDS_PER_DAY);
+        let notes = self.contents.notes.len();
+
+        self.contents
+            .notes
+            .retain(|note| note.date >= oldest_allowed);
+
+        if self.contents.notes.len() != notes {
+            self.changed = true;
+        }
+    }
+}
+
+impl NoteBoards {
+    pub fn load(files: &dyn Files) -> Self {
+        let now = unix_time();
+        let mut boards = Vec::with_capacity(BOARDS.len());
+
+        for (name, description, expire_days, trust) in BOARDS {
+            let mut board = Board {
+                name,
+                description,
+                expire_days: *expire_days,
+                trust: *trust,
+                contents: BoardContents::default(),
+                changed: false,
+            };
+
+            let path = board.file_path();
+
+            match files.read_file_raw(&path) {
+                Ok(bytes) => {
+                    board.contents = serde_json::from_slice(&bytes)
+                        .unwrap_or_else(|err| panic!("Could not parse {}: {}", path, err));
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+                Err(err) => panic!("Could not read {}: {}", path, err),
+            }
+
+            board.expire_notes(now);
+            boards.push(board);
+        }
+
+        NoteBoards {
+            boards,
+            drafts: BTreeMap::new(),
+            selected_board: BTreeMap::new(),
+        }
+    }
+
+    pub fn save_changes(&mut self, files: &dyn Files) -> Result<(), std::io::Error> {
+        for board in &mut self.boards {
+            if !board.changed {
+                continue;
+            }
+
+            let contents =
+                serde_json::to_vec_pretty(&board.contents).expect("Notes should serialize");
+            files.write_file_raw(&board.file_path(), &contents)?;
+            board.changed = false;
+        }
+
+        Ok(())
+    }
+
+    pub fn boards(&self) -> impl Iterator<Item = &Board> {
+        self.boards.iter()
+    }
+
+    fn board_index(&self, name: &str) -> Option<usize> {
+        self.boards
+            .iter()
+            .position(|board| board.name.eq_ignore_ascii_case(name))
+    }
+}
+
+// Note commands
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_note(&mut self, words: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can read and write notes.\r\n");
+                return;
+            }
+        };
+
+        match *words {
+            [] | ["list"] => self.do_note_list(&player),
+            ["read"] => self.do_note_read(&player, None),
+            ["read", number] => self.do_note_read(&player, Some(number)),
+            [number] if number.parse::<usize>().is_ok() => self.do_note_read(&player, Some(number)),
+            ["write"] => self.do_note_write(&player, None),
+            ["write", ref subject @ ..] => self.do_note_write(&player, Some(&subject.join(" "))),
+            ["to", ref to @ ..] if !to.is_empty() => self.do_note_to(&player, &to.join(" ")),
+            ["subject", ref subject @ ..] if !subject.is_empty() => {
+                self.do_note_subject(&player, &subject.join(" "))
+            }
+            ["+", ref line @ ..] => self.do_note_add_line(&player, &line.join(" ")),
+            ["-"] => self.do_note_remove_line(&player),
+            ["show"] => self.do_note_show(&player),
+            ["clear"] => self.do_note_clear(&player),
+            ["post"] | ["send"] => self.do_note_post(&player),
+            ["remove", number] => self.do_note_remove(&player, number),
+            ["catchup"] => self.do_note_catchup(&player),
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: `Wnote`^ [`Wlist`^|`Wread`^ [number]|`Wwrite`^ [subject]|`Wto`^ <names>|\
+                    `Wsubject`^ <text>|\r\n       `W+`^ <line>|`W-`^|`Wshow`^|`Wclear`^|`Wpost`^|\
+                    `Wremove`^ <number>|`Wcatchup`^]\r\n"
+                );
+            }
+        }
+    }
+
+    pub fn do_board(&mut self, board_name: Option<&str>) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can read and write notes.\r\n");
+                return;
+            }
+        };
+
+        let board_name = match board_name {
+            Some(board_name) => board_name,
+            None => {
+                let current = self.current_board(&player);
+                let mut info = self.players.info(&myself);
+                echo!(info, "Note boards:\r\n");
+                for (index, board) in self.note_boards.boards().enumerate() {
+                    if self.accounts.trust(&player) < board.trust {
+                        continue;
+                    }
+                    echo!(
+                        info,
+                        "{} `W{:<10}`^ `g{:>3}`^ unread - {}\r\n",
+                        if index == current { "*" } else { " " },
+                        board.name,
+                        board.unread_count(&player),
+                        board.description,
+                    );
+                }
+                echo!(
+                    info,
+                    "Use '`Wboard <name>`^' to change your current board.\r\n"
+                );
+                return;
+            }
+        };
+
+        let board_index = self
+            .note_boards
+            .board_index(board_name)
+            .filter(|index| self.may_use_board(&player, *index));
+
+        match board_index {
+            Some(index) => {
+                self.note_boards
+                    .selected_board
+                    .insert(player.clone(), index);
+                let board_name = self.note_boards.boards[index].name;
+                echo!(
+                    self.info(),
+                    "You are now reading the `W{}`^ board.\r\n",
+                    board_name
+                );
+                if self.current_board(&player) != index {
+                    echo!(
+                        self.info(),
+                        "The board in front of you will be used while you are near it, though.\r\n"
+                    );
+                }
+            }
+            None => {
+                echo!(
+                    self.info(),
+                    "There is no such board; type '`Wboard`^' for a list.\r\n"
+                );
+            }
+        }
+    }
+
+    /// Show how many unread notes a player has, e.g. when logging in.
+    pub fn echo_unread_notes(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player,
+            None => return,
+        };
+
+        let trust = self.accounts.trust(player);
+        let mut info = self.players.info(&myself);
+        for board in self.note_boards.boards() {
+            if trust < board.trust {
+                continue;
+            }
+            let unread = board.unread_count(player);
+            if unread > 0 {
+                echo!(
+                    info,
+                    "You have `W{}`^ unread note{} on the `W{}`^ board.\r\n",
+                    unread,
+                    if unread == 1 { "" } else { "s" },
+                    board.name
+                );
+            }
+        }
+    }
+
+    /// Find a board attached to an object in the room or in the inventory.
+    fn nearby_board(&self) -> Option<usize> {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        myself
+            .objects()
+            .chain(myself.room().objects())
+            .filter_map(|object| object.components().object.as_ref()?.board.as_deref())
+            .find_map(|board_name| self.note_boards.board_index(board_name))
+    }
+
+    fn may_use_board(&self, player: &str, board_index: usize) -> bool {
+        self.accounts.trust(player) >= self.note_boards.boards[board_index].trust
+    }
+
+    /// The board nearby, or else the selected one, as long as the player is
+    /// trusted with it; the general board otherwise.
+    fn current_board(&self, player: &str) -> usize {
+        self.nearby_board()
+            .filter(|index| self.may_use_board(player, *index))
+            .or_else(|| {
+                self.note_boards
+                    .selected_board
+                    .get(player)
+                    .copied()
+                    .filter(|index| self.may_use_board(player, *index))
+            })
+            .unwrap_or(0)
+    }
+
+    fn do_note_list(&mut self, player: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let board = &self.note_boards.boards[self.current_board(player)];
+        let last_read = board.last_read(player);
+
+        let mut info = self.players.info(&myself);
+        echo!(info, "Notes on the `W{}`^ board:\r\n", board.name);
+
+        let mut empty = true;
+        for (number, note) in board.visible_notes(player) {
+            empty = false;
+            let unread = note.date > last_read && note.sender != player;
+            echo!(
+                info,
+                "`W[{:>3}{}]`^ `M{}`^: {}\r\n",
+                number,
+                if unread { "`RN`W" } else { " " },
+                note.sender,
+                note.subject
+            );
+        }
+
+        if empty {
+            echo!(info, "There are no notes for you here.\r\n");
+        }
+    }
+
+    fn do_note_read(&mut self, player: &str, number: Option<&str>) {
+        let board_index = self.current_board(player);
+        let board = &self.note_boards.boards[board_index];
+        let last_read = board.last_read(player);
+
+        let found = match number {
+            Some(number) => match number.parse::<usize>() {
+                Ok(number) => board
+                    .visible_notes(player)
+                    .find(|(note_number, _note)| *note_number == number)
+                    .ok_or("There is no note with that number."),
+                Err(_) => Err("Syntax: '`Wnote read <number>`^'"),
+            },
+            None => board
+                .visible_notes(player)
+                .find(|(_number, note)| note.date > last_read && note.sender != player)
+                .ok_or("You have no unread notes on this board."),
+        };
+
+        let (number, note) = match found {
+            Ok((number, note)) => (number, note.clone()),
+            Err(error) => {
+                echo!(self.info(), "{}\r\n", error);
+                return;
+            }
+        };
+
+        self.note_boards.boards[board_index].mark_read(player, note.date);
+
+        let now = unix_time();
+        let mut info = self.info();
+        echo!(
+            info,
+            "`W[{}] `M{}`W: {}`^\r\n",
+            number,
+            note.sender,
+            note.subject
+        );
+        echo!(
+            info,
+            "`STo: {}, written {}.`^\r\n",
+            note.to,
+            format_age(now.saturating_sub(note.date))
+        );
+        echo!(info, "{}", note.text);
+    }
+
+    fn do_note_write(&mut self, player: &str, subject: Option<&str>) {
+        let draft = self
+            .note_boards
+            .drafts
+            .entry(player.to_string())
+            .or_insert_with(|| Note {
+                sender: player.to_string(),
+                to: "all".to_string(),
+                ..Default::default()
+            });
+
+        if let Some(subject) = subject {
+            draft.subject = subject.to_string();
+        }
+
+        echo!(
+            self.info(),
+            "You are writing a note. Use '`Wnote to`^', '`Wnote subject`^' and '`Wnote +`^' to\r\n\
+            fill it in, '`Wnote show`^' to review it, and '`Wnote post`^' when you are done.\r\n"
+        );
+    }
+
+    fn draft(&mut self, player: &str) -> Option<&mut Note> {
+        if !self.note_boards.drafts.contains_key(player) {
+            echo!(
+                self.info(),
+                "You aren't writing a note; start one with '`Wnote write`^'.\r\n"
+            );
+            return None;
+        }
+
+        self.note_boards.drafts.get_mut(player)
+    }
+
+    fn do_note_to(&mut self, player: &str, to: &str) {
+        if let Some(draft) = self.draft(player) {
+            draft.to = to.to_string();
+            echo!(self.info(), "The note is now addressed to: {}\r\n", to);
+        }
+    }
+
+    fn do_note_subject(&mut self, player: &str, subject: &str) {
+        if let Some(draft) = self.draft(player) {
+            draft.subject = subject.to_string();
+            echo!(self.info(), "Subject set.\r\n");
+        }
+    }
+
+    fn do_note_add_line(&mut self, player: &str, line: &str) {
+        if let Some(draft) = self.draft(player) {
+            draft.text.push_str(line);
+            draft.text.push_str("\r\n");
+            echo!(self.info(), "Line added.\r\n");
+        }
+    }
+
+    fn do_note_remove_line(&mut self, player: &str) {
+        if let Some(draft) = self.draft(player) {
+            let without_newline = draft.text.trim_end_matches("\r\n");
+            let line_start = without_newline.rfind("\r\n").map(|end| end + 2);
+            let removed = line_start.is_some() || !without_newline.is_empty();
+            draft.text.truncate(line_start.unwrap_or(0));
+
+            if removed {
+                echo!(self.info(), "Last line removed.\r\n");
+            } else {
+                echo!(self.info(), "The note has no lines to remove.\r\n");
+            }
+        }
+    }
+
+    fn do_note_show(&mut self, player: &str) {
+        if let Some(draft) = self.draft(player) {
+            let draft = draft.clone();
+            let mut info = self.info();
+            echo!(info, "`WFrom: `M{}`^\r\n", draft.sender);
+            echo!(info, "`WTo: `^{}\r\n", draft.to);
+            echo!(info, "`WSubject: `^{}\r\n", draft.subject);
+            echo!(info, "{}", draft.text);
+        }
+    }
+
+    fn do_note_clear(&mut self, player: &str) {
+        if self.note_boards.drafts.remove(player).is_some() {
+            echo!(self.info(), "Note discarded.\r\n");
+        } else {
+            echo!(self.info(), "You aren't writing a note.\r\n");
+        }
+    }
+
+    fn do_note_post(&mut self, player: &str) {
+        let error = match self.note_boards.drafts.get(player) {
+            None => Some("You aren't writing a note; start one with '`Wnote write`^'."),
+            Some(draft) if draft.subject.is_empty() => {
+                Some("Your note needs a subject; use '`Wnote subject <text>`^'.")
+            }
+            Some(draft) if draft.text.is_empty() => {
+                Some("Your note is empty; add lines with '`Wnote + <text>`^'.")
+            }
+            Some(_) => None,
+        };
+
+        if let Some(error) = error {
+            echo!(self.info(), "{}\r\n", error);
+            return;
+        }
+
+        let draft = self
+            .note_boards
+            .drafts
+            .remove(player)
+            .expect("Checked above");
+        let board_index = self.current_board(player);
+        let board = &mut self.note_boards.boards[board_index];
+        board.post(draft, unix_time());
+        let board_name = board.name;
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let mut act = self.players.act_alone(&myself);
+        echo!(
+            act.myself(),
+            "You post your note on the `W{}`^ board.\r\n",
+            board_name
+        );
+        echo!(act.others(), "$^$n posts a note.\r\n");
+    }
+
+    fn do_note_remove(&mut self, player: &str, number: &str) {
+        let board_index = self.current_board(player);
+        let board = &self.note_boards.boards[board_index];
+
+        let date = number.parse::<usize>().ok().and_then(|number| {
+            board
+                .visible_notes(player)
+                .find(|(note_number, _note)| *note_number == number)
+                .map(|(_number, note)| (note.date, note.sender == player))
+        });
+
+        match date {
+            Some((date, true)) => {
+                let board = &mut self.note_boards.boards[board_index];
+                board.contents.notes.retain(|note| note.date != date);
+                board.changed = true;
+                echo!(self.info(), "Note removed.\r\n");
+            }
+            Some((_date, false)) => {
+                echo!(self.info(), "You can only remove notes that you wrote.\r\n");
+            }
+            None => {
+                echo!(self.info(), "There is no note with that number.\r\n");
+            }
+        }
+    }
+
+    fn do_note_catchup(&mut self, player: &str) {
+        let board_index = self.current_board(player);
+        let board = &mut self.note_boards.boards[board_index];
+
+        if let Some(last_note) = board.contents.notes.last() {
+            let date = last_note.date;
+            board.mark_read(player, date);
+        }
+
+        echo!(
+            self.info(),
+            "All notes on this board are now marked as read.\r\n"
+        );
+    }
+}
+
+pub(crate) fn format_age(seconds: u64) -> String {
+    let (amount, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / SECONDS_PER_DAY, "day"),
+    };
+
+    format!(
+        "{} {}{} ago",
+        amount,
+        unit,
+        if amount == 1 { "" } else { "s" }
+    )
+}
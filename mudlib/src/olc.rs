@@ -0,0 +1,740 @@
+//! Online creation: changing areas from within the game, and saving them.
+//!
+//! `aedit` creates new areas and changes their settings (name, vnum range,
+//! builders, security, credits and reset interval), which are kept with the rest of the
+//! area data in `WorldState::world`. `asave` writes areas back to their files
+//! in `data/area` with the area writer from `crate::save`, or as JSON with
+//! `crate::export` for areas from JSON files, and adds new areas to the area
+//! list so that they are loaded on the next start. CircleMUD zones are saved
+//! as Dawn of Time areas, in place of the zone. Areas of other sources
+//! (see `crate::config::AreaSource`) aren't saved, since their files have
+//! the vnums and names they had before they were moved.
+//!
+//! Like snapshots, `asave` only queues a request; files are written later by
+//! `WorldState::run_area_saves()`, which has access to them.
+//!
+//! Builders may only change areas that list them as builders, and whose
+//! security isn't above what their trust allows (see `Trust::security()`),
+//! and only on the vnums an immortal has assigned to them with `vnums`, so
+//! that they can't damage the rest of the world by accident. Immortals may
+//! build anywhere.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    agent::EntityAgent,
+    circle::stem,
+    echo,
+    export::areas_json,
+    files::Files,
+    load::DEFAULT_RESET_INTERVAL,
+    mpedit::MobProgDraft,
+    save::{save_area, save_area_list},
+    snapshots::tell,
+    world::{AreaData, AreaFormat, Encounter, Vnum, World},
+    WorldState,
+};
+
+const AREA_DIRECTORY: &str = "data/area";
+
+pub(crate) struct Olc {
+    save_requests: Vec<SaveRequest>,
+    /// Mobprogs being edited with `mpedit`, by player
+    pub mobprog_drafts: BTreeMap<String, MobProgDraft>,
+}
+
+struct SaveRequest {
+    by: String,
+    areas: AreaSelection,
+}
+
+enum AreaSelection {
+    /// Indices into `World::areas`
+    Areas(Vec<usize>),
+    All,
+}
+
+impl Olc {
+    pub fn new() -> Self {
+        Olc {
+            save_requests: Vec::new(),
+            mobprog_drafts: BTreeMap::new(),
+        }
+    }
+}
+
+/// Write the selected areas, and the area list if new areas were written.
+/// Returns the short names of the saved areas.
+fn save_areas(
+    world: &mut World,
+    files: &dyn Files,
+    selection: &AreaSelection,
+) -> Result<Vec<String>, std::io::Error> {
+    let indices: Vec<usize> = match selection {
+        AreaSelection::Areas(indices) => indices.clone(),
+        AreaSelection::All => (0..world.areas.len()).collect(),
+    }
+    .into_iter()
+    .filter(|&index| world.areas[index].0.source.is_none())
+    .collect();
+
+    let mut area_list_changed = matches!(selection, AreaSelection::All);
+    let mut saved = Vec::with_capacity(indices.len());
+
+    for index in indices {
+        // CircleMUD zones and TOML areas are saved as Dawn of Time areas,
+        // which take their place in the area list
+        let area_data = &mut world.areas[index].0;
+        if let AreaFormat::Circle | AreaFormat::Toml = area_data.format {
+            area_data.file_name = format!("{}.are", stem(&area_data.file_name));
+            area_data.on_disk = false;
+        }
+
+        // JSON files are written again with all of their areas
+        let file_name = world.areas[index].0.file_name.clone();
+        let json = world.areas[index].0.format == AreaFormat::Json;
+        let (contents, in_file) = if json {
+            let in_file: Vec<usize> = (0..world.areas.len())
+                .filter(|&other| world.areas[other].0.file_name == file_name)
+                .collect();
+            (areas_json(world, &in_file), in_file)
+        } else {
+            (save_area(world, index), vec![index])
+        };
+
+        let path = format!("{}/{}", AREA_DIRECTORY, file_name);
+        files.write_file_raw(&path, contents.as_bytes())?;
+
+        for index in in_file {
+            let area_data = &mut world.areas[index].0;
+            area_list_changed |= !area_data.on_disk;
+            area_data.on_disk = true;
+            area_data.changed = false;
+            area_data.format = if json {
+                AreaFormat::Json
+            } else {
+                AreaFormat::DawnOfTime
+            };
+        }
+        saved.push(world.areas[index].0.short_name.clone());
+    }
+
+    if area_list_changed {
+        let path = format!("{}/arealist.txt", AREA_DIRECTORY);
+        files.write_file_raw(&path, save_area_list(world).as_bytes())?;
+    }
+
+    Ok(saved)
+}
+
+/// Carry out `asave` commands.
+pub(crate) fn run_area_saves(world_state: &mut WorldState, files: &dyn Files) {
+    for request in std::mem::take(&mut world_state.olc.save_requests) {
+        let message = match save_areas(&mut world_state.world, files, &request.areas) {
+            Ok(saved) if saved.is_empty() => "There are no changed areas to save.\r\n".to_string(),
+            Ok(saved) => {
+                println!("{} saved the areas {}.", request.by, saved.join(", "));
+                format!("Saved the areas: `W{}`^.\r\n", saved.join("`^, `W"))
+            }
+            Err(err) => format!("Could not save the areas: {}\r\n", err),
+        };
+
+        tell(world_state, &request.by, &message);
+    }
+}
+
+fn vnum_range_is_set(vnums: (Vnum, Vnum)) -> bool {
+    vnums != (Vnum(0), Vnum(0))
+}
+
+/// Find the area whose range of vnums includes a vnum.
+pub(crate) fn area_of_vnum(world: &World, vnum: usize) -> Option<usize> {
+    world.areas.iter().position(|(area, _resets)| {
+        vnum_range_is_set(area.vnums) && area.vnums.0 .0 <= vnum && vnum <= area.vnums.1 .0
+    })
+}
+
+/// Check if a player is listed as one of the builders of an area.
+fn is_builder_of(area: &AreaData, player: &str) -> bool {
+    area.builders
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .any(|builder| builder.eq_ignore_ascii_case(player))
+}
+
+/// Find an area by its short name.
+pub(crate) fn area_named(world: &World, short_name: &str) -> Option<usize> {
+    world
+        .areas
+        .iter()
+        .position(|(area, _resets)| area.short_name == short_name)
+}
+
+/// Check that an area (or a new one, without an index) can have the given
+/// range of vnums: it may not overlap with other areas, and must hold all
+/// rooms, mobiles, objects and mobprogs of the area.
+fn check_vnums(
+    world: &World,
+    index: Option<usize>,
+    first: usize,
+    last: usize,
+) -> Result<(), String> {
+    if first == 0 || first > last {
+        return Err("That's not a valid range of vnums.".to_string());
+    }
+
+    for (other_index, (other, _resets)) in world.areas.iter().enumerate() {
+        let (other_first, other_last) = other.vnums;
+        if Some(other_index) != index
+            && vnum_range_is_set(other.vnums)
+            && first <= other_last.0
+            && other_first.0 <= last
+        {
+            return Err(format!(
+                "Those vnums overlap with the area {} ({} to {}).",
+                other.short_name, other_first.0, other_last.0
+            ));
+        }
+    }
+
+    if let Some(index) = index {
+        let short_name = &world.areas[index].0.short_name;
+        let vnums = world
+            .rooms
+            .iter()
+            .filter(|room| room.area == *short_name)
+            .map(|room| room.vnum)
+            .chain(
+                world
+                    .mobiles
+                    .iter()
+                    .filter(|mobile| mobile.area == *short_name)
+                    .map(|mobile| mobile.vnum),
+            )
+            .chain(
+                world
+                    .objects
+                    .iter()
+                    .filter(|object| object.area == *short_name)
+                    .map(|object| object.vnum),
+            )
+            .chain(
+                world
+                    .mobprogs
+                    .iter()
+                    .filter(|mobprog| mobprog.area == *short_name)
+                    .map(|mobprog| mobprog.vnum),
+            );
+
+        for vnum in vnums {
+            if vnum.0 < first || vnum.0 > last {
+                return Err(format!(
+                    "The area already uses vnum {}, which is outside of that range.",
+                    vnum.0
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Check that the current player may change an area, and things with the
+    /// given range of vnums, telling them why not otherwise.
+    pub(crate) fn may_build(
+        &mut self,
+        area_index: Option<usize>,
+        vnums: Option<(usize, usize)>,
+    ) -> bool {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let player = match myself.player_name() {
+            Some(player) => player,
+            None => {
+                echo!(self.info(), "Only players can build.\r\n");
+                return false;
+            }
+        };
+        if self.accounts.is_immortal(player) {
+            return true;
+        }
+
+        let listed =
+            area_index.is_none_or(|index| is_builder_of(&self.world.areas[index].0, player));
+        let security = self.accounts.trust(player).security();
+        let secure = area_index.is_some_and(|index| self.world.areas[index].0.security > security);
+
+        let refusal = match (vnums, self.accounts.builder_vnums(player)) {
+            _ if !listed => "You are not one of the builders of that area.".to_string(),
+            _ if secure => "That area is too secure for you to build in.".to_string(),
+            (Some(_), None) => {
+                "You have no vnums to build on; ask an immortal to assign you some.".to_string()
+            }
+            (Some((first, last)), Some((own_first, own_last)))
+                if first < own_first || last > own_last =>
+            {
+                format!("You may only build on vnums {} to {}.", own_first, own_last)
+            }
+            _ => return true,
+        };
+
+        echo!(self.players.info(&myself), "{}\r\n", refusal);
+        false
+    }
+
+    /// Find an area by its short name or file name.
+    pub(crate) fn find_area(&self, name: &str) -> Option<usize> {
+        self.world.find_area(name)
+    }
+
+    pub fn do_aedit(&mut self, words: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can edit areas.\r\n");
+                return;
+            }
+        };
+
+        match words {
+            [] | ["list"] => self.do_aedit_list(),
+            ["create", short_name, first, last] => {
+                match (first.parse::<usize>(), last.parse::<usize>()) {
+                    (Ok(first), Ok(last)) => self.do_aedit_create(&player, short_name, first, last),
+                    _ => echo!(self.info(), "That's not a valid range of vnums.\r\n"),
+                }
+            }
+            [name, ref command @ ..] => {
+                let index = match self.find_area(name) {
+                    Some(index) => index,
+                    None => {
+                        echo!(self.info(), "There is no area named like that.\r\n");
+                        return;
+                    }
+                };
+
+                let text = command.get(1..).unwrap_or_default().join(" ");
+                if text.contains('~') {
+                    echo!(self.info(), "Area settings can't contain '~'.\r\n");
+                    return;
+                }
+
+                if !matches!(command, [] | ["show"]) && !self.may_build(Some(index), None) {
+                    return;
+                }
+
+                match command {
+                    [] | ["show"] => self.do_aedit_show(index),
+                    ["name", _, ..] => {
+                        let area_data = &mut self.world.areas[index].0;
+                        area_data.name = text;
+                        area_data.changed = true;
+                        echo!(self.info(), "Area name set.\r\n");
+                    }
+                    ["credits", ..] => {
+                        let area_data = &mut self.world.areas[index].0;
+                        area_data.credits = text;
+                        area_data.changed = true;
+                        echo!(self.info(), "Area credits set.\r\n");
+                    }
+                    ["builders", _, ..] => {
+                        let area_data = &mut self.world.areas[index].0;
+                        area_data.builders = if text == "none" { String::new() } else { text };
+                        area_data.changed = true;
+                        echo!(self.info(), "Area builders set.\r\n");
+                    }
+                    ["security", security] => self.do_aedit_security(&player, index, security),
+                    ["vnums", first, last] => {
+                        self.do_aedit_vnums(index, first, last);
+                    }
+                    ["reset", minutes] => match minutes.parse::<u16>() {
+                        Ok(minutes) if minutes > 0 => {
+                            let area_data = &mut self.world.areas[index].0;
+                            area_data.reset_interval = minutes;
+                            area_data.changed = true;
+                            echo!(
+                                self.info(),
+                                "The area now resets every {} minutes.\r\n",
+                                minutes
+                            );
+                        }
+                        _ => echo!(self.info(), "That's not a valid number of minutes.\r\n"),
+                    },
+                    ["encounter", sector, vnum, limit] => {
+                        self.do_aedit_encounter(index, sector, vnum, limit);
+                    }
+                    _ => self.echo_aedit_syntax(),
+                }
+            }
+        }
+    }
+
+    fn echo_aedit_syntax(&mut self) {
+        echo!(
+            self.info(),
+            "Syntax: '`Waedit`^', '`Waedit create <short name> <first vnum> <last vnum>`^',\r\n\
+            '`Waedit <area>`^', '`Waedit <area> name <name>`^', \
+            '`Waedit <area> credits <credits>`^',\r\n\
+            '`Waedit <area> builders <names>`^', '`Waedit <area> builders none`^',\r\n\
+            '`Waedit <area> security <0-9>`^', '`Waedit <area> vnums <first> <last>`^', '`Waedit <area> reset <minutes>`^',\r\n\
+            '`Waedit <area> encounter <sector> <mobile vnum> <limit>`^'.\r\n"
+        );
+    }
+
+    fn do_aedit_list(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let own_vnums = myself
+            .player_name()
+            .and_then(|player| self.accounts.builder_vnums(player));
+        let mut info = self.players.info(&myself);
+
+        if let Some((first, last)) = own_vnums {
+            echo!(info, "You may build on vnums {} to {}.\r\n", first, last);
+        }
+        echo!(info, "Areas (`Y*`^ has unsaved changes):\r\n");
+        for (area, _resets) in &self.world.areas {
+            let vnums = if vnum_range_is_set(area.vnums) {
+                format!("{}-{}", area.vnums.0 .0, area.vnums.1 .0)
+            } else {
+                "no vnums".to_string()
+            };
+
+            echo!(
+                info,
+                "  {}`W{:<14}`^ {:<14} {:<12} {}\r\n",
+                if area.changed { "`Y*`^" } else { " " },
+                area.short_name,
+                area.file_name,
+                vnums,
+                area.builders
+            );
+        }
+    }
+
+    fn do_aedit_show(&mut self, index: usize) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let area = &self.world.areas[index].0;
+        let mut info = self.players.info(&myself);
+
+        let or_none = |text: &str| {
+            if text.is_empty() {
+                "none".to_string()
+            } else {
+                text.to_string()
+            }
+        };
+
+        echo!(info, "Area `W{}`^ ({})\r\n", area.short_name, area.name);
+        echo!(
+            info,
+            "File: {}{}\r\n",
+            area.file_name,
+            if !area.on_disk {
+                " (not saved yet)"
+            } else if area.changed {
+                " (unsaved changes)"
+            } else {
+                ""
+            }
+        );
+        echo!(info, "Format: {}\r\n", area.format.name());
+        if let Some(source) = &area.source {
+            echo!(
+                info,
+                "Loaded from {}, with vnums moved up by {}\r\n",
+                source.directory,
+                source.vnum_offset
+            );
+        }
+        if vnum_range_is_set(area.vnums) {
+            echo!(
+                info,
+                "Vnums: {} to {}\r\n",
+                area.vnums.0 .0,
+                area.vnums.1 .0
+            );
+        } else {
+            echo!(info, "Vnums: none\r\n");
+        }
+        echo!(info, "Builders: {}\r\n", or_none(&area.builders));
+        echo!(info, "Security: {}\r\n", area.security);
+        echo!(info, "Credits: {}\r\n", or_none(&area.credits));
+        echo!(info, "Resets every {} minutes\r\n", area.reset_interval);
+        for encounter in &area.encounters {
+            echo!(
+                info,
+                "Encounter in {}: mobile {}, at most {}\r\n",
+                encounter.sector,
+                encounter.vnum.0,
+                encounter.limit
+            );
+        }
+    }
+
+    /// Change the security of an area, up to what the player may build at,
+    /// so that they don't lock themselves out.
+    fn do_aedit_security(&mut self, player: &str, index: usize, security: &str) {
+        let own_security = self.accounts.trust(player).security();
+        match security.parse::<u8>() {
+            Ok(security) if security > own_security => {
+                echo!(
+                    self.info(),
+                    "You may only set the security of an area up to {}.\r\n",
+                    own_security
+                );
+            }
+            Ok(security) if security <= 9 => {
+                let area_data = &mut self.world.areas[index].0;
+                area_data.security = security;
+                area_data.changed = true;
+                echo!(self.info(), "Area security set to {}.\r\n", security);
+            }
+            _ => echo!(self.info(), "The security must be from 0 to 9.\r\n"),
+        }
+    }
+
+    /// Add an encounter to an area, or change its limit; a limit of 0
+    /// removes it.
+    fn do_aedit_encounter(&mut self, index: usize, sector: &str, vnum: &str, limit: &str) {
+        let vnum = match vnum.parse::<usize>() {
+            Ok(vnum) if self.world.mobiles.get(vnum).map(|mobile| mobile.vnum.0) == Some(vnum) => {
+                Vnum(vnum)
+            }
+            _ => {
+                echo!(self.info(), "There is no mobile with that vnum.\r\n");
+                return;
+            }
+        };
+        let limit = match limit.parse::<u16>() {
+            Ok(limit) => limit,
+            Err(_) => {
+                echo!(self.info(), "That's not a valid limit.\r\n");
+                return;
+            }
+        };
+
+        let area_data = &mut self.world.areas[index].0;
+        area_data
+            .encounters
+            .retain(|encounter| encounter.sector != sector || encounter.vnum != vnum);
+        if limit > 0 {
+            area_data.encounters.push(Encounter {
+                sector: sector.to_string(),
+                vnum,
+                limit,
+            });
+        }
+        area_data.changed = true;
+
+        if limit > 0 {
+            echo!(
+                self.info(),
+                "Players may now run into mobile {} in {} rooms, {} at most.\r\n",
+                vnum.0,
+                sector,
+                limit
+            );
+        } else {
+            echo!(self.info(), "Encounter removed.\r\n");
+        }
+    }
+
+    fn do_aedit_create(&mut self, player: &str, short_name: &str, first: usize, last: usize) {
+        let file_name = format!("{}.are", short_name.to_lowercase());
+
+        if !short_name.chars().all(|c| c.is_ascii_alphanumeric()) || short_name.len() > 20 {
+            echo!(
+                self.info(),
+                "Short names of areas must be a single word of at most 20 letters or digits.\r\n"
+            );
+            return;
+        } else if self.find_area(short_name).is_some() || self.find_area(&file_name).is_some() {
+            echo!(self.info(), "That area already exists.\r\n");
+            return;
+        } else if let Err(err) = check_vnums(self.world, None, first, last) {
+            echo!(self.info(), "{}\r\n", err);
+            return;
+        } else if !self.may_build(None, Some((first, last))) {
+            return;
+        }
+
+        self.world.areas.push((
+            AreaData {
+                name: short_name.to_string(),
+                short_name: short_name.to_string(),
+                vnums: (Vnum(first), Vnum(last)),
+                credits: String::new(),
+                continent: String::new(),
+                builders: player.to_string(),
+                security: self.accounts.trust(player).security(),
+                reset_interval: DEFAULT_RESET_INTERVAL,
+                encounters: Vec::new(),
+                file_name,
+                source: None,
+                on_disk: false,
+                changed: true,
+                format: AreaFormat::DawnOfTime,
+            },
+            Vec::new(),
+        ));
+        println!(
+            "{} created the area {} with vnums {} to {}.",
+            player, short_name, first, last
+        );
+
+        echo!(
+            self.info(),
+            "Created the area `W{}`^, with vnums {} to {}. Use '`Wasave {}`^' to save it.\r\n",
+            short_name,
+            first,
+            last,
+            short_name
+        );
+    }
+
+    fn do_aedit_vnums(&mut self, index: usize, first: &str, last: &str) {
+        let (first, last) = match (first.parse::<usize>(), last.parse::<usize>()) {
+            (Ok(first), Ok(last)) => (first, last),
+            _ => {
+                echo!(self.info(), "That's not a valid range of vnums.\r\n");
+                return;
+            }
+        };
+
+        if let Err(err) = check_vnums(self.world, Some(index), first, last) {
+            echo!(self.info(), "{}\r\n", err);
+            return;
+        } else if !self.may_build(Some(index), Some((first, last))) {
+            return;
+        }
+
+        let area_data = &mut self.world.areas[index].0;
+        area_data.vnums = (Vnum(first), Vnum(last));
+        area_data.changed = true;
+        echo!(self.info(), "Area vnums set to {} to {}.\r\n", first, last);
+    }
+
+    /// Show or assign the vnums a builder may build on.
+    pub fn do_vnums(&mut self, words: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let (player_name, vnums) = match words {
+            [player_name] => (player_name, None),
+            [player_name, "none"] => (player_name, Some(None)),
+            [player_name, first, last] => match (first.parse::<usize>(), last.parse::<usize>()) {
+                (Ok(first), Ok(last)) if first > 0 && first <= last => {
+                    (player_name, Some(Some((first, last))))
+                }
+                _ => {
+                    echo!(self.info(), "That's not a valid range of vnums.\r\n");
+                    return;
+                }
+            },
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Wvnums <player>`^', '`Wvnums <player> <first> <last>`^', \
+                    '`Wvnums <player> none`^'.\r\n"
+                );
+                return;
+            }
+        };
+
+        let name = match self.accounts.account(player_name) {
+            Some(account) => account.name.clone(),
+            None => {
+                echo!(self.info(), "There is no character named like that.\r\n");
+                return;
+            }
+        };
+
+        let vnums = match vnums {
+            Some(vnums) => {
+                self.accounts.set_builder_vnums(&name, vnums);
+                println!(
+                    "{} set the vnums of {} to {:?}.",
+                    myself.player_name().unwrap_or("Someone"),
+                    name,
+                    vnums
+                );
+                vnums
+            }
+            None => self.accounts.builder_vnums(&name),
+        };
+
+        match vnums {
+            Some((first, last)) => echo!(
+                self.info(),
+                "{} may build on vnums {} to {}.\r\n",
+                name,
+                first,
+                last
+            ),
+            None => echo!(self.info(), "{} has no vnums to build on.\r\n", name),
+        }
+    }
+
+    pub fn do_asave(&mut self, words: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can save areas.\r\n");
+                return;
+            }
+        };
+
+        let immortal = self.accounts.is_immortal(&player);
+
+        let areas = match words {
+            ["changed"] => AreaSelection::Areas(
+                (0..self.world.areas.len())
+                    .filter(|index| {
+                        let area = &self.world.areas[*index].0;
+                        area.changed && (immortal || is_builder_of(area, &player))
+                    })
+                    .collect(),
+            ),
+            ["world"] if immortal => AreaSelection::All,
+            ["world"] => {
+                echo!(self.info(), "Only immortals can save the whole world.\r\n");
+                return;
+            }
+            [name] => match self.find_area(name) {
+                Some(index) if self.world.areas[index].0.source.is_some() => {
+                    let area = &self.world.areas[index].0;
+                    let message = format!(
+                        "The area {} was loaded from {} with its vnums moved, \
+                        so it can't be saved.\r\n",
+                        area.short_name,
+                        area.path(AREA_DIRECTORY)
+                    );
+                    echo!(self.info(), "{}", message);
+                    return;
+                }
+                Some(index) if self.may_build(Some(index), None) => {
+                    AreaSelection::Areas(vec![index])
+                }
+                Some(_) => return,
+                None => {
+                    echo!(self.info(), "There is no area named like that.\r\n");
+                    return;
+                }
+            },
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Wasave <area>`^', '`Wasave changed`^', '`Wasave world`^'.\r\n"
+                );
+                return;
+            }
+        };
+
+        self.olc
+            .save_requests
+            .push(SaveRequest { by: player, areas });
+    }
+}
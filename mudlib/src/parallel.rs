@@ -0,0 +1,28 @@
+//! Running the loading of the world on several threads, with rayon.
+//!
+//! Rayon's global thread pool isn't used: its threads would be left running
+//! after the world is loaded, and crash once the mudlib library they run in
+//! is unloaded to hot-swap it. A pool of its own is started for each load
+//! instead, and its threads are joined before it returns.
+
+/// Run `work` inside a thread pool of its own, so that the parallel
+/// iterators in it are spread over its threads. Where threads can't be
+/// started, e.g. in WASI, it runs on this thread.
+pub(crate) fn on_threads<R, F>(work: F) -> R
+where
+    R: Send,
+    F: FnOnce() -> R + Send,
+{
+    let mut work = Some(work);
+    let result = rayon::ThreadPoolBuilder::new()
+        .thread_name(|index| format!("loading-{}", index))
+        .build_scoped(
+            |thread| thread.run(),
+            |pool| pool.install(|| work.take().map(|work| work())),
+        );
+
+    match result {
+        Ok(Some(result)) => result,
+        _ => work.take().expect("Work should run once")(),
+    }
+}
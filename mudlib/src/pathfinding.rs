@@ -0,0 +1,203 @@
+//! Finding the way between rooms, and the things that walk it.
+//!
+//! `find_path()` searches the rooms breadth first for the shortest way from
+//! one room to another, through open exits only. Mobiles only go where they
+//! could wander (see `crate::wander`), which keeps them out of `no_mob`
+//! rooms, and in their area if they have to stay there.
+//!
+//! Hunting mobiles use it to chase their prey, players can `track` someone
+//! in the same area, and mobprogs can send a mobile walking to a room with
+//! `mob walk <vnum>`, one step every mobile pulse.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    agent::EntityAgent,
+    components::Mobile,
+    echo,
+    entity::{EntityId, EntityInfo, EntityWorld},
+    wander::can_wander_to,
+    world::{Vnum, World},
+    WorldState,
+};
+
+/// How many rooms are searched at most for a path
+const MAX_PATH_ROOMS: usize = 2000;
+
+/// The exits to take, one after the other, for someone to get from a room to
+/// another, if there is a way.
+pub(crate) fn find_path(
+    world: &World,
+    entity_world: &EntityWorld,
+    traveller: &EntityInfo,
+    from_room_id: EntityId,
+    to_room_id: EntityId,
+) -> Option<Vec<String>> {
+    // The exit taken into each room found so far, and where it was from
+    let mut came_from: HashMap<EntityId, Option<(EntityId, String)>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    came_from.insert(from_room_id, None);
+    queue.push_back(from_room_id);
+
+    while let Some(room_id) = queue.pop_front() {
+        if room_id == to_room_id {
+            let mut path = Vec::new();
+            let mut room_id = room_id;
+            while let Some(Some((previous_room_id, exit))) = came_from.remove(&room_id) {
+                path.push(exit);
+                room_id = previous_room_id;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if came_from.len() > MAX_PATH_ROOMS {
+            return None;
+        }
+
+        let room = entity_world.entity_info(room_id);
+        for (exit, next_room) in entity_world.adjacent_rooms(room_id) {
+            let closed = exit
+                .components()
+                .door
+                .as_ref()
+                .map(|door| door.closed)
+                .unwrap_or(false);
+            let next_room_id = next_room.entity_id();
+            if closed || came_from.contains_key(&next_room_id) {
+                continue;
+            }
+
+            if traveller.is_mobile() && !can_wander_to(world, traveller, &room, &next_room) {
+                continue;
+            }
+
+            came_from.insert(
+                next_room_id,
+                Some((room_id, exit.main_keyword().to_string())),
+            );
+            queue.push_back(next_room_id);
+        }
+    }
+
+    None
+}
+
+pub(crate) fn update_walking(world_state: &mut WorldState) {
+    let mut walkers = Vec::new();
+
+    for (entity, mobile) in world_state.entity_world.query::<&Mobile>() {
+        if let Some(walk_to) = mobile.walk_to {
+            walkers.push((entity.entity_id(), walk_to));
+        }
+    }
+
+    for (walker_id, walk_to) in walkers {
+        let mut agent = EntityAgent::new(world_state, walker_id);
+        agent.walk(walk_to);
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// The first exit to take for the current entity to get to a room.
+    pub(crate) fn first_step_to(&self, to_room_id: EntityId) -> Option<String> {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let from_room_id = self.entity_world.room_of(self.entity_id);
+
+        find_path(
+            self.world,
+            self.entity_world,
+            &myself,
+            from_room_id,
+            to_room_id,
+        )
+        .and_then(|path| path.into_iter().next())
+    }
+
+    fn set_walk_to(&mut self, walk_to: Option<Vnum>) {
+        let mut myself = self.entity_world.entity_info_mut(self.entity_id);
+        if let Some(mobile) = &mut myself.components().mobile {
+            mobile.walk_to = walk_to;
+        }
+    }
+
+    /// Take a step towards a room, and stop once there or if it can't be
+    /// reached.
+    fn walk(&mut self, walk_to: Vnum) {
+        let room_id = self.room_with_vnum(walk_to.0);
+
+        let step = room_id.and_then(|room_id| self.first_step_to(room_id));
+        if let Some(exit) = &step {
+            self.do_move(exit);
+        }
+
+        let here = self.entity_world.room_of(self.entity_id);
+        if step.is_none() || Some(here) == room_id {
+            self.set_walk_to(None);
+        }
+    }
+
+    pub fn do_mob_walk(&mut self, to_room: &str) {
+        let to_room_vnum: usize = match to_room.parse() {
+            Ok(vnum) => vnum,
+            Err(_) => {
+                echo!(self.info(), "Room '{}' is not a valid vnum.\r\n", to_room);
+                return;
+            }
+        };
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if !myself.is_mobile() {
+            echo!(
+                self.info(),
+                "But you are not a mobile! You walk on your own.\r\n"
+            );
+            return;
+        }
+
+        self.set_walk_to(Some(Vnum(to_room_vnum)));
+        echo!(self.info(), "Walking to room {}.\r\n", to_room_vnum);
+    }
+
+    pub fn do_track(&mut self, target: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let area = &myself.room().components().general.area;
+
+        let target = self
+            .entity_world
+            .entities_in_area(area)
+            .filter(|entity| entity.is_mobile() || entity.is_player())
+            .filter(|entity| entity.entity_id() != self.entity_id)
+            .filter(|entity| self.players.can_see(&myself, entity))
+            .find(|entity| {
+                entity
+                    .component_info()
+                    .keyword()
+                    .split_whitespace()
+                    .any(|word| word.eq_ignore_ascii_case(target))
+            })
+            .map(|entity| (entity.entity_id(), entity.room().entity_id()));
+
+        let (target_id, target_room_id) = match target {
+            Some(target) => target,
+            None => {
+                echo!(
+                    self.info(),
+                    "You can't find a trail of anyone like that here.\r\n"
+                );
+                return;
+            }
+        };
+
+        if target_room_id == myself.room().entity_id() {
+            let target = self.entity_world.entity_info(target_id);
+            let mut act = self.players.act_with(&myself, &target);
+            echo!(act.myself(), "$^$N is right here!\r\n");
+            return;
+        }
+
+        match self.first_step_to(target_room_id) {
+            Some(exit) => echo!(self.info(), "You sense a trail {} from here.\r\n", exit),
+            None => echo!(self.info(), "You can't find a trail from here.\r\n"),
+        }
+    }
+}
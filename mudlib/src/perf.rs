@@ -0,0 +1,242 @@
+//! Where the time of the game goes, for immortals finding out why it lags.
+//!
+//! Parts of the game run in `tracing` spans: each pulse and each system the
+//! scheduler runs in it, each command, each mobprog, each subscriber hearing
+//! about events, and reading from and writing to connections. Spans can carry a `name`, e.g. the command or the
+//! vnum of the mobprog, to tell them apart.
+//!
+//! One subscriber times the spans for the whole process, and adds them up
+//! into the `Perf` of the `WorldState` that is running on the thread: while
+//! the world is being updated or runs a command, or for the server, while it
+//! handles an event. The `perf` command lists them, with the
+//! ones that took the most time first, and `perf reset` starts over.
+
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex, Once},
+    time::{Duration, Instant},
+};
+
+use tracing::{
+    field::{Field, Visit},
+    span, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+use crate::{agent::EntityAgent, echo};
+
+/// How long a span took, over all the times it ran.
+#[derive(Default, Clone, Copy)]
+struct SpanTimes {
+    runs: u64,
+    total: Duration,
+    longest: Duration,
+}
+
+struct Timings {
+    spans: HashMap<String, SpanTimes>,
+    /// When the timings started adding up
+    since: Instant,
+}
+
+type SharedTimings = Arc<Mutex<Timings>>;
+
+thread_local! {
+    /// The timings of the game running on this thread, if any
+    static CURRENT: RefCell<Option<SharedTimings>> = const { RefCell::new(None) };
+}
+
+static INSTALL: Once = Once::new();
+
+pub(crate) struct Perf {
+    timings: SharedTimings,
+}
+
+impl Perf {
+    /// New timings, and the subscriber that adds them up, set as the global
+    /// default once for the whole process. If the program already set a
+    /// subscriber of its own, nothing is timed.
+    pub fn new() -> Self {
+        INSTALL.call_once(|| {
+            let _ = tracing::subscriber::set_global_default(
+                tracing_subscriber::registry().with(TimingLayer),
+            );
+        });
+
+        Perf {
+            timings: Arc::new(Mutex::new(Timings {
+                spans: HashMap::new(),
+                since: Instant::now(),
+            })),
+        }
+    }
+
+    /// Add up spans into these timings, until the guard is dropped.
+    pub fn enter(&self) -> PerfGuard {
+        let previous = CURRENT.with(|current| current.replace(Some(self.timings.clone())));
+        PerfGuard { previous }
+    }
+
+    fn reset(&self) {
+        let mut timings = self.timings.lock().expect("Perf timings poisoned");
+        timings.spans.clear();
+        timings.since = Instant::now();
+    }
+}
+
+/// Spans go back to the timings they were added to before, if any, when
+/// this is dropped.
+pub(crate) struct PerfGuard {
+    previous: Option<SharedTimings>,
+}
+
+impl Drop for PerfGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// The span label, with its `name` field if it has one, and when it was
+/// last entered.
+struct SpanTiming {
+    label: String,
+    entered: Option<Instant>,
+}
+
+struct NameVisitor<'l>(&'l mut String);
+
+impl Visit for NameVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0.push(' ');
+            self.0.push_str(value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "name" {
+            self.0.push_str(&format!(" {:?}", value));
+        }
+    }
+}
+
+struct TimingLayer;
+
+impl<S> Layer<S> for TimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut label = attrs.metadata().name().to_string();
+        attrs.record(&mut NameVisitor(&mut label));
+
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanTiming {
+                label,
+                entered: None,
+            });
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+                timing.entered = Some(Instant::now());
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut extensions = span.extensions_mut();
+        let timing = match extensions.get_mut::<SpanTiming>() {
+            Some(timing) => timing,
+            None => return,
+        };
+
+        let elapsed = match timing.entered.take() {
+            Some(entered) => entered.elapsed(),
+            None => return,
+        };
+
+        CURRENT.with(|current| {
+            if let Some(timings) = &*current.borrow() {
+                let mut timings = timings.lock().expect("Perf timings poisoned");
+                let times = timings.spans.entry(timing.label.clone()).or_default();
+                times.runs += 1;
+                times.total += elapsed;
+                times.longest = times.longest.max(elapsed);
+            }
+        });
+    }
+}
+
+/// How many spans `perf` lists
+const LISTED_SPANS: usize = 30;
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_perf(&mut self, args: &[&str]) {
+        if let ["reset"] = args {
+            self.perf.reset();
+            echo!(self.info(), "Timings reset.\r\n");
+            return;
+        }
+
+        let (mut spans, since) = {
+            let timings = self.perf.timings.lock().expect("Perf timings poisoned");
+            let spans: Vec<(String, SpanTimes)> = timings
+                .spans
+                .iter()
+                .map(|(label, times)| (label.clone(), *times))
+                .collect();
+            (spans, timings.since.elapsed())
+        };
+        spans.sort_by_key(|(_, times)| Reverse(times.total));
+
+        let mut info = self.info();
+        echo!(
+            info,
+            "Timings of the last {} seconds; 'perf reset' starts over.\r\n",
+            since.as_secs()
+        );
+        if spans.is_empty() {
+            echo!(info, "Nothing was timed yet.\r\n");
+            return;
+        }
+
+        echo!(
+            info,
+            "`W{:<28} {:>8} {:>10} {:>10} {:>10} {:>6}`^\r\n",
+            "Span",
+            "Runs",
+            "Total",
+            "Average",
+            "Longest",
+            "Time"
+        );
+        for (label, times) in spans.iter().take(LISTED_SPANS) {
+            let average = times.total / times.runs.max(1) as u32;
+            let share = times.total.as_secs_f64() / since.as_secs_f64().max(f64::EPSILON);
+            echo!(
+                info,
+                "{:<28} {:>8} {:>8}ms {:>8}us {:>8}us {:>5.1}%\r\n",
+                label,
+                times.runs,
+                times.total.as_millis(),
+                average.as_micros(),
+                times.longest.as_micros(),
+                share * 100.0
+            );
+        }
+        if spans.len() > LISTED_SPANS {
+            echo!(info, "...and {} more.\r\n", spans.len() - LISTED_SPANS);
+        }
+    }
+}
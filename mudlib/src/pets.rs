@@ -0,0 +1,188 @@
+//! Pet shops, laid out like in Diku: a room with the `pet_shop` room flag
+//! sells copies of the mobiles in the room with the next vnum, which players
+//! shouldn't be able to get into. `list` and `buy` work there like in other
+//! shops, but no shopkeeper is needed, and `buy <pet> <name>` also gives the
+//! pet a name.
+//!
+//! A pet follows the player who bought it around, and doesn't wander off on
+//! its own. Players can only have one pet at a time.
+
+use crate::{
+    agent::EntityAgent, components::Mobile, echo, entity::EntityId, find_entities::EntityIterator,
+};
+
+/// What a pet costs, from the level of its template.
+fn pet_price(level: u32) -> usize {
+    let level = level.max(1) as usize;
+    10 * level * level
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// The room that pets are sold from, if the current entity is in a pet
+    /// shop.
+    pub(crate) fn pet_stock_room(&self) -> Option<EntityId> {
+        let room_id = self.entity_world.room_of(self.entity_id);
+        let vnum = self
+            .entity_world
+            .entity_info(room_id)
+            .components()
+            .general
+            .vnum
+            .0;
+
+        match self.world.rooms.get(vnum) {
+            Some(room) if vnum != 0 && room.vnum.0 == vnum && room.pet_shop => (),
+            _ => return None,
+        }
+
+        let permanent_id = *self.entity_world.templates.room(vnum + 1)?;
+        self.entity_world
+            .old_entity(&permanent_id)
+            .map(|room| room.entity_id())
+    }
+
+    /// What a pet in the stock room costs.
+    fn price_of_pet(&self, pet_id: EntityId) -> usize {
+        let vnum = self
+            .entity_world
+            .entity_info(pet_id)
+            .components()
+            .general
+            .vnum
+            .0;
+        match self.world.mobiles.get(vnum) {
+            Some(template) if vnum != 0 && template.vnum.0 == vnum => pet_price(template.level),
+            _ => pet_price(0),
+        }
+    }
+
+    pub(crate) fn do_list_pets(&mut self, stock_room_id: EntityId) {
+        let pets: Vec<(String, usize)> = self
+            .entity_world
+            .entity_info(stock_room_id)
+            .mobiles()
+            .map(|pet| {
+                (
+                    pet.component_info().short_description().to_string(),
+                    self.price_of_pet(pet.entity_id()),
+                )
+            })
+            .collect();
+
+        let mut info = self.info();
+        if pets.is_empty() {
+            echo!(info, "There are no pets for sale right now.\r\n");
+            return;
+        }
+
+        echo!(info, "Pets for sale:\r\n");
+        for (pet, price) in pets {
+            echo!(info, "  {}: `W{}`^ silver coins\r\n", pet, price);
+        }
+    }
+
+    pub fn do_buy_pet(&mut self, pet_name: &str, name: Option<&str>) {
+        let stock_room_id = match self.pet_stock_room() {
+            Some(stock_room_id) => stock_room_id,
+            None => {
+                echo!(
+                    self.info(),
+                    "Only pets bought in a pet shop can be named.\r\n"
+                );
+                return;
+            }
+        };
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can keep pets.\r\n");
+                return;
+            }
+        };
+        let leader = myself.weak_ref();
+
+        if let Some(name) = name {
+            if name.len() > 12 || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+                echo!(
+                    self.info(),
+                    "A pet's name can only have letters, and at most 12 of them.\r\n"
+                );
+                return;
+            }
+        }
+
+        let has_pet = self
+            .entity_world
+            .query::<&Mobile>()
+            .any(|(_entity, mobile)| mobile.pet_of.as_ref() == Some(&player));
+        if has_pet {
+            echo!(self.info(), "You already have a pet.\r\n");
+            return;
+        }
+
+        let found = self
+            .entity_world
+            .entity_info(stock_room_id)
+            .mobiles()
+            .filter_by_keyword(pet_name)
+            .find_one_or("There is no pet named like that for sale.");
+
+        let pet_id = match found {
+            Ok(pet) => pet.entity_id(),
+            Err(error) => return self.echo_error(error),
+        };
+
+        let cost = self.price_of_pet(pet_id);
+        if !self.remove_silver(cost, self.entity_id) {
+            echo!(
+                self.info(),
+                "You don't have the {} silver to pay for it!\r\n",
+                cost
+            );
+            return;
+        }
+
+        // Sell a new copy, so that the shop never runs out
+        let room_id = self.entity_world.room_of(self.entity_id);
+        let pet = self.entity_world.entity_info(pet_id);
+        let vnum = pet.components().general.vnum.0;
+        let keyword = pet.component_info().keyword().to_string();
+        let short_description = pet.component_info().short_description().to_string();
+
+        let spawned = match vnum {
+            0 => None,
+            vnum => self.entity_world.spawn_mobile(vnum, room_id),
+        };
+        let bought_id = match spawned {
+            Some(bought_id) => bought_id,
+            None => self.entity_world.clone_entity_recursive(pet_id, room_id),
+        };
+
+        if let Some(name) = name {
+            let name = format!("{}{}", name[..1].to_uppercase(), &name[1..].to_lowercase());
+            let (mut bought, interner) = self.entity_world.entity_info_mut_with_interner(bought_id);
+            bought.set_keyword(interner, &format!("{} {}", name.to_lowercase(), keyword));
+            bought.set_short_description(interner, &format!("{}, {}", name, short_description));
+        }
+
+        let mut bought = self.entity_world.entity_info_mut(bought_id);
+        let components = bought.components();
+        components.general.following = Some(leader);
+        if let Some(mobile) = &mut components.mobile {
+            mobile.wander = false;
+            mobile.pet_of = Some(player);
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let bought = self.entity_world.entity_info(bought_id);
+        let mut act = self.players.act_with(&myself, &bought);
+        echo!(
+            act.myself(),
+            "You buy $N for {} silver coins, and $E starts following you.\r\n",
+            cost
+        );
+        echo!(act.others(), "$^$n buys $N as a pet.\r\n");
+    }
+}
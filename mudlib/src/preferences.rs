@@ -0,0 +1,333 @@
+//! Per-player preferences: config toggles, channels, aliases and prompt.
+//!
+//! Preferences are kept in the player's account, so they are saved along
+//! with it. Every field has a default, and missing fields are filled in with
+//! it when loading, so older save files keep working when new toggles are
+//! added.
+//!
+//! Players without an account (e.g. in the CLI) get preferences that last
+//! until the world is reloaded.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{agent::EntityAgent, echo, entity::EntityId, entity::EntityWorld, trust::Trust};
+
+/// Name and description of each config toggle
+static TOGGLES: &[(&str, &str)] = &[
+    ("brief", "Skip room descriptions when moving around"),
+    ("color", "Show colors"),
+];
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub(crate) struct Preferences {
+    pub brief: bool,
+    pub color: bool,
+    /// Channels the player turned off
+    pub channels_off: BTreeSet<String>,
+    /// Words that get replaced with other commands
+    pub aliases: BTreeMap<String, String>,
+    /// Custom prompt; see `render_prompt()`
+    pub prompt: Option<String>,
+    /// What others see after an immortal's name when they arrive with `goto`
+    pub poofin: Option<String>,
+    /// What others see after an immortal's name when they leave with `goto`
+    pub poofout: Option<String>,
+    /// Players below this trust can't see an immortal; see `visibility.rs`
+    pub wizinvis: Option<Trust>,
+    pub holylight: bool,
+    /// Kinds of events in the immortal log that the player turned off
+    pub log_off: BTreeSet<String>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            brief: false,
+            color: true,
+            channels_off: BTreeSet::new(),
+            aliases: BTreeMap::new(),
+            prompt: None,
+            poofin: None,
+            poofout: None,
+            wizinvis: None,
+            holylight: false,
+            log_off: BTreeSet::new(),
+        }
+    }
+}
+
+impl Preferences {
+    fn toggle(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "brief" => Some(&mut self.brief),
+            "color" | "colour" => Some(&mut self.color),
+            _ => None,
+        }
+    }
+
+    /// Replace an alias at the start of a command with what it stands for.
+    ///
+    /// Aliases are only expanded once, so they can't loop.
+    pub fn expand_alias(&self, words: &[&str]) -> Option<Vec<String>> {
+        let (first, rest) = words.split_first()?;
+
+        if *first == "alias" || *first == "unalias" {
+            return None;
+        }
+
+        let expansion = self.aliases.get(*first)?;
+
+        Some(
+            expansion
+                .split_whitespace()
+                .chain(rest.iter().copied())
+                .map(String::from)
+                .collect(),
+        )
+    }
+}
+
+/// Render a prompt, replacing codes like `%n` with the player's state.
+///
+/// Codes: `%n` for the player's name, `%r` for the room, `%s` for the silver
+/// they carry, `%h`, `%m` and `%v` for their hit points, mana and moves (and
+/// `%H`, `%M` and `%V` for the most they can have), and `%%` for a percent
+/// sign.
+pub(crate) fn render_prompt(
+    prompt: Option<&str>,
+    entity_world: &EntityWorld,
+    player_id: EntityId,
+) -> String {
+    let player = entity_world.entity_info(player_id);
+    let name = player.player_name().unwrap_or("");
+
+    let prompt = match prompt {
+        Some(prompt) => prompt,
+        None => return format!("{}> ", name),
+    };
+
+    let mut rendered = String::new();
+    let mut chars = prompt.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            rendered.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => rendered.push_str(name),
+            Some('r') => rendered.push_str(player.room().component_info().internal_title()),
+            Some('s') => {
+                let silver: usize = player
+                    .objects()
+                    .filter_map(|object| object.components().wealth.as_ref())
+                    .map(|wealth| wealth.silver_value())
+                    .sum();
+                rendered.push_str(&silver.to_string());
+            }
+            Some(code @ ('h' | 'H' | 'm' | 'M' | 'v' | 'V')) => match &player.components().vitals {
+                Some(vitals) => {
+                    let value = match code {
+                        'h' => vitals.hit,
+                        'H' => vitals.max_hit,
+                        'm' => vitals.mana,
+                        'M' => vitals.max_mana,
+                        'v' => vitals.moves,
+                        _ => vitals.max_moves,
+                    };
+                    rendered.push_str(&value.to_string());
+                }
+                None => rendered.push('?'),
+            },
+            Some('%') => rendered.push('%'),
+            Some(other) => {
+                rendered.push('%');
+                rendered.push(other);
+            }
+            None => rendered.push('%'),
+        }
+    }
+
+    rendered.push(' ');
+    rendered
+}
+
+// Preference commands
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Get the preferences of the current player, or None for mobiles.
+    pub fn my_preferences(&mut self) -> Option<&mut Preferences> {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = myself.player_name()?;
+        Some(self.accounts.preferences_mut(player))
+    }
+
+    pub fn do_config(&mut self, toggle: Option<&str>, value: Option<bool>) {
+        let preferences = match self.my_preferences() {
+            Some(preferences) => preferences,
+            None => {
+                echo!(self.info(), "Only players have preferences.\r\n");
+                return;
+            }
+        };
+
+        let toggle = match toggle {
+            Some(toggle) => toggle,
+            None => {
+                let mut preferences = preferences.clone();
+                let mut info = self.info();
+                echo!(info, "Your settings:\r\n");
+                for (name, description) in TOGGLES {
+                    let enabled = *preferences.toggle(name).expect("All toggles exist");
+                    echo!(
+                        info,
+                        "  `W{:<8}`^ {} - {}\r\n",
+                        name,
+                        if enabled { "`Gon `^" } else { "`Roff`^" },
+                        description
+                    );
+                }
+                echo!(
+                    info,
+                    "Use '`Wconfig <setting>`^' to turn one on or off.\r\n"
+                );
+                return;
+            }
+        };
+
+        match preferences.toggle(toggle) {
+            Some(enabled) => {
+                *enabled = value.unwrap_or(!*enabled);
+                let enabled = *enabled;
+                echo!(
+                    self.info(),
+                    "The '`W{}`^' setting is now {}.\r\n",
+                    toggle,
+                    if enabled { "`Gon`^" } else { "`Roff`^" }
+                );
+            }
+            None => {
+                echo!(
+                    self.info(),
+                    "There is no such setting; type '`Wconfig`^' for a list.\r\n"
+                );
+            }
+        }
+    }
+
+    pub fn do_prompt(&mut self, prompt: &str) {
+        let preferences = match self.my_preferences() {
+            Some(preferences) => preferences,
+            None => {
+                echo!(self.info(), "Only players have a prompt.\r\n");
+                return;
+            }
+        };
+
+        match prompt {
+            "" => {
+                let prompt = preferences.prompt.clone();
+                let mut info = self.info();
+                match prompt {
+                    Some(prompt) => echo!(info, "Your prompt is: {}\r\n", prompt),
+                    None => echo!(info, "You are using the default prompt.\r\n"),
+                }
+                echo!(
+                    info,
+                    "Use '`Wprompt <text>`^' to change it; '`W%n`^' is replaced with your name,\r\n\
+                    '`W%r`^' with the room you are in, '`W%s`^' with your silver, and '`W%h`^',\r\n\
+                    '`W%m`^' and '`W%v`^' with your hit points, mana and moves ('`W%H`^', '`W%M`^' and\r\n\
+                    '`W%V`^' for the most you can have). Use '`Wprompt default`^' to go back to\r\n\
+                    the default one.\r\n"
+                );
+            }
+            "default" => {
+                preferences.prompt = None;
+                echo!(self.info(), "Prompt reset to the default one.\r\n");
+            }
+            prompt => {
+                preferences.prompt = Some(prompt.to_string());
+                echo!(self.info(), "Prompt set.\r\n");
+            }
+        }
+
+        // So that the new prompt is rendered
+        self.entity_world.mark_changed(self.entity_id);
+    }
+
+    pub fn do_alias(&mut self, alias: Option<&str>, expansion: &str) {
+        let max_aliases = self.config.limits.aliases;
+        let preferences = match self.my_preferences() {
+            Some(preferences) => preferences,
+            None => {
+                echo!(self.info(), "Only players can have aliases.\r\n");
+                return;
+            }
+        };
+
+        match alias {
+            None if preferences.aliases.is_empty() => {
+                echo!(
+                    self.info(),
+                    "You have no aliases. Use '`Walias <word> <command>`^' to add one.\r\n"
+                );
+            }
+            None => {
+                let aliases = preferences.aliases.clone();
+                let mut info = self.info();
+                echo!(info, "Your aliases:\r\n");
+                for (alias, expansion) in aliases {
+                    echo!(info, "  `W{:<10}`^ {}\r\n", alias, expansion);
+                }
+            }
+            Some("alias") | Some("unalias") => {
+                echo!(self.info(), "That word can't be used as an alias.\r\n");
+            }
+            Some(alias) if expansion.is_empty() => {
+                let expansion = preferences.aliases.get(alias).cloned();
+                match expansion {
+                    Some(expansion) => {
+                        echo!(self.info(), "`W{}`^ stands for: {}\r\n", alias, expansion)
+                    }
+                    None => echo!(self.info(), "You have no alias named like that.\r\n"),
+                }
+            }
+            Some(alias) => {
+                if !preferences.aliases.contains_key(alias)
+                    && preferences.aliases.len() >= max_aliases
+                {
+                    echo!(
+                        self.info(),
+                        "You already have too many aliases; remove some with '`Wunalias`^'.\r\n"
+                    );
+                    return;
+                }
+
+                preferences
+                    .aliases
+                    .insert(alias.to_string(), expansion.to_string());
+                echo!(
+                    self.info(),
+                    "`W{}`^ now stands for: {}\r\n",
+                    alias,
+                    expansion
+                );
+            }
+        }
+    }
+
+    pub fn do_unalias(&mut self, alias: &str) {
+        let removed = self
+            .my_preferences()
+            .map(|preferences| preferences.aliases.remove(alias).is_some());
+
+        if removed == Some(true) {
+            echo!(self.info(), "Alias removed.\r\n");
+        } else {
+            echo!(self.info(), "You have no alias named like that.\r\n");
+        }
+    }
+}
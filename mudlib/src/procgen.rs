@@ -0,0 +1,331 @@
+//! Dungeons generated from a seed, so that the game can run without any
+//! stock areas, and be stress-tested with worlds as large as needed.
+//!
+//! A dungeon is an area made of the same types of `crate::world` that area
+//! files are loaded into, so it is imported and played like them: a maze of
+//! rooms on a grid, joined by exits both ways, some of them behind doors, and
+//! a few kinds of mobiles and objects spawned in them by resets. The same
+//! seed and size always make the same dungeon.
+//!
+//! With `dungeon_rooms` set under `[world]`, a dungeon of that many rooms is
+//! added after the areas of the area files, with vnums above theirs and a
+//! seed rolled from the game's; the area list can then be left out. Once it
+//! is saved with `asave dungeon`, its file is loaded instead. Tools and
+//! benchmarks can write one as an area file with `generate_area_file()`.
+
+use std::{collections::HashMap, convert::TryFrom};
+
+use crate::{
+    load::{remember_area, DEFAULT_RESET_INTERVAL},
+    rng::GameRng,
+    save::save_area,
+    world::{
+        Area, AreaData, AreaFormat, Exit, Mobile, Object, ObjectFlags, ResetCommand, Room, Vnum,
+        World,
+    },
+};
+
+/// Short name of generated dungeons
+pub(crate) const DUNGEON_AREA: &str = "dungeon";
+
+/// Directions of exits, and where they lead on the grid; opposite directions
+/// are two apart
+const DIRECTIONS: [(&str, (i32, i32)); 4] = [
+    ("north", (0, -1)),
+    ("east", (1, 0)),
+    ("south", (0, 1)),
+    ("west", (-1, 0)),
+];
+
+const ADJECTIVES: &[&str] = &[
+    "damp",
+    "narrow",
+    "crumbling",
+    "dusty",
+    "silent",
+    "flooded",
+    "cold",
+    "winding",
+    "collapsed",
+    "dark",
+];
+
+const PLACES: &[&str] = &[
+    "corridor", "cellar", "crypt", "hall", "tunnel", "cave", "chamber", "passage", "vault",
+    "gallery",
+];
+
+const SIGHTS: &[&str] = &[
+    "Water drips from the ceiling into shallow puddles on the floor.",
+    "Old bones lie scattered in a corner, gnawed clean long ago.",
+    "Roots have pushed their way through cracks in the stone walls.",
+    "A faint draft carries the smell of earth and rot.",
+    "Scratches on the walls look like someone was counting days.",
+    "The floor is covered in a thick layer of grey dust.",
+    "Cobwebs hang in heavy sheets from the corners.",
+    "Broken pottery crunches underfoot.",
+];
+
+/// Keywords, short, long and full descriptions, and level of each mobile
+const MOBILES: &[(&str, &str, &str, &str, u32)] = &[
+    (
+        "rat giant",
+        "a giant rat",
+        "A giant rat sniffs around for scraps.",
+        "This rat is as big as a dog, with yellow teeth and a long bald tail.",
+        2,
+    ),
+    (
+        "skeleton",
+        "a skeleton",
+        "A skeleton shambles about aimlessly.",
+        "Bits of rotten cloth still cling to the bones of this restless dead.",
+        5,
+    ),
+    (
+        "goblin scout",
+        "a goblin scout",
+        "A goblin scout peers at you from the shadows.",
+        "A small green creature with pointed ears, clutching a crude knife.",
+        4,
+    ),
+];
+
+/// Generate a dungeon as an area file, in the Dawn of Time format, with
+/// vnums from the first one on.
+pub fn generate_area_file(seed: u64, rooms: usize, first_vnum: usize) -> String {
+    let mut rng = GameRng::new(Some(seed));
+    let mut world = World::default();
+    world.add_generated_area(generate_area(&mut rng, rooms, first_vnum));
+    save_area(&world, 0)
+}
+
+/// Generate a dungeon of some rooms, with vnums from the first one on for
+/// its rooms, mobiles and objects.
+pub(crate) fn generate_area(rng: &mut GameRng, rooms: usize, first_vnum: usize) -> Area {
+    let count = rooms.max(1);
+    let rooms = lay_out_rooms(rng, count, first_vnum);
+
+    let mobiles: Vec<Mobile> = MOBILES
+        .iter()
+        .enumerate()
+        .map(|(index, (name, short, long, description, level))| Mobile {
+            vnum: Vnum(first_vnum + index),
+            name: name.to_string(),
+            short_description: short.to_string(),
+            long_description: long.to_string(),
+            description: wrap(description),
+            level: *level,
+            stay_area: true,
+            ..Default::default()
+        })
+        .collect();
+
+    let objects = vec![
+        Object {
+            vnum: Vnum(first_vnum),
+            name: "torch".to_string(),
+            short_description: "a torch".to_string(),
+            description: "A torch lies on the floor, still smoking.".to_string(),
+            item_type: "light".to_string(),
+            flags: ObjectFlags::Light {
+                capacity: 0,
+                hours: 24,
+            },
+            cost: 5,
+            take: true,
+            ..Default::default()
+        },
+        Object {
+            vnum: Vnum(first_vnum + 1),
+            name: "sword rusty".to_string(),
+            short_description: "a rusty sword".to_string(),
+            description: "A rusty sword has been dropped here.".to_string(),
+            item_type: "weapon".to_string(),
+            cost: 20,
+            take: true,
+            wear: Some("wield".to_string()),
+            ..Default::default()
+        },
+        Object {
+            vnum: Vnum(first_vnum + 2),
+            name: "bones pile".to_string(),
+            short_description: "a pile of bones".to_string(),
+            description: "A pile of bones is heaped against the wall.".to_string(),
+            item_type: "trash".to_string(),
+            ..Default::default()
+        },
+    ];
+
+    // A mobile in one room out of four, and an object in one out of six
+    let mut resets = Vec::new();
+    for room in &rooms {
+        if rng.percent(25) {
+            resets.push(ResetCommand::Mob {
+                m_num: mobiles[rng.range(0..mobiles.len())].vnum,
+                global_limit: u16::try_from(count).unwrap_or(u16::MAX),
+                r_num: room.vnum,
+                room_limit: 1,
+                only_at: None,
+            });
+        }
+        if rng.percent(16) {
+            resets.push(ResetCommand::Object {
+                o_num: objects[rng.range(0..objects.len())].vnum,
+                global_limit: -1,
+                r_num: room.vnum,
+                only_at: None,
+            });
+        }
+    }
+
+    let mut area = Area {
+        area_data: AreaData {
+            name: format!("A Dungeon of Seed {}", rng.seed()),
+            short_name: DUNGEON_AREA.to_string(),
+            vnums: (Vnum(first_vnum), Vnum(first_vnum + count - 1)),
+            credits: "Procgen".to_string(),
+            continent: String::new(),
+            builders: String::new(),
+            security: 0,
+            reset_interval: DEFAULT_RESET_INTERVAL,
+            encounters: Vec::new(),
+            file_name: format!("{}.are", DUNGEON_AREA),
+            source: None,
+            on_disk: false,
+            changed: true,
+            format: AreaFormat::Generated,
+        },
+        rooms,
+        objects,
+        mobiles,
+        resets,
+        shops: Vec::new(),
+        mobprogs: Vec::new(),
+        helps: Vec::new(),
+        socials: Vec::new(),
+    };
+    remember_area(&mut area);
+    area
+}
+
+/// Rooms on a grid, grown from the first one by joining new rooms to those
+/// already there, so that all of them can be reached, and then joined in a
+/// few more places so that the maze has loops.
+fn lay_out_rooms(rng: &mut GameRng, count: usize, first_vnum: usize) -> Vec<Room> {
+    // Twice as many places as rooms, so that the maze doesn't fill the grid
+    let width = ((count * 2) as f64).sqrt().ceil() as i32;
+    let on_grid = |(x, y): (i32, i32)| x >= 0 && y >= 0 && x < width && y < width;
+
+    let mut places = vec![(0, 0)];
+    let mut room_at: HashMap<(i32, i32), usize> = HashMap::new();
+    room_at.insert((0, 0), 0);
+    let mut rooms = vec![new_room(rng, first_vnum)];
+    // Rooms that may still have free places next to them
+    let mut growing = vec![0];
+
+    while rooms.len() < count {
+        let which = rng.range(0..growing.len());
+        let index = growing[which];
+        let (x, y) = places[index];
+        let free: Vec<usize> = (0..DIRECTIONS.len())
+            .filter(|&direction| {
+                let (dx, dy) = DIRECTIONS[direction].1;
+                let place = (x + dx, y + dy);
+                on_grid(place) && !room_at.contains_key(&place)
+            })
+            .collect();
+        if free.is_empty() {
+            growing.swap_remove(which);
+            continue;
+        }
+
+        let direction = free[rng.range(0..free.len())];
+        let (dx, dy) = DIRECTIONS[direction].1;
+        let new_index = rooms.len();
+        places.push((x + dx, y + dy));
+        room_at.insert((x + dx, y + dy), new_index);
+        rooms.push(new_room(rng, first_vnum + new_index));
+        growing.push(new_index);
+        join(rng, &mut rooms, index, new_index, direction);
+    }
+
+    for _ in 0..count / 10 {
+        let index = rng.range(0..count);
+        let direction = rng.range(0..DIRECTIONS.len());
+        let (x, y) = places[index];
+        let (dx, dy) = DIRECTIONS[direction].1;
+        let name = DIRECTIONS[direction].0;
+        if let Some(&other) = room_at.get(&(x + dx, y + dy)) {
+            if !rooms[index].exits.iter().any(|exit| exit.name == name) {
+                join(rng, &mut rooms, index, other, direction);
+            }
+        }
+    }
+
+    rooms
+}
+
+/// Join two rooms with exits both ways, sometimes with a closed door.
+fn join(rng: &mut GameRng, rooms: &mut [Room], from: usize, to: usize, direction: usize) {
+    let door = rng.percent(8);
+    let back = (direction + 2) % DIRECTIONS.len();
+    let to_vnum = rooms[to].vnum;
+    let from_vnum = rooms[from].vnum;
+
+    for (room, leads_to, direction) in [(from, to_vnum, direction), (to, from_vnum, back)] {
+        rooms[room].exits.push(Exit {
+            name: DIRECTIONS[direction].0.to_string(),
+            vnum: leads_to,
+            has_door: door,
+            is_closed: door,
+            ..Default::default()
+        });
+    }
+}
+
+fn new_room(rng: &mut GameRng, vnum: usize) -> Room {
+    let adjective = ADJECTIVES[rng.range(0..ADJECTIVES.len())];
+    let place = PLACES[rng.range(0..PLACES.len())];
+    let first = rng.range(0..SIGHTS.len());
+    let second = (first + rng.range(1..SIGHTS.len())) % SIGHTS.len();
+
+    Room {
+        vnum: Vnum(vnum),
+        name: format!("{} {}", capitalize(adjective), capitalize(place)),
+        description: wrap(&format!(
+            "You are in a {} {}. {} {}",
+            adjective, place, SIGHTS[first], SIGHTS[second]
+        )),
+        sector: "inside".to_string(),
+        ..Default::default()
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Words of a description in lines of up to 78 characters, as area files
+/// have them.
+fn wrap(text: &str) -> String {
+    let mut wrapped = String::new();
+    let mut line_length = 0;
+    for word in text.split_whitespace() {
+        if line_length > 0 && line_length + 1 + word.len() > 78 {
+            wrapped.push_str("\r\n");
+            line_length = 0;
+        } else if line_length > 0 {
+            wrapped.push(' ');
+            line_length += 1;
+        }
+        wrapped.push_str(word);
+        line_length += word.len();
+    }
+    wrapped.push_str("\r\n");
+    wrapped
+}
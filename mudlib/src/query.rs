@@ -0,0 +1,74 @@
+//! Typed queries of the components of entities, so that game systems only go
+//! through the entities that have the components they work on.
+//!
+//! ```ignore
+//! for (entity, (mobile, general)) in entity_world.query::<(&Mobile, &GeneralData)>() {
+//!     // Only mobiles get here
+//! }
+//! ```
+//!
+//! Each part of a query is a reference to a component; entities without one
+//! of them are left out. `Option<&Decay>` asks for a component without
+//! leaving out entities that don't have it. Entities come in the order they
+//! were inserted, like with `EntityWorld::all_entities()`.
+
+use crate::components::{
+    Attributes, Components, Decay, Door, GeneralData, MobProg, Mobile, Object, Vitals, Wealth,
+};
+
+/// What a query asks of the components of an entity, and gives back for
+/// those that have it.
+pub(crate) trait Query<'c>: Sized {
+    /// The components asked for, or None if the entity doesn't have them.
+    fn fetch(components: &'c Components) -> Option<Self>;
+}
+
+impl<'c> Query<'c> for &'c GeneralData {
+    fn fetch(components: &'c Components) -> Option<Self> {
+        Some(&components.general)
+    }
+}
+
+/// Components only some entities have, like those of mobiles
+macro_rules! optional {
+    ($($component:ty => $field:ident),*) => {
+        $(
+            impl<'c> Query<'c> for &'c $component {
+                fn fetch(components: &'c Components) -> Option<Self> {
+                    components.$field.as_ref()
+                }
+            }
+        )*
+    };
+}
+
+optional!(
+    Mobile => mobile,
+    Object => object,
+    Door => door,
+    MobProg => mobprog,
+    Wealth => wealth,
+    Vitals => vitals,
+    Decay => decay,
+    Attributes => attributes
+);
+
+impl<'c, Q: Query<'c>> Query<'c> for Option<Q> {
+    fn fetch(components: &'c Components) -> Option<Self> {
+        Some(Q::fetch(components))
+    }
+}
+
+macro_rules! tuple {
+    ($($query:ident),*) => {
+        impl<'c, $($query: Query<'c>),*> Query<'c> for ($($query,)*) {
+            fn fetch(components: &'c Components) -> Option<Self> {
+                Some(($($query::fetch(components)?,)*))
+            }
+        }
+    };
+}
+
+tuple!(A, B);
+tuple!(A, B, C);
+tuple!(A, B, C, D);
@@ -0,0 +1,103 @@
+//! Shutdowns and reboots scheduled for later, with countdown warnings.
+//!
+//! `shutdown in <minutes>` and `reboot in <minutes>` warn everyone on the
+//! system channel as the time comes closer, and can be called off with
+//! `shutdown cancel`. A reboot is like `restart`: the game is created again
+//! from a fresh copy of the mudlib, while players stay connected.
+//!
+//! Right before the server goes down, everything that changed is saved, along
+//! with a snapshot of the world.
+
+use crate::state::unix_time;
+
+/// Seconds before going down at which everyone is warned
+const WARNINGS: &[u64] = &[30 * 60, 15 * 60, 10 * 60, 5 * 60, 2 * 60, 60, 30, 10];
+/// Anything further ahead than this is probably a typo
+const MAX_MINUTES: u64 = 24 * 60;
+
+pub(crate) struct ScheduledExit {
+    pub reboot: bool,
+    /// When the server goes down, in seconds since the Unix epoch
+    at: u64,
+    /// The last warning given, in seconds before going down
+    last_warning: Option<u64>,
+}
+
+pub(crate) enum ExitDue {
+    Warning(String),
+    Now,
+}
+
+fn format_time_left(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{} seconds", seconds),
+        60..=119 => "1 minute".to_string(),
+        _ => format!("{} minutes", seconds / 60),
+    }
+}
+
+/// The closest warning mark that was passed with this many seconds left.
+fn warning_mark(left: u64) -> Option<u64> {
+    WARNINGS.iter().copied().filter(|mark| left <= *mark).min()
+}
+
+impl ScheduledExit {
+    pub fn new(reboot: bool, minutes: &str) -> Result<Self, String> {
+        let minutes: u64 = match minutes.parse() {
+            Ok(minutes) if minutes > 0 && minutes <= MAX_MINUTES => minutes,
+            _ => {
+                return Err(format!(
+                    "That's not a number of minutes from 1 to {}.",
+                    MAX_MINUTES
+                ))
+            }
+        };
+
+        // Scheduling it is the first warning
+        Ok(ScheduledExit {
+            reboot,
+            at: unix_time() + minutes * 60,
+            last_warning: warning_mark(minutes * 60),
+        })
+    }
+
+    fn what(&self) -> &'static str {
+        if self.reboot {
+            "reboot"
+        } else {
+            "shut down"
+        }
+    }
+
+    /// Tell everyone how long they have left.
+    pub fn announcement(&self) -> String {
+        format!(
+            "The server will {} in {}.",
+            self.what(),
+            format_time_left(self.at.saturating_sub(unix_time()))
+        )
+    }
+
+    pub fn cancellation(&self) -> String {
+        format!("The server will no longer {}.", self.what())
+    }
+
+    /// Check if it's time for a warning, or to go down.
+    pub fn poll(&mut self) -> Option<ExitDue> {
+        let left = self.at.saturating_sub(unix_time());
+        if left == 0 {
+            return Some(ExitDue::Now);
+        }
+
+        let mark = warning_mark(left)?;
+        if self
+            .last_warning
+            .is_some_and(|last_warning| last_warning <= mark)
+        {
+            return None;
+        }
+
+        self.last_warning = Some(mark);
+        Some(ExitDue::Warning(self.announcement()))
+    }
+}
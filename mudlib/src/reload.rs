@@ -0,0 +1,442 @@
+//! Reloading data files while the game runs, with `reload <what>`, so that
+//! changes to their content don't need a restart.
+//!
+//! Socials are read again from `data/socials.txt`, with those of the areas
+//! added to them, command trust levels from `data/command_trust.txt`, and
+//! settings from the configuration file; see `crate::config`. The new tables
+//! only replace the old ones once the whole file was read without problems,
+//! so a mistake in a file leaves the game as it was. Help files are built
+//! into the mudlib, and are loaded again by `restart` instead; helps of
+//! areas come back with `reload area`.
+//!
+//! `reload area <area>` loads one area from its file again. Its rooms are
+//! replaced by new ones, where players standing in the old ones are moved,
+//! along with anything from other areas; mobiles and objects of the area are
+//! removed to limbo like purged ones, and its resets run again. Exits from
+//! other areas are linked to the new rooms, and nothing else is touched.
+//! An area with errors, like resets of mobiles that don't exist, isn't
+//! replaced; see `crate::validate`.
+//!
+//! `reload diff <area>` reads an area from its file the same way, but only
+//! shows what reloading it would change; see `crate::diff`.
+//!
+//! Like snapshots, `reload` only queues a request; files are read later by
+//! `WorldState::run_reloads()`, which has access to them.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::{
+    agent::EntityAgent,
+    archive::ArchiveFiles,
+    clock::time_of_day,
+    config::Config,
+    diff::{diff_area, ChangeKind},
+    echo,
+    entity::{ContentsPolicy, EntityId, EntityInfo, Place},
+    files::Files,
+    import::{import_area, place_landmarks, run_resets},
+    load::apply_source,
+    snapshots::tell,
+    socials::load_socials,
+    trust::CommandTrust,
+    validate::{validate_replacement, Severity},
+    world::{parse_area_file, read_area_text, Area},
+    WorldState,
+};
+
+pub(crate) struct Reloads {
+    requests: Vec<ReloadRequest>,
+}
+
+struct ReloadRequest {
+    by: String,
+    data: DataFile,
+}
+
+enum DataFile {
+    Socials,
+    CommandTrust,
+    Config,
+    /// An area, by its short name or file name
+    Area(String),
+    /// What reloading an area would change, without reloading it
+    AreaDiff(String),
+}
+
+impl Reloads {
+    pub fn new() -> Self {
+        Reloads {
+            requests: Vec::new(),
+        }
+    }
+}
+
+/// Read a data file with a loader. Loaders panic on bad data, since they are
+/// meant to run when the game starts; the panic is caught here, so that a
+/// typo in a file doesn't take the game down.
+fn try_load<T>(load: impl FnOnce() -> T) -> Option<T> {
+    catch_unwind(AssertUnwindSafe(load)).ok()
+}
+
+/// Carry out reload commands.
+pub(crate) fn run_reloads(world_state: &mut WorldState, files: &dyn Files) {
+    for request in std::mem::take(&mut world_state.reloads.requests) {
+        // Nothing is reloaded by comparing an area with its file
+        let reloads = !matches!(request.data, DataFile::AreaDiff(_));
+        let message = match request.data {
+            DataFile::Socials => {
+                match try_load(|| load_socials(files, &world_state.config.world.socials)) {
+                    Some(mut socials) => {
+                        socials.add_area_socials(&world_state.world);
+                        let count = socials.list().count();
+                        world_state.socials = socials;
+                        format!("Reloaded {} socials.\r\n", count)
+                    }
+                    None => "Could not reload the socials; see the server log.\r\n".to_string(),
+                }
+            }
+            DataFile::CommandTrust => match try_load(|| CommandTrust::load(files)) {
+                Some(command_trust) => {
+                    world_state.command_trust = command_trust;
+                    "Reloaded the trust levels of commands.\r\n".to_string()
+                }
+                None => "Could not reload the trust levels of commands; see the server log.\r\n"
+                    .to_string(),
+            },
+            DataFile::Config => match Config::load() {
+                Ok(config) => {
+                    world_state.config = config;
+                    "Reloaded the configuration. Limits apply now; the world and landmarks\r\n\
+                    change on the next restart, and the server settings on the next shutdown.\r\n"
+                        .to_string()
+                }
+                // Backticks would be taken for colors
+                Err(err) => format!(
+                    "Could not reload the configuration: {}\r\n",
+                    err.replace('`', "'")
+                ),
+            },
+            DataFile::Area(area) => reload_area(world_state, files, &area),
+            DataFile::AreaDiff(area) => area_diff(world_state, files, &area),
+        };
+
+        if reloads {
+            println!(
+                "{} reloaded a data file: {}",
+                request.by,
+                message.trim_end()
+            );
+        }
+        tell(world_state, &request.by, &message);
+    }
+}
+
+/// Load an area from its file again, and find the index of the area of the
+/// world it would replace. Fails with a message for the player, saying what
+/// could not be done, like "reload" or "compare".
+fn load_area_again(
+    world_state: &mut WorldState,
+    files: &dyn Files,
+    name: &str,
+    doing: &str,
+) -> Result<(usize, Area), String> {
+    let index = match world_state.world.find_area(name) {
+        Some(index) => index,
+        None => return Err(format!("There is no area called '{}'.\r\n", name)),
+    };
+    let (area_data, _resets) = &world_state.world.areas[index];
+    if !area_data.on_disk {
+        return Err(format!(
+            "The area {} was never saved, so it has no file to load.\r\n",
+            area_data.short_name
+        ));
+    }
+    let old_name = area_data.short_name.clone();
+    let source = area_data.source.clone();
+    let path = area_data.path(&world_state.config.world.areas);
+
+    // Backticks would be taken for colors
+    let failed = |err: &dyn std::fmt::Display| {
+        Err(format!(
+            "Could not {} the area {}: {}\r\n",
+            doing,
+            old_name,
+            err.to_string().replace('`', "'").replace('\n', "\r\n")
+        ))
+    };
+
+    let contents = match read_area_text(&ArchiveFiles::new(files), &path) {
+        Ok(contents) => contents,
+        Err(err) => return failed(&err),
+    };
+    let mut rng = world_state.rng.split();
+    let mut areas = match parse_area_file(&contents, &path, &mut rng) {
+        Ok(areas) => areas,
+        Err(err) => return failed(&err),
+    };
+    // Areas of other sources are changed as when they were first loaded
+    if let Some(source) = &source {
+        for area in &mut areas {
+            if let Err(err) = apply_source(area, source) {
+                return failed(&err);
+            }
+        }
+    }
+    // Files of several areas also have others, which are left as they are
+    let area = if areas.len() == 1 {
+        areas.remove(0)
+    } else {
+        match areas
+            .into_iter()
+            .find(|area| area.area_data.short_name == old_name)
+        {
+            Some(area) => area,
+            None => return failed(&format!("{} doesn't have it anymore", path)),
+        }
+    };
+
+    Ok((index, area))
+}
+
+/// Load an area from its file again, and replace it in the game.
+fn reload_area(world_state: &mut WorldState, files: &dyn Files, name: &str) -> String {
+    let (index, area) = match load_area_again(world_state, files, name, "reload") {
+        Ok(loaded) => loaded,
+        Err(message) => return message,
+    };
+    let (area_data, _resets) = &world_state.world.areas[index];
+    let old_name = area_data.short_name.clone();
+    let had_changes = area_data.changed;
+
+    // Backticks would be taken for colors
+    let failed = |err: &dyn std::fmt::Display| {
+        format!(
+            "Could not reload the area {}: {}\r\n",
+            old_name,
+            err.to_string().replace('`', "'").replace('\n', "\r\n")
+        )
+    };
+
+    let new_name = area.area_data.short_name.clone();
+    if world_state
+        .world
+        .find_area(&new_name)
+        .is_some_and(|other| other != index)
+    {
+        return failed(&format!("another area is called {}", new_name));
+    }
+    // What would be left out of the area; see crate::validate
+    let report = validate_replacement(&area, &world_state.world, index);
+    if report.errors() > 0 {
+        let errors: Vec<&str> = report
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        return failed(&errors.join("\n"));
+    }
+
+    world_state.world.replace_area(index, area);
+    world_state.socials.add_area_socials(&world_state.world);
+    let rooms = replace_area_entities(world_state, index, &old_name);
+
+    let mut message = format!("Reloaded the area {}, with {} rooms.\r\n", new_name, rooms);
+    if had_changes {
+        message.push_str("Changes to it that were not saved are gone.\r\n");
+    }
+    message
+}
+
+/// What loading an area from its file again would change, without
+/// replacing it.
+fn area_diff(world_state: &mut WorldState, files: &dyn Files, name: &str) -> String {
+    let (index, area) = match load_area_again(world_state, files, name, "compare") {
+        Ok(loaded) => loaded,
+        Err(message) => return message,
+    };
+    let diff = diff_area(&world_state.world, index, &area);
+    let old_name = &world_state.world.areas[index].0.short_name;
+    if diff.is_empty() {
+        return format!("The area {} is the same as its file.\r\n", old_name);
+    }
+
+    let mut message = format!("Reloading the area {} would change:\r\n", old_name);
+    for change in &diff.changes {
+        let color = match change.kind {
+            ChangeKind::Added => "`G",
+            ChangeKind::Removed => "`R",
+            ChangeKind::Changed => "`Y",
+        };
+        // Backticks would be taken for colors
+        message.push_str(&format!(
+            "{}{}`^\r\n",
+            color,
+            change.to_string().replace('`', "'")
+        ));
+    }
+    message.push_str(&format!("{}.\r\n", diff.summary()));
+    message
+}
+
+/// Replace the rooms of an area in the entity world, and what was spawned
+/// in it, with new ones from the world. Returns how many rooms it has now.
+fn replace_area_entities(world_state: &mut WorldState, index: usize, old_name: &str) -> usize {
+    let entity_world = &mut world_state.entity_world;
+    let world = &world_state.world;
+    let (area_data, resets) = &world.areas[index];
+    let is_old_area = |entity: &EntityInfo| &*entity.components().general.area == old_name;
+
+    let old_rooms: Vec<(usize, EntityId)> = entity_world
+        .rooms_of_area(old_name)
+        .map(|room| (room.components().general.vnum.0, room.entity_id()))
+        .collect();
+
+    // Templates of the old area, so that none are left if it has fewer now
+    let vnum_templates = &mut entity_world.templates;
+    for (vnum, _room_id) in &old_rooms {
+        vnum_templates.vnum_to_room_entity[*vnum] = None;
+    }
+    for templates in [
+        &mut vnum_templates.object_components,
+        &mut vnum_templates.mobile_components,
+    ] {
+        for template in templates.iter_mut() {
+            if template
+                .as_ref()
+                .is_some_and(|(components, _contents)| &*components.general.area == old_name)
+            {
+                *template = None;
+            }
+        }
+    }
+
+    let new_rooms = import_area(entity_world, world, &area_data.short_name);
+    // Landmarks in rooms that are gone now are moved too
+    place_landmarks(entity_world, &world_state.config);
+    let limbo = entity_world
+        .landmark("limbo")
+        .expect("Limbo should always exist");
+    let start = entity_world.place(Place::Start).unwrap_or(limbo);
+
+    for (vnum, old_room_id) in old_rooms {
+        let new_room_id = new_rooms.get(&vnum).copied();
+        let old_room = entity_world.entity_info(old_room_id);
+
+        let exits_from_elsewhere: Vec<EntityId> = old_room
+            .leads_from()
+            .filter(|exit| !is_old_area(exit))
+            .map(|exit| exit.entity_id())
+            .collect();
+        let old_exits: Vec<EntityId> = old_room.exits().map(|exit| exit.entity_id()).collect();
+        // Players, pets following them, and whatever came from other areas
+        // go to the new room; mobiles and objects of the area go to limbo
+        let moved: Vec<(EntityId, EntityId)> = old_room
+            .contained_entities()
+            .filter(|entity| !entity.is_exit() && !entity.is_extra_description())
+            .map(|entity| {
+                let kept = entity.is_player()
+                    || entity.components().general.following.is_some()
+                    || !is_old_area(&entity);
+                let to_room_id = match new_room_id {
+                    Some(new_room_id) if kept => new_room_id,
+                    None if entity.is_player() => start,
+                    _ => limbo,
+                };
+                (entity.entity_id(), to_room_id)
+            })
+            .collect();
+
+        for exit_id in exits_from_elsewhere {
+            match new_room_id {
+                Some(new_room_id) => entity_world.set_leads_to(exit_id, new_room_id),
+                None => entity_world.clear_leads_to(exit_id),
+            }
+        }
+        for exit_id in old_exits {
+            entity_world.clear_leads_to(exit_id);
+        }
+        for (entity_id, to_room_id) in moved {
+            match to_room_id == limbo {
+                true => entity_world.extract_entity(entity_id, ContentsPolicy::Destroy),
+                false => entity_world.move_entity(entity_id, to_room_id),
+            }
+        }
+
+        // Only its exits and extra descriptions are left in it by now
+        entity_world.extract_entity(old_room_id, ContentsPolicy::Junk);
+    }
+
+    // Mobiles of the area that wandered out of it
+    let world_entity_id = entity_world.world_entity_id();
+    let wandered: Vec<EntityId> = entity_world
+        .entities_from_area(old_name)
+        .filter(|entity| {
+            entity.is_mobile()
+                && entity.components().general.following.is_none()
+                && entity.room().entity_id() != limbo
+                && entity.room().room().entity_id() == world_entity_id
+        })
+        .map(|entity| entity.entity_id())
+        .collect();
+    for mobile_id in wandered {
+        entity_world.extract_entity(mobile_id, ContentsPolicy::Destroy);
+    }
+
+    let time_of_day = time_of_day(world_state.clock.hour());
+    run_resets(entity_world, resets, |only_at| {
+        only_at.is_none() || only_at == Some(time_of_day)
+    });
+
+    new_rooms.len()
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_reload(&mut self, what: Option<&str>, area: Option<&str>) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can reload data files.\r\n");
+                return;
+            }
+        };
+
+        let data = match (what, area) {
+            (Some("socials"), _) => DataFile::Socials,
+            (Some("commands"), _) => DataFile::CommandTrust,
+            (Some("helps") | Some("help"), _) => {
+                echo!(
+                    self.info(),
+                    "Help files are built into the game; '`Wrestart`^' loads changed ones\r\n\
+                    without disconnecting anyone.\r\n"
+                );
+                return;
+            }
+            (Some("skills"), _) => {
+                echo!(self.info(), "There are no skills in this game yet.\r\n");
+                return;
+            }
+            (Some("config"), _) => DataFile::Config,
+            (Some("area"), Some(area)) => DataFile::Area(area.to_string()),
+            (Some("diff"), Some(area)) => DataFile::AreaDiff(area.to_string()),
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Wreload socials`^', '`Wreload commands`^', '`Wreload config`^',\r\n\
+                    '`Wreload area <area>`^' or '`Wreload diff <area>`^'.\r\n"
+                );
+                return;
+            }
+        };
+
+        let waiting = match data {
+            DataFile::AreaDiff(_) => "Comparing...",
+            _ => "Reloading...",
+        };
+        self.reloads
+            .requests
+            .push(ReloadRequest { by: player, data });
+        echo!(self.info(), "{}\r\n", waiting);
+    }
+}
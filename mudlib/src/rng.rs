@@ -0,0 +1,90 @@
+//! Random numbers for the whole game, from a generator that can be seeded.
+//!
+//! Everything random that happens in the game (wandering mobiles, random
+//! mobprog triggers, the weather, stumbling drunks, encounters, genders of
+//! mobiles that are random) is rolled from the same `GameRng`. The seed it
+//! starts from is either picked at random when the world is created, or given
+//! with `WorldState::with_seed()`, from `--seed` on the command line of the
+//! CLI or the `DEMIMUD_SEED` environment variable of the server.
+//!
+//! Started from the same seed, with the same commands at the same pulses, a
+//! game plays out the same way again, which makes it possible to reproduce
+//! bugs and test what happens. Immortals see the seed with `pulses`.
+//!
+//! Password salts don't come from here, as they shouldn't be predictable.
+
+use rand::{
+    distr::uniform::{SampleRange, SampleUniform},
+    rngs::StdRng,
+    Rng, SeedableRng,
+};
+
+/// Environment variable with the seed for the server
+#[cfg(feature = "net")]
+pub(crate) const SEED_VARIABLE: &str = "DEMIMUD_SEED";
+
+pub(crate) struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    /// A generator starting from a seed, or from a random one if there is
+    /// none.
+    pub fn new(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(rand::random);
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// The seed of the server from `DEMIMUD_SEED`, if it is set to a number.
+    #[cfg(feature = "net")]
+    pub fn from_environment() -> Self {
+        let seed = std::env::var(SEED_VARIABLE)
+            .ok()
+            .and_then(|seed| seed.trim().parse().ok());
+        GameRng::new(seed)
+    }
+
+    /// A generator of its own, seeded from this one, for something done on
+    /// another thread, e.g. parsing an area.
+    pub fn split(&mut self) -> GameRng {
+        GameRng::new(Some(self.rng.random()))
+    }
+
+    /// The seed this generator started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// A number in a range, e.g. `range(1..=6)` to roll a die.
+    pub fn range<T, R>(&mut self, range: R) -> T
+    where
+        T: SampleUniform,
+        R: SampleRange<T>,
+    {
+        self.rng.random_range(range)
+    }
+
+    /// True one time in 2^bits.
+    pub fn one_in_bits(&mut self, bits: u8) -> bool {
+        (self.rng.random::<u32>() >> 7) & ((1u32 << bits) - 1) == 0
+    }
+
+    /// True with a chance in percent.
+    pub fn percent(&mut self, chance: u8) -> bool {
+        self.range(0..100) < u32::from(chance)
+    }
+
+    /// Heads or tails.
+    pub fn coin(&mut self) -> bool {
+        self.rng.random()
+    }
+
+    /// Roll some dice, e.g. `dice(2, 6)` for 2d6.
+    pub fn dice(&mut self, count: i32, sides: i32) -> i32 {
+        (0..count).map(|_| self.range(1..=sides)).sum()
+    }
+}
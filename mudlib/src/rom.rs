@@ -0,0 +1,863 @@
+//! ROM 2.4 area loader.
+//!
+//! Stock ROM areas, with their `#AREA` header, are read into the same types
+//! from `crate::world` as Dawn of Time areas, so they are imported and
+//! played the same way. Areas saved by ROM's OLC, with an `#AREADATA`
+//! header, are read too, as their header is the same as Dawn of Time's.
+//!
+//! ROM keeps most properties as numbers and flags rather than words: flags
+//! are letters from A to Z and then a to z, or a number with the same bits.
+//! They are turned into the words that Dawn of Time areas use, for what the
+//! game knows about. Everything else is read and skipped, as are specials.
+//! Helps are kept in `World::helps`, for the `help` command, and socials in
+//! `World::socials`, which the game adds to those of the socials file.
+//! Door resets set whether doors start closed or locked, like exit flags do
+//! in Dawn of Time areas; those of exits without a door are kept as resets,
+//! for `crate::validate` to report.
+//!
+//! Files with only helps or socials, like ROM's `help.are` and `social.are`,
+//! have no `#AREA` section; they are loaded as an area without rooms, named
+//! after the file.
+//!
+//! An area edited with the OLC is saved as a Dawn of Time area.
+
+use std::convert::TryFrom;
+
+use crate::{
+    components::Wealth,
+    file_parser::{FileParser, ParseResult},
+    load::{
+        check_vnum, keep_extra, load_area_data, parse_mobprog_trigger, read_number,
+        read_vnum_section, remember_area, skip_specials, DEFAULT_RESET_INTERVAL,
+    },
+    rng::GameRng,
+    socials::load_area_socials,
+    world::{
+        Area, AreaData, AreaFormat, Exit, ExtraDescription, Gender, Help, MobProg, Mobile, Object,
+        ObjectFlags, ResetCommand, Room, Shop, Vnum,
+    },
+};
+
+/// Directions of exits, by their number
+pub(super) const DIRECTIONS: [&str; 6] = ["north", "east", "south", "west", "up", "down"];
+
+/// Sectors of rooms, by their number; 8 is unused
+const SECTORS: [&str; 11] = [
+    "inside", "city", "field", "forest", "hills", "mountain", "swim", "noswim", "inside", "air",
+    "desert",
+];
+
+/// Wear flags, by their bit; "take" is kept apart from where things are worn
+pub(super) const WEAR_FLAGS: [&str; 17] = [
+    "take", "finger", "neck", "body", "head", "legs", "feet", "hands", "arms", "shield", "about",
+    "waist", "wrist", "wield", "hold", "nosac", "float",
+];
+
+/// Wear locations of equipment resets, by their number
+pub(super) const WEAR_LOCATIONS: [&str; 19] = [
+    "light", "lfinger", "rfinger", "neck1", "neck2", "body", "head", "legs", "feet", "hands",
+    "arms", "shield", "about", "waist", "lwrist", "rwrist", "wielded", "hold", "floating",
+];
+
+// Flags of mobiles, rooms and containers that the game knows about
+const ACT_SENTINEL: u64 = bit(b'B');
+const ACT_SCAVENGER: u64 = bit(b'C');
+const ACT_AGGRESSIVE: u64 = bit(b'F');
+const ACT_STAY_AREA: u64 = bit(b'G');
+const AFF_FLYING: u64 = bit(b'T');
+const ROOM_NO_MOB: u64 = bit(b'C');
+const ROOM_PET_SHOP: u64 = bit(b'M');
+const ROOM_NO_RECALL: u64 = bit(b'N');
+pub(super) const CONT_CLOSEABLE: u64 = 1;
+pub(super) const CONT_CLOSED: u64 = 4;
+pub(super) const CONT_LOCKED: u64 = 8;
+
+/// The bit of a flag letter.
+const fn bit(letter: u8) -> u64 {
+    match letter {
+        b'A'..=b'Z' => 1 << (letter - b'A'),
+        b'a'..=b'z' => 1 << (letter - b'a' + 26),
+        _ => 0,
+    }
+}
+
+/// Parse flags like "ABV", "1024", or several of them like "A|D".
+fn parse_flags(parser: &FileParser, word: &str) -> ParseResult<u64> {
+    let mut flags = 0;
+
+    for part in word.split('|') {
+        if part.bytes().all(|c| c.is_ascii_alphabetic()) {
+            flags |= part.bytes().map(bit).fold(0, |flags, bit| flags | bit);
+        } else {
+            let number: i64 = parser.parse(part, "flags")?;
+            flags |= number.max(0) as u64;
+        }
+    }
+
+    Ok(flags)
+}
+
+fn read_flags(parser: &mut FileParser) -> ParseResult<u64> {
+    let word = parser.read_word()?;
+    parse_flags(parser, word)
+}
+
+pub(super) fn to_vnum(parser: &FileParser, number: i64) -> ParseResult<Vnum> {
+    let vnum =
+        usize::try_from(number).map_err(|_| parser.error(&format!("Invalid vnum: {}", number)))?;
+    Ok(Vnum(check_vnum(parser, vnum)?))
+}
+
+/// The name of an item type, given as a word, or as a number in older areas
+/// and in shops.
+fn item_type(parser: &FileParser, word: &str) -> ParseResult<String> {
+    let name = match word {
+        "drink" => "drink_con",
+        "1" => "light",
+        "2" => "scroll",
+        "3" => "wand",
+        "4" => "staff",
+        "5" => "weapon",
+        "8" => "treasure",
+        "9" => "armor",
+        "10" => "potion",
+        "11" => "clothing",
+        "12" => "furniture",
+        "13" => "trash",
+        "15" => "container",
+        "17" => "drink_con",
+        "18" => "key",
+        "19" => "food",
+        "20" => "money",
+        "22" => "boat",
+        "23" => "npc_corpse",
+        "24" => "pc_corpse",
+        "25" => "fountain",
+        "26" => "pill",
+        "27" => "protect",
+        "28" => "map",
+        "29" => "portal",
+        "30" => "warp_stone",
+        "31" => "room_key",
+        "32" => "gem",
+        "33" => "jewelry",
+        "34" => "jukebox",
+        word if word.bytes().all(|c| c.is_ascii_digit()) => {
+            return Err(parser.error(&format!("Unknown item type: {}", word)))
+        }
+        word => word,
+    };
+
+    Ok(name.to_string())
+}
+
+/// The short name of an area, from its file name, e.g. "midgaard" for
+/// "data/area/midgaard.are".
+pub(super) fn short_name(file_name: &str) -> String {
+    let file_name = file_name.trim().rsplit(['/', '\\']).next().unwrap_or("");
+    file_name
+        .strip_suffix(".are")
+        .unwrap_or(file_name)
+        .to_string()
+}
+
+/// Parse a ROM 2.4 area file.
+pub(super) fn load_rom_area(
+    area_file_contents: &str,
+    file_name: &str,
+    rng: &mut GameRng,
+) -> ParseResult<Area> {
+    let mut parser = FileParser::new(area_file_contents, file_name);
+
+    let mut area_data = None;
+    let mut mobiles = None;
+    let mut objects = None;
+    let mut rooms = None;
+    let mut resets = None;
+    let mut shops = None;
+    let mut mobprogs = None;
+    let mut helps = None;
+    let mut socials = None;
+    // Rooms, exits and how their doors start, from the resets
+    let mut doors = Vec::new();
+
+    // Merc areas have their credits on the same line as the section
+    let first_line = area_file_contents.trim_start().lines().next().unwrap_or("");
+    if first_line.starts_with("#AREA") && first_line.split_whitespace().nth(1).is_some() {
+        parser.read_word()?;
+        area_data = Some(load_area_header(&mut parser, file_name)?);
+    }
+
+    loop {
+        let section = parser.read_section()?;
+
+        match section {
+            "$" => break,
+            "AREA" => area_data = Some(load_area_header(&mut parser, file_name)?),
+            "AREADATA" => area_data = Some(load_area_data(&mut parser)?),
+            "MOBILES" => mobiles = Some(load_mobile_data(&mut parser, rng)?),
+            "OBJECTS" => objects = Some(load_object_data(&mut parser)?),
+            "ROOMS" => rooms = Some(load_room_data(&mut parser)?),
+            "RESETS" => resets = Some(load_resets(&mut parser, &mut doors)?),
+            "SHOPS" => shops = Some(load_shops(&mut parser, item_type)?),
+            "SPECIALS" => skip_specials(&mut parser)?,
+            "MOBPROGS" => mobprogs = Some(load_mobprogs(&mut parser)?),
+            "HELPS" => helps = Some(load_helps(&mut parser)?),
+            "SOCIALS" => socials = Some(load_area_socials(&mut parser)?),
+            section => return Err(parser.error(&format!("Unrecognized section: '#{}'", section))),
+        }
+    }
+
+    let mut area_data = match area_data {
+        Some(area_data) => area_data,
+        None if helps.is_some() || socials.is_some() => AreaData {
+            name: short_name(file_name),
+            short_name: Default::default(),
+            vnums: Default::default(),
+            credits: Default::default(),
+            continent: Default::default(),
+            builders: Default::default(),
+            security: 0,
+            reset_interval: DEFAULT_RESET_INTERVAL,
+            encounters: Vec::new(),
+            file_name: Default::default(),
+            source: None,
+            on_disk: false,
+            changed: false,
+            format: AreaFormat::Rom,
+        },
+        None => return Err(parser.error("Missing the #AREA section")),
+    };
+    if area_data.short_name.is_empty() {
+        area_data.short_name = short_name(file_name);
+    }
+    area_data.credits = clean_credits(&area_data.credits, &area_data.name);
+
+    let mut area = Area {
+        area_data,
+        rooms: rooms.unwrap_or_default(),
+        objects: objects.unwrap_or_default(),
+        mobiles: mobiles.unwrap_or_default(),
+        resets: resets.unwrap_or_default(),
+        shops: shops.unwrap_or_default(),
+        mobprogs: mobprogs.unwrap_or_default(),
+        helps: helps.unwrap_or_default(),
+        socials: socials.unwrap_or_default(),
+    };
+
+    apply_door_resets(&mut area, doors);
+
+    // Merc areas don't say which vnums they have
+    if area.area_data.vnums == (Vnum(0), Vnum(0)) {
+        let vnums = area
+            .rooms
+            .iter()
+            .map(|room| room.vnum.0)
+            .chain(area.objects.iter().map(|object| object.vnum.0))
+            .chain(area.mobiles.iter().map(|mobile| mobile.vnum.0));
+        let lowest = vnums.clone().min().unwrap_or(0);
+        let highest = vnums.max().unwrap_or(0);
+        area.area_data.vnums = (Vnum(lowest), Vnum(highest));
+    }
+
+    remember_area(&mut area);
+
+    Ok(area)
+}
+
+/// Set how doors start from door resets, given as the room, the direction and
+/// whether the door is closed (1) or locked too (2). Those of exits without a
+/// door are kept as resets, for `crate::validate` to report.
+pub(super) fn apply_door_resets(area: &mut Area, doors: Vec<(Vnum, &str, i64)>) {
+    for (room_vnum, direction, state) in doors {
+        let exit = area
+            .rooms
+            .iter_mut()
+            .filter(|room| room.vnum == room_vnum)
+            .flat_map(|room| room.exits.iter_mut())
+            .find(|exit| exit.name == direction && exit.has_door);
+        match exit {
+            Some(exit) => {
+                exit.is_closed = state >= 1;
+                exit.is_locked = state >= 2;
+            }
+            // Kept as they are, for crate::validate to report
+            None => area.resets.push(ResetCommand::Door {
+                r_num: room_vnum,
+                direction: direction.to_string(),
+                door_flags: ["closed", "locked"]
+                    .iter()
+                    .take(state.clamp(0, 2) as usize)
+                    .map(|flag| flag.to_string())
+                    .collect(),
+            }),
+        }
+    }
+}
+
+/// Read the `#AREA` header: the file name, the name, the credits with the
+/// level range, and the vnums. Merc areas only have the credits.
+fn load_area_header(parser: &mut FileParser, file_name: &str) -> ParseResult<AreaData> {
+    let mut area_data = AreaData {
+        name: Default::default(),
+        short_name: Default::default(),
+        vnums: Default::default(),
+        credits: Default::default(),
+        continent: Default::default(),
+        builders: Default::default(),
+        security: 0,
+        reset_interval: DEFAULT_RESET_INTERVAL,
+        encounters: Vec::new(),
+        file_name: Default::default(),
+        source: None,
+        on_disk: false,
+        changed: false,
+        format: AreaFormat::Rom,
+    };
+
+    let first = parser.read_string()?;
+    let credits = if first.trim().ends_with(".are") {
+        area_data.short_name = short_name(first);
+        area_data.name = parser.read_string()?.trim().to_string();
+        let credits = parser.read_string()?;
+        let lowest = read_number(parser, "vnum")?;
+        let highest = read_number(parser, "vnum")?;
+        area_data.vnums = (to_vnum(parser, lowest)?, to_vnum(parser, highest)?);
+        credits
+    } else {
+        // The author comes first and the name after, e.g. "{ 5 35} Diku    Midgaard"
+        area_data.short_name = short_name(file_name);
+        let mut words = first
+            .split_once('}')
+            .map_or(first, |(_levels, rest)| rest)
+            .split_whitespace();
+        words.next();
+        area_data.name = words.collect::<Vec<_>>().join(" ");
+        if area_data.name.is_empty() {
+            area_data.name = area_data.short_name.clone();
+        }
+        first
+    };
+
+    area_data.credits = credits.to_string();
+
+    Ok(area_data)
+}
+
+/// The authors of an area, from credits like "{ 5 35} Diku    Midgaard"
+/// with the level range first and the area's name last.
+fn clean_credits(credits: &str, name: &str) -> String {
+    let credits = credits
+        .split_once('}')
+        .map_or(credits, |(_levels, rest)| rest);
+    let credits = credits.split_whitespace().collect::<Vec<_>>().join(" ");
+    credits
+        .strip_suffix(name)
+        .unwrap_or(&credits)
+        .trim()
+        .to_string()
+}
+
+fn load_mobile_data(parser: &mut FileParser, rng: &mut GameRng) -> ParseResult<Vec<Mobile>> {
+    let mut mobiles = Vec::new();
+
+    loop {
+        let vnum = read_vnum_section(parser)?;
+
+        if vnum == 0 {
+            break;
+        }
+
+        mobiles.push(load_mobile(parser, vnum, rng)?)
+    }
+
+    Ok(mobiles)
+}
+
+fn load_mobile(parser: &mut FileParser, vnum: usize, rng: &mut GameRng) -> ParseResult<Mobile> {
+    let mut mobile = Mobile {
+        vnum: Vnum(vnum),
+        name: parser.read_string()?.to_string(),
+        short_description: parser.read_string()?.to_string(),
+        long_description: parser.read_string()?.to_string(),
+        description: parser.read_string()?.to_string(),
+        ..Default::default()
+    };
+    // Kept under the keys of Dawn of Time areas, for saving
+    keep_extra(&mut mobile.extras, "Race", parser.read_string()?);
+
+    let mut act = read_flags(parser)?;
+    let mut affected_by = read_flags(parser)?;
+    let alignment: i32 = read_number(parser, "alignment")?;
+    keep_extra(&mut mobile.extras, "Align", &alignment.to_string());
+    let _group: i32 = read_number(parser, "group")?;
+    let level: i32 = read_number(parser, "level")?;
+    mobile.level = level.max(0) as u32;
+    let hitroll: i32 = read_number(parser, "hitroll")?;
+    keep_extra(&mut mobile.extras, "Hitroll", &hitroll.to_string());
+    // Hit, mana and damage dice, and the damage type
+    for key in ["HitDice", "ManaDice", "DamDice", "DamType"] {
+        keep_extra(&mut mobile.extras, key, parser.read_word()?);
+    }
+    // Armor class against piercing, bashing, slashing and magic
+    for _ in 0..4 {
+        let _armor_class: i32 = read_number(parser, "armor class")?;
+    }
+    // Offensive flags, immunities, resistances and vulnerabilities
+    for _ in 0..4 {
+        read_flags(parser)?;
+    }
+    let _start_position = parser.read_word()?;
+    let _default_position = parser.read_word()?;
+    mobile.gender = match parser.read_word()? {
+        "male" => Gender::Male,
+        "female" => Gender::Female,
+        "none" | "neutral" => Gender::Neutral,
+        "either" | "random" => {
+            if rng.coin() {
+                Gender::Male
+            } else {
+                Gender::Female
+            }
+        }
+        gender => return Err(parser.error(&format!("Unknown sex/gender: {}", gender))),
+    };
+    let wealth: i64 = read_number(parser, "wealth")?;
+    mobile.wealth = Wealth::from_silver(wealth.max(0) as usize);
+    let _form = read_flags(parser)?;
+    let _parts = read_flags(parser)?;
+    keep_extra(&mut mobile.extras, "Size", parser.read_quoted_word()?);
+    keep_extra(&mut mobile.extras, "Material", parser.read_quoted_word()?);
+
+    loop {
+        match parser.peek_char() {
+            // Flags taken away, e.g. those the race gives
+            Some('F') => {
+                parser.read_word()?;
+                let which = parser.read_word()?;
+                let removed = read_flags(parser)?;
+                match which {
+                    "act" => act &= !removed,
+                    "aff" => affected_by &= !removed,
+                    _ => (),
+                }
+            }
+            Some('M') => {
+                parser.read_word()?;
+                let trigger = parser.read_word()?.to_ascii_uppercase();
+                let mobprog_vnum = parser.read_word()?;
+                let phrase = parser.read_string()?.trim();
+
+                let phrase = match (trigger.as_str(), phrase.parse::<usize>()) {
+                    ("EXIT" | "EXALL", Ok(door)) if door < DIRECTIONS.len() => DIRECTIONS[door],
+                    _ => phrase,
+                };
+                let trigger = match trigger.as_str() {
+                    "RAND" => "RANDOM",
+                    "SURR" => continue,
+                    trigger => trigger,
+                };
+
+                let value = format!("{} {} {}", trigger, mobprog_vnum, phrase);
+                match parse_mobprog_trigger(&value) {
+                    Ok(Some((trigger, vnum))) => mobile.mobprog_triggers.push((trigger, vnum)),
+                    Ok(None) => (),
+                    Err(err) => return Err(parser.error(&err)),
+                }
+            }
+            _ => break,
+        }
+    }
+
+    mobile.sentinel = act & ACT_SENTINEL != 0;
+    mobile.scavenger = act & ACT_SCAVENGER != 0;
+    mobile.aggressive = act & ACT_AGGRESSIVE != 0;
+    mobile.stay_area = act & ACT_STAY_AREA != 0;
+    mobile.flying = affected_by & AFF_FLYING != 0;
+
+    Ok(mobile)
+}
+
+fn load_object_data(parser: &mut FileParser) -> ParseResult<Vec<Object>> {
+    let mut objects = Vec::new();
+
+    loop {
+        let vnum = read_vnum_section(parser)?;
+
+        if vnum == 0 {
+            break;
+        }
+
+        objects.push(load_object(parser, vnum)?)
+    }
+
+    Ok(objects)
+}
+
+fn load_object(parser: &mut FileParser, vnum: usize) -> ParseResult<Object> {
+    let mut object = Object {
+        vnum: Vnum(vnum),
+        name: parser.read_string()?.to_string(),
+        short_description: parser.read_string()?.to_string(),
+        description: parser.read_string()?.to_string(),
+        ..Default::default()
+    };
+    // Kept under the keys of Dawn of Time areas, for saving
+    keep_extra(&mut object.extras, "Material", parser.read_string()?);
+
+    let word = parser.read_word()?;
+    object.item_type = item_type(parser, word)?;
+    let _extra_flags = read_flags(parser)?;
+    let wear_flags = read_flags(parser)?;
+
+    object.take = wear_flags & bit(b'A') != 0;
+    object.wear = WEAR_FLAGS
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find(|(index, flag)| wear_flags & (1 << index) != 0 && **flag != "nosac")
+        .map(|(_index, flag)| flag.to_string());
+
+    // Values are numbers, flags or words like spells and liquids, by type
+    let mut values = Vec::with_capacity(5);
+    for _ in 0..5 {
+        values.push(parser.read_quoted_word()?);
+    }
+
+    match object.item_type.as_str() {
+        "container" => {
+            let flags = parse_flags(parser, values[1])?;
+            object.flags = ObjectFlags::Container {
+                closable: flags & CONT_CLOSEABLE != 0,
+                closed: flags & CONT_CLOSED != 0,
+                locked: flags & CONT_LOCKED != 0,
+            };
+        }
+        "light" => {
+            object.flags = ObjectFlags::Light {
+                capacity: 0,
+                hours: parser.parse(values[2], "light hours")?,
+            };
+        }
+        "drink_con" | "fountain" => {
+            object.flags = ObjectFlags::Liquid {
+                liquid: values[2].to_string(),
+            };
+        }
+        _ => (),
+    }
+
+    let level: i32 = read_number(parser, "level")?;
+    keep_extra(&mut object.extras, "Level", &level.to_string());
+    let weight: i32 = read_number(parser, "weight")?;
+    keep_extra(&mut object.extras, "Weight", &weight.to_string());
+    object.cost = read_number(parser, "cost")?;
+    let _condition = parser.read_word()?;
+
+    loop {
+        match parser.peek_char() {
+            // Affects on whoever wears it
+            Some('A') => {
+                parser.read_word()?;
+                let _location: i32 = read_number(parser, "apply location")?;
+                let _modifier: i32 = read_number(parser, "apply modifier")?;
+            }
+            Some('F') => {
+                parser.read_word()?;
+                let _applies_to = parser.read_word()?;
+                let _location: i32 = read_number(parser, "apply location")?;
+                let _modifier: i32 = read_number(parser, "apply modifier")?;
+                read_flags(parser)?;
+            }
+            Some('E') => {
+                parser.read_word()?;
+                object.extra_descriptions.push(ExtraDescription {
+                    keyword: parser.read_string()?.to_string(),
+                    description: parser.read_string()?.to_string(),
+                });
+            }
+            _ => break,
+        }
+    }
+
+    Ok(object)
+}
+
+fn load_room_data(parser: &mut FileParser) -> ParseResult<Vec<Room>> {
+    let mut rooms = Vec::new();
+
+    loop {
+        let vnum = read_vnum_section(parser)?;
+
+        if vnum == 0 {
+            break;
+        }
+
+        rooms.push(load_room(parser, vnum)?)
+    }
+
+    Ok(rooms)
+}
+
+fn load_room(parser: &mut FileParser, vnum: usize) -> ParseResult<Room> {
+    let mut room = Room {
+        vnum: Vnum(vnum),
+        name: parser.read_string()?.to_string(),
+        description: parser.read_string()?.to_string(),
+        ..Default::default()
+    };
+
+    let _area_number: i32 = read_number(parser, "area number")?;
+    let room_flags = read_flags(parser)?;
+    room.no_mob = room_flags & ROOM_NO_MOB != 0;
+    room.pet_shop = room_flags & ROOM_PET_SHOP != 0;
+    room.no_recall = room_flags & ROOM_NO_RECALL != 0;
+    let sector: usize = read_number(parser, "sector")?;
+    room.sector = SECTORS.get(sector).copied().unwrap_or("inside").to_string();
+
+    loop {
+        // A letter, and for exits their direction right after it, e.g. "D0"
+        let word = parser.read_word()?;
+        let letter = word.chars().next().unwrap_or_default();
+        let rest = &word[letter.len_utf8()..];
+        let mut number = |what: &str| -> ParseResult<i64> {
+            match rest {
+                "" => read_number(parser, what),
+                rest => parser.parse(rest, what),
+            }
+        };
+
+        match letter {
+            'S' => break,
+            'H' => room.heal_rate = u32::try_from(number("heal rate")?).ok(),
+            'M' => room.mana_rate = u32::try_from(number("mana rate")?).ok(),
+            // Clans and owners
+            'C' | 'O' => {
+                parser.read_string()?;
+            }
+            'D' => {
+                let door = number("direction")?;
+                let name = usize::try_from(door)
+                    .ok()
+                    .and_then(|door| DIRECTIONS.get(door))
+                    .ok_or_else(|| parser.error(&format!("Invalid direction: {}", door)))?;
+                let description = parser.read_string()?;
+                let keywords = parser.read_string()?;
+                let locks: i32 = read_number(parser, "exit locks")?;
+                let key: i64 = read_number(parser, "key vnum")?;
+                let to_room: i64 = read_number(parser, "exit vnum")?;
+
+                // Exits that lead nowhere
+                if to_room < 0 {
+                    continue;
+                }
+
+                let not_empty =
+                    |text: &str| Some(text.to_string()).filter(|text| !text.trim().is_empty());
+                room.exits.push(Exit {
+                    name: name.to_string(),
+                    vnum: to_vnum(parser, to_room)?,
+                    description: not_empty(description),
+                    extra_keywords: not_empty(keywords),
+                    has_door: locks > 0,
+                    key: if key > 0 {
+                        Some(to_vnum(parser, key)?)
+                    } else {
+                        None
+                    },
+                    ..Default::default()
+                });
+            }
+            'E' => room.extra_descriptions.push(ExtraDescription {
+                keyword: parser.read_string()?.to_string(),
+                description: parser.read_string()?.to_string(),
+            }),
+            _ => return Err(parser.error(&format!("Unrecognized room data: '{}'", word))),
+        }
+    }
+
+    room.heal_rate = room.heal_rate.filter(|rate| *rate != 100);
+    room.mana_rate = room.mana_rate.filter(|rate| *rate != 100);
+
+    Ok(room)
+}
+
+/// Read the resets, keeping door resets apart as the room, the direction
+/// and whether the door is closed (1) or locked too (2).
+fn load_resets(
+    parser: &mut FileParser,
+    doors: &mut Vec<(Vnum, &'static str, i64)>,
+) -> ParseResult<Vec<ResetCommand>> {
+    let mut resets = Vec::new();
+
+    loop {
+        let reset_type = parser.read_word()?;
+
+        let reset_type = match reset_type {
+            "S" => {
+                parser.read_until_newline()?;
+                break;
+            }
+            "M" | "O" | "P" | "G" | "E" | "D" | "R" => reset_type,
+            // Comments
+            _ => {
+                parser.read_until_newline()?;
+                continue;
+            }
+        };
+
+        let _if_flag: i64 = read_number(parser, "reset flag")?;
+        let arg1: i64 = read_number(parser, "reset argument")?;
+        let arg2: i64 = read_number(parser, "reset argument")?;
+        let arg3: i64 = match reset_type {
+            "G" | "R" => 0,
+            _ => read_number(parser, "reset argument")?,
+        };
+        let arg4: i64 = match reset_type {
+            "M" | "P" => read_number(parser, "reset argument")?,
+            _ => 0,
+        };
+        // Whatever is left is a comment
+        parser.read_until_newline()?;
+
+        let limit = |limit: i64| limit.clamp(i16::MIN.into(), i16::MAX.into()) as i16;
+
+        match reset_type {
+            "M" => resets.push(ResetCommand::Mob {
+                m_num: to_vnum(parser, arg1)?,
+                global_limit: arg2.clamp(0, u16::MAX.into()) as u16,
+                r_num: to_vnum(parser, arg3)?,
+                room_limit: arg4.clamp(0, u16::MAX.into()) as u16,
+                only_at: None,
+            }),
+            "O" => resets.push(ResetCommand::Object {
+                o_num: to_vnum(parser, arg1)?,
+                global_limit: limit(arg2),
+                r_num: to_vnum(parser, arg3)?,
+                only_at: None,
+            }),
+            "P" => resets.push(ResetCommand::Put {
+                o_num: to_vnum(parser, arg1)?,
+                global_limit: limit(arg2),
+                c_num: to_vnum(parser, arg3)?,
+                container_limit: limit(arg4),
+            }),
+            "G" => resets.push(ResetCommand::Give {
+                o_num: to_vnum(parser, arg1)?,
+                global_limit: limit(arg2),
+            }),
+            "E" => {
+                let o_num = to_vnum(parser, arg1)?;
+                // Equipment that isn't worn is only carried
+                match usize::try_from(arg3)
+                    .ok()
+                    .and_then(|at| WEAR_LOCATIONS.get(at))
+                {
+                    Some(location) => resets.push(ResetCommand::Equip {
+                        o_num,
+                        global_limit: limit(arg2),
+                        location: location.to_string(),
+                    }),
+                    None => resets.push(ResetCommand::Give {
+                        o_num,
+                        global_limit: limit(arg2),
+                    }),
+                }
+            }
+            "D" => {
+                let direction = usize::try_from(arg2)
+                    .ok()
+                    .and_then(|door| DIRECTIONS.get(door))
+                    .ok_or_else(|| parser.error(&format!("Invalid direction: {}", arg2)))?;
+                doors.push((to_vnum(parser, arg1)?, *direction, arg3));
+            }
+            // Exits shuffled around for mazes
+            _ => (),
+        }
+    }
+
+    Ok(resets)
+}
+
+/// Read the shops, with the item types they buy given as numbers, which
+/// SMAUG numbers differently.
+pub(super) fn load_shops(
+    parser: &mut FileParser,
+    item_type: fn(&FileParser, &str) -> ParseResult<String>,
+) -> ParseResult<Vec<Shop>> {
+    let mut shops = Vec::new();
+
+    loop {
+        let keeper: i64 = read_number(parser, "shopkeeper")?;
+        if keeper == 0 {
+            parser.read_until_newline()?;
+            break;
+        }
+
+        let mut buy_types = Vec::new();
+        for _ in 0..5 {
+            match parser.read_word()? {
+                "0" => (),
+                word => buy_types.push(item_type(parser, word)?),
+            }
+        }
+
+        shops.push(Shop {
+            vnum: to_vnum(parser, keeper)?,
+            buy_types,
+            sell_types: Vec::new(),
+            profit_buy: read_number(parser, "buy profit")?,
+            profit_sell: read_number(parser, "sell profit")?,
+            open_hour: read_number(parser, "open hour")?,
+            close_hour: read_number(parser, "close hour")?,
+            area: String::new(),
+        });
+        parser.read_until_newline()?;
+    }
+
+    Ok(shops)
+}
+
+fn load_mobprogs(parser: &mut FileParser) -> ParseResult<Vec<MobProg>> {
+    let mut mobprogs = Vec::new();
+
+    loop {
+        let vnum = read_vnum_section(parser)?;
+
+        if vnum == 0 {
+            break;
+        }
+
+        mobprogs.push(MobProg {
+            vnum: Vnum(vnum),
+            title: "<untitled>".to_string(),
+            code: parser.read_string()?.to_string(),
+            disabled: false,
+            area: String::new(),
+        });
+    }
+
+    Ok(mobprogs)
+}
+
+/// Help entries are a level, keywords and text, until the keyword "$".
+pub(super) fn load_helps(parser: &mut FileParser) -> ParseResult<Vec<Help>> {
+    let mut helps = Vec::new();
+    loop {
+        let level = read_number(parser, "help level")?;
+        let keywords = parser.read_string()?;
+        if keywords.trim_start().starts_with('$') {
+            return Ok(helps);
+        }
+
+        // A leading '.' keeps the spaces after it, which would be skipped
+        let text = parser.read_string()?;
+        let text = text.strip_prefix('.').unwrap_or(text);
+        helps.push(Help {
+            level,
+            keywords: keywords.trim().to_string(),
+            text: text.to_string(),
+            area: String::new(),
+        });
+    }
+}
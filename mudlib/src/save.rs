@@ -0,0 +1,498 @@
+//! Dawn of Time area writer.
+//!
+//! The counterpart of `crate::load`: turns the plain object types from
+//! `crate::world` back into area files, in the subset of the format that the
+//! loader understands. Loading a saved area gives back the same area.
+
+use std::fmt::Write;
+
+use crate::{
+    load::{MOBILE_TEXT_KEYS, OBJECT_TEXT_KEYS, ROOM_TEXT_KEYS},
+    socials::Social,
+    world::{
+        AreaData, Exit, ExtraDescription, Gender, Help, MobProg, MobProgTrigger, Mobile, Object,
+        ObjectFlags, ResetCommand, Room, Shop, TimeOfDay, Vnum, VnumOrKeyword, World,
+    },
+};
+
+/// Write the contents of an area file for one of the world's areas.
+pub(super) fn save_area(world: &World, area_index: usize) -> String {
+    let (area_data, resets) = &world.areas[area_index];
+    let in_area = |area: &str| area == area_data.short_name;
+
+    let mut file = String::new();
+
+    save_area_data(&mut file, area_data);
+
+    file.push_str("#MOBILES\n");
+    for mobile in world.mobiles.iter().filter(|mobile| in_area(&mobile.area)) {
+        save_mobile(&mut file, mobile);
+    }
+    file.push_str("#0\n\n\n");
+
+    file.push_str("#OBJECTS\n");
+    for object in world.objects.iter().filter(|object| in_area(&object.area)) {
+        save_object(&mut file, object);
+    }
+    file.push_str("#0\n\n\n");
+
+    file.push_str("#ROOMS\n");
+    for room in world.rooms.iter().filter(|room| in_area(&room.area)) {
+        save_room(&mut file, room);
+    }
+    file.push_str("#0\n\n\n");
+
+    file.push_str("#RESETS2\n");
+    for reset in resets {
+        save_reset(&mut file, reset);
+    }
+    file.push_str("S\n\n\n");
+
+    file.push_str("#SHOPS\n");
+    for shop in world.shops.iter().filter(|shop| in_area(&shop.area)) {
+        save_shop(&mut file, shop);
+    }
+    file.push_str("#0\n\n\n");
+
+    file.push_str("#MOBPROGS\n");
+    for mobprog in world
+        .mobprogs
+        .iter()
+        .filter(|mobprog| in_area(&mobprog.area))
+    {
+        save_mobprog(&mut file, mobprog);
+    }
+    file.push_str("#0\n\n");
+
+    let mut helps = world
+        .helps
+        .iter()
+        .filter(|help| in_area(&help.area))
+        .peekable();
+    if helps.peek().is_some() {
+        file.push_str("#HELPS\n");
+        for help in helps {
+            save_help(&mut file, help);
+        }
+        file.push_str("0 $~\n\n");
+    }
+
+    let mut socials = world
+        .socials
+        .iter()
+        .filter(|social| in_area(&social.area))
+        .peekable();
+    if socials.peek().is_some() {
+        file.push_str("#SOCIALS\n");
+        for social in socials {
+            save_social(&mut file, social);
+        }
+        file.push_str("#0\n\n");
+    }
+    file.push_str("#$\n");
+
+    // Loaded text uses telnet's newlines; files use plain ones
+    file.replace("\r\n", "\n")
+}
+
+/// Write the list of area files that are loaded at startup.
+pub(super) fn save_area_list(world: &World) -> String {
+    let mut file = String::new();
+
+    let mut listed = Vec::new();
+    // Areas of other sources are in the area lists of their directories
+    let listed_here = |area: &AreaData| area.on_disk && area.source.is_none();
+    for (area_data, _resets) in world.areas.iter().filter(|(area, _)| listed_here(area)) {
+        // JSON files may have several areas, and are only listed once
+        if !listed.contains(&&area_data.file_name) {
+            writeln!(file, "{}", area_data.file_name).unwrap();
+            listed.push(&area_data.file_name);
+        }
+    }
+    file.push_str("$\n");
+
+    file
+}
+
+fn save_area_data(file: &mut String, area_data: &AreaData) {
+    file.push_str("#DAWNAREADATA\n");
+    writeln!(file, "Name        {}~", area_data.name).unwrap();
+    writeln!(file, "ShortName   {}~", area_data.short_name).unwrap();
+
+    if !area_data.builders.is_empty() {
+        writeln!(file, "Builders    {}~", area_data.builders).unwrap();
+    }
+    if area_data.security > 0 {
+        writeln!(file, "Security    {}", area_data.security).unwrap();
+    }
+    if !area_data.credits.is_empty() {
+        writeln!(file, "Credits     {}~", area_data.credits).unwrap();
+    }
+    if !area_data.continent.is_empty() {
+        writeln!(file, "Continent   {}~", area_data.continent).unwrap();
+    }
+
+    let (first, last) = area_data.vnums;
+    writeln!(file, "VNUMs       {} {}", first.0, last.0).unwrap();
+    writeln!(file, "ResetInterval {}", area_data.reset_interval).unwrap();
+    for encounter in &area_data.encounters {
+        writeln!(
+            file,
+            "Encounter   {} {} {}",
+            encounter.sector, encounter.vnum.0, encounter.limit
+        )
+        .unwrap();
+    }
+    file.push_str("End\n\n\n");
+}
+
+fn save_mobile(file: &mut String, mobile: &Mobile) {
+    writeln!(file, "#{}", mobile.vnum.0).unwrap();
+    writeln!(file, "Name {}~", mobile.name).unwrap();
+    writeln!(file, "ShortD {}~", mobile.short_description).unwrap();
+    writeln!(file, "LongD {}~", mobile.long_description).unwrap();
+    if let Some(night_long_description) = &mobile.night_long_description {
+        writeln!(file, "NightLongD {}~", night_long_description).unwrap();
+    }
+    writeln!(file, "Desc {}~", mobile.description).unwrap();
+
+    let act_flags: Vec<&str> = [
+        (mobile.sentinel, "dont_wander"),
+        (mobile.stay_area, "stay_area"),
+        (mobile.scavenger, "scavenger"),
+        (mobile.aggressive, "aggressive"),
+        (mobile.unseen, "unseen"),
+        (mobile.postmaster, "postmaster"),
+        (mobile.banker, "banker"),
+        (mobile.repairer, "repairer"),
+    ]
+    .iter()
+    .filter(|(set, _flag)| *set)
+    .map(|(_set, flag)| *flag)
+    .collect();
+    if !act_flags.is_empty() {
+        writeln!(file, "Act    {}~", act_flags.join(" ")).unwrap();
+    }
+    if mobile.flying {
+        writeln!(file, "AffBy  flying~").unwrap();
+    }
+    if mobile.level != 0 {
+        writeln!(file, "Level  {}", mobile.level).unwrap();
+    }
+    if !mobile.wealth.is_empty() {
+        writeln!(file, "Wealth {}", mobile.wealth.silver_value()).unwrap();
+    }
+
+    let gender = match mobile.gender {
+        Gender::Male => "male",
+        Gender::Female => "female",
+        Gender::Neutral => "neutral",
+    };
+    writeln!(file, "Sex    {}~", gender).unwrap();
+
+    for (trigger, vnum) in &mobile.mobprog_triggers {
+        writeln!(file, "MProg {}~", format_mobprog_trigger(trigger, *vnum)).unwrap();
+    }
+    save_extras(file, &mobile.extras, MOBILE_TEXT_KEYS);
+
+    file.push_str("\nEND\n\n");
+}
+
+/// Write a mobprog trigger the way `crate::load::parse_mobprog_trigger()`
+/// reads it, e.g. "GREET 3 100".
+pub(super) fn format_mobprog_trigger(trigger: &MobProgTrigger, vnum: Vnum) -> String {
+    let vnum = vnum.0;
+
+    match trigger {
+        MobProgTrigger::Random { chance } => format!("RANDOM {} {}", vnum, chance),
+        MobProgTrigger::Greet { chance } => format!("GREET {} {}", vnum, chance),
+        MobProgTrigger::Entry { chance } => format!("ENTRY {} {}", vnum, chance),
+        MobProgTrigger::Speech { pattern } => format!("SPEECH {} {}", vnum, pattern),
+        MobProgTrigger::Act { pattern } => format!("ACT {} {}", vnum, pattern),
+        MobProgTrigger::Exit { direction } => format!("EXIT {} {}", vnum, direction),
+        MobProgTrigger::Bribe { amount } => format!("BRIBE {} {}", vnum, amount),
+        MobProgTrigger::Give { item_vnum } => match item_vnum {
+            VnumOrKeyword::Vnum(item) => format!("GIVE {} {}", vnum, item.0),
+            VnumOrKeyword::Keyword(keyword) => format!("GIVE {} {}", vnum, keyword),
+        },
+        MobProgTrigger::Kill { chance } => format!("KILL {} {}", vnum, chance),
+        MobProgTrigger::Death { chance } => format!("DEATH {} {}", vnum, chance),
+        MobProgTrigger::Hour { hour } => format!("HOUR {} {}", vnum, hour),
+        MobProgTrigger::LoginRoom => format!("LOGINROOM {}", vnum),
+    }
+}
+
+fn save_object(file: &mut String, object: &Object) {
+    writeln!(file, "#{}", object.vnum.0).unwrap();
+    writeln!(file, "Name {}~", object.name).unwrap();
+    writeln!(file, "Short {}~", object.short_description).unwrap();
+    writeln!(file, "Desc {}~", object.description).unwrap();
+    writeln!(file, "ItemType {}~", object.item_type).unwrap();
+    writeln!(file, "Cost {}", object.cost).unwrap();
+
+    let wear_flags: Vec<&str> = object
+        .take
+        .then_some("take")
+        .into_iter()
+        .chain(object.wear.as_deref())
+        .collect();
+    if !wear_flags.is_empty() {
+        writeln!(file, "Wear {}~", wear_flags.join(" ")).unwrap();
+    }
+
+    if let ObjectFlags::Container {
+        closable,
+        closed,
+        locked,
+    } = object.flags
+    {
+        let flags: String = [(closable, 'A'), (closed, 'C'), (locked, 'D')]
+            .iter()
+            .filter(|(set, _flag)| *set)
+            .map(|(_set, flag)| *flag)
+            .collect();
+        let flags = if flags.is_empty() { "0" } else { &flags };
+        writeln!(file, "Values 0 {} 0 0 0", flags).unwrap();
+    }
+    if let ObjectFlags::Light { capacity, hours } = object.flags {
+        writeln!(file, "Values {} 0 {} 0 0", capacity, hours).unwrap();
+    }
+    if let ObjectFlags::Liquid { liquid } = &object.flags {
+        writeln!(file, "Values 0 0 '{}' 0 0", liquid).unwrap();
+    }
+    save_extras(file, &object.extras, OBJECT_TEXT_KEYS);
+
+    save_extra_descriptions(file, &object.extra_descriptions);
+    file.push_str("End\n\n");
+}
+
+fn save_room(file: &mut String, room: &Room) {
+    writeln!(file, "#{}", room.vnum.0).unwrap();
+    writeln!(file, "Name {}~", room.name).unwrap();
+    writeln!(file, "Desc {}~", room.description).unwrap();
+    if let Some(night_description) = &room.night_description {
+        writeln!(file, "NightDesc {}~", night_description).unwrap();
+    }
+    if !room.sector.is_empty() {
+        writeln!(file, "Sector {}~", room.sector).unwrap();
+    }
+    let room_flags: Vec<&str> = [
+        (room.pet_shop, "pet_shop"),
+        (room.stall, "stall"),
+        (room.no_mob, "no_mob"),
+        (room.no_recall, "no_recall"),
+    ]
+    .iter()
+    .filter(|(set, _flag)| *set)
+    .map(|(_set, flag)| *flag)
+    .chain(room.flags.iter().map(|flag| flag.as_str()))
+    .collect();
+    if !room_flags.is_empty() {
+        writeln!(file, "RoomFlags {}~", room_flags.join(" ")).unwrap();
+    }
+    if let Some(heal_rate) = room.heal_rate {
+        writeln!(file, "Heal {}", heal_rate).unwrap();
+    }
+    if let Some(mana_rate) = room.mana_rate {
+        writeln!(file, "Mana {}", mana_rate).unwrap();
+    }
+    // Before the exits, whose keys follow them
+    save_extras(file, &room.extras, ROOM_TEXT_KEYS);
+
+    for exit in &room.exits {
+        save_exit(file, exit);
+    }
+
+    save_extra_descriptions(file, &room.extra_descriptions);
+    file.push_str("End\n\n");
+}
+
+fn save_exit(file: &mut String, exit: &Exit) {
+    writeln!(file, "Exit {} {}", exit.name, exit.vnum.0).unwrap();
+
+    if let Some(description) = &exit.description {
+        writeln!(file, "EDesc {}~", description).unwrap();
+    }
+
+    let flags: Vec<&str> = [
+        (exit.has_door, "door"),
+        (exit.is_closed, "closed"),
+        (exit.is_locked, "locked"),
+    ]
+    .iter()
+    .filter(|(set, _flag)| *set)
+    .map(|(_set, flag)| *flag)
+    .collect();
+    if !flags.is_empty() {
+        writeln!(file, "EFlags {}~", flags.join(" ")).unwrap();
+    }
+
+    if let Some(key) = exit.key {
+        writeln!(file, "EKeyvnum {}", key.0).unwrap();
+    }
+    if let Some(keywords) = &exit.extra_keywords {
+        writeln!(file, "EKeywords {}~", keywords).unwrap();
+    }
+}
+
+/// Write the keys kept by `crate::load::keep_extra()` as they were read.
+fn save_extras(file: &mut String, extras: &[(String, String)], text_keys: &[&str]) {
+    for (key, value) in extras {
+        let end = if text_keys.contains(&key.as_str()) {
+            "~"
+        } else {
+            ""
+        };
+        writeln!(file, "{} {}{}", key, value, end).unwrap();
+    }
+}
+
+fn save_extra_descriptions(file: &mut String, extra_descriptions: &[ExtraDescription]) {
+    for extra_description in extra_descriptions {
+        writeln!(
+            file,
+            "ExtraDesc {}~\n{}~",
+            extra_description.keyword, extra_description.description
+        )
+        .unwrap();
+    }
+}
+
+/// Write the first number of a mobile or object reset the way
+/// `crate::load` reads it.
+fn format_reset_time(only_at: Option<TimeOfDay>) -> &'static str {
+    match only_at {
+        None => "0",
+        Some(TimeOfDay::Day) => "day",
+        Some(TimeOfDay::Night) => "night",
+    }
+}
+
+fn save_reset(file: &mut String, reset: &ResetCommand) {
+    match reset {
+        ResetCommand::Mob {
+            m_num,
+            global_limit,
+            r_num,
+            room_limit,
+            only_at,
+        } => writeln!(
+            file,
+            "M {} {} {} {} {}",
+            format_reset_time(*only_at),
+            m_num.0,
+            global_limit,
+            r_num.0,
+            room_limit
+        ),
+        ResetCommand::Object {
+            o_num,
+            global_limit,
+            r_num,
+            only_at,
+        } => writeln!(
+            file,
+            "O {} {} {} {}",
+            format_reset_time(*only_at),
+            o_num.0,
+            global_limit,
+            r_num.0
+        ),
+        ResetCommand::Door {
+            r_num,
+            direction,
+            door_flags,
+        } => writeln!(
+            file,
+            "D 0 {} {} {}",
+            r_num.0,
+            direction,
+            door_flags.join(" ")
+        ),
+        ResetCommand::Give {
+            o_num,
+            global_limit,
+        } => writeln!(file, "G 0 {} {}", o_num.0, global_limit),
+        ResetCommand::Equip {
+            o_num,
+            global_limit,
+            location,
+        } => writeln!(file, "E 0 {} {} {}~", o_num.0, global_limit, location),
+        ResetCommand::Put {
+            o_num,
+            global_limit,
+            c_num,
+            container_limit,
+        } => writeln!(
+            file,
+            "P 0 {} {} {} {}",
+            o_num.0, global_limit, c_num.0, container_limit
+        ),
+    }
+    .unwrap();
+}
+
+fn save_shop(file: &mut String, shop: &Shop) {
+    writeln!(file, "#{}", shop.vnum.0).unwrap();
+
+    // The loader keeps the space in front of these
+    for buy_type in &shop.buy_types {
+        writeln!(file, "buy_type {}~", buy_type.trim_start()).unwrap();
+    }
+    for sell_type in &shop.sell_types {
+        writeln!(file, "sell_type {}~", sell_type.trim_start()).unwrap();
+    }
+
+    writeln!(file, "profit_buy {}", shop.profit_buy).unwrap();
+    writeln!(file, "profit_sell {}", shop.profit_sell).unwrap();
+    writeln!(file, "open_hour {}", shop.open_hour).unwrap();
+    writeln!(file, "close_hour {}", shop.close_hour).unwrap();
+    file.push_str("END\n\n");
+}
+
+fn save_help(file: &mut String, help: &Help) {
+    writeln!(file, "{} {}~", help.level, help.keywords).unwrap();
+    // The loader skips spaces before the text, and a '.' before them
+    if help
+        .text
+        .starts_with(|c: char| c.is_whitespace() || c == '.')
+    {
+        file.push('.');
+    }
+    writeln!(file, "{}~", help.text).unwrap();
+}
+
+/// Socials are written as in ROM's social.are, with "$" for no message, and
+/// "#" after the last one.
+fn save_social(file: &mut String, social: &Social) {
+    let messages = [
+        &social.untargetted_self,
+        &social.untargetted_others,
+        &social.targetted_self,
+        &social.targetted_others,
+        &social.targetted_target,
+        "",
+        &social.reflected_self,
+        &social.reflected_others,
+    ];
+    let count = messages
+        .iter()
+        .rposition(|message| !message.is_empty())
+        .map_or(0, |last| last + 1);
+
+    writeln!(file, "{} 0 0", social.name).unwrap();
+    for message in &messages[..count] {
+        // The loader capitalizes messages that start with "$n" again
+        let message = message.strip_prefix("$^").unwrap_or(message);
+        writeln!(file, "{}", if message.is_empty() { "$" } else { message }).unwrap();
+    }
+    file.push_str("#\n\n");
+}
+
+fn save_mobprog(file: &mut String, mobprog: &MobProg) {
+    writeln!(file, "#{}", mobprog.vnum.0).unwrap();
+    writeln!(file, "title {}~", mobprog.title).unwrap();
+    writeln!(file, "code {}~", mobprog.code).unwrap();
+    writeln!(file, "disabled {}", mobprog.disabled).unwrap();
+    file.push_str("END\n\n");
+}
@@ -0,0 +1,176 @@
+//! Scavengers, which keep the streets clean.
+//!
+//! Now and then, a mobile with the `scavenger` act flag picks up the most
+//! valuable thing lying in its room, if it can be taken (the `take` wear
+//! flag) or is silver. If it is also `aggressive`, it wears or wields what it
+//! found when that is worth more than what it has on already.
+//!
+//! Scavengers drop everything they carry when they die, so that what they
+//! picked up can be got back.
+
+use crate::{agent::EntityAgent, components::Mobile, echo, entity::EntityId, WorldState};
+
+/// The wear locations (as in resets) that a wear flag can go on.
+fn wear_locations(wear: &str) -> &'static [&'static str] {
+    match wear {
+        "finger" => &["lfinger", "rfinger"],
+        "neck" => &["neck1", "neck2"],
+        "wrist" => &["lwrist", "rwrist"],
+        "wield" => &["wielded"],
+        "wearfloat" | "float" => &["floating"],
+        "body" => &["body"],
+        "head" => &["head"],
+        "legs" => &["legs"],
+        "feet" => &["feet"],
+        "hands" => &["hands"],
+        "arms" => &["arms"],
+        "shield" => &["shield"],
+        "about" => &["about"],
+        "waist" => &["waist"],
+        "hold" => &["hold"],
+        _ => &[],
+    }
+}
+
+pub(crate) fn update_scavengers(world_state: &mut WorldState) {
+    let entity_world = &world_state.entity_world;
+    let mut finds = Vec::new();
+
+    for (entity, mobile) in entity_world.query::<&Mobile>() {
+        if !mobile.scavenger || !world_state.rng.one_in_bits(3) {
+            continue;
+        }
+
+        let most_valuable = entity
+            .room()
+            .objects()
+            .filter_map(|object| {
+                let components = object.components();
+                let value = match (&components.wealth, &components.object) {
+                    (Some(wealth), _) => wealth.silver_value() as i32,
+                    (None, Some(object)) if object.take => object.cost,
+                    _ => return None,
+                };
+                Some((value, object.entity_id()))
+            })
+            .filter(|(value, _object_id)| *value > 0)
+            .max_by_key(|(value, _object_id)| *value);
+
+        if let Some((_value, object_id)) = most_valuable {
+            finds.push((entity.entity_id(), object_id));
+        }
+    }
+
+    for (scavenger_id, object_id) in finds {
+        let mut agent = EntityAgent::new(world_state, scavenger_id);
+        agent.scavenge(object_id);
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Pick up an object from the room, and wear it if it is an upgrade.
+    fn scavenge(&mut self, object_id: EntityId) {
+        // Another scavenger in the room could have been quicker
+        let room_id = self.entity_world.room_of(self.entity_id);
+        if self.entity_world.room_of(object_id) != room_id {
+            return;
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let object = self.entity_world.entity_info(object_id);
+        let mut act = self.players.act_with(&myself, &object);
+        echo!(act.others(), "$^$n gets $N.\r\n");
+
+        let is_silver = object.components().wealth.is_some();
+        self.entity_world.move_entity(object_id, self.entity_id);
+
+        if is_silver {
+            self.merge_coins(object_id);
+            return;
+        }
+
+        let aggressive = self
+            .entity_world
+            .entity_info(self.entity_id)
+            .components()
+            .mobile
+            .as_ref()
+            .map(|mobile| mobile.aggressive)
+            .unwrap_or(false);
+        if aggressive {
+            self.wear_upgrade(object_id);
+        }
+    }
+
+    /// Wear or wield an object being carried, if there is a free spot for
+    /// it, or if it is worth more than what is on the spot.
+    fn wear_upgrade(&mut self, object_id: EntityId) {
+        let object = self.entity_world.entity_info(object_id);
+        let (cost, wear) = match &object.components().object {
+            Some(object) => (object.cost, object.wear.clone()),
+            None => return,
+        };
+        let locations = match wear {
+            Some(wear) => wear_locations(&wear),
+            None => return,
+        };
+        if locations.is_empty() {
+            return;
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let worn: Vec<(&str, EntityId, i32)> = locations
+            .iter()
+            .filter_map(|location| {
+                myself
+                    .objects()
+                    .find(|item| item.equipped() == Some(location))
+                    .map(|item| {
+                        let cost = item.components().object.as_ref().map_or(0, |o| o.cost);
+                        (*location, item.entity_id(), cost)
+                    })
+            })
+            .collect();
+
+        let location = match locations
+            .iter()
+            .find(|location| worn.iter().all(|(worn_at, _, _)| worn_at != *location))
+        {
+            Some(free) => *free,
+            None => {
+                let (location, worn_id, worn_cost) = worn
+                    .iter()
+                    .min_by_key(|(_location, _worn_id, worn_cost)| *worn_cost)
+                    .copied()
+                    .expect("Every location is taken");
+                if worn_cost >= cost {
+                    return;
+                }
+
+                let worn = self.entity_world.entity_info(worn_id);
+                let mut act = self.players.act_with(&myself, &worn);
+                echo!(act.others(), "$^$n stops using $N.\r\n");
+                self.entity_world
+                    .entity_info_mut(worn_id)
+                    .components()
+                    .general
+                    .equipped = None;
+                location
+            }
+        };
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let object = self.entity_world.entity_info(object_id);
+        let mut act = self.players.act_with(&myself, &object);
+        match location {
+            "wielded" => echo!(act.others(), "$^$n wields $N.\r\n"),
+            "hold" => echo!(act.others(), "$^$n holds $N in $s hand.\r\n"),
+            _ => echo!(act.others(), "$^$n wears $N.\r\n"),
+        }
+        self.entity_world
+            .entity_info_mut(object_id)
+            .components()
+            .general
+            .equipped = Some(location.to_string());
+    }
+}
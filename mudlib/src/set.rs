@@ -0,0 +1,386 @@
+//! The `set` command, for immortals to fix things in the world while it runs,
+//! by changing fields of an entity's components.
+//!
+//! Targets are found like with `stat`. Values are checked before anything is
+//! changed, and every change is put in the immortal log.
+
+use std::sync::Arc;
+
+use crate::{
+    agent::EntityAgent,
+    components::{AttrValue, Decay, Denomination, Wealth},
+    durability::NEW_CONDITION,
+    echo,
+    entity::EntityId,
+    wizlog::LogKind,
+    world::Gender,
+};
+
+/// Fields that can be set, and what they are
+static FIELDS: &[(&str, &str)] = &[
+    ("keywords", "Words it is known by; not for players"),
+    ("short", "What it is called in messages; not for players"),
+    ("gender", "male, female or neutral"),
+    ("copper", "Copper coins carried, or in a pile of coins"),
+    ("silver", "Silver coins carried, or in a pile of coins"),
+    ("gold", "Gold coins carried, or in a pile of coins"),
+    ("cost", "What an object is worth"),
+    ("wander", "Whether a mobile wanders around"),
+    ("stay_area", "Whether a mobile wanders only in its area"),
+    ("flying", "Whether a mobile wanders through the air"),
+    ("scavenger", "Whether a mobile picks up things lying around"),
+    ("aggressive", "Whether a scavenger wears what it finds"),
+    ("postmaster", "Whether a mobile handles mail"),
+    ("banker", "Whether a mobile keeps silver in the bank"),
+    ("repairer", "Whether a mobile mends weapons and armor"),
+    ("container", "Whether an object holds things"),
+    ("food", "Whether an object can be eaten"),
+    ("drink", "Whether an object can be drunk from"),
+    (
+        "condition",
+        "How worn a weapon or armor is, from 100 when new to 0",
+    ),
+    (
+        "decay",
+        "Game hours until an object rots or fades away, 0 for never",
+    ),
+    ("hp", "Hit points, which raises the maximum if it is more"),
+    ("mana", "Mana, which raises the maximum if it is more"),
+    ("moves", "Moves, which raises the maximum if it is more"),
+    ("poisoned", "Game hours a player or mobile stays poisoned"),
+    ("drunk", "How drunk a player or mobile is, up to 48"),
+    ("closed", "Whether a door is closed"),
+    ("locked", "Whether a door is locked"),
+    (
+        "attribute",
+        "A name=value tagged on anything, or name= to remove it",
+    ),
+];
+
+fn parse_flag(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "yes" | "on" | "true" => Ok(true),
+        "no" | "off" | "false" => Ok(false),
+        _ => Err("The value must be yes or no.".to_string()),
+    }
+}
+
+/// Attribute names are single words, so that mobprogs can name them.
+pub(crate) fn is_attribute_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_amount(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("'{}' is not a positive number.", value))
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_set(&mut self, words: &[&str]) {
+        let (target_name, field, value) = match words {
+            [target_name, field, value @ ..] if !value.is_empty() => {
+                (*target_name, field.to_lowercase(), value.join(" "))
+            }
+            _ => {
+                let mut info = self.info();
+                echo!(
+                    info,
+                    "Syntax: '`Wset <target> <field> <value>`^'. Fields:\r\n"
+                );
+                for (name, description) in FIELDS {
+                    echo!(info, "  `W{:<11}`^ {}\r\n", name, description);
+                }
+                return;
+            }
+        };
+
+        if !FIELDS.iter().any(|(name, _description)| *name == field) {
+            echo!(
+                self.info(),
+                "There is no field named like that; use '`Wset`^' to list them.\r\n"
+            );
+            return;
+        }
+
+        let target_id = match self.find_stat_target(target_name, |_entity| true) {
+            Some(target_id) => target_id,
+            None => {
+                echo!(self.info(), "There is nothing named like that.\r\n");
+                return;
+            }
+        };
+
+        let target = self.entity_world.entity_info(target_id);
+        if let Some(player) = target.player_name() {
+            if target_id != self.entity_id && !self.outranks(player) {
+                let myself = self.entity_world.entity_info(self.entity_id);
+                echo!(
+                    self.players.info(&myself),
+                    "{} is too powerful for you.\r\n",
+                    player
+                );
+                return;
+            }
+        }
+        let described = format!("{} ({})", target, target_id);
+
+        match self.set_field(target_id, &field, &value) {
+            Ok(()) => {
+                echo!(
+                    self.info(),
+                    "You set {} of {} to {}.\r\n",
+                    field,
+                    described,
+                    value
+                );
+
+                let myself = self.entity_world.entity_info(self.entity_id);
+                let message = format!("{} sets {} of {} to {}.", myself, field, described, value);
+                self.log_to_immortals(LogKind::Admin, &message);
+            }
+            Err(problem) => echo!(self.info(), "{}\r\n", problem),
+        }
+    }
+
+    fn set_field(&mut self, target_id: EntityId, field: &str, value: &str) -> Result<(), String> {
+        let target = self.entity_world.entity_info(target_id);
+        let components = target.components();
+
+        let missing = |what: &str| Err(format!("That is not {}.", what));
+
+        match field {
+            "keywords" | "short" if target.is_player() => {
+                Err("Players are known by their name.".to_string())
+            }
+            "keywords" => {
+                let (mut target, interner) =
+                    self.entity_world.entity_info_mut_with_interner(target_id);
+                target.set_keyword(interner, &value.to_lowercase());
+                Ok(())
+            }
+            "short" => {
+                let (mut target, interner) =
+                    self.entity_world.entity_info_mut_with_interner(target_id);
+                target.set_short_description(interner, value);
+                Ok(())
+            }
+            "gender" => {
+                let gender = match value.to_lowercase().as_str() {
+                    "male" => Gender::Male,
+                    "female" => Gender::Female,
+                    "neutral" => Gender::Neutral,
+                    _ => return Err("The gender must be male, female or neutral.".to_string()),
+                };
+                let mut target = self.entity_world.entity_info_mut(target_id);
+                Arc::make_mut(&mut target.components().act_info).set_gender(gender);
+                Ok(())
+            }
+            "copper" | "silver" | "gold"
+                if components.wealth.is_some() || target.is_mobile() || target.is_player() =>
+            {
+                let denomination = Denomination::from_name(field).expect("Matched above");
+                let amount = parse_amount(value)?;
+                self.set_coins_of(target_id, denomination, amount)
+            }
+            "copper" | "silver" | "gold" => missing("a pile of coins, nor can it carry any"),
+            "cost" if components.object.is_some() => {
+                let cost = value
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|cost| *cost >= 0)
+                    .ok_or_else(|| format!("'{}' is not a positive number.", value))?;
+                let mut target = self.entity_world.entity_info_mut(target_id);
+                target
+                    .components()
+                    .object
+                    .as_mut()
+                    .expect("Checked above")
+                    .cost = cost;
+                Ok(())
+            }
+            "wander" | "stay_area" | "flying" | "scavenger" | "aggressive" | "postmaster"
+            | "banker" | "repairer"
+                if components.mobile.is_some() =>
+            {
+                let flag = parse_flag(value)?;
+                let mut target = self.entity_world.entity_info_mut(target_id);
+                let mobile = target.components().mobile.as_mut().expect("Checked above");
+                match field {
+                    "wander" => mobile.wander = flag,
+                    "stay_area" => mobile.stay_area = flag,
+                    "flying" => mobile.flying = flag,
+                    "scavenger" => mobile.scavenger = flag,
+                    "aggressive" => mobile.aggressive = flag,
+                    "postmaster" => mobile.postmaster = flag,
+                    "banker" => mobile.banker = flag,
+                    _ => mobile.repairer = flag,
+                }
+                Ok(())
+            }
+            "hp" | "mana" | "moves" if target.is_mobile() || target.is_player() => {
+                let amount = value
+                    .parse::<u32>()
+                    .map_err(|_| format!("'{}' is not a positive number.", value))?;
+                let mut target = self.entity_world.entity_info_mut(target_id);
+                match &mut target.components().vitals {
+                    Some(vitals) => {
+                        let (current, max) = match field {
+                            "hp" => (&mut vitals.hit, &mut vitals.max_hit),
+                            "mana" => (&mut vitals.mana, &mut vitals.max_mana),
+                            _ => (&mut vitals.moves, &mut vitals.max_moves),
+                        };
+                        *current = amount;
+                        *max = (*max).max(amount);
+                        Ok(())
+                    }
+                    None => Err("It has no vitals yet; try again after the next tick.".to_string()),
+                }
+            }
+            "poisoned" if target.is_mobile() || target.is_player() => {
+                let hours = value
+                    .parse::<u16>()
+                    .map_err(|_| format!("'{}' is not a number of hours.", value))?;
+                let mut target = self.entity_world.entity_info_mut(target_id);
+                match &mut target.components().vitals {
+                    Some(vitals) => {
+                        vitals.poisoned = hours;
+                        Ok(())
+                    }
+                    None => Err("It has no vitals yet; try again after the next tick.".to_string()),
+                }
+            }
+            "drunk" if target.is_mobile() || target.is_player() => {
+                let drunk = value
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|drunk| *drunk <= 48)
+                    .ok_or_else(|| format!("'{}' is not a number from 0 to 48.", value))?;
+                let mut target = self.entity_world.entity_info_mut(target_id);
+                match &mut target.components().vitals {
+                    Some(vitals) => {
+                        vitals.drunk = drunk;
+                        Ok(())
+                    }
+                    None => Err("It has no vitals yet; try again after the next tick.".to_string()),
+                }
+            }
+            "container" | "food" | "drink" if components.object.is_some() => {
+                let flag = parse_flag(value)?;
+                let mut target = self.entity_world.entity_info_mut(target_id);
+                let object = target.components().object.as_mut().expect("Checked above");
+                match field {
+                    "container" => object.container = flag,
+                    "food" => object.food = flag,
+                    _ => object.drink = flag,
+                }
+                Ok(())
+            }
+            "condition" if components.object.is_some() => {
+                let condition = value
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|condition| *condition <= NEW_CONDITION)
+                    .ok_or_else(|| format!("'{}' is not a number from 0 to 100.", value))?;
+                let mut target = self.entity_world.entity_info_mut(target_id);
+                target
+                    .components()
+                    .object
+                    .as_mut()
+                    .expect("Checked above")
+                    .condition = Some(condition);
+                Ok(())
+            }
+            "decay" if target.is_object() => {
+                let hours = value
+                    .parse::<u16>()
+                    .map_err(|_| format!("'{}' is not a number of hours.", value))?;
+                let decay = match (&components.decay, hours) {
+                    (_, 0) => None,
+                    (Some(decay), hours) => Some(Decay {
+                        kind: decay.kind,
+                        ticks: hours,
+                    }),
+                    (None, hours) => Some(Decay::for_object(&target, hours)),
+                };
+                let mut target = self.entity_world.entity_info_mut(target_id);
+                target.components().decay = decay;
+                Ok(())
+            }
+            "closed" | "locked" if components.door.is_some() => {
+                let flag = parse_flag(value)?;
+                let mut target = self.entity_world.entity_info_mut(target_id);
+                let door = target.components().door.as_mut().expect("Checked above");
+                match field {
+                    "closed" => door.closed = flag,
+                    _ => door.locked = flag,
+                }
+                // Doors can't be locked while open
+                if door.locked {
+                    door.closed = true;
+                }
+                Ok(())
+            }
+            "attribute" => {
+                let (name, value) = value
+                    .split_once('=')
+                    .filter(|(name, _value)| is_attribute_name(name))
+                    .ok_or("The value must be name=value, with a name of letters, digits and _.")?;
+                let value = Some(value.trim())
+                    .filter(|value| !value.is_empty())
+                    .map(AttrValue::parse);
+                let (mut target, interner) =
+                    self.entity_world.entity_info_mut_with_interner(target_id);
+                target.set_attribute(interner, name, value);
+                Ok(())
+            }
+            "cost" | "container" | "food" | "drink" | "condition" | "decay" => missing("an object"),
+            "hp" | "mana" | "moves" | "poisoned" | "drunk" => missing("a player or a mobile"),
+            "wander" | "stay_area" | "flying" | "scavenger" | "aggressive" | "postmaster"
+            | "banker" | "repairer" => missing("a mobile"),
+            "closed" | "locked" => missing("a door"),
+            _ => unreachable!("Fields are checked before setting them"),
+        }
+    }
+
+    /// Change how many coins of a denomination are in a pile, or carried by
+    /// a mobile or player, whose coins then end up in a single pile.
+    fn set_coins_of(
+        &mut self,
+        target_id: EntityId,
+        denomination: Denomination,
+        amount: usize,
+    ) -> Result<(), String> {
+        let target = self.entity_world.entity_info(target_id);
+
+        if let Some(wealth) = &target.components().wealth {
+            let mut wealth = wealth.clone();
+            *wealth.coins_mut(denomination) = amount;
+            if wealth.is_empty() {
+                return Err("Use '`Wpurge`^' to get rid of a pile of coins.".to_string());
+            }
+            self.entity_world.set_coins(target_id, wealth);
+            return Ok(());
+        }
+
+        let piles: Vec<(EntityId, Wealth)> = target
+            .objects()
+            .filter_map(|object| {
+                let wealth = object.components().wealth.clone()?;
+                Some((object.entity_id(), wealth))
+            })
+            .collect();
+
+        let mut wealth = Wealth::default();
+        for (pile_id, pile) in piles {
+            wealth.add(&pile);
+            self.switch_agent(pile_id).do_die();
+        }
+
+        *wealth.coins_mut(denomination) = amount;
+        if !wealth.is_empty() {
+            self.entity_world.add_coins(target_id, &wealth);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,477 @@
+//! Shopkeepers, which are mobiles with a `Shop` from their area's `#SHOPS`
+//! section: `list` shows their wares, `buy` and `sell` trade with them, and
+//! `value` asks what they would pay for something.
+//!
+//! A shopkeeper's base stock is the objects it was given by area resets; it
+//! never runs out of them, since bought objects are new copies of their
+//! templates. Shops only buy objects whose item type is among their buy types.
+//! What players sell to them is kept and sold again, but only once, and the
+//! oldest of it is thrown away to make room; if a shopkeeper already has the
+//! same thing in its base stock, it is thrown away right away. All of this
+//! stays with the shopkeeper, even through death and restores.
+//!
+//! Prices are an object's cost, scaled by the shop's profit percentages, and
+//! shopkeepers only trade between their opening and closing hours. They give
+//! better deals to customers with a high `charisma` or `level` attribute, and
+//! to those in good standing with their faction: a shopkeeper's `faction`
+//! attribute names the attribute of its customers that holds their standing,
+//! from -100 to 100. Before each deal, a player can `haggle` once, which
+//! makes it better or, if the shopkeeper takes offense, worse. Shopkeepers
+//! never pay more for something than they would sell it for.
+//!
+//! In a pet shop, `list` and `buy` deal in pets instead; see `crate::pets`.
+//! At a stall, `buy` buys from the player renting it; see `crate::stalls`.
+
+use crate::{
+    agent::EntityAgent,
+    clock::is_open,
+    components::AttrValue,
+    echo,
+    entity::{EntityId, EntityInfo},
+    find_entities::{EntityIterator, MatchError},
+    world::{Shop, World},
+};
+
+/// What an object costs, scaled by a shop's profit percentage.
+fn price(cost: i32, profit: u32) -> usize {
+    cost.max(0) as usize * profit as usize / 100
+}
+
+/// Charisma of customers without a `charisma` attribute.
+const AVERAGE_CHARISMA: i64 = 13;
+
+/// How many percent better than usual a shopkeeper's deals are for a
+/// customer, or worse if negative.
+fn deal(customer: &EntityInfo, shopkeeper: &EntityInfo) -> i64 {
+    let attributes = customer.component_info();
+    let number = |name: &str| attributes.attribute(name).map(AttrValue::number);
+
+    let charisma = number("charisma").unwrap_or(AVERAGE_CHARISMA).clamp(3, 25) - AVERAGE_CHARISMA;
+    let level = number("level").unwrap_or(0).clamp(0, 100) / 10;
+    let standing = match shopkeeper.component_info().attribute("faction") {
+        Some(AttrValue::Text(faction)) => number(faction).unwrap_or(0).clamp(-100, 100) / 10,
+        _ => 0,
+    };
+    let haggled = match (customer.player_name(), &shopkeeper.components().mobile) {
+        (Some(player), Some(mobile)) => mobile
+            .haggled
+            .iter()
+            .find(|(name, _)| name == player)
+            .map_or(0, |(_, better)| *better),
+        _ => 0,
+    };
+
+    (charisma + level + standing + haggled).clamp(-50, 50)
+}
+
+/// What a shopkeeper sells an object for.
+fn selling_price(cost: i32, shop: &Shop, deal: i64) -> usize {
+    price(cost, shop.profit_buy) * (100 - deal) as usize / 100
+}
+
+/// What a shopkeeper pays for an object, which is never more than what it
+/// would sell it for.
+fn buying_price(cost: i32, shop: &Shop, deal: i64) -> usize {
+    let paid = price(cost, shop.profit_sell) * (100 + deal) as usize / 100;
+    paid.min(selling_price(cost, shop, deal))
+}
+
+/// Objects a shopkeeper sells; what it wears and the silver it carries are
+/// not for sale.
+fn is_ware(object: &EntityInfo) -> bool {
+    let components = object.components();
+    components.object.is_some()
+        && components.wealth.is_none()
+        && components.general.equipped.is_none()
+}
+
+/// Objects bought from players; a shopkeeper keeps only this many of them.
+const MAX_RESOLD: usize = 10;
+
+/// Whether a ware was bought from a player, rather than being base stock.
+fn is_resold(object: &EntityInfo) -> bool {
+    object
+        .components()
+        .object
+        .as_ref()
+        .map(|object| object.resold)
+        .unwrap_or(false)
+}
+
+/// Whether a shop buys an object, by the item type of its template.
+fn shop_buys(shop: &Shop, world: &World, object: &EntityInfo) -> bool {
+    let vnum = object.components().general.vnum.0;
+    let item_type = match world.objects.get(vnum) {
+        Some(template) if vnum != 0 && template.vnum.0 == vnum => template.item_type.as_str(),
+        _ => return false,
+    };
+
+    shop.buy_types
+        .iter()
+        .any(|buy_type| buy_type.trim().eq_ignore_ascii_case(item_type))
+}
+
+/// Find a shopkeeper in the room that is open for business.
+fn find_shopkeeper<'e>(
+    myself: &EntityInfo<'e>,
+    hour: u8,
+) -> Result<(EntityInfo<'e>, &'e Shop), MatchError> {
+    let (shopkeeper, shop) = myself
+        .room()
+        .contained_entities()
+        .with_component::<Shop>()
+        .filter_or(|e| e != myself, "The only shopkeeper here is yourself.")
+        .find_one_with_component_or("You don't see any shopkeepers here.")?;
+
+    if !is_open(hour, shop.open_hour, shop.close_hour) {
+        return Err(MatchError::MessageWithActor(
+            "$^$N says, 'Sorry, we're closed. Come back later.'",
+            shopkeeper.entity_id(),
+        ));
+    }
+
+    Ok((shopkeeper, shop))
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_list(&mut self) {
+        if let Some(stock_room_id) = self.pet_stock_room() {
+            return self.do_list_pets(stock_room_id);
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let (shopkeeper, shop) = match find_shopkeeper(&myself, self.clock.hour()) {
+            Ok(found) => found,
+            Err(error) => return self.echo_error(error),
+        };
+
+        let mut act = self.players.act_with(&myself, &shopkeeper);
+        echo!(
+            act.target(),
+            "$^$n asks you about your wares, and you show $m what you have.\r\n"
+        );
+        echo!(act.others(), "$^$n asks $N about $S wares.\r\n");
+
+        if !shopkeeper.objects().any(|object| is_ware(&object)) {
+            echo!(act.myself(), "$^$N has nothing to sell right now.\r\n");
+            return;
+        }
+
+        echo!(act.myself(), "$^$N shows you $S wares:\r\n");
+
+        let deal = deal(&myself, &shopkeeper);
+        let mut info = self.players.info(&myself);
+        for item in shopkeeper.objects().filter(is_ware) {
+            let object = item
+                .components()
+                .object
+                .as_ref()
+                .expect("Checked by is_ware");
+            echo!(
+                info,
+                "  {}: `W{}`^ silver coins{}\r\n",
+                item.component_info().short_description(),
+                selling_price(object.cost, shop, deal),
+                if object.resold {
+                    " `D(secondhand)`^"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+
+    pub fn do_buy(&mut self, item_name: &str) {
+        if self.pet_stock_room().is_some() {
+            return self.do_buy_pet(item_name, None);
+        }
+        if let Some(vnum) = self.stall_vnum() {
+            return self.do_buy_from_stall(vnum, item_name);
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let (shopkeeper, shop) = match find_shopkeeper(&myself, self.clock.hour()) {
+            Ok(found) => found,
+            Err(error) => return self.echo_error(error),
+        };
+
+        let found = shopkeeper
+            .objects()
+            .filter(is_ware)
+            .filter_by_keyword(item_name)
+            .find_one_or("You don't see anything named like that to buy.");
+
+        let item = match found {
+            Ok(item) => item,
+            Err(error) => return self.echo_error(error),
+        };
+
+        let shopkeeper_id = shopkeeper.entity_id();
+        let item_id = item.entity_id();
+        let vnum = item.components().general.vnum.0;
+        let object = item
+            .components()
+            .object
+            .as_ref()
+            .expect("Checked by is_ware");
+        let cost = selling_price(object.cost, shop, deal(&myself, &shopkeeper));
+        let resold = object.resold;
+
+        if !self.remove_silver(cost, self.entity_id) {
+            echo!(
+                self.info(),
+                "You don't have the {} silver to pay for it!\r\n",
+                cost
+            );
+            return;
+        }
+        self.end_haggle(shopkeeper_id);
+
+        let has_template = self.entity_world.templates.has_object(vnum);
+
+        // Base stock is a new copy, so that the shopkeeper can keep selling it
+        let bought_id = if resold {
+            self.entity_world.move_entity(item_id, self.entity_id);
+            let mut item = self.entity_world.entity_info_mut(item_id);
+            if let Some(object) = &mut item.components().object {
+                object.resold = false;
+            }
+            item_id
+        } else if vnum != 0 && has_template {
+            self.entity_world
+                .spawn_object(vnum, self.entity_id)
+                .expect("Checked above")
+        } else {
+            self.entity_world
+                .clone_entity_recursive(item_id, self.entity_id)
+        };
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let shopkeeper = self.entity_world.entity_info(shopkeeper_id);
+        let item = self.entity_world.entity_info(bought_id);
+        let mut act = self.players.act_with(&myself, &shopkeeper);
+        echo!(
+            act.myself(),
+            "You buy {} from $N for {} silver.\r\n",
+            item,
+            cost
+        );
+        echo!(
+            act.target(),
+            "$^$n buys {} from you for {} silver.\r\n",
+            item,
+            cost
+        );
+        echo!(act.others(), "$^$n buys {} from $N.\r\n", item);
+    }
+
+    /// Find what the current entity could sell to a shopkeeper in the room,
+    /// and what it would get for it.
+    fn find_sale(&self, item_name: &str) -> Result<(EntityId, EntityId, usize), MatchError> {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let (shopkeeper, shop) = find_shopkeeper(&myself, self.clock.hour())?;
+
+        let world = &*self.world;
+        let found = myself
+            .objects()
+            .filter_by_keyword(item_name)
+            .filter_or(
+                |e| e.components().wealth.is_none(),
+                "Shopkeepers trade for silver, not in it.",
+            )
+            .filter_or(
+                |e| e.components().general.equipped.is_none(),
+                "You need to remove $N first.",
+            )
+            .filter_or(
+                |e| e.objects().next().is_none(),
+                "You need to empty $N first.",
+            )
+            .filter_or(
+                |e| shop_buys(shop, world, e),
+                "The shopkeeper doesn't buy things like $N.",
+            )
+            .find_one_or("You don't own anything named like that to sell.")?;
+
+        let cost = match &found.components().object {
+            Some(object) => buying_price(object.cost, shop, deal(&myself, &shopkeeper)),
+            None => 0,
+        };
+
+        Ok((shopkeeper.entity_id(), found.entity_id(), cost))
+    }
+
+    pub fn do_sell(&mut self, item_name: &str) {
+        let (shopkeeper_id, item_id, cost) = match self.find_sale(item_name) {
+            Ok(found) => found,
+            Err(error) => return self.echo_error(error),
+        };
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let shopkeeper = self.entity_world.entity_info(shopkeeper_id);
+        let item = self.entity_world.entity_info(item_id);
+        let mut act = self.players.act_with(&myself, &shopkeeper);
+        echo!(
+            act.myself(),
+            "You sell {} to $N for {} silver.\r\n",
+            item,
+            cost
+        );
+        echo!(
+            act.target(),
+            "$^$n sells {} to you for {} silver.\r\n",
+            item,
+            cost
+        );
+        echo!(act.others(), "$^$n sells {} to $N.\r\n", item);
+
+        self.add_silver(cost, self.entity_id);
+        self.end_haggle(shopkeeper_id);
+        self.stock_resold(shopkeeper_id, item_id);
+    }
+
+    pub fn do_haggle(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can haggle.\r\n");
+                return;
+            }
+        };
+
+        let (shopkeeper, _shop) = match find_shopkeeper(&myself, self.clock.hour()) {
+            Ok(found) => found,
+            Err(error) => return self.echo_error(error),
+        };
+
+        let mobile = shopkeeper
+            .components()
+            .mobile
+            .as_ref()
+            .expect("Shopkeepers are mobiles");
+        if mobile.haggled.iter().any(|(name, _)| *name == player) {
+            let mut act = self.players.act_with(&myself, &shopkeeper);
+            echo!(
+                act.myself(),
+                "$^$N says, 'We've haggled enough. Make your deal.'\r\n"
+            );
+            return;
+        }
+
+        // Charming customers, and those the shopkeeper likes, haggle better
+        let chance = (50 + deal(&myself, &shopkeeper) * 2).clamp(5, 95) as u8;
+        let better = if self.rng.percent(chance) {
+            self.rng.range(5..=15)
+        } else if self.rng.coin() {
+            0
+        } else {
+            -self.rng.range(1..=5)
+        };
+
+        let mut act = self.players.act_with(&myself, &shopkeeper);
+        echo!(act.others(), "$^$n haggles with $N.\r\n");
+        echo!(act.target(), "$^$n haggles with you.\r\n");
+        if better > 0 {
+            echo!(
+                act.myself(),
+                "You haggle with $N, who grudgingly gives you {}% better terms on your next deal.\r\n",
+                better
+            );
+        } else if better == 0 {
+            echo!(
+                act.myself(),
+                "You haggle with $N, but $E won't budge on $S prices.\r\n"
+            );
+        } else {
+            echo!(
+                act.myself(),
+                "You haggle with $N, who takes offense and gives you {}% worse terms on your next deal.\r\n",
+                -better
+            );
+        }
+
+        let shopkeeper_id = shopkeeper.entity_id();
+        let mut shopkeeper = self.entity_world.entity_info_mut(shopkeeper_id);
+        if let Some(mobile) = &mut shopkeeper.components().mobile {
+            mobile.haggled.push((player, better));
+        }
+    }
+
+    /// Forget how the current entity haggled with a shopkeeper, once it made
+    /// its deal.
+    fn end_haggle(&mut self, shopkeeper_id: EntityId) {
+        let player = match self.entity_world.entity_info(self.entity_id).player_name() {
+            Some(player) => player.to_string(),
+            None => return,
+        };
+
+        let mut shopkeeper = self.entity_world.entity_info_mut(shopkeeper_id);
+        if let Some(mobile) = &mut shopkeeper.components().mobile {
+            mobile.haggled.retain(|(name, _)| *name != player);
+        }
+    }
+
+    /// Put an object bought from a player up for sale again, unless the
+    /// shopkeeper has plenty of them already.
+    fn stock_resold(&mut self, shopkeeper_id: EntityId, item_id: EntityId) {
+        let shopkeeper = self.entity_world.entity_info(shopkeeper_id);
+        let vnum = self
+            .entity_world
+            .entity_info(item_id)
+            .components()
+            .general
+            .vnum;
+
+        let in_base_stock = shopkeeper
+            .objects()
+            .filter(is_ware)
+            .any(|ware| !is_resold(&ware) && ware.components().general.vnum == vnum);
+
+        if in_base_stock {
+            self.switch_agent(item_id).do_die();
+            return;
+        }
+
+        self.entity_world.move_entity(item_id, shopkeeper_id);
+        let mut item = self.entity_world.entity_info_mut(item_id);
+        if let Some(object) = &mut item.components().object {
+            object.resold = true;
+        }
+
+        // Wares are in the order they came in, so the oldest go first
+        let resold: Vec<EntityId> = self
+            .entity_world
+            .entity_info(shopkeeper_id)
+            .objects()
+            .filter(is_resold)
+            .map(|ware| ware.entity_id())
+            .collect();
+
+        for old_item_id in resold.iter().take(resold.len().saturating_sub(MAX_RESOLD)) {
+            self.switch_agent(*old_item_id).do_die();
+        }
+    }
+
+    pub fn do_value(&mut self, item_name: &str) {
+        let (shopkeeper_id, item_id, cost) = match self.find_sale(item_name) {
+            Ok(found) => found,
+            Err(error) => return self.echo_error(error),
+        };
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let shopkeeper = self.entity_world.entity_info(shopkeeper_id);
+        let item = self.entity_world.entity_info(item_id);
+        let mut act = self.players.act_with(&myself, &shopkeeper);
+        echo!(
+            act.myself(),
+            "$^$N would give you {} silver for {}.\r\n",
+            cost,
+            item
+        );
+        echo!(act.target(), "$^$n asks you what {} is worth.\r\n", item);
+        echo!(act.others(), "$^$n asks $N what {} is worth.\r\n", item);
+    }
+}
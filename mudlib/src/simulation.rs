@@ -0,0 +1,289 @@
+//! Running the game without a server, to test what happens in it from end to
+//! end.
+//!
+//! A `Simulation` loads a world from files kept in memory (`MemoryFiles`),
+//! from a seed so that it plays out the same way every time (see the `rng`
+//! module). Players log in without a connection, type commands, and time
+//! passes pulse by pulse. Then what players were told, where they and
+//! others are, and what they carry can be checked, e.g. to test a quest
+//! from start to finish:
+//!
+//! ```no_run
+//! use mudlib::simulation::{MemoryFiles, Simulation};
+//!
+//! let mut simulation = Simulation::new(MemoryFiles::basic(), 1);
+//! simulation.login("Tester");
+//! simulation.command("Tester", "recall mudschool");
+//! assert!(simulation.command("Tester", "say hello").contains("You say"));
+//! simulation.advance_ticks(2);
+//! assert!(simulation.room_of("Tester").is_some());
+//! ```
+//!
+//! Anything saved goes into the `MemoryFiles`, where it can be looked at
+//! too. `snapshot()` and `restore()` go back to a known point, e.g. to try
+//! several ways through a quest from the same place.
+
+use std::{cell::RefCell, collections::BTreeMap};
+
+use crate::{
+    components::EntityType,
+    decolorize,
+    entity::{EntityInfo, EntitySnapshot},
+    snapshots::give_players_bodies,
+    tick::PULSE_TICK,
+    Files, WorldState,
+};
+
+/// Files held in memory, which the game can read and write like the real
+/// ones. Files that were never added are not found, like saved data that
+/// doesn't exist yet.
+#[derive(Default)]
+pub struct MemoryFiles {
+    files: RefCell<BTreeMap<String, Vec<u8>>>,
+}
+
+impl MemoryFiles {
+    /// No files at all; add an area list, areas and socials before loading
+    /// a world from them.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The basic area and socials, as in the CLI.
+    pub fn basic() -> Self {
+        let mut files = Self::new();
+        files
+            .insert(
+                "data/socials.txt",
+                include_bytes!("../../data/basic_socials.txt").to_vec(),
+            )
+            .insert("data/area/arealist.txt", b"basic.are".to_vec())
+            .insert(
+                "data/area/basic.are",
+                include_bytes!("../../data/basic_area.txt").to_vec(),
+            );
+        files
+    }
+
+    /// Add a file, or replace it.
+    pub fn insert(&mut self, path: &str, contents: impl Into<Vec<u8>>) -> &mut Self {
+        self.files
+            .get_mut()
+            .insert(path.to_string(), contents.into());
+        self
+    }
+
+    /// The contents of a file, if there is one.
+    pub fn get(&self, path: &str) -> Option<Vec<u8>> {
+        self.files.borrow().get(path).cloned()
+    }
+}
+
+impl Files for MemoryFiles {
+    fn read_file_raw(&self, path: &str) -> Result<Vec<u8>, std::io::Error> {
+        self.get(path)
+            .ok_or_else(|| std::io::ErrorKind::NotFound.into())
+    }
+
+    fn write_file_raw(&self, path: &str, contents: &[u8]) -> Result<(), std::io::Error> {
+        self.files
+            .borrow_mut()
+            .insert(path.to_string(), contents.to_vec());
+        Ok(())
+    }
+
+    fn append_file_raw(&self, path: &str, contents: &[u8]) -> Result<(), std::io::Error> {
+        self.files
+            .borrow_mut()
+            .entry(path.to_string())
+            .or_default()
+            .extend_from_slice(contents);
+        Ok(())
+    }
+
+    fn list_directory(&self, path: &str) -> Result<Vec<String>, std::io::Error> {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        Ok(self
+            .files
+            .borrow()
+            .keys()
+            .filter_map(|file| file.strip_prefix(&prefix))
+            .filter(|name| !name.contains('/'))
+            .map(|name| name.to_string())
+            .collect())
+    }
+
+    fn remove_file(&self, path: &str) -> Result<(), std::io::Error> {
+        match self.files.borrow_mut().remove(path) {
+            Some(_contents) => Ok(()),
+            None => Err(std::io::ErrorKind::NotFound.into()),
+        }
+    }
+}
+
+/// The entity world of a simulation at some point; see `Simulation::snapshot()`.
+#[derive(Clone)]
+pub struct WorldSnapshot(EntitySnapshot);
+
+/// A game running on its own, with players that only exist in it.
+pub struct Simulation {
+    world: WorldState,
+    files: MemoryFiles,
+}
+
+impl Simulation {
+    /// Load the world from files, rolling everything random from a seed.
+    pub fn new(files: MemoryFiles, seed: u64) -> Self {
+        let world = WorldState::with_seed(&files, seed);
+        Simulation { world, files }
+    }
+
+    /// The game itself, for anything else there is to do or check.
+    pub fn world(&mut self) -> &mut WorldState {
+        &mut self.world
+    }
+
+    /// The files the world was loaded from, and anything saved since.
+    pub fn files(&self) -> &MemoryFiles {
+        &self.files
+    }
+
+    /// Bring a player into the world, as if they logged in, and return what
+    /// they saw when they did.
+    pub fn login(&mut self, player: &str) -> String {
+        self.world.add_player(player);
+        self.output(player)
+    }
+
+    /// Have a player type a command, and return what they saw then, along
+    /// with anything else they were told since the last time.
+    pub fn command(&mut self, player: &str, line: &str) -> String {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        self.world.process_player_command(player, &words);
+        self.output(player)
+    }
+
+    /// Take what a player was told since the last time, without colors.
+    pub fn output(&mut self, player: &str) -> String {
+        match self.world.player_echoes(player) {
+            Some(echoes) => {
+                let output = decolorize(echoes).into_owned();
+                echoes.clear();
+                output
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Let some pulses (seconds) of game time go by.
+    pub fn advance(&mut self, pulses: u32) {
+        for _ in 0..pulses {
+            self.world.update_world();
+        }
+    }
+
+    /// Let some ticks (game hours) go by.
+    pub fn advance_ticks(&mut self, ticks: u32) {
+        self.advance(ticks * PULSE_TICK);
+    }
+
+    /// Save whatever changed into the files, as the server does every now
+    /// and then, and answer `history` lookups from them.
+    pub fn save(&mut self) -> Result<(), std::io::Error> {
+        self.world.save_changes(&self.files)?;
+        self.world.run_history_lookups(&self.files);
+        Ok(())
+    }
+
+    /// Copy the entity world as it is now, to go back to it with `restore()`.
+    pub fn snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot(self.world.entity_world.snapshot())
+    }
+
+    /// Go back to a snapshot: everything in the world is where it was then,
+    /// and players who logged in since are given a new body. Like
+    /// `snapshot restore`, accounts, the clock and random numbers go on.
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) {
+        self.world.entity_world.restore(&snapshot.0);
+        give_players_bodies(&mut self.world);
+    }
+
+    fn player(&self, player: &str) -> Option<EntityInfo<'_>> {
+        let entity_world = &self.world.entity_world;
+        entity_world
+            .player_entity_id(player)
+            .map(|player_id| entity_world.entity_info(player_id))
+    }
+
+    fn room(&self, vnum: usize) -> Option<EntityInfo<'_>> {
+        self.world.entity_world.all_entities().find(|entity| {
+            let general = &entity.components().general;
+            general.entity_type == EntityType::Room && general.vnum.0 == vnum
+        })
+    }
+
+    /// The vnum of the room a player is in.
+    pub fn room_of(&self, player: &str) -> Option<usize> {
+        self.player(player)
+            .map(|player| player.room().components().general.vnum.0)
+    }
+
+    /// The short descriptions of the mobiles and players in a room.
+    pub fn occupants(&self, vnum: usize) -> Vec<String> {
+        self.room(vnum)
+            .map(|room| {
+                room.contained_entities()
+                    .filter(|entity| entity.is_mobile() || entity.is_player())
+                    .map(|entity| short_description(&entity))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The short descriptions of the objects lying in a room.
+    pub fn room_objects(&self, vnum: usize) -> Vec<String> {
+        self.room(vnum)
+            .map(|room| {
+                room.objects()
+                    .map(|object| short_description(&object))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The short descriptions of what a player carries, worn or not.
+    pub fn inventory(&self, player: &str) -> Vec<String> {
+        self.player(player)
+            .map(|player| {
+                player
+                    .objects()
+                    .map(|object| short_description(&object))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// What the coins a player carries are worth in silver.
+    pub fn silver(&self, player: &str) -> usize {
+        self.player(player)
+            .map(|player| {
+                player
+                    .objects()
+                    .filter_map(|object| object.components().wealth.as_ref())
+                    .map(|wealth| wealth.silver_value())
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+
+    /// A player's hit points, mana and moves.
+    pub fn vitals(&self, player: &str) -> Option<(u32, u32, u32)> {
+        let player = self.player(player)?;
+        let vitals = player.components().vitals.as_ref()?;
+        Some((vitals.hit, vitals.mana, vitals.moves))
+    }
+}
+
+fn short_description(entity: &EntityInfo) -> String {
+    entity.component_info().short_description().to_string()
+}
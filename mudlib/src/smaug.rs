@@ -0,0 +1,768 @@
+//! SMAUG area loader.
+//!
+//! SMAUG areas look like Merc areas, with the name on the `#AREA` line, but
+//! add sections of their own like `#AUTHOR`, `#RANGES`, `#FLAGS` and
+//! `#ECONOMY`, which is how they are told apart. Their flags are numbers,
+//! or several numbers joined by '&' for the extended flags of later
+//! versions, each holding the next 32 bits.
+//!
+//! Like ROM areas (see `crate::rom`), SMAUG areas are read into the types of
+//! `crate::world`: flags the game knows about are turned into words or
+//! properties, and the rest is read and skipped, as are mudprogs and
+//! specials; repair shops make their mobiles repairers, and helps are kept,
+//! as in ROM areas. SMAUG has many more room flags than the game
+//! has a use for; they are kept as words, like "dark" or "safe", so saving
+//! the area doesn't lose them. Exits keep whether their doors start closed
+//! or locked in their flags, and door resets can change that.
+//!
+//! An area edited with the OLC is saved as a Dawn of Time area.
+
+use std::convert::TryFrom;
+
+use crate::{
+    file_parser::{FileParser, ParseResult},
+    load::{read_number, read_vnum_section, remember_area, skip_specials, DEFAULT_RESET_INTERVAL},
+    rng::GameRng,
+    rom::{
+        apply_door_resets, load_helps, load_shops, short_name, to_vnum, CONT_CLOSEABLE,
+        CONT_CLOSED, CONT_LOCKED, DIRECTIONS, WEAR_FLAGS, WEAR_LOCATIONS,
+    },
+    world::{
+        Area, AreaData, AreaFormat, Exit, ExtraDescription, Gender, Mobile, Object, ObjectFlags,
+        ResetCommand, Room, Vnum,
+    },
+};
+
+/// Sections only SMAUG areas have
+const SMAUG_SECTIONS: [&str; 9] = [
+    "#AUTHOR",
+    "#RANGES",
+    "#RESETMSG",
+    "#FLAGS",
+    "#ECONOMY",
+    "#CLIMATE",
+    "#NEIGHBOR",
+    "#VERSION",
+    "#REPAIRS",
+];
+
+/// Sectors of rooms, by their number
+const SECTORS: [&str; 16] = [
+    "inside",
+    "city",
+    "field",
+    "forest",
+    "hills",
+    "mountain",
+    "swim",
+    "noswim",
+    "underwater",
+    "air",
+    "desert",
+    "inside",
+    "underwater",
+    "cave",
+    "lava",
+    "swamp",
+];
+
+/// Room flags the game has no use for, by their bit; those it knows about
+/// and unused bits are empty
+const ROOM_FLAGS: [&str; 32] = [
+    "dark",
+    "death",
+    "",
+    "indoors",
+    "lawful",
+    "neutral",
+    "chaotic",
+    "no_magic",
+    "tunnel",
+    "private",
+    "safe",
+    "solitary",
+    "",
+    "",
+    "donation",
+    "no_drop_all",
+    "silence",
+    "log_speech",
+    "no_drop",
+    "clan_storeroom",
+    "no_summon",
+    "no_astral",
+    "teleport",
+    "tele_show_desc",
+    "no_floor",
+    "no_supplicate",
+    "arena",
+    "no_missile",
+    "",
+    "",
+    "prototype",
+    "dnd",
+];
+
+/// Item types, by their number; 0 is unused
+const ITEM_TYPES: [&str; 64] = [
+    "trash",
+    "light",
+    "scroll",
+    "wand",
+    "staff",
+    "weapon",
+    "fireweapon",
+    "missile",
+    "treasure",
+    "armor",
+    "potion",
+    "clothing",
+    "furniture",
+    "trash",
+    "trap",
+    "container",
+    "note",
+    "drink_con",
+    "key",
+    "food",
+    "money",
+    "pen",
+    "boat",
+    "npc_corpse",
+    "pc_corpse",
+    "fountain",
+    "pill",
+    "blood",
+    "bloodstain",
+    "scraps",
+    "pipe",
+    "herb_con",
+    "herb",
+    "incense",
+    "fire",
+    "book",
+    "switch",
+    "lever",
+    "pullchain",
+    "button",
+    "dial",
+    "rune",
+    "rune_pouch",
+    "match",
+    "trap",
+    "map",
+    "portal",
+    "paper",
+    "tinder",
+    "lockpick",
+    "spike",
+    "disease",
+    "oil",
+    "fuel",
+    "trash",
+    "trash",
+    "missile_weapon",
+    "projectile",
+    "quiver",
+    "shovel",
+    "salve",
+    "cook",
+    "keyring",
+    "odor",
+];
+
+/// Liquids of drink containers and fountains, by their number
+const LIQUIDS: [&str; 16] = [
+    "water",
+    "beer",
+    "wine",
+    "ale",
+    "dark ale",
+    "whisky",
+    "lemonade",
+    "firebreather",
+    "local specialty",
+    "slime mold juice",
+    "milk",
+    "tea",
+    "coffee",
+    "blood",
+    "salt water",
+    "cola",
+];
+
+// Flags of mobiles, rooms and exits that the game knows about
+const ACT_SENTINEL: u64 = 1 << 1;
+const ACT_SCAVENGER: u64 = 1 << 2;
+const ACT_AGGRESSIVE: u64 = 1 << 5;
+const ACT_STAY_AREA: u64 = 1 << 6;
+const AFF_FLYING: u64 = 1 << 19;
+const ROOM_NO_MOB: u64 = 1 << 2;
+const ROOM_PET_SHOP: u64 = 1 << 12;
+const ROOM_NO_RECALL: u64 = 1 << 13;
+const EX_ISDOOR: u64 = 1;
+const EX_CLOSED: u64 = 2;
+const EX_LOCKED: u64 = 4;
+
+/// Whether an area file is a SMAUG area, from the sections it has.
+pub(super) fn is_smaug_area(area_file_contents: &str) -> bool {
+    area_file_contents.lines().any(|line| {
+        let section = line.split_whitespace().next().unwrap_or_default();
+        SMAUG_SECTIONS.contains(&section)
+    })
+}
+
+/// Parse flags like "1024", or extended flags like "1024&8", where each
+/// number after the first holds the next 32 bits.
+fn parse_flags(parser: &FileParser, word: &str) -> ParseResult<u64> {
+    let mut flags = 0;
+
+    for (index, part) in word.split('&').take(2).enumerate() {
+        let number: i64 = parser.parse(part, "flags")?;
+        flags |= (number as u64 & 0xffff_ffff) << (32 * index);
+    }
+
+    Ok(flags)
+}
+
+/// The name of an item type, from its number.
+fn item_type(parser: &FileParser, word: &str) -> ParseResult<String> {
+    let item_type: usize = parser.parse(word, "item type")?;
+    ITEM_TYPES
+        .get(item_type)
+        .map(|name| name.to_string())
+        .ok_or_else(|| parser.error(&format!("Unknown item type: {}", item_type)))
+}
+
+/// Read the next line that isn't blank.
+fn read_line<'a>(parser: &mut FileParser<'a>) -> ParseResult<&'a str> {
+    parser.skip_all_space();
+    parser.read_until_newline()
+}
+
+/// Mudprogs, each starting with a '>', up to a line with a '|'.
+fn skip_mudprogs(parser: &mut FileParser) -> ParseResult<()> {
+    if parser.peek_char() == Some('>') {
+        while !read_line(parser)?.trim_start().starts_with('|') {}
+    }
+    Ok(())
+}
+
+/// Parse a SMAUG area file.
+pub(super) fn load_smaug_area(
+    area_file_contents: &str,
+    file_name: &str,
+    rng: &mut GameRng,
+) -> ParseResult<Area> {
+    let mut parser = FileParser::new(area_file_contents, file_name);
+
+    let mut area_data = AreaData {
+        name: Default::default(),
+        short_name: short_name(file_name),
+        vnums: Default::default(),
+        credits: Default::default(),
+        continent: Default::default(),
+        builders: Default::default(),
+        security: 0,
+        reset_interval: DEFAULT_RESET_INTERVAL,
+        encounters: Vec::new(),
+        file_name: Default::default(),
+        source: None,
+        on_disk: false,
+        changed: false,
+        format: AreaFormat::Smaug,
+    };
+    let mut has_header = false;
+    let mut version = 0;
+    let mut mobiles = Vec::new();
+    let mut objects = Vec::new();
+    let mut rooms = Vec::new();
+    let mut resets = Vec::new();
+    let mut shops = Vec::new();
+    let mut repairers = Vec::new();
+    let mut helps = Vec::new();
+    // Rooms, exits and how their doors start, from the resets
+    let mut doors = Vec::new();
+
+    loop {
+        // Some sections have their value on the same line, e.g. "#AREA Name~"
+        let word = parser.read_word()?;
+        let section = word
+            .strip_prefix('#')
+            .ok_or_else(|| parser.error(&format!("Expected a section, got '{}'", word)))?;
+
+        match section {
+            "$" => break,
+            "AREA" => {
+                area_data.name = parser.read_string()?.trim().to_string();
+                has_header = true;
+            }
+            "AUTHOR" => area_data.credits = parser.read_string()?.trim().to_string(),
+            // Level ranges, up to a '$'
+            "RANGES" => while read_line(&mut parser)?.trim() != "$" {},
+            "RESETMSG" | "NEIGHBOR" => {
+                parser.read_string()?;
+            }
+            // The area flags, and how often it resets
+            "FLAGS" => {
+                let line = read_line(&mut parser)?;
+                let reset_frequency = line.split_whitespace().nth(1).unwrap_or("0");
+                let reset_frequency: u16 = parser.parse(reset_frequency, "reset frequency")?;
+                if reset_frequency > 0 {
+                    area_data.reset_interval = reset_frequency;
+                }
+            }
+            "ECONOMY" | "CLIMATE" => {
+                read_line(&mut parser)?;
+            }
+            "VERSION" => version = read_number(&mut parser, "version")?,
+            "MOBILES" => {
+                mobiles = load_records(&mut parser, |parser, vnum| load_mobile(parser, vnum, rng))?
+            }
+            "OBJECTS" => {
+                objects = load_records(&mut parser, |parser, vnum| {
+                    load_object(parser, vnum, version)
+                })?
+            }
+            "ROOMS" => rooms = load_records(&mut parser, load_room)?,
+            "RESETS" => resets = load_resets(&mut parser, &mut doors)?,
+            "SHOPS" => shops = load_shops(&mut parser, item_type)?,
+            "REPAIRS" => repairers = load_repairers(&mut parser)?,
+            "SPECIALS" => skip_specials(&mut parser)?,
+            "HELPS" => helps = load_helps(&mut parser)?,
+            section => return Err(parser.error(&format!("Unrecognized section: '#{}'", section))),
+        }
+    }
+
+    if !has_header {
+        return Err(parser.error("Missing the #AREA section"));
+    }
+    for mobile in &mut mobiles {
+        mobile.repairer = repairers.contains(&mobile.vnum);
+    }
+    if area_data.name.is_empty() {
+        area_data.name = area_data.short_name.clone();
+    }
+
+    let mut area = Area {
+        area_data,
+        rooms,
+        objects,
+        mobiles,
+        resets,
+        shops,
+        mobprogs: Vec::new(),
+        helps,
+        socials: Vec::new(),
+    };
+
+    apply_door_resets(&mut area, doors);
+
+    // SMAUG areas don't say which vnums they have
+    let vnums = area
+        .rooms
+        .iter()
+        .map(|room| room.vnum.0)
+        .chain(area.objects.iter().map(|object| object.vnum.0))
+        .chain(area.mobiles.iter().map(|mobile| mobile.vnum.0));
+    let lowest = vnums.clone().min().unwrap_or(0);
+    let highest = vnums.max().unwrap_or(0);
+    area.area_data.vnums = (Vnum(lowest), Vnum(highest));
+
+    remember_area(&mut area);
+
+    Ok(area)
+}
+
+/// Read the records of a section, each starting with its vnum like "#3001",
+/// until "#0".
+fn load_records<'a, T>(
+    parser: &mut FileParser<'a>,
+    mut load: impl FnMut(&mut FileParser<'a>, usize) -> ParseResult<T>,
+) -> ParseResult<Vec<T>> {
+    let mut records = Vec::new();
+
+    loop {
+        let vnum = read_vnum_section(parser)?;
+
+        if vnum == 0 {
+            break;
+        }
+
+        records.push(load(parser, vnum)?);
+    }
+
+    Ok(records)
+}
+
+fn load_mobile(parser: &mut FileParser, vnum: usize, rng: &mut GameRng) -> ParseResult<Mobile> {
+    let mut mobile = Mobile {
+        vnum: Vnum(vnum),
+        name: parser.read_string()?.to_string(),
+        short_description: parser.read_string()?.to_string(),
+        long_description: parser.read_string()?.to_string(),
+        description: parser.read_string()?.to_string(),
+        ..Default::default()
+    };
+
+    // The act and affect flags, the alignment, and whether the mobile is
+    // simple or complex
+    let line = read_line(parser)?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if words.len() < 4 {
+        return Err(parser.error(&format!("Invalid mobile data: '{}'", line)));
+    }
+    let act = parse_flags(parser, words[0])?;
+    let affected_by = parse_flags(parser, words[1])?;
+    let complex = words[3] == "C";
+
+    // The level, THAC0, armor class, hit points and damage
+    let line = read_line(parser)?;
+    let level = line.split_whitespace().next().unwrap_or_default();
+    let level: i32 = parser.parse(level, "level")?;
+    mobile.level = level.max(0) as u32;
+    // Gold and experience
+    let line = read_line(parser)?;
+    let gold = line.split_whitespace().next().unwrap_or("0");
+    let gold: i64 = parser.parse(gold, "gold")?;
+    mobile.wealth.gold = gold.max(0) as usize;
+    // Positions, and the sex
+    let line = read_line(parser)?;
+    let sex = line.split_whitespace().nth(2).unwrap_or("0");
+    mobile.gender = match parser.parse(sex, "sex")? {
+        1 => Gender::Male,
+        2 => Gender::Female,
+        3 => {
+            if rng.coin() {
+                Gender::Male
+            } else {
+                Gender::Female
+            }
+        }
+        _ => Gender::Neutral,
+    };
+
+    // Attributes, saving throws, race and languages, and combat bonuses
+    if complex {
+        for _ in 0..4 {
+            read_line(parser)?;
+        }
+    }
+
+    skip_mudprogs(parser)?;
+
+    mobile.sentinel = act & ACT_SENTINEL != 0;
+    mobile.scavenger = act & ACT_SCAVENGER != 0;
+    mobile.aggressive = act & ACT_AGGRESSIVE != 0;
+    mobile.stay_area = act & ACT_STAY_AREA != 0;
+    mobile.flying = affected_by & AFF_FLYING != 0;
+
+    Ok(mobile)
+}
+
+fn load_object(parser: &mut FileParser, vnum: usize, version: i32) -> ParseResult<Object> {
+    let mut object = Object {
+        vnum: Vnum(vnum),
+        name: parser.read_string()?.to_string(),
+        short_description: parser.read_string()?.to_string(),
+        description: parser.read_string()?.to_string(),
+        ..Default::default()
+    };
+    let _action_description = parser.read_string()?;
+
+    // The item type, the extra and wear flags, and the layers
+    let line = read_line(parser)?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if words.len() < 3 {
+        return Err(parser.error(&format!("Invalid object data: '{}'", line)));
+    }
+    object.item_type = item_type(parser, words[0])?;
+    let wear_flags = parse_flags(parser, words[2])?;
+
+    object.take = wear_flags & 1 != 0;
+    object.wear = WEAR_FLAGS
+        .iter()
+        .enumerate()
+        .skip(1)
+        .take(14)
+        .find(|(index, _flag)| wear_flags & (1 << index) != 0)
+        .map(|(_index, flag)| flag.to_string());
+
+    // Up to six values
+    let line = read_line(parser)?;
+    let mut values = [0; 6];
+    for (value, word) in values.iter_mut().zip(line.split_whitespace()) {
+        *value = parser.parse(word, "object value")?;
+    }
+
+    match object.item_type.as_str() {
+        "container" => {
+            let flags = values[1].max(0) as u64;
+            object.flags = ObjectFlags::Container {
+                closable: flags & CONT_CLOSEABLE != 0,
+                closed: flags & CONT_CLOSED != 0,
+                locked: flags & CONT_LOCKED != 0,
+            };
+        }
+        "light" => {
+            object.flags = ObjectFlags::Light {
+                capacity: 0,
+                hours: values[2],
+            };
+        }
+        "drink_con" | "fountain" => {
+            object.flags = ObjectFlags::Liquid {
+                liquid: usize::try_from(values[2])
+                    .ok()
+                    .and_then(|liquid| LIQUIDS.get(liquid))
+                    .unwrap_or(&"water")
+                    .to_string(),
+            };
+        }
+        _ => (),
+    }
+
+    // The weight, cost and rent
+    let line = read_line(parser)?;
+    let cost = line.split_whitespace().nth(1).unwrap_or("0");
+    object.cost = parser.parse(cost, "cost")?;
+
+    // Since version 1, the spells of magic items are given as names
+    if version >= 1 {
+        let spells = match object.item_type.as_str() {
+            "pill" | "potion" | "scroll" => 3,
+            "salve" => 2,
+            "staff" | "wand" => 1,
+            _ => 0,
+        };
+        for _ in 0..spells {
+            parser.read_quoted_word()?;
+        }
+    }
+
+    loop {
+        match parser.peek_char() {
+            // Affects on whoever wears it
+            Some('A') => {
+                parser.read_word()?;
+                let _location: i32 = read_number(parser, "apply location")?;
+                let _modifier: i32 = read_number(parser, "apply modifier")?;
+            }
+            Some('E') => {
+                parser.read_word()?;
+                object.extra_descriptions.push(ExtraDescription {
+                    keyword: parser.read_string()?.to_string(),
+                    description: parser.read_string()?.to_string(),
+                });
+            }
+            _ => break,
+        }
+    }
+
+    skip_mudprogs(parser)?;
+
+    Ok(object)
+}
+
+fn load_room(parser: &mut FileParser, vnum: usize) -> ParseResult<Room> {
+    let mut room = Room {
+        vnum: Vnum(vnum),
+        name: parser.read_string()?.to_string(),
+        description: parser.read_string()?.to_string(),
+        ..Default::default()
+    };
+
+    // The area number, the flags and the sector, and for teleporting rooms
+    // where to and how soon
+    let line = read_line(parser)?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if words.len() < 3 {
+        return Err(parser.error(&format!("Invalid room data: '{}'", line)));
+    }
+    let room_flags = parse_flags(parser, words[1])?;
+    room.no_mob = room_flags & ROOM_NO_MOB != 0;
+    room.pet_shop = room_flags & ROOM_PET_SHOP != 0;
+    room.no_recall = room_flags & ROOM_NO_RECALL != 0;
+    room.flags = ROOM_FLAGS
+        .iter()
+        .enumerate()
+        .filter(|(index, flag)| room_flags & (1 << index) != 0 && !flag.is_empty())
+        .map(|(_index, flag)| flag.to_string())
+        .collect();
+    let sector: usize = parser.parse(words[2], "sector")?;
+    room.sector = SECTORS.get(sector).copied().unwrap_or("inside").to_string();
+
+    loop {
+        // A letter, and for exits their direction right after it, e.g. "D0"
+        let word = parser.read_word()?;
+        let letter = word.chars().next().unwrap_or_default();
+
+        match letter {
+            'S' => break,
+            'D' => {
+                let door: usize = parser.parse(&word[1..], "direction")?;
+                let name = DIRECTIONS
+                    .get(door)
+                    .ok_or_else(|| parser.error(&format!("Invalid direction: {}", door)))?;
+                let description = parser.read_string()?;
+                let keywords = parser.read_string()?;
+
+                // The exit flags, the key and where it leads, and for
+                // exits that pull how far and how strongly
+                let line = read_line(parser)?;
+                let words: Vec<&str> = line.split_whitespace().collect();
+                if words.len() < 3 {
+                    return Err(parser.error(&format!("Invalid exit data: '{}'", line)));
+                }
+                let exit_flags = parse_flags(parser, words[0])?;
+                let key: i64 = parser.parse(words[1], "key vnum")?;
+                let to_room: i64 = parser.parse(words[2], "exit vnum")?;
+
+                // Exits that lead nowhere
+                if to_room < 0 {
+                    continue;
+                }
+
+                let not_empty =
+                    |text: &str| Some(text.to_string()).filter(|text| !text.trim().is_empty());
+                let has_door = exit_flags & EX_ISDOOR != 0;
+                room.exits.push(Exit {
+                    name: name.to_string(),
+                    vnum: to_vnum(parser, to_room)?,
+                    description: not_empty(description),
+                    extra_keywords: not_empty(keywords),
+                    has_door,
+                    is_closed: has_door && exit_flags & EX_CLOSED != 0,
+                    is_locked: has_door && exit_flags & EX_LOCKED != 0,
+                    key: if key > 0 {
+                        Some(to_vnum(parser, key)?)
+                    } else {
+                        None
+                    },
+                });
+            }
+            'E' => room.extra_descriptions.push(ExtraDescription {
+                keyword: parser.read_string()?.to_string(),
+                description: parser.read_string()?.to_string(),
+            }),
+            // Where the room is on a map
+            'M' => {
+                parser.read_until_newline()?;
+            }
+            '>' => {
+                parser.read_until_newline()?;
+                while !read_line(parser)?.trim_start().starts_with('|') {}
+            }
+            _ => return Err(parser.error(&format!("Unrecognized room data: '{}'", word))),
+        }
+    }
+
+    Ok(room)
+}
+
+/// Read the resets, keeping door resets apart as the room, the direction
+/// and whether the door is closed (1) or locked too (2).
+fn load_resets(
+    parser: &mut FileParser,
+    doors: &mut Vec<(Vnum, &'static str, i64)>,
+) -> ParseResult<Vec<ResetCommand>> {
+    let mut resets = Vec::new();
+
+    loop {
+        let line = read_line(parser)?;
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+
+        // Whatever is after the arguments is a comment
+        let mut args = [0; 4];
+        if matches!(command, "M" | "O" | "P" | "G" | "E" | "D") {
+            let count = match command {
+                "G" => 3,
+                _ => 4,
+            };
+            for arg in args.iter_mut().take(count) {
+                let word = words.next().unwrap_or_default();
+                *arg = parser.parse(word, "reset argument")?;
+            }
+        }
+        let [_extra, arg1, arg2, arg3]: [i64; 4] = args;
+        let limit = |limit: i64| limit.clamp(0, i16::MAX.into()) as i16;
+
+        match command {
+            "S" => break,
+            "M" => resets.push(ResetCommand::Mob {
+                m_num: to_vnum(parser, arg1)?,
+                global_limit: limit(arg2) as u16,
+                r_num: to_vnum(parser, arg3)?,
+                room_limit: limit(arg2) as u16,
+                only_at: None,
+            }),
+            "O" => resets.push(ResetCommand::Object {
+                o_num: to_vnum(parser, arg1)?,
+                global_limit: limit(arg2),
+                r_num: to_vnum(parser, arg3)?,
+                only_at: None,
+            }),
+            "P" => resets.push(ResetCommand::Put {
+                o_num: to_vnum(parser, arg1)?,
+                global_limit: limit(arg2),
+                c_num: to_vnum(parser, arg3)?,
+                container_limit: limit(arg2),
+            }),
+            "G" => resets.push(ResetCommand::Give {
+                o_num: to_vnum(parser, arg1)?,
+                global_limit: limit(arg2),
+            }),
+            "E" => {
+                let o_num = to_vnum(parser, arg1)?;
+                // Equipment that isn't worn, or is worn where the game has
+                // no place for it, is only carried
+                match usize::try_from(arg3)
+                    .ok()
+                    .filter(|at| *at < 18)
+                    .and_then(|at| WEAR_LOCATIONS.get(at))
+                {
+                    Some(location) => resets.push(ResetCommand::Equip {
+                        o_num,
+                        global_limit: limit(arg2),
+                        location: location.to_string(),
+                    }),
+                    None => resets.push(ResetCommand::Give {
+                        o_num,
+                        global_limit: limit(arg2),
+                    }),
+                }
+            }
+            "D" => {
+                let direction = usize::try_from(arg2)
+                    .ok()
+                    .and_then(|door| DIRECTIONS.get(door))
+                    .ok_or_else(|| parser.error(&format!("Invalid direction: {}", arg2)))?;
+                doors.push((to_vnum(parser, arg1)?, *direction, arg3));
+            }
+            // Exits shuffled around for mazes, traps, hidden objects, and
+            // comments
+            _ => (),
+        }
+    }
+
+    Ok(resets)
+}
+
+/// Read the repair shops, up to a 0, keeping only which mobiles they are;
+/// what repairers mend and charge is up to the game.
+fn load_repairers(parser: &mut FileParser) -> ParseResult<Vec<Vnum>> {
+    let mut repairers = Vec::new();
+
+    loop {
+        let line = read_line(parser)?;
+        let keeper = line.split_whitespace().next().unwrap_or("0");
+        let keeper: i64 = parser.parse(keeper, "repairer")?;
+        if keeper == 0 {
+            break;
+        }
+        repairers.push(to_vnum(parser, keeper)?);
+    }
+
+    Ok(repairers)
+}
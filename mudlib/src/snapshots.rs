@@ -0,0 +1,296 @@
+//! Timestamped snapshots of the entity world, and rolling back to them.
+//!
+//! Every entity (rooms, mobiles, objects, and players with everything they
+//! carry) is written to `data/snapshots/<unix time>.json` every half hour, and
+//! whenever an immortal uses `snapshot take`. Only the latest few are kept;
+//! how often and how many is set in the `[limits]` of the configuration.
+//! Scheduled snapshots are skipped if no entity changed since the last one.
+//!
+//! `snapshot restore <name>` replaces the whole world with an older snapshot,
+//! e.g. after a mobprog went haywire. Data kept outside of the entity world
+//! (accounts, notes, mail, clans) is not rolled back. The current world is
+//! saved to a new snapshot first, so a rollback can itself be undone.
+//!
+//! Commands only queue a request; files are written and read later by
+//! `WorldState::run_snapshot_jobs()`, which has access to them.
+
+use crate::{
+    agent::EntityAgent,
+    config::Config,
+    echo,
+    entity::{EntitySnapshot, EntityWorld},
+    files::Files,
+    notes::format_age,
+    state::unix_time,
+    WorldState,
+};
+
+const SNAPSHOTS_DIRECTORY: &str = "data/snapshots";
+
+pub(crate) struct Snapshots {
+    requests: Vec<SnapshotRequest>,
+    /// When the next scheduled snapshot is due, in seconds since the Unix epoch
+    next_scheduled: u64,
+    /// The change tick of the entity world when the last snapshot was taken
+    taken_in: Option<u64>,
+}
+
+enum SnapshotRequest {
+    Take { by: String },
+    List { by: String },
+    Restore { by: String, name: String },
+}
+
+impl Snapshots {
+    pub fn new(config: &Config) -> Self {
+        Snapshots {
+            requests: Vec::new(),
+            next_scheduled: unix_time() + config.limits.snapshot_minutes * 60,
+            taken_in: None,
+        }
+    }
+}
+
+fn snapshot_path(name: &str) -> String {
+    format!("{}/{}.json", SNAPSHOTS_DIRECTORY, name)
+}
+
+/// Get the names of all snapshots, oldest first.
+fn list_snapshots(files: &dyn Files) -> Result<Vec<u64>, std::io::Error> {
+    let file_names = match files.list_directory(SNAPSHOTS_DIRECTORY) {
+        Ok(file_names) => file_names,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err),
+    };
+
+    let mut snapshots: Vec<u64> = file_names
+        .iter()
+        .filter_map(|file_name| file_name.strip_suffix(".json"))
+        .filter_map(|name| name.parse().ok())
+        .collect();
+    snapshots.sort_unstable();
+
+    Ok(snapshots)
+}
+
+/// Write a snapshot of the current world, and throw away the oldest ones.
+pub(crate) fn take_snapshot(
+    world_state: &mut WorldState,
+    files: &dyn Files,
+) -> Result<String, std::io::Error> {
+    let name = unix_time().to_string();
+    files.write_file_raw(
+        &snapshot_path(&name),
+        &world_state.entity_world.snapshot_bytes(),
+    )?;
+    world_state.snapshots.taken_in = Some(world_state.entity_world.change_tick());
+
+    let snapshots = list_snapshots(files)?;
+    let excess = snapshots
+        .len()
+        .saturating_sub(world_state.config.limits.snapshots);
+    for old_snapshot in &snapshots[..excess] {
+        files.remove_file(&snapshot_path(&old_snapshot.to_string()))?;
+    }
+
+    Ok(name)
+}
+
+fn restore_snapshot(
+    world_state: &mut WorldState,
+    files: &dyn Files,
+    name: &str,
+) -> Result<(), String> {
+    let no_such_snapshot = || "There is no snapshot named like that.".to_string();
+
+    // Names are timestamps, which also keeps them from pointing elsewhere
+    if name.parse::<u64>().is_err() {
+        return Err(no_such_snapshot());
+    }
+
+    let bytes = match files.read_file_raw(&snapshot_path(name)) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Err(no_such_snapshot()),
+        Err(err) => return Err(format!("Could not read the snapshot: {}", err)),
+    };
+
+    let snapshot = EntitySnapshot::from_bytes(&bytes)
+        .map_err(|err| format!("Could not load the snapshot: {}", err))?;
+    let entity_world = EntityWorld::from_snapshot(snapshot, &world_state.entity_world);
+
+    // Templates remember the rooms they belong to, so the snapshot must have
+    // been taken with the same areas.
+    let vnum_to_room_entity = &world_state.entity_world.templates.vnum_to_room_entity;
+    for (vnum, room) in vnum_to_room_entity.iter().enumerate() {
+        if let Some(room) = room {
+            match entity_world.old_entity(room) {
+                Some(room) if room.components().general.vnum.0 == vnum => (),
+                _ => {
+                    return Err(
+                        "That snapshot was taken with different areas, and can't be restored."
+                            .to_string(),
+                    )
+                }
+            }
+        }
+    }
+
+    let undo_name = take_snapshot(world_state, files)
+        .map_err(|err| format!("Could not save the current world first: {}", err))?;
+
+    world_state.entity_world = entity_world;
+    println!(
+        "Rolled the world back to snapshot {}; the previous world was saved as {}.",
+        name, undo_name
+    );
+
+    let players = give_players_bodies(world_state);
+    for player in &players {
+        let player_id = world_state
+            .entity_world
+            .player_entity_id(player)
+            .expect("Created above");
+
+        let mut agent = EntityAgent::new(world_state, player_id);
+        echo!(
+            agent.info(),
+            "\r\n`MThe world shimmers, and time flows backwards around you...`^\r\n"
+        );
+        agent.do_look();
+    }
+
+    Ok(())
+}
+
+/// Give a new body to the players who arrived after the snapshot the world
+/// was rolled back to. Returns the names of all players in the game.
+pub(crate) fn give_players_bodies(world_state: &mut WorldState) -> Vec<String> {
+    let players: Vec<String> = world_state.players.player_echoes.keys().cloned().collect();
+    for player in &players {
+        if world_state.entity_world.player_entity_id(player).is_none() {
+            world_state.add_player_body(player);
+        }
+    }
+    players
+}
+
+/// Echo something to a player outside of any command.
+pub(crate) fn tell(world_state: &mut WorldState, player: &str, message: &str) {
+    if let Some(echoes) = world_state.player_echoes(player) {
+        echoes.push_str(message);
+    }
+}
+
+/// Take a scheduled snapshot if one is due, and carry out snapshot commands.
+pub(crate) fn run_snapshot_jobs(world_state: &mut WorldState, files: &dyn Files) {
+    let now = unix_time();
+    if now >= world_state.snapshots.next_scheduled {
+        world_state.snapshots.next_scheduled =
+            now + world_state.config.limits.snapshot_minutes * 60;
+
+        let unchanged = world_state.snapshots.taken_in.is_some_and(|taken_in| {
+            world_state
+                .entity_world
+                .changed_since(taken_in)
+                .next()
+                .is_none()
+        });
+        if !unchanged {
+            if let Err(err) = take_snapshot(world_state, files) {
+                println!("Could not take a scheduled snapshot: {}", err);
+            }
+        }
+    }
+
+    for request in std::mem::take(&mut world_state.snapshots.requests) {
+        match request {
+            SnapshotRequest::Take { by } => match take_snapshot(world_state, files) {
+                Ok(name) => {
+                    println!("{} took snapshot {}.", by, name);
+                    tell(
+                        world_state,
+                        &by,
+                        &format!("Saved a snapshot of the world as `W{}`^.\r\n", name),
+                    );
+                }
+                Err(err) => tell(
+                    world_state,
+                    &by,
+                    &format!("Could not save a snapshot: {}\r\n", err),
+                ),
+            },
+            SnapshotRequest::List { by } => {
+                let message = match list_snapshots(files) {
+                    Ok(snapshots) if snapshots.is_empty() => {
+                        "There are no snapshots yet.\r\n".to_string()
+                    }
+                    Ok(snapshots) => {
+                        let mut message = "Snapshots of the world, oldest first:\r\n".to_string();
+                        for snapshot in snapshots {
+                            message.push_str(&format!(
+                                "  `W{}`^ ({})\r\n",
+                                snapshot,
+                                format_age(now.saturating_sub(snapshot))
+                            ));
+                        }
+                        message
+                    }
+                    Err(err) => format!("Could not list the snapshots: {}\r\n", err),
+                };
+                tell(world_state, &by, &message);
+            }
+            SnapshotRequest::Restore { by, name } => {
+                match restore_snapshot(world_state, files, &name) {
+                    Ok(()) => {
+                        println!("{} rolled the world back to snapshot {}.", by, name);
+                        tell(
+                            world_state,
+                            &by,
+                            &format!(
+                                "Rolled the world back to snapshot `W{}`^. The world from before \
+                                is in the newest snapshot.\r\n",
+                                name
+                            ),
+                        );
+                    }
+                    Err(err) => tell(world_state, &by, &format!("{}\r\n", err)),
+                }
+            }
+        }
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_snapshot(&mut self, words: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(
+                    self.info(),
+                    "Only players can take and restore snapshots.\r\n"
+                );
+                return;
+            }
+        };
+
+        let request = match words {
+            [] | ["list"] => SnapshotRequest::List { by: player },
+            ["take"] => SnapshotRequest::Take { by: player },
+            ["restore", name] => SnapshotRequest::Restore {
+                by: player,
+                name: name.to_string(),
+            },
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Wsnapshot list`^', '`Wsnapshot take`^', '`Wsnapshot restore <name>`^'.\r\n"
+                );
+                return;
+            }
+        };
+
+        self.snapshots.requests.push(request);
+    }
+}
@@ -1,12 +1,18 @@
 use std::collections::BTreeMap;
 
-use crate::{file_parser::FileParser, files::Files};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    file_parser::{FileParser, ParseResult},
+    files::Files,
+    world::World,
+};
 
 pub(crate) struct Socials {
     socials: BTreeMap<String, Social>,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub(crate) struct Social {
     pub(crate) name: String,
     social_flags: String,
@@ -21,6 +27,10 @@ pub(crate) struct Social {
 
     pub(crate) reflected_self: String,
     pub(crate) reflected_others: String,
+
+    /// The area it came from, for socials of area files
+    #[serde(skip)]
+    pub(crate) area: String,
 }
 
 impl Socials {
@@ -31,11 +41,34 @@ impl Socials {
     pub fn get(&self, name: &str) -> Option<&Social> {
         self.socials.get(name)
     }
+
+    /// Add the socials of the world's areas, in place of those of the
+    /// socials file with the same names.
+    pub fn add_area_socials(&mut self, world: &World) {
+        for social in &world.socials {
+            self.socials.insert(social.name.clone(), social.clone());
+        }
+    }
+}
+
+/// Messages starting with "$n" are capitalized by starting them with "$^".
+fn capitalized(message: &str) -> String {
+    if message.starts_with('$') {
+        String::from("$^") + message
+    } else {
+        message.to_string()
+    }
 }
 
+/// Load socials, panicking if the file is missing or broken, as the game
+/// can't start without them.
 pub(crate) fn load_socials(files: &dyn Files, path: &str) -> Socials {
     let contents = files.read_file(path).unwrap();
-    let mut parser = FileParser::new(&contents, path);
+    parse_socials(&contents, path).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn parse_socials(contents: &str, path: &str) -> ParseResult<Socials> {
+    let mut parser = FileParser::new(contents, path);
 
     let mut socials = BTreeMap::new();
 
@@ -43,29 +76,35 @@ pub(crate) fn load_socials(files: &dyn Files, path: &str) -> Socials {
 
     loop {
         if current_social.is_none() {
-            let section = parser.read_section();
+            let section = parser.read_section()?;
 
             if section == "END" {
                 break;
             }
 
-            assert_eq!(section, "SOCIAL");
+            if section != "SOCIAL" {
+                return Err(parser.error(&format!("Expected #SOCIAL, got #{}", section)));
+            }
         }
 
-        let key = parser.read_word();
+        let key = parser.read_word()?;
 
         if key == "End" {
-            let social: Social = current_social.take().unwrap();
+            let social: Social = current_social
+                .take()
+                .ok_or_else(|| parser.error("End of a social without a Name"))?;
             socials.insert(social.name.clone(), social);
             continue;
         }
 
-        parser.skip_one_space();
+        parser.skip_one_space()?;
 
         if current_social.is_none() {
-            assert_eq!(key, "Name");
+            if key != "Name" {
+                return Err(parser.error(&format!("Expected Name, got {}", key)));
+            }
             current_social = Some(Social {
-                name: parser.read_until_tilde().trim_start().to_string(),
+                name: parser.read_until_tilde()?.trim_start().to_string(),
                 ..Default::default()
             });
             continue;
@@ -82,39 +121,82 @@ pub(crate) fn load_socials(files: &dyn Files, path: &str) -> Socials {
             "OthersFound" => &mut social.targetted_others,
             "VictFound" => &mut social.targetted_target,
             "acts[7]" => {
-                parser.read_until_tilde();
+                parser.read_until_tilde()?;
                 continue;
             }
-            key => parser.panic_on_line(&format!("Unrecognized key '{}' in socials file", key)),
+            key => return Err(parser.error(&format!("Unrecognized key '{}' in socials file", key))),
         };
 
-        let message = parser.read_until_tilde().trim_start();
+        *attribute = capitalized(parser.read_until_tilde()?.trim_start());
+    }
 
-        if message.starts_with('$') {
-            *attribute = String::from("$^") + message;
-        } else {
-            *attribute = message.to_string();
+    Ok(Socials { socials })
+}
+
+/// ROM's socials, as in the `#SOCIALS` section of its social.are: a name on
+/// a line of its own, and then a line for each message, or "$" for none.
+/// A "#" line ends a social before its last messages; "#0" ends them all.
+pub(crate) fn load_area_socials(parser: &mut FileParser) -> ParseResult<Vec<Social>> {
+    let mut socials = Vec::new();
+    loop {
+        let name = parser.read_word()?;
+        match name {
+            "#0" => return Ok(socials),
+            "#" => continue,
+            _ => (),
         }
-    }
+        // ROM 2.3 has numbers after the name
+        parser.read_until_newline()?;
 
-    Socials { socials }
+        let mut social = Social {
+            name: name.to_lowercase(),
+            ..Default::default()
+        };
+        let messages = [
+            &mut social.untargetted_self,
+            &mut social.untargetted_others,
+            &mut social.targetted_self,
+            &mut social.targetted_others,
+            &mut social.targetted_target,
+            // Shown when the target isn't there, which the game says itself
+            &mut String::new(),
+            &mut social.reflected_self,
+            &mut social.reflected_others,
+        ];
+        for message in messages {
+            parser.skip_all_space();
+            match parser.read_until_newline()?.trim() {
+                "#" => break,
+                "$" => (),
+                line => *message = capitalized(line),
+            }
+        }
+        socials.push(social);
+    }
 }
 
 // Dawn-format socials; currently using Ultra-Envy socials instead
 #[allow(dead_code)]
 fn load_old_socials(files: &dyn Files, path: &str) -> Socials {
     let contents = files.read_file(path).unwrap();
-    let mut parser = FileParser::new(&contents, path);
+    parse_old_socials(&contents, path).unwrap_or_else(|err| panic!("{}", err))
+}
+
+#[allow(dead_code)]
+fn parse_old_socials(contents: &str, path: &str) -> ParseResult<Socials> {
+    let mut parser = FileParser::new(contents, path);
 
     let mut socials = BTreeMap::new();
 
     let mut current_social = None;
 
     loop {
-        let key = parser.read_word();
+        let key = parser.read_word()?;
 
         if key == "EOF~" {
-            assert!(current_social.is_none());
+            if current_social.is_some() {
+                return Err(parser.error("Last social has no END"));
+            }
             break;
         }
 
@@ -124,12 +206,14 @@ fn load_old_socials(files: &dyn Files, path: &str) -> Socials {
             continue;
         }
 
-        parser.skip_one_space();
+        parser.skip_one_space()?;
 
         if current_social.is_none() {
-            assert_eq!(key, "name");
+            if key != "name" {
+                return Err(parser.error(&format!("Expected name, got {}", key)));
+            }
             current_social = Some(Social {
-                name: parser.read_until_tilde().to_string(),
+                name: parser.read_until_tilde()?.to_string(),
                 ..Default::default()
             });
             continue;
@@ -148,14 +232,14 @@ fn load_old_socials(files: &dyn Files, path: &str) -> Socials {
             "acts[5]" => &mut social.targetted_target,
             "acts[6]" => &mut social.targetted_others,
             "acts[7]" => {
-                parser.read_until_tilde();
+                parser.read_until_tilde()?;
                 continue;
             }
-            key => parser.panic_on_line(&format!("Unrecognized key '{}' in socials file", key)),
+            key => return Err(parser.error(&format!("Unrecognized key '{}' in socials file", key))),
         };
 
-        *attribute = parser.read_until_tilde().to_string();
+        *attribute = parser.read_until_tilde()?.to_string();
     }
 
-    Socials { socials }
+    Ok(Socials { socials })
 }
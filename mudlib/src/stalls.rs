@@ -0,0 +1,484 @@
+//! Stalls that players rent, to sell their things to other players.
+//!
+//! A stall is any room with the `stall` room flag. A player can `stall rent`
+//! one nobody has yet, put things up for sale there with
+//! `stall stock <item> <price>`, and get them back with `stall take <item>`.
+//! Others can `browse` the stall and `buy` from it, even while its owner is
+//! away, and the silver goes to the owner's bank account. `stall leave` gives
+//! the stall up again, along with anything still in it.
+//!
+//! Like mail attachments, goods are kept as vnums and recreated from their
+//! templates when they are bought. Stalls are saved to `data/stalls.json`
+//! whenever they change.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{agent::EntityAgent, echo, files::Files, find_entities::EntityIterator};
+
+const STALLS_FILE: &str = "data/stalls.json";
+/// Silver it costs to rent a stall
+const STALL_RENT: usize = 500;
+/// Goods a stall has room for
+const MAX_GOODS: usize = 10;
+
+pub(crate) struct Stalls {
+    /// Rented stalls, by room vnum
+    stalls: BTreeMap<usize, Stall>,
+    changed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Stall {
+    owner: String,
+    goods: Vec<Good>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Good {
+    vnum: usize,
+    keywords: String,
+    /// What the object is called, e.g. "a short sword"
+    item: String,
+    price: usize,
+}
+
+impl Good {
+    fn has_keyword(&self, keyword: &str) -> bool {
+        self.keywords
+            .split_whitespace()
+            .any(|word| word.eq_ignore_ascii_case(keyword))
+    }
+}
+
+impl Stalls {
+    pub fn load(files: &dyn Files) -> Self {
+        let stalls = match files.read_file_raw(STALLS_FILE) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .unwrap_or_else(|err| panic!("Could not parse {}: {}", STALLS_FILE, err)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => panic!("Could not read {}: {}", STALLS_FILE, err),
+        };
+
+        Stalls {
+            stalls,
+            changed: false,
+        }
+    }
+
+    pub fn save_changes(&mut self, files: &dyn Files) -> Result<(), std::io::Error> {
+        if self.changed {
+            let contents =
+                serde_json::to_vec_pretty(&self.stalls).expect("Stalls should serialize");
+            files.write_file_raw(STALLS_FILE, &contents)?;
+            self.changed = false;
+        }
+
+        Ok(())
+    }
+
+    /// Take away a deleted character's stall, and what was in it.
+    pub fn forget_player(&mut self, player: &str) {
+        let before = self.stalls.len();
+        self.stalls.retain(|_vnum, stall| stall.owner != player);
+        if self.stalls.len() != before {
+            self.changed = true;
+        }
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// The vnum of the room, if the current entity is at a stall.
+    pub(crate) fn stall_vnum(&self) -> Option<usize> {
+        let room_id = self.entity_world.room_of(self.entity_id);
+        let vnum = self
+            .entity_world
+            .entity_info(room_id)
+            .components()
+            .general
+            .vnum
+            .0;
+
+        match self.world.rooms.get(vnum) {
+            Some(room) if vnum != 0 && room.vnum.0 == vnum && room.stall => Some(vnum),
+            _ => None,
+        }
+    }
+
+    /// The name of the player using a stall, or tell them they can't.
+    fn stall_user(&mut self) -> Option<String> {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        match myself.player_name() {
+            Some(player) if self.accounts.account(player).is_some() => Some(player.to_string()),
+            _ => {
+                echo!(
+                    self.info(),
+                    "Only players with an account can trade at stalls.\r\n"
+                );
+                None
+            }
+        }
+    }
+
+    /// The vnum of the stall here, if the current entity rents it.
+    fn own_stall(&mut self, player: &str) -> Option<usize> {
+        let vnum = match self.stall_vnum() {
+            Some(vnum) => vnum,
+            None => {
+                echo!(self.info(), "There is no stall here.\r\n");
+                return None;
+            }
+        };
+
+        match self.stalls.stalls.get(&vnum) {
+            Some(stall) if stall.owner == player => Some(vnum),
+            _ => {
+                echo!(self.info(), "You don't rent this stall.\r\n");
+                None
+            }
+        }
+    }
+
+    /// Give a good to the current entity, unless its template is gone.
+    fn hand_over_good(&mut self, good: &Good) {
+        // Templates can disappear if the areas change between reboots
+        self.entity_world.spawn_object(good.vnum, self.entity_id);
+    }
+
+    pub fn do_stall(&mut self, words: &[&str]) {
+        match words {
+            [] => self.do_browse(),
+            ["rent"] => self.do_stall_rent(),
+            ["stock", item, price] => self.do_stall_stock(item, price),
+            ["take", item] => self.do_stall_take(item),
+            ["leave"] => self.do_stall_leave(),
+            _ => {
+                echo!(
+                    self.info(),
+                    "Syntax: '`Wstall rent`^', '`Wstall stock <item> <price>`^', \
+                    '`Wstall take <item>`^', '`Wstall leave`^', '`Wbrowse`^'.\r\n"
+                );
+            }
+        }
+    }
+
+    pub fn do_browse(&mut self) {
+        let vnum = match self.stall_vnum() {
+            Some(vnum) => vnum,
+            None => {
+                echo!(self.info(), "There is no stall here.\r\n");
+                return;
+            }
+        };
+
+        let stall = match self.stalls.stalls.get(&vnum) {
+            Some(stall) => stall.clone(),
+            None => {
+                echo!(
+                    self.info(),
+                    "Nobody rents this stall; '`Wstall rent`^' rents it for {} silver coins.\r\n",
+                    STALL_RENT
+                );
+                return;
+            }
+        };
+
+        let mut info = self.info();
+        if stall.goods.is_empty() {
+            echo!(info, "{}'s stall is empty.\r\n", stall.owner);
+            return;
+        }
+
+        echo!(info, "{}'s stall has for sale:\r\n", stall.owner);
+        for good in &stall.goods {
+            echo!(info, "  {}: `W{}`^ silver coins\r\n", good.item, good.price);
+        }
+    }
+
+    fn do_stall_rent(&mut self) {
+        let player = match self.stall_user() {
+            Some(player) => player,
+            None => return,
+        };
+        let vnum = match self.stall_vnum() {
+            Some(vnum) => vnum,
+            None => {
+                echo!(self.info(), "There is no stall here.\r\n");
+                return;
+            }
+        };
+
+        if let Some(owner) = self
+            .stalls
+            .stalls
+            .get(&vnum)
+            .map(|stall| stall.owner.clone())
+        {
+            if owner == player {
+                echo!(self.info(), "You already rent this stall.\r\n");
+            } else {
+                echo!(self.info(), "{} already rents this stall.\r\n", owner);
+            }
+            return;
+        }
+
+        if self
+            .stalls
+            .stalls
+            .values()
+            .any(|stall| stall.owner == player)
+        {
+            echo!(
+                self.info(),
+                "You already rent a stall elsewhere; '`Wstall leave`^' it first.\r\n"
+            );
+            return;
+        }
+
+        if !self.remove_silver(STALL_RENT, self.entity_id) {
+            echo!(
+                self.info(),
+                "Renting a stall costs {} silver coins, which you don't have.\r\n",
+                STALL_RENT
+            );
+            return;
+        }
+
+        self.stalls.stalls.insert(
+            vnum,
+            Stall {
+                owner: player,
+                goods: Vec::new(),
+            },
+        );
+        self.stalls.changed = true;
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let mut act = self.players.act_alone(&myself);
+        echo!(
+            act.myself(),
+            "You pay {} silver coins, and the stall is yours.\r\n",
+            STALL_RENT
+        );
+        echo!(act.others(), "$^$n rents the stall.\r\n");
+    }
+
+    fn do_stall_stock(&mut self, object_name: &str, price: &str) {
+        let price = match price.parse::<usize>() {
+            Ok(price) if price > 0 => price,
+            _ => {
+                echo!(self.info(), "That's not a valid price.\r\n");
+                return;
+            }
+        };
+
+        let player = match self.stall_user() {
+            Some(player) => player,
+            None => return,
+        };
+        let vnum = match self.own_stall(&player) {
+            Some(vnum) => vnum,
+            None => return,
+        };
+
+        if self.stalls.stalls[&vnum].goods.len() >= MAX_GOODS {
+            echo!(
+                self.info(),
+                "Your stall has no room for more than {} things.\r\n",
+                MAX_GOODS
+            );
+            return;
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let found = myself
+            .objects()
+            .filter_by_keyword(object_name)
+            .filter_or(
+                |e| e.components().wealth.is_none(),
+                "Silver can't be sold at a stall.",
+            )
+            .filter_or(
+                |e| e.components().general.equipped.is_none(),
+                "You need to remove $N first.",
+            )
+            .filter_or(
+                |e| e.objects().next().is_none(),
+                "You need to empty $N first.",
+            )
+            .filter_or(
+                |e| e.components().general.vnum.0 != 0,
+                "$^$N can't be sold at a stall.",
+            )
+            .find_one_or("You aren't holding anything named like that.");
+
+        let object = match found {
+            Ok(object) => object,
+            Err(err) => return self.echo_error(err),
+        };
+
+        let good = Good {
+            vnum: object.components().general.vnum.0,
+            keywords: object.component_info().keyword().to_string(),
+            item: object.component_info().short_description().to_string(),
+            price,
+        };
+
+        let mut act = self.players.act_with(&myself, &object);
+        echo!(
+            act.myself(),
+            "You put $N up for sale, for {} silver coins.\r\n",
+            price
+        );
+        echo!(act.others(), "$^$n puts $N up for sale.\r\n");
+
+        let object_id = object.entity_id();
+        self.switch_agent(object_id).do_die();
+
+        let stall = self.stalls.stalls.get_mut(&vnum).expect("Checked above");
+        stall.goods.push(good);
+        self.stalls.changed = true;
+    }
+
+    fn do_stall_take(&mut self, object_name: &str) {
+        let player = match self.stall_user() {
+            Some(player) => player,
+            None => return,
+        };
+        let vnum = match self.own_stall(&player) {
+            Some(vnum) => vnum,
+            None => return,
+        };
+
+        let stall = self.stalls.stalls.get_mut(&vnum).expect("Checked above");
+        let good = match stall
+            .goods
+            .iter()
+            .position(|good| good.has_keyword(object_name))
+        {
+            Some(index) => stall.goods.remove(index),
+            None => {
+                echo!(self.info(), "Your stall has nothing named like that.\r\n");
+                return;
+            }
+        };
+        self.stalls.changed = true;
+
+        self.hand_over_good(&good);
+        echo!(
+            self.info(),
+            "You take {} back from your stall.\r\n",
+            good.item
+        );
+    }
+
+    fn do_stall_leave(&mut self) {
+        let player = match self.stall_user() {
+            Some(player) => player,
+            None => return,
+        };
+        let vnum = match self.own_stall(&player) {
+            Some(vnum) => vnum,
+            None => return,
+        };
+
+        let stall = self.stalls.stalls.remove(&vnum).expect("Checked above");
+        self.stalls.changed = true;
+
+        for good in &stall.goods {
+            self.hand_over_good(good);
+            echo!(
+                self.info(),
+                "You take {} back from your stall.\r\n",
+                good.item
+            );
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let mut act = self.players.act_alone(&myself);
+        echo!(act.all(), "$^$n $[give|gives] up $s stall.\r\n");
+    }
+
+    pub(crate) fn do_buy_from_stall(&mut self, vnum: usize, object_name: &str) {
+        let player = match self.stall_user() {
+            Some(player) => player,
+            None => return,
+        };
+
+        let stall = match self.stalls.stalls.get(&vnum) {
+            Some(stall) => stall,
+            None => {
+                echo!(self.info(), "Nobody sells anything at this stall.\r\n");
+                return;
+            }
+        };
+
+        if stall.owner == player {
+            echo!(
+                self.info(),
+                "That's your own stall; '`Wstall take <item>`^' takes things back.\r\n"
+            );
+            return;
+        }
+
+        let index = match stall
+            .goods
+            .iter()
+            .position(|good| good.has_keyword(object_name))
+        {
+            Some(index) => index,
+            None => {
+                echo!(
+                    self.info(),
+                    "You don't see anything named like that to buy.\r\n"
+                );
+                return;
+            }
+        };
+        let owner = stall.owner.clone();
+        let price = stall.goods[index].price;
+
+        if !self.remove_silver(price, self.entity_id) {
+            echo!(
+                self.info(),
+                "You don't have the {} silver to pay for it!\r\n",
+                price
+            );
+            return;
+        }
+
+        let stall = self.stalls.stalls.get_mut(&vnum).expect("Checked above");
+        let good = stall.goods.remove(index);
+        self.stalls.changed = true;
+
+        let balance = self.accounts.bank_balance(&owner);
+        self.accounts.set_bank_balance(&owner, balance + price);
+
+        self.hand_over_good(&good);
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let mut act = self.players.act_alone(&myself);
+        echo!(
+            act.myself(),
+            "You buy {} from {}'s stall for {} silver.\r\n",
+            good.item,
+            owner,
+            price
+        );
+        echo!(act.others(), "$^$n buys {} from the stall.\r\n", good.item);
+
+        if let Some(owner_id) = self.entity_world.player_entity_id(&owner) {
+            let owner = self.entity_world.entity_info(owner_id);
+            echo!(
+                self.players.info(&owner),
+                "`W{} buys {} from your stall; {} silver coins go to your bank account.`^\r\n",
+                player,
+                good.item,
+                price
+            );
+        }
+    }
+}
@@ -0,0 +1,420 @@
+//! Commands for immortals to inspect entities: `stat`, `mstat`, `ostat` and
+//! `rstat` show everything in an entity's components, what it contains, and
+//! where it is.
+//!
+//! Targets are first looked for where the immortal is, like with `look`, and
+//! then anywhere in the world, so that they can be inspected from afar.
+
+use crate::{
+    agent::EntityAgent,
+    components::{DecayKind, EntityType, Position},
+    echo,
+    entity::{EntityId, EntityInfo, Found},
+    save::format_mobprog_trigger,
+    world::{Gender, TimeOfDay},
+};
+
+fn entity_type_name(entity_type: EntityType) -> &'static str {
+    match entity_type {
+        EntityType::Player => "player",
+        EntityType::Mobile => "mobile",
+        EntityType::Object => "object",
+        EntityType::Room => "room",
+        EntityType::Exit => "exit",
+        EntityType::ExtraDescription => "extra description",
+        EntityType::MobProg => "mobprog",
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// Show a description that may span several lines, or "(none)".
+fn or_none(text: &str) -> String {
+    let text = text.trim_end();
+    if text.is_empty() {
+        "(none)".to_string()
+    } else if text.contains('\n') {
+        format!("\r\n{}", text)
+    } else {
+        text.to_string()
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Find an entity to inspect: here, a player in the realm, or anything
+    /// with the keyword in the world.
+    pub(crate) fn find_stat_target<F>(&self, name: &str, matcher: F) -> Option<EntityId>
+    where
+        F: Fn(&EntityInfo) -> bool,
+    {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        match myself.find_entity(name, &matcher) {
+            Found::Myself => return Some(self.entity_id),
+            Found::Other(entity) => return Some(entity.entity_id()),
+            _ => (),
+        }
+
+        if let Some(player_id) = self.find_player_anywhere(name) {
+            if matcher(&self.entity_world.entity_info(player_id)) {
+                return Some(player_id);
+            }
+        }
+
+        self.entity_world
+            .all_entities()
+            .filter(|entity| matcher(entity))
+            .find(|entity| {
+                entity
+                    .component_info()
+                    .keyword()
+                    .split_whitespace()
+                    .any(|word| word.eq_ignore_ascii_case(name))
+            })
+            .map(|entity| entity.entity_id())
+    }
+
+    pub fn do_stat(&mut self, kind: &str, name: Option<&str>) {
+        let target_id = match (kind, name) {
+            ("rstat", None) => Some(self.entity_world.room_of(self.entity_id)),
+            ("rstat", Some(location)) => self.find_location(location),
+            (_, None) => {
+                echo!(self.info(), "Syntax: '`W{} <name>`^'.\r\n", kind);
+                return;
+            }
+            ("mstat", Some(name)) => {
+                self.find_stat_target(name, |entity| entity.is_mobile() || entity.is_player())
+            }
+            ("ostat", Some(name)) => self.find_stat_target(name, |entity| entity.is_object()),
+            (_, Some(name)) => self.find_stat_target(name, |_entity| true),
+        };
+
+        match target_id {
+            Some(target_id) => self.echo_stat(target_id),
+            None if kind == "rstat" => echo!(self.info(), "No such location.\r\n"),
+            None => echo!(self.info(), "There is nothing named like that.\r\n"),
+        }
+    }
+
+    fn echo_stat(&mut self, target_id: EntityId) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let target = self.entity_world.entity_info(target_id);
+        let components = target.components();
+        let component_info = target.component_info();
+        let general = &components.general;
+        let mut info = self.players.info(&myself);
+
+        echo!(
+            info,
+            "`W{}`^: {} {}, vnum {}, area {}\r\n",
+            component_info.short_description(),
+            entity_type_name(general.entity_type),
+            target_id,
+            general.vnum.0,
+            if general.area.is_empty() {
+                "(none)"
+            } else {
+                &general.area
+            }
+        );
+        if let Some(player) = target.player_name() {
+            echo!(info, "Played by {}\r\n", player);
+        }
+        if let Some(archetype) = &general.archetype {
+            echo!(info, "Archetype: {}\r\n", archetype);
+        }
+
+        let gender = match component_info.gender() {
+            Gender::Male => "male",
+            Gender::Female => "female",
+            Gender::Neutral => "neutral",
+        };
+        echo!(info, "Keywords: {}\r\n", component_info.keyword());
+        echo!(info, "Gender: {}\r\n", gender);
+        echo!(
+            info,
+            "Title: {}\r\n",
+            or_none(component_info.internal_title())
+        );
+        echo!(
+            info,
+            "Lateral: {}\r\n",
+            or_none(component_info.lateral_description())
+        );
+        echo!(
+            info,
+            "External: {}\r\n",
+            or_none(component_info.external_description())
+        );
+        echo!(
+            info,
+            "Internal: {}\r\n",
+            or_none(component_info.internal_description())
+        );
+        let night_lateral = component_info.lateral_description_at(TimeOfDay::Night);
+        if night_lateral != component_info.lateral_description() {
+            echo!(info, "Lateral at night: {}\r\n", or_none(night_lateral));
+        }
+        let night_internal = component_info.internal_description_at(TimeOfDay::Night);
+        if night_internal != component_info.internal_description() {
+            echo!(info, "Internal at night: {}\r\n", or_none(night_internal));
+        }
+
+        if let Some(sector) = &general.sector {
+            echo!(info, "Sector: {}\r\n", sector);
+        }
+        if let Some(equipped) = &general.equipped {
+            echo!(info, "Equipped: {}\r\n", equipped);
+        }
+        if let Some(following) = &general.following {
+            match self.entity_world.resolve(following) {
+                Some(leader) => echo!(
+                    info,
+                    "Following: {} ({})\r\n",
+                    leader.component_info().short_description(),
+                    leader.entity_id()
+                ),
+                None => echo!(info, "Following: someone gone\r\n"),
+            }
+        }
+        match general.only_at {
+            Some(TimeOfDay::Day) => echo!(info, "Only around during the day\r\n"),
+            Some(TimeOfDay::Night) => echo!(info, "Only around at night\r\n"),
+            None => (),
+        }
+        for (delay, command) in &general.command_queue {
+            echo!(info, "Queued in {}s: {}\r\n", delay, command);
+        }
+        for (key, value) in general.extras.iter().flat_map(|extras| extras.iter()) {
+            echo!(
+                info,
+                "Kept from the area file: {} {}\r\n",
+                key,
+                value.trim_end()
+            );
+        }
+        for (name, value) in component_info.attributes() {
+            echo!(info, "Attribute: {}={}\r\n", name, value);
+        }
+
+        if let Some(mobile) = &components.mobile {
+            echo!(
+                info,
+                "Mobile: wanders {}, stays in area {}, flies {}, postmaster {}, banker {}, repairer {}, remembers {}\r\n",
+                yes_no(mobile.wander),
+                yes_no(mobile.stay_area),
+                yes_no(mobile.flying),
+                yes_no(mobile.postmaster),
+                yes_no(mobile.banker),
+                yes_no(mobile.repairer),
+                mobile.remember.as_deref().unwrap_or("nobody")
+            );
+            if !mobile.wealth.is_empty() {
+                echo!(info, "Spawns with: {}\r\n", mobile.wealth);
+            }
+            if mobile.hunting > 0 {
+                echo!(
+                    info,
+                    "Hunting: for {} more mobile pulses\r\n",
+                    mobile.hunting
+                );
+            }
+            if let Some(walk_to) = mobile.walk_to {
+                echo!(info, "Walking to: room {}\r\n", walk_to.0);
+            }
+            if mobile.scavenger {
+                echo!(
+                    info,
+                    "Scavenger: wears what it finds {}\r\n",
+                    yes_no(mobile.aggressive)
+                );
+            }
+            if let Some(pet_of) = &mobile.pet_of {
+                echo!(info, "Pet of: {}\r\n", pet_of);
+            }
+            if let Some(shop) = &mobile.shopkeeper {
+                echo!(
+                    info,
+                    "Shop: buys {}, sells {}, profit {}%/{}%, open {} to {}\r\n",
+                    shop.buy_types.join(","),
+                    shop.sell_types.join(","),
+                    shop.profit_buy,
+                    shop.profit_sell,
+                    shop.open_hour,
+                    shop.close_hour
+                );
+            }
+        }
+
+        if let Some(object) = &components.object {
+            echo!(
+                info,
+                "Object: cost {}, container {}, food {}, drink {}, key {}, board {}, resold {}\r\n",
+                object.cost,
+                yes_no(object.container),
+                yes_no(object.food),
+                yes_no(object.drink),
+                object
+                    .key
+                    .map(|key| key.0.to_string())
+                    .unwrap_or_else(|| "none".to_string()),
+                object.board.as_deref().unwrap_or("none"),
+                yes_no(object.resold)
+            );
+            echo!(
+                info,
+                "Wear: can be taken {}, worn {}, boat {}\r\n",
+                yes_no(object.take),
+                object.wear.as_deref().unwrap_or("nowhere"),
+                yes_no(object.boat)
+            );
+            if let Some(light) = &object.light {
+                echo!(
+                    info,
+                    "Light: lit {}, burns for {}, holds {} hours of oil\r\n",
+                    yes_no(light.lit),
+                    light
+                        .hours
+                        .map(|hours| format!("{} more hours", hours))
+                        .unwrap_or_else(|| "ever".to_string()),
+                    light.capacity
+                );
+            }
+            if let Some(liquid) = &object.liquid {
+                echo!(info, "Liquid: {}\r\n", liquid);
+            }
+            if let Some(condition) = object.condition {
+                echo!(info, "Condition: {}%\r\n", condition);
+            }
+        }
+
+        if let Some(door) = &components.door {
+            echo!(
+                info,
+                "Door: closed {}, locked {}, key {}\r\n",
+                yes_no(door.closed),
+                yes_no(door.locked),
+                door.key
+                    .map(|key| key.0.to_string())
+                    .unwrap_or_else(|| "none".to_string())
+            );
+        }
+        if let Some(to_room_id) = target.leads_to() {
+            let to_room = self.entity_world.entity_info(to_room_id);
+            echo!(
+                info,
+                "Leads to: {} ({}, vnum {})\r\n",
+                to_room.component_info().internal_title(),
+                to_room_id,
+                to_room.components().general.vnum.0
+            );
+        }
+
+        if let Some(mobprog) = &components.mobprog {
+            echo!(
+                info,
+                "Mobprog: triggered by '{}'\r\n",
+                format_mobprog_trigger(&mobprog.trigger, general.vnum)
+            );
+            for line in mobprog.code.trim().lines() {
+                echo!(info, "  {}\r\n", line.trim_end());
+            }
+        }
+
+        if let Some(wealth) = &components.wealth {
+            echo!(
+                info,
+                "Coins: {}, worth {} silver\r\n",
+                wealth,
+                wealth.silver_value()
+            );
+        }
+
+        if let Some(decay) = &components.decay {
+            let kind = match decay.kind {
+                DecayKind::Corpse => "rots as a corpse",
+                DecayKind::Food => "spoils on the ground",
+                DecayKind::Conjured => "fades away",
+            };
+            echo!(info, "Decay: {} in {} hours\r\n", kind, decay.ticks);
+        }
+
+        if let Some(vitals) = &components.vitals {
+            let position = match vitals.position {
+                Position::Standing => "standing",
+                Position::Resting => "resting",
+                Position::Sleeping => "sleeping",
+            };
+            echo!(
+                info,
+                "Vitals: {}/{} hit, {}/{} mana, {}/{} moves, {}\r\n",
+                vitals.hit,
+                vitals.max_hit,
+                vitals.mana,
+                vitals.max_mana,
+                vitals.moves,
+                vitals.max_moves,
+                position
+            );
+            echo!(
+                info,
+                "Conditions: {} hours until hungry, {} until thirsty, {} more poisoned, {} drunk\r\n",
+                vitals.hunger,
+                vitals.thirst,
+                vitals.poisoned,
+                vitals.drunk
+            );
+        }
+
+        // From the entity itself up to the world, which contains everything
+        let world_id = self.entity_world.world_entity_id();
+        let mut path = Vec::new();
+        let mut current_id = target_id;
+        while current_id != world_id {
+            current_id = self.entity_world.room_of(current_id);
+            let container = self.entity_world.entity_info(current_id);
+            path.push(if current_id == world_id {
+                "the world".to_string()
+            } else if container.is_mobile() || container.is_player() || container.is_object() {
+                format!("{} ({})", container, current_id)
+            } else {
+                format!(
+                    "{} ({})",
+                    container.component_info().internal_title(),
+                    current_id
+                )
+            });
+        }
+        if !path.is_empty() {
+            echo!(info, "Location: {}\r\n", path.join(" < "));
+        }
+
+        let contents: Vec<EntityInfo> = target.contained_entities().collect();
+        if contents.is_empty() {
+            echo!(info, "Contents: none\r\n");
+        } else {
+            echo!(info, "Contents:\r\n");
+            for entity in contents {
+                let general = &entity.components().general;
+                echo!(
+                    info,
+                    "  {:<8} {:<17} vnum {:<6} {}\r\n",
+                    entity.entity_id(),
+                    entity_type_name(general.entity_type),
+                    general.vnum.0,
+                    if entity.is_exit() || entity.is_extra_description() {
+                        entity.component_info().keyword().to_string()
+                    } else {
+                        entity.to_string()
+                    }
+                );
+            }
+        }
+    }
+}
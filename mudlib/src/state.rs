@@ -7,21 +7,46 @@
 //! * The EntityWorld, which contains all things in the game
 //! * The Players struct, which is used to store output to players
 //!
+//! It also holds persistent data (accounts, notes, mail, tells, clans, the
+//! auction, stalls), which is loaded from files when the state is created, and
+//! saved with `save_changes()`.
+//!
 //! Splitting the two makes it possible to hold multiple read-only references
 //! into the entity world, while mutating Players to echo things to players.
 //!
 //! On a crash or restart, this entire state is thrown away and reloaded.
 
 use crate::{
+    accounts::Accounts,
     acting::{PlayerEcho, Players},
     agent::EntityAgent,
+    auction::Auction,
+    clans::Clans,
+    clock::{time_of_day, Clock},
+    components::EntityComponentInfo,
+    config::Config,
     echo,
-    entity::EntityWorld,
-    import::{import_from_world, VnumTemplates},
+    entity::{EntityId, EntityWorld, Place},
+    event_log::{EventKind, EventLog},
+    events::{dispatch_events, EventBus},
+    import::import_from_world,
+    mail::PostOffice,
     mobprogs::Action,
+    notes::NoteBoards,
+    olc::Olc,
+    perf::Perf,
+    preferences::render_prompt,
+    reload::Reloads,
+    rng::GameRng,
+    snapshots::Snapshots,
     socials::Socials,
+    stalls::Stalls,
+    tells::MessageQueue,
     tick::update_entity_world,
-    world::Vnum,
+    tick::Scheduler,
+    trust::{CommandTrust, Trust},
+    visibility::refresh_sight,
+    weather::Weather,
     world::World,
     Files,
 };
@@ -29,75 +54,386 @@ use crate::{
 pub struct WorldState {
     pub(crate) socials: Socials,
     pub(crate) entity_world: EntityWorld,
-    pub(crate) vnum_templates: VnumTemplates,
-    pub(crate) areas: Vec<Area>,
+    /// The areas the world was imported from, to edit and save them again
+    pub(crate) world: World,
+    pub(crate) olc: Olc,
+    pub(crate) accounts: Accounts,
+    pub(crate) note_boards: NoteBoards,
+    pub(crate) post_office: PostOffice,
+    pub(crate) message_queue: MessageQueue,
+    pub(crate) clans: Clans,
+    pub(crate) auction: Auction,
+    pub(crate) stalls: Stalls,
+    pub(crate) snapshots: Snapshots,
+    pub(crate) reloads: Reloads,
+    pub(crate) event_log: EventLog,
+    pub(crate) event_bus: EventBus,
+    pub(crate) command_trust: CommandTrust,
+    pub(crate) config: Config,
 
     pub(crate) players: Players,
-    pub(crate) wander_ticks: u8,
-}
-
-// Note: this should probably become an entity that contains all of its rooms
-pub(crate) struct Area {
-    pub name: String,
-    pub vnums: (Vnum, Vnum),
-    pub credits: String,
+    pub(crate) scheduler: Scheduler,
+    /// How long the parts of the game take, see `perf.rs`
+    pub(crate) perf: Perf,
+    pub(crate) clock: Clock,
+    pub(crate) weather: Weather,
+    /// Where everything random in the game comes from
+    pub(crate) rng: GameRng,
+    /// Hour of the game clock at the last update, to notice when it changes
+    pub(crate) hour: u8,
 }
 
-pub(super) fn create_state(world: World, socials: Socials) -> WorldState {
+pub(super) fn create_state(
+    world: World,
+    mut socials: Socials,
+    files: &dyn Files,
+    mut rng: GameRng,
+    config: Config,
+) -> WorldState {
     let players = Players {
         player_echoes: Default::default(),
     };
 
+    // Broken resets and triggers are left out of the game, and reported here
+    let report = crate::validate::validate_world(&world);
+    if !report.diagnostics.is_empty() {
+        print!("{}", report);
+    }
+
+    // Socials of area files, like ROM's social.are, are used like the others
+    socials.add_area_socials(&world);
+
+    let clock = Clock::load(files);
     let mut entity_world = EntityWorld::new();
-    let (vnum_templates, areas) = import_from_world(&mut entity_world, &world);
+    import_from_world(
+        &mut entity_world,
+        &world,
+        time_of_day(clock.hour()),
+        &config,
+    );
+    // Nothing needs to hear about the world being made
+    entity_world.take_events();
+    let accounts = Accounts::load(files);
+    let note_boards = NoteBoards::load(files);
+    let post_office = PostOffice::load(files);
+    let message_queue = MessageQueue::load(files);
+    let clans = Clans::load(files);
+    let auction = Auction::load(files);
+    let stalls = Stalls::load(files);
+    let command_trust = CommandTrust::load(files);
+    let weather = Weather::new(&world, clock.now().season(), &mut rng);
 
     WorldState {
         entity_world,
-        vnum_templates,
-        areas,
+        world,
+        olc: Olc::new(),
+        accounts,
+        note_boards,
+        post_office,
+        message_queue,
+        clans,
+        auction,
+        stalls,
+        snapshots: Snapshots::new(&config),
+        reloads: Reloads::new(),
+        event_log: EventLog::new(),
+        event_bus: EventBus::new(),
+        command_trust,
+        config,
         socials,
         players,
-        wander_ticks: 0,
+        scheduler: Scheduler::new(),
+        perf: Perf::new(),
+        hour: clock.hour(),
+        weather,
+        rng,
+        clock,
     }
 }
 
 impl WorldState {
     pub fn from_files(files: &dyn Files) -> WorldState {
-        let world = crate::world::load_world(files, "data/area");
-        let socials = crate::socials::load_socials(files, "data/socials.txt");
-        create_state(world, socials)
+        Self::load(files, GameRng::new(None))
+    }
+
+    /// Like `from_files()`, but with everything random in the game rolled
+    /// from a seed, so that the same commands play out the same way again.
+    /// See the `rng` module.
+    pub fn with_seed(files: &dyn Files, seed: u64) -> WorldState {
+        Self::load(files, GameRng::new(Some(seed)))
+    }
+
+    /// Always with the default configuration, as the CLI has no file for it
+    /// and the same seed should play out the same way anywhere.
+    fn load(files: &dyn Files, mut rng: GameRng) -> WorldState {
+        let config = Config::default();
+        let world = crate::world::load_world(files, &config.world, &mut rng);
+        let socials = crate::socials::load_socials(files, &config.world.socials);
+        create_state(world, socials, files, rng, config)
+    }
+
+    /// The seed everything random in the game is rolled from.
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
     }
 
     pub fn update_world(&mut self) {
+        let _timed = self.perf.enter();
         update_entity_world(self);
     }
 
-    pub fn add_player(&mut self, name: &str) {
-        let player_components = self.entity_world.make_player_components(name);
+    /// Write any persistent data that changed since the last call.
+    pub fn save_changes(&mut self, files: &dyn Files) -> Result<(), std::io::Error> {
+        self.accounts.save_changes(files)?;
+        self.note_boards.save_changes(files)?;
+        self.post_office.save_changes(files)?;
+        self.message_queue.save_changes(files)?;
+        self.clans.save_changes(files)?;
+        self.auction.save_changes(files)?;
+        self.stalls.save_changes(files)?;
+        self.event_log.save_changes(files)
+    }
 
-        let player_id = self.entity_world.add_player(name, player_components);
-        let starting_location = self
+    /// Save everything before the server goes down: anything that changed,
+    /// and a snapshot of the world.
+    pub fn save_all(&mut self, files: &dyn Files) -> Result<(), std::io::Error> {
+        self.save_changes(files)?;
+        crate::snapshots::take_snapshot(self, files).map(|_name| ())
+    }
+
+    /// Take a scheduled snapshot of the world if one is due, and carry out
+    /// snapshot commands; see the `snapshots` module.
+    pub fn run_snapshot_jobs(&mut self, files: &dyn Files) {
+        crate::snapshots::run_snapshot_jobs(self, files);
+    }
+
+    /// Write areas that were asked to be saved with `asave`; see the `olc`
+    /// module.
+    pub fn run_area_saves(&mut self, files: &dyn Files) {
+        crate::olc::run_area_saves(self, files);
+    }
+
+    /// Read data files again that were asked to be reloaded with `reload`;
+    /// see the `reload` module.
+    pub fn run_reloads(&mut self, files: &dyn Files) {
+        crate::reload::run_reloads(self, files);
+    }
+
+    /// Look up the events of players that immortals asked for with
+    /// `history`; see the `event_log` module.
+    pub fn run_history_lookups(&mut self, files: &dyn Files) {
+        crate::event_log::run_history_lookups(self, files);
+    }
+
+    /// Check a name for a new character; see `Accounts::check_new_name()`.
+    pub fn check_new_name(&self, name: &str) -> Result<String, &'static str> {
+        let interner = &self.entity_world.interner;
+        let mobile_keywords = self
             .entity_world
-            .landmark("gnomehill")
-            .expect("Starting location should exist");
-        self.entity_world.move_entity(player_id, starting_location);
+            .templates
+            .mobile_components
+            .iter()
+            .flatten()
+            .flat_map(|(components, _mobprogs)| {
+                EntityComponentInfo::new(components, interner)
+                    .keyword()
+                    .split_whitespace()
+            });
+
+        self.accounts.check_new_name(name, mobile_keywords)
+    }
+
+    /// Create an account for a new character, checking both name and password.
+    pub fn create_account(&mut self, name: &str, password: &str) -> Result<(), &'static str> {
+        self.check_new_name(name)?;
+        Accounts::check_new_password(password)?;
+        self.accounts.create(name, password);
+        Ok(())
+    }
+
+    /// Names of characters deleted since the last call; their connections
+    /// should be closed.
+    pub fn take_deleted_players(&mut self) -> Vec<String> {
+        self.accounts.take_deleted()
+    }
+
+    pub fn add_player(&mut self, name: &str) {
+        let player_id = self.add_player_body(name);
 
         self.players
             .player_echoes
             .insert(name.to_string(), PlayerEcho::default());
+        refresh_sight(&self.accounts, &mut self.players, name);
 
         let player = self.entity_world.entity_info(player_id);
         let mut act = self.players.act_alone(&player);
         echo!(act.others(), "$^$n materializes from thin air.\r\n");
 
         let mut agent = EntityAgent::new(self, player_id);
-        agent.add_silver(200, player_id);
         agent.do_look();
+        agent.echo_unread_notes();
+        agent.echo_unread_mail();
+        agent.echo_unread_messages();
         agent.check_triggers_others(Action::Login);
     }
 
+    /// Create a player's entity (or find their existing one), and put it in
+    /// the starting location, or in jail if they are frozen. Starting silver
+    /// is only given once per account, so that logging in again or a restart
+    /// mints nothing; guests get it with each new entity.
+    pub(crate) fn add_player_body(&mut self, name: &str) -> EntityId {
+        let is_new = self.entity_world.player_entity_id(name).is_none();
+        let player_components = self.entity_world.make_player_components(name);
+
+        let player_id = self.entity_world.add_player(name, player_components);
+        let place = if self.accounts.is_frozen(name) {
+            Place::Jail
+        } else {
+            Place::Start
+        };
+        let starting_location = self
+            .entity_world
+            .place(place)
+            .expect("Starting location should exist");
+        self.entity_world.move_entity(player_id, starting_location);
+
+        let first_time = self.accounts.give_starting_silver(name).unwrap_or(is_new);
+        if first_time {
+            EntityAgent::new(self, player_id).add_silver(200, player_id);
+        }
+
+        player_id
+    }
+
+    /// The prompt to show to a player after their output, with color codes.
+    ///
+    /// It is only rendered again once the player, their room or what they
+    /// carry changed since the last time.
+    pub fn prompt(&mut self, player: &str) -> String {
+        let player_id = match self.entity_world.player_entity_id(player) {
+            Some(player_id) => player_id,
+            None => return format!("{}> ", player),
+        };
+
+        let entity_world = &self.entity_world;
+        let player_echo = self.players.player_echoes.get_mut(player);
+        if let Some(Some((rendered_in, prompt))) = player_echo.as_ref().map(|echo| &echo.prompt) {
+            let entity = entity_world.entity_info(player_id);
+            let changed = entity.changed_since(*rendered_in)
+                || entity.room().changed_since(*rendered_in)
+                || entity
+                    .contained_entities()
+                    .any(|content| content.changed_since(*rendered_in));
+            if !changed {
+                return prompt.clone();
+            }
+        }
+
+        let prompt = render_prompt(
+            self.accounts.preferences(player).prompt.as_deref(),
+            entity_world,
+            player_id,
+        );
+        if let Some(player_echo) = player_echo {
+            player_echo.prompt = Some((entity_world.change_tick(), prompt.clone()));
+        }
+        prompt
+    }
+
+    /// Whether to send color codes to a player, or strip them.
+    pub fn wants_color(&self, player: &str) -> bool {
+        self.accounts.preferences(player).color
+    }
+
+    /// Copy what snooped players were sent to whoever is snooping them.
+    ///
+    /// This should be called before sending out the echoes of all players.
+    pub fn mirror_snooped_echoes(&mut self) {
+        let mirrored: Vec<(String, String)> = self
+            .players
+            .player_echoes
+            .iter()
+            .filter(|(_player, player_echo)| !player_echo.echo_buffer.is_empty())
+            .filter_map(|(player, player_echo)| {
+                player_echo.snooped_by.as_ref()?;
+                Some((player.clone(), player_echo.echo_buffer.clone()))
+            })
+            .collect();
+
+        for (player, echoes) in mirrored {
+            self.echo_to_snooper(&player, &echoes);
+        }
+    }
+
+    /// Echo something to the immortal snooping a player, if any.
+    pub(crate) fn echo_to_snooper(&mut self, player: &str, text: &str) {
+        let snooper = match self.players.player_echoes.get(player) {
+            Some(player_echo) => match &player_echo.snooped_by {
+                Some(snooper) => snooper.clone(),
+                None => return,
+            },
+            None => return,
+        };
+
+        if let Some(snooper_echo) = self.players.player_echoes.get_mut(&snooper) {
+            if snooper_echo.link_dead {
+                return;
+            }
+
+            for line in text.lines() {
+                snooper_echo.echo_buffer.push_str("`w%`^ ");
+                snooper_echo.echo_buffer.push_str(line);
+                snooper_echo.echo_buffer.push_str("\r\n");
+            }
+        }
+    }
+
+    /// Mark a player as link-dead after their connection closes; their body
+    /// stays in the world until they log in again.
+    pub fn disconnect_player(&mut self, name: &str) {
+        if let Some(player_echo) = self.players.player_echoes.get_mut(name) {
+            player_echo.link_dead = true;
+        }
+
+        if let Some(player_id) = self.entity_world.player_entity_id(name) {
+            EntityAgent::new(self, player_id).record_event(EventKind::Logout, "Disconnected.");
+        }
+    }
+
+    /// Check if a player is trusted with a command; see the `trust` module.
+    pub fn may_use_command(&self, player: &str, command: &str) -> bool {
+        self.accounts.trust(player) >= self.command_trust.required(command)
+    }
+
+    /// Check if a player would see another one, e.g. in the list of who is
+    /// online. Players who aren't logged in yet see as little as possible.
+    pub fn can_see_player(&self, viewer: Option<&str>, player: &str) -> bool {
+        let sight = |player: &str| {
+            self.players
+                .player_echoes
+                .get(player)
+                .map(|player_echo| player_echo.sight)
+                .unwrap_or_default()
+        };
+
+        viewer
+            .map(sight)
+            .unwrap_or_default()
+            .can_see(&sight(player))
+    }
+
+    /// Trust players without an account with every command.
+    ///
+    /// This is meant for single-player games like the CLI, where the only
+    /// player doesn't log in.
+    pub fn trust_guests(&mut self) {
+        self.accounts.set_guest_trust(Trust::Implementor);
+    }
+
     pub fn process_player_command(&mut self, player: &str, words: &[&str]) {
+        let _timed = self.perf.enter();
         crate::commands::process_player_command(self, player, words);
+        dispatch_events(self);
     }
 
     /// Get a mutable reference to a player's output echo buffer.
@@ -137,3 +473,11 @@ impl WorldState {
         false
     }
 }
+
+/// Seconds since the Unix epoch.
+pub(crate) fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
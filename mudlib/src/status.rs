@@ -0,0 +1,106 @@
+//! The status of the server as JSON documents, for netcore's read-only HTTP
+//! API. They are published every tick while it is served, which it only is
+//! with `[server] http_address` in the configuration:
+//!
+//! * `/who`: the players online, leaving out invisible immortals
+//! * `/uptime`: when the server and the current game started, in unix time
+//! * `/areas`: the areas with their vnums and credits
+//! * `/stats`: how many areas, rooms, mobiles and objects there are
+//! * `/status`: who, uptime and stats together
+
+use netcore::NetServer;
+use serde_json::{json, Value};
+
+use crate::{components::EntityType, state::unix_time, WorldState};
+
+/// When things started, in unix time
+pub(crate) struct Started {
+    /// The server, which lasts through restarts
+    pub server: u64,
+    /// The current game, since the last restart or crash
+    pub game: u64,
+}
+
+impl WorldState {
+    fn who_json(&self, players: &[&str]) -> Value {
+        let players: Vec<Value> = players
+            .iter()
+            .filter(|player| self.can_see_player(None, player))
+            .map(|player| {
+                json!({
+                    "name": player,
+                    "immortal": self.accounts.is_immortal(player),
+                })
+            })
+            .collect();
+
+        json!({
+            "count": players.len(),
+            "players": players,
+        })
+    }
+
+    fn areas_json(&self) -> Value {
+        let areas: Vec<Value> = self
+            .world
+            .areas
+            .iter()
+            .map(|(area, _resets)| {
+                json!({
+                    "name": area.name,
+                    "short_name": area.short_name,
+                    "vnums": [area.vnums.0 .0, area.vnums.1 .0],
+                    "credits": area.credits,
+                })
+            })
+            .collect();
+
+        json!({ "areas": areas })
+    }
+
+    fn stats_json(&self, players: usize) -> Value {
+        let counts = self.entity_world.entity_counts_by_type();
+        let count = |entity_type| counts.get(&entity_type).copied().unwrap_or(0);
+
+        json!({
+            "areas": self.world.areas.len(),
+            "rooms": count(EntityType::Room),
+            "mobiles": count(EntityType::Mobile),
+            "objects": count(EntityType::Object),
+            "players": players,
+        })
+    }
+
+    /// Publish the status documents to the HTTP server, if there is one.
+    /// `players` are those logged in.
+    pub(crate) fn publish_status(
+        &self,
+        net_server: &mut NetServer,
+        players: &[&str],
+        started: &Started,
+    ) {
+        if !net_server.serves_http() {
+            return;
+        }
+
+        let now = unix_time();
+        let who = self.who_json(players);
+        let uptime = json!({
+            "started": started.server,
+            "game_started": started.game,
+            "uptime": now.saturating_sub(started.server),
+        });
+        let stats = self.stats_json(who["count"].as_u64().unwrap_or(0) as usize);
+        let status = json!({
+            "who": who,
+            "uptime": uptime,
+            "stats": stats,
+        });
+
+        net_server.publish("/who", who.to_string());
+        net_server.publish("/uptime", uptime.to_string());
+        net_server.publish("/areas", self.areas_json().to_string());
+        net_server.publish("/stats", stats.to_string());
+        net_server.publish("/status", status.to_string());
+    }
+}
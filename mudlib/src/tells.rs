@@ -0,0 +1,244 @@
+//! Private messages between players, queued while the recipient is away.
+//!
+//! A `tell` to a player who is offline or link-dead is saved in
+//! `data/messages.json` instead, as long as they have an account. They are
+//! told about it at their next login, and can review the messages with the
+//! `messages` command.
+//!
+//! Each sender can only have a few messages waiting for the same player, so
+//! that nobody's queue gets flooded.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    acting::EscapeVariables, agent::EntityAgent, echo, files::Files, notes::format_age,
+    state::unix_time,
+};
+
+const MESSAGES_FILE: &str = "data/messages.json";
+
+pub(crate) struct MessageQueue {
+    /// Messages for each player, by lowercase player name
+    queues: BTreeMap<String, Vec<QueuedMessage>>,
+    changed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct QueuedMessage {
+    pub sender: String,
+    pub text: String,
+    /// Seconds since the Unix epoch
+    pub date: u64,
+    pub read: bool,
+}
+
+impl MessageQueue {
+    pub fn load(files: &dyn Files) -> Self {
+        let queues = match files.read_file_raw(MESSAGES_FILE) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .unwrap_or_else(|err| panic!("Could not parse {}: {}", MESSAGES_FILE, err)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => panic!("Could not read {}: {}", MESSAGES_FILE, err),
+        };
+
+        MessageQueue {
+            queues,
+            changed: false,
+        }
+    }
+
+    pub fn save_changes(&mut self, files: &dyn Files) -> Result<(), std::io::Error> {
+        if self.changed {
+            let contents =
+                serde_json::to_vec_pretty(&self.queues).expect("Messages should serialize");
+            files.write_file_raw(MESSAGES_FILE, &contents)?;
+            self.changed = false;
+        }
+
+        Ok(())
+    }
+
+    /// Throw away a deleted character's messages.
+    pub fn forget_player(&mut self, player: &str) {
+        if self.queues.remove(&player.to_lowercase()).is_some() {
+            self.changed = true;
+        }
+    }
+
+    fn queue(&self, player: &str) -> &[QueuedMessage] {
+        self.queues
+            .get(&player.to_lowercase())
+            .map(|messages| messages.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_tell(&mut self, target: &str, message: &str) {
+        if message.is_empty() {
+            echo!(self.info(), "Tell them what?\r\n");
+            return;
+        }
+        let message = &self.slurred(message);
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        // Use the account's spelling of the name, if there is one
+        let target = match self.accounts.account(target) {
+            Some(account) => account.name.clone(),
+            None => target.to_string(),
+        };
+
+        let target_id = self.entity_world.player_entity_id(&target);
+        let link_dead = self
+            .players
+            .player_echoes
+            .get(&target)
+            .map(|player_echo| player_echo.link_dead)
+            .unwrap_or(true);
+
+        match target_id {
+            Some(target_id) if target_id == self.entity_id => {
+                echo!(
+                    self.info(),
+                    "You tell yourself something. It sounds much better in your head.\r\n"
+                );
+            }
+            Some(target_id) if !link_dead => {
+                let target = self.entity_world.entity_info(target_id);
+                let mut act = self.players.act_with(&myself, &target);
+                echo!(
+                    act.myself(),
+                    "`GYou tell $N, '{}'`^\r\n",
+                    EscapeVariables(message)
+                );
+                echo!(
+                    act.target(),
+                    "`G$^$n tells you, '{}'`^\r\n",
+                    EscapeVariables(message)
+                );
+            }
+            _ => self.queue_tell(&target, message),
+        }
+    }
+
+    fn queue_tell(&mut self, target: &str, message: &str) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let sender = match myself.player_name() {
+            Some(sender) if self.accounts.account(target).is_some() => sender,
+            Some(_sender) => {
+                echo!(self.info(), "There is no one named like that.\r\n");
+                return;
+            }
+            None => {
+                echo!(self.info(), "They aren't here right now.\r\n");
+                return;
+            }
+        };
+
+        let queue = self
+            .message_queue
+            .queues
+            .entry(target.to_lowercase())
+            .or_default();
+
+        let already_queued = queue
+            .iter()
+            .filter(|queued| queued.sender == sender)
+            .count();
+
+        if already_queued >= self.config.limits.queued_tells {
+            echo!(
+                self.info(),
+                "{} isn't here, and already has too many messages from you waiting.\r\n",
+                target
+            );
+            return;
+        }
+
+        queue.push(QueuedMessage {
+            sender: sender.to_string(),
+            text: message.to_string(),
+            date: unix_time(),
+            read: false,
+        });
+        self.message_queue.changed = true;
+
+        echo!(
+            self.info(),
+            "{} isn't here right now; your message will be waiting for them.\r\n",
+            target
+        );
+    }
+
+    pub fn do_messages(&mut self, clear: bool) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player.to_lowercase(),
+            None => {
+                echo!(self.info(), "Only players can receive messages.\r\n");
+                return;
+            }
+        };
+
+        if clear {
+            if self.message_queue.queues.remove(&player).is_some() {
+                self.message_queue.changed = true;
+            }
+            echo!(self.info(), "Messages cleared.\r\n");
+            return;
+        }
+
+        let messages = match self.message_queue.queues.get_mut(&player) {
+            Some(messages) if !messages.is_empty() => messages,
+            _ => {
+                echo!(self.info(), "Nobody left you any messages.\r\n");
+                return;
+            }
+        };
+
+        let now = unix_time();
+        let mut info = self.players.info(&myself);
+        echo!(info, "Messages left for you while you were away:\r\n");
+        for message in messages.iter_mut() {
+            echo!(
+                info,
+                "{}`S{}:`^ `G{} told you, '{}'`^\r\n",
+                if message.read { " " } else { "`W*`^" },
+                format_age(now.saturating_sub(message.date)),
+                message.sender,
+                message.text
+            );
+            message.read = true;
+        }
+        echo!(info, "Type '`Wmessages clear`^' to throw them away.\r\n");
+        self.message_queue.changed = true;
+    }
+
+    /// Tell a player about messages left for them, e.g. when logging in.
+    pub fn echo_unread_messages(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player,
+            None => return,
+        };
+
+        let unread = self
+            .message_queue
+            .queue(player)
+            .iter()
+            .filter(|message| !message.read)
+            .count();
+
+        if unread > 0 {
+            echo!(
+                self.players.info(&myself),
+                "You have `W{}`^ new message{} from while you were away; type '`Wmessages`^' to read them.\r\n",
+                unread,
+                if unread == 1 { "" } else { "s" }
+            );
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! The templates mobiles and objects are spawned from, by vnum, and the rooms
+//! and mobprogs of each vnum.
+//!
+//! They are made by `crate::import` from the areas of the world, and kept by
+//! the `EntityWorld`, so that resets, shops, mobprogs and immortals all spawn
+//! through `EntityWorld::spawn_mobile()` and `spawn_object()`. Resets keep to
+//! their limits with `EntityWorld::count_in_game()`.
+
+use crate::{components::Components, entity::PermanentEntityId, lazy::WaitingAreas};
+
+#[derive(Clone, Default)]
+pub(crate) struct TemplateRegistry {
+    pub vnum_to_room_entity: Vec<Option<PermanentEntityId>>,
+    pub vnum_to_mobprog: Vec<Option<String>>,
+    pub object_components: Vec<Option<(Components, Vec<Components>)>>,
+    pub mobile_components: Vec<Option<(Components, Vec<Components>)>>,
+    /// Areas whose rooms are only imported once someone goes in them; see
+    /// `crate::lazy`
+    pub waiting_areas: WaitingAreas,
+}
+
+impl TemplateRegistry {
+    /// The template of a mobile, with those of its mobprogs.
+    pub fn mobile(&self, vnum: usize) -> Option<&(Components, Vec<Components>)> {
+        self.mobile_components
+            .get(vnum)
+            .and_then(|template| template.as_ref())
+    }
+
+    /// The template of an object, with those of its extra descriptions.
+    pub fn object(&self, vnum: usize) -> Option<&(Components, Vec<Components>)> {
+        self.object_components
+            .get(vnum)
+            .and_then(|template| template.as_ref())
+    }
+
+    pub fn has_object(&self, vnum: usize) -> bool {
+        self.object(vnum).is_some()
+    }
+
+    /// The components of every template, along with those of their mobprogs
+    /// and extra descriptions.
+    pub fn all_components(&self) -> impl Iterator<Item = &Components> {
+        self.mobile_components
+            .iter()
+            .chain(&self.object_components)
+            .flatten()
+            .flat_map(|(components, contents)| std::iter::once(components).chain(contents))
+    }
+
+    pub fn all_components_mut(&mut self) -> impl Iterator<Item = &mut Components> {
+        self.mobile_components
+            .iter_mut()
+            .chain(&mut self.object_components)
+            .flatten()
+            .flat_map(|(components, contents)| std::iter::once(components).chain(contents))
+    }
+
+    /// The room with a vnum, if its area was imported.
+    pub fn room(&self, vnum: usize) -> Option<&PermanentEntityId> {
+        self.vnum_to_room_entity
+            .get(vnum)
+            .and_then(|room| room.as_ref())
+    }
+}
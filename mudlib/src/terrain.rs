@@ -0,0 +1,95 @@
+//! What it takes to move over the terrain of each sector.
+//!
+//! Like in ROM, moving from one room to another costs players moves: half of
+//! what the two sectors cost together, from 1 indoors to 10 through the air.
+//! Players who are too tired to pay stay where they are.
+//!
+//! Anyone can swim across shallow water (`swim`), but deep water (`noswim`)
+//! needs a boat in the inventory, and only those who fly go through the
+//! `air`. Players can't fly yet, so the air is left to immortals, who go
+//! anywhere without getting tired. Mobiles aren't held back or charged,
+//! though wandering mobiles keep out of the air and deep water unless they
+//! fly.
+
+use crate::{
+    agent::EntityAgent,
+    entity::{EntityId, EntityInfo},
+};
+
+/// Moves it costs to go through a sector.
+fn sector_cost(sector: Option<&str>) -> u32 {
+    match sector.unwrap_or("inside") {
+        "inside" => 1,
+        "city" => 2,
+        "field" => 2,
+        "forest" => 3,
+        "hills" => 4,
+        "mountain" | "desert" => 6,
+        "swim" | "water_swim" | "swamp" | "cave" => 4,
+        "noswim" | "water_noswim" => 1,
+        "air" => 10,
+        _ => 2,
+    }
+}
+
+/// Whether only those who fly can go into a sector.
+pub(crate) fn needs_flight(sector: &str) -> bool {
+    sector == "air"
+}
+
+/// Whether a sector is deep water, which needs a boat to cross.
+pub(crate) fn needs_boat(sector: &str) -> bool {
+    sector == "noswim" || sector == "water_noswim"
+}
+
+fn sector<'e>(room: &EntityInfo<'e>) -> Option<&'e str> {
+    room.components().general.sector.as_deref()
+}
+
+fn has_boat(entity: &EntityInfo) -> bool {
+    entity
+        .objects()
+        .any(|object| matches!(&object.components().object, Some(object) if object.boat))
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Check that the current entity can go from one room to another over
+    /// their terrain, and take the moves it costs. Tells why not otherwise.
+    /// Mobiles go where they like, as in ROM.
+    pub(crate) fn cross_terrain(
+        &mut self,
+        from_room_id: EntityId,
+        to_room_id: EntityId,
+    ) -> Result<(), &'static str> {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player,
+            None => return Ok(()),
+        };
+        if self.accounts.is_immortal(player) {
+            return Ok(());
+        }
+
+        let from = self.entity_world.entity_info(from_room_id);
+        let to = self.entity_world.entity_info(to_room_id);
+        if let Some(sector) = sector(&to) {
+            if needs_flight(sector) {
+                return Err("You can't fly.");
+            }
+            if needs_boat(sector) && !has_boat(&myself) {
+                return Err("You need a boat to go there.");
+            }
+        }
+
+        let cost = (sector_cost(sector(&from)) + sector_cost(sector(&to))) / 2;
+        let mut myself = self.entity_world.entity_info_mut(self.entity_id);
+        match &mut myself.components().vitals {
+            Some(vitals) if vitals.moves < cost => Err("You are too exhausted."),
+            Some(vitals) => {
+                vitals.moves -= cost;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
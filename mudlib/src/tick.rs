@@ -1,64 +1,213 @@
-use crate::components::MyStringInterner;
-use crate::{agent::EntityAgent, commands::process_agent_command, WorldState};
-use rand::Rng;
+//! The pulse scheduler, and the systems it runs.
+//!
+//! The game loop calls `update_entity_world()` once a second; each call is a
+//! pulse. Systems register with the scheduler to run every few pulses, and
+//! the scheduler keeps track of how long each of them takes, which immortals
+//! can see with the `pulses` command.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    agent::EntityAgent,
+    auction::update_auction,
+    clock::{announce_hour, time_of_day},
+    commands::process_agent_command,
+    components::Mobile,
+    day_night::update_day_night,
+    decay::update_decay,
+    echo,
+    encounters::update_encounters,
+    entity::EntityId,
+    events::dispatch_events,
+    hunting::update_hunting,
+    lights::update_lights,
+    memory::update_strings,
+    mobprogs::Action,
+    pathfinding::update_walking,
+    scavengers::update_scavengers,
+    vitals::update_regeneration,
+    wander::update_wander,
+    weather::update_weather,
+    world::MobProgTrigger,
+    WorldState,
+};
+
+/// Systems that run every pulse, e.g. queued commands
+pub(crate) const PULSE: u32 = 1;
+/// Mobiles act every few pulses, e.g. to wander around
+pub(crate) const PULSE_MOBILE: u32 = 4;
+/// A tick is a game hour; see `crate::clock`
+pub(crate) const PULSE_TICK: u32 = 60;
+
+/// A game system that the scheduler runs every few pulses.
+struct System {
+    name: &'static str,
+    /// Pulses between runs
+    every: u32,
+    update: fn(&mut WorldState),
+    /// Pulses left until the next run
+    countdown: u32,
+
+    runs: u64,
+    total_time: Duration,
+    longest_time: Duration,
+}
 
-pub(super) fn update_entity_world(world_state: &mut WorldState) {
-    update_wander(world_state);
-    update_command_queue(world_state);
+pub(crate) struct Scheduler {
+    pulses: u64,
+    systems: Vec<System>,
 }
 
-pub(super) fn update_wander(world_state: &mut WorldState) {
-    world_state.wander_ticks += 1;
+impl Scheduler {
+    /// A scheduler with all of the game's systems, in the order they run.
+    pub fn new() -> Self {
+        let mut scheduler = Scheduler {
+            pulses: 0,
+            systems: Vec::new(),
+        };
 
-    // Make mobs wander every 4 seconds.
-    if world_state.wander_ticks < 4 {
-        return;
+        scheduler.register("wander", PULSE_MOBILE, update_wander);
+        scheduler.register("scavengers", PULSE_MOBILE, update_scavengers);
+        scheduler.register("hunting", PULSE_MOBILE, update_hunting);
+        scheduler.register("walking", PULSE_MOBILE, update_walking);
+        scheduler.register("encounters", PULSE_MOBILE, update_encounters);
+        scheduler.register("command queue", PULSE, update_command_queue);
+        scheduler.register("hour", PULSE, update_hour);
+        scheduler.register("auction", PULSE, update_auction);
+        scheduler.register("weather", PULSE_TICK, update_weather);
+        scheduler.register("regeneration", PULSE_TICK, update_regeneration);
+        scheduler.register("decay", PULSE_TICK, update_decay);
+        scheduler.register("lights", PULSE_TICK, update_lights);
+        scheduler.register("strings", PULSE_TICK * 24, update_strings);
+
+        scheduler
     }
 
-    world_state.wander_ticks = 0;
+    /// Run a system every few pulses, starting that many pulses from now.
+    pub fn register(&mut self, name: &'static str, every: u32, update: fn(&mut WorldState)) {
+        self.systems.push(System {
+            name,
+            every: every.max(1),
+            update,
+            countdown: every.max(1),
+            runs: 0,
+            total_time: Duration::default(),
+            longest_time: Duration::default(),
+        });
+    }
 
-    let entity_world = &mut world_state.entity_world;
-    let mut interner = MyStringInterner::default();
+    /// Start a new pulse, and return the systems that should run in it.
+    fn pulse(&mut self) -> Vec<usize> {
+        self.pulses += 1;
 
-    let mut wanderers = Vec::new();
+        let mut due = Vec::new();
+        for (index, system) in self.systems.iter_mut().enumerate() {
+            system.countdown -= 1;
+            if system.countdown == 0 {
+                system.countdown = system.every;
+                due.push(index);
+            }
+        }
 
-    for entity in entity_world.all_entities() {
-        let wander = match &entity.components().mobile {
-            Some(mobile) => mobile.wander,
-            None => continue,
-        };
+        due
+    }
+}
 
-        if !wander || !random_bits(4) {
-            continue;
-        }
+pub(super) fn update_entity_world(world_state: &mut WorldState) {
+    let _pulse = tracing::info_span!("pulse").entered();
+    world_state.entity_world.next_change_tick();
 
-        let room_id = entity_world.room_of(entity.entity_id());
-        let room = entity_world.entity_info(room_id);
+    for index in world_state.scheduler.pulse() {
+        let System { name, update, .. } = world_state.scheduler.systems[index];
 
-        let random_exit = rand::rng().random_range(0..10);
+        let started = Instant::now();
+        tracing::info_span!("system", name).in_scope(|| update(world_state));
+        let elapsed = started.elapsed();
+        dispatch_events(world_state);
 
-        if let Some(exit) = room.exits().nth(random_exit) {
-            let entity_id = entity.entity_id();
+        let system = &mut world_state.scheduler.systems[index];
+        system.runs += 1;
+        system.total_time += elapsed;
+        system.longest_time = system.longest_time.max(elapsed);
+    }
+}
 
-            let exit_symbol = interner.get_or_intern(exit.main_keyword());
-            wanderers.push((entity_id, exit_symbol));
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_pulses(&mut self) {
+        let scheduler = self.scheduler;
+        let seed = self.rng.seed();
+        let mut info = self.info();
+
+        echo!(
+            info,
+            "{} pulses since the game started, one every second.\r\n",
+            scheduler.pulses
+        );
+        echo!(info, "Random numbers seeded with {}.\r\n", seed);
+        echo!(
+            info,
+            "`W{:<16} {:>6} {:>8} {:>10} {:>10}`^\r\n",
+            "System",
+            "Every",
+            "Runs",
+            "Average",
+            "Longest"
+        );
+        for system in &scheduler.systems {
+            let average = match system.runs {
+                0 => Duration::default(),
+                runs => system.total_time / runs as u32,
+            };
+            echo!(
+                info,
+                "{:<16} {:>6} {:>8} {:>8}us {:>8}us\r\n",
+                system.name,
+                system.every,
+                system.runs,
+                average.as_micros(),
+                system.longest_time.as_micros()
+            );
         }
     }
+}
 
-    for (wanderer_id, exit_symbol) in wanderers {
-        let mut agent = EntityAgent {
-            entity_world,
-            socials: &world_state.socials,
-            vnum_templates: &world_state.vnum_templates,
-            areas: &world_state.areas,
-            players: &mut world_state.players,
-            entity_id: wanderer_id,
-        };
-        let exit_name = interner
-            .resolve(exit_symbol)
-            .expect("Interned in previous loop");
+/// Run hour mobprogs once, when their hour starts on the game clock, tell
+/// players outdoors about the sun, and change what is around at night.
+pub(super) fn update_hour(world_state: &mut WorldState) {
+    let hour = world_state.clock.hour();
+
+    if hour == world_state.hour {
+        return;
+    }
+
+    let changed = time_of_day(hour) != time_of_day(world_state.hour);
+    world_state.hour = hour;
+    announce_hour(world_state, hour);
+    if changed {
+        update_day_night(world_state, time_of_day(hour));
+    }
 
-        agent.do_move(exit_name);
+    let mobiles: Vec<EntityId> = world_state
+        .entity_world
+        .query::<&Mobile>()
+        .map(|(entity, _mobile)| entity)
+        .filter(|entity| {
+            entity.contained_entities().any(|item| {
+                matches!(
+                    &item.components().mobprog,
+                    Some(mobprog) if matches!(
+                        mobprog.trigger,
+                        MobProgTrigger::Hour { hour: at_hour } if at_hour == hour
+                    )
+                )
+            })
+        })
+        .map(|entity| entity.entity_id())
+        .collect();
+
+    for mobile_id in mobiles {
+        let mut agent = EntityAgent::new(world_state, mobile_id);
+        agent.check_triggers_self(Action::Hour { hour });
     }
 }
 
@@ -67,13 +216,14 @@ pub(super) fn update_command_queue(world_state: &mut WorldState) {
     let mut commands = Vec::new();
 
     for mut entity in entity_world.all_entities_mut() {
-        let entity_id = entity.entity_id();
-        let command_queue = &mut entity.components().general.command_queue;
-
-        if command_queue.is_empty() {
+        // Only those with commands queued, so that the others don't count as changed
+        if entity.peek().general.command_queue.is_empty() {
             continue;
         }
 
+        let entity_id = entity.entity_id();
+        let command_queue = &mut entity.components().general.command_queue;
+
         for (tick, _command) in command_queue.iter_mut() {
             *tick = tick.saturating_sub(1);
         }
@@ -91,20 +241,9 @@ pub(super) fn update_command_queue(world_state: &mut WorldState) {
     }
 
     for (entity_id, command) in commands {
-        let mut agent = EntityAgent {
-            entity_world,
-            socials: &world_state.socials,
-            vnum_templates: &world_state.vnum_templates,
-            areas: &world_state.areas,
-            players: &mut world_state.players,
-            entity_id,
-        };
+        let mut agent = EntityAgent::new(world_state, entity_id);
 
         let command_words: Vec<_> = command.split_whitespace().collect();
         process_agent_command(&mut agent, &command_words);
     }
 }
-
-fn random_bits(bits: u8) -> bool {
-    (rand::random::<u32>() >> 7) & ((1u32 << bits) - 1) == 0
-}
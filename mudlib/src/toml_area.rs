@@ -0,0 +1,559 @@
+//! Areas written by hand in TOML, so that builders can make new ones without
+//! learning the tilde-delimited formats of area files.
+//!
+//! ```toml
+//! [area]
+//! name = "The Old Mill"
+//! short_name = "mill"          # the name of the file if left out
+//! vnums = [7000, 7099]
+//! builders = "Annie"
+//!
+//! [[rooms]]
+//! vnum = 7000
+//! name = "By the Mill"
+//! description = """
+//! The old mill creaks in the wind.
+//! """
+//! sector = "field"             # "inside" if left out
+//! flags = ["no_mob"]
+//! exits.north = 7001
+//! exits.east = { to = 7002, door = "locked", key = 7050, keywords = "gate" }
+//!
+//! [[mobiles]]
+//! vnum = 7000
+//! keywords = "miller old man"
+//! short = "the old miller"
+//! long = "The old miller dusts flour off his apron."
+//! description = "He has seen better days."
+//! level = 5
+//! gender = "male"
+//! flags = ["sentinel"]
+//! wealth = { silver = 20, copper = 5 }
+//!
+//! [[objects]]
+//! vnum = 7050
+//! keywords = "key iron"
+//! short = "an iron key"
+//! description = "An iron key lies here."
+//! item_type = "key"
+//! wear = "hold"
+//!
+//! [[resets]]
+//! mobile = 7000
+//! room = 7000
+//!
+//! [[resets]]
+//! give = 7050                  # to the mobile of the reset above
+//! ```
+//!
+//! Containers may have a `container` table, like `{ closable = true }`,
+//! lights a `light` one, like `{ hours = 24 }`, and fountains and drink
+//! containers a `liquid`. Objects can be picked up unless they say
+//! `take = false`.
+//!
+//! Resets are one of `mobile` or `object` in a `room`, or `give`, `equip`
+//! (at a `location`) or `put` (in a `container`), with optional limits.
+//! Doors start as their exits say. Files are told apart from other formats by
+//! starting with a table like `[area]`, after any comments; see
+//! `crate::world::AreaFormat`. Unknown keys are an error, to catch typos.
+//!
+//! They are read into the types of `crate::world`, like other formats, and an
+//! area edited in the game is saved as a Dawn of Time area, which takes its
+//! place in the area list. Shops and mobprogs aren't part of the format yet.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    components::Wealth,
+    file_parser::{FileParser, ParseResult},
+    files::fix_newlines,
+    load::{check_vnum, remember_area, DEFAULT_RESET_INTERVAL},
+    rom::{short_name, DIRECTIONS},
+    world::{
+        Area, AreaData, AreaFormat, Exit, ExtraDescription, Gender, Mobile, Object, ObjectFlags,
+        ResetCommand, Room, TimeOfDay, Vnum,
+    },
+};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AreaFile {
+    area: AreaTable,
+    #[serde(default)]
+    rooms: Vec<Spanned<RoomTable>>,
+    #[serde(default)]
+    mobiles: Vec<Spanned<MobileTable>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectTable>>,
+    #[serde(default)]
+    resets: Vec<Spanned<ResetTable>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AreaTable {
+    name: String,
+    short_name: Option<String>,
+    vnums: (usize, usize),
+    #[serde(default)]
+    builders: String,
+    /// How trusted builders must be, from 0 to 9
+    #[serde(default)]
+    security: u8,
+    #[serde(default)]
+    credits: String,
+    #[serde(default)]
+    continent: String,
+    /// Minutes between area resets
+    reset_interval: Option<u16>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RoomTable {
+    vnum: usize,
+    name: String,
+    description: String,
+    night_description: Option<String>,
+    sector: Option<String>,
+    #[serde(default)]
+    flags: Vec<String>,
+    heal_rate: Option<u32>,
+    mana_rate: Option<u32>,
+    /// Exits by their direction
+    #[serde(default)]
+    exits: BTreeMap<String, ExitTable>,
+    #[serde(default)]
+    extra_descriptions: Vec<ExtraTable>,
+}
+
+/// An exit, either as the vnum it leads to, or as a table with its door
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExitTable {
+    To(usize),
+    Door(DoorTable),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DoorTable {
+    to: usize,
+    /// "open", "closed" or "locked", if there is a door
+    door: Option<String>,
+    key: Option<usize>,
+    keywords: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExtraTable {
+    keywords: String,
+    description: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MobileTable {
+    vnum: usize,
+    keywords: String,
+    short: String,
+    long: String,
+    night_long: Option<String>,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    level: u32,
+    gender: Option<String>,
+    #[serde(default)]
+    flags: Vec<String>,
+    /// Copper, silver and gold coins it carries
+    #[serde(default)]
+    wealth: Wealth,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectTable {
+    vnum: usize,
+    keywords: String,
+    short: String,
+    description: String,
+    item_type: String,
+    #[serde(default)]
+    cost: i32,
+    /// Whether it can be picked up
+    take: Option<bool>,
+    wear: Option<String>,
+    container: Option<ContainerTable>,
+    light: Option<LightTable>,
+    liquid: Option<String>,
+    #[serde(default)]
+    extra_descriptions: Vec<ExtraTable>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ContainerTable {
+    #[serde(default)]
+    closable: bool,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    locked: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct LightTable {
+    #[serde(default)]
+    capacity: i32,
+    /// Hours it burns for, or -1 for ever
+    #[serde(default = "burns_for_ever")]
+    hours: i32,
+}
+
+fn burns_for_ever() -> i32 {
+    -1
+}
+
+/// A reset, told by which of `mobile`, `object`, `give`, `equip` and `put`
+/// it has
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResetTable {
+    mobile: Option<usize>,
+    object: Option<usize>,
+    give: Option<usize>,
+    equip: Option<usize>,
+    put: Option<usize>,
+    room: Option<usize>,
+    container: Option<usize>,
+    location: Option<String>,
+    /// How many can be in the world at once
+    limit: Option<i16>,
+    room_limit: Option<u16>,
+    container_limit: Option<i16>,
+    only_at: Option<String>,
+}
+
+/// Parse an area written in TOML.
+pub(super) fn load_toml_area(contents: &str, file_name: &str) -> ParseResult<Area> {
+    let parser = FileParser::new(contents, file_name);
+    let file: AreaFile = toml::from_str(contents).map_err(|err| {
+        let position = err.span().map_or(0, |span| span.start);
+        parser.error_at(position, &format!("Invalid TOML: {}", err.message()))
+    })?;
+
+    let (first, last) = file.area.vnums;
+    let area_data = AreaData {
+        name: file.area.name,
+        short_name: file
+            .area
+            .short_name
+            .unwrap_or_else(|| short_name(file_name.strip_suffix(".toml").unwrap_or(file_name))),
+        vnums: (
+            Vnum(check_vnum(&parser, first)?),
+            Vnum(check_vnum(&parser, last)?),
+        ),
+        credits: file.area.credits,
+        continent: file.area.continent,
+        builders: file.area.builders,
+        security: file.area.security,
+        reset_interval: file.area.reset_interval.unwrap_or(DEFAULT_RESET_INTERVAL),
+        encounters: Vec::new(),
+        file_name: Default::default(),
+        source: None,
+        on_disk: false,
+        changed: false,
+        format: AreaFormat::Toml,
+    };
+
+    let mut area = Area {
+        area_data,
+        rooms: spanned(&parser, file.rooms, load_room)?,
+        objects: spanned(&parser, file.objects, load_object)?,
+        mobiles: spanned(&parser, file.mobiles, load_mobile)?,
+        resets: spanned(&parser, file.resets, load_reset)?,
+        shops: Vec::new(),
+        mobprogs: Vec::new(),
+        helps: Vec::new(),
+        socials: Vec::new(),
+    };
+    remember_area(&mut area);
+    Ok(area)
+}
+
+/// Turn each table into a template, with errors at the line of its table.
+fn spanned<T, U>(
+    parser: &FileParser,
+    tables: Vec<Spanned<T>>,
+    load: impl Fn(&FileParser, T) -> Result<U, String>,
+) -> ParseResult<Vec<U>> {
+    tables
+        .into_iter()
+        .map(|table| {
+            let position = table.span().start;
+            load(parser, table.into_inner()).map_err(|err| parser.error_at(position, &err))
+        })
+        .collect()
+}
+
+/// A vnum, or an error for one too high to be used.
+fn vnum(parser: &FileParser, vnum: usize) -> Result<Vnum, String> {
+    check_vnum(parser, vnum)
+        .map(Vnum)
+        .map_err(|_| format!("Vnum {} is too high", vnum))
+}
+
+/// Text of several lines, which area files end with a newline.
+fn text(text: &str) -> String {
+    let mut text = fix_newlines(text).into_owned();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push_str("\r\n");
+    }
+    text
+}
+
+fn extra_descriptions(extras: Vec<ExtraTable>) -> Vec<ExtraDescription> {
+    extras
+        .into_iter()
+        .map(|extra| ExtraDescription {
+            keyword: extra.keywords,
+            description: text(&extra.description),
+        })
+        .collect()
+}
+
+fn load_room(parser: &FileParser, table: RoomTable) -> Result<Room, String> {
+    let mut room = Room {
+        vnum: vnum(parser, table.vnum)?,
+        name: table.name,
+        description: text(&table.description),
+        night_description: table.night_description.as_deref().map(text),
+        sector: table.sector.unwrap_or_else(|| "inside".to_string()),
+        heal_rate: table.heal_rate,
+        mana_rate: table.mana_rate,
+        extra_descriptions: extra_descriptions(table.extra_descriptions),
+        ..Default::default()
+    };
+
+    for flag in table.flags {
+        match flag.as_str() {
+            "pet_shop" => room.pet_shop = true,
+            "stall" => room.stall = true,
+            "no_mob" => room.no_mob = true,
+            "no_recall" => room.no_recall = true,
+            _ => room.flags.push(flag),
+        }
+    }
+
+    // The usual directions first, in their usual order, then any others
+    let mut exits: Vec<(String, ExitTable)> = table.exits.into_iter().collect();
+    exits.sort_by_key(|(name, _exit)| {
+        DIRECTIONS
+            .iter()
+            .position(|direction| direction == name)
+            .unwrap_or(DIRECTIONS.len())
+    });
+
+    for (name, exit) in exits {
+        let exit = match exit {
+            ExitTable::To(to) => Exit {
+                name,
+                vnum: vnum(parser, to)?,
+                ..Default::default()
+            },
+            ExitTable::Door(door) => {
+                let (has_door, is_closed, is_locked) = match door.door.as_deref() {
+                    None => (false, false, false),
+                    Some("open") => (true, false, false),
+                    Some("closed") => (true, true, false),
+                    Some("locked") => (true, true, true),
+                    Some(other) => {
+                        return Err(format!(
+                            "Unknown door '{}' to the {}; doors are open, closed or locked",
+                            other, name
+                        ))
+                    }
+                };
+                Exit {
+                    vnum: vnum(parser, door.to)?,
+                    description: door.description.as_deref().map(text),
+                    extra_keywords: door.keywords,
+                    has_door,
+                    is_closed,
+                    is_locked,
+                    key: door.key.map(|key| vnum(parser, key)).transpose()?,
+                    name,
+                }
+            }
+        };
+        room.exits.push(exit);
+    }
+
+    Ok(room)
+}
+
+fn load_mobile(parser: &FileParser, table: MobileTable) -> Result<Mobile, String> {
+    let mut mobile = Mobile {
+        vnum: vnum(parser, table.vnum)?,
+        name: table.keywords,
+        short_description: table.short,
+        long_description: table.long,
+        night_long_description: table.night_long,
+        description: text(&table.description),
+        level: table.level,
+        wealth: table.wealth,
+        gender: match table.gender.as_deref() {
+            None | Some("neutral") => Gender::Neutral,
+            Some("male") => Gender::Male,
+            Some("female") => Gender::Female,
+            Some(other) => {
+                return Err(format!(
+                    "Unknown gender '{}'; mobiles are male, female or neutral",
+                    other
+                ))
+            }
+        },
+        ..Default::default()
+    };
+
+    for flag in table.flags {
+        match flag.as_str() {
+            "sentinel" => mobile.sentinel = true,
+            "stay_area" => mobile.stay_area = true,
+            "flying" => mobile.flying = true,
+            "scavenger" => mobile.scavenger = true,
+            "aggressive" => mobile.aggressive = true,
+            "unseen" => mobile.unseen = true,
+            "postmaster" => mobile.postmaster = true,
+            "banker" => mobile.banker = true,
+            "repairer" => mobile.repairer = true,
+            other => {
+                return Err(format!(
+                    "Unknown mobile flag '{}'; mobiles can be sentinel, stay_area, flying, \
+                    scavenger, aggressive, unseen, postmaster, banker or repairer",
+                    other
+                ))
+            }
+        }
+    }
+
+    Ok(mobile)
+}
+
+fn load_object(parser: &FileParser, table: ObjectTable) -> Result<Object, String> {
+    let item_type = table.item_type;
+    let flags = match (
+        item_type.as_str(),
+        table.container,
+        table.light,
+        table.liquid,
+    ) {
+        ("container", container, None, None) => {
+            let container = container.unwrap_or_default();
+            ObjectFlags::Container {
+                closable: container.closable,
+                closed: container.closed,
+                locked: container.locked,
+            }
+        }
+        ("light", None, light, None) => {
+            let light = light.unwrap_or_default();
+            ObjectFlags::Light {
+                capacity: light.capacity,
+                hours: light.hours,
+            }
+        }
+        ("fountain" | "drink_con", None, None, liquid) => ObjectFlags::Liquid {
+            liquid: liquid.unwrap_or_else(|| "water".to_string()),
+        },
+        (_, None, None, None) => ObjectFlags::Unknown,
+        (item_type, _, _, _) => {
+            return Err(format!(
+                "A {} can't have a container, light or liquid; only containers, lights, \
+                fountains and drink_cons have them",
+                item_type
+            ))
+        }
+    };
+
+    Ok(Object {
+        vnum: vnum(parser, table.vnum)?,
+        name: table.keywords,
+        short_description: table.short,
+        description: table.description,
+        cost: table.cost,
+        item_type,
+        flags,
+        take: table.take.unwrap_or(true),
+        wear: table.wear,
+        extra_descriptions: extra_descriptions(table.extra_descriptions),
+        ..Default::default()
+    })
+}
+
+fn load_reset(parser: &FileParser, table: ResetTable) -> Result<ResetCommand, String> {
+    let only_at = match table.only_at.as_deref() {
+        None => None,
+        Some("day") => Some(TimeOfDay::Day),
+        Some("night") => Some(TimeOfDay::Night),
+        Some(other) => return Err(format!("Unknown time '{}'; it is day or night", other)),
+    };
+    let room = || {
+        table
+            .room
+            .ok_or_else(|| "The reset needs a room".to_string())
+            .and_then(|room| vnum(parser, room))
+    };
+
+    let reset = match (
+        table.mobile,
+        table.object,
+        table.give,
+        table.equip,
+        table.put,
+    ) {
+        (Some(mobile), None, None, None, None) => ResetCommand::Mob {
+            m_num: vnum(parser, mobile)?,
+            global_limit: table.limit.map_or(1, |limit| limit.max(0) as u16),
+            r_num: room()?,
+            room_limit: table.room_limit.unwrap_or(1),
+            only_at,
+        },
+        (None, Some(object), None, None, None) => ResetCommand::Object {
+            o_num: vnum(parser, object)?,
+            global_limit: table.limit.unwrap_or(-1),
+            r_num: room()?,
+            only_at,
+        },
+        (None, None, Some(give), None, None) => ResetCommand::Give {
+            o_num: vnum(parser, give)?,
+            global_limit: table.limit.unwrap_or(-1),
+        },
+        (None, None, None, Some(equip), None) => ResetCommand::Equip {
+            o_num: vnum(parser, equip)?,
+            global_limit: table.limit.unwrap_or(-1),
+            location: table
+                .location
+                .ok_or_else(|| "Equipping needs a location, like \"wield\"".to_string())?,
+        },
+        (None, None, None, None, Some(put)) => ResetCommand::Put {
+            o_num: vnum(parser, put)?,
+            global_limit: table.limit.unwrap_or(-1),
+            c_num: table
+                .container
+                .ok_or_else(|| "Putting needs a container".to_string())
+                .and_then(|container| vnum(parser, container))?,
+            container_limit: table.container_limit.unwrap_or(1),
+        },
+        _ => return Err("A reset needs one of mobile, object, give, equip or put".to_string()),
+    };
+
+    Ok(reset)
+}
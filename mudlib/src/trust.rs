@@ -0,0 +1,281 @@
+//! Trust levels, and which commands need them.
+//!
+//! Every account has a trust level; players without an account are plain
+//! players. Each command needs a minimum level, given by the command table
+//! (see the `command_table` module), and can be changed by adding lines like
+//! `force builder` to the optional `data/command_trust.txt` file.
+//!
+//! Commands a player isn't trusted with are treated as unknown commands, so
+//! they don't even learn that they exist, and abbreviations only stand for
+//! commands they are trusted with. Commands run through others, like
+//! with `force`, are checked against the trust of whoever started them.
+//! Mobiles, and so mobprogs, are trusted like builders, since builders write
+//! mobprogs and could otherwise use them to get around their own trust.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agent::EntityAgent,
+    command_table::{command_named, find_command, Command, COMMANDS},
+    echo,
+    files::Files,
+    visibility::refresh_sight,
+};
+
+const COMMAND_TRUST_FILE: &str = "data/command_trust.txt";
+
+/// What mobiles are trusted with, e.g. `mob` commands but not `trust`
+const MOBILE_TRUST: Trust = Trust::Builder;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Trust {
+    #[default]
+    Player,
+    /// Can use mobprog commands, e.g. to test areas
+    Builder,
+    Immortal,
+    /// Can do anything, including changing the trust of others
+    Implementor,
+}
+
+static TRUST_LEVELS: &[(Trust, &str)] = &[
+    (Trust::Player, "player"),
+    (Trust::Builder, "builder"),
+    (Trust::Immortal, "immortal"),
+    (Trust::Implementor, "implementor"),
+];
+
+impl Trust {
+    pub fn name(self) -> &'static str {
+        TRUST_LEVELS
+            .iter()
+            .find(|(trust, _name)| *trust == self)
+            .map(|(_trust, name)| *name)
+            .expect("All levels have a name")
+    }
+
+    /// The highest area security this level may build at, from 0 to 9 as in
+    /// ROM areas: builders can be kept out of areas with more than 5.
+    pub fn security(self) -> u8 {
+        match self {
+            Trust::Player => 0,
+            Trust::Builder => 5,
+            Trust::Immortal | Trust::Implementor => 9,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Trust> {
+        TRUST_LEVELS
+            .iter()
+            .find(|(_trust, level_name)| level_name.eq_ignore_ascii_case(name))
+            .map(|(trust, _name)| *trust)
+    }
+}
+
+pub(crate) struct CommandTrust {
+    /// Levels from the data file, by command
+    overrides: BTreeMap<String, Trust>,
+}
+
+impl CommandTrust {
+    pub fn load(files: &dyn Files) -> Self {
+        let contents = match files.read_file(COMMAND_TRUST_FILE) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => panic!("Could not read {}: {}", COMMAND_TRUST_FILE, err),
+        };
+
+        let mut overrides = BTreeMap::new();
+
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [command, level] => {
+                    let trust = Trust::from_name(level).unwrap_or_else(|| {
+                        panic!("Unknown trust level {} in {}", level, COMMAND_TRUST_FILE)
+                    });
+                    overrides.insert(command.to_lowercase(), trust);
+                }
+                _ => panic!(
+                    "Lines in {} should look like 'command level', not: {}",
+                    COMMAND_TRUST_FILE, line
+                ),
+            }
+        }
+
+        CommandTrust { overrides }
+    }
+
+    /// The trust needed to use a command.
+    pub fn required(&self, command: &str) -> Trust {
+        let mut command = command.to_lowercase();
+
+        // "mq5 say hi" is short for "mq 5 say hi"
+        if command.starts_with("mq") && command[2..].parse::<u32>().is_ok() {
+            command.truncate(2);
+        }
+
+        if let Some(trust) = self.overrides.get(&command) {
+            return *trust;
+        }
+
+        command_named(&command)
+            .map(|command| command.trust)
+            .unwrap_or(Trust::Player)
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Check if the current player is trusted with a command, by its whole
+    /// name. Mobiles are trusted like builders.
+    fn trusted_with_name(&self, name: &str) -> bool {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let trust = match myself.player_name() {
+            Some(player) => self.accounts.trust(player),
+            None => MOBILE_TRUST,
+        };
+        trust >= self.command_trust.required(name)
+    }
+
+    /// The command in the command table that a word is the name or an
+    /// abbreviation of, out of those the current entity is trusted with.
+    pub fn command_for(&self, word: &str) -> Option<&'static Command> {
+        find_command(word, |name| self.trusted_with_name(name))
+    }
+
+    /// Check if the current entity is trusted with what a word, typed as a
+    /// command, would run. Words that aren't commands, like socials, are
+    /// checked by themselves, as they can be given a trust level too.
+    pub fn trusted_with(&self, word: &str) -> bool {
+        // "mq5 say hi" is short for "mq 5 say hi"
+        let word = if word.starts_with("mq") && word[2..].parse::<u32>().is_ok() {
+            "mq"
+        } else {
+            word
+        };
+
+        self.command_for(word).is_some()
+            || (find_command(word, |_| true).is_none() && self.trusted_with_name(word))
+    }
+
+    /// List the commands that need more than a player's trust.
+    pub fn do_wizhelp(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let trust = myself
+            .player_name()
+            .map(|player| self.accounts.trust(player))
+            .unwrap_or_default();
+
+        let mut commands: Vec<(&str, Trust, &str)> = COMMANDS
+            .iter()
+            .map(|command| {
+                (
+                    command.name,
+                    self.command_trust.required(command.name),
+                    command.description,
+                )
+            })
+            .collect();
+        commands.extend(
+            self.command_trust
+                .overrides
+                .iter()
+                .filter(|(name, _trust)| command_named(name).is_none())
+                .map(|(name, trust)| (name.as_str(), *trust, "")),
+        );
+        commands.retain(|(_name, required, _description)| {
+            *required > Trust::Player && *required <= trust
+        });
+        commands.sort_by_key(|(_name, required, _description)| *required);
+
+        let mut info = self.players.info(&myself);
+        echo!(info, "Your trust level is `W{}`^.\r\n", trust.name());
+
+        if commands.is_empty() {
+            echo!(info, "There are no special commands for you.\r\n");
+            return;
+        }
+
+        echo!(info, "Commands you are trusted with:\r\n");
+        for (name, required, description) in commands {
+            echo!(
+                info,
+                "  `W{:<10}`^ {:<12} {}\r\n",
+                name,
+                required.name(),
+                description
+            );
+        }
+    }
+
+    pub fn do_trust(&mut self, player_name: &str, level: Option<&str>) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+
+        let name = match self.accounts.account(player_name) {
+            Some(account) => account.name.clone(),
+            None => {
+                echo!(self.info(), "There is no character named like that.\r\n");
+                return;
+            }
+        };
+
+        let level = match level {
+            Some(level) => level,
+            None => {
+                let trust = self.accounts.trust(&name);
+                echo!(
+                    self.info(),
+                    "{} has the trust level `W{}`^.\r\n",
+                    name,
+                    trust.name()
+                );
+                return;
+            }
+        };
+
+        let trust = match Trust::from_name(level) {
+            Some(trust) => trust,
+            None => {
+                let levels: Vec<&str> = TRUST_LEVELS.iter().map(|(_trust, name)| *name).collect();
+                echo!(self.info(), "Trust levels are: {}.\r\n", levels.join(", "));
+                return;
+            }
+        };
+
+        if myself.player_name() == Some(name.as_str()) {
+            echo!(self.info(), "You can't change your own trust.\r\n");
+            return;
+        }
+
+        self.accounts.set_trust(&name, trust);
+        refresh_sight(self.accounts, self.players, &name);
+        println!(
+            "{} set the trust of {} to {}.",
+            myself.player_name().unwrap_or("Someone"),
+            name,
+            trust.name()
+        );
+
+        if let Some(target_id) = self.entity_world.player_entity_id(&name) {
+            let target = self.entity_world.entity_info(target_id);
+            echo!(
+                self.players.info(&target),
+                "Your trust level is now `W{}`^.\r\n",
+                trust.name()
+            );
+        }
+
+        echo!(
+            self.info(),
+            "{} now has the trust level `W{}`^.\r\n",
+            name,
+            trust.name()
+        );
+    }
+}
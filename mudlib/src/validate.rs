@@ -0,0 +1,388 @@
+//! Checks of the areas of a world, for what would break in the game: exits
+//! to rooms that don't exist, resets of mobiles, objects or rooms that
+//! don't exist, door resets of exits without a door, and mobiles with
+//! triggers of mobprogs that don't exist.
+//!
+//! Errors are parts of an area that are left out when it is loaded, like a
+//! reset that can't run; warnings are parts that load, but probably not as
+//! they were meant to. The areas are checked when the game starts, and the
+//! report is printed to the server log. Builders can check them again after
+//! editing with `validate [area]`, and `clicore validate` checks them
+//! without playing. An area reloaded with `reload area` is only replaced if
+//! it has no errors.
+//!
+//! Resets are numbered from 1, in the order the area has them; door resets
+//! that ROM areas keep come after all the others.
+
+use std::fmt;
+
+use crate::{
+    agent::EntityAgent,
+    echo,
+    world::{Area, MobProg, Mobile, Object, ResetCommand, Room, Vnum, World},
+    WorldState,
+};
+
+/// How bad something found in an area is
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    /// Loads, but probably not as it was meant to
+    Warning,
+    /// Left out when the area is loaded
+    Error,
+}
+
+/// Something wrong in an area
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Short name of the area
+    pub area: String,
+    pub message: String,
+}
+
+/// Everything wrong in the areas that were checked
+#[derive(Default, Debug)]
+pub struct Report {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn errors(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    /// One line with how many errors and warnings there are.
+    pub fn summary(&self) -> String {
+        let plural = |count: usize, word: &str| match count {
+            1 => format!("1 {}", word),
+            count => format!("{} {}s", count, word),
+        };
+        format!(
+            "{} and {}",
+            plural(self.errors(), "error"),
+            plural(self.warnings(), "warning")
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{} in {}: {}", severity, self.area, self.message)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        writeln!(f, "{}.", self.summary())
+    }
+}
+
+/// Templates that areas can use, by vnum: those of the world, or also those
+/// of an area loaded again to replace one of the world's.
+struct Templates<'a> {
+    world: &'a World,
+    /// An area loaded again, and the short name of the area it replaces
+    replacing: Option<(&'a Area, &'a str)>,
+}
+
+/// A template by its vnum, from a replacing area or the world; those of the
+/// replaced area don't count, nor do empty slots of the world.
+fn find<'a, T>(
+    vnum: Vnum,
+    replacing: Option<(&'a [T], &str)>,
+    world: &'a [T],
+    key: impl Fn(&T) -> (Vnum, &str),
+) -> Option<&'a T> {
+    let in_world = world.get(vnum.0).filter(|template| key(template).0 .0 != 0);
+
+    match replacing {
+        Some((templates, old_name)) => templates
+            .iter()
+            .find(|template| key(template).0 == vnum)
+            .or_else(|| in_world.filter(|template| key(template).1 != old_name)),
+        None => in_world,
+    }
+}
+
+impl<'a> Templates<'a> {
+    fn room(&self, vnum: Vnum) -> Option<&'a Room> {
+        let replacing = self
+            .replacing
+            .map(|(area, old_name)| (area.rooms.as_slice(), old_name));
+        find(vnum, replacing, &self.world.rooms, |room| {
+            (room.vnum, &room.area)
+        })
+    }
+
+    fn mobile(&self, vnum: Vnum) -> Option<&'a Mobile> {
+        let replacing = self
+            .replacing
+            .map(|(area, old_name)| (area.mobiles.as_slice(), old_name));
+        find(vnum, replacing, &self.world.mobiles, |mobile| {
+            (mobile.vnum, &mobile.area)
+        })
+    }
+
+    fn object(&self, vnum: Vnum) -> Option<&'a Object> {
+        let replacing = self
+            .replacing
+            .map(|(area, old_name)| (area.objects.as_slice(), old_name));
+        find(vnum, replacing, &self.world.objects, |object| {
+            (object.vnum, &object.area)
+        })
+    }
+
+    fn mobprog(&self, vnum: Vnum) -> Option<&'a MobProg> {
+        let replacing = self
+            .replacing
+            .map(|(area, old_name)| (area.mobprogs.as_slice(), old_name));
+        find(vnum, replacing, &self.world.mobprogs, |mobprog| {
+            (mobprog.vnum, &mobprog.area)
+        })
+    }
+}
+
+/// Check the rooms, mobiles and resets of an area.
+fn check_area(
+    report: &mut Report,
+    area: &str,
+    rooms: &[&Room],
+    mobiles: &[&Mobile],
+    resets: &[ResetCommand],
+    templates: &Templates,
+) {
+    let mut add = |severity, message: String| {
+        report.diagnostics.push(Diagnostic {
+            severity,
+            area: area.to_string(),
+            message,
+        })
+    };
+
+    for room in rooms {
+        for exit in &room.exits {
+            if templates.room(exit.vnum).is_none() {
+                add(
+                    Severity::Warning,
+                    format!(
+                        "the exit {} of room {} leads to room {}, which doesn't exist",
+                        exit.name, room.vnum.0, exit.vnum.0
+                    ),
+                );
+            }
+            if let Some(key) = exit.key {
+                if templates.object(key).is_none() {
+                    add(
+                        Severity::Warning,
+                        format!(
+                            "the door {} of room {} is opened by object {}, which doesn't exist",
+                            exit.name, room.vnum.0, key.0
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    for mobile in mobiles {
+        for (_trigger, vnum) in &mobile.mobprog_triggers {
+            if templates.mobprog(*vnum).is_none() {
+                add(
+                    Severity::Error,
+                    format!(
+                        "mobile {} triggers mobprog {}, which doesn't exist",
+                        mobile.vnum.0, vnum.0
+                    ),
+                );
+            }
+        }
+    }
+
+    for (number, reset) in resets.iter().enumerate() {
+        let missing = |what: &str, vnum: Vnum| {
+            format!(
+                "reset {} uses {} {}, which doesn't exist",
+                number + 1,
+                what,
+                vnum.0
+            )
+        };
+
+        match reset {
+            ResetCommand::Mob { m_num, r_num, .. } => {
+                if templates.mobile(*m_num).is_none() {
+                    add(Severity::Error, missing("mobile", *m_num));
+                }
+                if templates.room(*r_num).is_none() {
+                    add(Severity::Error, missing("room", *r_num));
+                }
+            }
+            ResetCommand::Object { o_num, r_num, .. } => {
+                if templates.object(*o_num).is_none() {
+                    add(Severity::Error, missing("object", *o_num));
+                }
+                if templates.room(*r_num).is_none() {
+                    add(Severity::Error, missing("room", *r_num));
+                }
+            }
+            ResetCommand::Give { o_num, .. } | ResetCommand::Equip { o_num, .. } => {
+                if templates.object(*o_num).is_none() {
+                    add(Severity::Error, missing("object", *o_num));
+                }
+            }
+            ResetCommand::Put { o_num, c_num, .. } => {
+                if templates.object(*o_num).is_none() {
+                    add(Severity::Error, missing("object", *o_num));
+                }
+                if templates.object(*c_num).is_none() {
+                    add(Severity::Error, missing("container", *c_num));
+                }
+            }
+            ResetCommand::Door {
+                r_num, direction, ..
+            } => {
+                let exit = templates.room(*r_num).map(|room| {
+                    room.exits
+                        .iter()
+                        .find(|exit| exit.name.eq_ignore_ascii_case(direction))
+                });
+                let problem = match exit {
+                    None => Some(missing("room", *r_num)),
+                    Some(None) => Some(format!(
+                        "reset {} sets a door {} of room {}, which has no such exit",
+                        number + 1,
+                        direction,
+                        r_num.0
+                    )),
+                    Some(Some(exit)) if !exit.has_door => Some(format!(
+                        "reset {} sets a door {} of room {}, but the exit has no door",
+                        number + 1,
+                        direction,
+                        r_num.0
+                    )),
+                    Some(Some(_)) => None,
+                };
+                if let Some(problem) = problem {
+                    add(Severity::Warning, problem);
+                }
+            }
+        }
+    }
+}
+
+/// Check one area of the world, by its index.
+fn check_world_area(report: &mut Report, world: &World, index: usize) {
+    let (area_data, resets) = &world.areas[index];
+    let name = &area_data.short_name;
+    let rooms: Vec<&Room> = world.rooms.iter().filter(|t| t.area == *name).collect();
+    let mobiles: Vec<&Mobile> = world.mobiles.iter().filter(|t| t.area == *name).collect();
+    let templates = Templates {
+        world,
+        replacing: None,
+    };
+
+    check_area(report, name, &rooms, &mobiles, resets, &templates);
+}
+
+/// Check every area of a world.
+pub(super) fn validate_world(world: &World) -> Report {
+    let mut report = Report::default();
+    for index in 0..world.areas.len() {
+        check_world_area(&mut report, world, index);
+    }
+    report
+}
+
+/// Check an area loaded again to replace the area of the world at an index,
+/// with the templates of the other areas.
+pub(super) fn validate_replacement(area: &Area, world: &World, index: usize) -> Report {
+    let mut report = Report::default();
+    let old_name = &world.areas[index].0.short_name;
+    let rooms: Vec<&Room> = area.rooms.iter().collect();
+    let mobiles: Vec<&Mobile> = area.mobiles.iter().collect();
+    let templates = Templates {
+        world,
+        replacing: Some((area, old_name)),
+    };
+
+    check_area(
+        &mut report,
+        &area.area_data.short_name,
+        &rooms,
+        &mobiles,
+        &area.resets,
+        &templates,
+    );
+    report
+}
+
+/// Check every area of a world, or only the one found by its short name or
+/// file name.
+fn validate_named(world: &World, area: Option<&str>) -> Result<Report, String> {
+    match area {
+        Some(area) => match world.find_area(area) {
+            Some(index) => {
+                let mut report = Report::default();
+                check_world_area(&mut report, world, index);
+                Ok(report)
+            }
+            None => Err(format!("There is no area called '{}'.", area)),
+        },
+        None => Ok(validate_world(world)),
+    }
+}
+
+impl WorldState {
+    /// What is wrong in every area, or only the one found by its short name
+    /// or file name.
+    pub fn validate(&self, area: Option<&str>) -> Result<Report, String> {
+        validate_named(&self.world, area)
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_validate(&mut self, area: Option<&str>) {
+        let report = match validate_named(self.world, area) {
+            Ok(report) => report,
+            Err(err) => {
+                echo!(self.info(), "{}\r\n", err);
+                return;
+            }
+        };
+
+        let mut info = self.info();
+        for diagnostic in &report.diagnostics {
+            let color = match diagnostic.severity {
+                Severity::Warning => "`Y",
+                Severity::Error => "`R",
+            };
+            // Backticks would be taken for colors
+            echo!(
+                info,
+                "{}{}`^\r\n",
+                color,
+                diagnostic.to_string().replace('`', "'")
+            );
+        }
+        echo!(info, "{}.\r\n", report.summary());
+    }
+}
@@ -0,0 +1,157 @@
+//! What players can see of each other: wizinvis and holylight.
+//!
+//! Immortals can become invisible with `wizinvis`, to players below a trust
+//! level of their choosing. They are left out of act messages, room listings
+//! and `who` for them. Immortals trusted enough to see through it still need
+//! `holylight` on to do so, which keeps crowded rooms readable.
+//!
+//! Acting doesn't have access to accounts, so each player's trust and
+//! settings are copied into a `Sight` stored with their echoes, which is
+//! refreshed whenever one of them changes. `Sight::can_see()` is the one place
+//! that decides what can be seen.
+//!
+//! Act messages, room listings, `who`, and commands that look for a target by
+//! name all go through it: those who can't be seen are left out, or called
+//! "someone" when a message is about them. A new way of being unseen only
+//! needs to be added to `Sight`.
+
+use crate::{
+    accounts::Accounts,
+    acting::{Actor, Players},
+    agent::EntityAgent,
+    echo,
+    entity::EntityInfo,
+    trust::Trust,
+};
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Sight {
+    pub trust: Trust,
+    /// Players below this trust can't see this player
+    pub wizinvis: Option<Trust>,
+    pub holylight: bool,
+}
+
+impl Sight {
+    pub fn can_see(&self, target: &Sight) -> bool {
+        match target.wizinvis {
+            Some(level) => self.holylight && self.trust >= level,
+            None => true,
+        }
+    }
+}
+
+impl Players {
+    /// Get what a player can see and how they are seen; mobiles and objects
+    /// get the sight of a player without any settings.
+    pub fn sight(&self, actor: &dyn Actor) -> Sight {
+        actor
+            .player_name()
+            .and_then(|player| self.player_echoes.get(player))
+            .map(|player_echo| player_echo.sight)
+            .unwrap_or_default()
+    }
+
+    pub fn can_see(&self, viewer: &dyn Actor, target: &dyn Actor) -> bool {
+        self.sight(viewer).can_see(&self.sight(target))
+    }
+
+    /// A filter for entities that a viewer can see, to look for targets with
+    /// `find_seen_entity()`.
+    pub fn seen_by<'a>(
+        &'a self,
+        viewer: &'a dyn Actor,
+    ) -> impl for<'v> Fn(&EntityInfo<'v>) -> bool + Copy + 'a {
+        move |entity| self.can_see(viewer, entity)
+    }
+}
+
+/// Copy a player's trust and settings into their sight, after a change.
+pub(crate) fn refresh_sight(accounts: &Accounts, players: &mut Players, player: &str) {
+    let trust = accounts.trust(player);
+    let preferences = accounts.preferences(player);
+
+    if let Some(player_echo) = players.player_echoes.get_mut(player) {
+        player_echo.sight = Sight {
+            trust,
+            // Losing trust also lowers how invisible one can be
+            wizinvis: preferences.wizinvis.map(|level| level.min(trust)),
+            holylight: preferences.holylight,
+        };
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_wizinvis(&mut self, level: Option<&str>) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player,
+            None => {
+                echo!(self.info(), "Only players can become invisible.\r\n");
+                return;
+            }
+        };
+
+        let trust = self.accounts.trust(player);
+        let was_invisible = self.accounts.preferences(player).wizinvis.is_some();
+
+        let wizinvis = match level {
+            None if was_invisible => None,
+            None => Some(trust),
+            Some(level) => match Trust::from_name(level) {
+                Some(level) if level > Trust::Player && level <= trust => Some(level),
+                _ => {
+                    echo!(
+                        self.info(),
+                        "You can be invisible to players below builder, immortal or\r\n\
+                        implementor, up to your own trust level.\r\n"
+                    );
+                    return;
+                }
+            },
+        };
+
+        if !was_invisible {
+            let mut act = self.players.act_alone(&myself);
+            echo!(act.others(), "$^$n slowly fades into thin air.\r\n");
+        }
+
+        self.accounts.preferences_mut(player).wizinvis = wizinvis;
+        refresh_sight(self.accounts, self.players, player);
+
+        let mut act = self.players.act_alone(&myself);
+        match wizinvis {
+            Some(level) => echo!(
+                act.myself(),
+                "You are now invisible to players below `W{}`^.\r\n",
+                level.name()
+            ),
+            None => {
+                echo!(act.myself(), "You are visible again.\r\n");
+                echo!(act.others(), "$^$n slowly fades into existence.\r\n");
+            }
+        }
+    }
+
+    pub fn do_holylight(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player,
+            None => {
+                echo!(self.info(), "Only players can use holylight.\r\n");
+                return;
+            }
+        };
+
+        let preferences = self.accounts.preferences_mut(player);
+        preferences.holylight = !preferences.holylight;
+        let holylight = preferences.holylight;
+        refresh_sight(self.accounts, self.players, player);
+
+        if holylight {
+            echo!(self.info(), "Holy light fills your eyes.\r\n");
+        } else {
+            echo!(self.info(), "Your eyes return to normal.\r\n");
+        }
+    }
+}
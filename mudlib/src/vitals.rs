@@ -0,0 +1,350 @@
+//! Hit points, mana and moves, and getting them back over time.
+//!
+//! Players and mobiles regenerate every tick (a game hour). Resting gets
+//! more back than standing, and sleeping more still; rooms can have their
+//! own rates with `Heal` and `Mana` lines, for inns and temples. Being hungry
+//! or thirsty halves what comes back, and poison stops hit points from
+//! coming back at all, and takes some away instead.
+//!
+//! Players get hungry and thirsty as the hours go by; eating food and
+//! drinking from fountains (or flasks) fills them up again. Mobiles never
+//! do. Entities that were created before vitals existed get them on the
+//! next tick.
+
+use crate::{
+    agent::EntityAgent,
+    components::{Position, Vitals},
+    drunk::sober_up,
+    echo,
+    entity::EntityInfo,
+    find_entities::EntityIterator,
+    world::World,
+    WorldState,
+};
+
+/// Game hours that eating or drinking lasts at most
+const MAX_CONDITION: u8 = 48;
+/// Game hours a meal lasts
+const MEAL: u8 = 12;
+
+impl Vitals {
+    pub fn for_player() -> Self {
+        Vitals::with_maximums(100, 100, 100)
+    }
+
+    /// The vitals of a mobile, from the level of its template.
+    pub fn for_level(level: u32) -> Self {
+        let level = level.max(1);
+        Vitals::with_maximums(10 + 8 * level, 100 + 10 * level, 100 + 5 * level)
+    }
+
+    fn with_maximums(max_hit: u32, max_mana: u32, max_moves: u32) -> Self {
+        Vitals {
+            hit: max_hit,
+            max_hit,
+            mana: max_mana,
+            max_mana,
+            moves: max_moves,
+            max_moves,
+            position: Position::Standing,
+            hunger: MAX_CONDITION,
+            thirst: MAX_CONDITION,
+            poisoned: 0,
+            drunk: 0,
+        }
+    }
+}
+
+/// The vitals a player, or a mobile from a template, starts with.
+fn new_vitals(world: &World, is_player: bool, vnum: usize) -> Vitals {
+    if is_player {
+        return Vitals::for_player();
+    }
+
+    match world.mobiles.get(vnum) {
+        Some(template) if vnum != 0 && template.vnum.0 == vnum => Vitals::for_level(template.level),
+        _ => Vitals::for_level(1),
+    }
+}
+
+/// What comes back in a tick, out of a maximum: a tenth when standing, and
+/// more when resting or sleeping, scaled by the room's rate in percent.
+fn gain(max: u32, position: Position, rate: u32) -> u32 {
+    let percent = match position {
+        Position::Standing => 100,
+        Position::Resting => 150,
+        Position::Sleeping => 200,
+    };
+    (max / 10).max(1) * percent / 100 * rate / 100
+}
+
+/// The message for a player who is getting hungry or thirsty.
+fn condition_message(
+    hours: u8,
+    hungry: &'static str,
+    famished: &'static str,
+) -> Option<&'static str> {
+    match hours {
+        0 => Some(famished),
+        1..=3 => Some(hungry),
+        _ => None,
+    }
+}
+
+pub(crate) fn update_regeneration(world_state: &mut WorldState) {
+    let world = &world_state.world;
+    let mut messages = Vec::new();
+
+    let living: Vec<_> = world_state
+        .entity_world
+        .all_entities()
+        .filter(|entity| entity.is_player() || entity.is_mobile())
+        .map(|entity| {
+            (
+                entity.entity_id(),
+                entity.is_player(),
+                entity.components().general.vnum.0,
+                entity.room().components().general.vnum.0,
+            )
+        })
+        .collect();
+
+    for (entity_id, is_player, vnum, room_vnum) in living {
+        let (heal_rate, mana_rate) = match world.rooms.get(room_vnum) {
+            Some(room) if room_vnum != 0 && room.vnum.0 == room_vnum => {
+                (room.heal_rate.unwrap_or(100), room.mana_rate.unwrap_or(100))
+            }
+            _ => (100, 100),
+        };
+
+        let mut entity = world_state.entity_world.entity_info_mut(entity_id);
+        let vitals = entity
+            .components()
+            .vitals
+            .get_or_insert_with(|| new_vitals(world, is_player, vnum));
+
+        let mut divisor = 1;
+        if is_player {
+            vitals.hunger = vitals.hunger.saturating_sub(1);
+            vitals.thirst = vitals.thirst.saturating_sub(1);
+
+            let hunger =
+                condition_message(vitals.hunger, "You are getting hungry.", "You are hungry.");
+            let thirst = condition_message(
+                vitals.thirst,
+                "You are getting thirsty.",
+                "You are thirsty.",
+            );
+            for message in hunger.into_iter().chain(thirst) {
+                messages.push((entity_id, message));
+            }
+
+            if vitals.hunger == 0 {
+                divisor *= 2;
+            }
+            if vitals.thirst == 0 {
+                divisor *= 2;
+            }
+        }
+
+        if let Some(message) = sober_up(vitals) {
+            messages.push((entity_id, message));
+        }
+
+        if vitals.poisoned > 0 {
+            vitals.poisoned -= 1;
+            vitals.hit = vitals
+                .hit
+                .saturating_sub((vitals.max_hit / 20).max(1))
+                .max(1);
+            messages.push((
+                entity_id,
+                if vitals.poisoned == 0 {
+                    "You feel better."
+                } else {
+                    "You shiver and suffer."
+                },
+            ));
+        } else {
+            let hit = gain(vitals.max_hit, vitals.position, heal_rate) / divisor;
+            vitals.hit = (vitals.hit + hit).min(vitals.max_hit);
+        }
+
+        let mana = gain(vitals.max_mana, vitals.position, mana_rate) / divisor;
+        vitals.mana = (vitals.mana + mana).min(vitals.max_mana);
+        let moves = gain(vitals.max_moves, vitals.position, heal_rate) / divisor;
+        vitals.moves = (vitals.moves + moves).min(vitals.max_moves);
+    }
+
+    for (entity_id, message) in messages {
+        let entity = world_state.entity_world.entity_info(entity_id);
+        echo!(world_state.players.info(&entity), "{}\r\n", message);
+    }
+}
+
+/// The position of an entity, if it has vitals.
+pub(crate) fn position(entity: &EntityInfo) -> Option<Position> {
+    entity
+        .components()
+        .vitals
+        .as_ref()
+        .map(|vitals| vitals.position)
+}
+
+/// What to tell an entity that needs to stand up before doing something, if
+/// it isn't standing.
+pub(crate) fn not_standing(entity: &EntityInfo) -> Option<&'static str> {
+    match position(entity) {
+        Some(Position::Resting) => Some("You need to stand up first."),
+        Some(Position::Sleeping) => Some("In your dreams, or what?"),
+        _ => None,
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    fn set_position(&mut self, position: Position) {
+        let mut myself = self.entity_world.entity_info_mut(self.entity_id);
+        if let Some(vitals) = &mut myself.components().vitals {
+            vitals.position = position;
+        }
+    }
+
+    /// Change position, with a message unless already there.
+    fn change_position(
+        &mut self,
+        new_position: Position,
+        already: &str,
+        to_myself: &str,
+        to_others: &str,
+    ) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if !myself.is_player() && !myself.is_mobile() {
+            echo!(self.info(), "Only players and mobiles can do that.\r\n");
+            return;
+        }
+
+        if myself.components().vitals.is_none() {
+            let vitals = new_vitals(
+                self.world,
+                myself.is_player(),
+                myself.components().general.vnum.0,
+            );
+            let mut myself = self.entity_world.entity_info_mut(self.entity_id);
+            myself.components().vitals = Some(vitals);
+        }
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        if position(&myself) == Some(new_position) {
+            echo!(self.info(), "{}\r\n", already);
+            return;
+        }
+
+        let mut act = self.players.act_alone(&myself);
+        echo!(act.myself(), "{}\r\n", to_myself);
+        echo!(act.others(), "{}\r\n", to_others);
+        self.set_position(new_position);
+    }
+
+    pub fn do_rest(&mut self) {
+        self.change_position(
+            Position::Resting,
+            "You are already resting.",
+            "You sit down and rest.",
+            "$^$n sits down and rests.",
+        );
+    }
+
+    pub fn do_sleep(&mut self) {
+        self.change_position(
+            Position::Sleeping,
+            "You are already asleep.",
+            "You lie down and go to sleep.",
+            "$^$n lies down and goes to sleep.",
+        );
+    }
+
+    pub fn do_stand(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let (to_myself, to_others) = match position(&myself) {
+            Some(Position::Sleeping) => ("You wake and stand up.", "$^$n wakes and stands up."),
+            _ => ("You stand up.", "$^$n stands up."),
+        };
+        self.change_position(
+            Position::Standing,
+            "You are already standing.",
+            to_myself,
+            to_others,
+        );
+    }
+
+    /// Fill up the current entity after eating something.
+    pub(crate) fn eat_meal(&mut self) {
+        let mut myself = self.entity_world.entity_info_mut(self.entity_id);
+        let full = match &mut myself.components().vitals {
+            Some(vitals) => {
+                vitals.hunger = vitals.hunger.saturating_add(MEAL).min(MAX_CONDITION);
+                vitals.hunger == MAX_CONDITION
+            }
+            None => false,
+        };
+
+        if full {
+            echo!(self.info(), "You are full.\r\n");
+        }
+    }
+
+    pub fn do_drink(&mut self, item_name: Option<&str>) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let is_drink = |entity: &EntityInfo| {
+            entity
+                .components()
+                .object
+                .as_ref()
+                .map(|object| object.drink)
+                .unwrap_or(false)
+        };
+
+        let drink = match item_name {
+            Some(item_name) => {
+                let found = myself
+                    .objects()
+                    .chain(myself.room().objects())
+                    .filter_by_keyword(item_name)
+                    .filter_or(is_drink, "You can't drink from $N.")
+                    .find_one_or("You don't see anything named like that to drink from.");
+                match found {
+                    Ok(drink) => drink,
+                    Err(error) => return self.echo_error(error),
+                }
+            }
+            None => {
+                let found = myself.room().objects().find(is_drink);
+                match found {
+                    Some(drink) => drink,
+                    None => {
+                        echo!(self.info(), "Drink what?\r\n");
+                        return;
+                    }
+                }
+            }
+        };
+
+        let liquid = drink
+            .components()
+            .object
+            .as_ref()
+            .and_then(|object| object.liquid.clone());
+
+        let mut act = self.players.act_with(&myself, &drink);
+        echo!(act.all(), "$^$n $[drink|drinks] from $N.\r\n");
+
+        let mut myself = self.entity_world.entity_info_mut(self.entity_id);
+        if let Some(vitals) = &mut myself.components().vitals {
+            vitals.thirst = MAX_CONDITION;
+        }
+
+        if let Some(liquid) = liquid {
+            self.drink_alcohol(&liquid);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Builder commands to look through the world's templates: `ofind`, `mfind`
+//! and `rfind` list the vnums of objects, mobiles and rooms whose keywords or
+//! names contain some text, and `vnum gap <area>` shows which vnums of an area
+//! are still free.
+//!
+//! They search `WorldState::world`, which is what `asave` writes, so they
+//! include changes that aren't saved yet.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    agent::EntityAgent,
+    echo,
+    world::{Vnum, World},
+};
+
+/// More results than this are probably a search that was too broad
+const MAX_RESULTS: usize = 100;
+
+#[derive(Clone, Copy)]
+pub(crate) enum TemplateKind {
+    Object,
+    Mobile,
+    Room,
+}
+
+impl TemplateKind {
+    fn name(self) -> &'static str {
+        match self {
+            TemplateKind::Object => "object",
+            TemplateKind::Mobile => "mobile",
+            TemplateKind::Room => "room",
+        }
+    }
+
+    fn command(self) -> &'static str {
+        match self {
+            TemplateKind::Object => "ofind",
+            TemplateKind::Mobile => "mfind",
+            TemplateKind::Room => "rfind",
+        }
+    }
+
+    /// Vnums in use, with the words to search, the name to show, and the
+    /// short name of their area.
+    fn templates(self, world: &World) -> Vec<(usize, String, &str, &str)> {
+        let used = |vnum: Vnum| vnum.0 != 0;
+
+        match self {
+            TemplateKind::Object => world
+                .objects
+                .iter()
+                .filter(|object| used(object.vnum))
+                .map(|object| {
+                    let words = format!("{} {}", object.name, object.short_description);
+                    (
+                        object.vnum.0,
+                        words,
+                        object.short_description.as_str(),
+                        object.area.as_str(),
+                    )
+                })
+                .collect(),
+            TemplateKind::Mobile => world
+                .mobiles
+                .iter()
+                .filter(|mobile| used(mobile.vnum))
+                .map(|mobile| {
+                    let words = format!("{} {}", mobile.name, mobile.short_description);
+                    (
+                        mobile.vnum.0,
+                        words,
+                        mobile.short_description.as_str(),
+                        mobile.area.as_str(),
+                    )
+                })
+                .collect(),
+            TemplateKind::Room => world
+                .rooms
+                .iter()
+                .filter(|room| used(room.vnum))
+                .map(|room| {
+                    (
+                        room.vnum.0,
+                        room.name.clone(),
+                        room.name.as_str(),
+                        room.area.as_str(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Turn a sorted list of vnums into ranges like "100-120, 130".
+fn format_ranges(vnums: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for &vnum in vnums {
+        match ranges.last_mut() {
+            Some((_first, last)) if *last + 1 == vnum => *last = vnum,
+            _ => ranges.push((vnum, vnum)),
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|(first, last)| {
+            if first == last {
+                first.to_string()
+            } else {
+                format!("{}-{}", first, last)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_find_vnums(&mut self, kind: TemplateKind, text: &str) {
+        let text = text.trim().to_lowercase();
+        if text.is_empty() {
+            echo!(self.info(), "Syntax: '`W{} <text>`^'.\r\n", kind.command());
+            return;
+        }
+
+        let found: Vec<(usize, String)> = kind
+            .templates(self.world)
+            .into_iter()
+            .filter(|(_vnum, words, _name, _area)| words.to_lowercase().contains(&text))
+            .map(|(vnum, _words, name, area)| {
+                let area = if area.is_empty() { "no area" } else { area };
+                (vnum, format!("{} `D({})`^", name, area))
+            })
+            .collect();
+
+        let mut info = self.info();
+        if found.is_empty() {
+            echo!(info, "No {} matches that.\r\n", kind.name());
+            return;
+        }
+
+        for (vnum, description) in found.iter().take(MAX_RESULTS) {
+            echo!(info, "`W{:>6}`^ {}\r\n", vnum, description);
+        }
+        if found.len() > MAX_RESULTS {
+            echo!(
+                info,
+                "...and {} more; try a longer search.\r\n",
+                found.len() - MAX_RESULTS
+            );
+        }
+    }
+
+    pub fn do_vnum_gap(&mut self, area_name: &str) {
+        let index = match self.find_area(area_name) {
+            Some(index) => index,
+            None => {
+                echo!(self.info(), "There is no area named like that.\r\n");
+                return;
+            }
+        };
+
+        let area = &self.world.areas[index].0;
+        let (first, last) = (area.vnums.0 .0, area.vnums.1 .0);
+        if (first, last) == (0, 0) {
+            echo!(self.info(), "That area has no vnums yet.\r\n");
+            return;
+        }
+        let short_name = area.short_name.clone();
+
+        let mut lines = Vec::new();
+        for kind in [
+            TemplateKind::Room,
+            TemplateKind::Object,
+            TemplateKind::Mobile,
+        ] {
+            let used: BTreeSet<usize> = kind
+                .templates(self.world)
+                .into_iter()
+                .map(|(vnum, _words, _name, _area)| vnum)
+                .collect();
+            let free: Vec<usize> = (first..=last).filter(|vnum| !used.contains(vnum)).collect();
+
+            let ranges = if free.is_empty() {
+                "none".to_string()
+            } else {
+                format_ranges(&free)
+            };
+            lines.push(format!("Free {} vnums: {}\r\n", kind.name(), ranges));
+        }
+
+        let mut info = self.info();
+        echo!(info, "Vnums of {}: {} to {}.\r\n", short_name, first, last);
+        for line in lines {
+            echo!(info, "{}", line);
+        }
+    }
+}
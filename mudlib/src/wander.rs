@@ -0,0 +1,103 @@
+//! Mobiles wandering around on their own.
+//!
+//! Every mobile pulse, each mobile that wanders has a small chance to leave
+//! through a random exit, like in ROM. Only open exits to other rooms are
+//! taken, and never into rooms with the `no_mob` room flag. Mobiles with the
+//! `stay_area` act flag keep to their own area, and only those that are
+//! `flying` (an `AffBy` flag) go through the air or over deep water.
+//!
+//! Sentinels (`dont_wander`), shopkeepers, pets and mobiles following
+//! someone stay where they are, and hunters and mobiles walking somewhere
+//! only go where they are headed.
+
+use crate::{
+    agent::EntityAgent,
+    components::{GeneralData, Mobile},
+    entity::EntityInfo,
+    terrain::{needs_boat, needs_flight},
+    world::World,
+    WorldState,
+};
+
+/// Whether a mobile that is in one room can wander into another.
+pub(crate) fn can_wander_to(
+    world: &World,
+    mobile: &EntityInfo,
+    from: &EntityInfo,
+    to: &EntityInfo,
+) -> bool {
+    let (stay_area, flying) = match &mobile.components().mobile {
+        Some(mobile) => (mobile.stay_area, mobile.flying),
+        None => return false,
+    };
+    let general = &to.components().general;
+
+    if stay_area && general.area != from.components().general.area {
+        return false;
+    }
+
+    if !flying {
+        if let Some(sector) = &general.sector {
+            if needs_flight(sector) || needs_boat(sector) {
+                return false;
+            }
+        }
+    }
+
+    let vnum = general.vnum.0;
+    match world.rooms.get(vnum) {
+        Some(room) if vnum != 0 && room.vnum.0 == vnum => !room.no_mob,
+        _ => true,
+    }
+}
+
+pub(crate) fn update_wander(world_state: &mut WorldState) {
+    let entity_world = &world_state.entity_world;
+    let world = &world_state.world;
+    let rng = &mut world_state.rng;
+    let mut wanderers = Vec::new();
+
+    for (entity, (mobile, general)) in entity_world.query::<(&Mobile, &GeneralData)>() {
+        let wander = mobile.wander
+            && mobile.shopkeeper.is_none()
+            && mobile.pet_of.is_none()
+            && mobile.hunting == 0
+            && mobile.walk_to.is_none();
+
+        if !wander || general.following.is_some() || !rng.one_in_bits(4) {
+            continue;
+        }
+
+        let room = entity.room();
+        let exits: Vec<String> = room
+            .exits()
+            .filter(|exit| {
+                exit.components()
+                    .door
+                    .as_ref()
+                    .map(|door| !door.closed)
+                    .unwrap_or(true)
+            })
+            .filter(|exit| match exit.leads_to() {
+                Some(to_room_id) if to_room_id != room.entity_id() => {
+                    let to_room = entity_world.entity_info(to_room_id);
+                    can_wander_to(world, &entity, &room, &to_room)
+                }
+                _ => false,
+            })
+            .map(|exit| exit.main_keyword().to_string())
+            .collect();
+
+        if exits.is_empty() {
+            continue;
+        }
+
+        let exit = rng.range(0..exits.len());
+        wanderers.push((entity.entity_id(), exits[exit].clone()));
+    }
+
+    for (wanderer_id, exit) in wanderers {
+        let mut agent = EntityAgent::new(world_state, wanderer_id);
+        agent.do_move(&exit);
+    }
+}
@@ -0,0 +1,229 @@
+//! Weather, which changes every game hour in each area on its own.
+//!
+//! Like in Merc and its descendants, each area has a barometric pressure that
+//! drifts up and down, around a usual pressure that depends on the season:
+//! high in summer, and low in autumn and winter. The sky follows it, from
+//! cloudless to cloudy, rain (or snow, in winter) and lightning storms, and
+//! players outdoors in the area see it change. The `weather` command
+//! describes the sky above, and the moon on clear nights.
+//!
+//! Weather isn't saved; a reboot starts every area with new weather.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    agent::EntityAgent,
+    clock::{is_daylight, GameTime, MoonPhase, Season},
+    echo,
+    entity::EntityInfo,
+    rng::GameRng,
+    world::World,
+    WorldState,
+};
+
+const MIN_PRESSURE: i32 = 960;
+const MAX_PRESSURE: i32 = 1040;
+const MAX_CHANGE: i32 = 12;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Sky {
+    Cloudless,
+    Cloudy,
+    Raining,
+    Lightning,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct AreaWeather {
+    pub sky: Sky,
+    /// Barometric pressure, in millimeters of mercury
+    pub pressure: i32,
+    /// How much the pressure changed in the last hour
+    pub change: i32,
+}
+
+pub(crate) struct Weather {
+    /// Weather of each area, by area name
+    areas: BTreeMap<String, AreaWeather>,
+}
+
+/// Whether a room is outdoors, where the sky can be seen.
+pub(crate) fn is_outdoors(room: &EntityInfo) -> bool {
+    match &room.components().general.sector {
+        Some(sector) => !sector.is_empty() && &**sector != "inside",
+        None => false,
+    }
+}
+
+/// One chance in four.
+fn one_in_four(rng: &mut GameRng) -> bool {
+    rng.range(0..4) == 0
+}
+
+impl AreaWeather {
+    fn new(season: Season, rng: &mut GameRng) -> Self {
+        let pressure = match season {
+            Season::Autumn | Season::Winter => MIN_PRESSURE + rng.range(1..=50),
+            Season::Spring | Season::Summer => MIN_PRESSURE + rng.range(1..=80),
+        };
+
+        let sky = match pressure {
+            pressure if pressure <= 980 => Sky::Lightning,
+            pressure if pressure <= 1000 => Sky::Raining,
+            pressure if pressure <= 1020 => Sky::Cloudy,
+            _ => Sky::Cloudless,
+        };
+
+        AreaWeather {
+            sky,
+            pressure,
+            change: 0,
+        }
+    }
+
+    /// Let an hour go by, and return what players outdoors see if the sky
+    /// changes.
+    fn update(&mut self, season: Season, rng: &mut GameRng) -> Option<&'static str> {
+        let winter = season == Season::Winter;
+
+        let usual = match season {
+            Season::Winter => 985,
+            Season::Spring => 1005,
+            Season::Summer => 1020,
+            Season::Autumn => 990,
+        };
+        let diff = if self.pressure > usual { -2 } else { 2 };
+
+        self.change += diff * rng.dice(1, 4) + rng.dice(2, 6) - rng.dice(2, 6);
+        self.change = self.change.clamp(-MAX_CHANGE, MAX_CHANGE);
+        self.pressure = (self.pressure + self.change).clamp(MIN_PRESSURE, MAX_PRESSURE);
+
+        let pressure = self.pressure;
+        let (sky, message) = match self.sky {
+            Sky::Cloudless if pressure < 990 || (pressure < 1010 && one_in_four(rng)) => {
+                (Sky::Cloudy, "The sky is getting cloudy.")
+            }
+            Sky::Cloudy if pressure < 970 || (pressure < 990 && one_in_four(rng)) => (
+                Sky::Raining,
+                if winter {
+                    "It starts to snow."
+                } else {
+                    "It starts to rain."
+                },
+            ),
+            Sky::Cloudy if pressure > 1030 && one_in_four(rng) => {
+                (Sky::Cloudless, "The clouds disappear.")
+            }
+            Sky::Raining if pressure < 970 && one_in_four(rng) => {
+                (Sky::Lightning, "Lightning flashes in the sky.")
+            }
+            Sky::Raining if pressure > 1030 || (pressure > 1010 && one_in_four(rng)) => (
+                Sky::Cloudy,
+                if winter {
+                    "The snow stops."
+                } else {
+                    "The rain stops."
+                },
+            ),
+            Sky::Lightning if pressure > 1010 || (pressure > 990 && one_in_four(rng)) => {
+                (Sky::Raining, "The lightning has stopped.")
+            }
+            _ => return None,
+        };
+
+        self.sky = sky;
+        Some(message)
+    }
+
+    fn describe(&self, time: GameTime) -> String {
+        let sky = match self.sky {
+            Sky::Cloudless => "cloudless",
+            Sky::Cloudy => "cloudy",
+            Sky::Raining if time.season() == Season::Winter => "white with falling snow",
+            Sky::Raining => "rainy",
+            Sky::Lightning => "lit by flashes of lightning",
+        };
+        let wind = if self.change >= 0 {
+            "a warm southerly breeze blows"
+        } else {
+            "a cold northern gust blows"
+        };
+
+        let moon = match time.moon_phase() {
+            _ if is_daylight(time.hour) || self.sky != Sky::Cloudless => String::new(),
+            MoonPhase::New => " There is no moon tonight.".to_string(),
+            phase => format!(" The moon is {}.", phase.describe()),
+        };
+
+        format!("The sky is {} and {}.{}", sky, wind, moon)
+    }
+}
+
+impl Weather {
+    pub fn new(world: &World, season: Season, rng: &mut GameRng) -> Self {
+        let mut weather = Weather {
+            areas: BTreeMap::new(),
+        };
+        weather.add_new_areas(world, season, rng);
+        weather
+    }
+
+    /// Start the weather of areas that don't have any yet, like ones just
+    /// created with `aedit`.
+    fn add_new_areas(&mut self, world: &World, season: Season, rng: &mut GameRng) {
+        for (area, _resets) in &world.areas {
+            self.areas
+                .entry(area.short_name.clone())
+                .or_insert_with(|| AreaWeather::new(season, rng));
+        }
+    }
+
+    /// The weather in an area, if it has any.
+    pub fn in_area(&self, area: &str) -> Option<&AreaWeather> {
+        self.areas.get(area)
+    }
+}
+
+/// Change the weather of each area, and tell players outdoors about it.
+pub(crate) fn update_weather(world_state: &mut WorldState) {
+    let season = world_state.clock.now().season();
+    world_state
+        .weather
+        .add_new_areas(&world_state.world, season, &mut world_state.rng);
+
+    let mut messages = BTreeMap::new();
+    for (area, weather) in world_state.weather.areas.iter_mut() {
+        if let Some(message) = weather.update(season, &mut world_state.rng) {
+            messages.insert(area.clone(), message);
+        }
+    }
+
+    for player in world_state.entity_world.all_players() {
+        let room = player.room();
+        if !is_outdoors(&room) {
+            continue;
+        }
+
+        if let Some(message) = messages.get(&*room.components().general.area) {
+            echo!(world_state.players.info(&player), "{}\r\n", message);
+        }
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_weather(&mut self) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let room = myself.room();
+
+        if !is_outdoors(&room) {
+            echo!(self.info(), "You can't see the weather indoors.\r\n");
+            return;
+        }
+
+        let description = match self.weather.in_area(&room.components().general.area) {
+            Some(weather) => weather.describe(self.clock.now()),
+            None => "There is no weather here.".to_string(),
+        };
+        echo!(self.info(), "{}\r\n", description);
+    }
+}
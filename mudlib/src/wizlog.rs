@@ -0,0 +1,183 @@
+//! The immortal log: noteworthy things that happen in the realm, so that
+//! immortals can keep an eye on players, mobprogs and each other.
+//!
+//! Everything logged is written to the server log, and shown to immortals
+//! trusted with the `log` command. They can turn each kind of event off or on
+//! with `log <kind>`; which kinds are off is kept in their preferences.
+
+use crate::{
+    agent::EntityAgent, colors::decolorize, echo, event_log::EventKind, state::WorldState,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LogKind {
+    Admin,
+    Death,
+    Login,
+    Command,
+    Script,
+}
+
+static LOG_KINDS: &[(LogKind, &str, &str)] = &[
+    (
+        LogKind::Admin,
+        "admin",
+        "What immortals do with commands like force",
+    ),
+    (LogKind::Death, "deaths", "Players dying"),
+    (
+        LogKind::Login,
+        "logins",
+        "Logins, and players on new addresses",
+    ),
+    (
+        LogKind::Command,
+        "commands",
+        "Players trying commands above their trust",
+    ),
+    (LogKind::Script, "scripts", "Mobprogs that run into errors"),
+];
+
+impl LogKind {
+    fn name(self) -> &'static str {
+        LOG_KINDS
+            .iter()
+            .find(|(kind, _name, _description)| *kind == self)
+            .map(|(_kind, name, _description)| *name)
+            .expect("All kinds have a name")
+    }
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    /// Write something to the server log, and tell immortals about it.
+    pub fn log_to_immortals(&mut self, kind: LogKind, message: &str) {
+        println!("{}", decolorize(message));
+
+        // Commands are all in the event log already, and scripts aren't
+        // something players do
+        let event_kind = match kind {
+            LogKind::Admin => Some(EventKind::Admin),
+            LogKind::Death => Some(EventKind::Death),
+            LogKind::Login => Some(EventKind::Login),
+            LogKind::Command | LogKind::Script => None,
+        };
+        if let Some(event_kind) = event_kind {
+            self.record_event(event_kind, message);
+        }
+
+        for listener in self.entity_world.all_players() {
+            let listener_name = listener.player_name().expect("Is a player");
+
+            let link_dead = self
+                .players
+                .player_echoes
+                .get(listener_name)
+                .map(|player_echo| player_echo.link_dead)
+                .unwrap_or(true);
+
+            if listener.entity_id() == self.entity_id
+                || link_dead
+                || self.accounts.trust(listener_name) < self.command_trust.required("log")
+                || self
+                    .accounts
+                    .preferences(listener_name)
+                    .log_off
+                    .contains(kind.name())
+            {
+                continue;
+            }
+
+            echo!(
+                self.players.info(&listener),
+                "`Y[Log: {}] {}`^\r\n",
+                kind.name(),
+                message
+            );
+        }
+    }
+
+    pub fn do_log(&mut self, words: &[&str]) {
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let player = match myself.player_name() {
+            Some(player) => player.to_string(),
+            None => {
+                echo!(self.info(), "Only players can read the log.\r\n");
+                return;
+            }
+        };
+
+        match words {
+            [] => {
+                let log_off = self.accounts.preferences(&player).log_off.clone();
+                let mut info = self.info();
+
+                echo!(info, "Events in the log:\r\n");
+                for (_kind, name, description) in LOG_KINDS {
+                    echo!(
+                        info,
+                        "  `W{:<9}`^ {} - {}\r\n",
+                        name,
+                        if log_off.contains(*name) {
+                            "`Roff`^"
+                        } else {
+                            "`Gon `^"
+                        },
+                        description
+                    );
+                }
+                echo!(info, "Use '`Wlog <kind>`^' to turn a kind on or off.\r\n");
+            }
+            [name] => {
+                let name = match LOG_KINDS
+                    .iter()
+                    .find(|(_kind, kind_name, _description)| kind_name.eq_ignore_ascii_case(name))
+                {
+                    Some((_kind, name, _description)) => *name,
+                    None => {
+                        echo!(
+                            self.info(),
+                            "There is no such kind of event; use '`Wlog`^' to list them.\r\n"
+                        );
+                        return;
+                    }
+                };
+
+                let log_off = &mut self.accounts.preferences_mut(&player).log_off;
+                let now_on = log_off.remove(name);
+                if !now_on {
+                    log_off.insert(name.to_string());
+                }
+
+                echo!(
+                    self.info(),
+                    "Logging of `W{}`^ is now {}.\r\n",
+                    name,
+                    if now_on { "`Gon`^" } else { "`Roff`^" }
+                );
+            }
+            _ => echo!(self.info(), "Syntax: '`Wlog`^' or '`Wlog <kind>`^'.\r\n"),
+        }
+    }
+}
+
+impl WorldState {
+    /// Log a player who just logged in, noting if it's from a new address.
+    #[cfg(feature = "net")]
+    pub(crate) fn log_login(&mut self, player: &str, address: &str, new_character: bool) {
+        let new_address = self.accounts.remember_address(player, address);
+
+        let message = if new_character {
+            format!("New character {} logged in from {}.", player, address)
+        } else if new_address {
+            format!("{} logged in from a new address, {}.", player, address)
+        } else {
+            format!("{} logged in from {}.", player, address)
+        };
+
+        let player_id = self
+            .entity_world
+            .player_entity_id(player)
+            .expect("Players have a body once logged in");
+        EntityAgent::new(self, player_id).log_to_immortals(LogKind::Login, &message);
+    }
+}
@@ -1,14 +1,28 @@
-//! Read-only representation of a set of Dawn of Time areas.
+//! Representation of a set of Dawn of Time areas.
 //!
 //! The types here mostly correspond to how they are serialized in the area
-//! files.
+//! files. They are read by `crate::load`, and written back by `crate::save`
+//! after areas were edited in the game.
 //!
 //! Not everything is loaded from area files yet; a lot of properties are
-//! missing because they were not yet needed.
+//! missing because they were not yet needed. Saving an area loses them.
 
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::files::Files;
+use crate::{
+    archive::ArchiveFiles,
+    circle::{load_circle_zone, read_circle_zone, CircleZone},
+    components::Wealth,
+    config::{AreaSource, WorldConfig},
+    file_parser::ParseResult,
+    files::Files,
+    load::apply_source,
+    parallel::on_threads,
+    procgen::{generate_area, DUNGEON_AREA},
+    rng::GameRng,
+    socials::Social,
+};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
 #[serde(transparent)]
@@ -19,12 +33,41 @@ pub(super) struct Room {
     pub(super) vnum: Vnum,
     pub(super) name: String,
     pub(super) description: String,
+    /// Seen instead of the description at night
+    #[serde(default)]
+    pub(super) night_description: Option<String>,
     pub(super) sector: String,
+    /// Pets are sold here, from the room with the next vnum
+    #[serde(default)]
+    pub(super) pet_shop: bool,
+    /// Players can rent this room to sell things
+    #[serde(default)]
+    pub(super) stall: bool,
+    /// Mobiles don't wander in here
+    #[serde(default)]
+    pub(super) no_mob: bool,
+    /// Players can't recall from here
+    #[serde(default)]
+    pub(super) no_recall: bool,
+    /// Room flags the game has no use for yet, like "dark" or "safe", kept
+    /// so that saving the area doesn't lose them
+    #[serde(default)]
+    pub(super) flags: Vec<String>,
+    /// Percent of the usual hit point and move regeneration, like in inns
+    #[serde(default)]
+    pub(super) heal_rate: Option<u32>,
+    /// Percent of the usual mana regeneration
+    #[serde(default)]
+    pub(super) mana_rate: Option<u32>,
 
     #[serde(default)]
     pub(super) exits: Vec<Exit>,
     #[serde(default)]
     pub(super) extra_descriptions: Vec<ExtraDescription>,
+    /// Keys of the area file the game has no use for yet, with their
+    /// values, kept so that saving or exporting the area doesn't lose them
+    #[serde(default)]
+    pub(super) extras: Vec<(String, String)>,
 
     #[serde(skip)]
     pub(super) area: String,
@@ -49,32 +92,54 @@ pub(super) struct ExtraDescription {
     pub(super) description: String,
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
 pub(super) enum Gender {
     Male,
     Female,
+    #[default]
     Neutral,
 }
 
-impl Default for Gender {
-    fn default() -> Self {
-        Gender::Neutral
-    }
-}
-
 #[derive(Serialize, Deserialize, Clone, Default)]
 pub(super) struct Mobile {
     pub(super) vnum: Vnum,
     pub(super) name: String,
     pub(super) short_description: String,
     pub(super) long_description: String,
+    /// Seen in the room instead of the long description at night
+    #[serde(default)]
+    pub(super) night_long_description: Option<String>,
     pub(super) description: String,
 
     pub(super) mobprog_triggers: Vec<(MobProgTrigger, Vnum)>,
     pub(super) gender: Gender,
     pub(super) area: String,
+    pub(super) level: u32,
     pub(super) sentinel: bool,
+    /// Never wanders out of its own area
+    #[serde(default)]
+    pub(super) stay_area: bool,
+    /// Can wander through the air, and over deep water
+    #[serde(default)]
+    pub(super) flying: bool,
+    /// Picks up valuable things lying around
+    #[serde(default)]
+    pub(super) scavenger: bool,
+    #[serde(default)]
+    pub(super) aggressive: bool,
     pub(super) unseen: bool,
+    pub(super) postmaster: bool,
+    pub(super) banker: bool,
+    /// Mends worn weapons and armor
+    #[serde(default)]
+    pub(super) repairer: bool,
+    /// Coins it carries when it is spawned
+    #[serde(default)]
+    pub(super) wealth: Wealth,
+    /// Keys of the area file the game has no use for yet, with their
+    /// values, kept so that saving or exporting the area doesn't lose them
+    #[serde(default)]
+    pub(super) extras: Vec<(String, String)>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -87,14 +152,25 @@ pub(super) struct Object {
     pub(super) cost: i32,
     pub(super) item_type: String,
     pub(super) flags: ObjectFlags,
+    /// Can be picked up
+    #[serde(default)]
+    pub(super) take: bool,
+    /// Where it is worn, as a wear flag like "body" or "wield"
+    #[serde(default)]
+    pub(super) wear: Option<String>,
 
     #[serde(default)]
     pub(super) extra_descriptions: Vec<ExtraDescription>,
+    /// Keys of the area file the game has no use for yet, with their
+    /// values, kept so that saving or exporting the area doesn't lose them
+    #[serde(default)]
+    pub(super) extras: Vec<(String, String)>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub(super) enum ObjectFlags {
     /// Not yet implemented for all object types
+    #[default]
     Unknown,
 
     /// Object is a container
@@ -103,12 +179,26 @@ pub(super) enum ObjectFlags {
         closed: bool,
         locked: bool,
     },
+
+    /// Object gives light while it is lit
+    Light {
+        /// Hours of oil it holds when filled up, or 0 if it can't be refilled
+        capacity: i32,
+        /// Hours it burns for, or -1 (or 999) for ever
+        hours: i32,
+    },
+
+    /// Object holds a liquid, like a fountain or a flask
+    Liquid { liquid: String },
 }
 
-impl Default for ObjectFlags {
-    fn default() -> Self {
-        ObjectFlags::Unknown
-    }
+/// A mobile that players may run into in rooms of some sector
+#[derive(Serialize, Deserialize, Clone)]
+pub(super) struct Encounter {
+    pub(super) sector: String,
+    pub(super) vnum: Vnum,
+    /// How many of the mobile can be in the world at once
+    pub(super) limit: u16,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -119,6 +209,99 @@ pub(super) struct AreaData {
     pub(super) vnums: (Vnum, Vnum),
     pub(super) credits: String,
     pub(super) continent: String,
+    /// Names of the players allowed to build in this area
+    pub(super) builders: String,
+    /// How trusted builders must be to build in this area, from 0 to 9 as in
+    /// ROM areas; see `Trust::security()`
+    #[serde(default)]
+    pub(super) security: u8,
+    /// Minutes between area resets
+    pub(super) reset_interval: u16,
+    /// Mobiles that players may run into while travelling through the area
+    #[serde(default)]
+    pub(super) encounters: Vec<Encounter>,
+
+    /// Name of the area file, without its directory
+    #[serde(skip)]
+    pub(super) file_name: String,
+    /// Where the area file is, if it isn't with the other areas; its vnums
+    /// and short name were changed when it was loaded
+    #[serde(skip)]
+    pub(super) source: Option<AreaSource>,
+    /// Whether the area file exists and is in the area list
+    #[serde(skip)]
+    pub(super) on_disk: bool,
+    /// Whether the area was changed since it was last saved
+    #[serde(skip)]
+    pub(super) changed: bool,
+    /// Format of the area file, as told from its first lines
+    #[serde(skip)]
+    pub(super) format: AreaFormat,
+}
+
+/// Formats of the area files that can be loaded, each with its own parser
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub(super) enum AreaFormat {
+    #[default]
+    DawnOfTime,
+    Rom,
+    Smaug,
+    Circle,
+    Json,
+    Toml,
+    /// Not loaded from a file, like a generated dungeon
+    Generated,
+}
+
+impl AreaFormat {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            AreaFormat::DawnOfTime => "Dawn of Time",
+            AreaFormat::Rom => "ROM",
+            AreaFormat::Smaug => "SMAUG",
+            AreaFormat::Circle => "CircleMUD",
+            AreaFormat::Json => "JSON",
+            AreaFormat::Toml => "TOML",
+            AreaFormat::Generated => "generated",
+        }
+    }
+
+    /// Tell the format of an area file from its first lines, whatever its
+    /// name; see `crate::load::load_area`.
+    pub(super) fn detect(contents: &str) -> Self {
+        let first_word = contents.split_whitespace().next().unwrap_or_default();
+        // TOML comments start with "# ", sections of area files with "#NAME"
+        let first_line = contents
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && *line != "#" && !line.starts_with("# "))
+            .unwrap_or_default();
+        if first_word.starts_with('{') {
+            AreaFormat::Json
+        } else if first_line.starts_with('[') {
+            AreaFormat::Toml
+        } else if first_word.len() > 1
+            && first_word.starts_with('#')
+            && first_word[1..].bytes().all(|byte| byte.is_ascii_digit())
+        {
+            // Circle zones start with their number, other formats with a
+            // section
+            AreaFormat::Circle
+        } else if crate::smaug::is_smaug_area(contents) {
+            AreaFormat::Smaug
+        } else if let "#AREA" | "#AREADATA" | "#HELPS" | "#SOCIALS" = first_word {
+            AreaFormat::Rom
+        } else {
+            AreaFormat::DawnOfTime
+        }
+    }
+}
+
+/// Part of the game day, for resets that only spawn during one of them
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum TimeOfDay {
+    Day,
+    Night,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -129,11 +312,16 @@ pub(super) enum ResetCommand {
         global_limit: u16,
         r_num: Vnum,
         room_limit: u16,
+        /// Only spawned during the day or the night, and gone during the other
+        #[serde(default)]
+        only_at: Option<TimeOfDay>,
     },
     Object {
         o_num: Vnum,
         global_limit: i16,
         r_num: Vnum,
+        #[serde(default)]
+        only_at: Option<TimeOfDay>,
     },
     Door {
         r_num: Vnum,
@@ -166,6 +354,9 @@ pub(super) struct Shop {
     pub profit_sell: u32,
     pub open_hour: u8,
     pub close_hour: u8,
+
+    #[serde(skip)]
+    pub area: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -174,6 +365,64 @@ pub(super) struct MobProg {
     pub title: String,
     pub code: String,
     pub disabled: bool,
+
+    #[serde(skip)]
+    pub area: String,
+}
+
+/// A help entry from the `#HELPS` section of an area file
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub(super) struct Help {
+    /// Level needed to read it, as in ROM; negative ones aren't listed there
+    pub level: i32,
+    /// Words it is found by; keywords of several words are quoted, like
+    /// `'DEATH TRAP' DT`
+    pub keywords: String,
+    pub text: String,
+
+    #[serde(skip)]
+    pub area: String,
+}
+
+impl Help {
+    /// Its keywords, with quoted ones as a single keyword.
+    pub(super) fn keywords(&self) -> Vec<&str> {
+        let mut keywords = Vec::new();
+        let mut remaining = self.keywords.trim_start();
+        while let Some(first) = remaining.chars().next() {
+            let (keyword, rest) = match first {
+                '\'' | '"' => match remaining[1..].find(first) {
+                    Some(end) => (&remaining[1..end + 1], &remaining[end + 2..]),
+                    None => (&remaining[1..], ""),
+                },
+                _ => remaining.split_at(remaining.find(' ').unwrap_or(remaining.len())),
+            };
+            keywords.push(keyword);
+            remaining = rest.trim_start();
+        }
+        keywords
+    }
+
+    /// Whether a topic, like "death trap", is found by this help: either a
+    /// keyword starts with the whole topic, or each of its words starts a
+    /// word of the keywords.
+    pub(super) fn matches(&self, topic: &str) -> bool {
+        let starts = |keyword: &str, word: &str| {
+            keyword.len() >= word.len()
+                && keyword.is_char_boundary(word.len())
+                && keyword[..word.len()].eq_ignore_ascii_case(word)
+        };
+        let keywords = self.keywords();
+
+        !topic.is_empty()
+            && (keywords.iter().any(|keyword| starts(keyword, topic))
+                || topic.split_whitespace().all(|word| {
+                    keywords
+                        .iter()
+                        .flat_map(|keyword| keyword.split_whitespace())
+                        .any(|keyword| starts(keyword, word))
+                }))
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -208,6 +457,10 @@ pub(super) struct Area {
     pub(super) resets: Vec<ResetCommand>,
     pub(super) shops: Vec<Shop>,
     pub(super) mobprogs: Vec<MobProg>,
+    #[serde(default)]
+    pub(super) helps: Vec<Help>,
+    #[serde(default)]
+    pub(super) socials: Vec<Social>,
 }
 
 #[derive(Default)]
@@ -219,68 +472,357 @@ pub(super) struct World {
     pub(super) mobiles: Vec<Mobile>,
     pub(super) shops: Vec<Shop>,
     pub(super) mobprogs: Vec<MobProg>,
+    /// Help entries of all areas, in the order they were loaded
+    pub(super) helps: Vec<Help>,
+    /// Socials of all areas, which are added to those of the socials file
+    pub(super) socials: Vec<Social>,
 }
 
-pub(super) fn load_world(files: &dyn Files, path: &str) -> World {
-    let mut world = World::default();
+impl World {
+    /// The first help entry a topic finds, out of those up to a level.
+    pub(super) fn find_help(&self, topic: &str, level: i32) -> Option<&Help> {
+        self.helps
+            .iter()
+            .find(|help| help.level <= level && help.matches(topic))
+    }
+
+    /// Find an area by its short name or file name.
+    pub(super) fn find_area(&self, name: &str) -> Option<usize> {
+        self.areas.iter().position(|(area, _resets)| {
+            area.short_name.eq_ignore_ascii_case(name)
+                || area.file_name.eq_ignore_ascii_case(name)
+                || area
+                    .file_name
+                    .eq_ignore_ascii_case(&format!("{}.are", name))
+        })
+    }
+
+    /// Add an area loaded from a file, and its templates.
+    fn add_area(&mut self, file_name: &str, source: Option<AreaSource>, mut area: Area) {
+        area.area_data.file_name = file_name.to_string();
+        area.area_data.source = source;
+        area.area_data.on_disk = true;
+
+        let area_and_resets = self.add_templates(area);
+        self.areas.push(area_and_resets);
+    }
 
-    // Note: not using &Path because paths are abstracted in the Files trait,
-    // and may not correspond to the current OS's paths.
-    let arealist_path = format!("{}/arealist.txt", path);
-    let area_names = files.read_file(&arealist_path).unwrap();
+    /// Add an area that wasn't loaded from a file, like a generated dungeon.
+    pub(super) fn add_generated_area(&mut self, area: Area) {
+        let area_and_resets = self.add_templates(area);
+        self.areas.push(area_and_resets);
+    }
 
-    let area_names: Vec<&str> = area_names
-        .split_whitespace()
-        .take_while(|area| *area != "$")
-        .collect();
+    /// Replace an area, and all of its templates, with one loaded from its
+    /// file again.
+    pub(super) fn replace_area(&mut self, index: usize, mut area: Area) {
+        let (old_area_data, _resets) = &self.areas[index];
+        let old_name = old_area_data.short_name.clone();
+        area.area_data.file_name = old_area_data.file_name.clone();
+        area.area_data.source = old_area_data.source.clone();
+        area.area_data.on_disk = true;
+
+        for room in self.rooms.iter_mut().filter(|room| room.area == old_name) {
+            *room = Room::default();
+        }
+        for object in self
+            .objects
+            .iter_mut()
+            .filter(|object| object.area == old_name)
+        {
+            *object = Object::default();
+        }
+        for mobile in self
+            .mobiles
+            .iter_mut()
+            .filter(|mobile| mobile.area == old_name)
+        {
+            *mobile = Mobile::default();
+        }
+        for shop in self.shops.iter_mut().filter(|shop| shop.area == old_name) {
+            *shop = Shop::default();
+        }
+        for mobprog in self
+            .mobprogs
+            .iter_mut()
+            .filter(|mobprog| mobprog.area == old_name)
+        {
+            *mobprog = MobProg::default();
+        }
+        self.helps.retain(|help| help.area != old_name);
+        self.socials.retain(|social| social.area != old_name);
 
-    for file_name in area_names {
-        let data_file_name = format!("{}/{}", path, file_name);
-        let contents = files.read_file(&data_file_name).unwrap();
-        let area = crate::load::load_area(&contents, &data_file_name);
+        self.areas[index] = self.add_templates(area);
+    }
 
-        world.areas.push((area.area_data, area.resets));
+    /// The first room, object or mobile of an area whose vnum another area
+    /// of the world already uses, and that area.
+    fn vnum_clash(&self, area: &Area) -> Option<(&'static str, usize, &str)> {
+        let rooms = area.rooms.iter().find_map(|room| {
+            let other = self.rooms.get(room.vnum.0)?;
+            Some(("room", room.vnum.0, other.area.as_str())).filter(|_| other.vnum.0 != 0)
+        });
+        let objects = || {
+            area.objects.iter().find_map(|object| {
+                let other = self.objects.get(object.vnum.0)?;
+                Some(("object", object.vnum.0, other.area.as_str())).filter(|_| other.vnum.0 != 0)
+            })
+        };
+        let mobiles = || {
+            area.mobiles.iter().find_map(|mobile| {
+                let other = self.mobiles.get(mobile.vnum.0)?;
+                Some(("mobile", mobile.vnum.0, other.area.as_str())).filter(|_| other.vnum.0 != 0)
+            })
+        };
+
+        rooms.or_else(objects).or_else(mobiles)
+    }
 
+    /// Put the templates of an area in the world, by their vnums, and give
+    /// back its data and resets.
+    fn add_templates(&mut self, area: Area) -> (AreaData, Vec<ResetCommand>) {
         for room in area.rooms {
             let vnum = room.vnum.0;
-            if world.rooms.len() <= vnum {
-                world.rooms.resize(vnum + 1, Room::default());
+            if self.rooms.len() <= vnum {
+                self.rooms.resize(vnum + 1, Room::default());
             }
-            world.rooms[vnum] = room;
+            self.rooms[vnum] = room;
         }
 
         for object in area.objects {
             let vnum = object.vnum.0;
-            if world.objects.len() <= vnum {
-                world.objects.resize(vnum + 1, Object::default());
+            if self.objects.len() <= vnum {
+                self.objects.resize(vnum + 1, Object::default());
             }
-            world.objects[vnum] = object;
+            self.objects[vnum] = object;
         }
 
         for mobile in area.mobiles {
             let vnum = mobile.vnum.0;
-            if world.mobiles.len() <= vnum {
-                world.mobiles.resize(vnum + 1, Mobile::default());
+            if self.mobiles.len() <= vnum {
+                self.mobiles.resize(vnum + 1, Mobile::default());
             }
-            world.mobiles[vnum] = mobile;
+            self.mobiles[vnum] = mobile;
         }
 
         for shop in area.shops {
             let vnum = shop.vnum.0;
-            if world.shops.len() <= vnum {
-                world.shops.resize(vnum + 1, Shop::default());
+            if self.shops.len() <= vnum {
+                self.shops.resize(vnum + 1, Shop::default());
             }
-            world.shops[vnum] = shop;
+            self.shops[vnum] = shop;
         }
 
         for mobprog in area.mobprogs {
             let vnum = mobprog.vnum.0;
-            if world.mobprogs.len() <= vnum {
-                world.mobprogs.resize(vnum + 1, MobProg::default());
+            if self.mobprogs.len() <= vnum {
+                self.mobprogs.resize(vnum + 1, MobProg::default());
             }
-            world.mobprogs[vnum] = mobprog;
+            self.mobprogs[vnum] = mobprog;
+        }
+
+        self.helps.extend(area.helps);
+        self.socials.extend(area.socials);
+
+        (area.area_data, area.resets)
+    }
+}
+
+/// What was read of an area file, or of the files of a CircleMUD zone
+pub(super) enum AreaText {
+    File(String),
+    Circle(CircleZone),
+}
+
+impl AreaText {
+    pub(super) fn format(&self) -> AreaFormat {
+        match self {
+            AreaText::File(contents) => AreaFormat::detect(contents),
+            AreaText::Circle(_) => AreaFormat::Circle,
         }
     }
+}
+
+/// Read an area file, or the files of a CircleMUD zone for zone files, which
+/// are told by their `.zon` extension or their first line.
+pub(super) fn read_area_text(files: &dyn Files, path: &str) -> std::io::Result<AreaText> {
+    if path.ends_with(".zon") {
+        return read_circle_zone(files, path).map(AreaText::Circle);
+    }
+
+    let contents = files.read_file(path)?;
+    if AreaFormat::detect(&contents) == AreaFormat::Circle {
+        read_circle_zone(files, path).map(AreaText::Circle)
+    } else {
+        Ok(AreaText::File(contents))
+    }
+}
+
+/// Parse the areas of an area file with the parser of its format, and
+/// remember that format; JSON files may have several areas.
+pub(super) fn parse_area_file(
+    text: &AreaText,
+    file_name: &str,
+    rng: &mut GameRng,
+) -> ParseResult<Vec<Area>> {
+    let format = text.format();
+    let mut areas = match text {
+        AreaText::Circle(zone) => vec![load_circle_zone(zone)?],
+        AreaText::File(contents) if format == AreaFormat::Json => {
+            crate::export::load_json_areas(contents, file_name)?
+        }
+        AreaText::File(contents) if format == AreaFormat::Toml => {
+            vec![crate::toml_area::load_toml_area(contents, file_name)?]
+        }
+        AreaText::File(contents) => vec![crate::load::load_area(contents, file_name, rng)?],
+    };
+
+    for area in &mut areas {
+        area.area_data.format = format;
+    }
+    Ok(areas)
+}
+
+impl AreaData {
+    /// Path of the area file, in the directory of its source, or with the
+    /// other areas.
+    pub(super) fn path(&self, areas: &str) -> String {
+        let directory = match &self.source {
+            Some(source) => &source.directory,
+            None => areas,
+        };
+        format!("{}/{}", directory, self.file_name)
+    }
+}
+
+/// Load the areas in the area list of a directory, and then those of other
+/// sources. Files are read one after the other, and then parsed on several
+/// threads, each area with random numbers of its own so that the same seed
+/// loads the same world.
+pub(super) fn load_world(files: &dyn Files, config: &WorldConfig, rng: &mut GameRng) -> World {
+    let mut world = World::default();
+    // Any of the directories may be an archive
+    let files = &ArchiveFiles::new(files);
+
+    let directories = std::iter::once((&config.areas, None)).chain(
+        config
+            .sources
+            .iter()
+            .map(|source| (&source.directory, Some(source))),
+    );
+
+    let mut area_files: Vec<(String, Option<&AreaSource>, String, AreaText, GameRng)> = Vec::new();
+    for (path, source) in directories {
+        // Note: not using &Path because paths are abstracted in the Files trait,
+        // and may not correspond to the current OS's paths.
+        let arealist_path = format!("{}/arealist.txt", path);
+        let area_names = match files.read_file(&arealist_path) {
+            Ok(area_names) => area_names,
+            // A generated dungeon is enough to play in
+            Err(err) if config.dungeon_rooms > 0 => {
+                println!("Skipping {}: {}", arealist_path, err);
+                continue;
+            }
+            Err(err) => panic!("Could not read {}: {}", arealist_path, err),
+        };
+
+        for file_name in area_names
+            .split_whitespace()
+            .take_while(|area| *area != "$")
+        {
+            let data_file_name = format!("{}/{}", path, file_name);
+            let contents = read_area_text(files, &data_file_name).unwrap();
+            area_files.push((
+                file_name.to_string(),
+                source,
+                data_file_name,
+                contents,
+                rng.split(),
+            ));
+        }
+    }
+
+    let areas: Vec<_> = on_threads(|| {
+        area_files
+            .into_par_iter()
+            .map(|(file_name, source, data_file_name, contents, mut rng)| {
+                let areas = parse_area_file(&contents, &data_file_name, &mut rng);
+                (file_name, source, data_file_name, contents.format(), areas)
+            })
+            .collect()
+    });
+
+    // How many files each parser loaded, to tell which ones a world needs
+    let mut formats: Vec<(AreaFormat, usize)> = Vec::new();
+    for (file_name, source, data_file_name, format, file_areas) in areas {
+        let file_areas = match file_areas {
+            Ok(file_areas) => file_areas,
+            Err(err) => {
+                println!(
+                    "Skipping {}, read as {}: {}",
+                    data_file_name,
+                    format.name(),
+                    err
+                );
+                continue;
+            }
+        };
+        match formats.iter_mut().find(|(other, _count)| *other == format) {
+            Some((_format, count)) => *count += 1,
+            None => formats.push((format, 1)),
+        }
+
+        for mut area in file_areas {
+            if let Some(source) = source {
+                if let Err(err) = apply_source(&mut area, source) {
+                    println!("Skipping {}: {}", data_file_name, err);
+                    continue;
+                }
+            }
+
+            let short_name = &area.area_data.short_name;
+            if world
+                .areas
+                .iter()
+                .any(|(other, _resets)| other.short_name.eq_ignore_ascii_case(short_name))
+            {
+                println!(
+                    "Skipping the area {} of {}: another area is called that.",
+                    short_name, data_file_name
+                );
+                continue;
+            }
+            if let Some((kind, vnum, other)) = world.vnum_clash(&area) {
+                println!(
+                    "The area {} of {} uses vnums of {}, like {} {}; \
+                    give the directory of one of them a vnum offset.",
+                    short_name, data_file_name, other, kind, vnum
+                );
+            }
+
+            world.add_area(&file_name, source.cloned(), area);
+        }
+    }
+
+    if !formats.is_empty() {
+        let counts: Vec<String> = formats
+            .iter()
+            .map(|(format, count)| format!("{} {}", count, format.name()))
+            .collect();
+        println!("Loaded area files: {}.", counts.join(", "));
+    }
+
+    // Saved with asave, a dungeon is loaded from its file instead
+    if config.dungeon_rooms > 0 && world.find_area(DUNGEON_AREA).is_none() {
+        let highest = world
+            .rooms
+            .len()
+            .max(world.objects.len())
+            .max(world.mobiles.len());
+        let first_vnum = (highest / 100 + 1) * 100;
+        let area = generate_area(&mut rng.split(), config.dungeon_rooms, first_vnum);
+        world.add_generated_area(area);
+    }
 
     world
 }
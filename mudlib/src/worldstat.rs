@@ -0,0 +1,321 @@
+//! The state of each area, for immortals looking for leaks: mobiles and
+//! objects that resets or mobprogs keep spawning, and entities that lost
+//! their container.
+//!
+//! `worldstat` lists each area with its rooms, its mobiles and objects next
+//! to how many templates they come from, how many of its entities were
+//! removed to limbo, and how many templates have more of them around than
+//! the global limit of the resets that spawn them. It also lists entities
+//! that can't be reached from the world anymore. `worldstat <area>` lists
+//! the templates of one area with how many of each are around, and the
+//! limit of their resets.
+//!
+//! Areas only run their resets again when day turns to night and back, for
+//! what is only around during one of them; their reset interval isn't used
+//! yet. They keep to their limits, so templates over them were spawned by
+//! something else.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    agent::EntityAgent,
+    clock::time_of_day,
+    components::EntityType,
+    echo,
+    entity::{EntityInfo, EntityWorld},
+    world::{AreaData, ResetCommand, World},
+};
+
+/// How many orphaned entities `worldstat` lists
+const LISTED_ORPHANS: usize = 10;
+
+/// Mobiles and objects around, by type and vnum, and those left in limbo
+#[derive(Default)]
+struct Instances {
+    live: HashMap<(EntityType, usize), usize>,
+    in_limbo: BTreeMap<String, usize>,
+}
+
+/// The global limits of the resets of an area, by type and vnum of what
+/// they spawn; objects without a limit are left out.
+fn reset_limits(resets: &[ResetCommand]) -> BTreeMap<(EntityType, usize), usize> {
+    let mut limits = BTreeMap::new();
+
+    for reset in resets {
+        let (entity_type, vnum, limit) = match reset {
+            ResetCommand::Mob {
+                m_num,
+                global_limit,
+                ..
+            } => (EntityType::Mobile, m_num.0, *global_limit as i32),
+            ResetCommand::Object {
+                o_num,
+                global_limit,
+                ..
+            }
+            | ResetCommand::Give {
+                o_num,
+                global_limit,
+            }
+            | ResetCommand::Equip {
+                o_num,
+                global_limit,
+                ..
+            }
+            | ResetCommand::Put {
+                o_num,
+                global_limit,
+                ..
+            } => (EntityType::Object, o_num.0, *global_limit as i32),
+            ResetCommand::Door { .. } => continue,
+        };
+
+        if limit > 0 {
+            let highest = limits.entry((entity_type, vnum)).or_insert(0);
+            *highest = (*highest).max(limit as usize);
+        }
+    }
+
+    limits
+}
+
+/// Count the mobiles and objects among some entities. Orphaned entities are
+/// left out, as they have no room to tell if they are in limbo.
+fn instances<'e>(
+    entity_world: &'e EntityWorld,
+    entities: impl Iterator<Item = EntityInfo<'e>>,
+) -> Instances {
+    let limbo = entity_world.landmark("limbo");
+    let mut instances = Instances::default();
+
+    for entity in entities {
+        let general = &entity.components().general;
+        if !matches!(general.entity_type, EntityType::Mobile | EntityType::Object)
+            || entity.is_orphaned()
+        {
+            continue;
+        }
+
+        if Some(entity.room().entity_id()) == limbo {
+            *instances
+                .in_limbo
+                .entry(general.area.to_string())
+                .or_insert(0) += 1;
+        } else {
+            *instances
+                .live
+                .entry((general.entity_type, general.vnum.0))
+                .or_insert(0) += 1;
+        }
+    }
+
+    instances
+}
+
+/// Templates of an area, by type and vnum, with their short description.
+fn area_templates<'w>(world: &'w World, area: &AreaData) -> BTreeMap<(EntityType, usize), &'w str> {
+    let mobiles = world
+        .mobiles
+        .iter()
+        .filter(|mobile| mobile.vnum.0 != 0 && mobile.area == area.short_name)
+        .map(|mobile| {
+            let key = (EntityType::Mobile, mobile.vnum.0);
+            (key, mobile.short_description.as_str())
+        });
+    let objects = world
+        .objects
+        .iter()
+        .filter(|object| object.vnum.0 != 0 && object.area == area.short_name)
+        .map(|object| {
+            let key = (EntityType::Object, object.vnum.0);
+            (key, object.short_description.as_str())
+        });
+
+    mobiles.chain(objects).collect()
+}
+
+impl<'e, 'p> EntityAgent<'e, 'p> {
+    pub fn do_worldstat(&mut self, args: &[&str]) {
+        match args {
+            [] => self.world_summary(),
+            [area] => match self.find_area(area) {
+                Some(index) => self.area_details(index),
+                None => echo!(self.info(), "There is no area called '{}'.\r\n", area),
+            },
+            _ => echo!(
+                self.info(),
+                "Syntax: '`Wworldstat`^' or '`Wworldstat <area>`^'.\r\n"
+            ),
+        }
+    }
+
+    fn world_summary(&mut self) {
+        let instances = instances(self.entity_world, self.entity_world.all_entities());
+
+        let mut by_area: BTreeMap<&str, HashMap<EntityType, usize>> = BTreeMap::new();
+        for entity in self.entity_world.all_entities() {
+            let general = &entity.components().general;
+            *by_area
+                .entry(&general.area)
+                .or_default()
+                .entry(general.entity_type)
+                .or_insert(0) += 1;
+        }
+
+        let hour = self.clock.hour();
+        let hours_to_change = (1..=24)
+            .find(|hours| time_of_day((hour + hours) % 24) != time_of_day(hour))
+            .unwrap_or(24);
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let mut info = self.players.info(&myself);
+
+        echo!(
+            info,
+            "{} entities. Day and night resets run next in {} game hours.\r\n",
+            self.entity_world.entity_count(),
+            hours_to_change
+        );
+        echo!(
+            info,
+            "`W{:<16} {:>6} {:>12} {:>12} {:>6} {:>6} {:>6} {:>7}`^\r\n",
+            "Area",
+            "Rooms",
+            "Mobs/tmpl",
+            "Objs/tmpl",
+            "Limbo",
+            "Limit+",
+            "Resets",
+            "Every"
+        );
+
+        for (area, resets) in self.world.areas.iter() {
+            let counts = by_area.get(area.short_name.as_str());
+            let count = |entity_type| {
+                counts
+                    .and_then(|counts| counts.get(&entity_type))
+                    .copied()
+                    .unwrap_or(0)
+            };
+
+            let templates = area_templates(self.world, area);
+            let mobile_templates = templates
+                .keys()
+                .filter(|(entity_type, _)| *entity_type == EntityType::Mobile)
+                .count();
+            let object_templates = templates.len() - mobile_templates;
+            let over_limit = reset_limits(resets)
+                .iter()
+                .filter(|(key, limit)| instances.live.get(key).copied().unwrap_or(0) > **limit)
+                .count();
+
+            echo!(
+                info,
+                "{:<16} {:>6} {:>12} {:>12} {:>6} {:>6} {:>6} {:>5}m\r\n",
+                area.short_name,
+                count(EntityType::Room),
+                format!("{}/{}", count(EntityType::Mobile), mobile_templates),
+                format!("{}/{}", count(EntityType::Object), object_templates),
+                instances
+                    .in_limbo
+                    .get(&area.short_name)
+                    .copied()
+                    .unwrap_or(0),
+                over_limit,
+                resets.len(),
+                area.reset_interval
+            );
+        }
+
+        let orphans: Vec<_> = self.entity_world.orphaned_entities().collect();
+        if orphans.is_empty() {
+            echo!(info, "No entity lost its container.\r\n");
+            return;
+        }
+
+        echo!(
+            info,
+            "`R{} entities lost their container:`^\r\n",
+            orphans.len()
+        );
+        for orphan in orphans.iter().take(LISTED_ORPHANS) {
+            let general = &orphan.components().general;
+            echo!(
+                info,
+                "  {} (vnum {}, from {})\r\n",
+                orphan.component_info().short_description(),
+                general.vnum.0,
+                general.area
+            );
+        }
+        if orphans.len() > LISTED_ORPHANS {
+            echo!(
+                info,
+                "  ...and {} more.\r\n",
+                orphans.len() - LISTED_ORPHANS
+            );
+        }
+    }
+
+    fn area_details(&mut self, index: usize) {
+        let (area, resets) = &self.world.areas[index];
+        let entities = self.entity_world.entities_from_area(&area.short_name);
+        let instances = instances(self.entity_world, entities);
+        let limits = reset_limits(resets);
+        let templates = area_templates(self.world, area);
+
+        let myself = self.entity_world.entity_info(self.entity_id);
+        let mut info = self.players.info(&myself);
+
+        echo!(
+            info,
+            "{} ({}), vnums {} to {}: {} resets, every {} minutes.\r\n",
+            area.name,
+            area.short_name,
+            area.vnums.0 .0,
+            area.vnums.1 .0,
+            resets.len(),
+            area.reset_interval
+        );
+        if templates.is_empty() {
+            echo!(info, "It has no mobiles or objects.\r\n");
+            return;
+        }
+
+        echo!(
+            info,
+            "`W{:<6} {:>7} {:<30} {:>6} {:>6}`^\r\n",
+            "Type",
+            "Vnum",
+            "Template",
+            "Around",
+            "Limit"
+        );
+        for ((entity_type, vnum), short_description) in &templates {
+            let key = (*entity_type, *vnum);
+            let live = instances.live.get(&key).copied().unwrap_or(0);
+            let limit = limits.get(&key);
+            let color = match limit {
+                Some(limit) if live > *limit => "`R",
+                _ => "",
+            };
+
+            echo!(
+                info,
+                "{}{:<6} {:>7} {:<30} {:>6} {:>6}`^\r\n",
+                color,
+                if *entity_type == EntityType::Mobile {
+                    "mobile"
+                } else {
+                    "object"
+                },
+                vnum,
+                short_description,
+                live,
+                limit
+                    .map(|limit| limit.to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            );
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! The server configuration, a TOML file read when the server starts.
+//!
+//! The file is `data/config.toml`, unless the `--config <file>` argument or
+//! the `DEMIMUD_CONFIG` environment variable names another one; without a
+//! file, every setting has its default. Settings of the file are overridden
+//! by environment variables named like `DEMIMUD_SERVER__TICK_MS=500`, with the
+//! section and the key separated by two underscores, and those by arguments
+//! like `--set server.tick_ms=500`.
+//!
+//! netcore only uses the `[server]` section; the mudlib reads the rest of the
+//! same table, and reads it all again for `reload config`.
+
+use std::{net::SocketAddr, time::Duration};
+
+use serde::Deserialize;
+
+pub use toml::Table;
+
+const DEFAULT_FILE: &str = "data/config.toml";
+const FILE_VARIABLE: &str = "DEMIMUD_CONFIG";
+const VARIABLE_PREFIX: &str = "DEMIMUD_";
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Address and port to listen on
+    pub address: SocketAddr,
+    /// Milliseconds between ticks of the game loop
+    pub tick_ms: u64,
+    /// Address and port of the read-only HTTP API, if it should be served
+    pub http_address: Option<SocketAddr>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            address: "0.0.0.0:23".parse().unwrap(),
+            tick_ms: 1000,
+            http_address: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn from_table(config: &Table) -> Result<Self, String> {
+        match config.get("server") {
+            Some(server) => server
+                .clone()
+                .try_into()
+                .map_err(|err| format!("In [server]: {}", err)),
+            None => Ok(ServerConfig::default()),
+        }
+    }
+
+    pub fn tick(&self) -> Duration {
+        Duration::from_millis(self.tick_ms.max(1))
+    }
+}
+
+struct Arguments {
+    /// The file to read, if one was named
+    file: Option<String>,
+    /// Settings to override, as `section.key` and a value
+    overrides: Vec<(String, String)>,
+}
+
+fn parse_arguments() -> Result<Arguments, String> {
+    let mut file = None;
+    let mut overrides = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => {
+                file = Some(args.next().ok_or("--config needs a file name")?);
+            }
+            "--set" => {
+                let setting = args.next().ok_or("--set needs a 'section.key=value'")?;
+                let (key, value) = setting
+                    .split_once('=')
+                    .ok_or_else(|| format!("Expected 'section.key=value', got '{}'", setting))?;
+                overrides.push((key.to_string(), value.to_string()));
+            }
+            _ => {
+                return Err(format!(
+                    "Unknown argument '{}'; expected '--config <file>' or \
+                    '--set <section.key=value>'",
+                    arg
+                ))
+            }
+        }
+    }
+
+    Ok(Arguments { file, overrides })
+}
+
+/// Settings from environment variables, as `section.key` and a value.
+fn environment_overrides() -> Vec<(String, String)> {
+    let mut overrides: Vec<_> = std::env::vars()
+        .filter_map(|(name, value)| {
+            let setting = name.strip_prefix(VARIABLE_PREFIX)?;
+            let (section, key) = setting.split_once("__")?;
+            let key = format!("{}.{}", section, key.replace("__", "."));
+            Some((key.to_lowercase(), value))
+        })
+        .collect();
+    overrides.sort();
+    overrides
+}
+
+/// A value as TOML if it is one, e.g. `500` or `[1, 2]`, or as a string.
+fn parse_value(value: &str) -> toml::Value {
+    format!("value = {}", value)
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+fn set(config: &mut Table, key: &str, value: &str) -> Result<(), String> {
+    let mut path: Vec<&str> = key.split('.').collect();
+    let last = path
+        .pop()
+        .filter(|last| !last.is_empty() && !path.is_empty());
+    let last = last.ok_or_else(|| format!("Expected 'section.key', got '{}'", key))?;
+
+    let mut table = config;
+    for name in path {
+        table = table
+            .entry(name)
+            .or_insert_with(|| toml::Value::Table(Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| format!("'{}' in '{}' is not a section", name, key))?;
+    }
+    table.insert(last.to_string(), parse_value(value));
+
+    Ok(())
+}
+
+/// Read the configuration file, and override it with environment variables
+/// and command line arguments.
+pub fn load_config() -> Result<Table, String> {
+    let arguments = parse_arguments()?;
+    let named_file = arguments.file.or_else(|| std::env::var(FILE_VARIABLE).ok());
+
+    let path = named_file.as_deref().unwrap_or(DEFAULT_FILE);
+    let mut config = match std::fs::read_to_string(path) {
+        Ok(contents) => contents
+            .parse::<Table>()
+            .map_err(|err| format!("In {}: {}", path, err))?,
+        // Only the default file may be left out
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound && named_file.is_none() => {
+            Table::new()
+        }
+        Err(err) => return Err(format!("Could not read {}: {}", path, err)),
+    };
+
+    for (key, value) in environment_overrides()
+        .into_iter()
+        .chain(arguments.overrides)
+    {
+        set(&mut config, &key, &value)?;
+    }
+
+    Ok(config)
+}
@@ -0,0 +1,151 @@
+//! A small read-only HTTP server, for websites to show the status of the
+//! game without logging in over telnet.
+//!
+//! It is only started when `[server] http_address` is set, and runs on a
+//! thread of its own. The mudlib publishes JSON documents by path with
+//! `NetServer::publish()`, and GET requests are answered with the last one
+//! published for their path; `/` lists the paths. As the documents are kept
+//! by netcore, they are still served while the mudlib restarts.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Time a client has to send its whole request, and then to take the whole
+/// answer
+const TIMEOUT: Duration = Duration::from_secs(2);
+/// Longest request read; anything after it is ignored
+const MAX_REQUEST: usize = 8192;
+
+/// JSON documents, by path
+pub(crate) type Documents = Arc<Mutex<BTreeMap<String, String>>>;
+
+/// Listen for requests on a thread, and answer them with the documents
+/// returned.
+pub(crate) fn serve(address: SocketAddr) -> io::Result<Documents> {
+    let listener = TcpListener::bind(address)?;
+    let documents = Documents::default();
+
+    let served = documents.clone();
+    std::thread::Builder::new()
+        .name("http".to_string())
+        .spawn(move || {
+            // One at a time; answers are small, and slow clients time out
+            // after TIMEOUT, however little they send at a time
+            for stream in listener.incoming() {
+                if let Err(err) = stream.and_then(|stream| answer(stream, &served)) {
+                    println!("HTTP error: {}", err);
+                }
+            }
+        })?;
+
+    Ok(documents)
+}
+
+/// The time left until a deadline, or an error if it is past.
+fn time_left(deadline: Instant) -> io::Result<Duration> {
+    match deadline.saturating_duration_since(Instant::now()) {
+        left if left.is_zero() => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "The client took too long",
+        )),
+        left => Ok(left),
+    }
+}
+
+/// The request line and headers of a request, up to the blank line, if they
+/// come before the deadline.
+fn read_request(stream: &mut TcpStream, deadline: Instant) -> io::Result<String> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+
+    while !request.windows(4).any(|end| end == b"\r\n\r\n") && request.len() < MAX_REQUEST {
+        stream.set_read_timeout(Some(time_left(deadline)?))?;
+
+        match stream.read(&mut buffer)? {
+            0 => break,
+            bytes => request.extend_from_slice(&buffer[..bytes]),
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&request).into_owned())
+}
+
+fn error(message: &str) -> String {
+    format!("{{\"error\":\"{}\"}}", message)
+}
+
+/// Send a whole answer, if the client takes it before the deadline.
+fn write_answer(stream: &mut TcpStream, mut answer: &[u8], deadline: Instant) -> io::Result<()> {
+    while !answer.is_empty() {
+        stream.set_write_timeout(Some(time_left(deadline)?))?;
+        match stream.write(answer)? {
+            0 => return Err(io::ErrorKind::WriteZero.into()),
+            bytes => answer = &answer[bytes..],
+        }
+    }
+
+    Ok(())
+}
+
+fn answer(mut stream: TcpStream, documents: &Documents) -> io::Result<()> {
+    let request = read_request(&mut stream, Instant::now() + TIMEOUT)?;
+    let mut request_line = request.lines().next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    // Query strings are of no use to any document
+    let path = request_line
+        .next()
+        .and_then(|target| target.split('?').next())
+        .unwrap_or("");
+
+    let (status, body) = match method {
+        "GET" | "HEAD" => {
+            let documents = documents.lock().unwrap_or_else(|err| err.into_inner());
+            let path = match path.trim_end_matches('/') {
+                "" => "/",
+                path => path,
+            };
+
+            if path == "/" {
+                let paths: Vec<String> = documents
+                    .keys()
+                    .map(|path| format!("\"{}\"", path))
+                    .collect();
+                ("200 OK", format!("{{\"paths\":[{}]}}", paths.join(",")))
+            } else if let Some(document) = documents.get(path) {
+                ("200 OK", document.clone())
+            } else if documents.is_empty() {
+                (
+                    "503 Service Unavailable",
+                    error("The game is still starting"),
+                )
+            } else {
+                ("404 Not Found", error("Nothing is published there"))
+            }
+        }
+        _ => (
+            "405 Method Not Allowed",
+            error("Only GET and HEAD are allowed"),
+        ),
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 {}\r\n\
+        Content-Type: application/json\r\n\
+        Content-Length: {}\r\n\
+        Access-Control-Allow-Origin: *\r\n\
+        Cache-Control: no-cache\r\n\
+        Connection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    if method != "HEAD" {
+        response.push_str(&body);
+    }
+
+    write_answer(&mut stream, response.as_bytes(), Instant::now() + TIMEOUT)
+}
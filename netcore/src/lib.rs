@@ -1,3 +1,5 @@
+pub mod config;
+mod http;
 mod net;
 
 pub use net::{NetEvent, NetServer, Source};
@@ -10,6 +12,10 @@ pub enum EntryCode {
 
 #[repr(C)]
 pub enum ExitCode {
-    PleaseRestart { initializer: Box<[u8]> },
+    PleaseRestart {
+        initializer: Box<[u8]>,
+    },
     Exit,
+    /// The mudlib panicked, after saving what it could
+    Crashed,
 }
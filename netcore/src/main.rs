@@ -1,15 +1,39 @@
-use std::{path::PathBuf, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use libloading::{library_filename, Symbol};
-use netcore::{EntryCode, ExitCode, NetServer};
+use netcore::{
+    config::{load_config, ServerConfig},
+    EntryCode, ExitCode, NetServer,
+};
+
+/// Set to start the server again from scratch when the mudlib crashes
+const RESTART_ON_CRASH: &str = "DEMIMUD_RESTART_ON_CRASH";
 
 fn main() {
-    let address = "0.0.0.0:23".parse().unwrap();
-    println!("Starting server on {}", address);
+    let config = match load_config().and_then(|config| ServerConfig::from_table(&config)) {
+        Ok(config) => config,
+        Err(err) => {
+            println!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    println!("Starting server on {}", config.address);
 
-    let mut net_server = NetServer::new(address);
+    let mut net_server = NetServer::new(config.address);
 
-    net_server.set_tick(Duration::from_millis(1000));
+    net_server.set_tick(config.tick());
+
+    if let Some(http_address) = config.http_address {
+        if let Err(err) = net_server.serve_http(http_address) {
+            println!("Could not serve HTTP on {}: {}", http_address, err);
+            std::process::exit(2);
+        }
+        println!("Serving the HTTP API on {}", http_address);
+    }
 
     let mut entry_initializer: Option<Box<[u8]>> = None;
 
@@ -47,14 +71,22 @@ fn main() {
         let exit_code = unsafe {
             let library = libloading::Library::new(&mudlib).expect("Couldn't load library");
 
-            let do_things: Symbol<fn(&mut NetServer, EntryCode) -> ExitCode>;
-            do_things = library.get(b"do_things").unwrap();
+            let do_things: Symbol<fn(&mut NetServer, EntryCode) -> ExitCode> =
+                library.get(b"do_things").unwrap();
 
             do_things(&mut net_server, entry_code)
         };
 
         match exit_code {
             ExitCode::Exit => break,
+            ExitCode::Crashed => {
+                // Let go of the port for the next process
+                drop(net_server);
+                if std::env::var_os(RESTART_ON_CRASH).is_some() {
+                    restart_process(&bin_path);
+                }
+                std::process::exit(1);
+            }
             ExitCode::PleaseRestart { initializer } => {
                 // It was good enough to trigger a restart, so back it up.
                 std::fs::copy(&mudlib, &mudlib_backup).expect("Couldn't create backup");
@@ -65,3 +97,29 @@ fn main() {
         }
     }
 }
+
+/// Replace this process with a new one of the same binary. Connections are
+/// lost, as sockets are closed on exec.
+#[cfg(unix)]
+fn restart_process(bin_path: &Path) {
+    use std::os::unix::process::CommandExt;
+
+    println!("Restarting after a crash...");
+    let err = Command::new(bin_path)
+        .args(std::env::args_os().skip(1))
+        .exec();
+    println!("Could not restart: {}", err);
+}
+
+/// Start a new process of the same binary, for this one to exit.
+/// Connections are lost with this process.
+#[cfg(not(unix))]
+fn restart_process(bin_path: &Path) {
+    println!("Restarting after a crash...");
+    if let Err(err) = Command::new(bin_path)
+        .args(std::env::args_os().skip(1))
+        .spawn()
+    {
+        println!("Could not restart: {}", err);
+    }
+}
@@ -1,11 +1,13 @@
 use mio::{Interest, Token};
 use std::{
     collections::BTreeMap,
-    io::{ErrorKind::WouldBlock, Read},
+    io::{self, ErrorKind::WouldBlock, Read},
     net::SocketAddr,
     time::Duration,
 };
 
+use crate::http::{self, Documents};
+
 #[repr(transparent)]
 #[derive(Debug)]
 pub struct Source(pub usize);
@@ -29,6 +31,8 @@ pub struct NetServer {
     read_buffer: Box<[u8; 4096]>,
     connections: BTreeMap<Token, NetConnection>,
     pending_errors: BTreeMap<Token, NetEvent<'static>>,
+    /// Documents of the HTTP server, if it was started
+    http_documents: Option<Documents>,
 }
 
 struct NetConnection {
@@ -63,6 +67,29 @@ impl NetServer {
             read_buffer: Box::new([0; 4096]),
             connections: BTreeMap::new(),
             pending_errors: BTreeMap::new(),
+            http_documents: None,
+        }
+    }
+
+    /// Start the read-only HTTP server on another address, see `http.rs`.
+    pub fn serve_http(&mut self, addr: SocketAddr) -> io::Result<()> {
+        self.http_documents = Some(http::serve(addr)?);
+        Ok(())
+    }
+
+    /// Whether there is an HTTP server to publish documents to.
+    pub fn serves_http(&self) -> bool {
+        self.http_documents.is_some()
+    }
+
+    /// Serve a JSON document at a path of the HTTP server, in place of the
+    /// one published there before. Does nothing without an HTTP server.
+    pub fn publish(&mut self, path: &str, document: String) {
+        if let Some(documents) = &self.http_documents {
+            documents
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .insert(path.to_string(), document);
         }
     }
 
@@ -115,12 +142,12 @@ impl NetServer {
                     } else {
                         let stream = &mut self
                             .connections
-                            .get_mut(&token)
+                            .get_mut(token)
                             .expect("Unregistered token")
                             .socket;
 
                         match stream.read(self.read_buffer.as_mut()) {
-                            Ok(bytes) if bytes == 0 => {
+                            Ok(0) => {
                                 let token = *token;
                                 self.mio_poll.registry().deregister(stream).unwrap();
                                 self.disconnect(token);
@@ -185,7 +212,7 @@ impl NetServer {
     pub fn schedule_disconnect(&mut self, target: &Source) {
         let token = Token(target.0);
 
-        if self.pending_errors.get(&token).is_some() {
+        if self.pending_errors.contains_key(&token) {
             // It's already pending a different kind of disconnect.
             return;
         }
@@ -251,7 +278,7 @@ impl NetServer {
         // Check if the token is still valid; it may no longer exist if there
         // was an error during a flush, and the Disconnect event was not yet
         // sent.
-        if self.pending_errors.get(&token).is_some() {
+        if self.pending_errors.contains_key(&token) {
             return;
         }
 
@@ -276,7 +303,7 @@ impl NetServer {
     pub fn try_flush(&mut self, target: &Source) {
         let token = Token(target.0);
 
-        if self.pending_errors.get(&token).is_some() {
+        if self.pending_errors.contains_key(&token) {
             return;
         }
 